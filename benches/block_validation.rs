@@ -26,7 +26,11 @@ fn create_test_block() -> Block {
 fn benchmark_connect_block(c: &mut Criterion) {
     let block = create_test_block();
     let utxo_set = UtxoSet::new();
-    let witnesses: Vec<Witness> = block.transactions.iter().map(|_| Vec::new()).collect();
+    let witnesses: Vec<Vec<Witness>> = block
+        .transactions
+        .iter()
+        .map(|tx| vec![Vec::new(); tx.inputs.len()])
+        .collect();
 
     c.bench_function("connect_block", |b| {
         b.iter(|| {
@@ -73,7 +77,11 @@ fn benchmark_connect_block_multi_tx(c: &mut Criterion) {
     };
 
     let utxo_set = UtxoSet::new();
-    let witnesses: Vec<Witness> = block.transactions.iter().map(|_| Vec::new()).collect();
+    let witnesses: Vec<Vec<Witness>> = block
+        .transactions
+        .iter()
+        .map(|tx| vec![Vec::new(); tx.inputs.len()])
+        .collect();
 
     c.bench_function("connect_block_multi_tx", |b| {
         b.iter(|| {
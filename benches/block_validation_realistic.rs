@@ -76,7 +76,11 @@ fn create_realistic_test_block(num_txs: usize) -> Block {
 fn benchmark_connect_block_realistic(c: &mut Criterion) {
     let block = create_realistic_test_block(100); // 100 transactions (more realistic)
     let utxo_set = UtxoSet::new();
-    let witnesses: Vec<Witness> = block.transactions.iter().map(|_| Vec::new()).collect();
+    let witnesses: Vec<Vec<Witness>> = block
+        .transactions
+        .iter()
+        .map(|tx| vec![Vec::new(); tx.inputs.len()])
+        .collect();
 
     c.bench_function("connect_block_realistic_100tx", |b| {
         b.iter(|| {
@@ -95,7 +99,11 @@ fn benchmark_connect_block_realistic(c: &mut Criterion) {
 fn benchmark_connect_block_realistic_1000tx(c: &mut Criterion) {
     let block = create_realistic_test_block(1000); // 1000 transactions (matches Core benchmark)
     let utxo_set = UtxoSet::new();
-    let witnesses: Vec<Witness> = block.transactions.iter().map(|_| Vec::new()).collect();
+    let witnesses: Vec<Vec<Witness>> = block
+        .transactions
+        .iter()
+        .map(|tx| vec![Vec::new(); tx.inputs.len()])
+        .collect();
 
     c.bench_function("connect_block_realistic_1000tx", |b| {
         b.iter(|| {
@@ -3,7 +3,6 @@ use bllvm_consensus::mempool::{
 };
 use bllvm_consensus::{OutPoint, Transaction, TransactionInput, TransactionOutput, UtxoSet};
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use std::collections::HashSet;
 
 fn create_test_transaction() -> Transaction {
     Transaction {
@@ -56,7 +55,7 @@ fn create_complex_transaction(input_count: usize, output_count: usize) -> Transa
 fn benchmark_mempool_acceptance(c: &mut Criterion) {
     let tx = create_test_transaction();
     let utxo_set = UtxoSet::new();
-    let mempool: Mempool = HashSet::new();
+    let mempool = Mempool::new();
 
     c.bench_function("accept_to_memory_pool_simple", |b| {
         b.iter(|| {
@@ -74,7 +73,7 @@ fn benchmark_mempool_acceptance(c: &mut Criterion) {
 fn benchmark_mempool_acceptance_complex(c: &mut Criterion) {
     let tx = create_complex_transaction(5, 3);
     let utxo_set = UtxoSet::new();
-    let mempool: Mempool = HashSet::new();
+    let mempool = Mempool::new();
 
     c.bench_function("accept_to_memory_pool_complex", |b| {
         b.iter(|| {
@@ -104,7 +103,7 @@ fn benchmark_replacement_checks(c: &mut Criterion) {
     let mut existing_tx = create_test_transaction();
     existing_tx.inputs[0].sequence = 0xfffffffe; // RBF
 
-    let mempool: Mempool = HashSet::new();
+    let mempool = Mempool::new();
     let utxo_set = UtxoSet::new();
 
     c.bench_function("replacement_checks", |b| {
@@ -204,7 +204,11 @@ fn bench_block_validation(c: &mut Criterion) {
 
     // Small block (10 txs) - typical for quick blocks
     let block_10 = create_realistic_block(10);
-    let witnesses_10: Vec<Witness> = block_10.transactions.iter().map(|_| Vec::new()).collect();
+    let witnesses_10: Vec<Vec<Witness>> = block_10
+        .transactions
+        .iter()
+        .map(|tx| vec![Vec::new(); tx.inputs.len()])
+        .collect();
 
     group.bench_function("10_txs", |b| {
         b.iter(|| {
@@ -221,7 +225,11 @@ fn bench_block_validation(c: &mut Criterion) {
 
     // Medium block (100 txs) - typical average block
     let block_100 = create_realistic_block(100);
-    let witnesses_100: Vec<Witness> = block_100.transactions.iter().map(|_| Vec::new()).collect();
+    let witnesses_100: Vec<Vec<Witness>> = block_100
+        .transactions
+        .iter()
+        .map(|tx| vec![Vec::new(); tx.inputs.len()])
+        .collect();
 
     group.bench_function("100_txs", |b| {
         b.iter(|| {
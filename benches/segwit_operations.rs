@@ -86,7 +86,7 @@ fn benchmark_calculate_transaction_weight(c: &mut Criterion) {
         b.iter(|| {
             black_box(calculate_transaction_weight(
                 black_box(&segwit_tx),
-                black_box(Some(&witness)),
+                black_box(Some(std::slice::from_ref(&witness))),
             ))
         })
     });
@@ -106,7 +106,7 @@ fn benchmark_calculate_block_weight(c: &mut Criterion) {
         transactions: vec![tx].into(),
     };
 
-    let witnesses: Vec<Witness> = vec![vec![]];
+    let witnesses: Vec<Vec<Witness>> = vec![vec![vec![]]];
 
     c.bench_function("calculate_block_weight", |b| {
         b.iter(|| {
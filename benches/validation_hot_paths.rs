@@ -0,0 +1,302 @@
+//! Benchmarks for the hot validation paths
+//!
+//! Covers the operations a node repeats for every input and every block:
+//! a P2WPKH signature check, a 2-of-3 multisig spend, script verification of
+//! a 100-input transaction, full-block validation and, with the
+//! `utxo-commitments` feature, UTXO tree insertion. With the `production` and
+//! `benchmarking` features it also compares opcode dispatch through the
+//! opcode table against the `match`.
+//!
+//! Run with `cargo bench --bench validation_hot_paths` (add
+//! `--features utxo-commitments` for the UTXO tree group, or
+//! `--features production,benchmarking` for the dispatch group).
+
+use bitcoin_hashes::{hash160, Hash as _};
+use bllvm_consensus::block::verify_block;
+use bllvm_consensus::chain_params::ChainParams;
+use bllvm_consensus::constants::SCRIPT_VERIFY_P2SH;
+use bllvm_consensus::crypto::signature::{Secp256k1Verifier, SignatureVerifier};
+use bllvm_consensus::mining::calculate_merkle_root;
+use bllvm_consensus::multisig::{MultisigSpend, SIGHASH_ALL};
+use bllvm_consensus::script::{verify_input, verify_transaction_with_utxo_set};
+use bllvm_consensus::segwit::Witness;
+use bllvm_consensus::transaction::check_tx_inputs;
+use bllvm_consensus::transaction_hash::calculate_bip143_sighash;
+use bllvm_consensus::types::*;
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use secp256k1::{Message, PublicKey, Secp256k1, SecretKey};
+
+const MANY_INPUTS: usize = 100;
+
+fn spending_tx(input_count: usize, output_value: Integer) -> Transaction {
+    Transaction {
+        version: 1,
+        inputs: (0..input_count)
+            .map(|i| TransactionInput {
+                prevout: OutPoint {
+                    hash: [0xab; 32],
+                    index: i as Natural,
+                },
+                script_sig: vec![0x51], // OP_1
+                sequence: 0xffffffff,
+            })
+            .collect(),
+        outputs: bllvm_consensus::tx_outputs![TransactionOutput {
+            value: output_value,
+            script_pubkey: vec![0x51],
+        }],
+        lock_time: 0,
+    }
+}
+
+/// UTXOs spent by `spending_tx`, each locked to OP_1 OP_EQUAL
+fn utxo_set_for(tx: &Transaction, value: Integer) -> UtxoSet {
+    tx.inputs
+        .iter()
+        .map(|input| {
+            (
+                input.prevout.clone(),
+                UTXO {
+                    value,
+                    script_pubkey: vec![0x51, 0x87],
+                    height: 0,
+                    is_coinbase: false,
+                },
+            )
+        })
+        .collect()
+}
+
+fn benchmark_p2wpkh_input(c: &mut Criterion) {
+    // The script engine has no witness-program interpreter, so this measures
+    // what a P2WPKH input costs: the BIP143 sighash plus one ECDSA check.
+    let secp = Secp256k1::new();
+    let secret_key = SecretKey::from_slice(&[0x11; 32]).unwrap();
+    let pubkey = PublicKey::from_secret_key(&secp, &secret_key).serialize();
+    let pubkey_hash = hash160::Hash::hash(&pubkey);
+
+    // OP_DUP OP_HASH160 <20> <pubkey hash> OP_EQUALVERIFY OP_CHECKSIG
+    let mut script_code = vec![0x76, 0xa9, 0x14];
+    script_code.extend_from_slice(pubkey_hash.as_ref());
+    script_code.extend_from_slice(&[0x88, 0xac]);
+
+    let tx = spending_tx(1, 90_000);
+    let amount = 100_000;
+    let sighash = calculate_bip143_sighash(&tx, 0, &script_code, amount, 0x01).unwrap();
+    let message = Message::from_digest_slice(&sighash).unwrap();
+    let signature = secp.sign_ecdsa(&message, &secret_key).serialize_der();
+    let verifier = Secp256k1Verifier::new();
+    assert!(verifier.verify_ecdsa(&pubkey, &signature, &sighash));
+
+    c.bench_function("p2wpkh_input_verify", |b| {
+        b.iter(|| {
+            let sighash =
+                calculate_bip143_sighash(black_box(&tx), 0, black_box(&script_code), amount, 0x01)
+                    .unwrap();
+            black_box(verifier.verify_ecdsa(&pubkey, black_box(&signature), &sighash))
+        })
+    });
+}
+
+fn benchmark_multisig_2_of_3(c: &mut Criterion) {
+    let secp = Secp256k1::new();
+    let keys: Vec<SecretKey> = (1u8..=3)
+        .map(|seed| SecretKey::from_slice(&[seed; 32]).unwrap())
+        .collect();
+    let pubkeys = keys
+        .iter()
+        .map(|sk| PublicKey::from_secret_key(&secp, sk).serialize().to_vec())
+        .collect();
+    let spend = MultisigSpend::new(2, pubkeys).unwrap();
+
+    let prevouts = vec![TransactionOutput {
+        value: 100_000,
+        script_pubkey: spend.p2sh_script_pubkey(),
+    }];
+    let mut tx = spending_tx(1, 90_000);
+    let sighash = spend.signature_hash(&tx, 0, &prevouts).unwrap();
+    let message = Message::from_digest_slice(&sighash).unwrap();
+    let signatures: Vec<ByteString> = [&keys[0], &keys[2]]
+        .iter()
+        .map(|sk| {
+            let mut signature = secp.sign_ecdsa(&message, sk).serialize_der().to_vec();
+            signature.push(SIGHASH_ALL);
+            signature
+        })
+        .collect();
+    tx.inputs[0].script_sig = spend.p2sh_script_sig(&signatures);
+    let flags = SCRIPT_VERIFY_P2SH;
+    let witness = Vec::new();
+    assert!(verify_input(&tx, 0, &prevouts, &witness, flags).unwrap());
+
+    c.bench_function("multisig_2_of_3_p2sh_verify", |b| {
+        b.iter(|| {
+            black_box(verify_input(
+                black_box(&tx),
+                0,
+                black_box(&prevouts),
+                &witness,
+                flags,
+            ))
+        })
+    });
+}
+
+fn benchmark_many_inputs(c: &mut Criterion) {
+    let tx = spending_tx(MANY_INPUTS, 90_000);
+    let utxo_set = utxo_set_for(&tx, 1_000);
+    assert!(verify_transaction_with_utxo_set(&tx, &utxo_set, 0, 1).unwrap());
+
+    let mut group = c.benchmark_group("tx_100_inputs");
+    group.bench_function("check_tx_inputs", |b| {
+        b.iter(|| black_box(check_tx_inputs(black_box(&tx), black_box(&utxo_set), 1)))
+    });
+    group.bench_function("verify_scripts", |b| {
+        b.iter(|| {
+            black_box(verify_transaction_with_utxo_set(
+                black_box(&tx),
+                black_box(&utxo_set),
+                0,
+                1,
+            ))
+        })
+    });
+    group.finish();
+}
+
+fn benchmark_full_block(c: &mut Criterion) {
+    let subsidy = 50 * 100_000_000;
+    let spend = spending_tx(MANY_INPUTS, MANY_INPUTS as Integer * 1_000 - 10_000);
+    let utxo_set = utxo_set_for(&spend, 1_000);
+    let coinbase = Transaction {
+        version: 1,
+        inputs: bllvm_consensus::tx_inputs![TransactionInput {
+            prevout: OutPoint {
+                hash: [0; 32],
+                index: 0xffffffff,
+            },
+            script_sig: vec![0x51, 0x51],
+            sequence: 0xffffffff,
+        }],
+        outputs: bllvm_consensus::tx_outputs![TransactionOutput {
+            value: subsidy + 10_000,
+            script_pubkey: vec![0x51],
+        }],
+        lock_time: 0,
+    };
+    let transactions = vec![coinbase, spend];
+    let block = Block {
+        header: BlockHeader {
+            version: 1,
+            prev_block_hash: [1; 32],
+            merkle_root: calculate_merkle_root(&transactions).unwrap(),
+            timestamp: 1231006505,
+            bits: 0x1d00ffff,
+            nonce: 0,
+        },
+        transactions: transactions.into_boxed_slice(),
+    };
+    let witnesses: Vec<Vec<Witness>> = block
+        .transactions
+        .iter()
+        .map(|tx| vec![Vec::new(); tx.inputs.len()])
+        .collect();
+    let params = ChainParams::mainnet();
+
+    let (result, _, _) =
+        verify_block(&block, &witnesses, utxo_set.clone(), 1, None, &params).unwrap();
+    assert!(matches!(result, ValidationResult::Valid), "{result:?}");
+
+    c.bench_function("verify_block_101_txins", |b| {
+        b.iter_batched(
+            || utxo_set.clone(),
+            |utxo_set| {
+                black_box(verify_block(
+                    black_box(&block),
+                    &witnesses,
+                    utxo_set,
+                    1,
+                    None,
+                    &params,
+                ))
+            },
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+#[cfg(feature = "utxo-commitments")]
+fn benchmark_utxo_tree_insert(c: &mut Criterion) {
+    use bllvm_consensus::utxo_commitments::merkle_tree::UtxoMerkleTree;
+
+    let utxos: Vec<(OutPoint, UTXO)> = (0..1_000u32)
+        .map(|i| {
+            let mut hash = [0u8; 32];
+            hash[..4].copy_from_slice(&i.to_le_bytes());
+            (
+                OutPoint { hash, index: 0 },
+                UTXO {
+                    value: 1_000,
+                    script_pubkey: vec![0x51],
+                    height: 1,
+                    is_coinbase: false,
+                },
+            )
+        })
+        .collect();
+
+    c.bench_function("utxo_tree_insert_1000", |b| {
+        b.iter_batched(
+            || utxos.clone(),
+            |utxos| {
+                let mut tree = UtxoMerkleTree::new().unwrap();
+                for (outpoint, utxo) in utxos {
+                    tree.insert(outpoint, utxo).unwrap();
+                }
+                black_box(tree.root())
+            },
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+#[cfg(not(feature = "utxo-commitments"))]
+fn benchmark_utxo_tree_insert(_c: &mut Criterion) {}
+
+#[cfg(all(feature = "production", feature = "benchmarking"))]
+fn benchmark_opcode_dispatch(c: &mut Criterion) {
+    use bllvm_consensus::error::Result;
+    use bllvm_consensus::script::{execute_opcode_via_match, execute_opcode_via_table};
+
+    // A P2PKH-like mix of stack, hash, comparison and numeric opcodes
+    const OPCODES: [u8; 12] = [
+        0x76, 0xa9, 0x87, 0x51, 0x52, 0x93, 0x76, 0x9c, 0x69, 0x75, 0x51, 0x8b,
+    ];
+    type Execute = fn(u8, &mut Vec<ByteString>, u32) -> Result<bool>;
+    let run = |execute: Execute| {
+        let mut stack = vec![vec![0x02; 33]];
+        for opcode in OPCODES {
+            black_box(execute(opcode, &mut stack, 0).unwrap());
+        }
+        stack
+    };
+
+    let mut group = c.benchmark_group("opcode_dispatch");
+    group.bench_function("table", |b| b.iter(|| run(execute_opcode_via_table)));
+    group.bench_function("match", |b| b.iter(|| run(execute_opcode_via_match)));
+    group.finish();
+}
+
+#[cfg(not(all(feature = "production", feature = "benchmarking")))]
+fn benchmark_opcode_dispatch(_c: &mut Criterion) {}
+
+criterion_group!(
+    benches,
+    benchmark_p2wpkh_input,
+    benchmark_multisig_2_of_3,
+    benchmark_many_inputs,
+    benchmark_full_block,
+    benchmark_utxo_tree_insert,
+    benchmark_opcode_dispatch
+);
+criterion_main!(benches);
@@ -128,6 +128,14 @@ fn extract_height_from_script_sig(script_sig: &[u8]) -> Result<Natural> {
 
     let first_byte = script_sig[0];
 
+    // Handle small-integer opcodes (CScript() << nHeight encodes 0-16 as OP_0, OP_1..OP_16)
+    if first_byte == 0x00 {
+        return Ok(0);
+    }
+    if (0x51..=0x60).contains(&first_byte) {
+        return Ok((first_byte - 0x50) as Natural);
+    }
+
     // Handle direct push (0x01-0x4b)
     if (1..=0x4b).contains(&first_byte) {
         let len = first_byte as usize;
@@ -234,14 +242,8 @@ pub fn check_bip66(
     height: Natural,
     network: crate::types::Network,
 ) -> Result<bool> {
-    let activation_height = match network {
-        crate::types::Network::Mainnet => 363_724,
-        crate::types::Network::Testnet => 330_776,
-        crate::types::Network::Regtest => 0,
-    };
-
     // BIP66 only applies after activation height
-    if height < activation_height {
+    if height < bip66_activation_height(network) {
         return Ok(true);
     }
 
@@ -251,6 +253,24 @@ pub fn check_bip66(
     is_strict_der(signature)
 }
 
+/// First block height at which BIP66 strict DER signatures are enforced
+pub fn bip66_activation_height(network: crate::types::Network) -> Natural {
+    match network {
+        crate::types::Network::Mainnet => 363_724,
+        crate::types::Network::Testnet => 330_776,
+        crate::types::Network::Regtest => 0,
+    }
+}
+
+/// First block height at which BIP147 NULLDUMMY is enforced
+pub fn bip147_activation_height(network: crate::types::Network) -> Natural {
+    match network {
+        crate::types::Network::Mainnet => 481_824,
+        crate::types::Network::Testnet => 834_624,
+        crate::types::Network::Regtest => 0,
+    }
+}
+
 /// Check if signature is strictly DER-encoded
 fn is_strict_der(signature: &[u8]) -> Result<bool> {
     use secp256k1::ecdsa::Signature;
@@ -6,10 +6,12 @@
 //! - Assume-Valid Blocks (Phase 4.1) - skip validation for trusted checkpoints
 
 use crate::bip113::get_median_time_past;
+use crate::chain_params::ChainParams;
 use crate::constants::*;
+use crate::crypto::signature::default_verifier;
 use crate::economic::get_block_subsidy;
 use crate::error::{ConsensusError, Result};
-use crate::script::verify_script_with_context_full;
+use crate::script::verify_script_with_witness;
 use std::borrow::Cow;
 
 #[cfg(feature = "production")]
@@ -47,10 +49,22 @@ fn make_fee_overflow_error(transaction_index: Option<usize>) -> ConsensusError {
     };
     ConsensusError::BlockValidation(message.into())
 }
-use crate::segwit::{
-    compute_witness_merkle_root, is_segwit_transaction, validate_witness_commitment, Witness,
-};
+
+/// Add transaction `i`'s fee to the block's running total
+///
+/// Checked, and bounded by `MAX_MONEY`: no valid block can collect more fees
+/// than the money supply, so a larger total is rejected rather than carried
+/// into the coinbase check.
+fn add_block_fee(total_fees: Integer, fee: Integer, i: usize) -> Result<Integer> {
+    total_fees
+        .checked_add(fee)
+        .filter(|total| (0..=MAX_MONEY).contains(total))
+        .ok_or_else(|| make_fee_overflow_error(Some(i)))
+}
+
+use crate::segwit::{is_segwit_transaction, validate_block_witness_commitment, Witness};
 use crate::transaction::{check_transaction, check_tx_inputs, is_coinbase};
+use crate::transaction_hash::SighashCache;
 use crate::types::*;
 
 // Rayon is used conditionally in the code, imported where needed
@@ -162,15 +176,16 @@ pub fn reset_assume_valid_height() {
 /// # Arguments
 ///
 /// * `block` - The block to validate and connect
-/// * `witnesses` - Witness data for each transaction in the block (one Witness per transaction)
+/// * `witnesses` - Witness data for each transaction in the block: one witness stack per input
 /// * `utxo_set` - Current UTXO set (will be modified)
 /// * `height` - Current block height
 /// * `recent_headers` - Optional recent block headers for median time-past calculation (BIP113)
+/// * `network` - Network whose activation heights and block limits apply
 #[track_caller] // Better error messages showing caller location
 pub fn connect_block(
     block: &Block,
-    witnesses: &[Witness],
-    mut utxo_set: UtxoSet,
+    witnesses: &[Vec<Witness>],
+    utxo_set: UtxoSet,
     height: Natural,
     recent_headers: Option<&[BlockHeader]>,
     network: crate::types::Network,
@@ -179,6 +194,209 @@ pub fn connect_block(
     UtxoSet,
     crate::reorganization::BlockUndoLog,
 )> {
+    connect_block_with_params(
+        block,
+        witnesses,
+        utxo_set,
+        height,
+        recent_headers,
+        &ChainParams::for_network(network),
+    )
+}
+
+/// VerifyBlock: ℬ × 𝒲* × 𝒰𝒮 × ℕ × ℋ* × 𝒫 → {valid, invalid} × 𝒰𝒮
+///
+/// `connect_block` with the block resource limits taken from `params` rather
+/// than the global constants:
+/// 1. Serialized size without witness data × 4 ≤ `params.max_block_weight`
+/// 2. Serialized size with witness data ≤ `params.max_block_serialized_size`
+/// 3. Weight ≤ `params.max_block_weight`
+/// 4. Total sigop cost ≤ `params.max_block_sigops_cost`
+///
+/// Activation heights follow `params.network`.
+#[track_caller]
+pub fn verify_block(
+    block: &Block,
+    witnesses: &[Vec<Witness>],
+    utxo_set: UtxoSet,
+    height: Natural,
+    recent_headers: Option<&[BlockHeader]>,
+    params: &ChainParams,
+) -> Result<(
+    ValidationResult,
+    UtxoSet,
+    crate::reorganization::BlockUndoLog,
+)> {
+    if let Some(reason) = check_transaction_count(block, params) {
+        return Ok((
+            ValidationResult::Invalid(reason),
+            utxo_set,
+            crate::reorganization::BlockUndoLog::new(),
+        ));
+    }
+
+    // Core: GetSerializeSize(TX_NO_WITNESS(block)) * WITNESS_SCALE_FACTOR > MAX_BLOCK_WEIGHT
+    let stripped_size = calculate_block_serialized_size(block);
+    if stripped_size * 4 > params.max_block_weight {
+        return Ok((
+            ValidationResult::Invalid(format!(
+                "bad-blk-length: stripped size {stripped_size} × 4 exceeds block weight {}",
+                params.max_block_weight
+            )),
+            utxo_set,
+            crate::reorganization::BlockUndoLog::new(),
+        ));
+    }
+
+    let total_size = crate::segwit::calculate_block_total_size(block, witnesses);
+    if total_size > params.max_block_serialized_size as Natural {
+        return Ok((
+            ValidationResult::Invalid(format!(
+                "bad-blk-length: block size {total_size} exceeds maximum {}",
+                params.max_block_serialized_size
+            )),
+            utxo_set,
+            crate::reorganization::BlockUndoLog::new(),
+        ));
+    }
+
+    let weight = crate::segwit::calculate_block_weight(block, witnesses)?;
+    if weight > params.max_block_weight as Natural {
+        return Ok((
+            ValidationResult::Invalid(format!(
+                "bad-blk-weight: block weight {weight} exceeds maximum {}",
+                params.max_block_weight
+            )),
+            utxo_set,
+            crate::reorganization::BlockUndoLog::new(),
+        ));
+    }
+
+    connect_block_with_params(block, witnesses, utxo_set, height, recent_headers, params)
+}
+
+/// Reject a transaction count that could not fit in the block weight limit
+///
+/// Every transaction weighs at least MIN_SERIALIZABLE_TRANSACTION_WEIGHT, so
+/// this needs no per-transaction work. Returns the reject reason, if any.
+fn check_transaction_count(block: &Block, params: &ChainParams) -> Option<String> {
+    let max_transactions = params.max_block_weight / MIN_SERIALIZABLE_TRANSACTION_WEIGHT;
+    if block.transactions.len() > max_transactions {
+        return Some(format!(
+            "bad-blk-length: {} transactions cannot fit in block weight {}",
+            block.transactions.len(),
+            params.max_block_weight
+        ));
+    }
+    None
+}
+
+/// Check the shape of a block's coinbase outputs
+///
+/// 1. The first transaction is a coinbase
+/// 2. It has at most MAX_OUTPUTS outputs and its serialized size fits in MAX_TX_SIZE
+/// 3. Every output that starts with the witness commitment prefix (OP_RETURN,
+///    36-byte push, 0xaa21a9ed) is at least MINIMUM_WITNESS_COMMITMENT bytes
+/// 4. There is at most one witness commitment output
+/// 5. If there is one, the coinbase input's witness is a single 32-byte
+///    reserved value
+///
+/// `witnesses` holds one witness stack per input for each transaction, as
+/// passed to `connect_block`.
+///
+/// Rule 4 is stricter than Bitcoin Core, which accepts several commitments
+/// and uses the last one, so this is not part of `connect_block`; use it to
+/// vet block templates and blocks before relay.
+pub fn check_coinbase_structure(block: &Block, witnesses: &[Vec<Witness>]) -> ValidationResult {
+    use crate::segwit::{witness_reserved_value, WITNESS_COMMITMENT_HEADER};
+    use crate::serialization::transaction::serialize_transaction;
+
+    let Some(coinbase) = block.transactions.first().filter(|tx| is_coinbase(tx)) else {
+        return ValidationResult::Invalid(
+            "bad-cb-missing: first transaction is not a coinbase".into(),
+        );
+    };
+
+    if coinbase.outputs.len() > MAX_OUTPUTS {
+        return ValidationResult::Invalid(format!(
+            "bad-cb-outputs: {} coinbase outputs exceed {MAX_OUTPUTS}",
+            coinbase.outputs.len()
+        ));
+    }
+    let coinbase_size = serialize_transaction(coinbase).len();
+    if coinbase_size > MAX_TX_SIZE {
+        return ValidationResult::Invalid(format!(
+            "bad-cb-outputs: coinbase of {coinbase_size} bytes exceeds {MAX_TX_SIZE}"
+        ));
+    }
+
+    let mut commitments = 0;
+    for (i, output) in coinbase.outputs.iter().enumerate() {
+        let script = &output.script_pubkey;
+        if !(script.starts_with(&[0x6a, 0x24])
+            && script[2..].starts_with(&WITNESS_COMMITMENT_HEADER))
+        {
+            continue;
+        }
+        if script.len() < MINIMUM_WITNESS_COMMITMENT {
+            return ValidationResult::Invalid(format!(
+                "bad-witness-commitment: coinbase output {i} is a truncated witness commitment"
+            ));
+        }
+        commitments += 1;
+        if commitments > 1 {
+            return ValidationResult::Invalid(format!(
+                "bad-witness-commitment: coinbase output {i} is a second witness commitment"
+            ));
+        }
+    }
+
+    let coinbase_witness = witnesses.first().and_then(|stacks| stacks.first());
+    if commitments > 0 && coinbase_witness.and_then(witness_reserved_value).is_none() {
+        return ValidationResult::Invalid(
+            "bad-witness-nonce-size: coinbase witness is not a single 32-byte reserved value"
+                .into(),
+        );
+    }
+
+    ValidationResult::Valid
+}
+
+/// Serialized block size in bytes, without witness data
+fn calculate_block_serialized_size(block: &Block) -> usize {
+    use crate::serialization::transaction::serialize_transaction;
+    use crate::serialization::varint::encode_varint;
+
+    80 + encode_varint(block.transactions.len() as u64).len()
+        + block
+            .transactions
+            .iter()
+            .map(|tx| serialize_transaction(tx).len())
+            .sum::<usize>()
+}
+
+#[track_caller]
+fn connect_block_with_params(
+    block: &Block,
+    witnesses: &[Vec<Witness>],
+    mut utxo_set: UtxoSet,
+    height: Natural,
+    recent_headers: Option<&[BlockHeader]>,
+    params: &ChainParams,
+) -> Result<(
+    ValidationResult,
+    UtxoSet,
+    crate::reorganization::BlockUndoLog,
+)> {
+    let network = params.network;
+
+    if let Some(reason) = check_transaction_count(block, params) {
+        return Ok((
+            ValidationResult::Invalid(reason),
+            utxo_set,
+            crate::reorganization::BlockUndoLog::new(),
+        ));
+    }
     #[cfg(feature = "production")]
     #[inline(always)]
     #[cfg(not(feature = "production"))]
@@ -437,6 +655,10 @@ pub fn connect_block(
                             result
                         };
 
+                        // Witness stacks and BIP143 digests, shared by every input
+                        let witness_stacks = &witnesses[i];
+                        let sighash_cache = SighashCache::new(tx);
+
                         // Parallelize script verification using pre-looked-up UTXOs
                         use rayon::prelude::*;
                         let script_results: Result<Vec<bool>> = input_utxos
@@ -452,24 +674,25 @@ pub fn connect_block(
 
                                     #[cfg(not(feature = "production"))]
                                     let input = &tx.inputs[*j];
-                                    let witness_elem = witnesses.get(i).and_then(|w| w.get(*j));
                                     let median_time_past = recent_headers
                                         .map(get_median_time_past)
                                         .filter(|&mtp| mtp > 0);
-                                    let tx_witness = witnesses.get(i);
+                                    let tx_witness = witnesses.get(i).map(Vec::as_slice);
                                     let flags = calculate_script_flags_for_block(tx, tx_witness);
 
-                                    verify_script_with_context_full(
+                                    verify_script_with_witness(
                                         &input.script_sig,
                                         script_pubkey,
-                                        witness_elem,
+                                        witness_stacks.get(*j).map_or(&[][..], Vec::as_slice),
                                         flags,
                                         tx,
                                         *j,
                                         &prevouts,
+                                        &sighash_cache,
                                         Some(height),
                                         median_time_past,
                                         network,
+                                        default_verifier(),
                                     )
                                 } else {
                                     Ok(false)
@@ -500,10 +723,7 @@ pub fn connect_block(
                     ));
                 }
 
-                // Use checked arithmetic to prevent fee overflow
-                total_fees = total_fees
-                    .checked_add(fee)
-                    .ok_or_else(|| make_fee_overflow_error(Some(i)))?;
+                total_fees = add_block_fee(total_fees, fee, i)?;
             }
         }
 
@@ -607,26 +827,29 @@ pub fn connect_block(
                         })
                         .collect();
 
+                    let witness_stacks = &witnesses[i];
+                    let sighash_cache = SighashCache::new(tx);
                     for (j, input) in tx.inputs.iter().enumerate() {
                         if let Some(utxo) = utxo_set.get(&input.prevout) {
-                            let witness_elem = witnesses.get(i).and_then(|w| w.get(j));
                             let median_time_past = recent_headers
                                 .map(get_median_time_past)
                                 .filter(|&mtp| mtp > 0);
-                            let tx_witness = witnesses.get(i);
+                            let tx_witness = witnesses.get(i).map(Vec::as_slice);
                             let flags = calculate_script_flags_for_block(tx, tx_witness);
 
-                            if !verify_script_with_context_full(
+                            if !verify_script_with_witness(
                                 &input.script_sig,
                                 &utxo.script_pubkey,
-                                witness_elem,
+                                witness_stacks.get(j).map_or(&[][..], Vec::as_slice),
                                 flags,
                                 tx,
                                 j,
                                 &prevouts,
+                                &sighash_cache,
                                 Some(height),
                                 median_time_past,
                                 network,
+                                default_verifier(),
                             )? {
                                 return Ok((
                                     ValidationResult::Invalid(format!(
@@ -641,10 +864,7 @@ pub fn connect_block(
                     }
                 }
 
-                // Use checked arithmetic to prevent fee overflow
-                total_fees = total_fees
-                    .checked_add(fee)
-                    .ok_or_else(|| make_fee_overflow_error(Some(i)))?;
+                total_fees = add_block_fee(total_fees, fee, i)?;
             }
         }
     }
@@ -687,32 +907,35 @@ pub fn connect_block(
                     })
                     .collect();
 
+                // Each input's witness stack, and the BIP143 digests all inputs share
+                let witness_stacks = &witnesses[i];
+                let sighash_cache = SighashCache::new(tx);
+
                 for (j, input) in tx.inputs.iter().enumerate() {
                     if let Some(utxo) = utxo_set.get(&input.prevout) {
-                        // Get witness for this transaction input if available
-                        let witness = witnesses.get(i).and_then(|w| w.get(j));
-
                         // Calculate median time-past if recent headers are available
                         let median_time_past = recent_headers
                             .map(get_median_time_past)
                             .filter(|&mtp| mtp > 0); // Only use if valid (> 0)
 
                         // Calculate script verification flags for this transaction
-                        let tx_witness = witnesses.get(i);
+                        let tx_witness = witnesses.get(i).map(Vec::as_slice);
                         let flags = calculate_script_flags_for_block(tx, tx_witness);
 
-                        // Use verify_script_with_context_full for BIP65/112 support
-                        if !verify_script_with_context_full(
+                        // Use verify_script_with_witness for BIP65/112 and BIP141 support
+                        if !verify_script_with_witness(
                             &input.script_sig,
                             &utxo.script_pubkey,
-                            witness, // Pass witness data (Option<&Vec<u8>> = Option<&ByteString>)
+                            witness_stacks.get(j).map_or(&[][..], Vec::as_slice),
                             flags,
                             tx,
                             j, // Input index
                             &prevouts,
+                            &sighash_cache,
                             Some(height), // Block height for block-height CLTV validation
                             median_time_past, // Median time-past for timestamp CLTV validation (BIP113)
                             network,          // Network for BIP66 and BIP147 activation heights
+                            default_verifier(),
                         )? {
                             return Ok((
                                 ValidationResult::Invalid(format!(
@@ -726,18 +949,16 @@ pub fn connect_block(
                 }
             }
 
-            // Use checked arithmetic to prevent fee overflow
-            total_fees = total_fees
-                .checked_add(fee)
-                .ok_or_else(|| make_fee_overflow_error(Some(i)))?;
+            total_fees = add_block_fee(total_fees, fee, i)?;
         }
     }
 
     // 3. Validate coinbase transaction
     if let Some(coinbase) = block.transactions.first() {
-        if !is_coinbase(coinbase) {
+        let coinbase_input = crate::transaction::check_coinbase_input(coinbase);
+        if !matches!(coinbase_input, ValidationResult::Valid) {
             return Ok((
-                ValidationResult::Invalid("First transaction must be coinbase".into()),
+                coinbase_input,
                 utxo_set,
                 crate::reorganization::BlockUndoLog::new(),
             ));
@@ -808,18 +1029,15 @@ pub fn connect_block(
 
         // Validate witness commitment if witnesses are present (SegWit block)
         // Check if any witness is non-empty (indicating SegWit block)
-        let has_segwit = witnesses.iter().any(|w| !w.is_empty());
-        if has_segwit && !witnesses.is_empty() {
-            let witness_merkle_root = compute_witness_merkle_root(block, witnesses)?;
-            if !validate_witness_commitment(coinbase, &witness_merkle_root)? {
-                return Ok((
-                    ValidationResult::Invalid(
-                        "Invalid witness commitment in coinbase transaction".to_string(),
-                    ),
-                    utxo_set,
-                    crate::reorganization::BlockUndoLog::new(),
-                ));
-            }
+        let has_segwit = witnesses.iter().flatten().any(|stack| !stack.is_empty());
+        if has_segwit && !validate_block_witness_commitment(block, witnesses)? {
+            return Ok((
+                ValidationResult::Invalid(
+                    "Invalid witness commitment in coinbase transaction".to_string(),
+                ),
+                utxo_set,
+                crate::reorganization::BlockUndoLog::new(),
+            ));
         }
     } else {
         return Ok((
@@ -831,15 +1049,16 @@ pub fn connect_block(
 
     // 3.5. Check block sigop cost limit (network rule)
     // Calculate total sigop cost for all transactions in the block
-    use crate::constants::MAX_BLOCK_SIGOPS_COST;
     use crate::sigop::get_transaction_sigop_cost;
 
     let mut total_sigop_cost = 0u64;
-    let flags =
-        calculate_script_flags_for_block(block.transactions.first().unwrap(), witnesses.first());
+    let flags = calculate_script_flags_for_block(
+        block.transactions.first().unwrap(),
+        witnesses.first().map(Vec::as_slice),
+    );
 
     for (i, tx) in block.transactions.iter().enumerate() {
-        let tx_witness = witnesses.get(i);
+        let tx_witness = witnesses.get(i).map(Vec::as_slice);
 
         let tx_sigop_cost = get_transaction_sigop_cost(tx, &utxo_set, tx_witness, flags)?;
 
@@ -850,10 +1069,11 @@ pub fn connect_block(
         })?;
     }
 
-    if total_sigop_cost > MAX_BLOCK_SIGOPS_COST {
+    if total_sigop_cost > params.max_block_sigops_cost {
         return Ok((
             ValidationResult::Invalid(format!(
-                "Block sigop cost {total_sigop_cost} exceeds maximum {MAX_BLOCK_SIGOPS_COST}"
+                "Block sigop cost {total_sigop_cost} exceeds maximum {}",
+                params.max_block_sigops_cost
             )),
             utxo_set,
             crate::reorganization::BlockUndoLog::new(),
@@ -976,6 +1196,59 @@ pub fn connect_block(
     Ok((ValidationResult::Valid, utxo_set, undo_log))
 }
 
+/// RebuildUtxoSet: 𝔹* × ℕ → 𝒰𝒮
+///
+/// Reconstruct the UTXO set from scratch by connecting `blocks` in order,
+/// starting from an empty set with `blocks[0]` at `start_height`. Every block
+/// is fully validated by `connect_block`, so coinbase maturity is enforced
+/// and the resulting UTXOs carry their `height` and `is_coinbase` flags.
+///
+/// Intended for recovery tooling: verifying a UTXO snapshot, or rebuilding
+/// the set after corruption. `witnesses[i]` holds the witness stacks of
+/// `blocks[i]`, in the shape `connect_block` takes, so segwit spends verify
+/// as they did when the blocks were first connected. Blocks are connected
+/// without recent headers, and the network is taken from
+/// `Network::from_env()`, matching `ConsensusProof`.
+///
+/// # Errors
+///
+/// Returns `ConsensusError::BlockValidation` if `witnesses` does not have one
+/// entry per block, or naming the height of the first block that fails
+/// validation.
+pub fn rebuild_utxo_set(
+    blocks: &[Block],
+    witnesses: &[Vec<Vec<Witness>>],
+    start_height: Natural,
+) -> Result<UtxoSet> {
+    if witnesses.len() != blocks.len() {
+        return Err(ConsensusError::BlockValidation(
+            format!(
+                "Witness count {} does not match block count {}",
+                witnesses.len(),
+                blocks.len()
+            )
+            .into(),
+        ));
+    }
+
+    let network = crate::types::Network::from_env();
+    let mut utxo_set = UtxoSet::new();
+
+    for (offset, (block, block_witnesses)) in blocks.iter().zip(witnesses).enumerate() {
+        let height = start_height + offset as Natural;
+        let (result, new_utxo_set, _undo_log) =
+            connect_block(block, block_witnesses, utxo_set, height, None, network)?;
+        if let ValidationResult::Invalid(reason) = result {
+            return Err(ConsensusError::BlockValidation(
+                format!("Block at height {height} is invalid: {reason}").into(),
+            ));
+        }
+        utxo_set = new_utxo_set;
+    }
+
+    Ok(utxo_set)
+}
+
 /// ApplyTransaction: 𝒯𝒳 × 𝒰𝒮 → 𝒰𝒮
 ///
 /// For transaction tx and UTXO set us:
@@ -1148,10 +1421,10 @@ fn validate_block_header(header: &BlockHeader) -> Result<bool> {
 /// Returns appropriate flags based on transaction type:
 /// - Base flags: Standard validation flags
 /// - SegWit flag (0x800): Enabled if transaction uses SegWit
-/// - Taproot flag (0x2000): Enabled if transaction uses Taproot
+/// - Taproot flag (0x20000): Enabled if transaction uses Taproot
 pub(crate) fn calculate_script_flags_for_block(
     tx: &Transaction,
-    tx_witness: Option<&Witness>,
+    tx_witness: Option<&[Witness]>,
 ) -> u32 {
     // Base flags (standard validation flags)
     // SCRIPT_VERIFY_P2SH = 0x01, SCRIPT_VERIFY_STRICTENC = 0x02, etc.
@@ -1170,7 +1443,7 @@ pub(crate) fn calculate_script_flags_for_block(
         let script = &output.script_pubkey;
         use crate::constants::TAPROOT_SCRIPT_LENGTH;
         if script.len() == TAPROOT_SCRIPT_LENGTH && script[0] == 0x51 && script[1] == 0x20 {
-            flags |= 0x2000; // SCRIPT_VERIFY_TAPROOT
+            flags |= 0x20000; // SCRIPT_VERIFY_TAPROOT
             break;
         }
     }
@@ -1253,6 +1526,18 @@ pub fn calculate_tx_id(tx: &Transaction) -> Hash {
     }
 }
 
+/// Calculate witness transaction ID (wtxid, BIP141)
+///
+/// Double SHA256 of the witness serialization. `witnesses` holds one witness
+/// stack per input; a transaction without witness data has wtxid == txid.
+pub fn calculate_wtxid(tx: &Transaction, witnesses: &[Witness]) -> Hash {
+    use crate::crypto::OptimizedSha256;
+    use crate::serialization::transaction::serialize_transaction_with_witness;
+
+    let serialized = serialize_transaction_with_witness(tx, witnesses);
+    OptimizedSha256::new().hash256(&serialized)
+}
+
 // ============================================================================
 // FORMAL VERIFICATION
 // ============================================================================
@@ -1644,10 +1929,10 @@ mod kani_proofs {
         }
 
         // Optimization: Pre-allocate witness vectors with capacity
-        let witnesses: Vec<Witness> = block
+        let witnesses: Vec<Vec<Witness>> = block
             .transactions
             .iter()
-            .map(|_| Vec::with_capacity(2))
+            .map(|tx| vec![Vec::new(); tx.inputs.len()])
             .collect();
         let result = connect_block(
             &block,
@@ -1710,10 +1995,10 @@ mod kani_proofs {
         }
 
         // Optimization: Pre-allocate witness vectors with capacity
-        let witnesses: Vec<Witness> = block
+        let witnesses: Vec<Vec<Witness>> = block
             .transactions
             .iter()
-            .map(|_| Vec::with_capacity(2))
+            .map(|tx| vec![Vec::new(); tx.inputs.len()])
             .collect();
         let result = connect_block(
             &block,
@@ -1755,7 +2040,7 @@ mod kani_proofs {
     /// - calculate_script_flags_for_block(tx, witness) = flags ⟹
     ///   1. Base flags always enabled (SCRIPT_VERIFY_P2SH, STRICTENC, etc.)
     ///   2. SCRIPT_VERIFY_WITNESS (0x800) enabled if witness present or is_segwit_transaction(tx)
-    ///   3. SCRIPT_VERIFY_TAPROOT (0x2000) enabled if any output is P2TR (0x5120)
+    ///   3. SCRIPT_VERIFY_TAPROOT (0x20000) enabled if any output is P2TR (0x5120)
     ///
     /// This ensures script verification flags are calculated correctly based on transaction type.
     #[kani::proof]
@@ -1795,8 +2080,8 @@ mod kani_proofs {
         assert_eq!(has_segwit_flag, has_witness || is_segwit,
             "Script flags calculation: SCRIPT_VERIFY_WITNESS must be enabled if witness present or transaction is SegWit");
 
-        // Critical invariant: Taproot flag (0x2000) enabled if any output is P2TR
-        let has_taproot_flag = (flags & 0x2000) != 0;
+        // Critical invariant: Taproot flag (0x20000) enabled if any output is P2TR
+        let has_taproot_flag = (flags & 0x20000) != 0;
         let has_p2tr_output = tx.outputs.iter().any(|output| {
             let script = &output.script_pubkey;
             script.len() == 34 && script[0] == 0x51 && script[1] == 0x20
@@ -1993,10 +2278,10 @@ mod kani_proofs {
         }
 
         // Optimization: Pre-allocate witness vectors with capacity
-        let witnesses: Vec<Witness> = block
+        let witnesses: Vec<Vec<Witness>> = block
             .transactions
             .iter()
-            .map(|_| Vec::with_capacity(2))
+            .map(|tx| vec![Vec::new(); tx.inputs.len()])
             .collect();
         let result = connect_block(
             &block,
@@ -2196,7 +2481,11 @@ mod property_tests {
                 }
             }
 
-            let witnesses: Vec<Witness> = bounded_block.transactions.iter().map(|_| Vec::new()).collect();
+            let witnesses: Vec<Vec<Witness>> = bounded_block
+            .transactions
+            .iter()
+            .map(|tx| vec![Vec::new(); tx.inputs.len()])
+            .collect();
             let result = connect_block(&bounded_block, &witnesses, utxo_set, height, None, crate::types::Network::Mainnet);
 
             match result {
@@ -2391,10 +2680,10 @@ mod kani_proofs_2 {
         }
 
         // Optimization: Pre-allocate witness vectors with capacity
-        let witnesses: Vec<Witness> = block
+        let witnesses: Vec<Vec<Witness>> = block
             .transactions
             .iter()
-            .map(|_| Vec::with_capacity(2))
+            .map(|tx| vec![Vec::new(); tx.inputs.len()])
             .collect();
         let result = connect_block(
             &block,
@@ -2842,10 +3131,10 @@ mod tests {
 
         let utxo_set = UtxoSet::new();
         // Optimization: Pre-allocate witness vectors with capacity
-        let witnesses: Vec<Witness> = block
+        let witnesses: Vec<Vec<Witness>> = block
             .transactions
             .iter()
-            .map(|_| Vec::with_capacity(2))
+            .map(|tx| vec![Vec::new(); tx.inputs.len()])
             .collect();
         let (result, new_utxo_set, _undo_log) = connect_block(
             &block,
@@ -2861,6 +3150,543 @@ mod tests {
         assert_eq!(new_utxo_set.len(), 1); // One new UTXO from coinbase
     }
 
+    #[test]
+    fn test_connect_block_with_signed_p2wpkh_spend() {
+        use crate::mining::{build_coinbase, build_coinbase_witness, calculate_merkle_root};
+        use crate::segwit::{compute_witness_commitment, compute_witness_merkle_root};
+        use crate::serialization::transaction::deserialize_transaction_with_witness;
+
+        // BIP143 "Native P2WPKH" example, signed: a P2PK input and a P2WPKH
+        // input spent by [sig, pubkey]
+        let (spend, spend_stacks) = deserialize_transaction_with_witness(
+            &hex::decode(
+                "01000000000102fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f00\
+                 000000494830450221008b9d1dc26ba6a9cb62127b02742fa9d754cd3bebf337f7a55d114c8e5cdd\
+                 30be022040529b194ba3f9281a99f2b1c0a19c0489bc22ede944ccf4ecbab4cc618ef3ed01eeffff\
+                 ffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffff\
+                 ffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac909351\
+                 0d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac000247304402203609\
+                 e17b84f6a7d30c80bfa610b5b4542f32a8a0d5447a12fb1366d7f01cc44a0220573a954c45183315\
+                 61406f90300e8f3358f51928d43c212a8caed02de67eebee0121025476c2e83188368da1ff3e292e\
+                 7acafcdb3566bb0ad253f62fc70f07aeee635711000000",
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert!(spend_stacks[0].is_empty());
+        assert_eq!(spend_stacks[1].len(), 2);
+
+        let mut utxo_set = UtxoSet::new();
+        for (input, (value, script_pubkey)) in spend.inputs.iter().zip([
+            (
+                625_000_000,
+                "2103c9f4836b9a4f77fc0d81f7bcb01b7f1b35916864b9476c241ce9fc198bd25432ac",
+            ),
+            (600_000_000, "00141d0f172a0ecb48aee1be1f2687d2963ae33f71a1"),
+        ]) {
+            utxo_set.insert(
+                input.prevout.clone(),
+                UTXO {
+                    value,
+                    script_pubkey: hex::decode(script_pubkey).unwrap(),
+                    height: 1,
+                    is_coinbase: false,
+                },
+            );
+        }
+
+        // Commit to the spend's witness; the coinbase's wtxid counts as zero
+        let height = 500_000;
+        let placeholder = build_coinbase(height, 0, &[], Some([0; 32])).unwrap();
+        let mut witnesses = vec![vec![build_coinbase_witness()], spend_stacks];
+        let unsigned = Block {
+            header: BlockHeader {
+                version: 0x20000000,
+                prev_block_hash: [0; 32],
+                merkle_root: [0; 32],
+                timestamp: 1_513_622_125,
+                bits: 0x1d00ffff,
+                nonce: 0,
+            },
+            transactions: vec![placeholder, spend.clone()].into_boxed_slice(),
+        };
+        let witness_root = compute_witness_merkle_root(&unsigned, &witnesses).unwrap();
+        let commitment = compute_witness_commitment(&witness_root, &[0; 32]);
+        let coinbase = build_coinbase(
+            height,
+            get_block_subsidy(height),
+            &[TransactionOutput {
+                value: get_block_subsidy(height),
+                script_pubkey: vec![0x51],
+            }],
+            Some(commitment),
+        )
+        .unwrap();
+        let transactions = vec![coinbase, spend];
+        let block = Block {
+            header: BlockHeader {
+                merkle_root: calculate_merkle_root(&transactions).unwrap(),
+                ..unsigned.header
+            },
+            transactions: transactions.into_boxed_slice(),
+        };
+
+        let (result, new_utxo_set, _undo_log) = connect_block(
+            &block,
+            &witnesses,
+            utxo_set.clone(),
+            height,
+            None,
+            crate::types::Network::Mainnet,
+        )
+        .unwrap();
+        assert_eq!(result, ValidationResult::Valid);
+        assert!(block.transactions[1]
+            .inputs
+            .iter()
+            .all(|input| !new_utxo_set.contains_key(&input.prevout)));
+
+        // Without the pubkey the P2WPKH input no longer verifies
+        witnesses[1][1].truncate(1);
+        let (result, _, _) = connect_block(
+            &block,
+            &witnesses,
+            utxo_set,
+            height,
+            None,
+            crate::types::Network::Mainnet,
+        )
+        .unwrap();
+        assert!(matches!(result, ValidationResult::Invalid(_)));
+    }
+
+    fn coinbase_block(tag: u8, value: Integer) -> Block {
+        let coinbase_tx = Transaction {
+            version: 1,
+            inputs: crate::tx_inputs![TransactionInput {
+                prevout: OutPoint {
+                    hash: [0; 32],
+                    index: 0xffffffff,
+                },
+                script_sig: vec![0x01, tag], // Distinct per block: no duplicate txids
+                sequence: 0xffffffff,
+            }],
+            outputs: crate::tx_outputs![TransactionOutput {
+                value,
+                script_pubkey: vec![0x51],
+            }],
+            lock_time: 0,
+        };
+        let merkle_root =
+            crate::mining::calculate_merkle_root(std::slice::from_ref(&coinbase_tx)).unwrap();
+        Block {
+            header: BlockHeader {
+                version: 1,
+                prev_block_hash: [tag; 32],
+                merkle_root,
+                timestamp: 1231006505 + tag as Natural * 600,
+                bits: 0x1d00ffff,
+                nonce: 0,
+            },
+            transactions: vec![coinbase_tx].into_boxed_slice(),
+        }
+    }
+
+    #[test]
+    fn test_rebuild_utxo_set_matches_incremental_connect() {
+        let blocks: Vec<Block> = (1..=3).map(|tag| coinbase_block(tag, 5000000000)).collect();
+
+        let mut incremental = UtxoSet::new();
+        for (offset, block) in blocks.iter().enumerate() {
+            let witnesses: Vec<Vec<Witness>> = vec![vec![Vec::new()]];
+            let (result, utxo_set, _undo_log) = connect_block(
+                block,
+                &witnesses,
+                incremental,
+                10 + offset as Natural,
+                None,
+                crate::types::Network::from_env(),
+            )
+            .unwrap();
+            assert_eq!(result, ValidationResult::Valid);
+            incremental = utxo_set;
+        }
+
+        let witnesses = vec![vec![Vec::new()]; blocks.len()];
+        let rebuilt = rebuild_utxo_set(&blocks, &witnesses, 10).unwrap();
+        assert_eq!(rebuilt, incremental);
+        assert_eq!(rebuilt.len(), 3);
+        assert!(rebuilt.values().all(|utxo| utxo.is_coinbase));
+        let mut heights: Vec<Natural> = rebuilt.values().map(|utxo| utxo.height).collect();
+        heights.sort_unstable();
+        assert_eq!(heights, vec![10, 11, 12]);
+    }
+
+    #[test]
+    fn test_rebuild_utxo_set_rejects_invalid_block() {
+        // Second block's coinbase claims more than the subsidy
+        let blocks = vec![coinbase_block(1, 5000000000), coinbase_block(2, 5000000001)];
+        let witnesses = vec![vec![Vec::new()]; blocks.len()];
+        let err = rebuild_utxo_set(&blocks, &witnesses, 10).unwrap_err();
+        assert!(err.to_string().contains("height 11"));
+
+        assert!(rebuild_utxo_set(&blocks, &witnesses[..1], 10).is_err());
+    }
+
+    #[test]
+    fn test_rebuild_utxo_set_with_segwit_spend() {
+        use crate::mining::{build_coinbase, build_coinbase_witness, calculate_merkle_root};
+        use crate::segwit::{compute_witness_commitment, compute_witness_merkle_root};
+
+        // P2WSH paying to the witness script OP_1
+        let witness_script = vec![0x51];
+        let mut p2wsh = vec![0x00, 0x20];
+        p2wsh.extend_from_slice(&crate::crypto::sha256(&witness_script));
+
+        let block_at = |transactions: Vec<Transaction>| Block {
+            header: BlockHeader {
+                version: 0x20000000,
+                prev_block_hash: [0; 32],
+                merkle_root: calculate_merkle_root(&transactions).unwrap(),
+                timestamp: 1_513_622_125,
+                bits: 0x1d00ffff,
+                nonce: 0,
+            },
+            transactions: transactions.into_boxed_slice(),
+        };
+        let coinbase_paying = |height: Natural, script_pubkey: &ByteString, commitment| {
+            build_coinbase(
+                height,
+                get_block_subsidy(height),
+                &[TransactionOutput {
+                    value: get_block_subsidy(height),
+                    script_pubkey: script_pubkey.clone(),
+                }],
+                commitment,
+            )
+            .unwrap()
+        };
+
+        // The first coinbase pays the P2WSH output; 100 more blocks mature it
+        let start_height = 500_000;
+        let mut blocks = Vec::new();
+        let mut witnesses = Vec::new();
+        for offset in 0..=COINBASE_MATURITY {
+            let height = start_height + offset;
+            let script_pubkey = if offset == 0 {
+                p2wsh.clone()
+            } else {
+                vec![0x51]
+            };
+            blocks.push(block_at(vec![coinbase_paying(
+                height,
+                &script_pubkey,
+                None,
+            )]));
+            witnesses.push(vec![Vec::new()]);
+        }
+
+        let funding = &blocks[0].transactions[0];
+        let spend = Transaction {
+            version: 2,
+            inputs: crate::tx_inputs![TransactionInput {
+                prevout: OutPoint {
+                    hash: calculate_tx_id(funding),
+                    index: 0,
+                },
+                script_sig: vec![],
+                sequence: 0xffffffff,
+            }],
+            outputs: crate::tx_outputs![TransactionOutput {
+                value: funding.outputs[0].value,
+                script_pubkey: vec![0x51],
+            }],
+            lock_time: 0,
+        };
+
+        // Commit to the spend's witness; the coinbase's wtxid counts as zero
+        let height = start_height + COINBASE_MATURITY + 1;
+        let spend_block_witnesses =
+            vec![vec![build_coinbase_witness()], vec![vec![witness_script]]];
+        let placeholder = build_coinbase(height, 0, &[], Some([0; 32])).unwrap();
+        let witness_root = compute_witness_merkle_root(
+            &block_at(vec![placeholder, spend.clone()]),
+            &spend_block_witnesses,
+        )
+        .unwrap();
+        let commitment = compute_witness_commitment(&witness_root, &[0; 32]);
+        let coinbase = coinbase_paying(height, &vec![0x51], Some(commitment));
+        blocks.push(block_at(vec![coinbase, spend.clone()]));
+        witnesses.push(spend_block_witnesses);
+
+        let rebuilt = rebuild_utxo_set(&blocks, &witnesses, start_height).unwrap();
+        let spend_id = calculate_tx_id(&spend);
+        assert!(!rebuilt.contains_key(&spend.inputs[0].prevout));
+        assert!(rebuilt.contains_key(&OutPoint {
+            hash: spend_id,
+            index: 0
+        }));
+
+        // Without its witness the P2WSH spend no longer verifies
+        witnesses.last_mut().unwrap()[1] = vec![Vec::new()];
+        let err = rebuild_utxo_set(&blocks, &witnesses, start_height).unwrap_err();
+        assert!(err.to_string().contains(&format!("height {height}")));
+        assert!(err.to_string().contains("Invalid script"));
+    }
+
+    #[test]
+    fn test_verify_block_uses_chain_params_limits() {
+        // 1001 bare CHECKMULTISIGs: 20,020 legacy sigops, cost 80,080
+        let mut block = coinbase_block(1, 5000000000);
+        let mut coinbase = block.transactions[0].clone();
+        coinbase.outputs[0].script_pubkey = vec![0xae; 1001];
+        block.header.merkle_root =
+            crate::mining::calculate_merkle_root(std::slice::from_ref(&coinbase)).unwrap();
+        block.transactions = vec![coinbase].into_boxed_slice();
+        let witnesses: Vec<Vec<Witness>> = vec![vec![Vec::new()]];
+
+        let (result, _, _) = verify_block(
+            &block,
+            &witnesses,
+            UtxoSet::new(),
+            1,
+            None,
+            &ChainParams::mainnet(),
+        )
+        .unwrap();
+        assert!(
+            matches!(result, ValidationResult::Invalid(ref reason) if reason.contains("sigop"))
+        );
+
+        let large_blocks = ChainParams {
+            max_block_weight: 8_000_000,
+            max_block_sigops_cost: 160_000,
+            max_block_serialized_size: 8_000_000,
+            ..ChainParams::mainnet()
+        };
+        let (result, utxo_set, _) =
+            verify_block(&block, &witnesses, UtxoSet::new(), 1, None, &large_blocks).unwrap();
+        assert_eq!(result, ValidationResult::Valid);
+        assert_eq!(utxo_set.len(), 1);
+
+        // Size and weight limits come from the params too
+        let tiny = ChainParams {
+            max_block_serialized_size: 100,
+            ..large_blocks
+        };
+        let (result, _, _) =
+            verify_block(&block, &witnesses, UtxoSet::new(), 1, None, &tiny).unwrap();
+        assert!(
+            matches!(result, ValidationResult::Invalid(ref reason) if reason.starts_with("bad-blk-length"))
+        );
+    }
+
+    #[test]
+    fn test_verify_block_accepts_full_legacy_block() {
+        // Legacy block just under 1MB: weight is 4 × size, under MAX_BLOCK_WEIGHT
+        let mut block = coinbase_block(1, 5000000000);
+        let mut coinbase = block.transactions[0].clone();
+        let mut script = vec![0x6a];
+        script.resize(999_000, 0x00);
+        coinbase.outputs[0].script_pubkey = script;
+        block.header.merkle_root =
+            crate::mining::calculate_merkle_root(std::slice::from_ref(&coinbase)).unwrap();
+        block.transactions = vec![coinbase].into_boxed_slice();
+        let witnesses: Vec<Vec<Witness>> = vec![vec![Vec::new()]];
+
+        let size = calculate_block_serialized_size(&block);
+        assert!(size > 999_000 && size < 1_000_000);
+        let tx_size = crate::transaction::calculate_transaction_size(&block.transactions[0]);
+        let weight = crate::segwit::calculate_block_weight(&block, &witnesses).unwrap();
+        assert_eq!(weight, 4 * tx_size as Natural);
+
+        let (result, _, _) = verify_block(
+            &block,
+            &witnesses,
+            UtxoSet::new(),
+            1,
+            None,
+            &ChainParams::mainnet(),
+        )
+        .unwrap();
+        assert_eq!(result, ValidationResult::Valid);
+
+        // One more kilobyte puts the stripped size over MAX_BLOCK_WEIGHT / 4
+        let mut coinbase = block.transactions[0].clone();
+        coinbase.outputs[0].script_pubkey.resize(1_000_000, 0x00);
+        block.header.merkle_root =
+            crate::mining::calculate_merkle_root(std::slice::from_ref(&coinbase)).unwrap();
+        block.transactions = vec![coinbase].into_boxed_slice();
+        let (result, _, _) = verify_block(
+            &block,
+            &witnesses,
+            UtxoSet::new(),
+            1,
+            None,
+            &ChainParams::mainnet(),
+        )
+        .unwrap();
+        assert!(
+            matches!(result, ValidationResult::Invalid(ref reason) if reason.starts_with("bad-blk-length: stripped size"))
+        );
+    }
+
+    #[test]
+    fn test_block_with_more_transactions_than_fit_is_rejected() {
+        let mut block = coinbase_block(1, 5000000000);
+        let coinbase = block.transactions[0].clone();
+        block.transactions = vec![coinbase; 3].into_boxed_slice();
+        let witnesses: Vec<Vec<Witness>> = vec![vec![Vec::new()]; 3];
+
+        // Room for two minimal transactions only
+        let params = ChainParams {
+            max_block_weight: 2 * MIN_SERIALIZABLE_TRANSACTION_WEIGHT,
+            ..ChainParams::regtest()
+        };
+        let (result, _, _) =
+            verify_block(&block, &witnesses, UtxoSet::new(), 1, None, &params).unwrap();
+        match result {
+            ValidationResult::Invalid(reason) => {
+                assert!(reason.starts_with("bad-blk-length: 3 transactions"))
+            }
+            ValidationResult::Valid => panic!("block cannot fit its transactions"),
+        }
+    }
+
+    #[test]
+    fn test_check_coinbase_structure() {
+        use crate::mining::build_coinbase_witness;
+        use crate::segwit::create_witness_commitment_script;
+
+        let mut block = coinbase_block(1, 5000000000);
+        let witnesses = vec![vec![build_coinbase_witness()]];
+        assert_eq!(
+            check_coinbase_structure(&block, &[]),
+            ValidationResult::Valid
+        );
+
+        let commitment = TransactionOutput {
+            value: 0,
+            script_pubkey: create_witness_commitment_script(&[7; 32]),
+        };
+        let mut coinbase = block.transactions[0].clone();
+        coinbase.outputs.push(commitment.clone());
+        block.transactions = vec![coinbase.clone()].into_boxed_slice();
+        assert_eq!(
+            check_coinbase_structure(&block, &witnesses),
+            ValidationResult::Valid
+        );
+
+        // A commitment needs the 32-byte reserved value as the coinbase witness
+        let bad_witnesses: [Vec<Vec<Witness>>; 3] = [
+            vec![],
+            vec![vec![vec![]]],
+            vec![vec![vec![vec![0; 32], vec![]]]],
+        ];
+        for bad_witness in bad_witnesses {
+            assert!(matches!(
+                check_coinbase_structure(&block, &bad_witness),
+                ValidationResult::Invalid(reason) if reason.starts_with("bad-witness-nonce-size")
+            ));
+        }
+
+        // An OP_RETURN push of 36 bytes without the header is not a commitment
+        let mut other = commitment.clone();
+        other.script_pubkey[2] = 0;
+        coinbase.outputs.push(other);
+        block.transactions = vec![coinbase.clone()].into_boxed_slice();
+        assert_eq!(
+            check_coinbase_structure(&block, &witnesses),
+            ValidationResult::Valid
+        );
+        coinbase.outputs.pop();
+
+        // Two witness commitments
+        coinbase.outputs.push(commitment);
+        block.transactions = vec![coinbase.clone()].into_boxed_slice();
+        assert!(matches!(
+            check_coinbase_structure(&block, &witnesses),
+            ValidationResult::Invalid(reason) if reason.contains("second witness commitment")
+        ));
+
+        // Truncated witness commitment
+        coinbase.outputs.pop();
+        coinbase.outputs[1]
+            .script_pubkey
+            .truncate(MINIMUM_WITNESS_COMMITMENT - 1);
+        block.transactions = vec![coinbase.clone()].into_boxed_slice();
+        assert!(matches!(
+            check_coinbase_structure(&block, &witnesses),
+            ValidationResult::Invalid(reason) if reason.contains("truncated")
+        ));
+
+        // Oversized coinbase
+        coinbase.outputs.pop();
+        coinbase.outputs[0].script_pubkey = vec![0x51; MAX_TX_SIZE];
+        block.transactions = vec![coinbase].into_boxed_slice();
+        assert!(matches!(
+            check_coinbase_structure(&block, &witnesses),
+            ValidationResult::Invalid(reason) if reason.starts_with("bad-cb-outputs")
+        ));
+
+        // No coinbase
+        block.transactions = Vec::new().into_boxed_slice();
+        assert!(matches!(
+            check_coinbase_structure(&block, &witnesses),
+            ValidationResult::Invalid(reason) if reason.starts_with("bad-cb-missing")
+        ));
+    }
+
+    #[test]
+    fn test_block_fees_bounded_by_max_money() {
+        assert_eq!(add_block_fee(0, MAX_MONEY, 1).unwrap(), MAX_MONEY);
+        assert!(add_block_fee(i64::MAX, 1, 1).is_err());
+        assert!(add_block_fee(MAX_MONEY, 1, 2).is_err());
+
+        // Two transactions each claiming MAX_MONEY in fees
+        let mut block = coinbase_block(1, 0);
+        let mut utxo_set = UtxoSet::new();
+        for tag in [2u8, 3] {
+            let prevout = OutPoint {
+                hash: [tag; 32],
+                index: 0,
+            };
+            utxo_set.insert(
+                prevout.clone(),
+                UTXO {
+                    value: MAX_MONEY,
+                    script_pubkey: vec![0x51, 0x87], // OP_1 OP_EQUAL
+                    height: 0,
+                    is_coinbase: false,
+                },
+            );
+            let mut transactions = block.transactions.to_vec();
+            transactions.push(Transaction {
+                version: 1,
+                inputs: crate::tx_inputs![TransactionInput {
+                    prevout,
+                    script_sig: vec![0x51], // OP_1
+                    sequence: 0xffffffff,
+                }],
+                outputs: crate::tx_outputs![TransactionOutput {
+                    value: 0,
+                    script_pubkey: vec![0x51],
+                }],
+                lock_time: 0,
+            });
+            block.transactions = transactions.into_boxed_slice();
+        }
+        block.header.merkle_root =
+            crate::mining::calculate_merkle_root(&block.transactions).unwrap();
+        let witnesses: Vec<Vec<Witness>> = vec![vec![Vec::new()]; 3];
+
+        let err =
+            connect_block(&block, &witnesses, utxo_set, 1, None, Network::Mainnet).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Total fees overflow at transaction 2"));
+    }
+
     #[test]
     fn test_apply_transaction_coinbase() {
         let coinbase_tx = Transaction {
@@ -2886,6 +3712,8 @@ mod tests {
         let (new_utxo_set, _undo_entries) = apply_transaction(&coinbase_tx, utxo_set, 0).unwrap();
 
         assert_eq!(new_utxo_set.len(), 1);
+        // Coinbase outputs are flagged for the maturity check
+        assert!(new_utxo_set.values().all(|utxo| utxo.is_coinbase));
     }
 
     // ============================================================================
@@ -2927,10 +3755,10 @@ mod tests {
 
         let utxo_set = UtxoSet::new();
         // Optimization: Pre-allocate witness vectors with capacity
-        let witnesses: Vec<Witness> = block
+        let witnesses: Vec<Vec<Witness>> = block
             .transactions
             .iter()
-            .map(|_| Vec::with_capacity(2))
+            .map(|tx| vec![Vec::new(); tx.inputs.len()])
             .collect();
         let (result, _, _undo_log) = connect_block(
             &block,
@@ -2961,10 +3789,10 @@ mod tests {
 
         let utxo_set = UtxoSet::new();
         // Optimization: Pre-allocate witness vectors with capacity
-        let witnesses: Vec<Witness> = block
+        let witnesses: Vec<Vec<Witness>> = block
             .transactions
             .iter()
-            .map(|_| Vec::with_capacity(2))
+            .map(|tx| vec![Vec::new(); tx.inputs.len()])
             .collect();
         let (result, _, _undo_log) = connect_block(
             &block,
@@ -3014,10 +3842,10 @@ mod tests {
 
         let utxo_set = UtxoSet::new();
         // Optimization: Pre-allocate witness vectors with capacity
-        let witnesses: Vec<Witness> = block
+        let witnesses: Vec<Vec<Witness>> = block
             .transactions
             .iter()
-            .map(|_| Vec::with_capacity(2))
+            .map(|tx| vec![Vec::new(); tx.inputs.len()])
             .collect();
         let (result, _, _undo_log) = connect_block(
             &block,
@@ -3067,10 +3895,10 @@ mod tests {
 
         let utxo_set = UtxoSet::new();
         // Optimization: Pre-allocate witness vectors with capacity
-        let witnesses: Vec<Witness> = block
+        let witnesses: Vec<Vec<Witness>> = block
             .transactions
             .iter()
-            .map(|_| Vec::with_capacity(2))
+            .map(|tx| vec![Vec::new(); tx.inputs.len()])
             .collect();
         let (result, _, _undo_log) = connect_block(
             &block,
@@ -3123,8 +3951,9 @@ mod tests {
 
         let (new_utxo_set, _undo_entries) = apply_transaction(&regular_tx, utxo_set, 1).unwrap();
 
-        // Should have 1 UTXO (the new output)
+        // Should have 1 UTXO (the new output), not flagged as coinbase
         assert_eq!(new_utxo_set.len(), 1);
+        assert!(new_utxo_set.values().all(|utxo| !utxo.is_coinbase));
     }
 
     #[test]
@@ -3390,10 +4219,10 @@ mod tests {
 
         let utxo_set = UtxoSet::new();
         // Optimization: Pre-allocate witness vectors with capacity
-        let witnesses: Vec<Witness> = block
+        let witnesses: Vec<Vec<Witness>> = block
             .transactions
             .iter()
-            .map(|_| Vec::with_capacity(2))
+            .map(|tx| vec![Vec::new(); tx.inputs.len()])
             .collect();
         let result = connect_block(
             &block,
@@ -3444,10 +4273,10 @@ mod tests {
 
         let utxo_set = UtxoSet::new();
         // Optimization: Pre-allocate witness vectors with capacity
-        let witnesses: Vec<Witness> = block
+        let witnesses: Vec<Vec<Witness>> = block
             .transactions
             .iter()
-            .map(|_| Vec::with_capacity(2))
+            .map(|tx| vec![Vec::new(); tx.inputs.len()])
             .collect();
         let result = connect_block(
             &block,
@@ -3607,10 +4436,10 @@ mod tests {
 
         let utxo_set = UtxoSet::new();
         // Optimization: Pre-allocate witness vectors with capacity
-        let witnesses: Vec<Witness> = block
+        let witnesses: Vec<Vec<Witness>> = block
             .transactions
             .iter()
-            .map(|_| Vec::with_capacity(2))
+            .map(|tx| vec![Vec::new(); tx.inputs.len()])
             .collect();
         let result = connect_block(
             &block,
@@ -3661,10 +4490,10 @@ mod tests {
 
         let utxo_set = UtxoSet::new();
         // Optimization: Pre-allocate witness vectors with capacity
-        let witnesses: Vec<Witness> = block
+        let witnesses: Vec<Vec<Witness>> = block
             .transactions
             .iter()
-            .map(|_| Vec::with_capacity(2))
+            .map(|tx| vec![Vec::new(); tx.inputs.len()])
             .collect();
         let result = connect_block(
             &block,
@@ -0,0 +1,156 @@
+//! Per-chain consensus parameters
+//!
+//! `ChainParams` carries the block resource limits that `verify_block`
+//! enforces and the difficulty retarget interval, so networks with different
+//! limits (regtest experiments, larger-block test chains) can be validated
+//! without touching the global constants. The presets for the Bitcoin
+//! networks use the values from `constants`, and each preset knows its
+//! network's genesis block.
+
+use crate::constants::{
+    DIFFICULTY_ADJUSTMENT_INTERVAL, MAX_BLOCK_SERIALIZED_SIZE, MAX_BLOCK_SIGOPS_COST,
+    MAX_BLOCK_WEIGHT,
+};
+use crate::types::*;
+
+/// Genesis coinbase scriptSig: nBits 0x1d00ffff, extra nonce 4, and the
+/// Times headline of 3 January 2009
+const GENESIS_SCRIPT_SIG: &str = "04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73";
+
+/// Genesis coinbase output: pay-to-pubkey to Satoshi's key
+const GENESIS_SCRIPT_PUBKEY: &str = "4104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac";
+
+/// Genesis merkle root (internal byte order), shared by all networks
+const GENESIS_MERKLE_ROOT: Hash = [
+    0x3b, 0xa3, 0xed, 0xfd, 0x7a, 0x7b, 0x12, 0xb2, 0x7a, 0xc7, 0x2c, 0x3e, 0x67, 0x76, 0x8f, 0x61,
+    0x7f, 0xc8, 0x1b, 0xc3, 0x88, 0x8a, 0x51, 0x32, 0x3a, 0x9f, 0xb8, 0xaa, 0x4b, 0x1e, 0x5e, 0x4a,
+];
+
+/// Consensus limits for one chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainParams {
+    /// Network whose activation heights apply (BIP34, BIP66, BIP90, ...)
+    pub network: Network,
+    /// Maximum block weight in weight units (BIP141)
+    pub max_block_weight: usize,
+    /// Maximum total sigop cost of a block
+    pub max_block_sigops_cost: u64,
+    /// Maximum serialized block size in bytes, with witness data
+    pub max_block_serialized_size: usize,
+    /// Blocks per difficulty retarget period
+    pub difficulty_adjustment_interval: u64,
+}
+
+impl ChainParams {
+    /// Bitcoin mainnet
+    pub fn mainnet() -> Self {
+        ChainParams {
+            network: Network::Mainnet,
+            max_block_weight: MAX_BLOCK_WEIGHT,
+            max_block_sigops_cost: MAX_BLOCK_SIGOPS_COST,
+            max_block_serialized_size: MAX_BLOCK_SERIALIZED_SIZE,
+            difficulty_adjustment_interval: DIFFICULTY_ADJUSTMENT_INTERVAL,
+        }
+    }
+
+    /// Bitcoin testnet (same limits as mainnet)
+    pub fn testnet() -> Self {
+        ChainParams {
+            network: Network::Testnet,
+            ..Self::mainnet()
+        }
+    }
+
+    /// Bitcoin regtest (same limits as mainnet)
+    pub fn regtest() -> Self {
+        ChainParams {
+            network: Network::Regtest,
+            ..Self::mainnet()
+        }
+    }
+
+    /// The network's genesis block
+    ///
+    /// All three networks share the same coinbase (and so merkle root); they
+    /// differ only in timestamp, bits and nonce.
+    pub fn genesis_block(&self) -> Block {
+        let (timestamp, bits, nonce) = match self.network {
+            Network::Mainnet => (1231006505, 0x1d00ffff, 2083236893),
+            Network::Testnet => (1296688602, 0x1d00ffff, 414098458),
+            Network::Regtest => (1296688602, 0x207fffff, 2),
+        };
+
+        let coinbase = Transaction {
+            version: 1,
+            inputs: crate::tx_inputs![TransactionInput {
+                prevout: OutPoint {
+                    hash: [0; 32],
+                    index: 0xffffffff,
+                },
+                script_sig: hex::decode(GENESIS_SCRIPT_SIG).expect("valid hex constant"),
+                sequence: 0xffffffff,
+            }],
+            outputs: crate::tx_outputs![TransactionOutput {
+                value: 50 * 100_000_000,
+                script_pubkey: hex::decode(GENESIS_SCRIPT_PUBKEY).expect("valid hex constant"),
+            }],
+            lock_time: 0,
+        };
+
+        Block {
+            header: BlockHeader {
+                version: 1,
+                prev_block_hash: [0; 32],
+                merkle_root: GENESIS_MERKLE_ROOT,
+                timestamp,
+                bits,
+                nonce,
+            },
+            transactions: vec![coinbase].into_boxed_slice(),
+        }
+    }
+
+    /// Preset for a Bitcoin network
+    pub fn for_network(network: Network) -> Self {
+        match network {
+            Network::Mainnet => Self::mainnet(),
+            Network::Testnet => Self::testnet(),
+            Network::Regtest => Self::regtest(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serialization::block_hash;
+
+    /// Hash in display (byte-reversed) hex order
+    fn display_hash(header: &BlockHeader) -> String {
+        let mut hash = block_hash(header);
+        hash.reverse();
+        hex::encode(hash)
+    }
+
+    #[test]
+    fn test_genesis_block_hashes() {
+        let genesis = ChainParams::mainnet().genesis_block();
+        assert_eq!(
+            display_hash(&genesis.header),
+            "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"
+        );
+        assert_eq!(
+            crate::mining::calculate_merkle_root(&genesis.transactions).unwrap(),
+            genesis.header.merkle_root
+        );
+
+        assert_eq!(
+            display_hash(&ChainParams::testnet().genesis_block().header),
+            "000000000933ea01ad0ee984209779baaec3ced90fa3f408719526f8d77f4943"
+        );
+        assert_eq!(
+            display_hash(&ChainParams::regtest().genesis_block().header),
+            "0f9188f13cb7b2c71f2a335e3a4fc328bf5beb436012afca590b1a11466e2206"
+        );
+    }
+}
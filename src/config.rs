@@ -125,6 +125,36 @@ pub struct MempoolConfig {
     /// Default: 1000 satoshis (Bitcoin Core standard)
     #[serde(default = "default_rbf_fee_increment")]
     pub rbf_fee_increment: i64,
+
+    /// Maximum sigop cost per standard transaction (Bitcoin Core: MAX_STANDARD_TX_SIGOPS_COST)
+    /// Transactions whose sigop cost exceeds this are rejected as non-standard
+    /// Default: 16000 (MAX_BLOCK_SIGOPS_COST / 5)
+    #[serde(default = "default_max_standard_tx_sigops_cost")]
+    pub max_standard_tx_sigops_cost: u64,
+
+    /// Highest transaction version treated as standard (Bitcoin Core: TX_MAX_STANDARD_VERSION)
+    /// Raise this to relay transactions using newer versions
+    /// Default: 2
+    #[serde(default = "default_max_standard_tx_version")]
+    pub max_standard_tx_version: u64,
+
+    /// Maximum scriptPubKey size of a standard OP_RETURN data carrier (Bitcoin Core: -datacarriersize)
+    /// Shared with the UTXO commitment spam filter, so relay and filtering agree
+    /// Default: 83 (80 bytes of data)
+    #[serde(default = "default_max_datacarrier_bytes")]
+    pub max_datacarrier_bytes: usize,
+}
+
+fn default_max_datacarrier_bytes() -> usize {
+    crate::constants::MAX_OP_RETURN_RELAY
+}
+
+fn default_max_standard_tx_sigops_cost() -> u64 {
+    crate::constants::MAX_STANDARD_TX_SIGOPS_COST
+}
+
+fn default_max_standard_tx_version() -> u64 {
+    crate::constants::MAX_STANDARD_TX_VERSION
 }
 
 fn default_rbf_fee_increment() -> i64 {
@@ -160,6 +190,9 @@ impl Default for MempoolConfig {
             min_relay_fee_rate: 1,
             min_tx_fee: 1000,
             rbf_fee_increment: 1000,
+            max_standard_tx_sigops_cost: crate::constants::MAX_STANDARD_TX_SIGOPS_COST,
+            max_standard_tx_version: crate::constants::MAX_STANDARD_TX_VERSION,
+            max_datacarrier_bytes: crate::constants::MAX_OP_RETURN_RELAY,
         }
     }
 }
@@ -423,7 +456,11 @@ impl Default for BlockValidationConfig {
 }
 
 /// Complete consensus configuration
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+///
+/// Not `Eq` with the `utxo-commitments` feature, whose peer consensus
+/// threshold is an `f64`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[cfg_attr(not(feature = "utxo-commitments"), derive(Eq))]
 pub struct ConsensusConfig {
     /// Network message size limits
     #[serde(default)]
@@ -572,6 +609,11 @@ impl ConsensusConfig {
                 config.mempool.rbf_fee_increment = increment;
             }
         }
+        if let Ok(val) = std::env::var("BLLVM_CONSENSUS_MEMPOOL_MAX_STANDARD_TX_VERSION") {
+            if let Ok(version) = val.parse::<u64>() {
+                config.mempool.max_standard_tx_version = version;
+            }
+        }
 
         // Load UTXO commitment configuration
         if let Ok(val) = std::env::var("BLLVM_CONSENSUS_UTXO_COMMITMENT_MAX_SET_MB") {
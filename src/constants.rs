@@ -7,7 +7,7 @@ pub const MAX_MONEY: i64 = 21_000_000 * 100_000_000;
 pub const MAX_TX_SIZE: usize = 1_000_000;
 
 /// Maximum block serialized size in bytes (network rule)
-/// This is the maximum size of a block when serialized without witness data
+/// This is the maximum size of a block when serialized with witness data
 pub const MAX_BLOCK_SERIALIZED_SIZE: usize = 4_000_000;
 
 /// Maximum block weight in weight units (network rule, BIP141)
@@ -15,11 +15,27 @@ pub const MAX_BLOCK_SERIALIZED_SIZE: usize = 4_000_000;
 /// This is the primary limit for SegWit blocks
 pub const MAX_BLOCK_WEIGHT: usize = 4_000_000;
 
+/// Minimum weight of any serializable transaction: 10 bytes (version, empty
+/// input and output counts, lock time) at 4 weight units per byte
+///
+/// Reference: Bitcoin Core `consensus.h` MIN_SERIALIZABLE_TRANSACTION_WEIGHT = 40
+pub const MIN_SERIALIZABLE_TRANSACTION_WEIGHT: usize = 40;
+
+/// Most transactions that can fit in a block of MAX_BLOCK_WEIGHT
+pub const MAX_BLOCK_TRANSACTIONS: usize = MAX_BLOCK_WEIGHT / MIN_SERIALIZABLE_TRANSACTION_WEIGHT;
+
 /// Maximum block size (deprecated - use MAX_BLOCK_WEIGHT for SegWit blocks)
 /// Kept for backward compatibility
 #[deprecated(note = "Use MAX_BLOCK_WEIGHT for SegWit blocks")]
 pub const MAX_BLOCK_SIZE: usize = MAX_BLOCK_WEIGHT;
 
+/// Size of a coinbase witness commitment output script (BIP141): OP_RETURN,
+/// a 36-byte push opcode, the 4-byte header 0xaa21a9ed and the 32-byte
+/// commitment
+///
+/// Reference: Bitcoin Core `validation.h` MINIMUM_WITNESS_COMMITMENT = 38
+pub const MINIMUM_WITNESS_COMMITMENT: usize = 38;
+
 /// Maximum number of inputs per transaction
 pub const MAX_INPUTS: usize = 1000;
 
@@ -35,9 +51,78 @@ pub const MAX_STACK_SIZE: usize = 1000;
 /// Maximum number of operations in script
 pub const MAX_SCRIPT_OPS: usize = 201;
 
+/// Maximum number of public keys in a CHECKMULTISIG
+///
+/// Each key also counts as one operation towards `MAX_SCRIPT_OPS`.
+///
+/// Reference: Bitcoin Core `script.h` MAX_PUBKEYS_PER_MULTISIG = 20
+pub const MAX_PUBKEYS_PER_MULTISIG: usize = 20;
+
 /// Maximum script element size (BIP141: witness elements can be up to 520 bytes)
 pub const MAX_SCRIPT_ELEMENT_SIZE: usize = 520;
 
+/// Script verification flag evaluating P2SH redeem scripts (BIP16)
+///
+/// Reference: Bitcoin Core `interpreter.h` SCRIPT_VERIFY_P2SH = (1U << 0)
+pub const SCRIPT_VERIFY_P2SH: u32 = 0x01;
+
+/// Script verification flag requiring defined sighash types and strictly
+/// encoded signatures and public keys
+///
+/// Reference: Bitcoin Core `interpreter.h` SCRIPT_VERIFY_STRICTENC = (1U << 1)
+pub const SCRIPT_VERIFY_STRICTENC: u32 = 0x02;
+
+/// Script verification flag requiring strict DER signatures (BIP66)
+///
+/// Reference: Bitcoin Core `interpreter.h` SCRIPT_VERIFY_DERSIG = (1U << 2)
+pub const SCRIPT_VERIFY_DERSIG: u32 = 0x04;
+
+/// Script verification flag rejecting ECDSA signatures whose S value is above
+/// half the curve order (BIP62 rule 5)
+///
+/// Reference: Bitcoin Core `interpreter.h` SCRIPT_VERIFY_LOW_S = (1U << 3)
+pub const SCRIPT_VERIFY_LOW_S: u32 = 0x08;
+
+/// Script verification flag requiring the extra element popped by
+/// OP_CHECKMULTISIG to be empty (BIP147)
+///
+/// Reference: Bitcoin Core `interpreter.h` SCRIPT_VERIFY_NULLDUMMY = (1U << 4)
+pub const SCRIPT_VERIFY_NULLDUMMY: u32 = 0x10;
+
+/// Script verification flag requiring numeric opcode operands to be
+/// minimally encoded script numbers
+///
+/// Core's flag also requires pushes to use the shortest opcode; only the
+/// numeric operand rule is enforced here.
+///
+/// Reference: Bitcoin Core `interpreter.h` SCRIPT_VERIFY_MINIMALDATA = (1U << 6)
+pub const SCRIPT_VERIFY_MINIMALDATA: u32 = 0x40;
+
+/// Script verification flag enforcing OP_CHECKSEQUENCEVERIFY (BIP112)
+///
+/// Without it, opcode 0xb2 is OP_NOP3.
+///
+/// Reference: Bitcoin Core `interpreter.h` SCRIPT_VERIFY_CHECKSEQUENCEVERIFY = (1U << 10)
+pub const SCRIPT_VERIFY_CHECKSEQUENCEVERIFY: u32 = 0x400;
+
+/// Script verification flag spending witness programs with their witness
+/// stacks (BIP141)
+///
+/// Reference: Bitcoin Core `interpreter.h` SCRIPT_VERIFY_WITNESS = (1U << 11)
+pub const SCRIPT_VERIFY_WITNESS: u32 = 0x800;
+
+/// Script verification flag requiring the OP_IF/OP_NOTIF argument to be
+/// empty or exactly `[0x01]` in witness v0 scripts (MINIMALIF)
+///
+/// Reference: Bitcoin Core `interpreter.h` SCRIPT_VERIFY_MINIMALIF = (1U << 13)
+pub const SCRIPT_VERIFY_MINIMALIF: u32 = 0x2000;
+
+/// Script verification flag requiring every signature that fails
+/// OP_CHECKSIG or OP_CHECKMULTISIG to be empty
+///
+/// Reference: Bitcoin Core `interpreter.h` SCRIPT_VERIFY_NULLFAIL = (1U << 14)
+pub const SCRIPT_VERIFY_NULLFAIL: u32 = 0x4000;
+
 /// Halving interval: 210,000 blocks
 pub const HALVING_INTERVAL: u64 = 210_000;
 
@@ -93,6 +178,52 @@ pub const COINBASE_MATURITY: u64 = 100;
 /// Reference: Bitcoin Core `consensus.h` MAX_BLOCK_SIGOPS_COST = 80000
 pub const MAX_BLOCK_SIGOPS_COST: u64 = 80_000;
 
+/// Maximum sigop cost of a single standard transaction (policy rule)
+///
+/// Transactions above this cost are non-standard and not relayed, so that no
+/// single transaction can use up more than a fifth of a block's sigop budget.
+///
+/// Reference: Bitcoin Core `policy.h` MAX_STANDARD_TX_SIGOPS_COST = MAX_BLOCK_SIGOPS_COST / 5
+pub const MAX_STANDARD_TX_SIGOPS_COST: u64 = MAX_BLOCK_SIGOPS_COST / 5;
+
+/// Maximum weight of a standard transaction (policy rule)
+///
+/// Heavier transactions are non-standard and not relayed, so that no single
+/// transaction can fill more than a tenth of a block.
+///
+/// Reference: Bitcoin Core `policy.h` MAX_STANDARD_TX_WEIGHT = 400000
+pub const MAX_STANDARD_TX_WEIGHT: usize = 400_000;
+
+/// Highest transaction version relayed as standard (policy rule)
+///
+/// Versions above this are reserved for future soft forks and policy changes,
+/// so transactions using them are non-standard by default.
+///
+/// Reference: Bitcoin Core `policy.h` TX_MAX_STANDARD_VERSION = 2
+pub const MAX_STANDARD_TX_VERSION: u64 = 2;
+
+/// Default maximum scriptPubKey size of a standard OP_RETURN data carrier
+/// (policy rule)
+///
+/// 80 bytes of data, plus one byte for OP_RETURN and two for the push opcode.
+/// Operators can change it with `MempoolConfig::max_datacarrier_bytes`.
+///
+/// Reference: Bitcoin Core `policy.h` MAX_OP_RETURN_RELAY = 83
+pub const MAX_OP_RETURN_RELAY: usize = 83;
+
+/// Maximum scriptSig size of a standard transaction input (policy rule)
+///
+/// Large enough for a 15-of-15 P2SH multisig spend with compressed keys;
+/// larger scriptSigs are non-standard and not relayed.
+///
+/// Reference: Bitcoin Core `policy.h` MAX_STANDARD_SCRIPTSIG_SIZE = 1650
+pub const MAX_STANDARD_SCRIPTSIG_SIZE: usize = 1650;
+
+/// Maximum number of transactions in a package evaluated together
+///
+/// Reference: Bitcoin Core `packages.h` MAX_PACKAGE_COUNT = 25
+pub const MAX_PACKAGE_COUNT: usize = 25;
+
 /// Witness commitment hash length (BIP141)
 ///
 /// The witness commitment in the coinbase transaction contains:
@@ -24,6 +24,8 @@ pub mod sha_ni;
 pub mod simd_bytes;
 
 pub mod int_ops;
+pub mod muhash;
+pub mod signature;
 
 /// CPU feature detection for runtime optimization selection
 pub mod cpu_features {
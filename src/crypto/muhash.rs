@@ -0,0 +1,328 @@
+//! MuHash3072 rolling set hash
+//!
+//! Implements Bitcoin Core's `MuHash3072` (`crypto/muhash.cpp`): each element is
+//! hashed to a number modulo the 3072-bit prime p = 2^3072 - 1103717, and the
+//! set hash is the product of those numbers. Elements can be added and removed
+//! in any order in O(1) multiplications; the modular inverse is only computed
+//! once, when the hash is finalized.
+//!
+//! Element-to-number mapping: SHA256(element) keys a ChaCha20 keystream, whose
+//! first 384 bytes are read as a little-endian 3072-bit integer.
+
+use sha2::{Digest, Sha256};
+
+/// Number of 64-bit limbs in a 3072-bit number
+const LIMBS: usize = 48;
+
+/// Size of a serialized 3072-bit number in bytes
+const BYTE_SIZE: usize = 384;
+
+/// p = 2^3072 - MAX_PRIME_DIFF
+const MAX_PRIME_DIFF: u64 = 1103717;
+
+/// A 3072-bit number, little-endian limbs, kept in [0, 2^3072)
+///
+/// Values are only partially reduced modulo p between operations; `full_reduce`
+/// brings them into [0, p) before serialization.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Num3072 {
+    limbs: [u64; LIMBS],
+}
+
+impl Num3072 {
+    fn one() -> Self {
+        let mut limbs = [0u64; LIMBS];
+        limbs[0] = 1;
+        Self { limbs }
+    }
+
+    fn from_bytes(bytes: &[u8; BYTE_SIZE]) -> Self {
+        let mut limbs = [0u64; LIMBS];
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            let mut word = [0u8; 8];
+            word.copy_from_slice(&bytes[8 * i..8 * i + 8]);
+            *limb = u64::from_le_bytes(word);
+        }
+        Self { limbs }
+    }
+
+    fn to_bytes(self) -> [u8; BYTE_SIZE] {
+        let mut bytes = [0u8; BYTE_SIZE];
+        for (i, limb) in self.limbs.iter().enumerate() {
+            bytes[8 * i..8 * i + 8].copy_from_slice(&limb.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Whether the value is >= p (i.e. every limb is all-ones above the lowest)
+    fn is_overflow(&self) -> bool {
+        self.limbs[0] > u64::MAX - MAX_PRIME_DIFF && self.limbs[1..].iter().all(|&l| l == u64::MAX)
+    }
+
+    /// Reduce a value in [p, 2^3072) into [0, p)
+    fn full_reduce(&mut self) {
+        // x - p = x + MAX_PRIME_DIFF - 2^3072; the 2^3072 carry is dropped
+        let mut carry = MAX_PRIME_DIFF as u128;
+        for limb in self.limbs.iter_mut() {
+            let sum = *limb as u128 + carry;
+            *limb = sum as u64;
+            carry = sum >> 64;
+        }
+    }
+
+    /// self = self * other mod p (partially reduced)
+    fn multiply(&mut self, other: &Num3072) {
+        // Schoolbook product into 96 limbs
+        let mut product = [0u64; 2 * LIMBS];
+        for i in 0..LIMBS {
+            let mut carry = 0u128;
+            let a = self.limbs[i] as u128;
+            for j in 0..LIMBS {
+                let t = a * other.limbs[j] as u128 + product[i + j] as u128 + carry;
+                product[i + j] = t as u64;
+                carry = t >> 64;
+            }
+            product[i + LIMBS] = carry as u64;
+        }
+
+        // 2^3072 = MAX_PRIME_DIFF (mod p): fold the high half into the low half
+        let mut limbs = [0u64; LIMBS];
+        let mut carry = 0u128;
+        for i in 0..LIMBS {
+            let t =
+                product[i] as u128 + product[i + LIMBS] as u128 * MAX_PRIME_DIFF as u128 + carry;
+            limbs[i] = t as u64;
+            carry = t >> 64;
+        }
+
+        // Fold the remaining carry until none is left (at most twice)
+        while carry != 0 {
+            let mut c = carry * MAX_PRIME_DIFF as u128;
+            carry = 0;
+            for limb in limbs.iter_mut() {
+                if c == 0 {
+                    break;
+                }
+                let t = *limb as u128 + c;
+                *limb = t as u64;
+                c = t >> 64;
+            }
+            carry += c;
+        }
+
+        self.limbs = limbs;
+    }
+
+    /// Modular inverse via Fermat's little theorem: a^(p-2) mod p
+    fn inverse(&self) -> Num3072 {
+        // p - 2 = 2^3072 - (MAX_PRIME_DIFF + 2): all ones except the low limb
+        let mut exponent = [u64::MAX; LIMBS];
+        exponent[0] = u64::MAX - (MAX_PRIME_DIFF + 1);
+
+        let mut result = Num3072::one();
+        for i in (0..LIMBS).rev() {
+            for bit in (0..64).rev() {
+                let square = result;
+                result.multiply(&square);
+                if (exponent[i] >> bit) & 1 == 1 {
+                    result.multiply(self);
+                }
+            }
+        }
+        result
+    }
+}
+
+/// Rolling set hash over byte-string elements
+///
+/// Insertion and removal commute, so any sequence of operations that yields the
+/// same multiset produces the same finalized hash.
+#[derive(Clone, PartialEq, Eq)]
+pub struct MuHash3072 {
+    numerator: Num3072,
+    denominator: Num3072,
+}
+
+impl std::fmt::Debug for MuHash3072 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MuHash3072").finish_non_exhaustive()
+    }
+}
+
+impl Default for MuHash3072 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MuHash3072 {
+    /// Hash of the empty set
+    pub fn new() -> Self {
+        Self {
+            numerator: Num3072::one(),
+            denominator: Num3072::one(),
+        }
+    }
+
+    /// Add an element to the set
+    pub fn insert(&mut self, element: &[u8]) {
+        self.numerator.multiply(&to_num3072(element));
+    }
+
+    /// Remove an element from the set
+    pub fn remove(&mut self, element: &[u8]) {
+        self.denominator.multiply(&to_num3072(element));
+    }
+
+    /// Combine with another set hash (set union)
+    pub fn combine(&mut self, other: &MuHash3072) {
+        self.numerator.multiply(&other.numerator);
+        self.denominator.multiply(&other.denominator);
+    }
+
+    /// Finalize to a 32-byte hash
+    ///
+    /// Computes numerator / denominator mod p, serializes it as 384 little-endian
+    /// bytes and returns SHA256 of that, as Core's `MuHash3072::Finalize`.
+    pub fn finalize(&self) -> [u8; 32] {
+        let mut value = self.numerator;
+        value.multiply(&self.denominator.inverse());
+        if value.is_overflow() {
+            value.full_reduce();
+        }
+        Sha256::digest(value.to_bytes()).into()
+    }
+}
+
+/// Map an element to a 3072-bit number: ChaCha20(key = SHA256(element)) keystream
+fn to_num3072(element: &[u8]) -> Num3072 {
+    let key: [u8; 32] = Sha256::digest(element).into();
+    let mut bytes = [0u8; BYTE_SIZE];
+    chacha20_keystream(&key, &mut bytes);
+    Num3072::from_bytes(&bytes)
+}
+
+/// ChaCha20 keystream with a zero nonce, starting at block counter 0
+fn chacha20_keystream(key: &[u8; 32], out: &mut [u8]) {
+    #[inline(always)]
+    fn quarter_round(s: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+        s[a] = s[a].wrapping_add(s[b]);
+        s[d] = (s[d] ^ s[a]).rotate_left(16);
+        s[c] = s[c].wrapping_add(s[d]);
+        s[b] = (s[b] ^ s[c]).rotate_left(12);
+        s[a] = s[a].wrapping_add(s[b]);
+        s[d] = (s[d] ^ s[a]).rotate_left(8);
+        s[c] = s[c].wrapping_add(s[d]);
+        s[b] = (s[b] ^ s[c]).rotate_left(7);
+    }
+
+    let mut input = [0u32; 16];
+    // "expand 32-byte k"
+    input[0] = 0x61707865;
+    input[1] = 0x3320646e;
+    input[2] = 0x79622d32;
+    input[3] = 0x6b206574;
+    for i in 0..8 {
+        input[4 + i] =
+            u32::from_le_bytes([key[4 * i], key[4 * i + 1], key[4 * i + 2], key[4 * i + 3]]);
+    }
+
+    for (counter, chunk) in out.chunks_mut(64).enumerate() {
+        input[12] = counter as u32;
+        let mut state = input;
+        for _ in 0..10 {
+            quarter_round(&mut state, 0, 4, 8, 12);
+            quarter_round(&mut state, 1, 5, 9, 13);
+            quarter_round(&mut state, 2, 6, 10, 14);
+            quarter_round(&mut state, 3, 7, 11, 15);
+            quarter_round(&mut state, 0, 5, 10, 15);
+            quarter_round(&mut state, 1, 6, 11, 12);
+            quarter_round(&mut state, 2, 7, 8, 13);
+            quarter_round(&mut state, 3, 4, 9, 14);
+        }
+        let mut block = [0u8; 64];
+        for i in 0..16 {
+            block[4 * i..4 * i + 4].copy_from_slice(&state[i].wrapping_add(input[i]).to_le_bytes());
+        }
+        chunk.copy_from_slice(&block[..chunk.len()]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_int(i: u8) -> MuHash3072 {
+        let mut element = [0u8; 32];
+        element[0] = i;
+        let mut muhash = MuHash3072::new();
+        muhash.insert(&element);
+        muhash
+    }
+
+    #[test]
+    fn test_chacha20_rfc8439_zero_key_vector() {
+        // RFC 8439 A.1 test vector #1: all-zero key and nonce, counter 0
+        let mut out = [0u8; 64];
+        chacha20_keystream(&[0u8; 32], &mut out);
+        assert_eq!(
+            hex::encode(out),
+            "76b8e0ada0f13d90405d6ae55386bd28bdd219b8a08ded1aa836efcc8b770dc7\
+             da41597c5157488d7724e03fb8d84a376a43b8f41518a11cc387b669b2ee6586"
+        );
+    }
+
+    #[test]
+    fn test_muhash_matches_core_vector() {
+        // Core crypto_tests.cpp muhash_tests: {0} * {1} / {2}
+        let mut acc = from_int(0);
+        acc.combine(&from_int(1));
+        let mut element = [0u8; 32];
+        element[0] = 2;
+        acc.remove(&element);
+        // Core compares against uint256S(), i.e. reversed (display) byte order
+        let mut out = acc.finalize();
+        out.reverse();
+        assert_eq!(
+            hex::encode(out),
+            "10d312b100cbd32ada024a6646e40d3482fcff103668d2625f10002a607d5863"
+        );
+    }
+
+    #[test]
+    fn test_muhash_order_independent() {
+        let mut a = MuHash3072::new();
+        a.insert(b"one");
+        a.insert(b"two");
+        a.insert(b"three");
+
+        let mut b = MuHash3072::new();
+        b.insert(b"three");
+        b.insert(b"one");
+        b.insert(b"two");
+
+        assert_eq!(a.finalize(), b.finalize());
+    }
+
+    #[test]
+    fn test_muhash_insert_remove_round_trip() {
+        let empty = MuHash3072::new().finalize();
+
+        let mut muhash = MuHash3072::new();
+        muhash.insert(b"utxo");
+        assert_ne!(muhash.finalize(), empty);
+        muhash.remove(b"utxo");
+        assert_eq!(muhash.finalize(), empty);
+    }
+
+    #[test]
+    fn test_num3072_inverse() {
+        let x = to_num3072(b"element");
+        let mut product = x;
+        product.multiply(&x.inverse());
+        if product.is_overflow() {
+            product.full_reduce();
+        }
+        assert!(product == Num3072::one());
+    }
+}
@@ -0,0 +1,114 @@
+//! Pluggable signature verification backend
+//!
+//! Script execution checks signature encoding rules (strict DER, LOW_S,
+//! hash types) itself and hands the curve operation to a `SignatureVerifier`.
+//! Script verification entry points such as
+//! `script::verify_script_with_witness` take the verifier to use, so an
+//! integrator can pass a batch verifier, a hardware backend or a test double.
+//! Entry points without a verifier parameter use `default_verifier()`,
+//! libsecp256k1.
+
+use secp256k1::{
+    ecdsa, schnorr, Message, PublicKey, Secp256k1, Verification, VerifyOnly, XOnlyPublicKey,
+};
+use std::sync::OnceLock;
+
+/// Backend for the curve operations of signature verification
+///
+/// Inputs are raw bytes as they appear in scripts, without the sighash type
+/// byte; malformed encodings verify as `false`.
+pub trait SignatureVerifier: Send + Sync {
+    /// Verify a DER-encoded ECDSA signature over `sighash`
+    fn verify_ecdsa(&self, pubkey: &[u8], signature: &[u8], sighash: &[u8; 32]) -> bool;
+
+    /// Verify a 64-byte BIP340 Schnorr signature over `sighash` with a 32-byte
+    /// x-only public key
+    fn verify_schnorr(&self, pubkey: &[u8], signature: &[u8], sighash: &[u8; 32]) -> bool;
+}
+
+/// The default backend: libsecp256k1
+#[derive(Debug, Clone)]
+pub struct Secp256k1Verifier {
+    secp: Secp256k1<VerifyOnly>,
+}
+
+impl Secp256k1Verifier {
+    pub fn new() -> Self {
+        Secp256k1Verifier {
+            secp: Secp256k1::verification_only(),
+        }
+    }
+}
+
+impl Default for Secp256k1Verifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SignatureVerifier for Secp256k1Verifier {
+    fn verify_ecdsa(&self, pubkey: &[u8], signature: &[u8], sighash: &[u8; 32]) -> bool {
+        match ecdsa::Signature::from_der(signature) {
+            Ok(signature) => secp256k1_verify_ecdsa(&self.secp, pubkey, &signature, sighash),
+            Err(_) => false,
+        }
+    }
+
+    fn verify_schnorr(&self, pubkey: &[u8], signature: &[u8], sighash: &[u8; 32]) -> bool {
+        let (Ok(pubkey), Ok(signature), Ok(message)) = (
+            XOnlyPublicKey::from_slice(pubkey),
+            schnorr::Signature::from_slice(signature),
+            Message::from_digest_slice(sighash),
+        ) else {
+            return false;
+        };
+        self.secp
+            .verify_schnorr(&signature, &message, &pubkey)
+            .is_ok()
+    }
+}
+
+/// ECDSA verification of an already-parsed signature with a caller's context
+fn secp256k1_verify_ecdsa<C: Verification>(
+    secp: &Secp256k1<C>,
+    pubkey: &[u8],
+    signature: &ecdsa::Signature,
+    sighash: &[u8; 32],
+) -> bool {
+    let Ok(pubkey) = PublicKey::from_slice(pubkey) else {
+        return false;
+    };
+    let Ok(message) = Message::from_digest_slice(sighash) else {
+        return false;
+    };
+    secp.verify_ecdsa(&message, signature, &pubkey).is_ok()
+}
+
+/// The shared libsecp256k1 verifier used when no other backend is given
+///
+/// Its verification-only context is created once and shared by all threads.
+pub fn default_verifier() -> &'static Secp256k1Verifier {
+    static DEFAULT_VERIFIER: OnceLock<Secp256k1Verifier> = OnceLock::new();
+    DEFAULT_VERIFIER.get_or_init(Secp256k1Verifier::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secp256k1::{Keypair, SecretKey};
+
+    #[test]
+    fn test_secp256k1_verifier_schnorr() {
+        let secp = Secp256k1::new();
+        let keypair = Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[0x22; 32]).unwrap());
+        let pubkey = keypair.x_only_public_key().0.serialize();
+        let sighash = [0x33; 32];
+        let message = Message::from_digest_slice(&sighash).unwrap();
+        let signature = secp.sign_schnorr_no_aux_rand(&message, &keypair);
+
+        let verifier = Secp256k1Verifier::new();
+        assert!(verifier.verify_schnorr(&pubkey, signature.as_ref(), &sighash));
+        assert!(!verifier.verify_schnorr(&pubkey, signature.as_ref(), &[0x34; 32]));
+        assert!(!verifier.verify_schnorr(&pubkey[1..], signature.as_ref(), &sighash));
+    }
+}
@@ -0,0 +1,408 @@
+//! Header store for out-of-order header download
+//!
+//! Peers may deliver headers in any order. `HeaderStore` indexes every header
+//! whose ancestry reaches the genesis header, buffers the rest as orphans keyed
+//! by their missing parent, and connects them as soon as that parent arrives.
+//! Each connected header carries its height and cumulative chain work, and the
+//! store tracks the header with the most work as the best tip.
+//!
+//! Like Core's orphan transaction pool, the orphan buffer is capped: once it
+//! holds more than `max_orphans` headers the oldest arrivals are evicted, so a
+//! peer sending unconnectable headers cannot grow it without bound.
+//!
+//! Only linkage and work are tracked here; proof of work and difficulty are
+//! checked separately (`check_proof_of_work`, `check_block_difficulty`).
+
+use crate::error::Result;
+use crate::pow::get_block_proof;
+use crate::serialization::block_hash;
+use crate::types::*;
+use std::collections::{BTreeMap, HashMap};
+
+/// Default cap on buffered orphan headers: one full `headers` message
+pub const DEFAULT_MAX_ORPHAN_HEADERS: usize = 2000;
+
+/// A header connected to the genesis header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderEntry {
+    pub header: BlockHeader,
+    pub height: Natural,
+    /// Sum of the block proofs from genesis up to and including this header
+    pub chain_work: u128,
+}
+
+/// Outcome of `HeaderStore::add_header`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeaderStatus {
+    /// The header connected, together with `descendants` buffered descendants
+    ///
+    /// `rejected` lists buffered descendants that could not be connected
+    /// (their `bits` do not expand), together with their own buffered
+    /// descendants; all of them are dropped from the buffer.
+    Connected {
+        descendants: usize,
+        rejected: Vec<Hash>,
+    },
+    /// The parent is unknown; the header is buffered until it arrives
+    Orphan,
+    /// The header is already known (connected or buffered)
+    Duplicate,
+}
+
+/// Header index that accepts headers in any order
+#[derive(Debug, Clone)]
+pub struct HeaderStore {
+    entries: HashMap<Hash, HeaderEntry>,
+    /// Buffered headers by hash, with their arrival sequence number
+    orphans: HashMap<Hash, (u64, BlockHeader)>,
+    /// Hashes of buffered headers, keyed by the parent hash they are waiting for
+    orphan_children: HashMap<Hash, Vec<Hash>>,
+    /// Hashes of buffered headers by arrival sequence number, oldest first
+    orphan_arrivals: BTreeMap<u64, Hash>,
+    next_orphan_seq: u64,
+    max_orphans: usize,
+    best_tip: Hash,
+}
+
+impl HeaderStore {
+    /// Create a store rooted at `genesis` (height 0)
+    pub fn new(genesis: BlockHeader) -> Result<Self> {
+        let hash = block_hash(&genesis);
+        let chain_work = get_block_proof(genesis.bits)?;
+        let mut entries = HashMap::new();
+        entries.insert(
+            hash,
+            HeaderEntry {
+                header: genesis,
+                height: 0,
+                chain_work,
+            },
+        );
+        Ok(HeaderStore {
+            entries,
+            orphans: HashMap::new(),
+            orphan_children: HashMap::new(),
+            orphan_arrivals: BTreeMap::new(),
+            next_orphan_seq: 0,
+            max_orphans: DEFAULT_MAX_ORPHAN_HEADERS,
+            best_tip: hash,
+        })
+    }
+
+    /// Cap the orphan buffer at `max_orphans` headers, evicting the oldest
+    /// arrivals if it already holds more
+    pub fn with_max_orphans(mut self, max_orphans: usize) -> Self {
+        self.max_orphans = max_orphans;
+        self.evict_orphans();
+        self
+    }
+
+    /// Add a header received from a peer
+    ///
+    /// Connects the header if its parent is known, then connects any buffered
+    /// orphans that were waiting on it (recursively). Otherwise buffers it,
+    /// evicting the oldest buffered headers beyond `max_orphans`.
+    ///
+    /// Fails, leaving the store unchanged, if the header's own `bits` cannot
+    /// be expanded. A buffered descendant that fails the same way is dropped
+    /// with its buffered subtree and listed in `HeaderStatus::Connected`.
+    pub fn add_header(&mut self, header: BlockHeader) -> Result<HeaderStatus> {
+        let hash = block_hash(&header);
+        if self.entries.contains_key(&hash) || self.orphans.contains_key(&hash) {
+            return Ok(HeaderStatus::Duplicate);
+        }
+
+        if !self.entries.contains_key(&header.prev_block_hash) {
+            self.add_orphan(hash, header);
+            self.evict_orphans();
+            return Ok(HeaderStatus::Orphan);
+        }
+
+        let mut pending = vec![self.connect(header)?];
+        let mut descendants = 0usize;
+        let mut rejected = Vec::new();
+        while let Some(parent) = pending.pop() {
+            for child in self.take_orphan_children(&parent) {
+                let child_hash = block_hash(&child);
+                match self.connect(child) {
+                    Ok(child_hash) => {
+                        descendants += 1;
+                        pending.push(child_hash);
+                    }
+                    Err(_) => {
+                        rejected.push(child_hash);
+                        self.drop_orphan_subtree(child_hash, &mut rejected);
+                    }
+                }
+            }
+        }
+
+        Ok(HeaderStatus::Connected {
+            descendants,
+            rejected,
+        })
+    }
+
+    /// Look up a connected header by hash
+    pub fn get(&self, hash: &Hash) -> Option<&HeaderEntry> {
+        self.entries.get(hash)
+    }
+
+    /// Whether a header is connected
+    pub fn contains(&self, hash: &Hash) -> bool {
+        self.entries.contains_key(hash)
+    }
+
+    /// Hash of the connected header with the most cumulative work
+    ///
+    /// On equal work the header connected first stays the tip.
+    pub fn best_tip_hash(&self) -> Hash {
+        self.best_tip
+    }
+
+    /// The connected header with the most cumulative work
+    pub fn best_tip(&self) -> &HeaderEntry {
+        &self.entries[&self.best_tip]
+    }
+
+    /// Number of connected headers, including genesis
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Always false: the genesis header is connected from the start
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Number of buffered headers whose ancestry is not yet known
+    pub fn orphan_count(&self) -> usize {
+        self.orphans.len()
+    }
+
+    /// Whether a header is buffered as an orphan
+    pub fn is_orphan(&self, hash: &Hash) -> bool {
+        self.orphans.contains_key(hash)
+    }
+
+    fn add_orphan(&mut self, hash: Hash, header: BlockHeader) {
+        let seq = self.next_orphan_seq;
+        self.next_orphan_seq += 1;
+        self.orphan_children
+            .entry(header.prev_block_hash)
+            .or_default()
+            .push(hash);
+        self.orphan_arrivals.insert(seq, hash);
+        self.orphans.insert(hash, (seq, header));
+    }
+
+    /// Remove one buffered header, leaving its own children buffered
+    fn remove_orphan(&mut self, hash: &Hash) -> Option<BlockHeader> {
+        let (seq, header) = self.orphans.remove(hash)?;
+        self.orphan_arrivals.remove(&seq);
+        if let Some(siblings) = self.orphan_children.get_mut(&header.prev_block_hash) {
+            siblings.retain(|sibling| sibling != hash);
+            if siblings.is_empty() {
+                self.orphan_children.remove(&header.prev_block_hash);
+            }
+        }
+        Some(header)
+    }
+
+    /// Remove and return the buffered headers waiting on `parent`
+    fn take_orphan_children(&mut self, parent: &Hash) -> Vec<BlockHeader> {
+        let children = self.orphan_children.remove(parent).unwrap_or_default();
+        children
+            .iter()
+            .filter_map(|hash| {
+                let (seq, header) = self.orphans.remove(hash)?;
+                self.orphan_arrivals.remove(&seq);
+                Some(header)
+            })
+            .collect()
+    }
+
+    /// Drop every buffered descendant of `hash`, recording their hashes
+    fn drop_orphan_subtree(&mut self, hash: Hash, dropped: &mut Vec<Hash>) {
+        let mut parents = vec![hash];
+        while let Some(parent) = parents.pop() {
+            for child in self.take_orphan_children(&parent) {
+                let child_hash = block_hash(&child);
+                dropped.push(child_hash);
+                parents.push(child_hash);
+            }
+        }
+    }
+
+    /// Evict the oldest buffered headers until at most `max_orphans` remain
+    fn evict_orphans(&mut self) {
+        while self.orphans.len() > self.max_orphans {
+            let Some((_, hash)) = self.orphan_arrivals.pop_first() else {
+                break;
+            };
+            self.remove_orphan(&hash);
+        }
+    }
+
+    /// Index a header whose parent is connected; returns its hash
+    fn connect(&mut self, header: BlockHeader) -> Result<Hash> {
+        let hash = block_hash(&header);
+        let parent = &self.entries[&header.prev_block_hash];
+        let height = parent.height + 1;
+        let chain_work = parent
+            .chain_work
+            .saturating_add(get_block_proof(header.bits)?);
+
+        if chain_work > self.best_tip().chain_work {
+            self.best_tip = hash;
+        }
+        self.entries.insert(
+            hash,
+            HeaderEntry {
+                header,
+                height,
+                chain_work,
+            },
+        );
+        Ok(hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(prev_block_hash: Hash, nonce: Natural) -> BlockHeader {
+        BlockHeader {
+            version: 1,
+            prev_block_hash,
+            merkle_root: [0; 32],
+            timestamp: 1231006505 + nonce * 600,
+            bits: 0x1d00ffff,
+            nonce,
+        }
+    }
+
+    #[test]
+    fn test_headers_in_reverse_order_connect() {
+        let genesis = header([0; 32], 0);
+        let h1 = header(block_hash(&genesis), 1);
+        let h2 = header(block_hash(&h1), 2);
+        let h3 = header(block_hash(&h2), 3);
+        let proof = get_block_proof(0x1d00ffff).unwrap();
+
+        let mut store = HeaderStore::new(genesis).unwrap();
+        assert_eq!(store.add_header(h3.clone()).unwrap(), HeaderStatus::Orphan);
+        assert_eq!(store.add_header(h2.clone()).unwrap(), HeaderStatus::Orphan);
+        assert_eq!(
+            store.add_header(h2.clone()).unwrap(),
+            HeaderStatus::Duplicate
+        );
+        assert_eq!(store.orphan_count(), 2);
+        assert_eq!(store.best_tip().height, 0);
+
+        assert_eq!(
+            store.add_header(h1.clone()).unwrap(),
+            HeaderStatus::Connected {
+                descendants: 2,
+                rejected: vec![]
+            }
+        );
+        assert_eq!(store.orphan_count(), 0);
+        assert_eq!(store.len(), 4);
+
+        for (height, h) in [(1, &h1), (2, &h2), (3, &h3)] {
+            let entry = store.get(&block_hash(h)).unwrap();
+            assert_eq!(entry.height, height);
+            assert_eq!(entry.chain_work, proof * (height as u128 + 1));
+        }
+        assert_eq!(store.best_tip_hash(), block_hash(&h3));
+        assert_eq!(store.best_tip().chain_work, proof * 4);
+    }
+
+    #[test]
+    fn test_orphan_buffer_evicts_oldest() {
+        let genesis = header([0; 32], 0);
+        let h1 = header(block_hash(&genesis), 1);
+        let orphans: Vec<BlockHeader> = (2..5)
+            .map(|nonce| header([nonce as u8; 32], nonce))
+            .collect();
+
+        let mut store = HeaderStore::new(genesis).unwrap().with_max_orphans(2);
+        let child = header(block_hash(&h1), 5);
+        assert_eq!(
+            store.add_header(child.clone()).unwrap(),
+            HeaderStatus::Orphan
+        );
+        for orphan in &orphans {
+            assert_eq!(
+                store.add_header(orphan.clone()).unwrap(),
+                HeaderStatus::Orphan
+            );
+        }
+
+        // Only the two most recent arrivals are kept
+        assert_eq!(store.orphan_count(), 2);
+        assert!(!store.is_orphan(&block_hash(&child)));
+        assert!(!store.is_orphan(&block_hash(&orphans[0])));
+        assert!(store.is_orphan(&block_hash(&orphans[1])));
+        assert!(store.is_orphan(&block_hash(&orphans[2])));
+
+        // The evicted child no longer connects with its parent
+        assert_eq!(
+            store.add_header(h1).unwrap(),
+            HeaderStatus::Connected {
+                descendants: 0,
+                rejected: vec![]
+            }
+        );
+        assert!(!store.contains(&block_hash(&child)));
+    }
+
+    #[test]
+    fn test_unconnectable_descendants_are_reported() {
+        let genesis = header([0; 32], 0);
+        let h1 = header(block_hash(&genesis), 1);
+        // Negative target: the bits cannot be expanded
+        let bad = BlockHeader {
+            bits: 0x1d800001,
+            ..header(block_hash(&h1), 2)
+        };
+        let bad_child = header(block_hash(&bad), 3);
+        let good = header(block_hash(&h1), 4);
+
+        let mut store = HeaderStore::new(genesis).unwrap();
+        for orphan in [&bad, &bad_child, &good] {
+            assert_eq!(
+                store.add_header(orphan.clone()).unwrap(),
+                HeaderStatus::Orphan
+            );
+        }
+
+        match store.add_header(h1).unwrap() {
+            HeaderStatus::Connected {
+                descendants,
+                mut rejected,
+            } => {
+                // The valid sibling still connects
+                assert_eq!(descendants, 1);
+                rejected.sort_unstable();
+                let mut expected = vec![block_hash(&bad), block_hash(&bad_child)];
+                expected.sort_unstable();
+                assert_eq!(rejected, expected);
+            }
+            status => panic!("expected Connected, got {status:?}"),
+        }
+        assert!(store.contains(&block_hash(&good)));
+        assert_eq!(store.orphan_count(), 0);
+
+        // A header whose own bits cannot be expanded is an error
+        let mut store = HeaderStore::new(header([0; 32], 0)).unwrap();
+        let bad_root = BlockHeader {
+            bits: 0x1d800001,
+            ..header(block_hash(&header([0; 32], 0)), 5)
+        };
+        assert!(store.add_header(bad_root).is_err());
+        assert_eq!(store.len(), 1);
+    }
+}
@@ -333,11 +333,12 @@ mod kani_proofs {
                 "Script path validation must check element count and control block size"
             );
         } else {
-            // Key path: must have exactly 1 element, 64 bytes
-            let key_path_valid = witness.len() == 1 && witness[0].len() == 64;
+            // Key path: must have exactly 1 element, a well-formed Taproot signature
+            let key_path_valid = witness.len() == 1
+                && crate::taproot::parse_taproot_signature(&witness[0]).is_some();
             assert_eq!(
                 is_valid, key_path_valid,
-                "Key path validation must check single 64-byte signature"
+                "Key path validation must check single 64- or 65-byte signature"
             );
         }
     }
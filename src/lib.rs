@@ -45,6 +45,8 @@
 pub mod config;
 pub mod constants;
 pub mod script;
+pub mod script_num;
+pub mod script_type;
 pub mod transaction;
 pub mod transaction_hash;
 pub mod types;
@@ -62,10 +64,12 @@ pub use transaction_hash::clear_sighash_templates;
 pub mod bip113;
 pub mod bip_validation;
 pub mod block;
+pub mod chain_params;
 pub mod crypto;
 pub mod economic;
 pub mod locktime;
 pub mod mempool;
+pub mod merkle_block;
 pub mod pow;
 #[cfg(feature = "k256")]
 pub mod script_k256;
@@ -74,16 +78,20 @@ pub mod sigop;
 pub mod witness;
 
 pub mod error;
+pub mod header_store;
 #[cfg(kani)]
 pub mod integration_proofs;
 #[cfg(kani)]
 pub mod kani_helpers;
 pub mod mining;
+pub mod multisig;
 pub mod network;
 pub mod reorganization;
 pub mod segwit;
 pub mod serialization;
 pub mod taproot;
+pub mod validation_cost;
+pub mod validation_report;
 
 #[cfg(feature = "utxo-commitments")]
 pub mod utxo_commitments;
@@ -111,6 +119,8 @@ pub use witness::{
     validate_witness_program_length, weight_to_vsize, Witness, WitnessVersion,
 };
 
+pub use script_type::{classify_script, ScriptType};
+
 // Re-export configuration types
 pub use config::{
     AdvancedConfig, BlockValidationConfig, ConsensusConfig, DebugConfig, FeatureFlagsConfig,
@@ -288,8 +298,11 @@ impl ConsensusProof {
     ) -> Result<(ValidationResult, UtxoSet)> {
         // Create empty witnesses for backward compatibility
         // Callers should use validate_block_with_context for full witness support
-        let witnesses: Vec<segwit::Witness> =
-            block.transactions.iter().map(|_| Vec::new()).collect();
+        let witnesses: Vec<Vec<segwit::Witness>> = block
+            .transactions
+            .iter()
+            .map(|tx| vec![Vec::new(); tx.inputs.len()])
+            .collect();
         let network = types::Network::from_env();
         let (result, new_utxo_set, _undo_log) =
             block::connect_block(block, &witnesses, utxo_set, height, None, network)?;
@@ -300,7 +313,7 @@ impl ConsensusProof {
     pub fn validate_block_with_context(
         &self,
         block: &Block,
-        witnesses: &[segwit::Witness],
+        witnesses: &[Vec<segwit::Witness>],
         utxo_set: UtxoSet,
         height: Natural,
         recent_headers: Option<&[BlockHeader]>,
@@ -311,6 +324,34 @@ impl ConsensusProof {
         Ok((result, new_utxo_set))
     }
 
+    /// Validate a transaction and return a serializable report
+    ///
+    /// See [`validation_report::validate_transaction_report`].
+    pub fn validate_transaction_report(
+        &self,
+        tx: &Transaction,
+        witness: Option<&[segwit::Witness]>,
+        utxo_set: &UtxoSet,
+        height: Natural,
+    ) -> Result<validation_report::TxValidationReport> {
+        let network = types::Network::from_env();
+        validation_report::validate_transaction_report(tx, witness, utxo_set, height, network)
+    }
+
+    /// Validate a block and return a serializable report
+    ///
+    /// See [`validation_report::validate_block_report`].
+    pub fn validate_block_report(
+        &self,
+        block: &Block,
+        witnesses: &[Vec<segwit::Witness>],
+        utxo_set: UtxoSet,
+        height: Natural,
+    ) -> Result<validation_report::BlockValidationReport> {
+        let network = types::Network::from_env();
+        validation_report::validate_block_report(block, witnesses, utxo_set, height, network)
+    }
+
     /// Verify script execution
     ///
     /// # Examples
@@ -337,6 +378,20 @@ impl ConsensusProof {
         script::verify_script(script_sig, script_pubkey, witness, flags)
     }
 
+    /// Verify every input script, returning the failing input and its error
+    ///
+    /// See [`script::validate_transaction_verbose`].
+    pub fn validate_transaction_verbose(
+        &self,
+        tx: &Transaction,
+        prevouts: &[PrevOut],
+        witnesses: &[witness::Witness],
+        flags: u32,
+    ) -> std::result::Result<(), (usize, ConsensusError)> {
+        let network = types::Network::from_env();
+        script::validate_transaction_verbose(tx, prevouts, witnesses, flags, network)
+    }
+
     /// Check proof of work
     ///
     /// # Examples
@@ -843,7 +898,7 @@ impl ConsensusProof {
     pub fn calculate_transaction_weight(
         &self,
         tx: &Transaction,
-        witness: Option<&segwit::Witness>,
+        witness: Option<&[segwit::Witness]>,
     ) -> Result<Natural> {
         segwit::calculate_transaction_weight(tx, witness)
     }
@@ -891,7 +946,7 @@ impl ConsensusProof {
     pub fn validate_segwit_block(
         &self,
         block: &Block,
-        witnesses: &[segwit::Witness],
+        witnesses: &[Vec<segwit::Witness>],
         max_block_weight: Natural,
     ) -> Result<bool> {
         segwit::validate_segwit_block(block, witnesses, max_block_weight)
@@ -81,6 +81,20 @@ pub fn decode_locktime_value(bytes: &ByteString) -> Option<u32> {
     Some(value)
 }
 
+/// Check if a locktime operand on the stack is a negative script number
+///
+/// Stack values are script numbers: the high bit of the last byte is the sign.
+/// BIP65 fails CLTV on a negative operand rather than reading the sign bit as
+/// part of the value. Negative zero (sign bit alone) is zero, not negative.
+pub fn is_negative_locktime(bytes: &ByteString) -> bool {
+    match bytes.split_last() {
+        Some((&last, rest)) if last & 0x80 != 0 => {
+            last & 0x7f != 0 || rest.iter().any(|&byte| byte != 0)
+        }
+        _ => false,
+    }
+}
+
 /// Encode locktime value to minimal-encoding byte string
 ///
 /// Encodes a u32 locktime value to minimal little-endian encoding for script stack.
@@ -110,11 +124,16 @@ pub fn encode_locktime_value(value: u32) -> ByteString {
         bytes.push(0);
     }
 
-    // Runtime assertion: Encoded length must be between 1 and 4 bytes (u32 max)
+    // Keep the value positive: a set high bit would read as the sign
+    if bytes.last().is_some_and(|&byte| byte & 0x80 != 0) {
+        bytes.push(0);
+    }
+
+    // Runtime assertion: Encoded length must be between 1 and 5 bytes (u32 max plus sign byte)
     let len = bytes.len();
     debug_assert!(
-        !bytes.is_empty() && len <= 4,
-        "Encoded locktime length ({len}) must be between 1 and 4 bytes"
+        !bytes.is_empty() && len <= 5,
+        "Encoded locktime length ({len}) must be between 1 and 5 bytes"
     );
 
     bytes
@@ -204,6 +223,22 @@ mod tests {
             encode_locktime_value(0x12345600),
             vec![0x00, 0x56, 0x34, 0x12]
         );
+        // A set high bit gets a sign byte so the value stays positive
+        assert_eq!(encode_locktime_value(0x80), vec![0x80, 0x00]);
+        assert_eq!(
+            encode_locktime_value(0xffffffff),
+            vec![0xff, 0xff, 0xff, 0xff, 0x00]
+        );
+    }
+
+    #[test]
+    fn test_is_negative_locktime() {
+        assert!(is_negative_locktime(&vec![0x81])); // -1
+        assert!(is_negative_locktime(&vec![0x00, 0x65, 0xcd, 0x9d])); // -500000000
+        assert!(!is_negative_locktime(&vec![0x80])); // negative zero
+        assert!(!is_negative_locktime(&vec![0x80, 0x00])); // 128
+        assert!(!is_negative_locktime(&vec![]));
+        assert!(!is_negative_locktime(&encode_locktime_value(0xffffffff)));
     }
 
     #[test]
@@ -1,24 +1,32 @@
 //! Mempool validation functions from Orange Paper Section 9
 
 use crate::constants::*;
+use crate::crypto::signature::default_verifier;
 use crate::economic::calculate_fee;
 use crate::error::{ConsensusError, Result};
-use crate::script::verify_script;
+use crate::script::{verify_script, verify_script_with_witness};
 use crate::segwit::{is_segwit_transaction, Witness};
-use crate::transaction::{check_transaction, check_tx_inputs};
+pub use crate::transaction::is_final_tx;
+use crate::transaction::{check_transaction_with_witness, check_tx_inputs};
+use crate::transaction_hash::SighashCache;
 use crate::types::*;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 /// AcceptToMemoryPool: 𝒯𝒳 × 𝒰𝒮 → {accepted, rejected}
 ///
 /// For transaction tx and UTXO set us:
 /// 1. Check if tx is already in mempool
-/// 2. Validate transaction structure
-/// 3. Check inputs against UTXO set
-/// 4. Verify scripts
-/// 5. Check mempool-specific rules (size, fee rate, etc.)
-/// 6. Check for conflicts with existing mempool transactions
-/// 7. Return acceptance result
+/// 2. Validate transaction structure, and cap its weight at
+///    `MAX_STANDARD_TX_WEIGHT`
+/// 3. Check inputs against UTXO set and the outputs of transactions already
+///    in the mempool (so chained unconfirmed spends are accepted); a tx with
+///    an input found in neither is an orphan and is rejected
+/// 4. Cap the sigop cost, including sigops in the redeem scripts of spent
+///    P2SH outputs, at `MempoolConfig::max_standard_tx_sigops_cost`
+/// 5. Verify scripts
+/// 6. Check mempool-specific rules (size, fee rate, etc.)
+/// 7. Check for conflicts with existing mempool transactions
+/// 8. Return acceptance result
 ///
 /// # Arguments
 ///
@@ -26,13 +34,38 @@ use std::collections::HashSet;
 /// * `witnesses` - Optional witness data for each input (Vec<Witness> where Witness = Vec<ByteString>)
 /// * `utxo_set` - Current UTXO set
 /// * `mempool` - Current mempool state
-/// * `height` - Current block height
+/// * `height` - Height of the next block (chain tip + 1), at which finality
+///   and coinbase maturity are checked
+///
+/// Time-based locktimes are checked against the current system time; use
+/// `accept_to_memory_pool_with_mtp` to check them against the chain tip's
+/// median time-past, as Core does.
 pub fn accept_to_memory_pool(
     tx: &Transaction,
     witnesses: Option<&[Witness]>,
     utxo_set: &UtxoSet,
     mempool: &Mempool,
     height: Natural,
+) -> Result<MempoolResult> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    accept_to_memory_pool_with_mtp(tx, witnesses, utxo_set, mempool, height, now)
+}
+
+/// `accept_to_memory_pool` with finality checked against the chain tip
+///
+/// A transaction that could not be included in the next block, at `height`
+/// with time `median_time_past` (the tip's median time-past, BIP113), is
+/// rejected as `bad-txns-nonfinal`.
+pub fn accept_to_memory_pool_with_mtp(
+    tx: &Transaction,
+    witnesses: Option<&[Witness]>,
+    utxo_set: &UtxoSet,
+    mempool: &Mempool,
+    height: Natural,
+    median_time_past: Natural,
 ) -> Result<MempoolResult> {
     // 1. Check if transaction is already in mempool
     let tx_id = crate::block::calculate_tx_id(tx);
@@ -42,26 +75,55 @@ pub fn accept_to_memory_pool(
         ));
     }
 
-    // 2. Validate transaction structure
-    if !matches!(check_transaction(tx)?, ValidationResult::Valid) {
+    // 2. Validate transaction structure (weight includes any witness data)
+    if !matches!(
+        check_transaction_with_witness(tx, witnesses.unwrap_or(&[]))?,
+        ValidationResult::Valid
+    ) {
         return Ok(MempoolResult::Rejected(
             "Invalid transaction structure".to_string(),
         ));
     }
 
-    // 2.5. Check transaction finality
-    // Note: block_time would typically come from network/chain state
-    // For mempool acceptance, we use current system time as approximation
-    // In production, this should use the chain tip's median time-past
-    let block_time = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .map(|d| d.as_secs())
-        .unwrap_or(0);
+    // 2.1. Check weight (policy), counting any witness data
+    let weight =
+        crate::transaction::calculate_transaction_weight_with_witness(tx, witnesses.unwrap_or(&[]));
+    if weight > MAX_STANDARD_TX_WEIGHT {
+        return Ok(MempoolResult::Rejected(format!(
+            "{}: weight {weight} exceeds {MAX_STANDARD_TX_WEIGHT}",
+            RejectReason::NonStandard
+        )));
+    }
+
+    // 2.5. Check transaction finality for the next block
+    if !is_final_tx(tx, height, median_time_past) {
+        return Ok(MempoolResult::Rejected(format!(
+            "{}: locktime {} not reached",
+            RejectReason::NonFinal,
+            tx.lock_time
+        )));
+    }
+
+    // 2.6. Resolve inputs against the chain and the pool's own outputs; all
+    // later checks see this combined view
+    let view = match resolve_mempool_inputs(tx, utxo_set, mempool, height) {
+        Ok(view) => view,
+        Err(i) => {
+            return Ok(MempoolResult::Rejected(format!(
+                "{}: input {i} is an orphan",
+                RejectReason::InputsMissingOrSpent
+            )));
+        }
+    };
+    let utxo_set = &view;
 
-    if !is_final_tx(tx, height, block_time) {
-        return Ok(MempoolResult::Rejected(
-            "Transaction not final (locktime not satisfied)".to_string(),
-        ));
+    // 2.7. Reject spends of immature coinbase outputs explicitly, so relay gets
+    // Core's specific reason rather than a generic input failure
+    if let Some((i, depth)) = find_immature_coinbase_spend(tx, utxo_set, height) {
+        return Ok(MempoolResult::Rejected(format!(
+            "{}: input {i} spends coinbase at depth {depth}",
+            RejectReason::PrematureCoinbaseSpend
+        )));
     }
 
     // 3. Check inputs against UTXO set
@@ -72,6 +134,20 @@ pub fn accept_to_memory_pool(
         ));
     }
 
+    // 3.5. Check sigop cost (policy); unlike `is_standard_tx`, the spent
+    // outputs are known here, so P2SH redeem scripts are counted
+    let sigop_cost =
+        crate::sigop::get_transaction_sigop_cost(tx, utxo_set, None, SCRIPT_VERIFY_P2SH)?;
+    let max_sigops_cost = crate::config::get_consensus_config()
+        .mempool
+        .max_standard_tx_sigops_cost;
+    if sigop_cost > max_sigops_cost {
+        return Ok(MempoolResult::Rejected(format!(
+            "{}: sigop cost {sigop_cost} exceeds {max_sigops_cost}",
+            RejectReason::TooManySigops
+        )));
+    }
+
     // 4. Verify scripts for non-coinbase transactions
     if !is_coinbase(tx) {
         // Calculate script verification flags
@@ -160,7 +236,80 @@ pub fn accept_to_memory_pool(
         ));
     }
 
-    Ok(MempoolResult::Accepted)
+    Ok(MempoolResult::Accepted {
+        replaceable: is_bip125_replaceable(tx, mempool),
+    })
+}
+
+/// Accept a transaction to the mempool and store it
+///
+/// Runs `accept_to_memory_pool` and, if the transaction is accepted, adds it
+/// to `mempool` with its fee, weight and vsize recorded in the entry.
+pub fn add_to_memory_pool(
+    tx: &Transaction,
+    witnesses: Vec<Witness>,
+    utxo_set: &UtxoSet,
+    mempool: &mut Mempool,
+    height: Natural,
+) -> Result<MempoolResult> {
+    let result = accept_to_memory_pool(tx, Some(&witnesses), utxo_set, mempool, height)?;
+    if matches!(result, MempoolResult::Accepted { .. }) {
+        // Acceptance resolved every input, so the view is complete
+        let view = resolve_mempool_inputs(tx, utxo_set, mempool, height).map_err(|i| {
+            ConsensusError::TransactionValidation(format!("Input {i} not found").into())
+        })?;
+        let fee = calculate_fee(tx, &view)?;
+        mempool.add_transaction(tx.clone(), witnesses, fee);
+    }
+    Ok(result)
+}
+
+/// Resolve the outputs spent by `tx` for mempool acceptance
+///
+/// Each prevout is looked up in the chain UTXO set first, then among the
+/// outputs of transactions stored in `mempool`. Pool outputs are treated as
+/// non-coinbase UTXOs at `height`. Returns a UTXO set holding just the spent
+/// outputs, or the index of the first input found in neither (an orphan).
+fn resolve_mempool_inputs(
+    tx: &Transaction,
+    utxo_set: &UtxoSet,
+    mempool: &Mempool,
+    height: Natural,
+) -> std::result::Result<UtxoSet, usize> {
+    let mut view = UtxoSet::with_capacity(tx.inputs.len());
+    for (i, input) in tx.inputs.iter().enumerate() {
+        let utxo = match utxo_set.get(&input.prevout) {
+            Some(utxo) => utxo.clone(),
+            None => match mempool.get_output(&input.prevout) {
+                Some(output) => UTXO {
+                    value: output.value,
+                    script_pubkey: output.script_pubkey.clone(),
+                    height,
+                    is_coinbase: false,
+                },
+                None => return Err(i),
+            },
+        };
+        view.insert(input.prevout.clone(), utxo);
+    }
+    Ok(view)
+}
+
+/// Find the first input spending a coinbase output less than COINBASE_MATURITY deep
+///
+/// Uses the same spend-height convention as `check_tx_inputs`: a coinbase created
+/// at height h is spendable at `height >= h + COINBASE_MATURITY`. Returns the input
+/// index and the coinbase's depth at `height`.
+fn find_immature_coinbase_spend(
+    tx: &Transaction,
+    utxo_set: &UtxoSet,
+    height: Natural,
+) -> Option<(usize, Natural)> {
+    tx.inputs.iter().enumerate().find_map(|(i, input)| {
+        let utxo = utxo_set.get(&input.prevout)?;
+        let depth = height.saturating_sub(utxo.height);
+        (utxo.is_coinbase && depth < COINBASE_MATURITY).then_some((i, depth))
+    })
 }
 
 /// Calculate script verification flags based on transaction type
@@ -168,7 +317,7 @@ pub fn accept_to_memory_pool(
 /// Returns appropriate flags for script validation:
 /// - Base flags: Standard validation flags (P2SH, STRICTENC, DERSIG, LOW_S, etc.)
 /// - SegWit flag (SCRIPT_VERIFY_WITNESS = 0x800): Enabled if transaction uses SegWit
-/// - Taproot flag (SCRIPT_VERIFY_TAPROOT = 0x2000): Enabled if transaction uses Taproot
+/// - Taproot flag (SCRIPT_VERIFY_TAPROOT = 0x20000): Enabled if transaction uses Taproot
 fn calculate_script_flags(tx: &Transaction, witnesses: Option<&[Witness]>) -> u32 {
     // Base flags (standard validation flags)
     // In Bitcoin Core, these are typically always enabled:
@@ -197,7 +346,7 @@ fn calculate_script_flags(tx: &Transaction, witnesses: Option<&[Witness]>) -> u3
     for output in &tx.outputs {
         let script = &output.script_pubkey;
         if script.len() == 34 && script[0] == 0x51 && script[1] == 0x20 {
-            flags |= 0x2000; // SCRIPT_VERIFY_TAPROOT
+            flags |= 0x20000; // SCRIPT_VERIFY_TAPROOT
             break;
         }
     }
@@ -211,17 +360,32 @@ fn calculate_script_flags(tx: &Transaction, witnesses: Option<&[Witness]>) -> u3
 /// 1. Transaction size limits
 /// 2. Script size limits
 /// 3. Standard script types
-/// 4. Fee rate requirements
+/// 4. Per-transaction sigop cost limit
+/// 5. Transaction version limit
+/// 6. No value locked in an all-OP_RETURN transaction
+/// 7. Fee rate requirements
+///
+/// Policy thresholds come from the global consensus config; see
+/// `is_standard_tx_with_config` to supply them explicitly.
 pub fn is_standard_tx(tx: &Transaction) -> Result<bool> {
+    let config = crate::config::get_consensus_config();
+    is_standard_tx_with_config(tx, &config.mempool)
+}
+
+/// IsStandardTx with explicit mempool policy thresholds
+pub fn is_standard_tx_with_config(
+    tx: &Transaction,
+    config: &crate::config::MempoolConfig,
+) -> Result<bool> {
     // 1. Check transaction size
     let tx_size = calculate_transaction_size(tx);
     if tx_size > MAX_TX_SIZE {
         return Ok(false);
     }
 
-    // 2. Check script sizes
+    // 2. Check script sizes (scriptSigs are held to the tighter standard limit)
     for input in &tx.inputs {
-        if input.script_sig.len() > MAX_SCRIPT_SIZE {
+        if input.script_sig.len() > MAX_STANDARD_SCRIPTSIG_SIZE {
             return Ok(false);
         }
     }
@@ -234,11 +398,27 @@ pub fn is_standard_tx(tx: &Transaction) -> Result<bool> {
 
     // 3. Check for standard script types (simplified)
     for output in &tx.outputs {
-        if !is_standard_script(&output.script_pubkey)? {
+        if !is_standard_script(&output.script_pubkey, config.max_datacarrier_bytes)? {
             return Ok(false);
         }
     }
 
+    // 4. Check sigop cost (legacy sigops scaled by the witness factor)
+    let sigop_cost = u64::from(crate::sigop::get_legacy_sigop_count(tx)) * 4;
+    if sigop_cost > config.max_standard_tx_sigops_cost {
+        return Ok(false);
+    }
+
+    // 5. Check version (higher versions are reserved for future upgrades)
+    if tx.version > config.max_standard_tx_version {
+        return Ok(false);
+    }
+
+    // 6. Check for value burned in provably-unspendable outputs
+    if is_op_return_only_with_value(tx) {
+        return Ok(false);
+    }
+
     Ok(true)
 }
 
@@ -247,7 +427,8 @@ pub fn is_standard_tx(tx: &Transaction) -> Result<bool> {
 /// Check if new transaction can replace existing one (BIP125 RBF rules).
 ///
 /// According to BIP125 and Orange Paper Section 9.3, replacement is allowed if:
-/// 1. Existing transaction signals RBF (nSequence < SEQUENCE_FINAL)
+/// 1. Existing transaction signals RBF (nSequence < SEQUENCE_FINAL), itself or
+///    through an unconfirmed ancestor in the mempool (inherited signaling)
 /// 2. New transaction has higher fee rate: FeeRate(tx_2) > FeeRate(tx_1)
 /// 3. New transaction pays absolute fee bump: Fee(tx_2) > Fee(tx_1) + MIN_RELAY_FEE
 /// 4. New transaction conflicts with existing: tx_2 spends at least one input from tx_1
@@ -260,7 +441,9 @@ pub fn replacement_checks(
 ) -> Result<bool> {
     // 1. Check RBF signaling - existing transaction must signal RBF
     // Note: new_tx doesn't need to signal RBF per BIP125, only existing_tx does
-    if !signals_rbf(existing_tx) {
+    if !signals_rbf(existing_tx)
+        && !mempool.is_replaceable(&crate::block::calculate_tx_id(existing_tx))
+    {
         return Ok(false);
     }
 
@@ -331,12 +514,366 @@ pub fn replacement_checks(
 // ============================================================================
 
 /// Mempool data structure
-pub type Mempool = HashSet<Hash>;
+///
+/// Tracks the set of transaction IDs in the pool and, for transactions added
+/// with `add_transaction`, the full entry indexed by both txid and wtxid so a
+/// SegWit transaction can be found by either identifier (e.g. for compact-block
+/// reconstruction, which uses wtxid-based short IDs).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Mempool {
+    /// Transaction IDs of all transactions in the pool
+    txids: HashSet<Hash>,
+    /// Full entries keyed by txid
+    entries: HashMap<Hash, MempoolEntry>,
+    /// wtxid → txid index
+    wtxids: HashMap<Hash, Hash>,
+    /// Spent outpoint → txid of the entry spending it
+    spenders: HashMap<OutPoint, Hash>,
+}
+
+/// A transaction stored in the mempool together with its identifiers
+///
+/// Size and fee metadata is computed once at insertion, so eviction, RBF and
+/// ancestor-limit checks can read it without reserializing the transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MempoolEntry {
+    pub tx: Transaction,
+    /// Witness stack per input (empty stacks for non-SegWit inputs)
+    pub witnesses: Vec<Witness>,
+    pub txid: Hash,
+    pub wtxid: Hash,
+    /// Fee in satoshis (inputs minus outputs)
+    pub fee: Integer,
+    /// BIP141 weight, including witness data
+    pub weight: Natural,
+    /// Virtual size: weight / 4, rounded up
+    pub vsize: Natural,
+    pub input_count: usize,
+    pub output_count: usize,
+    /// When the transaction entered the pool, in seconds since the Unix epoch
+    pub entry_time: Natural,
+}
+
+impl MempoolEntry {
+    /// Fee rate in sat/vB
+    pub fn fee_rate(&self) -> f64 {
+        if self.vsize == 0 {
+            return 0.0;
+        }
+        self.fee as f64 / self.vsize as f64
+    }
+}
+
+impl Mempool {
+    /// Create an empty mempool
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Insert a transaction ID without storing the transaction
+    ///
+    /// Returns true if the ID was not already present.
+    pub fn insert(&mut self, txid: Hash) -> bool {
+        self.txids.insert(txid)
+    }
+
+    /// Add a full transaction, indexing it by txid and wtxid
+    ///
+    /// Returns the (txid, wtxid) pair. `witnesses` holds one stack per input;
+    /// `fee` is the fee the transaction pays, as found during acceptance. The
+    /// entry time is the current system time.
+    pub fn add_transaction(
+        &mut self,
+        tx: Transaction,
+        witnesses: Vec<Witness>,
+        fee: Integer,
+    ) -> (Hash, Hash) {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0);
+        self.add_transaction_at(tx, witnesses, fee, now)
+    }
+
+    /// `add_transaction` with an explicit entry time, as when reloading a
+    /// persisted pool
+    ///
+    /// An entry with the same txid is replaced, as is any entry spending one
+    /// of the same outpoints, so the wtxid and spender indexes only ever point
+    /// at stored entries.
+    pub fn add_transaction_at(
+        &mut self,
+        tx: Transaction,
+        witnesses: Vec<Witness>,
+        fee: Integer,
+        entry_time: Natural,
+    ) -> (Hash, Hash) {
+        let txid = crate::block::calculate_tx_id(&tx);
+        let wtxid = crate::block::calculate_wtxid(&tx, &witnesses);
+        let weight = crate::transaction::calculate_transaction_weight_with_witness(&tx, &witnesses)
+            as Natural;
+
+        self.remove(&txid);
+        for conflict in self.find_conflicts(&tx) {
+            self.remove(&conflict);
+        }
+
+        self.txids.insert(txid);
+        self.wtxids.insert(wtxid, txid);
+        for input in &tx.inputs {
+            self.spenders.insert(input.prevout.clone(), txid);
+        }
+        self.entries.insert(
+            txid,
+            MempoolEntry {
+                input_count: tx.inputs.len(),
+                output_count: tx.outputs.len(),
+                tx,
+                witnesses,
+                txid,
+                wtxid,
+                fee,
+                weight,
+                vsize: crate::witness::weight_to_vsize(weight),
+                entry_time,
+            },
+        );
+
+        (txid, wtxid)
+    }
+
+    /// Check whether a transaction ID is in the pool
+    pub fn contains(&self, txid: &Hash) -> bool {
+        self.txids.contains(txid)
+    }
+
+    /// Check whether a witness transaction ID is in the pool
+    pub fn contains_wtxid(&self, wtxid: &Hash) -> bool {
+        self.wtxids.contains_key(wtxid)
+    }
+
+    /// Look up an entry by txid
+    pub fn get(&self, txid: &Hash) -> Option<&MempoolEntry> {
+        self.entries.get(txid)
+    }
+
+    /// Look up an entry by wtxid
+    pub fn get_by_wtxid(&self, wtxid: &Hash) -> Option<&MempoolEntry> {
+        self.wtxids
+            .get(wtxid)
+            .and_then(|txid| self.entries.get(txid))
+    }
+
+    /// Output `outpoint` of a transaction stored in the pool, if any
+    pub fn get_output(&self, outpoint: &OutPoint) -> Option<&TransactionOutput> {
+        let entry = self.entries.get(&outpoint.hash)?;
+        entry.tx.outputs.get(usize::try_from(outpoint.index).ok()?)
+    }
+
+    /// Remove a transaction by txid, dropping it from both indexes
+    ///
+    /// Returns true if the ID was present.
+    pub fn remove(&mut self, txid: &Hash) -> bool {
+        if let Some(entry) = self.entries.remove(txid) {
+            self.wtxids.remove(&entry.wtxid);
+            for input in &entry.tx.inputs {
+                if self.spenders.get(&input.prevout) == Some(txid) {
+                    self.spenders.remove(&input.prevout);
+                }
+            }
+        }
+        self.txids.remove(txid)
+    }
+
+    /// Find entries that spend any of `tx`'s inputs
+    ///
+    /// Returns the txids of all stored entries spending an outpoint that `tx`
+    /// also spends, each listed once, in input order. Only entries added with
+    /// `add_transaction` are considered.
+    pub fn find_conflicts(&self, tx: &Transaction) -> Vec<Hash> {
+        let mut conflicts = Vec::new();
+        for input in &tx.inputs {
+            if let Some(txid) = self.spenders.get(&input.prevout) {
+                if !conflicts.contains(txid) {
+                    conflicts.push(*txid);
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// Whether a stored transaction is replaceable under BIP125
+    ///
+    /// Replaceability is inherited: the transaction is replaceable if it, or
+    /// any of its unconfirmed ancestors in the pool, signals RBF. Returns false
+    /// for txids without a stored entry.
+    pub fn is_replaceable(&self, txid: &Hash) -> bool {
+        let mut pending = vec![*txid];
+        let mut visited = HashSet::new();
+        while let Some(txid) = pending.pop() {
+            if !visited.insert(txid) {
+                continue;
+            }
+            let Some(entry) = self.entries.get(&txid) else {
+                continue;
+            };
+            if signals_rbf(&entry.tx) {
+                return true;
+            }
+            pending.extend(entry.tx.inputs.iter().map(|input| input.prevout.hash));
+        }
+        false
+    }
+
+    /// Number of transactions in the pool
+    pub fn len(&self) -> usize {
+        self.txids.len()
+    }
+
+    /// Whether the pool is empty
+    pub fn is_empty(&self) -> bool {
+        self.txids.is_empty()
+    }
+
+    /// Iterate over transaction IDs in the pool
+    pub fn iter(&self) -> std::collections::hash_set::Iter<'_, Hash> {
+        self.txids.iter()
+    }
+
+    /// Iterate over stored entries
+    pub fn entries(&self) -> impl Iterator<Item = &MempoolEntry> {
+        self.entries.values()
+    }
+
+    /// Iterate over stored entries in the order a fee-maximizing miner would
+    /// include them
+    ///
+    /// Each step takes the entry whose package (itself plus its in-pool
+    /// ancestors not yet yielded) has the highest fee rate, and yields that
+    /// package ancestors first. A low-fee parent is thus carried by a high-fee
+    /// child, and no entry comes before its ancestors. Ties go to the lower
+    /// txid. Packages are rescored after every step, as in Core's
+    /// `BlockAssembler::addPackageTxs`, so the cost is quadratic in pool size.
+    pub fn iter_by_ancestor_feerate(&self) -> impl Iterator<Item = &MempoolEntry> {
+        let ancestors: HashMap<Hash, HashSet<Hash>> = self
+            .entries
+            .keys()
+            .map(|txid| (*txid, self.in_pool_ancestors(txid)))
+            .collect();
+        let mut remaining: HashSet<Hash> = self.entries.keys().copied().collect();
+        let mut order = Vec::with_capacity(remaining.len());
+
+        while !remaining.is_empty() {
+            let package_of = |txid: &Hash| -> Vec<Hash> {
+                let mut package: Vec<Hash> = ancestors[txid]
+                    .iter()
+                    .filter(|ancestor| remaining.contains(*ancestor))
+                    .copied()
+                    .collect();
+                package.push(*txid);
+                package
+            };
+            let score = |package: &[Hash]| {
+                package.iter().fold((0i128, 0i128), |(fee, vsize), txid| {
+                    let entry = &self.entries[txid];
+                    (fee + entry.fee as i128, vsize + entry.vsize as i128)
+                })
+            };
+
+            // Compare fee rates as fee_a × vsize_b against fee_b × vsize_a
+            let (_, mut package, _) = remaining
+                .iter()
+                .map(|txid| {
+                    let package = package_of(txid);
+                    let package_score = score(&package);
+                    (*txid, package, package_score)
+                })
+                .max_by(
+                    |(txid_a, _, (fee_a, vsize_a)), (txid_b, _, (fee_b, vsize_b))| {
+                        (fee_a * vsize_b)
+                            .cmp(&(fee_b * vsize_a))
+                            .then_with(|| txid_b.cmp(txid_a))
+                    },
+                )
+                .expect("remaining is not empty");
+
+            // An entry has more in-pool ancestors than any of its ancestors
+            package.sort_by_key(|txid| (ancestors[txid].len(), *txid));
+            for txid in package {
+                remaining.remove(&txid);
+                order.push(&self.entries[&txid]);
+            }
+        }
+
+        order.into_iter()
+    }
+
+    /// Txids of the stored entries that `txid` spends from, directly or
+    /// through other stored entries
+    fn in_pool_ancestors(&self, txid: &Hash) -> HashSet<Hash> {
+        let mut ancestors = HashSet::new();
+        let mut pending = vec![*txid];
+        while let Some(txid) = pending.pop() {
+            let Some(entry) = self.entries.get(&txid) else {
+                continue;
+            };
+            for input in &entry.tx.inputs {
+                let parent = input.prevout.hash;
+                if self.entries.contains_key(&parent) && ancestors.insert(parent) {
+                    pending.push(parent);
+                }
+            }
+        }
+        ancestors
+    }
+
+    /// Persist the stored entries to `writer`
+    ///
+    /// See `serialization::mempool` for the format. Txids added with
+    /// `insert` alone have no transaction to persist and are skipped.
+    pub fn serialize<W: std::io::Write>(&self, writer: &mut W) -> Result<()> {
+        crate::serialization::mempool::write_mempool(self, writer)
+    }
+
+    /// Reload a pool persisted with `serialize`
+    ///
+    /// Every entry is re-validated against `utxo_set` at `height`; entries
+    /// that are no longer acceptable are dropped.
+    pub fn deserialize<R: std::io::Read>(
+        reader: &mut R,
+        utxo_set: &UtxoSet,
+        height: Natural,
+    ) -> Result<Self> {
+        crate::serialization::mempool::read_mempool(reader, utxo_set, height)
+    }
+}
+
+impl From<HashSet<Hash>> for Mempool {
+    fn from(txids: HashSet<Hash>) -> Self {
+        Self {
+            txids,
+            ..Self::default()
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a Mempool {
+    type Item = &'a Hash;
+    type IntoIter = std::collections::hash_set::Iter<'a, Hash>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.txids.iter()
+    }
+}
 
 /// Result of mempool acceptance
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum MempoolResult {
-    Accepted,
+    /// Accepted; `replaceable` is whether it can be replaced by fee bumping
+    /// under BIP125 (see `is_bip125_replaceable`)
+    Accepted {
+        replaceable: bool,
+    },
     Rejected(String),
 }
 
@@ -535,73 +1072,264 @@ fn check_mempool_rules(tx: &Transaction, fee: Integer, mempool: &Mempool) -> Res
 }
 
 /// Check for transaction conflicts
+///
+/// A conflict is an input already spent by a transaction in the mempool.
+/// Spending an output *of* a mempool transaction is not a conflict.
 fn has_conflicts(tx: &Transaction, mempool: &Mempool) -> Result<bool> {
-    // Check if any input is already spent by mempool transaction
-    for input in &tx.inputs {
-        // In a real implementation, we'd check if this input is already spent
-        // by another transaction in the mempool
-        // For now, we'll do a simplified check
-        if mempool.contains(&input.prevout.hash) {
-            return Ok(true);
+    Ok(!mempool.find_conflicts(tx).is_empty())
+}
+
+/// Result of evaluating a package with `evaluate_package`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageResult {
+    /// Verdict for the package as a whole
+    pub result: MempoolResult,
+    /// Fee paid by each transaction, in package order (up to the first failure)
+    pub tx_fees: Vec<Integer>,
+    /// Sum of the fees of all transactions
+    pub total_fee: Integer,
+    /// Sum of the sizes (vbytes) of all transactions
+    pub total_size: usize,
+}
+
+impl PackageResult {
+    /// Package fee rate in sat/vB
+    pub fn fee_rate(&self) -> f64 {
+        if self.total_size == 0 {
+            return 0.0;
         }
+        self.total_fee as f64 / self.total_size as f64
     }
-
-    Ok(false)
 }
 
-/// Check if transaction is final (Orange Paper Section 9.1 - Transaction Finality)
+/// Evaluate a package of related transactions together (child-pays-for-parent)
+///
+/// The package must be topologically sorted: a transaction may spend outputs
+/// of transactions before it in the package, but not after. Each transaction
+/// is validated against `utxo_set` plus the outputs of the package
+/// transactions before it, and its scripts are verified with its witness
+/// stacks from `witnesses` (one `Vec<Witness>` per transaction). The fee
+/// floor is then applied to the package as a whole, so a high-fee child can
+/// carry a parent that pays too little on its own.
 ///
-/// Matches Bitcoin Core's IsFinalTx() exactly.
+/// Structural failures (too many transactions, duplicates, unsorted, spends
+/// in conflict) reject the package before any transaction is validated.
 ///
-/// A transaction is final if:
-/// 1. tx.lock_time == 0 (no locktime restriction), OR
-/// 2. If locktime < LOCKTIME_THRESHOLD (block height): height > tx.lock_time
-/// 3. If locktime >= LOCKTIME_THRESHOLD (timestamp): block_time > tx.lock_time
-/// 4. OR if all inputs have SEQUENCE_FINAL (0xffffffff), locktime is ignored
+/// # Errors
 ///
-/// Mathematical specification:
-/// ∀ tx ∈ Transaction, height ∈ ℕ, block_time ∈ ℕ:
-/// - is_final_tx(tx, height, block_time) = true ⟹
-///   (tx.lock_time = 0 ∨
-///   (tx.lock_time < LOCKTIME_THRESHOLD ∧ height > tx.lock_time) ∨
-///   (tx.lock_time >= LOCKTIME_THRESHOLD ∧ block_time > tx.lock_time) ∨
-///   (∀ input ∈ tx.inputs: input.sequence == SEQUENCE_FINAL))
-pub fn is_final_tx(tx: &Transaction, height: Natural, block_time: Natural) -> bool {
-    use crate::constants::SEQUENCE_FINAL;
-
-    // If locktime is 0, transaction is always final
-    if tx.lock_time == 0 {
-        return true;
-    }
-
-    // Check if locktime is satisfied based on type
-    // Core's logic: if (tx.nLockTime < (tx.nLockTime < LOCKTIME_THRESHOLD ? nBlockHeight : nBlockTime))
-    // This means: locktime < (condition ? height : block_time)
-    // So: if locktime < threshold, check locktime < height
-    //     if locktime >= threshold, check locktime < block_time
-    let locktime_satisfied = if (tx.lock_time as u32) < LOCKTIME_THRESHOLD {
-        // Block height locktime: check if locktime < height
-        (tx.lock_time as Natural) < height
-    } else {
-        // Timestamp locktime: check if locktime < block_time
-        (tx.lock_time as Natural) < block_time
+/// Returns `ConsensusError::TransactionValidation` if `witnesses` does not
+/// have one entry per transaction.
+pub fn evaluate_package(
+    txs: &[Transaction],
+    witnesses: &[Vec<Witness>],
+    utxo_set: &UtxoSet,
+    height: Natural,
+) -> Result<PackageResult> {
+    if witnesses.len() != txs.len() {
+        return Err(ConsensusError::TransactionValidation(
+            format!(
+                "Witness count {} does not match transaction count {}",
+                witnesses.len(),
+                txs.len()
+            )
+            .into(),
+        ));
+    }
+
+    let mut package = PackageResult {
+        result: MempoolResult::Accepted {
+            replaceable: txs.iter().any(signals_rbf),
+        },
+        tx_fees: Vec::with_capacity(txs.len()),
+        total_fee: 0,
+        total_size: 0,
     };
+    if let Some(reason) = check_package_structure(txs) {
+        package.result = MempoolResult::Rejected(reason);
+        return Ok(package);
+    }
+
+    // Outputs created by the package so far; the structure checks above
+    // rule out two package transactions spending the same one
+    let mut package_outputs = UtxoSet::new();
+    for (i, (tx, tx_witnesses)) in txs.iter().zip(witnesses).enumerate() {
+        let view = resolve_package_inputs(tx, utxo_set, &package_outputs);
+        if let Some(reason) = check_package_tx(tx, tx_witnesses, &view, height)? {
+            package.result = MempoolResult::Rejected(format!("tx {i}: {reason}"));
+            return Ok(package);
+        }
+
+        let fee = calculate_fee(tx, &view)?;
+        package.tx_fees.push(fee);
+        package.total_fee += fee;
+        package.total_size += calculate_transaction_size_vbytes(tx);
+
+        // Later transactions may spend this one's outputs
+        let txid = crate::block::calculate_tx_id(tx);
+        for (index, output) in tx.outputs.iter().enumerate() {
+            package_outputs.insert(
+                OutPoint {
+                    hash: txid,
+                    index: index as Natural,
+                },
+                UTXO {
+                    value: output.value,
+                    script_pubkey: output.script_pubkey.clone(),
+                    height,
+                    is_coinbase: false,
+                },
+            );
+        }
+    }
+
+    let config = crate::config::get_consensus_config();
+    if package.total_fee < config.mempool.min_tx_fee
+        || package.fee_rate() < config.mempool.min_relay_fee_rate as f64
+    {
+        package.result = MempoolResult::Rejected(format!(
+            "{}: package fee {} for {} vbytes",
+            RejectReason::InsufficientFee,
+            package.total_fee,
+            package.total_size
+        ));
+    }
+
+    Ok(package)
+}
+
+/// Context-free package checks; returns the reject reason if any fails
+fn check_package_structure(txs: &[Transaction]) -> Option<String> {
+    if txs.is_empty() {
+        return Some("package-empty".to_string());
+    }
+    if txs.len() > MAX_PACKAGE_COUNT {
+        return Some("package-too-many-transactions".to_string());
+    }
+
+    let txids: Vec<Hash> = txs.iter().map(crate::block::calculate_tx_id).collect();
+    let unique: HashSet<&Hash> = txids.iter().collect();
+    if unique.len() != txids.len() {
+        return Some("package-contains-duplicates".to_string());
+    }
+
+    // Every in-package parent must come before its child
+    for (i, tx) in txs.iter().enumerate() {
+        if tx
+            .inputs
+            .iter()
+            .any(|input| txids[i + 1..].contains(&input.prevout.hash))
+        {
+            return Some("package-not-sorted".to_string());
+        }
+    }
 
-    if locktime_satisfied {
-        return true;
+    let mut spent = HashSet::new();
+    for tx in txs {
+        if tx.inputs.iter().any(|input| !spent.insert(&input.prevout)) {
+            return Some("conflict-in-package".to_string());
+        }
     }
 
-    // Even if locktime isn't satisfied, transaction is final if all inputs have SEQUENCE_FINAL
-    // This allows transactions to bypass locktime by setting all sequences to 0xffffffff
-    // Core's behavior: if all inputs have SEQUENCE_FINAL, locktime is ignored
+    None
+}
+
+/// Resolve the outputs spent by a package transaction
+///
+/// Each prevout is looked up among the outputs of earlier package
+/// transactions first, then in the chain UTXO set. Like
+/// `resolve_mempool_inputs`, returns a UTXO set holding just the spent
+/// outputs; inputs found in neither are left out, and `check_package_tx`
+/// rejects them as orphans.
+fn resolve_package_inputs(
+    tx: &Transaction,
+    utxo_set: &UtxoSet,
+    package_outputs: &UtxoSet,
+) -> UtxoSet {
+    let mut view = UtxoSet::with_capacity(tx.inputs.len());
     for input in &tx.inputs {
-        if (input.sequence as u32) != SEQUENCE_FINAL {
-            return false;
+        if let Some(utxo) = package_outputs
+            .get(&input.prevout)
+            .or_else(|| utxo_set.get(&input.prevout))
+        {
+            view.insert(input.prevout.clone(), utxo.clone());
         }
     }
+    view
+}
+
+/// Validate one package transaction against `view`; returns the reject
+/// reason if it fails. Fee floors are applied to the package, not here.
+fn check_package_tx(
+    tx: &Transaction,
+    witnesses: &[Witness],
+    view: &UtxoSet,
+    height: Natural,
+) -> Result<Option<String>> {
+    if is_coinbase(tx) {
+        return Ok(Some("coinbase".to_string()));
+    }
+    if let ValidationResult::Invalid(reason) = crate::transaction::check_transaction(tx)? {
+        return Ok(Some(reason));
+    }
+    if let Some(i) = tx
+        .inputs
+        .iter()
+        .position(|input| !view.contains_key(&input.prevout))
+    {
+        return Ok(Some(format!(
+            "{}: input {i} is an orphan",
+            RejectReason::InputsMissingOrSpent
+        )));
+    }
+    if let (ValidationResult::Invalid(reason), _) = check_tx_inputs(tx, view, height)? {
+        return Ok(Some(reason));
+    }
+
+    // Signature checks need every spent output and the transaction itself
+    let prevouts: Vec<PrevOut> = tx
+        .inputs
+        .iter()
+        .map(|input| {
+            let utxo = &view[&input.prevout];
+            PrevOut {
+                value: utxo.value,
+                script_pubkey: utxo.script_pubkey.clone(),
+            }
+        })
+        .collect();
+    let sighash_cache = SighashCache::new(tx);
+    let flags = calculate_script_flags(tx, Some(witnesses));
+    for (i, input) in tx.inputs.iter().enumerate() {
+        if !verify_script_with_witness(
+            &input.script_sig,
+            &prevouts[i].script_pubkey,
+            witnesses.get(i).map_or(&[][..], Vec::as_slice),
+            flags,
+            tx,
+            i,
+            &prevouts,
+            &sighash_cache,
+            Some(height),
+            None,
+            Network::from_env(),
+            default_verifier(),
+        )? {
+            return Ok(Some(format!("Invalid script at input {i}")));
+        }
+    }
+    Ok(None)
+}
 
-    // All inputs have SEQUENCE_FINAL - transaction is final regardless of locktime
-    true
+/// Whether a transaction entering the pool is replaceable under BIP125
+///
+/// True if the transaction signals RBF itself, or spends an output of an
+/// in-pool transaction that is replaceable (inherited signaling).
+pub fn is_bip125_replaceable(tx: &Transaction, mempool: &Mempool) -> bool {
+    signals_rbf(tx)
+        || tx
+            .inputs
+            .iter()
+            .any(|input| mempool.is_replaceable(&input.prevout.hash))
 }
 
 /// Check if transaction signals RBF
@@ -618,13 +1346,10 @@ pub fn signals_rbf(tx: &Transaction) -> bool {
 
 /// Calculate transaction size in virtual bytes (vbytes)
 ///
-/// For SegWit transactions, uses weight/4 (virtual bytes).
-/// For non-SegWit transactions, uses byte size.
-/// This is a simplified version - proper implementation would use segwit::calculate_weight()
+/// Transactions here carry no witness data, so this is the vsize of the
+/// stripped transaction, equal to its serialized size.
 fn calculate_transaction_size_vbytes(tx: &Transaction) -> usize {
-    // Simplified: use byte size as approximation
-    // In production, should use proper weight calculation for SegWit
-    calculate_transaction_size(tx)
+    crate::transaction::calculate_transaction_vsize(tx, &[])
 }
 
 /// Check if new transaction conflicts with existing transaction
@@ -682,8 +1407,21 @@ fn creates_new_dependencies(
     Ok(false)
 }
 
+/// Whether every output is OP_RETURN while the outputs carry value
+///
+/// OP_RETURN outputs are provably unspendable, so any value they hold is
+/// burned; a transaction made only of them cannot be paying anyone.
+pub fn is_op_return_only_with_value(tx: &Transaction) -> bool {
+    !tx.outputs.is_empty()
+        && tx
+            .outputs
+            .iter()
+            .all(|output| output.script_pubkey.first() == Some(&0x6a))
+        && tx.outputs.iter().any(|output| output.value != 0)
+}
+
 /// Check if script is standard
-fn is_standard_script(script: &ByteString) -> Result<bool> {
+fn is_standard_script(script: &ByteString, max_datacarrier_bytes: usize) -> Result<bool> {
     // Simplified standard script check
     // In reality, this would check for P2PKH, P2SH, P2WPKH, P2WSH, etc.
     if script.is_empty() {
@@ -695,6 +1433,22 @@ fn is_standard_script(script: &ByteString) -> Result<bool> {
         return Ok(false);
     }
 
+    // Witness programs: only P2WPKH, P2WSH (v0) and P2TR (v1, 32 bytes) are
+    // standard. Other v0 lengths are unspendable; other versions and lengths
+    // are consensus-valid but reserved for future soft forks, so not relayed.
+    if let Some((version, program)) = crate::script_type::witness_program(script) {
+        return Ok(matches!(
+            (version, program.len()),
+            (0, SEGWIT_P2WPKH_LENGTH) | (0, SEGWIT_P2WSH_LENGTH) | (1, TAPROOT_PROGRAM_LENGTH)
+        ));
+    }
+
+    // Data carriers: OP_RETURN followed only by pushes, up to the size limit
+    if let crate::script_type::ScriptType::OpReturn(_) = crate::script_type::classify_script(script)
+    {
+        return Ok(script.len() <= max_datacarrier_bytes);
+    }
+
     // Check for non-standard opcodes (simplified)
     for &byte in script {
         if byte > 0x60 && byte < 0x7f {
@@ -1191,6 +1945,35 @@ mod tests {
         assert!(matches!(result, MempoolResult::Rejected(_)));
     }
 
+    #[test]
+    fn test_accept_to_memory_pool_immature_coinbase_spend() {
+        let tx = create_valid_transaction();
+        let mut utxo_set = UtxoSet::new();
+        utxo_set.insert(
+            tx.inputs[0].prevout.clone(),
+            UTXO {
+                value: 10000,
+                script_pubkey: vec![0x51, 0x87], // OP_1 OP_EQUAL
+                height: 500,
+                is_coinbase: true,
+            },
+        );
+        let mempool = Mempool::new();
+
+        // 99 blocks deep: immature
+        let result = accept_to_memory_pool(&tx, None, &utxo_set, &mempool, 599).unwrap();
+        match result {
+            MempoolResult::Rejected(reason) => {
+                assert!(reason.starts_with("bad-txns-premature-spend-of-coinbase"));
+            }
+            MempoolResult::Accepted { .. } => panic!("immature coinbase spend must be rejected"),
+        }
+
+        // 100 blocks deep: mature
+        let result = accept_to_memory_pool(&tx, None, &utxo_set, &mempool, 600).unwrap();
+        assert_eq!(result, MempoolResult::Accepted { replaceable: false });
+    }
+
     #[test]
     fn test_is_standard_tx_valid() {
         let tx = create_valid_transaction();
@@ -1353,6 +2136,190 @@ mod tests {
         assert!(!result);
     }
 
+    #[test]
+    fn test_is_standard_tx_sigop_cost_limit() {
+        let mut tx = create_valid_transaction();
+        // Each bare OP_CHECKMULTISIG counts as 20 legacy sigops (cost 80)
+        let per_tx_sigops = MAX_STANDARD_TX_SIGOPS_COST / 4;
+        let checkmultisigs = (per_tx_sigops / 20) as usize;
+        tx.outputs[0].script_pubkey = vec![0xae; checkmultisigs];
+        assert!(is_standard_tx(&tx).unwrap());
+
+        // One more pushes the cost over the per-transaction cap
+        tx.outputs[0].script_pubkey.push(0xae);
+        assert!(!is_standard_tx(&tx).unwrap());
+    }
+
+    #[test]
+    fn test_accept_to_memory_pool_rejects_overweight() {
+        let tx = create_valid_transaction();
+        let utxo_set = create_test_utxo_set();
+        let mempool = Mempool::new();
+
+        // Witness bytes count once: 400,000 of them on top of the stripped
+        // transaction put it over the standard weight
+        let witnesses = vec![vec![vec![0u8; MAX_STANDARD_TX_WEIGHT]]];
+        match accept_to_memory_pool(&tx, Some(&witnesses), &utxo_set, &mempool, 100).unwrap() {
+            MempoolResult::Rejected(reason) => {
+                assert!(reason.starts_with("non-standard: weight"), "{reason}")
+            }
+            result => panic!("expected rejection, got {result:?}"),
+        }
+    }
+
+    #[test]
+    fn test_accept_to_memory_pool_counts_p2sh_sigops() {
+        // Redeem script with 20 `OP_16 OP_CHECKMULTISIG`s in an unexecuted
+        // branch: 320 sigops (cost 1,280) that never run
+        let mut redeem_script = vec![0x00, 0x63]; // OP_0 OP_IF
+        for _ in 0..20 {
+            redeem_script.extend_from_slice(&[0x60, 0xae]); // OP_16 OP_CHECKMULTISIG
+        }
+        redeem_script.extend_from_slice(&[0x68, 0x51]); // OP_ENDIF OP_1
+        use ripemd::Ripemd160;
+        use sha2::{Digest, Sha256};
+        let mut p2sh = vec![0xa9, 0x14];
+        p2sh.extend_from_slice(&Ripemd160::digest(Sha256::digest(&redeem_script)));
+        p2sh.push(0x87);
+        let mut script_sig = vec![redeem_script.len() as u8];
+        script_sig.extend_from_slice(&redeem_script);
+
+        let spend = |input_count: u8| {
+            let mut utxo_set = UtxoSet::new();
+            let mut inputs = Vec::new();
+            for i in 0..input_count {
+                let prevout = OutPoint {
+                    hash: [i + 1; 32],
+                    index: 0,
+                };
+                utxo_set.insert(
+                    prevout.clone(),
+                    UTXO {
+                        value: 100_000,
+                        script_pubkey: p2sh.clone(),
+                        height: 0,
+                        is_coinbase: false,
+                    },
+                );
+                inputs.push(TransactionInput {
+                    prevout,
+                    script_sig: script_sig.clone(),
+                    sequence: 0xffffffff,
+                });
+            }
+            let tx = Transaction {
+                version: 1,
+                inputs: inputs.into(),
+                outputs: vec![create_dummy_output()].into(),
+                lock_time: 0,
+            };
+            accept_to_memory_pool(&tx, None, &utxo_set, &Mempool::new(), 100).unwrap()
+        };
+
+        // The scriptSigs and outputs carry no sigops; 12 inputs cost 15,360
+        assert!(matches!(spend(12), MempoolResult::Accepted { .. }));
+
+        // 13 inputs cost 16,640, over the 16,000 cap
+        match spend(13) {
+            MempoolResult::Rejected(reason) => {
+                assert!(reason.starts_with("bad-txns-too-many-sigops"), "{reason}")
+            }
+            result => panic!("expected rejection, got {result:?}"),
+        }
+    }
+
+    #[test]
+    fn test_is_standard_tx_version_limit() {
+        let mut tx = create_valid_transaction();
+        tx.version = 3;
+        assert!(!is_standard_tx(&tx).unwrap());
+
+        let mut config = crate::config::MempoolConfig::default();
+        assert!(!is_standard_tx_with_config(&tx, &config).unwrap());
+        config.max_standard_tx_version = 3;
+        assert!(is_standard_tx_with_config(&tx, &config).unwrap());
+    }
+
+    #[test]
+    fn test_is_standard_tx_datacarrier_size() {
+        let mut tx = create_valid_transaction();
+        let mut script_pubkey = vec![0x6a, 0x4c, 100]; // OP_RETURN OP_PUSHDATA1 100
+        script_pubkey.extend([0xab; 100]);
+        tx.outputs.push(TransactionOutput {
+            value: 0,
+            script_pubkey,
+        });
+
+        // 100 bytes of data exceed the default 80
+        let mut config = crate::config::MempoolConfig::default();
+        assert_eq!(config.max_datacarrier_bytes, MAX_OP_RETURN_RELAY);
+        assert!(!is_standard_tx_with_config(&tx, &config).unwrap());
+
+        config.max_datacarrier_bytes = 103;
+        assert!(is_standard_tx_with_config(&tx, &config).unwrap());
+
+        // Data carriers must be push-only after OP_RETURN
+        tx.outputs[1].script_pubkey = vec![0x6a, 0x51, 0x93];
+        assert!(!is_standard_tx_with_config(&tx, &config).unwrap());
+    }
+
+    #[test]
+    fn test_is_op_return_only_with_value() {
+        let mut tx = create_valid_transaction();
+        tx.outputs[0] = TransactionOutput {
+            value: 5000,
+            script_pubkey: vec![0x6a, 0x04, 0xde, 0xad, 0xbe, 0xef],
+        };
+        assert!(is_op_return_only_with_value(&tx));
+        assert!(!is_standard_tx(&tx).unwrap());
+
+        // Zero-value data carriers lock nothing up
+        tx.outputs[0].value = 0;
+        assert!(!is_op_return_only_with_value(&tx));
+
+        // A payment alongside the OP_RETURN output is not flagged
+        tx.outputs[0].value = 5000;
+        let mut tx_with_payment = tx.clone();
+        tx_with_payment.outputs.push(TransactionOutput {
+            value: 1000,
+            script_pubkey: vec![0x51],
+        });
+        assert!(!is_op_return_only_with_value(&tx_with_payment));
+    }
+
+    #[test]
+    fn test_is_standard_tx_scriptsig_size_limit() {
+        let mut tx = create_valid_transaction();
+        tx.inputs[0].script_sig = vec![0x51; MAX_STANDARD_SCRIPTSIG_SIZE - 1];
+        assert!(is_standard_tx(&tx).unwrap());
+
+        tx.inputs[0].script_sig = vec![0x51; MAX_STANDARD_SCRIPTSIG_SIZE + 1];
+        assert!(!is_standard_tx(&tx).unwrap());
+    }
+
+    #[test]
+    fn test_is_standard_tx_witness_versions() {
+        let with_output = |version_op: u8, program_len: usize| {
+            let mut tx = create_valid_transaction();
+            let mut script = vec![version_op, program_len as u8];
+            script.resize(2 + program_len, 0x7a);
+            tx.outputs[0].script_pubkey = script;
+            is_standard_tx(&tx).unwrap()
+        };
+
+        // v0: P2WPKH and P2WSH only
+        assert!(with_output(0x00, 20));
+        assert!(with_output(0x00, 32));
+        assert!(!with_output(0x00, 30));
+
+        // v1/32 (Taproot) is standard
+        assert!(with_output(0x51, 32));
+        // v1 of another length is non-standard
+        assert!(!with_output(0x51, 30));
+        // v5/32 is reserved for a future soft fork
+        assert!(!with_output(0x55, 32));
+    }
+
     #[test]
     fn test_replacement_checks_new_unconfirmed_dependency() {
         let utxo_set = create_test_utxo_set();
@@ -1474,13 +2441,238 @@ mod tests {
         let tx = create_valid_transaction();
         let mut mempool = Mempool::new();
 
-        // Add a conflicting transaction to mempool
-        mempool.insert(tx.inputs[0].prevout.hash);
+        // Add a transaction spending the same outpoint to the mempool
+        let mut conflicting = tx.clone();
+        conflicting.outputs[0].value = 500;
+        mempool.add_transaction(conflicting, vec![], 0);
 
         let result = has_conflicts(&tx, &mempool).unwrap();
         assert!(result);
     }
 
+    #[test]
+    fn test_accept_to_memory_pool_reports_replaceability() {
+        let mut utxo_set = create_test_utxo_set();
+        for utxo in utxo_set.values_mut() {
+            utxo.script_pubkey = vec![0x51, 0x87]; // OP_1 OP_EQUAL
+        }
+        let mut mempool = Mempool::new();
+
+        let final_tx = create_valid_transaction();
+        let result = accept_to_memory_pool(&final_tx, None, &utxo_set, &mempool, 100).unwrap();
+        assert_eq!(result, MempoolResult::Accepted { replaceable: false });
+
+        let mut signaling = create_valid_transaction();
+        signaling.inputs[0].sequence = SEQUENCE_RBF as Natural;
+        signaling.outputs[0] = TransactionOutput {
+            value: 8000,
+            script_pubkey: vec![0x51, 0x87],
+        };
+        let result = accept_to_memory_pool(&signaling, None, &utxo_set, &mempool, 100).unwrap();
+        assert_eq!(result, MempoolResult::Accepted { replaceable: true });
+
+        // A non-signaling child of a signaling parent inherits replaceability
+        let (parent_txid, _) = mempool.add_transaction(signaling, vec![], 2000);
+        let mut child = create_valid_transaction();
+        child.inputs[0].prevout = OutPoint {
+            hash: parent_txid,
+            index: 0,
+        };
+        child.outputs[0].value = 6000;
+        let result = accept_to_memory_pool(&child, None, &utxo_set, &mempool, 100).unwrap();
+        assert_eq!(result, MempoolResult::Accepted { replaceable: true });
+    }
+
+    #[test]
+    fn test_accept_to_memory_pool_rejects_non_final() {
+        let mut utxo_set = create_test_utxo_set();
+        for utxo in utxo_set.values_mut() {
+            utxo.script_pubkey = vec![0x51, 0x87]; // OP_1 OP_EQUAL
+        }
+        let mempool = Mempool::new();
+        let mtp = 1_600_000_000;
+        let accept = |tx: &Transaction| {
+            accept_to_memory_pool_with_mtp(tx, None, &utxo_set, &mempool, 100, mtp).unwrap()
+        };
+
+        // Locktime only applies with a non-final sequence
+        let mut tx = create_valid_transaction();
+        tx.inputs[0].sequence = 0xfffffffe;
+
+        // Height locktime: includable only once it is below the next height
+        tx.lock_time = 100;
+        match accept(&tx) {
+            MempoolResult::Rejected(reason) => assert!(reason.starts_with("bad-txns-nonfinal")),
+            MempoolResult::Accepted { .. } => panic!("future locktime must be rejected"),
+        }
+        tx.lock_time = 99;
+        assert!(matches!(accept(&tx), MempoolResult::Accepted { .. }));
+
+        // Time locktime is compared with the median time-past, not the clock
+        tx.lock_time = mtp + 1;
+        assert!(matches!(accept(&tx), MempoolResult::Rejected(_)));
+        tx.lock_time = mtp - 1;
+        assert!(matches!(accept(&tx), MempoolResult::Accepted { .. }));
+
+        // Final sequences disable the locktime
+        tx.lock_time = 1_000_000;
+        tx.inputs[0].sequence = 0xffffffff;
+        assert!(matches!(accept(&tx), MempoolResult::Accepted { .. }));
+    }
+
+    #[test]
+    fn test_accept_to_memory_pool_chained_unconfirmed_spend() {
+        let mut utxo_set = create_test_utxo_set();
+        for utxo in utxo_set.values_mut() {
+            utxo.script_pubkey = vec![0x51, 0x87]; // OP_1 OP_EQUAL
+        }
+        let mut mempool = Mempool::new();
+
+        let mut parent = create_valid_transaction();
+        parent.outputs[0] = TransactionOutput {
+            value: 8000,
+            script_pubkey: vec![0x51, 0x87],
+        };
+        let result = accept_to_memory_pool(&parent, None, &utxo_set, &mempool, 100).unwrap();
+        assert_eq!(result, MempoolResult::Accepted { replaceable: false });
+        let (parent_txid, _) = mempool.add_transaction(parent.clone(), vec![], 2000);
+
+        // Child spends the unconfirmed parent's output
+        let mut child = create_valid_transaction();
+        child.inputs[0].prevout = OutPoint {
+            hash: parent_txid,
+            index: 0,
+        };
+        child.outputs[0].value = 6000;
+        let result = accept_to_memory_pool(&child, None, &utxo_set, &mempool, 100).unwrap();
+        assert_eq!(result, MempoolResult::Accepted { replaceable: false });
+
+        // An output the parent does not have is an orphan
+        child.inputs[0].prevout.index = 1;
+        match accept_to_memory_pool(&child, None, &utxo_set, &mempool, 100).unwrap() {
+            MempoolResult::Rejected(reason) => {
+                assert!(reason.starts_with("bad-txns-inputs-missingorspent"));
+            }
+            MempoolResult::Accepted { .. } => panic!("orphan must be rejected"),
+        }
+
+        // A second spend of the parent's input still conflicts
+        let mut double_spend = parent;
+        double_spend.outputs[0].value = 7000;
+        let result = accept_to_memory_pool(&double_spend, None, &utxo_set, &mempool, 100).unwrap();
+        assert_eq!(
+            result,
+            MempoolResult::Rejected("Transaction conflicts with mempool".to_string())
+        );
+    }
+
+    #[test]
+    fn test_evaluate_package_child_pays_for_parent() {
+        let mut utxo_set = create_test_utxo_set();
+        for utxo in utxo_set.values_mut() {
+            utxo.script_pubkey = vec![0x51, 0x87]; // OP_1 OP_EQUAL
+        }
+
+        // Parent pays 100 sats: below the minimum fee on its own
+        let mut parent = create_valid_transaction();
+        parent.outputs[0] = TransactionOutput {
+            value: 9900,
+            script_pubkey: vec![0x51, 0x87],
+        };
+        let result = accept_to_memory_pool(&parent, None, &utxo_set, &Mempool::new(), 100).unwrap();
+        assert_eq!(
+            result,
+            MempoolResult::Rejected("Failed mempool rules".to_string())
+        );
+        let alone =
+            evaluate_package(std::slice::from_ref(&parent), &[vec![]], &utxo_set, 100).unwrap();
+        match alone.result {
+            MempoolResult::Rejected(reason) => assert!(reason.starts_with("min relay fee not met")),
+            MempoolResult::Accepted { .. } => panic!("parent alone must not meet the floor"),
+        }
+
+        // Child pays 4900 sats, lifting the package above the floor
+        let mut child = create_valid_transaction();
+        child.inputs[0].prevout = OutPoint {
+            hash: crate::block::calculate_tx_id(&parent),
+            index: 0,
+        };
+        child.outputs[0].value = 5000;
+        let package = vec![parent.clone(), child.clone()];
+        let result = evaluate_package(&package, &[vec![], vec![]], &utxo_set, 100).unwrap();
+        assert_eq!(
+            result.result,
+            MempoolResult::Accepted { replaceable: false }
+        );
+        assert_eq!(result.tx_fees, vec![100, 4900]);
+        assert_eq!(result.total_fee, 5000);
+        assert!(result.fee_rate() >= 1.0);
+
+        // The child must come after its parent
+        let unsorted = vec![child, parent];
+        let result = evaluate_package(&unsorted, &[vec![], vec![]], &utxo_set, 100).unwrap();
+        assert_eq!(
+            result.result,
+            MempoolResult::Rejected("package-not-sorted".to_string())
+        );
+    }
+
+    #[test]
+    fn test_evaluate_package_verifies_signed_segwit_spend() {
+        use crate::serialization::transaction::deserialize_transaction_with_witness;
+
+        // BIP143 "Native P2WPKH" example, signed: a P2PK input and a P2WPKH
+        // input spent by [sig, pubkey]
+        let (spend, witnesses) = deserialize_transaction_with_witness(
+            &hex::decode(
+                "01000000000102fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f00\
+                 000000494830450221008b9d1dc26ba6a9cb62127b02742fa9d754cd3bebf337f7a55d114c8e5cdd\
+                 30be022040529b194ba3f9281a99f2b1c0a19c0489bc22ede944ccf4ecbab4cc618ef3ed01eeffff\
+                 ffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffff\
+                 ffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac909351\
+                 0d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac000247304402203609\
+                 e17b84f6a7d30c80bfa610b5b4542f32a8a0d5447a12fb1366d7f01cc44a0220573a954c45183315\
+                 61406f90300e8f3358f51928d43c212a8caed02de67eebee0121025476c2e83188368da1ff3e292e\
+                 7acafcdb3566bb0ad253f62fc70f07aeee635711000000",
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        let mut utxo_set = UtxoSet::new();
+        for (input, (value, script_pubkey)) in spend.inputs.iter().zip([
+            (
+                625_000_000,
+                "2103c9f4836b9a4f77fc0d81f7bcb01b7f1b35916864b9476c241ce9fc198bd25432ac",
+            ),
+            (600_000_000, "00141d0f172a0ecb48aee1be1f2687d2963ae33f71a1"),
+        ]) {
+            utxo_set.insert(
+                input.prevout.clone(),
+                UTXO {
+                    value,
+                    script_pubkey: hex::decode(script_pubkey).unwrap(),
+                    height: 1,
+                    is_coinbase: false,
+                },
+            );
+        }
+
+        let package = std::slice::from_ref(&spend);
+        let result = evaluate_package(package, &[witnesses.clone()], &utxo_set, 100).unwrap();
+        assert_eq!(result.result, MempoolResult::Accepted { replaceable: true });
+
+        // Without its witness the P2WPKH input no longer verifies
+        let result = evaluate_package(package, &[vec![]], &utxo_set, 100).unwrap();
+        assert_eq!(
+            result.result,
+            MempoolResult::Rejected("tx 0: Invalid script at input 1".to_string())
+        );
+
+        // One witness list per transaction
+        assert!(evaluate_package(package, &[], &utxo_set, 100).is_err());
+    }
+
     #[test]
     fn test_signals_rbf_true() {
         let mut tx = create_valid_transaction();
@@ -1489,6 +2681,105 @@ mod tests {
         assert!(signals_rbf(&tx));
     }
 
+    #[test]
+    fn test_add_to_memory_pool_records_entry_metadata() {
+        let mut utxo_set = create_test_utxo_set();
+        for utxo in utxo_set.values_mut() {
+            utxo.script_pubkey = vec![0x51, 0x87]; // OP_1 OP_EQUAL
+        }
+        let mut mempool = Mempool::new();
+        let mut tx = create_valid_transaction();
+        tx.outputs[0].value = 7000;
+        let witnesses = vec![Vec::new()];
+
+        let result =
+            add_to_memory_pool(&tx, witnesses.clone(), &utxo_set, &mut mempool, 100).unwrap();
+        assert_eq!(result, MempoolResult::Accepted { replaceable: false });
+
+        let entry = mempool.get(&crate::block::calculate_tx_id(&tx)).unwrap();
+        let weight = crate::transaction::calculate_transaction_weight_with_witness(&tx, &witnesses)
+            as Natural;
+        assert_eq!(entry.fee, calculate_fee(&tx, &utxo_set).unwrap());
+        assert_eq!(entry.fee, 3000);
+        assert_eq!(entry.weight, weight);
+        assert_eq!(entry.vsize, crate::witness::weight_to_vsize(weight));
+        assert_eq!(entry.weight, 4 * calculate_transaction_size(&tx) as Natural);
+        assert_eq!(entry.input_count, 1);
+        assert_eq!(entry.output_count, 1);
+        assert_eq!(entry.fee_rate(), 3000.0 / entry.vsize as f64);
+
+        // Rejected transactions are not stored
+        let mut spent_twice = tx.clone();
+        spent_twice.outputs[0].value = 6000;
+        let result = add_to_memory_pool(&spent_twice, vec![], &utxo_set, &mut mempool, 100);
+        assert!(matches!(result.unwrap(), MempoolResult::Rejected(_)));
+        assert_eq!(mempool.len(), 1);
+    }
+
+    #[test]
+    fn test_is_replaceable_inherited_signaling() {
+        let mut mempool = Mempool::new();
+
+        let mut parent = create_valid_transaction();
+        parent.inputs[0].sequence = 0xfffffffd; // signals RBF
+        let (parent_txid, _) = mempool.add_transaction(parent, vec![], 0);
+
+        // Child does not signal, but spends the signaling parent
+        let mut child = create_valid_transaction();
+        child.inputs[0].prevout = OutPoint {
+            hash: parent_txid,
+            index: 0,
+        };
+        assert!(!signals_rbf(&child));
+        let (child_txid, _) = mempool.add_transaction(child, vec![], 0);
+
+        assert!(mempool.is_replaceable(&parent_txid));
+        assert!(mempool.is_replaceable(&child_txid));
+
+        // Without a signaling ancestor nothing is replaceable
+        let mut unrelated = create_valid_transaction();
+        unrelated.inputs[0].prevout.hash = [2; 32];
+        let (unrelated_txid, _) = mempool.add_transaction(unrelated, vec![], 0);
+        assert!(!mempool.is_replaceable(&unrelated_txid));
+        assert!(!mempool.is_replaceable(&[3; 32]));
+    }
+
+    #[test]
+    fn test_iter_by_ancestor_feerate_carries_parent_with_child() {
+        let mut mempool = Mempool::new();
+        let tx_spending = |hash: Hash| {
+            let mut tx = create_valid_transaction();
+            tx.inputs[0].prevout = OutPoint { hash, index: 0 };
+            tx
+        };
+
+        // All four transactions have the same vsize, so fees order the rates:
+        // child alone 10, independent 8, parent + child package 5.5, parent 1
+        let (parent, _) = mempool.add_transaction(tx_spending([1; 32]), vec![], 62);
+        let (child, _) = mempool.add_transaction(tx_spending(parent), vec![], 620);
+        let (high, _) = mempool.add_transaction(tx_spending([2; 32]), vec![], 496);
+        let (low, _) = mempool.add_transaction(tx_spending([3; 32]), vec![], 124);
+        assert!(mempool.entries().all(|entry| entry.vsize == 62));
+
+        // The child's rate cannot put it ahead of its parent; the package
+        // goes in as a unit after the independent transaction that beats it
+        let order: Vec<Hash> = mempool
+            .iter_by_ancestor_feerate()
+            .map(|entry| entry.txid)
+            .collect();
+        assert_eq!(order, vec![high, parent, child, low]);
+
+        // Once the child pays more than the independent one as a package,
+        // the parent comes first of all
+        mempool.remove(&child);
+        let (child, _) = mempool.add_transaction(tx_spending(parent), vec![], 1000);
+        let order: Vec<Hash> = mempool
+            .iter_by_ancestor_feerate()
+            .map(|entry| entry.txid)
+            .collect();
+        assert_eq!(order, vec![parent, child, high, low]);
+    }
+
     #[test]
     fn test_signals_rbf_false() {
         let tx = create_valid_transaction(); // sequence = 0xffffffff (final)
@@ -1534,28 +2825,28 @@ mod tests {
     #[test]
     fn test_is_standard_script_empty() {
         let script = vec![];
-        let result = is_standard_script(&script).unwrap();
+        let result = is_standard_script(&script, MAX_OP_RETURN_RELAY).unwrap();
         assert!(!result);
     }
 
     #[test]
     fn test_is_standard_script_too_large() {
         let script = vec![0x51; MAX_SCRIPT_SIZE + 1];
-        let result = is_standard_script(&script).unwrap();
+        let result = is_standard_script(&script, MAX_OP_RETURN_RELAY).unwrap();
         assert!(!result);
     }
 
     #[test]
     fn test_is_standard_script_non_standard_opcode() {
         let script = vec![0x65]; // Non-standard opcode
-        let result = is_standard_script(&script).unwrap();
+        let result = is_standard_script(&script, MAX_OP_RETURN_RELAY).unwrap();
         assert!(!result);
     }
 
     #[test]
     fn test_is_standard_script_valid() {
         let script = vec![0x51]; // OP_1
-        let result = is_standard_script(&script).unwrap();
+        let result = is_standard_script(&script, MAX_OP_RETURN_RELAY).unwrap();
         assert!(result);
     }
 
@@ -1618,6 +2909,120 @@ mod tests {
         assert!(!is_coinbase(&regular_tx));
     }
 
+    #[test]
+    fn test_mempool_lookup_by_txid_and_wtxid() {
+        let tx = create_valid_transaction();
+        let witnesses = vec![vec![vec![0x30; 71], vec![0x02; 33]]];
+        let mut mempool = Mempool::new();
+
+        let (txid, wtxid) = mempool.add_transaction(tx.clone(), witnesses.clone(), 0);
+
+        assert_eq!(txid, crate::block::calculate_tx_id(&tx));
+        assert_eq!(wtxid, crate::block::calculate_wtxid(&tx, &witnesses));
+        assert_ne!(
+            txid, wtxid,
+            "SegWit transaction must have distinct txid and wtxid"
+        );
+
+        assert!(mempool.contains(&txid));
+        assert!(mempool.contains_wtxid(&wtxid));
+        assert_eq!(mempool.get(&txid).unwrap().tx, tx);
+        assert_eq!(mempool.get_by_wtxid(&wtxid).unwrap().tx, tx);
+        assert_eq!(mempool.get_by_wtxid(&wtxid).unwrap().witnesses, witnesses);
+
+        // Re-submitting with a malleated witness keeps the txid but changes the wtxid
+        let malleated = vec![vec![vec![0x31; 71], vec![0x02; 33]]];
+        assert_eq!(
+            crate::block::calculate_tx_id(&tx),
+            txid,
+            "txid must not commit to witness data"
+        );
+        assert_ne!(crate::block::calculate_wtxid(&tx, &malleated), wtxid);
+
+        assert!(mempool.remove(&txid));
+        assert!(!mempool.contains(&txid));
+        assert!(!mempool.contains_wtxid(&wtxid));
+        assert!(mempool.is_empty());
+    }
+
+    #[test]
+    fn test_mempool_find_conflicts() {
+        let existing = create_valid_transaction();
+        let mut mempool = Mempool::new();
+        let (existing_txid, _) = mempool.add_transaction(existing.clone(), vec![], 0);
+
+        // Spends the same outpoint with a different output: conflicts
+        let mut conflicting = existing.clone();
+        conflicting.outputs[0].value -= 100;
+        assert_eq!(mempool.find_conflicts(&conflicting), vec![existing_txid]);
+
+        // Spending the same outpoint twice over still reports the entry once
+        conflicting.inputs.push(conflicting.inputs[0].clone());
+        assert_eq!(mempool.find_conflicts(&conflicting), vec![existing_txid]);
+
+        // Spends an unrelated outpoint: no conflict
+        let mut unrelated = existing.clone();
+        unrelated.inputs[0].prevout.index += 1;
+        assert!(mempool.find_conflicts(&unrelated).is_empty());
+
+        // Removing the entry clears its spent outpoints
+        assert!(mempool.remove(&existing_txid));
+        assert!(mempool.find_conflicts(&conflicting).is_empty());
+    }
+
+    #[test]
+    fn test_mempool_readd_replaces_wtxid() {
+        let tx = create_valid_transaction();
+        let mut mempool = Mempool::new();
+        let (txid, old_wtxid) = mempool.add_transaction(tx.clone(), vec![vec![vec![1]]], 0);
+
+        // Same txid with a malleated witness: only the new wtxid is indexed
+        let (readded_txid, new_wtxid) = mempool.add_transaction(tx, vec![vec![vec![2]]], 0);
+        assert_eq!(readded_txid, txid);
+        assert_ne!(new_wtxid, old_wtxid);
+        assert!(!mempool.contains_wtxid(&old_wtxid));
+        assert!(mempool.contains_wtxid(&new_wtxid));
+        assert_eq!(mempool.len(), 1);
+    }
+
+    #[test]
+    fn test_mempool_conflicting_add_evicts_previous_spender() {
+        let mut existing = create_valid_transaction();
+        let mut second_input = existing.inputs[0].clone();
+        second_input.prevout.index += 1;
+        existing.inputs.push(second_input.clone());
+        let mut mempool = Mempool::new();
+        let (existing_txid, existing_wtxid) = mempool.add_transaction(existing.clone(), vec![], 0);
+
+        // Shares only the first outpoint with the stored entry
+        let mut conflicting = existing.clone();
+        conflicting.inputs.truncate(1);
+        conflicting.outputs[0].value -= 100;
+        let (conflicting_txid, _) = mempool.add_transaction(conflicting, vec![], 0);
+
+        assert!(!mempool.contains(&existing_txid));
+        assert!(!mempool.contains_wtxid(&existing_wtxid));
+        assert!(mempool.get(&existing_txid).is_none());
+        assert!(mempool.contains(&conflicting_txid));
+        assert_eq!(mempool.len(), 1);
+
+        // The evicted entry's other outpoint is no longer indexed
+        let mut other = existing;
+        other.inputs = vec![second_input];
+        assert!(mempool.find_conflicts(&other).is_empty());
+    }
+
+    #[test]
+    fn test_mempool_wtxid_equals_txid_without_witness() {
+        let tx = create_valid_transaction();
+        let mut mempool = Mempool::new();
+
+        let (txid, wtxid) = mempool.add_transaction(tx, vec![Vec::new()], 0);
+
+        assert_eq!(txid, wtxid);
+        assert!(mempool.get_by_wtxid(&txid).is_some());
+    }
+
     // Helper functions for tests
     fn create_valid_transaction() -> Transaction {
         Transaction {
@@ -1688,6 +3093,7 @@ mod tests {
 mod kani_proofs_2 {
     use super::*;
     use crate::economic::calculate_fee;
+    use crate::transaction::check_transaction;
     use kani::*;
 
     /// Kani proof: RBF replacement rules (Orange Paper Section 9.3, BIP125)
@@ -1869,7 +3275,7 @@ mod kani_proofs_2 {
 
         if result.is_ok() {
             match result.unwrap() {
-                MempoolResult::Accepted => {
+                MempoolResult::Accepted { .. } => {
                     // If accepted, verify all acceptance rules are satisfied
 
                     // Rule 1: Transaction must be valid
@@ -0,0 +1,218 @@
+//! BIP37 partial merkle trees
+//!
+//! A `merkleblock` message proves that a set of transactions is committed to
+//! by a block header without sending the whole block. It carries the number
+//! of transactions in the block, a depth-first list of hashes and a bit
+//! vector of traversal flags. `parse_partial_merkle_tree` walks that tree,
+//! returning the merkle root it commits to (to be compared with the header's
+//! `merkle_root`) and the txids it marks as matched.
+//!
+//! Reference: Bitcoin Core `merkleblock.cpp` CPartialMerkleTree::ExtractMatches
+
+use crate::constants::MAX_BLOCK_TRANSACTIONS;
+use crate::crypto::hash256;
+use crate::error::{ConsensusError, Result};
+use crate::types::*;
+
+/// Parse a BIP37 partial merkle tree
+///
+/// `hashes` are in internal byte order and `flags` is the serialized bit
+/// vector (least significant bit first). Returns the computed merkle root and
+/// the matched txids in block order.
+///
+/// Rejects trees that do not consume every hash and every flag byte, trees
+/// with more hashes than transactions, and trees whose two children of a node
+/// are identical (CVE-2012-2459).
+pub fn parse_partial_merkle_tree(
+    total_tx: u32,
+    hashes: &[Hash],
+    flags: &[u8],
+) -> Result<(Hash, Vec<Hash>)> {
+    if total_tx == 0 {
+        return Err(invalid_tree("partial merkle tree has no transactions"));
+    }
+    if total_tx as usize > MAX_BLOCK_TRANSACTIONS {
+        return Err(invalid_tree(
+            "partial merkle tree has more transactions than fit in a block",
+        ));
+    }
+    if hashes.len() > total_tx as usize {
+        return Err(invalid_tree(
+            "partial merkle tree has more hashes than transactions",
+        ));
+    }
+    if flags.len() * 8 < hashes.len() {
+        return Err(invalid_tree(
+            "partial merkle tree has fewer flag bits than hashes",
+        ));
+    }
+
+    let mut tree = PartialMerkleTree {
+        total_tx,
+        hashes,
+        flags,
+        hashes_used: 0,
+        bits_used: 0,
+        matches: Vec::new(),
+    };
+    let root = tree.traverse(tree.height(), 0)?;
+
+    if tree.bits_used.div_ceil(8) != flags.len() {
+        return Err(invalid_tree("partial merkle tree has unused flag bytes"));
+    }
+    if tree.hashes_used != hashes.len() {
+        return Err(invalid_tree("partial merkle tree has unused hashes"));
+    }
+
+    Ok((root, tree.matches))
+}
+
+fn invalid_tree(message: &'static str) -> ConsensusError {
+    ConsensusError::BlockValidation(message.into())
+}
+
+struct PartialMerkleTree<'a> {
+    total_tx: u32,
+    hashes: &'a [Hash],
+    flags: &'a [u8],
+    hashes_used: usize,
+    bits_used: usize,
+    matches: Vec<Hash>,
+}
+
+impl PartialMerkleTree<'_> {
+    /// Number of nodes at `height` (leaves are height 0)
+    fn width(&self, height: u32) -> u32 {
+        (self.total_tx + (1 << height) - 1) >> height
+    }
+
+    /// Height of the root
+    fn height(&self) -> u32 {
+        let mut height = 0;
+        while self.width(height) > 1 {
+            height += 1;
+        }
+        height
+    }
+
+    fn next_flag(&mut self) -> Result<bool> {
+        let bit = self.bits_used;
+        if bit >= self.flags.len() * 8 {
+            return Err(invalid_tree("partial merkle tree ran out of flag bits"));
+        }
+        self.bits_used += 1;
+        Ok(self.flags[bit / 8] & (1 << (bit % 8)) != 0)
+    }
+
+    fn next_hash(&mut self) -> Result<Hash> {
+        let hash = *self
+            .hashes
+            .get(self.hashes_used)
+            .ok_or_else(|| invalid_tree("partial merkle tree ran out of hashes"))?;
+        self.hashes_used += 1;
+        Ok(hash)
+    }
+
+    /// Depth-first traversal of the node at `height`/`pos`, returning its hash
+    fn traverse(&mut self, height: u32, pos: u32) -> Result<Hash> {
+        let parent_of_match = self.next_flag()?;
+
+        if height == 0 || !parent_of_match {
+            // Leaf, or a subtree with no matches: the hash is given
+            let hash = self.next_hash()?;
+            if height == 0 && parent_of_match {
+                self.matches.push(hash);
+            }
+            return Ok(hash);
+        }
+
+        let left = self.traverse(height - 1, pos * 2)?;
+        let right = if pos * 2 + 1 < self.width(height - 1) {
+            let right = self.traverse(height - 1, pos * 2 + 1)?;
+            if right == left {
+                return Err(invalid_tree(
+                    "partial merkle tree has identical siblings (CVE-2012-2459)",
+                ));
+            }
+            right
+        } else {
+            left
+        };
+
+        let mut combined = [0u8; 64];
+        combined[..32].copy_from_slice(&left);
+        combined[32..].copy_from_slice(&right);
+        Ok(hash256(&combined))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Internal byte order of a hash given in display order
+    fn from_display_hex(hex_str: &str) -> Hash {
+        let mut hash: Hash = hex::decode(hex_str).unwrap().try_into().unwrap();
+        hash.reverse();
+        hash
+    }
+
+    #[test]
+    fn test_parse_partial_merkle_tree_block_170() {
+        // merkleblock for mainnet block 170 matching its second transaction
+        let coinbase =
+            from_display_hex("b1fea52486ce0c62bb442b530a3f0132b826c74e473d1f2c220bfa78111c5082");
+        let txid =
+            from_display_hex("f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16");
+        let merkle_root =
+            from_display_hex("7dac2c5666815c17a3b36427de37bb9d2e2c5ccec3f8633eb91a4205cb4c10ff");
+
+        // Flags 1 (root), 0 (coinbase, not matched), 1 (matched leaf)
+        let (root, matches) = parse_partial_merkle_tree(2, &[coinbase, txid], &[0x05]).unwrap();
+        assert_eq!(root, merkle_root);
+        assert_eq!(matches, vec![txid]);
+
+        // Nothing matched: the root hash alone proves nothing is included
+        let (root, matches) = parse_partial_merkle_tree(2, &[merkle_root], &[0x00]).unwrap();
+        assert_eq!(root, merkle_root);
+        assert!(matches.is_empty());
+    }
+
+    #[test]
+    fn test_parse_partial_merkle_tree_odd_width() {
+        // Three transactions; the last is paired with itself. Match tx 2.
+        let txids: Vec<Hash> = (1u8..=3).map(|i| [i; 32]).collect();
+        let pair = |a: &Hash, b: &Hash| {
+            let mut combined = [0u8; 64];
+            combined[..32].copy_from_slice(a);
+            combined[32..].copy_from_slice(b);
+            hash256(&combined)
+        };
+        let left = pair(&txids[0], &txids[1]);
+        let right = pair(&txids[2], &txids[2]);
+        let expected_root = pair(&left, &right);
+
+        // Flags: root 1, left subtree 0, right subtree 1, leaf 1 -> 0b1101
+        let (root, matches) = parse_partial_merkle_tree(3, &[left, txids[2]], &[0x0d]).unwrap();
+        assert_eq!(root, expected_root);
+        assert_eq!(matches, vec![txids[2]]);
+    }
+
+    #[test]
+    fn test_parse_partial_merkle_tree_rejects_malformed() {
+        let a = [0xaa; 32];
+        let b = [0xbb; 32];
+
+        assert!(parse_partial_merkle_tree(0, &[], &[]).is_err());
+        // More hashes than transactions
+        assert!(parse_partial_merkle_tree(1, &[a, b], &[0x01]).is_err());
+        // Unused hash
+        assert!(parse_partial_merkle_tree(2, &[a, b, a], &[0x05]).is_err());
+        // Unused flag byte
+        assert!(parse_partial_merkle_tree(2, &[a, b], &[0x05, 0x00]).is_err());
+        // Ran out of hashes
+        assert!(parse_partial_merkle_tree(2, &[a], &[0x07]).is_err());
+        // Identical siblings (CVE-2012-2459)
+        assert!(parse_partial_merkle_tree(2, &[a, a], &[0x07]).is_err());
+    }
+}
@@ -1,8 +1,10 @@
 //! Mining and block creation functions from Orange Paper Section 10.1
 
+use crate::crypto::hash256;
 use crate::economic::get_block_subsidy;
 use crate::error::Result;
 use crate::pow::get_next_work_required;
+use crate::serialization::block_hash;
 use crate::transaction::check_transaction;
 use crate::types::*;
 
@@ -39,7 +41,7 @@ pub fn create_new_block(
     // 2. Select transactions from mempool with proper validation
     // Use mempool validation to ensure transactions are valid and properly formatted
     let mut selected_txs = Vec::new();
-    let temp_mempool: Mempool = std::collections::HashSet::new(); // Temporary empty mempool for validation
+    let temp_mempool = Mempool::new(); // Temporary empty mempool for validation
 
     for tx in mempool_txs {
         // First check basic transaction structure
@@ -49,7 +51,7 @@ pub fn create_new_block(
 
         // Then validate through mempool acceptance (includes input validation, script verification, etc.)
         match accept_to_memory_pool(tx, None, utxo_set, &temp_mempool, height)? {
-            MempoolResult::Accepted => {
+            MempoolResult::Accepted { .. } => {
                 selected_txs.push(tx.clone());
             }
             MempoolResult::Rejected(_reason) => {
@@ -75,7 +77,7 @@ pub fn create_new_block(
     // 6. Create block header
     let header = BlockHeader {
         version: 1,
-        prev_block_hash: calculate_block_hash(prev_header),
+        prev_block_hash: block_hash(prev_header),
         merkle_root,
         timestamp: get_current_timestamp(),
         bits: next_work,
@@ -101,7 +103,7 @@ pub fn mine_block(mut block: Block, max_attempts: Natural) -> Result<(Block, Min
     for nonce in 0..max_attempts {
         block.header.nonce = nonce;
 
-        let block_hash = calculate_block_hash(&block.header);
+        let block_hash = block_hash(&block.header);
         let hash_u128 = u128::from_le_bytes(block_hash[..16].try_into().unwrap());
 
         if hash_u128 <= target {
@@ -177,10 +179,115 @@ pub fn create_block_template(
     })
 }
 
+/// Coinbase witness reserved value (BIP141)
+///
+/// The single 32-byte element carried in the coinbase witness of a SegWit block.
+pub const WITNESS_RESERVED_VALUE: Hash = [0u8; 32];
+
+/// BuildCoinbase: ℕ × ℤ × 𝒯* × ℍ? → 𝒯𝒳
+///
+/// Construct the coinbase transaction for a block at height h:
+/// 1. Single null-prevout input whose scriptSig starts with the BIP34 height push
+/// 2. Payout outputs, whose sum must not exceed value (subsidy + fees)
+/// 3. If a witness commitment is given: an OP_RETURN commitment output (BIP141)
+///
+/// The commitment output is appended after the payouts. Pair the returned
+/// transaction with `build_coinbase_witness()` when committing to witness data.
+pub fn build_coinbase(
+    height: Natural,
+    value: Integer,
+    outputs: &[TransactionOutput],
+    witness_commitment: Option<Hash>,
+) -> Result<Transaction> {
+    if outputs.is_empty() && witness_commitment.is_none() {
+        return Err(crate::error::ConsensusError::TransactionValidation(
+            "Coinbase must have at least one output".into(),
+        ));
+    }
+
+    let total_out = outputs.iter().try_fold(0i64, |acc, output| {
+        acc.checked_add(output.value).ok_or_else(|| {
+            crate::error::ConsensusError::TransactionValidation(
+                "Coinbase output value overflow".into(),
+            )
+        })
+    })?;
+    if total_out > value {
+        return Err(crate::error::ConsensusError::TransactionValidation(
+            format!("Coinbase outputs {total_out} exceed available value {value}").into(),
+        ));
+    }
+
+    // BIP34 height followed by OP_0 so the scriptSig always meets the 2-byte minimum
+    let mut script_sig = encode_bip34_height(height);
+    script_sig.push(0x00);
+
+    let coinbase_input = TransactionInput {
+        prevout: OutPoint {
+            hash: [0u8; 32],
+            index: 0xffffffff,
+        },
+        script_sig,
+        sequence: 0xffffffff,
+    };
+
+    let mut coinbase_tx = Transaction {
+        version: 1,
+        inputs: crate::tx_inputs![coinbase_input],
+        outputs: crate::tx_outputs![],
+        lock_time: 0,
+    };
+    coinbase_tx.outputs.extend(outputs.iter().cloned());
+    if let Some(commitment) = witness_commitment {
+        coinbase_tx.outputs.push(TransactionOutput {
+            value: 0,
+            script_pubkey: crate::segwit::create_witness_commitment_script(&commitment),
+        });
+    }
+
+    Ok(coinbase_tx)
+}
+
+/// Coinbase witness for a block that carries a witness commitment
+///
+/// Returns the witness stack containing only the witness reserved value.
+pub fn build_coinbase_witness() -> crate::segwit::Witness {
+    vec![WITNESS_RESERVED_VALUE.to_vec()]
+}
+
 // ============================================================================
 // HELPER FUNCTIONS
 // ============================================================================
 
+/// Encode a block height the way BIP34 expects it (`CScript() << nHeight`)
+///
+/// Heights 0-16 use OP_0/OP_1..OP_16; larger heights are a minimal
+/// little-endian CScriptNum push.
+fn encode_bip34_height(height: Natural) -> ByteString {
+    if height == 0 {
+        return vec![0x00];
+    }
+    if height <= 16 {
+        return vec![0x50 + height as u8];
+    }
+
+    let mut num = Vec::new();
+    let mut h = height;
+    while h > 0 {
+        num.push((h & 0xff) as u8);
+        h >>= 8;
+    }
+    // Heights are positive: add a sign byte if the top bit is set
+    if num.last().is_some_and(|b| b & 0x80 != 0) {
+        num.push(0x00);
+    }
+
+    let mut script = Vec::with_capacity(num.len() + 1);
+    script.push(num.len() as u8);
+    script.extend_from_slice(&num);
+    script
+}
+
 /// Result of mining attempt
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum MiningResult {
@@ -265,7 +372,7 @@ pub fn calculate_merkle_root(transactions: &[Transaction]) -> Result<Hash> {
     };
 
     #[cfg(not(feature = "production"))]
-    let mut hashes: Vec<Hash> = {
+    let hashes: Vec<Hash> = {
         // Sequential fallback for non-production builds
         let mut hashes = Vec::with_capacity(transactions.len());
         for tx in transactions {
@@ -328,7 +435,7 @@ pub fn calculate_merkle_root(transactions: &[Transaction]) -> Result<Hash> {
                             let mut combined = Vec::with_capacity(64);
                             combined.extend_from_slice(chunk[0].as_bytes());
                             combined.extend_from_slice(chunk[1].as_bytes());
-                            let hash = sha256_hash(&combined);
+                            let hash = hash256(&combined);
                             CacheAlignedHash::new(hash)
                         } else {
                             // Odd number: duplicate the last hash
@@ -343,7 +450,7 @@ pub fn calculate_merkle_root(transactions: &[Transaction]) -> Result<Hash> {
                             let mut combined = Vec::with_capacity(64);
                             combined.extend_from_slice(chunk[0].as_bytes());
                             combined.extend_from_slice(chunk[0].as_bytes());
-                            let hash = sha256_hash(&combined);
+                            let hash = hash256(&combined);
                             CacheAlignedHash::new(hash)
                         }
                     })
@@ -404,7 +511,7 @@ pub fn calculate_merkle_root(transactions: &[Transaction]) -> Result<Hash> {
                         let mut combined = Vec::with_capacity(64);
                         combined.extend_from_slice(chunk[0].as_bytes());
                         combined.extend_from_slice(chunk[1].as_bytes());
-                        let hash = sha256_hash(&combined);
+                        let hash = hash256(&combined);
                         next_level.push(CacheAlignedHash::new(hash));
                     } else {
                         // Odd number: duplicate the last hash
@@ -419,7 +526,7 @@ pub fn calculate_merkle_root(transactions: &[Transaction]) -> Result<Hash> {
                         let mut combined = Vec::with_capacity(64);
                         combined.extend_from_slice(chunk[0].as_bytes());
                         combined.extend_from_slice(chunk[0].as_bytes());
-                        let hash = sha256_hash(&combined);
+                        let hash = hash256(&combined);
                         next_level.push(CacheAlignedHash::new(hash));
                     }
                 }
@@ -450,84 +557,97 @@ pub fn calculate_merkle_root(transactions: &[Transaction]) -> Result<Hash> {
     // Note: Mutation detection is handled in both production and non-production paths above
 
     #[cfg(not(feature = "production"))]
-    {
-        let mut mutated = false;
+    calculate_merkle_root_from_hashes(hashes)
+}
 
-        while hashes.len() > 1 {
-            // CVE-2012-2459: Detect mutations (duplicate hashes at same level)
-            // Check for duplicate adjacent hashes BEFORE duplicating last hash for odd levels
-            for pos in (0..hashes.len().saturating_sub(1)).step_by(2) {
-                if hashes[pos] == hashes[pos + 1] {
-                    mutated = true;
-                }
-            }
+/// Build a Bitcoin merkle tree over precomputed leaf hashes and return its root
+///
+/// Shared by the transaction merkle root and the BIP141 witness merkle root.
+/// Odd levels duplicate their last hash; duplicate adjacent pairs are rejected
+/// as a CVE-2012-2459 mutation.
+pub(crate) fn calculate_merkle_root_from_hashes(mut hashes: Vec<Hash>) -> Result<Hash> {
+    if hashes.is_empty() {
+        return Err(crate::error::ConsensusError::InvalidProofOfWork(
+            "Cannot calculate merkle root for empty hash list".into(),
+        ));
+    }
 
-            // Duplicate last hash if odd number of hashes (Bitcoin's special rule)
-            if hashes.len() & 1 != 0 {
-                hashes.push(hashes[hashes.len() - 1]);
+    let mut mutated = false;
+
+    while hashes.len() > 1 {
+        // CVE-2012-2459: Detect mutations (duplicate hashes at same level)
+        // Check for duplicate adjacent hashes BEFORE duplicating last hash for odd levels
+        for pos in (0..hashes.len().saturating_sub(1)).step_by(2) {
+            if hashes[pos] == hashes[pos + 1] {
+                mutated = true;
             }
+        }
 
-            let mut next_level = Vec::with_capacity(hashes.len() / 2);
+        // Duplicate last hash if odd number of hashes (Bitcoin's special rule)
+        if hashes.len() & 1 != 0 {
+            hashes.push(hashes[hashes.len() - 1]);
+        }
+
+        let mut next_level = Vec::with_capacity(hashes.len() / 2);
+
+        // Process pairs of hashes sequentially
+        for chunk in hashes.chunks(2) {
+            // Runtime assertion: Chunk must have at least 1 element (chunks(2) guarantees this)
+            debug_assert!(
+                !chunk.is_empty(),
+                "Merkle tree chunk must have at least 1 element"
+            );
 
-            // Process pairs of hashes sequentially
-            for chunk in hashes.chunks(2) {
-                // Runtime assertion: Chunk must have at least 1 element (chunks(2) guarantees this)
+            if chunk.len() == 2 {
+                // Hash two hashes together
+                // BLLVM Optimization: Pre-allocate 64-byte buffer (2 * 32-byte hashes)
+                let mut combined = Vec::with_capacity(64);
+                combined.extend_from_slice(&chunk[0]);
+                combined.extend_from_slice(&chunk[1]);
+                next_level.push(hash256(&combined));
+            } else {
+                // Odd number: duplicate the last hash
+                // Runtime assertion: Chunk must have exactly 1 element
                 debug_assert!(
-                    !chunk.is_empty(),
-                    "Merkle tree chunk must have at least 1 element"
+                    chunk.len() == 1,
+                    "Odd-length chunk must have exactly 1 element, got {}",
+                    chunk.len()
                 );
 
-                if chunk.len() == 2 {
-                    // Hash two hashes together
-                    // BLLVM Optimization: Pre-allocate 64-byte buffer (2 * 32-byte hashes)
-                    let mut combined = Vec::with_capacity(64);
-                    combined.extend_from_slice(&chunk[0]);
-                    combined.extend_from_slice(&chunk[1]);
-                    next_level.push(sha256_hash(&combined));
-                } else {
-                    // Odd number: duplicate the last hash
-                    // Runtime assertion: Chunk must have exactly 1 element
-                    debug_assert!(
-                        chunk.len() == 1,
-                        "Odd-length chunk must have exactly 1 element, got {}",
-                        chunk.len()
-                    );
-
-                    // BLLVM Optimization: Pre-allocate 64-byte buffer
-                    let mut combined = Vec::with_capacity(64);
-                    combined.extend_from_slice(&chunk[0]);
-                    combined.extend_from_slice(&chunk[0]);
-                    next_level.push(sha256_hash(&combined));
-                }
+                // BLLVM Optimization: Pre-allocate 64-byte buffer
+                let mut combined = Vec::with_capacity(64);
+                combined.extend_from_slice(&chunk[0]);
+                combined.extend_from_slice(&chunk[0]);
+                next_level.push(hash256(&combined));
             }
-
-            hashes = next_level;
-        }
-
-        // If mutation was detected, treat as invalid (matches Core's behavior)
-        // Core treats mutated merkle roots as invalid to prevent CVE-2012-2459
-        if mutated {
-            return Err(crate::error::ConsensusError::InvalidProofOfWork(
-                "Merkle root mutation detected (CVE-2012-2459)".into(),
-            ));
         }
 
-        // Runtime assertion: Final result must have exactly 1 hash (the merkle root)
-        debug_assert!(
-            hashes.len() == 1,
-            "Merkle tree calculation must result in exactly 1 hash (root), got {}",
-            hashes.len()
-        );
-
-        // Runtime assertion: Merkle root must be 32 bytes
-        debug_assert!(
-            hashes[0].len() == 32,
-            "Merkle root hash must be 32 bytes, got {}",
-            hashes[0].len()
-        );
+        hashes = next_level;
+    }
 
-        Ok(hashes[0])
+    // If mutation was detected, treat as invalid (matches Core's behavior)
+    // Core treats mutated merkle roots as invalid to prevent CVE-2012-2459
+    if mutated {
+        return Err(crate::error::ConsensusError::InvalidProofOfWork(
+            "Merkle root mutation detected (CVE-2012-2459)".into(),
+        ));
     }
+
+    // Runtime assertion: Final result must have exactly 1 hash (the merkle root)
+    debug_assert!(
+        hashes.len() == 1,
+        "Merkle tree calculation must result in exactly 1 hash (root), got {}",
+        hashes.len()
+    );
+
+    // Runtime assertion: Merkle root must be 32 bytes
+    debug_assert!(
+        hashes[0].len() == 32,
+        "Merkle root hash must be 32 bytes, got {}",
+        hashes[0].len()
+    );
+
+    Ok(hashes[0])
 }
 
 /// Serialize transaction for hashing (used for batch hashing optimization)
@@ -615,31 +735,6 @@ fn encode_varint(value: u64) -> Vec<u8> {
     }
 }
 
-/// Calculate block hash using proper Bitcoin header serialization
-fn calculate_block_hash(header: &BlockHeader) -> Hash {
-    let mut data = Vec::new();
-
-    // Version (4 bytes, little-endian)
-    data.extend_from_slice(&(header.version as u32).to_le_bytes());
-
-    // Previous block hash (32 bytes)
-    data.extend_from_slice(&header.prev_block_hash);
-
-    // Merkle root (32 bytes)
-    data.extend_from_slice(&header.merkle_root);
-
-    // Timestamp (4 bytes, little-endian)
-    data.extend_from_slice(&(header.timestamp as u32).to_le_bytes());
-
-    // Bits (4 bytes, little-endian)
-    data.extend_from_slice(&(header.bits as u32).to_le_bytes());
-
-    // Nonce (4 bytes, little-endian)
-    data.extend_from_slice(&(header.nonce as u32).to_le_bytes());
-
-    sha256_hash(&data)
-}
-
 /// Simple SHA256 hash function
 ///
 /// Performance optimization: Uses OptimizedSha256 (SHA-NI or AVX2) instead of sha2 crate
@@ -795,6 +890,26 @@ mod tests {
         assert_ne!(merkle_root, [0u8; 32]);
     }
 
+    #[test]
+    fn test_merkle_root_mainnet_block_170() {
+        // Hashes are displayed byte-reversed
+        let internal = |display: &str| -> Hash {
+            let mut hash: Hash = hex::decode(display).unwrap().try_into().unwrap();
+            hash.reverse();
+            hash
+        };
+        let txids = vec![
+            internal("b1fea52486ce0c62bb442b530a3f0132b826c74e473d1f2c220bfa78111c5082"),
+            internal("f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16"),
+        ];
+
+        // Inner nodes are double SHA256
+        assert_eq!(
+            calculate_merkle_root_from_hashes(txids).unwrap(),
+            internal("7dac2c5666815c17a3b36427de37bb9d2e2c5ccec3f8633eb91a4205cb4c10ff")
+        );
+    }
+
     #[test]
     fn test_merkle_root_empty() {
         let txs = vec![];
@@ -953,13 +1068,13 @@ mod tests {
     #[test]
     fn test_calculate_block_hash() {
         let header = create_valid_block_header();
-        let hash = calculate_block_hash(&header);
+        let hash = block_hash(&header);
 
         // Should be a 32-byte hash
         assert_eq!(hash.len(), 32);
 
         // Same header should produce same hash
-        let hash2 = calculate_block_hash(&header);
+        let hash2 = block_hash(&header);
         assert_eq!(hash, hash2);
     }
 
@@ -969,8 +1084,8 @@ mod tests {
         let mut header2 = header1.clone();
         header2.version = 2; // Different version
 
-        let hash1 = calculate_block_hash(&header1);
-        let hash2 = calculate_block_hash(&header2);
+        let hash1 = block_hash(&header1);
+        let hash2 = block_hash(&header2);
 
         // Different headers should produce different hashes
         assert_ne!(hash1, hash2);
@@ -1285,6 +1400,62 @@ mod tests {
         assert_ne!(hash1, hash2);
     }
 
+    #[test]
+    fn test_build_coinbase_bip34_and_witness_commitment() {
+        use crate::bip_validation::check_bip34;
+        use crate::segwit::validate_witness_commitment;
+
+        let payout = TransactionOutput {
+            value: 312_500_000,
+            script_pubkey: vec![0x51],
+        };
+        let commitment = [0xab; 32];
+
+        for (height, network) in [
+            (840_000, Network::Mainnet),
+            (227_836, Network::Mainnet),
+            (5, Network::Regtest),
+            (128, Network::Regtest),
+        ] {
+            let coinbase =
+                build_coinbase(height, 312_500_000, &[payout.clone()], Some(commitment)).unwrap();
+
+            assert!(is_coinbase(&coinbase));
+            assert_eq!(
+                check_transaction(&coinbase).unwrap(),
+                ValidationResult::Valid
+            );
+            assert_eq!(coinbase.outputs.len(), 2);
+            assert_eq!(coinbase.outputs[0], payout);
+            assert_eq!(coinbase.outputs[1].value, 0);
+
+            let block = Block {
+                header: create_valid_block_header(),
+                transactions: vec![coinbase.clone()].into_boxed_slice(),
+            };
+            assert!(check_bip34(&block, height, network).unwrap());
+            assert!(!check_bip34(&block, height + 1, network).unwrap());
+
+            assert!(validate_witness_commitment(&coinbase, &commitment).unwrap());
+            assert!(!validate_witness_commitment(&coinbase, &[0xcd; 32]).unwrap());
+        }
+
+        assert_eq!(
+            build_coinbase_witness(),
+            vec![WITNESS_RESERVED_VALUE.to_vec()]
+        );
+    }
+
+    #[test]
+    fn test_build_coinbase_rejects_overpayment() {
+        let payout = TransactionOutput {
+            value: 5_000_000_001,
+            script_pubkey: vec![0x51],
+        };
+        assert!(build_coinbase(1, 5_000_000_000, &[payout], None).is_err());
+        assert!(build_coinbase(1, 5_000_000_000, &[], None).is_err());
+    }
+
     #[test]
     fn test_sha256_hash_empty_data() {
         let data = vec![];
@@ -1304,7 +1475,7 @@ mod kani_proofs {
     ///
     /// Mathematical specification:
     /// ∀ header ∈ BlockHeader:
-    /// - calculate_block_hash(header) = SHA256(SHA256(serialize_header(header)))
+    /// - block_hash(header) = SHA256(SHA256(serialize_header(header)))
     ///
     /// This ensures block hash calculation matches Bitcoin Core specification exactly.
     #[kani::proof]
@@ -1313,7 +1484,7 @@ mod kani_proofs {
 
         let header = crate::kani_helpers::create_bounded_block_header();
 
-        // Serialize header (same as in calculate_block_hash)
+        // Serialize header (same as in block_hash)
         let mut data = Vec::new();
         data.extend_from_slice(&(header.version as u32).to_le_bytes());
         data.extend_from_slice(&header.prev_block_hash);
@@ -1330,7 +1501,7 @@ mod kani_proofs {
         spec_hash.copy_from_slice(&hash2);
 
         // Calculate using implementation
-        let impl_hash = calculate_block_hash(&header);
+        let impl_hash = block_hash(&header);
 
         // Critical invariant: implementation must match specification
         assert_eq!(impl_hash, spec_hash,
@@ -1341,7 +1512,7 @@ mod kani_proofs {
     ///
     /// Mathematical specification:
     /// ∀ header ∈ BlockHeader:
-    /// - calculate_block_hash(header) is deterministic (same header → same hash)
+    /// - block_hash(header) is deterministic (same header → same hash)
     ///
     /// Optimization: Uses function stubbing to avoid expensive SHA256 computation.
     /// The property only requires same input → same output, not the actual hash value.
@@ -1351,8 +1522,8 @@ mod kani_proofs {
         let header = crate::kani_helpers::create_bounded_block_header();
 
         // Calculate hash twice
-        let hash1 = calculate_block_hash(&header);
-        let hash2 = calculate_block_hash(&header);
+        let hash1 = block_hash(&header);
+        let hash2 = block_hash(&header);
 
         // Critical invariant: same header must produce same hash
         assert_eq!(
@@ -1687,7 +1858,7 @@ mod kani_proofs {
             }
 
             // Critical invariant: prev_block_hash must match previous header hash
-            let prev_hash = calculate_block_hash(&prev_header);
+            let prev_hash = block_hash(&prev_header);
             assert_eq!(
                 block.header.prev_block_hash, prev_hash,
                 "CreateNewBlock: block prev_block_hash must match previous header hash"
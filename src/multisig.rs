@@ -0,0 +1,179 @@
+//! Bare and P2SH multisig spends
+//!
+//! `MultisigSpend` builds the scripts for an m-of-n multisig output and the
+//! scriptSig that spends it, for both bare (`OP_m <keys> OP_n OP_CHECKMULTISIG`
+//! as the scriptPubKey) and P2SH (the same script as the redeem script). The
+//! signed scriptCode is the multisig script itself. Spends are checked by the
+//! script interpreter, e.g. with [`crate::script::verify_input`].
+//!
+//! Only SIGHASH_ALL signatures are produced.
+
+use crate::error::{ConsensusError, Result};
+use crate::script::push_script;
+use crate::transaction_hash::{calculate_legacy_sighash, hash160, SighashType};
+use crate::types::*;
+
+const OP_0: u8 = 0x00;
+const OP_1: u8 = 0x51;
+const OP_EQUAL: u8 = 0x87;
+const OP_HASH160: u8 = 0xa9;
+const OP_CHECKMULTISIG: u8 = 0xae;
+
+/// SIGHASH_ALL, appended to every DER signature
+pub const SIGHASH_ALL: u8 = 0x01;
+
+/// Maximum number of keys in a standard multisig script
+const MAX_MULTISIG_KEYS: usize = 16;
+
+/// An m-of-n multisig policy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultisigSpend {
+    /// Number of signatures required (m)
+    pub required: u8,
+    /// Public keys (n), in script order
+    pub pubkeys: Vec<ByteString>,
+}
+
+impl MultisigSpend {
+    /// Create an m-of-n policy
+    ///
+    /// # Errors
+    ///
+    /// Returns `ConsensusError::ScriptExecution` unless 1 <= m <= n <= 16 and
+    /// every key is a 33- or 65-byte public key.
+    pub fn new(required: u8, pubkeys: Vec<ByteString>) -> Result<Self> {
+        if required == 0 || required as usize > pubkeys.len() {
+            return Err(ConsensusError::ScriptExecution(
+                format!(
+                    "Multisig requires 1 <= m <= n, got m = {required}, n = {}",
+                    pubkeys.len()
+                )
+                .into(),
+            ));
+        }
+        if pubkeys.len() > MAX_MULTISIG_KEYS {
+            return Err(ConsensusError::ScriptExecution(
+                format!("Multisig allows at most {MAX_MULTISIG_KEYS} keys").into(),
+            ));
+        }
+        if let Some(i) = pubkeys
+            .iter()
+            .position(|key| key.len() != 33 && key.len() != 65)
+        {
+            return Err(ConsensusError::ScriptExecution(
+                format!("Multisig key {i} is not a 33- or 65-byte public key").into(),
+            ));
+        }
+
+        Ok(Self { required, pubkeys })
+    }
+
+    /// `OP_m <pubkey>... OP_n OP_CHECKMULTISIG`
+    ///
+    /// The scriptPubKey of a bare multisig output, and the redeem script of a
+    /// P2SH one.
+    pub fn redeem_script(&self) -> ByteString {
+        let mut script = vec![OP_1 + self.required - 1];
+        for key in &self.pubkeys {
+            script.extend_from_slice(&push_script(key));
+        }
+        script.push(OP_1 + self.pubkeys.len() as u8 - 1);
+        script.push(OP_CHECKMULTISIG);
+        script
+    }
+
+    /// `OP_HASH160 <HASH160(redeem script)> OP_EQUAL`
+    pub fn p2sh_script_pubkey(&self) -> ByteString {
+        let mut script = vec![OP_HASH160, 20];
+        script.extend_from_slice(&hash160(&self.redeem_script()));
+        script.push(OP_EQUAL);
+        script
+    }
+
+    /// Legacy SIGHASH_ALL digest each signer signs for `input_index`
+    ///
+    /// The scriptCode is the multisig script for both bare and P2SH outputs.
+    pub fn signature_hash(
+        &self,
+        tx: &Transaction,
+        input_index: usize,
+        prevouts: &[PrevOut],
+    ) -> Result<Hash> {
+        calculate_legacy_sighash(
+            tx,
+            input_index,
+            prevouts,
+            &self.redeem_script(),
+            SighashType::All as u32,
+        )
+    }
+
+    /// `OP_0 <sig>...` spending a bare multisig output
+    ///
+    /// `signatures` are DER signatures with the sighash byte appended, in the
+    /// same order as the keys they sign for.
+    pub fn bare_script_sig(&self, signatures: &[ByteString]) -> ByteString {
+        let mut script = vec![OP_0];
+        for signature in signatures {
+            script.extend_from_slice(&push_script(signature));
+        }
+        script
+    }
+
+    /// `OP_0 <sig>... <redeem script>` spending a P2SH multisig output
+    pub fn p2sh_script_sig(&self, signatures: &[ByteString]) -> ByteString {
+        let mut script = self.bare_script_sig(signatures);
+        script.extend_from_slice(&push_script(&self.redeem_script()));
+        script
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script_type::{classify_script, parse_pushes, ScriptType};
+
+    fn keys() -> Vec<ByteString> {
+        vec![vec![0x02; 33], vec![0x03; 33], vec![0x02; 33]]
+    }
+
+    #[test]
+    fn test_multisig_spend_new_rejects_bad_policies() {
+        assert!(MultisigSpend::new(0, keys()).is_err());
+        assert!(MultisigSpend::new(4, keys()).is_err());
+        assert!(MultisigSpend::new(1, vec![vec![0x02; 32]]).is_err());
+        assert!(MultisigSpend::new(1, vec![vec![0x02; 33]; 17]).is_err());
+        assert!(MultisigSpend::new(2, keys()).is_ok());
+    }
+
+    #[test]
+    fn test_multisig_scripts_classify() {
+        let spend = MultisigSpend::new(2, keys()).unwrap();
+        assert_eq!(
+            classify_script(&spend.redeem_script()),
+            ScriptType::Multisig {
+                m: 2,
+                n: 3,
+                keys: keys(),
+            }
+        );
+        assert_eq!(
+            classify_script(&spend.p2sh_script_pubkey()),
+            ScriptType::P2SH(hash160(&spend.redeem_script()))
+        );
+
+        // The 105-byte redeem script needs OP_PUSHDATA1 in the scriptSig
+        let sigs = vec![vec![0x30; 72], vec![0x30; 71]];
+        let script_sig = spend.p2sh_script_sig(&sigs);
+        let pushes = parse_pushes(&script_sig).unwrap();
+        assert_eq!(
+            pushes,
+            vec![
+                vec![],
+                sigs[0].clone(),
+                sigs[1].clone(),
+                spend.redeem_script()
+            ]
+        );
+    }
+}
@@ -1,8 +1,16 @@
 //! Network protocol functions from Orange Paper Section 9.2
 
+use crate::block::verify_block;
+use crate::chain_params::ChainParams;
+use crate::crypto::muhash::MuHash3072;
 use crate::error::Result;
+use crate::reorganization::BlockUndoLog;
+use crate::segwit::Witness;
+use crate::serialization::block_hash;
+use crate::serialization::utxo::serialize_utxo_for_commitment;
 use crate::types::*;
-use std::collections::HashMap;
+use crate::validation_report::{block_report, block_spent_utxos, BlockValidationReport};
+use std::collections::{HashMap, HashSet};
 
 /// NetworkMessage: 𝒯𝒳 × 𝒰𝒮 → {accepted, rejected}
 ///
@@ -550,6 +558,8 @@ pub struct ChainState {
     pub transactions: HashMap<Hash, Transaction>,
     pub headers: HashMap<Hash, BlockHeader>,
     pub mempool: Vec<Transaction>,
+    /// Running MuHash of the UTXO set, updated on connect/disconnect
+    utxo_muhash: MuHash3072,
 }
 
 impl ChainState {
@@ -559,9 +569,103 @@ impl ChainState {
             transactions: HashMap::new(),
             headers: HashMap::new(),
             mempool: Vec::new(),
+            utxo_muhash: MuHash3072::new(),
         }
     }
 
+    /// Apply a connected block to the UTXO commitment
+    ///
+    /// Adds every output the block creates and removes every output it spends.
+    /// `spent` is the block's undo data: the UTXOs consumed by its inputs, as they
+    /// were in the UTXO set before the block. Outputs created and spent within the
+    /// same block never enter the set, so they are neither added nor removed.
+    pub fn connect_block(&mut self, block: &Block, height: Natural, spent: &[(OutPoint, UTXO)]) {
+        for (outpoint, utxo) in block_created_utxos(block, height) {
+            self.utxo_muhash
+                .insert(&serialize_utxo_for_commitment(&outpoint, &utxo));
+        }
+        for (outpoint, utxo) in spent {
+            self.utxo_muhash
+                .remove(&serialize_utxo_for_commitment(outpoint, utxo));
+        }
+    }
+
+    /// Revert a block previously applied with `connect_block`
+    ///
+    /// Takes the same `spent` undo data: the outputs the block spent are restored
+    /// and the outputs it created are removed.
+    pub fn disconnect_block(&mut self, block: &Block, height: Natural, spent: &[(OutPoint, UTXO)]) {
+        for (outpoint, utxo) in block_created_utxos(block, height) {
+            self.utxo_muhash
+                .remove(&serialize_utxo_for_commitment(&outpoint, &utxo));
+        }
+        for (outpoint, utxo) in spent {
+            self.utxo_muhash
+                .insert(&serialize_utxo_for_commitment(outpoint, utxo));
+        }
+    }
+
+    /// Validate a block and, if valid, connect it
+    ///
+    /// Runs `verify_block` with `params` at `height` against the part of
+    /// `utxo_set` the block touches: the outputs it spends, and any existing
+    /// outputs under its txids (for BIP30). On success the block and its
+    /// header are stored, the UTXO commitment is updated, the block's undo
+    /// log is applied to `utxo_set` and returned. On failure neither `self`
+    /// nor `utxo_set` is touched and the error is a `BlockValidationReport`
+    /// explaining why, built around `verify_block`'s verdict without
+    /// validating the block again. Either way the cost is in the size of the
+    /// block, not of `utxo_set`.
+    pub fn connect_validated_block(
+        &mut self,
+        block: &Block,
+        witnesses: &[Vec<Witness>],
+        utxo_set: &mut UtxoSet,
+        height: Natural,
+        params: &ChainParams,
+    ) -> std::result::Result<BlockUndoLog, BlockValidationReport> {
+        // Outputs the block spends, as they were before it: all the report and
+        // the commitment update need of the pre-block set
+        let spent = block_spent_utxos(block, utxo_set);
+        let rejected = |spent: UtxoSet, reason: String| {
+            let result = ValidationResult::Invalid(reason.clone());
+            block_report(block, witnesses, spent, height, params.network, result).unwrap_or_else(
+                |_| {
+                    let mut report = BlockValidationReport::new(height);
+                    report.reject_reason = Some(reason);
+                    report
+                },
+            )
+        };
+
+        let view = block_utxo_view(block, utxo_set, &spent);
+        let undo_log = match verify_block(block, witnesses, view, height, None, params) {
+            Ok((ValidationResult::Valid, _, undo_log)) => undo_log,
+            Ok((ValidationResult::Invalid(reason), _, _)) => return Err(rejected(spent, reason)),
+            Err(e) => return Err(rejected(spent, e.to_string())),
+        };
+
+        let spent: Vec<(OutPoint, UTXO)> = spent.into_iter().collect();
+        self.connect_block(block, height, &spent);
+
+        let hash = block_hash(&block.header);
+        self.headers.insert(hash, block.header.clone());
+        self.blocks.insert(hash, block.clone());
+        // Entries are most recent first: replay them oldest first
+        for entry in undo_log.entries.iter().rev() {
+            match &entry.new_utxo {
+                Some(utxo) => utxo_set.insert(entry.outpoint.clone(), utxo.clone()),
+                None => utxo_set.remove(&entry.outpoint),
+            };
+        }
+        Ok(undo_log)
+    }
+
+    /// MuHash commitment to the current UTXO set
+    pub fn utxo_commitment(&self) -> Hash {
+        self.utxo_muhash.finalize()
+    }
+
     pub fn has_object(&self, hash: &Hash) -> bool {
         self.blocks.contains_key(hash) || self.transactions.contains_key(hash)
     }
@@ -607,6 +711,62 @@ impl Default for ChainState {
     }
 }
 
+/// The entries of `utxo_set` that validating `block` reads
+///
+/// `spent` is the block's spent outputs from `block_spent_utxos`; to those
+/// are added any outputs already stored under the block's own txids, which
+/// BIP30 must see. `verify_block` against this view reaches the same verdict
+/// and undo log as against all of `utxo_set`.
+fn block_utxo_view(block: &Block, utxo_set: &UtxoSet, spent: &UtxoSet) -> UtxoSet {
+    let mut view = spent.clone();
+    for tx in block.transactions.iter() {
+        let txid = crate::block::calculate_tx_id(tx);
+        for index in 0..tx.outputs.len() {
+            let outpoint = OutPoint {
+                hash: txid,
+                index: index as Natural,
+            };
+            if let Some(utxo) = utxo_set.get(&outpoint) {
+                view.insert(outpoint, utxo.clone());
+            }
+        }
+    }
+    view
+}
+
+/// Outputs created by a block that are not spent within the same block
+fn block_created_utxos(block: &Block, height: Natural) -> Vec<(OutPoint, UTXO)> {
+    let spent_in_block: HashSet<&OutPoint> = block
+        .transactions
+        .iter()
+        .flat_map(|tx| tx.inputs.iter().map(|input| &input.prevout))
+        .collect();
+
+    let mut created = Vec::new();
+    for (i, tx) in block.transactions.iter().enumerate() {
+        let txid = crate::block::calculate_tx_id(tx);
+        for (index, output) in tx.outputs.iter().enumerate() {
+            let outpoint = OutPoint {
+                hash: txid,
+                index: index as Natural,
+            };
+            if spent_in_block.contains(&outpoint) {
+                continue;
+            }
+            created.push((
+                outpoint,
+                UTXO {
+                    value: output.value,
+                    script_pubkey: output.script_pubkey.clone(),
+                    height,
+                    is_coinbase: i == 0,
+                },
+            ));
+        }
+    }
+    created
+}
+
 /// Chain object (block or transaction)
 #[derive(Debug, Clone)]
 pub enum ChainObject {
@@ -1222,4 +1382,87 @@ mod tests {
         // The current implementation accepts any pong message
         assert!(matches!(response, NetworkResponse::Ok));
     }
+
+    #[test]
+    fn test_chain_state_utxo_commitment_connect_disconnect() {
+        fn coinbase(tag: u8) -> Transaction {
+            Transaction {
+                version: 1,
+                inputs: crate::tx_inputs![TransactionInput {
+                    prevout: OutPoint {
+                        hash: [0; 32],
+                        index: 0xffffffff,
+                    },
+                    script_sig: vec![0x01, tag],
+                    sequence: 0xffffffff,
+                }],
+                outputs: crate::tx_outputs![TransactionOutput {
+                    value: 5_000_000_000,
+                    script_pubkey: vec![0x51],
+                }],
+                lock_time: 0,
+            }
+        }
+        fn block(transactions: Vec<Transaction>) -> Block {
+            Block {
+                header: BlockHeader {
+                    version: 1,
+                    prev_block_hash: [0; 32],
+                    merkle_root: [0; 32],
+                    timestamp: 1231006505,
+                    bits: 0x1d00ffff,
+                    nonce: 0,
+                },
+                transactions: transactions.into_boxed_slice(),
+            }
+        }
+
+        let mut chain_state = ChainState::new();
+        let empty = chain_state.utxo_commitment();
+
+        // Block 1: coinbase only
+        let coinbase1 = coinbase(1);
+        let block1 = block(vec![coinbase1.clone()]);
+        chain_state.connect_block(&block1, 1, &[]);
+        let after_block1 = chain_state.utxo_commitment();
+        assert_ne!(after_block1, empty);
+
+        // Block 2: spends block 1's coinbase output
+        let spent_outpoint = OutPoint {
+            hash: crate::block::calculate_tx_id(&coinbase1),
+            index: 0,
+        };
+        let spent = vec![(
+            spent_outpoint.clone(),
+            UTXO {
+                value: 5_000_000_000,
+                script_pubkey: vec![0x51],
+                height: 1,
+                is_coinbase: true,
+            },
+        )];
+        let spend = Transaction {
+            version: 1,
+            inputs: crate::tx_inputs![TransactionInput {
+                prevout: spent_outpoint,
+                script_sig: vec![0x51],
+                sequence: 0xffffffff,
+            }],
+            outputs: crate::tx_outputs![TransactionOutput {
+                value: 4_000_000_000,
+                script_pubkey: vec![0x52],
+            }],
+            lock_time: 0,
+        };
+        let block2 = block(vec![coinbase(2), spend]);
+
+        chain_state.connect_block(&block2, 2, &spent);
+        assert_ne!(chain_state.utxo_commitment(), after_block1);
+
+        chain_state.disconnect_block(&block2, 2, &spent);
+        assert_eq!(chain_state.utxo_commitment(), after_block1);
+
+        chain_state.disconnect_block(&block1, 1, &[]);
+        assert_eq!(chain_state.utxo_commitment(), empty);
+    }
 }
@@ -1,5 +1,6 @@
 //! Proof of Work functions from Orange Paper Section 8 Section 7
 
+use crate::chain_params::ChainParams;
 use crate::constants::*;
 use crate::error::{ConsensusError, Result};
 use crate::types::*;
@@ -54,6 +55,109 @@ pub fn get_next_work_required_corrected(
     get_next_work_required_internal(_current_header, prev_headers, true)
 }
 
+/// Whether `height` is a difficulty retarget boundary
+///
+/// True for every multiple of `params.difficulty_adjustment_interval` except
+/// the genesis block, which starts the first period without retargeting.
+pub fn is_retarget_height(height: Natural, params: &ChainParams) -> bool {
+    height != 0 && height % params.difficulty_adjustment_interval == 0
+}
+
+/// First height of the retarget period containing `height`
+pub fn retarget_period_start(height: Natural, params: &ChainParams) -> Natural {
+    height - height % params.difficulty_adjustment_interval
+}
+
+/// CheckBlockDifficulty: ℋ × ℋ* × ℕ × Network → {valid, invalid}
+///
+/// Check that a block's `bits` is the difficulty the chain requires at `height`:
+/// - At a retarget boundary (height % 2016 = 0): the value computed by
+///   `get_next_work_required` over the last 2016 headers of `prev_headers`
+/// - Otherwise: the previous block's `bits`, unchanged
+///
+/// Network rules as in Core's chain params:
+/// - Regtest never retargets (`fPowNoRetargeting`)
+/// - Testnet allows a minimum-difficulty block if its timestamp is more than
+///   20 minutes after the previous block; otherwise the expected bits are those
+///   of the last block in the period that was not a minimum-difficulty block
+///
+/// `prev_headers` ends with the block at `height - 1`.
+///
+/// # Errors
+///
+/// Returns `ConsensusError::InvalidProofOfWork` if the bits differ from the
+/// expected value, or if `prev_headers` is too short to compute it.
+pub fn check_block_difficulty(
+    header: &BlockHeader,
+    prev_headers: &[BlockHeader],
+    height: Natural,
+    network: Network,
+) -> Result<()> {
+    let prev = prev_headers.last().ok_or_else(|| {
+        ConsensusError::InvalidProofOfWork("No previous header for difficulty check".into())
+    })?;
+
+    let expected_bits = if !is_retarget_height(height, &ChainParams::for_network(network))
+        || network == Network::Regtest
+    {
+        if network == Network::Testnet {
+            testnet_expected_bits(header, prev_headers, height)
+        } else {
+            prev.bits
+        }
+    } else {
+        let interval = DIFFICULTY_ADJUSTMENT_INTERVAL as usize;
+        if prev_headers.len() < interval {
+            return Err(ConsensusError::InvalidProofOfWork(
+                format!(
+                    "Retarget at height {height} needs {interval} previous headers, got {}",
+                    prev_headers.len()
+                )
+                .into(),
+            ));
+        }
+        get_next_work_required(header, &prev_headers[prev_headers.len() - interval..])?
+    };
+
+    if header.bits != expected_bits {
+        return Err(ConsensusError::InvalidProofOfWork(
+            format!(
+                "Incorrect difficulty bits {:#010x} at height {height}, expected {:#010x}",
+                header.bits, expected_bits
+            )
+            .into(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Expected bits for a non-retarget testnet block (Core's fPowAllowMinDifficultyBlocks)
+fn testnet_expected_bits(
+    header: &BlockHeader,
+    prev_headers: &[BlockHeader],
+    height: Natural,
+) -> Natural {
+    let pow_limit = MAX_TARGET as Natural;
+    let prev = &prev_headers[prev_headers.len() - 1];
+
+    // More than 2 * 10 minutes since the previous block: minimum difficulty allowed
+    if header.timestamp > prev.timestamp + TARGET_TIME_PER_BLOCK * 2 {
+        return pow_limit;
+    }
+
+    // Otherwise return the bits of the last block that was not a
+    // minimum-difficulty block, stopping at the start of the period
+    let mut prev_height = height - 1;
+    for block in prev_headers.iter().rev() {
+        if prev_height % DIFFICULTY_ADJUSTMENT_INTERVAL == 0 || block.bits != pow_limit {
+            return block.bits;
+        }
+        prev_height -= 1;
+    }
+    prev_headers[0].bits
+}
+
 /// Internal implementation of difficulty adjustment
 ///
 /// `use_corrected`: If true, fixes the off-by-one error by adjusting expected_time
@@ -490,6 +594,58 @@ impl U256 {
     fn is_zero(&self) -> bool {
         self.0.iter().all(|&x| x == 0)
     }
+
+    /// Bitwise complement (Core's `operator~`)
+    fn not(&self) -> Self {
+        U256([!self.0[0], !self.0[1], !self.0[2], !self.0[3]])
+    }
+
+    /// Add one, wrapping on overflow
+    fn wrapping_add_one(&self) -> Self {
+        let mut result = self.clone();
+        for word in result.0.iter_mut() {
+            let (sum, carry) = word.overflowing_add(1);
+            *word = sum;
+            if !carry {
+                break;
+            }
+        }
+        result
+    }
+
+    /// Subtract, wrapping on underflow
+    fn wrapping_sub(&self, rhs: &Self) -> Self {
+        let mut result = U256::zero();
+        let mut borrow = false;
+        for i in 0..4 {
+            let (diff, b1) = self.0[i].overflowing_sub(rhs.0[i]);
+            let (diff, b2) = diff.overflowing_sub(borrow as u64);
+            result.0[i] = diff;
+            borrow = b1 || b2;
+        }
+        result
+    }
+
+    /// Divide by a U256 (binary long division)
+    ///
+    /// Division by zero returns max value, as in `div_u64`.
+    fn div(&self, rhs: &Self) -> Self {
+        if rhs.is_zero() {
+            return U256([u64::MAX; 4]);
+        }
+
+        let mut quotient = U256::zero();
+        let mut remainder = U256::zero();
+        for bit in (0..256).rev() {
+            remainder = remainder.shl(1);
+            remainder.0[0] |= (self.0[bit / 64] >> (bit % 64)) & 1;
+            if remainder >= *rhs {
+                remainder = remainder.wrapping_sub(rhs);
+                quotient.0[bit / 64] |= 1 << (bit % 64);
+            }
+        }
+        quotient
+    }
 }
 
 impl PartialOrd for U256 {
@@ -552,10 +708,14 @@ pub fn expand_target(bits: Natural) -> Result<U256> {
     // }
 
     let exponent = (bits >> 24) as u8;
-    // Core uses 0x007fffff (23 bits), but we need to handle the full 24-bit mantissa
-    // The sign bit (0x00800000) is handled separately in Core, but for expansion
-    // we use the full mantissa including the sign bit
-    let mantissa = bits & 0x00ffffff;
+    // Core uses a 23-bit mantissa; bit 0x00800000 is the sign bit
+    let mantissa = bits & 0x007fffff;
+
+    // Core: fNegative = nWord != 0 && (nCompact & 0x00800000) != 0
+    // A negative target can never be met, so it is invalid for proof of work
+    if mantissa != 0 && (bits & 0x00800000) != 0 {
+        return Err(ConsensusError::InvalidProofOfWork("Negative target".into()));
+    }
 
     // Validate target format (Core allows nSize up to 34, but we clamp to 32 for safety)
     if !(3..=32).contains(&exponent) {
@@ -589,16 +749,59 @@ pub fn expand_target(bits: Natural) -> Result<U256> {
         // When exponent = 4: shift left by 8 bits
         // When exponent = 29: shift left by 208 bits
         let shift = 8u32 * (exponent as u32 - 3);
-        if shift >= 256 {
-            return Err(crate::error::ConsensusError::InvalidProofOfWork(
-                "Target too large".into(),
-            ));
-        }
         let mantissa_u256 = U256::from_u32(mantissa as u32);
         Ok(mantissa_u256.shl(shift))
     }
 }
 
+/// Block proof: bits → ℕ
+///
+/// Expected number of hashes needed to meet the target, 2^256 / (target + 1),
+/// as in Bitcoin Core's `GetBlockProof()`. Core computes it as
+/// `~target / (target + 1) + 1`, since 2^256 does not fit in 256 bits.
+///
+/// Chain work is the sum of the proofs of its blocks. Proofs above `u128::MAX`
+/// (targets below 2^128, far beyond any real difficulty) saturate.
+pub fn get_block_proof(bits: Natural) -> Result<u128> {
+    let target = expand_target(bits)?;
+    if target.is_zero() {
+        return Ok(0);
+    }
+
+    let proof = target
+        .not()
+        .div(&target.wrapping_add_one())
+        .wrapping_add_one();
+    if proof.0[2] != 0 || proof.0[3] != 0 {
+        return Ok(u128::MAX);
+    }
+    Ok(((proof.0[1] as u128) << 64) | proof.0[0] as u128)
+}
+
+/// Difficulty: bits → ℝ
+///
+/// Human-readable difficulty relative to difficulty 1 (bits 0x1d00ffff):
+/// difficulty = Target(0x1d00ffff) / Target(bits).
+///
+/// Matches Bitcoin Core's `GetDifficulty()`: the mantissa ratio is scaled by
+/// 256 per exponent step away from 29, in floating point. A zero mantissa
+/// yields infinity.
+pub fn difficulty_from_bits(bits: u32) -> f64 {
+    let mut shift = (bits >> 24) & 0xff;
+    let mut difficulty = 0x0000ffff as f64 / (bits & 0x00ffffff) as f64;
+
+    while shift < 29 {
+        difficulty *= 256.0;
+        shift += 1;
+    }
+    while shift > 29 {
+        difficulty /= 256.0;
+        shift -= 1;
+    }
+
+    difficulty
+}
+
 /// Compress target to compact representation
 ///
 /// Reverse of expand_target: converts U256 target back to compact bits format.
@@ -1616,6 +1819,134 @@ mod tests {
         assert!(result.is_err());
     }
 
+    fn header_chain(count: usize, bits: Natural, spacing: Natural) -> Vec<BlockHeader> {
+        (0..count)
+            .map(|i| BlockHeader {
+                version: 1,
+                prev_block_hash: [0; 32],
+                merkle_root: [0; 32],
+                timestamp: 1_231_006_505 + i as Natural * spacing,
+                bits,
+                nonce: 0,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_retarget_boundaries() {
+        let params = ChainParams::mainnet();
+        assert!(!is_retarget_height(0, &params));
+        assert!(!is_retarget_height(2015, &params));
+        assert!(is_retarget_height(2016, &params));
+        assert!(is_retarget_height(4032, &params));
+
+        assert_eq!(retarget_period_start(0, &params), 0);
+        assert_eq!(retarget_period_start(2015, &params), 0);
+        assert_eq!(retarget_period_start(2016, &params), 2016);
+        assert_eq!(retarget_period_start(4032, &params), 4032);
+        assert_eq!(retarget_period_start(4031, &params), 2016);
+    }
+
+    #[test]
+    fn test_check_block_difficulty_non_retarget() {
+        let prev_headers = header_chain(10, 0x1b04864c, TARGET_TIME_PER_BLOCK);
+        let mut header = prev_headers[9].clone();
+        header.timestamp += TARGET_TIME_PER_BLOCK;
+
+        // Mid-period block keeps the previous bits
+        assert!(check_block_difficulty(&header, &prev_headers, 100_005, Network::Mainnet).is_ok());
+
+        // Tampered bits (easier target) are rejected
+        header.bits = 0x1b04864d;
+        assert!(check_block_difficulty(&header, &prev_headers, 100_005, Network::Mainnet).is_err());
+    }
+
+    #[test]
+    fn test_check_block_difficulty_retarget_boundary() {
+        // Period mined in half the expected time: difficulty must go up
+        let interval = DIFFICULTY_ADJUSTMENT_INTERVAL as usize;
+        let prev_headers = header_chain(interval, 0x1c0ffff0, TARGET_TIME_PER_BLOCK / 2);
+        let mut header = prev_headers[interval - 1].clone();
+        header.timestamp += TARGET_TIME_PER_BLOCK;
+
+        let expected = get_next_work_required(&header, &prev_headers).unwrap();
+        assert_ne!(expected, 0x1c0ffff0);
+
+        // Claiming the old difficulty at the boundary is rejected
+        assert!(check_block_difficulty(&header, &prev_headers, 4032, Network::Mainnet).is_err());
+        header.bits = expected;
+        assert!(check_block_difficulty(&header, &prev_headers, 4032, Network::Mainnet).is_ok());
+
+        // Not enough history to compute the retarget
+        assert!(
+            check_block_difficulty(&header, &prev_headers[1..], 4032, Network::Mainnet).is_err()
+        );
+    }
+
+    #[test]
+    fn test_check_block_difficulty_testnet_min_difficulty() {
+        let prev_headers = header_chain(10, 0x1c0ffff0, TARGET_TIME_PER_BLOCK);
+        let mut header = prev_headers[9].clone();
+
+        // 21 minutes after the previous block: minimum difficulty is allowed
+        header.timestamp += 21 * 60;
+        header.bits = MAX_TARGET as Natural;
+        assert!(check_block_difficulty(&header, &prev_headers, 5, Network::Testnet).is_ok());
+
+        // On time: minimum difficulty is not allowed
+        header.timestamp = prev_headers[9].timestamp + TARGET_TIME_PER_BLOCK;
+        assert!(check_block_difficulty(&header, &prev_headers, 5, Network::Testnet).is_err());
+        header.bits = 0x1c0ffff0;
+        assert!(check_block_difficulty(&header, &prev_headers, 5, Network::Testnet).is_ok());
+    }
+
+    #[test]
+    fn test_difficulty_from_bits() {
+        // Genesis block: difficulty 1
+        assert_eq!(difficulty_from_bits(0x1d00ffff), 1.0);
+
+        // Block 100000 (bits 0x1b04864c): difficulty ~14484.16
+        let difficulty = difficulty_from_bits(0x1b04864c);
+        assert!(difficulty > 1.0);
+        assert!((difficulty - 14484.1623).abs() < 0.001);
+
+        // Easier than difficulty 1 (larger target)
+        assert!(difficulty_from_bits(0x1d01fffe) < 1.0);
+    }
+
+    #[test]
+    fn test_get_block_proof() {
+        // Genesis block: Core reports chainwork 0x100010001
+        assert_eq!(get_block_proof(0x1d00ffff).unwrap(), 0x100010001);
+
+        // A target 256 times smaller takes about 256 times the work
+        let harder = get_block_proof(0x1c00ffff).unwrap();
+        assert_eq!(harder / 0x100010001, 256);
+
+        // Zero target contributes no work
+        assert_eq!(get_block_proof(0x1d000000).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_expand_target_sign_bit() {
+        // Sign bit with non-zero mantissa is a negative target
+        assert!(expand_target(0x1d80ffff).is_err());
+        assert!(expand_target(0x04923456).is_err());
+        // Sign bit with zero mantissa is just zero, as in Core
+        assert!(expand_target(0x1d800000).unwrap().is_zero());
+        // Largest positive mantissa is still accepted
+        assert!(!expand_target(0x1d7fffff).unwrap().is_zero());
+    }
+
+    #[test]
+    fn test_expand_target_exponent_boundary() {
+        // Exponent 29 is the largest accepted, with any positive mantissa
+        assert!(!expand_target(0x1d7fffff).unwrap().is_zero());
+        assert!(expand_target(0x1e000001).is_err());
+        // A zero mantissa expands to zero within the accepted range
+        assert!(expand_target(0x1d000000).unwrap().is_zero());
+    }
+
     #[test]
     fn test_expand_target_exponent_3() {
         let result = expand_target(0x0300ffff).unwrap();
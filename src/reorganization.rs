@@ -3,8 +3,9 @@
 use crate::block::connect_block;
 use crate::error::Result;
 use crate::segwit::Witness;
+use crate::serialization::block_hash;
 use crate::types::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 /// Reorganization: When a longer chain is found (simplified API)
 ///
@@ -17,10 +18,8 @@ pub fn reorganize_chain(
     current_height: Natural,
 ) -> Result<ReorganizationResult> {
     // Create empty witnesses for all blocks (simplified)
-    let empty_witnesses: Vec<Vec<Witness>> = new_chain
-        .iter()
-        .map(|block| block.transactions.iter().map(|_| Vec::new()).collect())
-        .collect();
+    let empty_witnesses: Vec<Vec<Vec<Witness>>> =
+        new_chain.iter().map(empty_block_witnesses).collect();
 
     reorganize_chain_with_witnesses(
         new_chain,
@@ -29,13 +28,22 @@ pub fn reorganize_chain(
         current_chain,
         current_utxo_set,
         current_height,
-        None::<fn(&Block) -> Option<Vec<Witness>>>, // No witness retrieval
+        None::<fn(&Block) -> Option<Vec<Vec<Witness>>>>, // No witness retrieval
         None::<fn(Natural) -> Option<Vec<BlockHeader>>>, // No header retrieval
-        None::<fn(&Hash) -> Option<BlockUndoLog>>,  // No undo log retrieval
-        None::<fn(&Hash, &BlockUndoLog) -> Result<()>>, // No undo log storage
+        None::<fn(&Hash) -> Option<BlockUndoLog>>,       // No undo log retrieval
+        None::<fn(&Hash, &BlockUndoLog) -> Result<()>>,  // No undo log storage
     )
 }
 
+/// Empty witness stacks for every input of every transaction in `block`
+fn empty_block_witnesses(block: &Block) -> Vec<Vec<Witness>> {
+    block
+        .transactions
+        .iter()
+        .map(|tx| vec![Vec::new(); tx.inputs.len()])
+        .collect()
+}
+
 /// Reorganization: When a longer chain is found (full API with witness support)
 ///
 /// For new chain with blocks [b1, b2, ..., bn] and current chain with blocks [c1, c2, ..., cm]:
@@ -47,7 +55,7 @@ pub fn reorganize_chain(
 /// # Arguments
 ///
 /// * `new_chain` - Blocks from the new (longer) chain
-/// * `new_chain_witnesses` - Witness data for each block in new_chain (one Vec<Witness> per block)
+/// * `new_chain_witnesses` - Witness data for each block in new_chain (per transaction, one witness stack per input)
 /// * `new_chain_headers` - Recent headers for median time-past calculation (last 11+ headers, oldest to newest)
 /// * `current_chain` - Blocks from the current chain
 /// * `current_utxo_set` - Current UTXO set
@@ -59,12 +67,12 @@ pub fn reorganize_chain(
 #[allow(clippy::too_many_arguments)]
 pub fn reorganize_chain_with_witnesses(
     new_chain: &[Block],
-    new_chain_witnesses: &[Vec<Witness>],
+    new_chain_witnesses: &[Vec<Vec<Witness>>],
     new_chain_headers: Option<&[BlockHeader]>,
     current_chain: &[Block],
     current_utxo_set: UtxoSet,
     current_height: Natural,
-    _get_witnesses_for_block: Option<impl Fn(&Block) -> Option<Vec<Witness>>>,
+    _get_witnesses_for_block: Option<impl Fn(&Block) -> Option<Vec<Vec<Witness>>>>,
     _get_headers_for_height: Option<impl Fn(Natural) -> Option<Vec<BlockHeader>>>,
     get_undo_log_for_block: Option<impl Fn(&Hash) -> Option<BlockUndoLog>>,
     store_undo_log_for_block: Option<impl Fn(&Hash, &BlockUndoLog) -> Result<()>>,
@@ -79,10 +87,11 @@ pub fn reorganize_chain_with_witnesses(
     let mut utxo_set = current_utxo_set;
     let disconnect_start = 0; // Simplified: disconnect from start
     let mut disconnected_undo_logs: HashMap<Hash, BlockUndoLog> = HashMap::new();
+    let mut utxo_changes = UtxoChanges::default();
 
     for i in (disconnect_start..current_chain.len()).rev() {
         if let Some(block) = current_chain.get(i) {
-            let block_hash = calculate_block_hash(&block.header);
+            let block_hash = block_hash(&block.header);
 
             // Retrieve undo log from persistent storage via callback
             // The callback should use BlockStore::get_undo_log() which reads from the database
@@ -100,6 +109,10 @@ pub fn reorganize_chain_with_witnesses(
             };
 
             utxo_set = disconnect_block(block, &undo_log, utxo_set, (i as Natural) + 1)?;
+            // Disconnecting reverses each entry, most recent first
+            for entry in &undo_log.entries {
+                utxo_changes.record(&entry.outpoint, &entry.new_utxo, &entry.previous_utxo);
+            }
             disconnected_undo_logs.insert(block_hash, undo_log);
         }
     }
@@ -127,7 +140,7 @@ pub fn reorganize_chain_with_witnesses(
         let witnesses = new_chain_witnesses
             .get(i)
             .cloned()
-            .unwrap_or_else(|| block.transactions.iter().map(|_| Vec::new()).collect());
+            .unwrap_or_else(|| empty_block_witnesses(block));
 
         // Get recent headers for median time-past (if available)
         // For the first block in new chain, use provided headers
@@ -151,7 +164,7 @@ pub fn reorganize_chain_with_witnesses(
         }
 
         // Store undo log for this block (keyed by block hash for future retrieval)
-        let block_hash = calculate_block_hash(&block.header);
+        let block_hash = block_hash(&block.header);
 
         // Persist undo log to database via callback (required for future reorganizations)
         if let Some(ref store_undo_log) = store_undo_log_for_block {
@@ -162,6 +175,11 @@ pub fn reorganize_chain_with_witnesses(
             }
         }
 
+        // Entries are most recent first; replay them in the order applied
+        for entry in undo_log.entries.iter().rev() {
+            utxo_changes.record(&entry.outpoint, &entry.previous_utxo, &entry.new_utxo);
+        }
+
         // Also store in-memory for the reorganization result
         connected_undo_logs.insert(block_hash, undo_log);
 
@@ -178,6 +196,7 @@ pub fn reorganize_chain_with_witnesses(
         connected_blocks,
         reorganization_depth: current_chain.len(),
         connected_block_undo_logs: connected_undo_logs,
+        utxo_delta: utxo_changes.into_delta(),
     })
 }
 
@@ -227,7 +246,7 @@ pub fn reorganize_chain_with_witnesses(
 ///     &current_chain,
 ///     current_utxo_set,
 ///     current_height,
-///     None::<fn(&Block) -> Option<Vec<Witness>>>,
+///     None::<fn(&Block) -> Option<Vec<Vec<Witness>>>>,
 ///     None::<fn(Natural) -> Option<Vec<BlockHeader>>>,
 /// );
 /// if let Ok(reorg_result) = reorg_result {
@@ -434,7 +453,14 @@ fn calculate_chain_work(chain: &[Block]) -> Result<u128> {
 /// Expand target from compact format (reused from mining module)
 fn expand_target(bits: Natural) -> Result<u128> {
     let exponent = (bits >> 24) as u8;
-    let mantissa = bits & 0x00ffffff;
+    let mantissa = bits & 0x007fffff;
+
+    // Sign bit set with a non-zero mantissa is a negative target
+    if mantissa != 0 && (bits & 0x00800000) != 0 {
+        return Err(crate::error::ConsensusError::InvalidProofOfWork(
+            "Negative target".into(),
+        ));
+    }
 
     if exponent <= 3 {
         let shift = 8 * (3 - exponent);
@@ -447,14 +473,18 @@ fn expand_target(bits: Natural) -> Result<u128> {
                 "Target too large".into(),
             ));
         }
-        // Calculate shift safely - exponent is bounded, so no overflow
-        let shift = 8 * (exponent - 3);
-        // Use checked shift to avoid overflow
-        let mantissa_u128 = mantissa as u128;
-        let expanded = mantissa_u128.checked_shl(shift as u32).ok_or_else(|| {
-            crate::error::ConsensusError::InvalidProofOfWork("Target expansion overflow".into())
-        })?;
-        Ok(expanded)
+        let shift = 8 * (exponent as u32 - 3);
+        // Reject if any significant mantissa bit would be shifted out of 128 bits
+        if mantissa == 0 {
+            return Ok(0);
+        }
+        let mantissa_bits = 64 - mantissa.leading_zeros();
+        if mantissa_bits + shift > 128 {
+            return Err(crate::error::ConsensusError::InvalidProofOfWork(
+                "Target expansion overflow".into(),
+            ));
+        }
+        Ok((mantissa as u128) << shift)
     }
 }
 
@@ -469,31 +499,6 @@ fn calculate_tx_id(tx: &Transaction) -> Hash {
     hash
 }
 
-/// Calculate block hash for indexing undo logs
-///
-/// Uses the block header to compute a unique identifier for the block.
-/// This is used to store and retrieve undo logs during reorganization.
-fn calculate_block_hash(header: &BlockHeader) -> Hash {
-    use sha2::{Digest, Sha256};
-
-    // Serialize block header (80 bytes: version, prev_block_hash, merkle_root, timestamp, bits, nonce)
-    let mut bytes = Vec::with_capacity(80);
-    bytes.extend_from_slice(&header.version.to_le_bytes());
-    bytes.extend_from_slice(&header.prev_block_hash);
-    bytes.extend_from_slice(&header.merkle_root);
-    bytes.extend_from_slice(&header.timestamp.to_le_bytes());
-    bytes.extend_from_slice(&header.bits.to_le_bytes());
-    bytes.extend_from_slice(&header.nonce.to_le_bytes());
-
-    // Double SHA256 (Bitcoin standard)
-    let first_hash = Sha256::digest(&bytes);
-    let second_hash = Sha256::digest(first_hash);
-
-    let mut hash = [0u8; 32];
-    hash.copy_from_slice(&second_hash);
-    hash
-}
-
 // ============================================================================
 // TYPES
 // ============================================================================
@@ -563,6 +568,71 @@ pub struct ReorganizationResult {
     /// Undo logs for connected blocks (keyed by block hash)
     /// These can be used for future disconnections
     pub connected_block_undo_logs: HashMap<Hash, BlockUndoLog>,
+    /// Net UTXO set change across all disconnected and connected blocks
+    pub utxo_delta: UtxoDelta,
+}
+
+/// Net change between two UTXO sets
+///
+/// Applying the delta to the set before a reorganization gives the set after
+/// it. Outputs removed and recreated unchanged within the reorganization do
+/// not appear, so a persistence layer writes only what differs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UtxoDelta {
+    /// Outputs that are new or changed afterwards, with their final state
+    pub created: HashMap<OutPoint, UTXO>,
+    /// Outputs present before that no longer exist afterwards
+    pub removed: HashSet<OutPoint>,
+}
+
+impl UtxoDelta {
+    /// Check if the delta changes nothing
+    pub fn is_empty(&self) -> bool {
+        self.created.is_empty() && self.removed.is_empty()
+    }
+
+    /// Apply the delta to a UTXO set
+    pub fn apply(&self, utxo_set: &mut UtxoSet) {
+        for outpoint in &self.removed {
+            utxo_set.remove(outpoint);
+        }
+        for (outpoint, utxo) in &self.created {
+            utxo_set.insert(outpoint.clone(), utxo.clone());
+        }
+    }
+}
+
+/// Each touched outpoint's state before the first change and after the last
+#[derive(Default)]
+struct UtxoChanges {
+    states: HashMap<OutPoint, (Option<UTXO>, Option<UTXO>)>,
+}
+
+impl UtxoChanges {
+    fn record(&mut self, outpoint: &OutPoint, before: &Option<UTXO>, after: &Option<UTXO>) {
+        self.states
+            .entry(outpoint.clone())
+            .or_insert_with(|| (before.clone(), None))
+            .1 = after.clone();
+    }
+
+    fn into_delta(self) -> UtxoDelta {
+        let mut delta = UtxoDelta::default();
+        for (outpoint, (original, current)) in self.states {
+            if original == current {
+                continue;
+            }
+            match current {
+                Some(utxo) => {
+                    delta.created.insert(outpoint, utxo);
+                }
+                None => {
+                    delta.removed.insert(outpoint);
+                }
+            }
+        }
+        delta
+    }
 }
 
 // ============================================================================
@@ -1000,7 +1070,11 @@ mod tests {
         utxo_set.insert(outpoint.clone(), utxo.clone());
 
         // Connect block and get undo log
-        let witnesses: Vec<Witness> = block.transactions.iter().map(|_| Vec::new()).collect();
+        let witnesses: Vec<Vec<Witness>> = block
+            .transactions
+            .iter()
+            .map(|tx| vec![Vec::new(); tx.inputs.len()])
+            .collect();
         let (result, new_utxo_set, undo_log) = connect_block(
             &block,
             &witnesses,
@@ -1020,7 +1094,7 @@ mod tests {
         );
 
         // Calculate block hash
-        let block_hash = calculate_block_hash(&block.header);
+        let block_hash = block_hash(&block.header);
 
         // Store undo log in a map (simulating persistent storage)
         let mut undo_log_storage: HashMap<Hash, BlockUndoLog> = HashMap::new();
@@ -1055,7 +1129,11 @@ mod tests {
         // Create a block and connect it to get undo log
         let block = create_test_block();
         let mut utxo_set = UtxoSet::new();
-        let witnesses: Vec<Witness> = block.transactions.iter().map(|_| Vec::new()).collect();
+        let witnesses: Vec<Vec<Witness>> = block
+            .transactions
+            .iter()
+            .map(|tx| vec![Vec::new(); tx.inputs.len()])
+            .collect();
 
         let (result, connected_utxo_set, undo_log) = connect_block(
             &block,
@@ -1073,7 +1151,7 @@ mod tests {
         assert!(matches!(result, crate::types::ValidationResult::Valid));
 
         // Store undo log
-        let block_hash = calculate_block_hash(&block.header);
+        let block_hash = block_hash(&block.header);
         let mut undo_log_storage: HashMap<Hash, BlockUndoLog> = HashMap::new();
         undo_log_storage.insert(block_hash, undo_log);
 
@@ -1084,10 +1162,8 @@ mod tests {
         // Reorganize with undo log callback
         let new_chain = vec![create_test_block()];
         let current_chain = vec![block];
-        let empty_witnesses: Vec<Vec<Witness>> = new_chain
-            .iter()
-            .map(|b| b.transactions.iter().map(|_| Vec::new()).collect())
-            .collect();
+        let empty_witnesses: Vec<Vec<Vec<Witness>>> =
+            new_chain.iter().map(empty_block_witnesses).collect();
 
         let reorg_result = reorganize_chain_with_witnesses(
             &new_chain,
@@ -1096,7 +1172,7 @@ mod tests {
             &current_chain,
             connected_utxo_set,
             1,
-            None::<fn(&Block) -> Option<Vec<Witness>>>,
+            None::<fn(&Block) -> Option<Vec<Vec<Witness>>>>,
             None::<fn(Natural) -> Option<Vec<BlockHeader>>>,
             Some(get_undo_log),
             None::<fn(&Hash, &BlockUndoLog) -> Result<()>>, // No storage in test
@@ -1114,6 +1190,95 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_reorganize_reports_net_utxo_delta() {
+        use crate::block::connect_block;
+        use crate::mining::calculate_merkle_root;
+        use crate::segwit::Witness;
+
+        // Blocks whose coinbases differ only in their scriptSig
+        let block_with_tag = |tag: u8| {
+            let mut block = create_test_block();
+            let mut coinbase = block.transactions[0].clone();
+            coinbase.inputs[0].script_sig = vec![0x00, tag];
+            block.header.merkle_root =
+                calculate_merkle_root(std::slice::from_ref(&coinbase)).unwrap();
+            block.transactions = vec![coinbase].into_boxed_slice();
+            block
+        };
+        let coinbase_outpoint = |block: &Block| OutPoint {
+            hash: crate::block::calculate_tx_id(&block.transactions[0]),
+            index: 0,
+        };
+
+        // An unrelated output that the reorganization leaves alone
+        let untouched = OutPoint {
+            hash: [7; 32],
+            index: 0,
+        };
+        let mut utxo_set = UtxoSet::new();
+        utxo_set.insert(
+            untouched.clone(),
+            UTXO {
+                value: 1000,
+                script_pubkey: vec![0x51],
+                height: 0,
+                is_coinbase: false,
+            },
+        );
+
+        let old_block = block_with_tag(1);
+        let witnesses: Vec<Vec<Witness>> = vec![vec![Vec::new()]];
+        let (result, connected_utxo_set, undo_log) = connect_block(
+            &old_block,
+            &witnesses,
+            utxo_set,
+            1,
+            None,
+            crate::types::Network::Mainnet,
+        )
+        .unwrap();
+        assert!(matches!(result, ValidationResult::Valid));
+        let old_hash = block_hash(&old_block.header);
+
+        // Replace the one block with two
+        let new_chain = vec![block_with_tag(2), block_with_tag(3)];
+        let new_witnesses: Vec<Vec<Vec<Witness>>> = vec![vec![vec![Vec::new()]]; 2];
+        let reorg_result = reorganize_chain_with_witnesses(
+            &new_chain,
+            &new_witnesses,
+            None,
+            std::slice::from_ref(&old_block),
+            connected_utxo_set.clone(),
+            1,
+            None::<fn(&Block) -> Option<Vec<Vec<Witness>>>>,
+            None::<fn(Natural) -> Option<Vec<BlockHeader>>>,
+            Some(|hash: &Hash| (*hash == old_hash).then(|| undo_log.clone())),
+            None::<fn(&Hash, &BlockUndoLog) -> Result<()>>,
+        )
+        .unwrap();
+
+        let delta = &reorg_result.utxo_delta;
+        assert_eq!(
+            delta.removed,
+            HashSet::from([coinbase_outpoint(&old_block)])
+        );
+        assert_eq!(delta.created.len(), 2);
+        for block in &new_chain {
+            let outpoint = coinbase_outpoint(block);
+            assert_eq!(
+                delta.created.get(&outpoint),
+                reorg_result.new_utxo_set.get(&outpoint)
+            );
+        }
+        assert!(!delta.created.contains_key(&untouched));
+
+        // The delta turns the old set into the new one
+        let mut applied = connected_utxo_set;
+        delta.apply(&mut applied);
+        assert_eq!(applied, reorg_result.new_utxo_set);
+    }
+
     #[test]
     fn test_reorganize_chain_empty_new_chain() {
         let new_chain = vec![];
@@ -1183,12 +1348,22 @@ mod tests {
         assert!(result.is_ok());
 
         // Test maximum valid target
-        let result = expand_target(0x03ffffff);
+        let result = expand_target(0x037fffff);
         assert!(result.is_ok());
 
         // Test invalid target (too large) - use exponent > 19
         let result = expand_target(0x14000000); // exponent = 20, which should fail (> 19)
         assert!(result.is_err());
+
+        // Sign bit with non-zero mantissa is negative
+        assert!(expand_target(0x0380ffff).is_err());
+
+        // Exponent 19 shifts by 128 bits: only a zero mantissa fits
+        assert_eq!(expand_target(0x13000000).unwrap(), 0);
+        assert!(expand_target(0x13000001).is_err());
+        // Exponent 18 shifts by 120 bits: mantissa must fit in 8 bits
+        assert_eq!(expand_target(0x120000ff).unwrap(), 0xffu128 << 120);
+        assert!(expand_target(0x12000100).is_err());
     }
 
     #[test]
@@ -1297,6 +1472,7 @@ mod tests {
             connected_blocks: connected_blocks.clone(),
             reorganization_depth: 0,
             connected_block_undo_logs: std::collections::HashMap::new(),
+            utxo_delta: UtxoDelta::default(),
         };
 
         // Add some transactions to mempool (including ones that will be in connected blocks)
@@ -8,15 +8,18 @@
 //! - Memory allocation optimizations
 
 use crate::constants::*;
+use crate::crypto::signature::{default_verifier, SignatureVerifier};
 use crate::error::{ConsensusError, Result};
+use crate::script_num::{ScriptNum, DEFAULT_MAX_NUM_SIZE};
+use crate::script_type::{classify_script, parse_pushes, ScriptType};
+use crate::transaction_hash::SighashCache;
 use crate::types::*;
 use ripemd::Ripemd160;
-use secp256k1::{ecdsa::Signature, Context, Message, PublicKey, Secp256k1, Verification};
+use secp256k1::ecdsa::Signature;
 use sha2::{Digest, Sha256};
 
 // Cold error construction helpers - these paths are rarely taken
 #[cold]
-#[allow(dead_code)]
 fn make_operation_limit_error() -> ConsensusError {
     ConsensusError::ScriptExecution("Operation limit exceeded".into())
 }
@@ -26,6 +29,24 @@ fn make_stack_overflow_error() -> ConsensusError {
     ConsensusError::ScriptExecution("Stack overflow".into())
 }
 
+/// SCRIPT_ERR_PUBKEY_COUNT: CHECKMULTISIG key count out of range
+#[cold]
+fn make_pubkey_count_error() -> ConsensusError {
+    ConsensusError::ScriptExecution("Pubkey count negative or limit exceeded".into())
+}
+
+/// SCRIPT_ERR_EQUALVERIFY: OP_EQUALVERIFY on unequal operands
+#[cold]
+fn make_equalverify_error() -> ConsensusError {
+    ConsensusError::ScriptExecution("Script failed an OP_EQUALVERIFY operation".into())
+}
+
+/// SCRIPT_ERR_BAD_OPCODE: push data runs past the end of the script
+#[cold]
+fn make_truncated_push_error() -> ConsensusError {
+    ConsensusError::ScriptExecution("Push data runs past the end of the script".into())
+}
+
 #[cfg(feature = "production")]
 use smallvec::SmallVec;
 
@@ -39,17 +60,6 @@ use std::sync::{
 #[cfg(feature = "production")]
 use std::thread_local;
 
-/// Thread-local Secp256k1 context for signature verification
-/// Reference: Orange Paper Section 13.1 - Performance Considerations
-///
-/// Secp256k1 context is stateless and thread-safe for verification-only operations.
-/// Reusing a single context avoids the overhead of creating new contexts on every
-/// signature verification (major performance bottleneck identified in analysis).
-#[cfg(feature = "production")]
-thread_local! {
-    static SECP256K1_CONTEXT: Secp256k1<secp256k1::All> = Secp256k1::new();
-}
-
 /// Script verification result cache (production feature only)
 ///
 /// Caches scriptPubKey verification results to avoid re-execution of identical scripts.
@@ -238,7 +248,7 @@ pub fn eval_script(script: &ByteString, stack: &mut Vec<ByteString>, flags: u32)
     }
     #[cfg(not(feature = "production"))]
     {
-        eval_script_inner(script, stack, flags)
+        eval_script_inner(script, stack, flags, u64::MAX)
     }
 }
 #[cfg(feature = "production")]
@@ -249,7 +259,7 @@ fn eval_script_impl(script: &ByteString, stack: &mut Vec<ByteString>, flags: u32
     // still provides stack allocation benefits for the initial allocation
     let small_stack: SmallVec<[ByteString; 8]> = SmallVec::from_vec(std::mem::take(stack));
     let mut vec_stack = small_stack.into_vec();
-    let result = eval_script_inner(script, &mut vec_stack, flags);
+    let result = eval_script_inner(script, &mut vec_stack, flags, u64::MAX);
     *stack = vec_stack;
     result
 }
@@ -257,18 +267,140 @@ fn eval_script_impl(script: &ByteString, stack: &mut Vec<ByteString>, flags: u32
 #[cfg(not(feature = "production"))]
 #[allow(dead_code)]
 fn eval_script_impl(script: &ByteString, stack: &mut Vec<ByteString>, flags: u32) -> Result<bool> {
-    eval_script_inner(script, stack, flags)
+    eval_script_inner(script, stack, flags, u64::MAX)
+}
+
+/// Execution cost of a signature check (OP_CHECKSIG, OP_CHECKSIGVERIFY)
+pub const SCRIPT_COST_SIGCHECK: u64 = 50;
+
+/// Execution cost of a hash opcode (OP_RIPEMD160 .. OP_HASH256)
+pub const SCRIPT_COST_HASH: u64 = 5;
+
+/// Execution cost of any other opcode
+pub const SCRIPT_COST_BASE: u64 = 1;
+
+/// Weighted execution cost of a single opcode
+///
+/// Multisig is charged as the maximum number of signature checks it may perform.
+#[inline]
+pub fn opcode_cost(opcode: u8) -> u64 {
+    match opcode {
+        // OP_CHECKSIG, OP_CHECKSIGVERIFY
+        0xac | 0xad => SCRIPT_COST_SIGCHECK,
+        // OP_CHECKMULTISIG, OP_CHECKMULTISIGVERIFY (up to 20 public keys)
+        0xae | 0xaf => SCRIPT_COST_SIGCHECK * 20,
+        // OP_RIPEMD160, OP_SHA1, OP_SHA256, OP_HASH160, OP_HASH256
+        0xa6..=0xaa => SCRIPT_COST_HASH,
+        _ => SCRIPT_COST_BASE,
+    }
+}
+
+/// EvalScriptBudgeted: 𝒮𝒞 × 𝒮𝒯 × ℕ × ℕ → {true, false}
+///
+/// Same as `eval_script`, with an additional cap on total weighted execution
+/// cost (see `opcode_cost`). The budget is checked before each opcode runs, so
+/// an expensive opcode that would exceed it is never executed.
+///
+/// Intended for DoS-sensitive pre-checks on untrusted input; consensus
+/// validation uses `eval_script`, which is equivalent to an unbounded budget.
+pub fn eval_script_budgeted(
+    script: &ByteString,
+    stack: &mut Vec<ByteString>,
+    flags: u32,
+    max_cost: u64,
+) -> Result<bool> {
+    if stack.capacity() < 20 {
+        stack.reserve(20);
+    }
+    eval_script_inner(script, stack, flags, max_cost)
+}
+
+fn eval_script_inner(
+    script: &ByteString,
+    stack: &mut Vec<ByteString>,
+    flags: u32,
+    max_cost: u64,
+) -> Result<bool> {
+    if !run_script(script, stack, flags, max_cost)? {
+        return Ok(false);
+    }
+
+    // Final stack check: exactly one non-zero value
+    // Optimization: Use bounds-optimized access in production
+    #[cfg(feature = "production")]
+    {
+        use crate::optimizations::kani_optimized_access::get_proven_by_kani;
+        if let Some(first_item) = get_proven_by_kani(stack, 0) {
+            if let Some(first_byte) = get_proven_by_kani(first_item, 0) {
+                Ok(stack.len() == 1 && !first_item.is_empty() && *first_byte != 0)
+            } else {
+                Ok(false)
+            }
+        } else {
+            Ok(false)
+        }
+    }
+
+    #[cfg(not(feature = "production"))]
+    {
+        Ok(stack.len() == 1 && !stack[0].is_empty() && stack[0][0] != 0)
+    }
 }
 
-fn eval_script_inner(script: &ByteString, stack: &mut Vec<ByteString>, flags: u32) -> Result<bool> {
+/// Run a script on `stack` without judging the resulting stack
+///
+/// Returns whether the script ran to completion, as
+/// `eval_script_with_context_full` does; `verify_script` runs scriptSig,
+/// scriptPubKey and redeem script on one stack in turn.
+fn run_script(
+    script: &ByteString,
+    stack: &mut Vec<ByteString>,
+    flags: u32,
+    max_cost: u64,
+) -> Result<bool> {
     let mut op_count = 0;
+    let mut cost: u64 = 0;
+    let mut altstack: Vec<ByteString> = Vec::new();
+    let mut exec_stack: Vec<bool> = Vec::new();
+
+    let mut pc = 0;
+    while pc < script.len() {
+        let (opcode, push, next) = read_instruction(script, pc)?;
+        pc = next;
+
+        // Check operation limit: pushes and OP_1NEGATE..OP_16 are not counted
+        if opcode > 0x60 {
+            op_count += 1;
+            if op_count > MAX_SCRIPT_OPS {
+                return Err(ConsensusError::ScriptExecution(
+                    "Operation limit exceeded".into(),
+                ));
+            }
+        }
 
-    for opcode in script {
-        // Check operation limit
-        op_count += 1;
-        if op_count > MAX_SCRIPT_OPS {
+        // Disabled opcodes fail the script even inside a branch not taken
+        if is_disabled_opcode(opcode) {
+            return Ok(false);
+        }
+
+        // Inside a branch not taken, only track conditional nesting
+        if !is_executing(&exec_stack) && !is_conditional_opcode(opcode) {
+            continue;
+        }
+
+        // CHECKMULTISIG counts each of its public keys as an operation
+        if matches!(opcode, 0xae | 0xaf) {
+            op_count += multisig_key_count(stack, flags)?;
+            if op_count > MAX_SCRIPT_OPS {
+                return Err(make_operation_limit_error());
+            }
+        }
+
+        // Check execution cost budget
+        cost = cost.saturating_add(opcode_cost(opcode));
+        if cost > max_cost {
             return Err(ConsensusError::ScriptExecution(
-                "Operation limit exceeded".into(),
+                "Script cost budget exceeded".into(),
             ));
         }
 
@@ -278,10 +410,8 @@ fn eval_script_inner(script: &ByteString, stack: &mut Vec<ByteString>, flags: u3
             "Operation count ({op_count}) must not exceed MAX_SCRIPT_OPS ({MAX_SCRIPT_OPS})"
         );
 
-        // Check stack size
-        if stack.len() > MAX_STACK_SIZE {
-            return Err(make_stack_overflow_error());
-        }
+        // Check stack size of the incoming stack
+        check_stack_size(stack, &altstack)?;
 
         // Runtime assertion: Stack size must be within bounds
         debug_assert!(
@@ -292,10 +422,25 @@ fn eval_script_inner(script: &ByteString, stack: &mut Vec<ByteString>, flags: u3
         );
 
         // Execute opcode
-        if !execute_opcode(*opcode, stack, flags)? {
+        let success = match opcode {
+            0x01..=0x4e => {
+                stack.push(push.to_vec());
+                true
+            }
+            // Bare and P2SH scripts: MINIMALIF does not apply
+            0x63 | 0x64 | 0x67 | 0x68 => {
+                execute_conditional_opcode(opcode, stack, &mut exec_stack, false)
+            }
+            0x6b | 0x6c => execute_altstack_opcode(opcode, stack, &mut altstack)?,
+            _ => execute_opcode(opcode, stack, flags)?,
+        };
+        if !success {
             return Ok(false);
         }
 
+        // Check stack size after every opcode
+        check_stack_size(stack, &altstack)?;
+
         // Runtime assertion: Stack size must remain within bounds after opcode execution
         debug_assert!(
             stack.len() <= MAX_STACK_SIZE,
@@ -305,35 +450,19 @@ fn eval_script_inner(script: &ByteString, stack: &mut Vec<ByteString>, flags: u3
         );
     }
 
-    // Final stack check: exactly one non-zero value
-    // Optimization: Use bounds-optimized access in production
-    #[cfg(feature = "production")]
-    {
-        use crate::optimizations::kani_optimized_access::get_proven_by_kani;
-        if let Some(first_item) = get_proven_by_kani(stack, 0) {
-            if let Some(first_byte) = get_proven_by_kani(first_item, 0) {
-                Ok(stack.len() == 1 && !first_item.is_empty() && *first_byte != 0)
-            } else {
-                Ok(false)
-            }
-        } else {
-            Ok(false)
-        }
-    }
-
-    #[cfg(not(feature = "production"))]
-    {
-        Ok(stack.len() == 1 && !stack[0].is_empty() && stack[0][0] != 0)
-    }
+    // Every OP_IF/OP_NOTIF must be closed by an OP_ENDIF
+    Ok(exec_stack.is_empty())
 }
 
 /// VerifyScript: 𝒮𝒞 × 𝒮𝒞 × 𝒲 × ℕ → {true, false}
 ///
 /// For scriptSig ss, scriptPubKey spk, witness w, and flags f:
 /// 1. Execute ss on empty stack
-/// 2. Execute spk on resulting stack
-/// 3. If witness present: execute w on stack
-/// 4. Return final stack has exactly one true value
+/// 2. Execute spk on resulting stack, which must leave a true value on top
+/// 3. If f has SCRIPT_VERIFY_P2SH and spk is P2SH: ss must be push-only; its
+///    last push is the redeem script, executed on the rest of ss's stack
+/// 4. If witness present: execute w on stack
+/// 5. Return final stack has exactly one true value
 ///
 /// Performance: Pre-allocates stack capacity, caches verification results in production mode
 #[cfg_attr(feature = "production", inline(always))]
@@ -347,62 +476,27 @@ pub fn verify_script(
     #[cfg(feature = "production")]
     {
         // Check cache first (unless disabled for benchmarking)
+        let cache_key = compute_script_cache_key(script_sig, script_pubkey, witness, flags);
         if !is_caching_disabled() {
-            let cache_key = compute_script_cache_key(script_sig, script_pubkey, witness, flags);
-            {
-                let cache = get_script_cache().read().unwrap();
-                if let Some(&cached_result) = cache.peek(&cache_key) {
-                    return Ok(cached_result);
-                }
+            let cache = get_script_cache().read().unwrap();
+            if let Some(&cached_result) = cache.peek(&cache_key) {
+                return Ok(cached_result);
             }
         }
 
         // Execute script (cache miss)
         // Use pooled stack to avoid allocation
         let mut stack = get_pooled_stack();
-        let cache_key = compute_script_cache_key(script_sig, script_pubkey, witness, flags);
-        let result = {
-            if !eval_script(script_sig, &mut stack, flags)? {
-                // Cache negative result (unless disabled)
-                if !is_caching_disabled() {
-                    let mut cache = get_script_cache().write().unwrap();
-                    cache.put(cache_key, false);
-                }
-                false
-            } else if !eval_script(script_pubkey, &mut stack, flags)? {
-                if !is_caching_disabled() {
-                    let mut cache = get_script_cache().write().unwrap();
-                    cache.put(cache_key, false);
-                }
-                false
-            } else if let Some(w) = witness {
-                if !eval_script(w, &mut stack, flags)? {
-                    if !is_caching_disabled() {
-                        let mut cache = get_script_cache().write().unwrap();
-                        cache.put(cache_key, false);
-                    }
-                    false
-                } else {
-                    let res = stack.len() == 1 && !stack[0].is_empty() && stack[0][0] != 0;
-                    if !is_caching_disabled() {
-                        let mut cache = get_script_cache().write().unwrap();
-                        cache.put(cache_key, res);
-                    }
-                    res
-                }
-            } else {
-                let res = stack.len() == 1 && !stack[0].is_empty() && stack[0][0] != 0;
-                if !is_caching_disabled() {
-                    let mut cache = get_script_cache().write().unwrap();
-                    cache.put(cache_key, res);
-                }
-                res
-            }
-        };
+        let result = verify_script_on_stack(script_sig, script_pubkey, witness, flags, &mut stack);
 
         // Return stack to pool
         return_pooled_stack(stack);
 
+        let result = result?;
+        if !is_caching_disabled() {
+            let mut cache = get_script_cache().write().unwrap();
+            cache.put(cache_key, result);
+        }
         Ok(result)
     }
 
@@ -410,27 +504,59 @@ pub fn verify_script(
     {
         // Pre-allocate stack with capacity hint (most scripts use <20 items)
         let mut stack = Vec::with_capacity(20);
+        verify_script_on_stack(script_sig, script_pubkey, witness, flags, &mut stack)
+    }
+}
+
+/// The steps of `verify_script`, on a caller-provided empty stack
+fn verify_script_on_stack(
+    script_sig: &ByteString,
+    script_pubkey: &ByteString,
+    witness: Option<&ByteString>,
+    flags: u32,
+    stack: &mut Vec<ByteString>,
+) -> Result<bool> {
+    // Execute scriptSig
+    if !run_script(script_sig, stack, flags, u64::MAX)? {
+        return Ok(false);
+    }
+
+    // P2SH: keep the scriptSig's stack to run the redeem script on
+    let is_p2sh = flags & SCRIPT_VERIFY_P2SH != 0
+        && matches!(classify_script(script_pubkey), ScriptType::P2SH(_));
+    let mut p2sh_stack = if is_p2sh { stack.clone() } else { Vec::new() };
+
+    // Execute scriptPubkey; it must leave a true value on top
+    if !run_script(script_pubkey, stack, flags, u64::MAX)?
+        || !stack.last().is_some_and(|top| cast_to_bool(top))
+    {
+        return Ok(false);
+    }
 
-        // Execute scriptSig
-        if !eval_script(script_sig, &mut stack, flags)? {
+    // The scriptPubKey checked the redeem script's hash; now run it on the
+    // rest of the scriptSig's pushes, which must be all it contains
+    if is_p2sh {
+        if parse_pushes(script_sig).is_none() {
             return Ok(false);
         }
-
-        // Execute scriptPubkey
-        if !eval_script(script_pubkey, &mut stack, flags)? {
+        let Some(redeem_script) = p2sh_stack.pop() else {
+            return Ok(false);
+        };
+        if !run_script(&redeem_script, &mut p2sh_stack, flags, u64::MAX)? {
             return Ok(false);
         }
+        *stack = p2sh_stack;
+    }
 
-        // Execute witness if present
-        if let Some(w) = witness {
-            if !eval_script(w, &mut stack, flags)? {
-                return Ok(false);
-            }
+    // Execute witness if present
+    if let Some(w) = witness {
+        if !run_script(w, stack, flags, u64::MAX)? {
+            return Ok(false);
         }
-
-        // Final validation
-        Ok(stack.len() == 1 && !stack[0].is_empty() && stack[0][0] != 0)
     }
+
+    // Final validation
+    Ok(stack.len() == 1 && !stack[0].is_empty() && stack[0][0] != 0)
 }
 
 /// VerifyScript with transaction context for signature verification
@@ -447,7 +573,7 @@ pub fn verify_script_with_context(
     flags: u32,
     tx: &Transaction,
     input_index: usize,
-    prevouts: &[TransactionOutput],
+    prevouts: &[PrevOut],
     network: crate::types::Network,
 ) -> Result<bool> {
     verify_script_with_context_full(
@@ -484,33 +610,14 @@ pub fn verify_script_with_context_full(
     flags: u32,
     tx: &Transaction,
     input_index: usize,
-    prevouts: &[TransactionOutput],
+    prevouts: &[PrevOut],
     block_height: Option<u64>,
     median_time_past: Option<u64>,
     network: crate::types::Network,
 ) -> Result<bool> {
-    // Pre-allocate stack with capacity hint
-    let mut stack = Vec::with_capacity(20);
-
-    // Execute scriptSig
-    if !eval_script_with_context_full(
+    let Some(mut stack) = eval_spend_scripts(
         script_sig,
-        &mut stack,
-        flags,
-        tx,
-        input_index,
-        prevouts,
-        block_height,
-        median_time_past,
-        network,
-    )? {
-        return Ok(false);
-    }
-
-    // Execute scriptPubkey
-    if !eval_script_with_context_full(
         script_pubkey,
-        &mut stack,
         flags,
         tx,
         input_index,
@@ -518,9 +625,11 @@ pub fn verify_script_with_context_full(
         block_height,
         median_time_past,
         network,
-    )? {
+        default_verifier(),
+    )?
+    else {
         return Ok(false);
-    }
+    };
 
     // Execute witness if present
     if let Some(w) = witness {
@@ -531,9 +640,11 @@ pub fn verify_script_with_context_full(
             tx,
             input_index,
             prevouts,
+            SigVersion::Base,
             block_height,
             median_time_past,
             network,
+            default_verifier(),
         )? {
             return Ok(false);
         }
@@ -543,777 +654,1697 @@ pub fn verify_script_with_context_full(
     Ok(stack.len() == 1 && !stack[0].is_empty() && stack[0][0] != 0)
 }
 
-/// EvalScript with transaction context for signature verification
-#[allow(dead_code)]
-fn eval_script_with_context(
-    script: &ByteString,
-    stack: &mut Vec<ByteString>,
+/// VerifyScript for an input with its BIP141 witness stack
+///
+/// Runs the scriptSig, scriptPubKey and P2SH redeem script as
+/// `verify_script_with_context_full` does. Under `SCRIPT_VERIFY_WITNESS` a
+/// witness program, either the scriptPubKey itself or a P2SH redeem script,
+/// is then spent by `witness`:
+///
+/// - v0 with a 20-byte program (P2WPKH): the witness is a signature and a
+///   public key hashing to the program, checked as
+///   `OP_DUP OP_HASH160 <program> OP_EQUALVERIFY OP_CHECKSIG`
+/// - v0 with a 32-byte program (P2WSH): the last witness item is a script
+///   hashing to the program, run on the other items
+/// - any other version is left unchecked, reserved for future soft forks
+///
+/// A native program needs an empty scriptSig, and a P2SH one a scriptSig that
+/// pushes only the redeem script. Witness v0 signatures sign the BIP143 hash;
+/// `sighash_cache` holds the digests it shares across the transaction's
+/// inputs, so build it once per transaction. A witness for any other spend
+/// fails it.
+///
+/// Reference: Bitcoin Core `interpreter.cpp` VerifyScript, VerifyWitnessProgram
+#[allow(clippy::too_many_arguments)]
+pub fn verify_script_with_witness(
+    script_sig: &ByteString,
+    script_pubkey: &ByteString,
+    witness: &[ByteString],
     flags: u32,
     tx: &Transaction,
     input_index: usize,
-    prevouts: &[TransactionOutput],
+    prevouts: &[PrevOut],
+    sighash_cache: &SighashCache,
+    block_height: Option<u64>,
+    median_time_past: Option<u64>,
     network: crate::types::Network,
+    verifier: &dyn SignatureVerifier,
 ) -> Result<bool> {
-    eval_script_with_context_full(
-        script,
-        stack,
+    use crate::script_type::witness_program;
+
+    let Some(stack) = eval_spend_scripts(
+        script_sig,
+        script_pubkey,
         flags,
         tx,
         input_index,
         prevouts,
-        None, // block_height
-        None, // median_time_past
+        block_height,
+        median_time_past,
         network,
-    )
+        verifier,
+    )?
+    else {
+        return Ok(false);
+    };
+
+    if flags & SCRIPT_VERIFY_WITNESS != 0 {
+        let mut program_script = None;
+        if witness_program(script_pubkey).is_some() {
+            if !script_sig.is_empty() {
+                return Ok(false);
+            }
+            program_script = Some(script_pubkey.clone());
+        } else if flags & SCRIPT_VERIFY_P2SH != 0
+            && matches!(classify_script(script_pubkey), ScriptType::P2SH(_))
+        {
+            // The redeem script the P2SH check just ran
+            let redeem_script = parse_pushes(script_sig).and_then(|mut pushes| pushes.pop());
+            if let Some(redeem_script) = redeem_script {
+                if witness_program(&redeem_script).is_some() {
+                    if *script_sig != push_script(&redeem_script) {
+                        return Ok(false);
+                    }
+                    program_script = Some(redeem_script);
+                }
+            }
+        }
+
+        if let Some(program_script) = program_script {
+            let (version, program) = witness_program(&program_script).unwrap();
+            return verify_witness_program(
+                version,
+                program,
+                witness,
+                flags,
+                tx,
+                input_index,
+                prevouts,
+                sighash_cache,
+                block_height,
+                median_time_past,
+                network,
+                verifier,
+            );
+        }
+        if !witness.is_empty() {
+            return Ok(false);
+        }
+    }
+
+    Ok(stack.len() == 1 && !stack[0].is_empty() && stack[0][0] != 0)
 }
 
-/// EvalScript with full context including block height, median time-past, and network
+/// Run an input's scriptSig, the spent scriptPubKey and, under
+/// `SCRIPT_VERIFY_P2SH`, the redeem script, all on one stack
+///
+/// Returns the final stack, or `None` if a script fails or the scriptPubKey
+/// leaves false on top.
 #[allow(clippy::too_many_arguments)]
-fn eval_script_with_context_full(
-    script: &ByteString,
-    stack: &mut Vec<ByteString>,
+fn eval_spend_scripts(
+    script_sig: &ByteString,
+    script_pubkey: &ByteString,
     flags: u32,
     tx: &Transaction,
     input_index: usize,
-    prevouts: &[TransactionOutput],
+    prevouts: &[PrevOut],
     block_height: Option<u64>,
     median_time_past: Option<u64>,
     network: crate::types::Network,
-) -> Result<bool> {
-    // Pre-allocate stack capacity if needed
-    if stack.capacity() < 20 {
-        stack.reserve(20);
+    verifier: &dyn SignatureVerifier,
+) -> Result<Option<Vec<ByteString>>> {
+    // An output starting with OP_RETURN is provably unspendable
+    if script_pubkey.first() == Some(&0x6a) {
+        return Err(ConsensusError::ScriptExecution(
+            "Spends a provably unspendable OP_RETURN output".into(),
+        ));
     }
-    let mut op_count = 0;
 
-    for opcode in script {
-        // Check operation limit
-        op_count += 1;
-        if op_count > MAX_SCRIPT_OPS {
-            return Err(ConsensusError::ScriptExecution(
-                "Operation limit exceeded".into(),
-            ));
-        }
+    // Pre-allocate stack with capacity hint
+    let mut stack = Vec::with_capacity(20);
 
-        // Check stack size
-        if stack.len() > MAX_STACK_SIZE {
-            return Err(make_stack_overflow_error());
-        }
+    // Execute scriptSig
+    if !eval_script_with_context_full(
+        script_sig,
+        &mut stack,
+        flags,
+        tx,
+        input_index,
+        prevouts,
+        SigVersion::Base,
+        block_height,
+        median_time_past,
+        network,
+        verifier,
+    )? {
+        return Ok(None);
+    }
 
-        // Runtime assertion: Stack size must be within bounds
-        debug_assert!(
-            stack.len() <= MAX_STACK_SIZE,
-            "Stack size ({}) must not exceed MAX_STACK_SIZE ({})",
-            stack.len(),
-            MAX_STACK_SIZE
-        );
+    // P2SH: keep the scriptSig's stack to run the redeem script on
+    let is_p2sh = flags & SCRIPT_VERIFY_P2SH != 0
+        && matches!(classify_script(script_pubkey), ScriptType::P2SH(_));
+    let mut p2sh_stack = if is_p2sh { stack.clone() } else { Vec::new() };
 
-        // Execute opcode with full transaction context
-        if !execute_opcode_with_context_full(
-            *opcode,
-            stack,
+    // Execute scriptPubkey; it must leave a true value on top
+    if !eval_script_with_context_full(
+        script_pubkey,
+        &mut stack,
+        flags,
+        tx,
+        input_index,
+        prevouts,
+        SigVersion::Base,
+        block_height,
+        median_time_past,
+        network,
+        verifier,
+    )? || !stack.last().is_some_and(|top| cast_to_bool(top))
+    {
+        return Ok(None);
+    }
+
+    // The scriptPubKey checked the redeem script's hash; now run it on the
+    // rest of the scriptSig's pushes, which must be all it contains
+    if is_p2sh {
+        if parse_pushes(script_sig).is_none() {
+            return Ok(None);
+        }
+        let Some(redeem_script) = p2sh_stack.pop() else {
+            return Ok(None);
+        };
+        if !eval_script_with_context_full(
+            &redeem_script,
+            &mut p2sh_stack,
             flags,
             tx,
             input_index,
             prevouts,
+            SigVersion::Base,
             block_height,
             median_time_past,
             network,
+            verifier,
         )? {
-            return Ok(false);
+            return Ok(None);
         }
+        stack = p2sh_stack;
     }
 
-    // Final stack check: exactly one non-zero value
-    Ok(stack.len() == 1 && !stack[0].is_empty() && stack[0][0] != 0)
+    Ok(Some(stack))
 }
 
-/// Execute a single opcode
-fn execute_opcode(opcode: u8, stack: &mut Vec<ByteString>, flags: u32) -> Result<bool> {
-    match opcode {
-        // OP_0 - push empty array
-        0x00 => {
-            stack.push(vec![]);
-            Ok(true)
-        }
+/// Spend a witness program with its witness stack (BIP141)
+///
+/// See `verify_script_with_witness` for the rules by version.
+#[allow(clippy::too_many_arguments)]
+fn verify_witness_program(
+    version: u8,
+    program: &[u8],
+    witness: &[ByteString],
+    flags: u32,
+    tx: &Transaction,
+    input_index: usize,
+    prevouts: &[PrevOut],
+    sighash_cache: &SighashCache,
+    block_height: Option<u64>,
+    median_time_past: Option<u64>,
+    network: crate::types::Network,
+    verifier: &dyn SignatureVerifier,
+) -> Result<bool> {
+    if version != 0 {
+        return Ok(true);
+    }
 
-        // OP_1 to OP_16 - push numbers 1-16
-        0x51..=0x60 => {
-            let num = opcode - 0x50;
-            stack.push(vec![num]);
-            Ok(true)
+    let (script, mut stack) = match program.len() {
+        20 => {
+            if witness.len() != 2 {
+                return Ok(false);
+            }
+            let mut script = vec![0x76, 0xa9, 0x14];
+            script.extend_from_slice(program);
+            script.extend_from_slice(&[0x88, 0xac]);
+            (script, witness.to_vec())
         }
-
-        // OP_DUP - duplicate top stack item
-        0x76 => {
-            if let Some(item) = stack.last().cloned() {
-                stack.push(item);
-                Ok(true)
-            } else {
-                Ok(false)
+        32 => {
+            let Some((witness_script, items)) = witness.split_last() else {
+                return Ok(false);
+            };
+            if crate::crypto::sha256(witness_script) != program {
+                return Ok(false);
             }
+            (witness_script.clone(), items.to_vec())
         }
+        _ => return Ok(false),
+    };
+    if stack
+        .iter()
+        .any(|item| item.len() > MAX_SCRIPT_ELEMENT_SIZE)
+    {
+        return Ok(false);
+    }
 
-        // OP_HASH160 - RIPEMD160(SHA256(x))
-        0xa9 => {
-            if let Some(item) = stack.pop() {
-                #[cfg(feature = "production")]
-                {
-                    // Check hash cache first (unless disabled)
-                    if !is_caching_disabled() {
-                        let cache_key = compute_hash_cache_key(&item, true);
-                        {
-                            let cache = get_hash_cache().read().unwrap();
-                            if let Some(cached_result) = cache.peek(&cache_key) {
-                                // Verify cached result is HASH160 (20 bytes)
-                                if cached_result.len() == 20 {
-                                    stack.push(cached_result.clone());
-                                    return Ok(true);
-                                }
-                            }
-                        }
-                    }
+    if !eval_script_with_context_full(
+        &script,
+        &mut stack,
+        flags,
+        tx,
+        input_index,
+        prevouts,
+        SigVersion::WitnessV0(sighash_cache),
+        block_height,
+        median_time_past,
+        network,
+        verifier,
+    )? {
+        return Ok(false);
+    }
 
-                    // Compute hash (cache miss or caching disabled)
-                    let sha256_hash = Sha256::digest(&item);
-                    let ripemd160_hash = Ripemd160::digest(sha256_hash);
-                    let result = ripemd160_hash.to_vec();
+    // The witness script must leave exactly one true element
+    Ok(stack.len() == 1 && cast_to_bool(&stack[0]))
+}
 
-                    // Cache result (unless disabled)
-                    if !is_caching_disabled() {
-                        let cache_key = compute_hash_cache_key(&item, true);
-                        let mut cache = get_hash_cache().write().unwrap();
-                        cache.put(cache_key, result.clone());
-                    }
+/// Verify all input scripts of a transaction against a UTXO set
+///
+/// Resolves each input's prevout in `utxo_set` to build the prevouts list, then
+/// runs `verify_script_with_witness` for every input at the given block
+/// height. `Transaction` carries no witness data, so every witness stack is
+/// empty. Coinbase transactions have no scripts to verify and return true.
+///
+/// The network is taken from `Network::from_env()`, matching `ConsensusProof`.
+///
+/// # Errors
+///
+/// Returns `ConsensusError::UtxoNotFound` if any input's prevout is missing from the set.
+pub fn verify_transaction_with_utxo_set(
+    tx: &Transaction,
+    utxo_set: &UtxoSet,
+    flags: u32,
+    height: Natural,
+) -> Result<bool> {
+    if crate::transaction::is_coinbase(tx) {
+        return Ok(true);
+    }
+
+    let mut prevouts = Vec::with_capacity(tx.inputs.len());
+    for (i, input) in tx.inputs.iter().enumerate() {
+        let utxo = utxo_set
+            .get(&input.prevout)
+            .ok_or_else(|| missing_prevout_error(i, &input.prevout))?;
+        prevouts.push(TransactionOutput {
+            value: utxo.value,
+            script_pubkey: utxo.script_pubkey.clone(),
+        });
+    }
+
+    let network = crate::types::Network::from_env();
+    let sighash_cache = SighashCache::new(tx);
+    for (i, input) in tx.inputs.iter().enumerate() {
+        if !verify_script_with_witness(
+            &input.script_sig,
+            &prevouts[i].script_pubkey,
+            &[],
+            flags,
+            tx,
+            i,
+            &prevouts,
+            &sighash_cache,
+            Some(height),
+            None,
+            network,
+            default_verifier(),
+        )? {
+            return Ok(false);
+        }
+    }
 
-                    stack.push(result);
-                    Ok(true)
-                }
+    Ok(true)
+}
 
-                #[cfg(not(feature = "production"))]
-                {
-                    let sha256_hash = Sha256::digest(&item);
-                    let ripemd160_hash = Ripemd160::digest(sha256_hash);
-                    stack.push(ripemd160_hash.to_vec());
-                    Ok(true)
-                }
-            } else {
-                Ok(false)
+/// Verify all input scripts of a transaction, pinpointing the first failure
+///
+/// `prevouts` are the spent outputs in input order. On failure returns the
+/// failing input's index with the script engine's error, or
+/// `ConsensusError::ScriptExecution` if the script ran but evaluated to false.
+/// A prevouts list of the wrong length fails with
+/// `ConsensusError::InvalidPrevoutsCount` at the first input it cannot cover.
+/// Coinbase transactions have no scripts to verify.
+///
+/// `witnesses` holds one witness stack per input, missing ones being empty;
+/// inputs are checked with `verify_script_with_witness`, sharing one
+/// `SighashCache`.
+pub fn validate_transaction_verbose(
+    tx: &Transaction,
+    prevouts: &[PrevOut],
+    witnesses: &[crate::witness::Witness],
+    flags: u32,
+    network: crate::types::Network,
+) -> std::result::Result<(), (usize, ConsensusError)> {
+    if crate::transaction::is_coinbase(tx) {
+        return Ok(());
+    }
+    if prevouts.len() != tx.inputs.len() {
+        return Err((
+            prevouts.len().min(tx.inputs.len()),
+            ConsensusError::InvalidPrevoutsCount(tx.inputs.len(), prevouts.len()),
+        ));
+    }
+
+    let sighash_cache = SighashCache::new(tx);
+    for (i, input) in tx.inputs.iter().enumerate() {
+        match verify_script_with_witness(
+            &input.script_sig,
+            &prevouts[i].script_pubkey,
+            witnesses.get(i).map_or(&[], |witness| witness.as_slice()),
+            flags,
+            tx,
+            i,
+            prevouts,
+            &sighash_cache,
+            None,
+            None,
+            network,
+            default_verifier(),
+        ) {
+            Ok(true) => {}
+            Ok(false) => {
+                return Err((
+                    i,
+                    ConsensusError::ScriptExecution(
+                        format!("Script evaluated to false at input {i}").into(),
+                    ),
+                ))
             }
+            Err(e) => return Err((i, e)),
         }
+    }
 
-        // OP_HASH256 - SHA256(SHA256(x))
-        0xaa => {
-            if let Some(item) = stack.pop() {
-                #[cfg(feature = "production")]
-                {
-                    // Check hash cache first (unless disabled)
-                    if !is_caching_disabled() {
-                        let cache_key = compute_hash_cache_key(&item, false);
-                        {
-                            let cache = get_hash_cache().read().unwrap();
-                            if let Some(cached_result) = cache.peek(&cache_key) {
-                                // Verify cached result is HASH256 (32 bytes)
-                                if cached_result.len() == 32 {
-                                    stack.push(cached_result.clone());
-                                    return Ok(true);
-                                }
-                            }
-                        }
-                    }
+    Ok(())
+}
 
-                    // Compute hash (cache miss or caching disabled)
-                    let hash1 = Sha256::digest(&item);
-                    let hash2 = Sha256::digest(hash1);
-                    let result = hash2.to_vec();
+/// Verify a single input of a transaction against the output it spends
+///
+/// Runs the input's scriptSig, its spent output's scriptPubKey, the P2SH
+/// redeem script (with `SCRIPT_VERIFY_P2SH`) and, under
+/// `SCRIPT_VERIFY_WITNESS`, `witness` with `verify_script_with_witness`.
+/// `Transaction` carries no witness data, so the input's witness stack is
+/// passed separately, empty for a non-witness spend.
+///
+/// `prevouts` are the outputs spent by every input, in input order, since
+/// sighashes may commit to all of them. The network is taken from
+/// `Network::from_env()`, with no block height or median time-past.
+///
+/// # Errors
+///
+/// Returns `ConsensusError::InvalidInputIndex` if `input_index` is out of
+/// range, and `ConsensusError::InvalidPrevoutsCount` if `prevouts` does not
+/// hold one output per input.
+pub fn verify_input(
+    tx: &Transaction,
+    input_index: usize,
+    prevouts: &[PrevOut],
+    witness: &crate::witness::Witness,
+    flags: u32,
+) -> Result<bool> {
+    let Some(input) = tx.inputs.get(input_index) else {
+        return Err(ConsensusError::InvalidInputIndex(input_index));
+    };
+    if prevouts.len() != tx.inputs.len() {
+        return Err(ConsensusError::InvalidPrevoutsCount(
+            tx.inputs.len(),
+            prevouts.len(),
+        ));
+    }
 
-                    // Cache result (unless disabled)
-                    if !is_caching_disabled() {
-                        let cache_key = compute_hash_cache_key(&item, false);
-                        let mut cache = get_hash_cache().write().unwrap();
-                        cache.put(cache_key, result.clone());
-                    }
+    verify_script_with_witness(
+        &input.script_sig,
+        &prevouts[input_index].script_pubkey,
+        witness,
+        flags,
+        tx,
+        input_index,
+        prevouts,
+        &SighashCache::new(tx),
+        None,
+        None,
+        crate::types::Network::from_env(),
+        default_verifier(),
+    )
+}
 
-                    stack.push(result);
-                    Ok(true)
-                }
+/// VerifyScript with transaction context, resolving prevouts by outpoint
+///
+/// Same as `verify_script_with_context`, but the spent outputs come from a map
+/// keyed by outpoint rather than a vector in input order. The scriptPubKey is
+/// the output spent by `tx.inputs[input_index]`.
+///
+/// # Errors
+///
+/// Returns `ConsensusError::UtxoNotFound` if any input's prevout is missing
+/// from the map (sighashes may commit to every spent output), and
+/// `ConsensusError::InvalidInputIndex` if `input_index` is out of range.
+pub fn verify_script_with_prevout_map(
+    script_sig: &ByteString,
+    witness: Option<&ByteString>,
+    flags: u32,
+    tx: &Transaction,
+    input_index: usize,
+    prevouts: &std::collections::HashMap<OutPoint, PrevOut>,
+    network: crate::types::Network,
+) -> Result<bool> {
+    if input_index >= tx.inputs.len() {
+        return Err(ConsensusError::InvalidInputIndex(input_index));
+    }
+
+    let ordered: Vec<PrevOut> = tx
+        .inputs
+        .iter()
+        .enumerate()
+        .map(|(i, input)| {
+            prevouts
+                .get(&input.prevout)
+                .cloned()
+                .ok_or_else(|| missing_prevout_error(i, &input.prevout))
+        })
+        .collect::<Result<_>>()?;
+
+    verify_script_with_context(
+        script_sig,
+        &ordered[input_index].script_pubkey,
+        witness,
+        flags,
+        tx,
+        input_index,
+        &ordered,
+        network,
+    )
+}
 
-                #[cfg(not(feature = "production"))]
-                {
-                    let hash1 = Sha256::digest(&item);
-                    let hash2 = Sha256::digest(hash1);
-                    stack.push(hash2.to_vec());
-                    Ok(true)
-                }
-            } else {
-                Ok(false)
+fn missing_prevout_error(input_index: usize, prevout: &OutPoint) -> ConsensusError {
+    ConsensusError::UtxoNotFound(
+        format!(
+            "Input {input_index} spends missing output {}:{}",
+            hex::encode(prevout.hash),
+            prevout.index
+        )
+        .into(),
+    )
+}
+
+/// EvalScript with transaction context for signature verification
+#[allow(dead_code)]
+fn eval_script_with_context(
+    script: &ByteString,
+    stack: &mut Vec<ByteString>,
+    flags: u32,
+    tx: &Transaction,
+    input_index: usize,
+    prevouts: &[PrevOut],
+    network: crate::types::Network,
+) -> Result<bool> {
+    eval_script_with_context_full(
+        script,
+        stack,
+        flags,
+        tx,
+        input_index,
+        prevouts,
+        SigVersion::Base,
+        None, // block_height
+        None, // median_time_past
+        network,
+        default_verifier(),
+    )
+}
+
+/// EvalScript with full context including block height, median time-past, and network
+///
+/// Returns whether the script ran to completion; unlike `eval_script`, the
+/// resulting stack is left for the caller to judge, as scriptSig, scriptPubKey
+/// and redeem script run on one stack in turn.
+#[allow(clippy::too_many_arguments)]
+fn eval_script_with_context_full(
+    script: &ByteString,
+    stack: &mut Vec<ByteString>,
+    flags: u32,
+    tx: &Transaction,
+    input_index: usize,
+    prevouts: &[PrevOut],
+    sig_version: SigVersion,
+    block_height: Option<u64>,
+    median_time_past: Option<u64>,
+    network: crate::types::Network,
+    verifier: &dyn SignatureVerifier,
+) -> Result<bool> {
+    // Pre-allocate stack capacity if needed
+    if stack.capacity() < 20 {
+        stack.reserve(20);
+    }
+    let mut op_count = 0;
+    let mut altstack: Vec<ByteString> = Vec::new();
+    let mut exec_stack: Vec<bool> = Vec::new();
+    // Start of the scriptCode signed by CHECKSIG: just after the last
+    // executed OP_CODESEPARATOR
+    let mut code_start = 0;
+
+    let mut pc = 0;
+    while pc < script.len() {
+        let (opcode, push, next) = read_instruction(script, pc)?;
+        pc = next;
+
+        // Check operation limit: pushes and OP_1NEGATE..OP_16 are not counted
+        if opcode > 0x60 {
+            op_count += 1;
+            if op_count > MAX_SCRIPT_OPS {
+                return Err(ConsensusError::ScriptExecution(
+                    "Operation limit exceeded".into(),
+                ));
             }
         }
 
-        // OP_EQUAL - check if top two stack items are equal
-        0x87 => {
-            if stack.len() < 2 {
-                return Ok(false);
-            }
-            let a = stack.pop().unwrap();
-            let b = stack.pop().unwrap();
-            stack.push(if a == b { vec![1] } else { vec![0] });
-            Ok(true)
+        // Disabled opcodes fail the script even inside a branch not taken
+        if is_disabled_opcode(opcode) {
+            return Ok(false);
         }
 
-        // OP_EQUALVERIFY - verify top two stack items are equal
-        0x88 => {
-            if stack.len() < 2 {
-                return Ok(false);
-            }
-            let a = stack.pop().unwrap();
-            let b = stack.pop().unwrap();
-            Ok(a == b)
+        // Inside a branch not taken, only track conditional nesting
+        if !is_executing(&exec_stack) && !is_conditional_opcode(opcode) {
+            continue;
         }
 
-        // OP_CHECKSIG - verify ECDSA signature
-        0xac => {
-            if stack.len() < 2 {
-                return Ok(false);
+        // CHECKMULTISIG counts each of its public keys as an operation
+        if matches!(opcode, 0xae | 0xaf) {
+            op_count += multisig_key_count(stack, flags)?;
+            if op_count > MAX_SCRIPT_OPS {
+                return Err(make_operation_limit_error());
             }
-            let pubkey_bytes = stack.pop().unwrap();
-            let signature_bytes = stack.pop().unwrap();
-
-            // Verify signature using secp256k1 (dummy hash for legacy compatibility)
-            // Note: Without transaction context, we use height 0 and Regtest network
-            // This is only used in basic execute_opcode without transaction context
-            let dummy_hash = [0u8; 32];
-            #[cfg(feature = "production")]
-            let result = SECP256K1_CONTEXT.with(|secp| {
-                verify_signature(
-                    secp,
-                    &pubkey_bytes,
-                    &signature_bytes,
-                    &dummy_hash,
-                    flags,
-                    0,
-                    crate::types::Network::Regtest,
-                )
-            });
-
-            #[cfg(not(feature = "production"))]
-            let result = {
-                let secp = Secp256k1::new();
-                verify_signature(
-                    &secp,
-                    &pubkey_bytes,
-                    &signature_bytes,
-                    &dummy_hash,
-                    flags,
-                    0,
-                    crate::types::Network::Regtest,
-                )
-            };
-
-            stack.push(if result.unwrap_or(false) {
-                vec![1]
-            } else {
-                vec![0]
-            });
-            Ok(true)
         }
 
-        // OP_CHECKSIGVERIFY - verify ECDSA signature and fail if invalid
-        0xad => {
-            if stack.len() < 2 {
-                return Ok(false);
-            }
-            let pubkey_bytes = stack.pop().unwrap();
-            let signature_bytes = stack.pop().unwrap();
-
-            // Verify signature using secp256k1 (dummy hash for legacy compatibility)
-            // Note: Without transaction context, we use height 0 and Regtest network
-            // This is only used in basic execute_opcode without transaction context
-            let dummy_hash = [0u8; 32];
-            #[cfg(feature = "production")]
-            let result = SECP256K1_CONTEXT.with(|secp| {
-                verify_signature(
-                    secp,
-                    &pubkey_bytes,
-                    &signature_bytes,
-                    &dummy_hash,
-                    flags,
-                    0,
-                    crate::types::Network::Regtest,
-                )
-            });
+        // Check stack size of the incoming stack
+        check_stack_size(stack, &altstack)?;
 
-            #[cfg(not(feature = "production"))]
-            let result = {
-                let secp = Secp256k1::new();
-                verify_signature(
-                    &secp,
-                    &pubkey_bytes,
-                    &signature_bytes,
-                    &dummy_hash,
-                    flags,
-                    0,
-                    crate::types::Network::Regtest,
-                )
-            };
+        // Runtime assertion: Stack size must be within bounds
+        debug_assert!(
+            stack.len() <= MAX_STACK_SIZE,
+            "Stack size ({}) must not exceed MAX_STACK_SIZE ({})",
+            stack.len(),
+            MAX_STACK_SIZE
+        );
 
-            Ok(result.unwrap_or(false))
+        // Execute opcode with full transaction context
+        let success = match opcode {
+            0x01..=0x4e => {
+                stack.push(push.to_vec());
+                true
+            }
+            0x63 | 0x64 | 0x67 | 0x68 => execute_conditional_opcode(
+                opcode,
+                stack,
+                &mut exec_stack,
+                minimal_if(flags, sig_version),
+            ),
+            0x6b | 0x6c => execute_altstack_opcode(opcode, stack, &mut altstack)?,
+            // OP_CODESEPARATOR
+            0xab => {
+                code_start = pc;
+                true
+            }
+            _ => execute_opcode_with_context_full(
+                opcode,
+                stack,
+                flags,
+                tx,
+                input_index,
+                prevouts,
+                &script[code_start..],
+                sig_version,
+                block_height,
+                median_time_past,
+                network,
+                verifier,
+            )?,
+        };
+        if !success {
+            return Ok(false);
         }
 
-        // OP_RETURN - always fail
-        0x6a => Ok(false),
+        // Check stack size after every opcode
+        check_stack_size(stack, &altstack)?;
+    }
 
-        // OP_VERIFY - check if top stack item is non-zero
-        0x69 => {
-            if let Some(item) = stack.pop() {
-                Ok(!item.is_empty() && item[0] != 0)
-            } else {
-                Ok(false)
-            }
-        }
-
-        // OP_CHECKLOCKTIMEVERIFY (BIP65) - 0xb1
-        // Note: Requires transaction context for proper validation.
-        // This basic implementation will fail - use verify_script_with_context for proper CLTV validation.
-        0xb1 => {
-            // CLTV requires transaction locktime and block context, so it always fails here
-            // Proper implementation is in execute_opcode_with_context
-            Ok(false)
-        }
-
-        // OP_CHECKSEQUENCEVERIFY (BIP112) - 0xb2
-        // Note: Requires transaction context for proper validation.
-        // This basic implementation will fail - use verify_script_with_context for proper CSV validation.
-        0xb2 => {
-            // CSV requires transaction sequence and block context, so it always fails here
-            // Proper implementation is in execute_opcode_with_context
-            Ok(false)
-        }
+    // Every OP_IF/OP_NOTIF must be closed by an OP_ENDIF
+    Ok(exec_stack.is_empty())
+}
 
-        // OP_IFDUP - duplicate top stack item if it's non-zero
-        0x73 => {
-            if let Some(item) = stack.last().cloned() {
-                if !item.is_empty() && item[0] != 0 {
-                    stack.push(item);
-                }
-                Ok(true)
-            } else {
-                Ok(false)
-            }
+/// Read the instruction starting at `pc`
+///
+/// Returns the opcode, its push payload and the position of the next
+/// instruction. Opcodes 0x01-0x4b push the next `opcode` bytes; OP_PUSHDATA1
+/// (0x4c), OP_PUSHDATA2 (0x4d) and OP_PUSHDATA4 (0x4e) push the number of
+/// bytes given by a 1, 2 or 4-byte little-endian length prefix. Every other
+/// opcode has an empty payload.
+///
+/// # Errors
+///
+/// Returns `ConsensusError::ScriptExecution` if a length prefix or payload
+/// runs past the end of the script, or if a payload exceeds
+/// `MAX_SCRIPT_ELEMENT_SIZE`. Like Core, this applies in branches not taken.
+fn read_instruction(script: &[u8], pc: usize) -> Result<(u8, &[u8], usize)> {
+    let opcode = script[pc];
+    let mut pos = pc + 1;
+    let len = match opcode {
+        0x01..=0x4b => opcode as usize,
+        0x4c..=0x4e => {
+            let prefix_len = match opcode {
+                0x4c => 1,
+                0x4d => 2,
+                _ => 4,
+            };
+            let prefix = script
+                .get(pos..pos + prefix_len)
+                .ok_or_else(make_truncated_push_error)?;
+            pos += prefix_len;
+            prefix
+                .iter()
+                .rev()
+                .fold(0usize, |len, &byte| (len << 8) | byte as usize)
         }
+        _ => return Ok((opcode, &[], pos)),
+    };
 
-        // OP_DEPTH - push stack size
-        0x74 => {
-            let depth = stack.len() as u8;
-            stack.push(vec![depth]);
-            Ok(true)
-        }
+    if len > MAX_SCRIPT_ELEMENT_SIZE {
+        return Err(ConsensusError::ScriptExecution(
+            format!("Push of {len} bytes exceeds MAX_SCRIPT_ELEMENT_SIZE").into(),
+        ));
+    }
+    let push = script
+        .get(pos..pos + len)
+        .ok_or_else(make_truncated_push_error)?;
+    Ok((opcode, push, pos + len))
+}
 
-        // OP_DROP - remove top stack item
-        0x75 => {
-            if stack.pop().is_some() {
-                Ok(true)
-            } else {
-                Ok(false)
-            }
-        }
+/// Key count of the CHECKMULTISIG about to execute, read from the stack top
+///
+/// An empty stack counts as zero keys; the opcode itself then fails. A count
+/// that is negative, not a valid script number or above
+/// `MAX_PUBKEYS_PER_MULTISIG` fails the script, as in Core.
+fn multisig_key_count(stack: &[ByteString], flags: u32) -> Result<usize> {
+    let Some(top) = stack.last() else {
+        return Ok(0);
+    };
+    decode_script_num(top, flags)
+        .and_then(|n| usize::try_from(n).ok())
+        .filter(|&n| n <= MAX_PUBKEYS_PER_MULTISIG)
+        .ok_or_else(make_pubkey_count_error)
+}
 
-        // OP_NIP - remove second-to-top stack item
-        0x77 => {
-            if stack.len() >= 2 {
-                let top = stack.pop().unwrap();
-                stack.pop(); // Remove second-to-top
-                stack.push(top);
-                Ok(true)
-            } else {
-                Ok(false)
-            }
-        }
+/// Enforce `MAX_STACK_SIZE` on the main and alt stacks combined
+#[inline]
+fn check_stack_size(stack: &[ByteString], altstack: &[ByteString]) -> Result<()> {
+    if stack.len() + altstack.len() > MAX_STACK_SIZE {
+        return Err(make_stack_overflow_error());
+    }
+    Ok(())
+}
 
-        // OP_OVER - copy second-to-top stack item to top
-        0x78 => {
-            if stack.len() >= 2 {
-                let second = stack[stack.len() - 2].clone();
-                stack.push(second);
-                Ok(true)
-            } else {
-                Ok(false)
-            }
-        }
+/// Execute an alt-stack opcode
+///
+/// OP_TOALTSTACK (0x6b) moves the top stack item to the alt stack and
+/// OP_FROMALTSTACK (0x6c) moves it back. The alt stack lives for one script
+/// evaluation, like Core's.
+///
+/// # Errors
+///
+/// Returns `ConsensusError::ScriptExecution` if the source stack is empty.
+fn execute_altstack_opcode(
+    opcode: u8,
+    stack: &mut Vec<ByteString>,
+    altstack: &mut Vec<ByteString>,
+) -> Result<bool> {
+    let (from, to, name) = match opcode {
+        0x6b => (stack, altstack, "OP_TOALTSTACK"),
+        0x6c => (altstack, stack, "OP_FROMALTSTACK"),
+        _ => return Ok(false),
+    };
+    let item = from
+        .pop()
+        .ok_or_else(|| ConsensusError::ScriptExecution(format!("{name} on empty stack").into()))?;
+    to.push(item);
+    Ok(true)
+}
 
-        // OP_PICK - copy nth stack item to top
-        0x79 => {
-            if let Some(n_bytes) = stack.pop() {
-                if n_bytes.is_empty() {
-                    return Ok(false);
-                }
-                let n = n_bytes[0] as usize;
-                if n < stack.len() {
-                    let item = stack[stack.len() - 1 - n].clone();
-                    stack.push(item);
-                    Ok(true)
-                } else {
-                    Ok(false)
-                }
-            } else {
-                Ok(false)
-            }
-        }
+/// Whether `opcode` lies in OP_IF..=OP_ENDIF
+///
+/// Like Core, these run even inside a branch not taken; that includes
+/// OP_VERIF (0x65) and OP_VERNOTIF (0x66), which always fail.
+#[inline]
+fn is_conditional_opcode(opcode: u8) -> bool {
+    (0x63..=0x68).contains(&opcode)
+}
 
-        // OP_ROLL - move nth stack item to top
-        0x7a => {
-            if let Some(n_bytes) = stack.pop() {
-                if n_bytes.is_empty() {
-                    return Ok(false);
-                }
-                let n = n_bytes[0] as usize;
-                if n < stack.len() {
-                    let item = stack.remove(stack.len() - 1 - n);
-                    stack.push(item);
-                    Ok(true)
-                } else {
-                    Ok(false)
-                }
-            } else {
-                Ok(false)
-            }
-        }
+/// Whether `opcode` is disabled: OP_CAT, OP_SUBSTR, OP_LEFT, OP_RIGHT,
+/// OP_INVERT, OP_AND, OP_OR, OP_XOR, OP_2MUL, OP_2DIV, OP_MUL, OP_DIV, OP_MOD,
+/// OP_LSHIFT or OP_RSHIFT
+///
+/// A disabled opcode fails the script wherever it appears, even in a branch
+/// not taken.
+#[inline]
+fn is_disabled_opcode(opcode: u8) -> bool {
+    matches!(
+        opcode,
+        0x7e..=0x81 | 0x83..=0x86 | 0x8d | 0x8e | 0x95..=0x99
+    )
+}
 
-        // OP_ROT - rotate top 3 stack items
-        0x7b => {
-            if stack.len() >= 3 {
-                let top = stack.pop().unwrap();
-                let second = stack.pop().unwrap();
-                let third = stack.pop().unwrap();
-                stack.push(second);
-                stack.push(top);
-                stack.push(third);
-                Ok(true)
-            } else {
-                Ok(false)
-            }
-        }
+/// Whether OP_IF/OP_NOTIF conditions must be minimal (MINIMALIF)
+///
+/// `SCRIPT_VERIFY_MINIMALIF` only applies to witness v0 scripts; bare and
+/// P2SH scripts accept any condition. (Tapscript, where the rule is always
+/// on, is not run by this interpreter.)
+#[inline]
+fn minimal_if(flags: u32, sig_version: SigVersion) -> bool {
+    flags & SCRIPT_VERIFY_MINIMALIF != 0 && matches!(sig_version, SigVersion::WitnessV0(_))
+}
 
-        // OP_SWAP - swap top 2 stack items
-        0x7c => {
-            if stack.len() >= 2 {
-                let top = stack.pop().unwrap();
-                let second = stack.pop().unwrap();
-                stack.push(top);
-                stack.push(second);
-                Ok(true)
-            } else {
-                Ok(false)
-            }
-        }
+/// Whether every enclosing conditional branch is being taken
+#[inline]
+fn is_executing(exec_stack: &[bool]) -> bool {
+    exec_stack.iter().all(|&taken| taken)
+}
 
-        // OP_TUCK - copy top stack item to before second-to-top
-        0x7d => {
-            if stack.len() >= 2 {
-                let top = stack.pop().unwrap();
-                let second = stack.pop().unwrap();
-                stack.push(top.clone());
-                stack.push(second);
-                stack.push(top);
-                Ok(true)
-            } else {
-                Ok(false)
-            }
-        }
+/// Script truth value: false for any encoding of zero, including negative zero
+#[inline]
+fn cast_to_bool(bytes: &[u8]) -> bool {
+    match bytes.split_last() {
+        Some((&last, rest)) => rest.iter().any(|&b| b != 0) || (last != 0 && last != 0x80),
+        None => false,
+    }
+}
 
-        // OP_2DROP - remove top 2 stack items
-        0x6d => {
-            if stack.len() >= 2 {
-                stack.pop();
-                stack.pop();
-                Ok(true)
-            } else {
-                Ok(false)
+/// Execute a conditional flow opcode
+///
+/// `exec_stack` holds one entry per open OP_IF/OP_NOTIF, true if that branch
+/// is being taken. OP_IF (0x63) and OP_NOTIF (0x64) pop their condition only
+/// when executing; inside a branch not taken they open a nested branch that is
+/// never taken. OP_ELSE (0x67) flips the innermost branch and OP_ENDIF (0x68)
+/// closes it.
+///
+/// Returns false for OP_IF/OP_NOTIF on an empty stack, OP_ELSE/OP_ENDIF with
+/// no open branch, and, when `minimal_if` is set, a condition other than
+/// empty or `[0x01]`.
+fn execute_conditional_opcode(
+    opcode: u8,
+    stack: &mut Vec<ByteString>,
+    exec_stack: &mut Vec<bool>,
+    minimal_if: bool,
+) -> bool {
+    match opcode {
+        // OP_IF, OP_NOTIF
+        0x63 | 0x64 => {
+            let mut taken = false;
+            if is_executing(exec_stack) {
+                let Some(condition) = stack.pop() else {
+                    return false;
+                };
+                if minimal_if && !(condition.is_empty() || condition == [0x01]) {
+                    return false;
+                }
+                taken = cast_to_bool(&condition) == (opcode == 0x63);
             }
+            exec_stack.push(taken);
+            true
         }
-
-        // OP_2DUP - duplicate top 2 stack items
-        0x6e => {
-            if stack.len() >= 2 {
-                let top = stack[stack.len() - 1].clone();
-                let second = stack[stack.len() - 2].clone();
-                stack.push(second);
-                stack.push(top);
-                Ok(true)
-            } else {
-                Ok(false)
+        // OP_ELSE
+        0x67 => match exec_stack.last_mut() {
+            Some(taken) => {
+                *taken = !*taken;
+                true
             }
-        }
+            None => false,
+        },
+        // OP_ENDIF
+        0x68 => exec_stack.pop().is_some(),
+        _ => false,
+    }
+}
 
-        // OP_3DUP - duplicate top 3 stack items
-        0x6f => {
-            if stack.len() >= 3 {
-                let top = stack[stack.len() - 1].clone();
-                let second = stack[stack.len() - 2].clone();
-                let third = stack[stack.len() - 3].clone();
-                stack.push(third);
-                stack.push(second);
-                stack.push(top);
-                Ok(true)
-            } else {
-                Ok(false)
+/// Signature shared by the opcode handlers behind `execute_opcode`
+type OpcodeHandler = fn(u8, &mut Vec<ByteString>, u32) -> Result<bool>;
+
+/// Declare the opcode-to-handler mapping once, as both a `match`
+/// (`execute_opcode_match`) and the `const fn` that fills `OPCODE_TABLE`
+macro_rules! opcode_handlers {
+    ($($pattern:pat => $handler:ident,)*) => {
+        /// Execute a single opcode by matching on it
+        #[cfg_attr(feature = "production", allow(dead_code))]
+        fn execute_opcode_match(
+            opcode: u8,
+            stack: &mut Vec<ByteString>,
+            flags: u32,
+        ) -> Result<bool> {
+            match opcode {
+                $($pattern => $handler(opcode, stack, flags),)*
             }
         }
 
-        // OP_2OVER - copy second pair of stack items to top
-        0x70 => {
-            if stack.len() >= 4 {
-                let fourth = stack[stack.len() - 4].clone();
-                let third = stack[stack.len() - 3].clone();
-                stack.push(fourth);
-                stack.push(third);
-                Ok(true)
-            } else {
-                Ok(false)
+        /// The handler `execute_opcode_match` runs for `opcode`
+        #[cfg_attr(not(feature = "production"), allow(dead_code))]
+        const fn opcode_handler(opcode: u8) -> OpcodeHandler {
+            match opcode {
+                $($pattern => $handler,)*
             }
         }
+    };
+}
 
-        // OP_2ROT - rotate second pair of stack items to top
-        0x71 => {
-            if stack.len() >= 6 {
-                let sixth = stack.remove(stack.len() - 6);
-                let fifth = stack.remove(stack.len() - 5);
-                stack.push(fifth);
-                stack.push(sixth);
-                Ok(true)
-            } else {
-                Ok(false)
-            }
-        }
+opcode_handlers! {
+    0x00 => op_push_empty,
+    0x4f | 0x51..=0x60 => op_push_number,
+    0x76 => op_dup,
+    0xa9 => op_hash160,
+    0xaa => op_hash256,
+    0x87 => op_equal,
+    0x88 => op_equalverify,
+    0x8b | 0x8c | 0x8f..=0x92 => op_unary_numeric,
+    0x93 | 0x94 | 0x9a..=0xa4 => op_binary_numeric,
+    0xa5 => op_within,
+    0xac => op_checksig,
+    0xad => op_checksigverify,
+    0xae | 0xaf => op_checkmultisig,
+    0x61 => op_nop,
+    0x6a => op_return,
+    0x69 => op_verify,
+    0xb1 => op_checklocktimeverify,
+    0xb2 => op_checksequenceverify,
+    0x73 => op_ifdup,
+    0x74 => op_depth,
+    0x75 => op_drop,
+    0x77 => op_nip,
+    0x78 => op_over,
+    0x79 => op_pick,
+    0x7a => op_roll,
+    0x7b => op_rot,
+    0x7c => op_swap,
+    0x7d => op_tuck,
+    0x6d => op_2drop,
+    0x6e => op_2dup,
+    0x6f => op_3dup,
+    0x70 => op_2over,
+    0x71 => op_2rot,
+    0x72 => op_2swap,
+    0x82 => op_size,
+    _ => op_unknown,
+}
 
-        // OP_2SWAP - swap second pair of stack items
-        0x72 => {
-            if stack.len() >= 4 {
-                let top = stack.pop().unwrap();
-                let second = stack.pop().unwrap();
-                let third = stack.pop().unwrap();
-                let fourth = stack.pop().unwrap();
-                stack.push(second);
-                stack.push(top);
-                stack.push(fourth);
-                stack.push(third);
-                Ok(true)
-            } else {
-                Ok(false)
-            }
-        }
+/// Handler of every opcode, indexed by opcode
+///
+/// Filled from `opcode_handler` at compile time, so it dispatches exactly like
+/// `execute_opcode_match`, with one indirect call in place of the branch tree.
+/// Used by `execute_opcode` with the production feature.
+#[cfg_attr(not(feature = "production"), allow(dead_code))]
+static OPCODE_TABLE: [OpcodeHandler; 256] = {
+    let mut table = [op_unknown as OpcodeHandler; 256];
+    let mut opcode = 0;
+    while opcode < 256 {
+        table[opcode] = opcode_handler(opcode as u8);
+        opcode += 1;
+    }
+    table
+};
 
-        // OP_SIZE - push size of top stack item
-        0x82 => {
-            if let Some(item) = stack.last().cloned() {
-                let size = item.len() as u8;
-                stack.push(vec![size]);
-                Ok(true)
-            } else {
-                Ok(false)
-            }
-        }
+/// Execute a single opcode
+///
+/// Dispatches through `OPCODE_TABLE` with the production feature and through
+/// `execute_opcode_match` otherwise; both run the same handlers.
+#[inline]
+fn execute_opcode(opcode: u8, stack: &mut Vec<ByteString>, flags: u32) -> Result<bool> {
+    #[cfg(feature = "production")]
+    {
+        OPCODE_TABLE[opcode as usize](opcode, stack, flags)
+    }
 
-        // Unknown opcode
-        _ => Ok(false),
+    #[cfg(not(feature = "production"))]
+    {
+        execute_opcode_match(opcode, stack, flags)
     }
 }
 
-/// Execute a single opcode with transaction context for signature verification
-#[allow(dead_code)]
-fn execute_opcode_with_context(
-    opcode: u8,
-    stack: &mut Vec<ByteString>,
-    flags: u32,
-    tx: &Transaction,
-    input_index: usize,
-    prevouts: &[TransactionOutput],
-    network: crate::types::Network,
-) -> Result<bool> {
-    execute_opcode_with_context_full(
-        opcode,
-        stack,
-        flags,
-        tx,
-        input_index,
-        prevouts,
-        None, // block_height
-        None, // median_time_past
-        network,
-    )
+/// OP_0 - push empty array
+fn op_push_empty(_opcode: u8, stack: &mut Vec<ByteString>, _flags: u32) -> Result<bool> {
+    stack.push(vec![]);
+    Ok(true)
 }
 
-/// Execute a single opcode with full context including block height, median time-past, and network
-#[allow(clippy::too_many_arguments)]
-fn execute_opcode_with_context_full(
-    opcode: u8,
-    stack: &mut Vec<ByteString>,
-    flags: u32,
-    tx: &Transaction,
-    input_index: usize,
-    prevouts: &[TransactionOutput],
-    block_height: Option<u64>,
-    median_time_past: Option<u64>,
-    network: crate::types::Network,
-) -> Result<bool> {
-    match opcode {
-        // OP_CHECKSIG - verify ECDSA signature
-        0xac => {
-            if stack.len() >= 2 {
-                let pubkey_bytes = stack.pop().unwrap();
-                let signature_bytes = stack.pop().unwrap();
+/// OP_1NEGATE and OP_1 to OP_16 - push -1 and the numbers 1-16
+fn op_push_number(opcode: u8, stack: &mut Vec<ByteString>, _flags: u32) -> Result<bool> {
+    stack.push(script_num_bytes(opcode as i64 - 0x50));
+    Ok(true)
+}
+
+/// OP_DUP - duplicate top stack item
+fn op_dup(_opcode: u8, stack: &mut Vec<ByteString>, _flags: u32) -> Result<bool> {
+    if let Some(item) = stack.last().cloned() {
+        stack.push(item);
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
 
-                // Calculate transaction sighash for signature verification
-                // Optimization: Use batch computation if available (for transactions with multiple inputs)
-                use crate::transaction_hash::{calculate_transaction_sighash, SighashType};
-                let sighash = {
-                    #[cfg(feature = "production")]
-                    {
-                        use crate::transaction_hash::batch_compute_sighashes;
-                        // Use batch computation if we have multiple inputs (more efficient)
-                        if tx.inputs.len() > 1 {
-                            let sighashes =
-                                batch_compute_sighashes(tx, prevouts, SighashType::All)?;
-                            sighashes[input_index]
-                        } else {
-                            // Single input: use individual calculation (no overhead)
-                            calculate_transaction_sighash(
-                                tx,
-                                input_index,
-                                prevouts,
-                                SighashType::All,
-                            )?
+/// OP_HASH160 - RIPEMD160(SHA256(x))
+fn op_hash160(_opcode: u8, stack: &mut Vec<ByteString>, _flags: u32) -> Result<bool> {
+    if let Some(item) = stack.pop() {
+        #[cfg(feature = "production")]
+        {
+            // Check hash cache first (unless disabled)
+            if !is_caching_disabled() {
+                let cache_key = compute_hash_cache_key(&item, true);
+                {
+                    let cache = get_hash_cache().read().unwrap();
+                    if let Some(cached_result) = cache.peek(&cache_key) {
+                        // Verify cached result is HASH160 (20 bytes)
+                        if cached_result.len() == 20 {
+                            stack.push(cached_result.clone());
+                            return Ok(true);
                         }
                     }
-                    #[cfg(not(feature = "production"))]
-                    {
-                        calculate_transaction_sighash(tx, input_index, prevouts, SighashType::All)?
-                    }
-                };
+                }
+            }
 
-                // Verify signature with real transaction hash
-                let height = block_height.unwrap_or(0);
-                #[cfg(feature = "production")]
-                let is_valid = SECP256K1_CONTEXT.with(|secp| {
-                    verify_signature(
-                        secp,
-                        &pubkey_bytes,
-                        &signature_bytes,
-                        &sighash,
-                        flags,
-                        height,
-                        network,
-                    )
-                })?;
-
-                #[cfg(not(feature = "production"))]
-                let is_valid = {
-                    let secp = Secp256k1::new();
-                    verify_signature(
-                        &secp,
-                        &pubkey_bytes,
-                        &signature_bytes,
-                        &sighash,
-                        flags,
-                        height,
-                        network,
-                    )?
-                };
+            // Compute hash (cache miss or caching disabled)
+            let sha256_hash = Sha256::digest(&item);
+            let ripemd160_hash = Ripemd160::digest(sha256_hash);
+            let result = ripemd160_hash.to_vec();
 
-                stack.push(vec![if is_valid { 1 } else { 0 }]);
-                Ok(true)
-            } else {
-                Ok(false)
+            // Cache result (unless disabled)
+            if !is_caching_disabled() {
+                let cache_key = compute_hash_cache_key(&item, true);
+                let mut cache = get_hash_cache().write().unwrap();
+                cache.put(cache_key, result.clone());
             }
+
+            stack.push(result);
+            Ok(true)
         }
 
-        // OP_CHECKSIGVERIFY - verify ECDSA signature and remove from stack
-        0xad => {
-            if stack.len() >= 2 {
-                let pubkey_bytes = stack.pop().unwrap();
-                let signature_bytes = stack.pop().unwrap();
+        #[cfg(not(feature = "production"))]
+        {
+            let sha256_hash = Sha256::digest(&item);
+            let ripemd160_hash = Ripemd160::digest(sha256_hash);
+            stack.push(ripemd160_hash.to_vec());
+            Ok(true)
+        }
+    } else {
+        Ok(false)
+    }
+}
 
-                // Calculate transaction sighash for signature verification
-                // Optimization: Use batch computation if available (for transactions with multiple inputs)
-                use crate::transaction_hash::{calculate_transaction_sighash, SighashType};
-                let sighash = {
-                    #[cfg(feature = "production")]
-                    {
-                        use crate::transaction_hash::batch_compute_sighashes;
-                        // Use batch computation if we have multiple inputs (more efficient)
-                        if tx.inputs.len() > 1 {
-                            let sighashes =
-                                batch_compute_sighashes(tx, prevouts, SighashType::All)?;
-                            sighashes[input_index]
-                        } else {
-                            // Single input: use individual calculation (no overhead)
-                            calculate_transaction_sighash(
-                                tx,
-                                input_index,
-                                prevouts,
-                                SighashType::All,
-                            )?
+/// OP_HASH256 - SHA256(SHA256(x))
+fn op_hash256(_opcode: u8, stack: &mut Vec<ByteString>, _flags: u32) -> Result<bool> {
+    if let Some(item) = stack.pop() {
+        #[cfg(feature = "production")]
+        {
+            // Check hash cache first (unless disabled)
+            if !is_caching_disabled() {
+                let cache_key = compute_hash_cache_key(&item, false);
+                {
+                    let cache = get_hash_cache().read().unwrap();
+                    if let Some(cached_result) = cache.peek(&cache_key) {
+                        // Verify cached result is HASH256 (32 bytes)
+                        if cached_result.len() == 32 {
+                            stack.push(cached_result.clone());
+                            return Ok(true);
                         }
                     }
-                    #[cfg(not(feature = "production"))]
-                    {
-                        calculate_transaction_sighash(tx, input_index, prevouts, SighashType::All)?
-                    }
-                };
+                }
+            }
 
-                // Verify signature with real transaction hash
-                let height = block_height.unwrap_or(0);
-                #[cfg(feature = "production")]
-                let is_valid = SECP256K1_CONTEXT.with(|secp| {
-                    verify_signature(
-                        secp,
-                        &pubkey_bytes,
-                        &signature_bytes,
-                        &sighash,
-                        flags,
-                        height,
-                        network,
-                    )
-                })?;
-
-                #[cfg(not(feature = "production"))]
-                let is_valid = {
-                    let secp = Secp256k1::new();
-                    verify_signature(
-                        &secp,
-                        &pubkey_bytes,
-                        &signature_bytes,
-                        &sighash,
-                        flags,
-                        height,
-                        network,
-                    )?
-                };
+            // Compute hash (cache miss or caching disabled)
+            let hash1 = Sha256::digest(&item);
+            let hash2 = Sha256::digest(hash1);
+            let result = hash2.to_vec();
 
-                if is_valid {
-                    Ok(true)
-                } else {
-                    Ok(false)
-                }
-            } else {
-                Ok(false)
+            // Cache result (unless disabled)
+            if !is_caching_disabled() {
+                let cache_key = compute_hash_cache_key(&item, false);
+                let mut cache = get_hash_cache().write().unwrap();
+                cache.put(cache_key, result.clone());
             }
+
+            stack.push(result);
+            Ok(true)
         }
 
-        // OP_CHECKLOCKTIMEVERIFY (BIP65) - 0xb1
-        // Validates that transaction locktime is >= top stack item
-        // Requires: block height and median time-past for full validation (BIP113)
-        // Note: Full BIP65 validation requires median time-past (BIP113) when locktime is time-based.
-        // This implementation validates locktime types match and transaction locktime >= required locktime.
+        #[cfg(not(feature = "production"))]
+        {
+            let hash1 = Sha256::digest(&item);
+            let hash2 = Sha256::digest(hash1);
+            stack.push(hash2.to_vec());
+            Ok(true)
+        }
+    } else {
+        Ok(false)
+    }
+}
+
+/// OP_EQUAL - check if top two stack items are equal
+fn op_equal(_opcode: u8, stack: &mut Vec<ByteString>, _flags: u32) -> Result<bool> {
+    if stack.len() < 2 {
+        return Ok(false);
+    }
+    let a = stack.pop().unwrap();
+    let b = stack.pop().unwrap();
+    stack.push(if a == b { vec![1] } else { vec![] });
+    Ok(true)
+}
+
+/// OP_EQUALVERIFY - verify top two stack items are equal
+fn op_equalverify(_opcode: u8, stack: &mut Vec<ByteString>, _flags: u32) -> Result<bool> {
+    if stack.len() < 2 {
+        return Ok(false);
+    }
+    let a = stack.pop().unwrap();
+    let b = stack.pop().unwrap();
+    if a != b {
+        return Err(make_equalverify_error());
+    }
+    Ok(true)
+}
+
+/// OP_1ADD, OP_1SUB, OP_NEGATE, OP_ABS, OP_NOT, OP_0NOTEQUAL
+fn op_unary_numeric(opcode: u8, stack: &mut Vec<ByteString>, flags: u32) -> Result<bool> {
+    Ok(execute_unary_numeric_opcode(opcode, stack, flags))
+}
+
+/// OP_ADD, OP_SUB, OP_BOOLAND .. OP_MAX (OP_MUL .. OP_RSHIFT are disabled)
+fn op_binary_numeric(opcode: u8, stack: &mut Vec<ByteString>, flags: u32) -> Result<bool> {
+    Ok(execute_binary_numeric_opcode(opcode, stack, flags))
+}
+
+/// OP_WITHIN - x min max -> min <= x < max
+fn op_within(_opcode: u8, stack: &mut Vec<ByteString>, flags: u32) -> Result<bool> {
+    if stack.len() < 3 {
+        return Ok(false);
+    }
+    let max = stack.pop().unwrap();
+    let min = stack.pop().unwrap();
+    let x = stack.pop().unwrap();
+    match (
+        decode_script_num(&x, flags),
+        decode_script_num(&min, flags),
+        decode_script_num(&max, flags),
+    ) {
+        (Some(x), Some(min), Some(max)) => {
+            stack.push(script_num_bytes((min <= x && x < max) as i64));
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
+
+/// OP_CHECKSIG - verify ECDSA signature
+fn op_checksig(_opcode: u8, stack: &mut Vec<ByteString>, flags: u32) -> Result<bool> {
+    if stack.len() < 2 {
+        return Ok(false);
+    }
+    let pubkey_bytes = stack.pop().unwrap();
+    let signature_bytes = stack.pop().unwrap();
+    if !check_signature_hashtype(&signature_bytes, flags) {
+        return Ok(false);
+    }
+
+    // Verify signature using secp256k1 (dummy hash for legacy compatibility)
+    // Note: Without transaction context, we use height 0 and Regtest network
+    // This is only used in basic execute_opcode without transaction context
+    let dummy_hash = [0u8; 32];
+    let result = verify_signature(
+        default_verifier(),
+        &pubkey_bytes,
+        &signature_bytes,
+        &dummy_hash,
+        flags,
+        0,
+        crate::types::Network::Regtest,
+    );
+
+    stack.push(if result.unwrap_or(false) {
+        vec![1]
+    } else {
+        vec![]
+    });
+    Ok(true)
+}
+
+/// OP_CHECKSIGVERIFY - verify ECDSA signature and fail if invalid
+fn op_checksigverify(_opcode: u8, stack: &mut Vec<ByteString>, flags: u32) -> Result<bool> {
+    if stack.len() < 2 {
+        return Ok(false);
+    }
+    let pubkey_bytes = stack.pop().unwrap();
+    let signature_bytes = stack.pop().unwrap();
+    if !check_signature_hashtype(&signature_bytes, flags) {
+        return Ok(false);
+    }
+
+    // Verify signature using secp256k1 (dummy hash for legacy compatibility)
+    // Note: Without transaction context, we use height 0 and Regtest network
+    // This is only used in basic execute_opcode without transaction context
+    let dummy_hash = [0u8; 32];
+    let result = verify_signature(
+        default_verifier(),
+        &pubkey_bytes,
+        &signature_bytes,
+        &dummy_hash,
+        flags,
+        0,
+        crate::types::Network::Regtest,
+    );
+
+    Ok(result.unwrap_or(false))
+}
+
+/// OP_CHECKMULTISIG / OP_CHECKMULTISIGVERIFY - against the same dummy
+/// hash, height and network as OP_CHECKSIG above
+fn op_checkmultisig(opcode: u8, stack: &mut Vec<ByteString>, flags: u32) -> Result<bool> {
+    execute_multisig_opcode(
+        opcode,
+        stack,
+        flags,
+        0,
+        crate::types::Network::Regtest,
+        default_verifier(),
+        |_, _| Ok([0u8; 32]),
+    )
+}
+
+/// OP_NOP - do nothing
+#[allow(clippy::ptr_arg)] // signature fixed by `OpcodeHandler`
+fn op_nop(_opcode: u8, _stack: &mut Vec<ByteString>, _flags: u32) -> Result<bool> {
+    Ok(true)
+}
+
+/// OP_RETURN - always fail
+#[allow(clippy::ptr_arg)] // signature fixed by `OpcodeHandler`
+fn op_return(_opcode: u8, _stack: &mut Vec<ByteString>, _flags: u32) -> Result<bool> {
+    Ok(false)
+}
+
+/// OP_VERIFY - check if top stack item is non-zero
+fn op_verify(_opcode: u8, stack: &mut Vec<ByteString>, _flags: u32) -> Result<bool> {
+    if let Some(item) = stack.pop() {
+        Ok(!item.is_empty() && item[0] != 0)
+    } else {
+        Ok(false)
+    }
+}
+
+/// OP_CHECKLOCKTIMEVERIFY (BIP65) - 0xb1
+/// Note: Requires transaction context for proper validation.
+/// This basic implementation will fail - use verify_script_with_context for proper CLTV validation.
+#[allow(clippy::ptr_arg)] // signature fixed by `OpcodeHandler`
+fn op_checklocktimeverify(_opcode: u8, _stack: &mut Vec<ByteString>, _flags: u32) -> Result<bool> {
+    // CLTV requires transaction locktime and block context, so it always fails here
+    // Proper implementation is in execute_opcode_with_context
+    Ok(false)
+}
+
+/// OP_CHECKSEQUENCEVERIFY (BIP112) - 0xb2
+/// Note: Requires transaction context for proper validation.
+/// This basic implementation will fail - use verify_script_with_context for proper CSV validation.
+#[allow(clippy::ptr_arg)] // signature fixed by `OpcodeHandler`
+fn op_checksequenceverify(_opcode: u8, _stack: &mut Vec<ByteString>, flags: u32) -> Result<bool> {
+    // Without SCRIPT_VERIFY_CHECKSEQUENCEVERIFY this is OP_NOP3. Otherwise CSV
+    // needs the input's sequence, so it always fails here; the proper
+    // implementation is in execute_opcode_with_context_full
+    Ok(flags & SCRIPT_VERIFY_CHECKSEQUENCEVERIFY == 0)
+}
+
+/// OP_IFDUP - duplicate top stack item if it's non-zero
+fn op_ifdup(_opcode: u8, stack: &mut Vec<ByteString>, _flags: u32) -> Result<bool> {
+    if let Some(item) = stack.last().cloned() {
+        if !item.is_empty() && item[0] != 0 {
+            stack.push(item);
+        }
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
+
+/// OP_DEPTH - push stack size
+fn op_depth(_opcode: u8, stack: &mut Vec<ByteString>, _flags: u32) -> Result<bool> {
+    stack.push(script_num_bytes(stack.len() as i64));
+    Ok(true)
+}
+
+/// OP_DROP - remove top stack item
+fn op_drop(_opcode: u8, stack: &mut Vec<ByteString>, _flags: u32) -> Result<bool> {
+    if stack.pop().is_some() {
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
+
+/// OP_NIP - remove second-to-top stack item
+fn op_nip(_opcode: u8, stack: &mut Vec<ByteString>, _flags: u32) -> Result<bool> {
+    if stack.len() >= 2 {
+        let top = stack.pop().unwrap();
+        stack.pop(); // Remove second-to-top
+        stack.push(top);
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
+
+/// OP_OVER - copy second-to-top stack item to top
+fn op_over(_opcode: u8, stack: &mut Vec<ByteString>, _flags: u32) -> Result<bool> {
+    if stack.len() >= 2 {
+        let second = stack[stack.len() - 2].clone();
+        stack.push(second);
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
+
+/// OP_PICK - copy nth stack item to top
+fn op_pick(_opcode: u8, stack: &mut Vec<ByteString>, flags: u32) -> Result<bool> {
+    let Some(index) = pop_stack_index(stack, flags) else {
+        return Ok(false);
+    };
+    let item = stack[index].clone();
+    stack.push(item);
+    Ok(true)
+}
+
+/// OP_ROLL - move nth stack item to top
+fn op_roll(_opcode: u8, stack: &mut Vec<ByteString>, flags: u32) -> Result<bool> {
+    let Some(index) = pop_stack_index(stack, flags) else {
+        return Ok(false);
+    };
+    let item = stack.remove(index);
+    stack.push(item);
+    Ok(true)
+}
+
+/// OP_ROT - rotate top 3 stack items
+fn op_rot(_opcode: u8, stack: &mut Vec<ByteString>, _flags: u32) -> Result<bool> {
+    if stack.len() >= 3 {
+        let top = stack.pop().unwrap();
+        let second = stack.pop().unwrap();
+        let third = stack.pop().unwrap();
+        stack.push(second);
+        stack.push(top);
+        stack.push(third);
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
+
+/// OP_SWAP - swap top 2 stack items
+fn op_swap(_opcode: u8, stack: &mut Vec<ByteString>, _flags: u32) -> Result<bool> {
+    if stack.len() >= 2 {
+        let top = stack.pop().unwrap();
+        let second = stack.pop().unwrap();
+        stack.push(top);
+        stack.push(second);
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
+
+/// OP_TUCK - copy top stack item to before second-to-top
+fn op_tuck(_opcode: u8, stack: &mut Vec<ByteString>, _flags: u32) -> Result<bool> {
+    if stack.len() >= 2 {
+        let top = stack.pop().unwrap();
+        let second = stack.pop().unwrap();
+        stack.push(top.clone());
+        stack.push(second);
+        stack.push(top);
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
+
+/// OP_2DROP - remove top 2 stack items
+fn op_2drop(_opcode: u8, stack: &mut Vec<ByteString>, _flags: u32) -> Result<bool> {
+    if stack.len() >= 2 {
+        stack.pop();
+        stack.pop();
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
+
+/// OP_2DUP - duplicate top 2 stack items
+fn op_2dup(_opcode: u8, stack: &mut Vec<ByteString>, _flags: u32) -> Result<bool> {
+    if stack.len() >= 2 {
+        let top = stack[stack.len() - 1].clone();
+        let second = stack[stack.len() - 2].clone();
+        stack.push(second);
+        stack.push(top);
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
+
+/// OP_3DUP - duplicate top 3 stack items
+fn op_3dup(_opcode: u8, stack: &mut Vec<ByteString>, _flags: u32) -> Result<bool> {
+    if stack.len() >= 3 {
+        let top = stack[stack.len() - 1].clone();
+        let second = stack[stack.len() - 2].clone();
+        let third = stack[stack.len() - 3].clone();
+        stack.push(third);
+        stack.push(second);
+        stack.push(top);
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
+
+/// OP_2OVER - copy second pair of stack items to top
+fn op_2over(_opcode: u8, stack: &mut Vec<ByteString>, _flags: u32) -> Result<bool> {
+    if stack.len() >= 4 {
+        let fourth = stack[stack.len() - 4].clone();
+        let third = stack[stack.len() - 3].clone();
+        stack.push(fourth);
+        stack.push(third);
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
+
+/// OP_2ROT - rotate second pair of stack items to top
+fn op_2rot(_opcode: u8, stack: &mut Vec<ByteString>, _flags: u32) -> Result<bool> {
+    if stack.len() >= 6 {
+        let sixth = stack.remove(stack.len() - 6);
+        let fifth = stack.remove(stack.len() - 5);
+        stack.push(fifth);
+        stack.push(sixth);
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
+
+/// OP_2SWAP - swap second pair of stack items
+fn op_2swap(_opcode: u8, stack: &mut Vec<ByteString>, _flags: u32) -> Result<bool> {
+    if stack.len() >= 4 {
+        let top = stack.pop().unwrap();
+        let second = stack.pop().unwrap();
+        let third = stack.pop().unwrap();
+        let fourth = stack.pop().unwrap();
+        stack.push(second);
+        stack.push(top);
+        stack.push(fourth);
+        stack.push(third);
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
+
+/// OP_SIZE - push size of top stack item
+fn op_size(_opcode: u8, stack: &mut Vec<ByteString>, _flags: u32) -> Result<bool> {
+    if let Some(item) = stack.last() {
+        stack.push(script_num_bytes(item.len() as i64));
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
+
+/// Unknown opcode
+#[allow(clippy::ptr_arg)] // signature fixed by `OpcodeHandler`
+fn op_unknown(_opcode: u8, _stack: &mut Vec<ByteString>, _flags: u32) -> Result<bool> {
+    Ok(false)
+}
+
+/// Execute a single opcode with transaction context for signature verification
+#[allow(dead_code)]
+#[allow(clippy::too_many_arguments)]
+fn execute_opcode_with_context(
+    opcode: u8,
+    stack: &mut Vec<ByteString>,
+    flags: u32,
+    tx: &Transaction,
+    input_index: usize,
+    prevouts: &[PrevOut],
+    script_code: &[u8],
+    network: crate::types::Network,
+) -> Result<bool> {
+    execute_opcode_with_context_full(
+        opcode,
+        stack,
+        flags,
+        tx,
+        input_index,
+        prevouts,
+        script_code,
+        SigVersion::Base,
+        None, // block_height
+        None, // median_time_past
+        network,
+        default_verifier(),
+    )
+}
+
+/// Decode a numeric opcode operand as a `DEFAULT_MAX_NUM_SIZE`-byte script number
+///
+/// Under `SCRIPT_VERIFY_MINIMALDATA` the operand must also be minimally
+/// encoded. Returns `None` for operands that do not decode.
+fn decode_script_num(bytes: &[u8], flags: u32) -> Option<i64> {
+    let require_minimal = flags & SCRIPT_VERIFY_MINIMALDATA != 0;
+    ScriptNum::from_bytes(bytes, DEFAULT_MAX_NUM_SIZE, require_minimal)
+        .ok()
+        .map(ScriptNum::value)
+}
+
+/// Minimal encoding of a numeric result (see `ScriptNum::to_bytes`)
+fn script_num_bytes(value: i64) -> ByteString {
+    ScriptNum::new(value).to_bytes()
+}
+
+/// Execute a unary numeric opcode (OP_1ADD .. OP_0NOTEQUAL)
+///
+/// The operand must decode with `decode_script_num`; the result is pushed
+/// minimally encoded. Returns false on an empty stack or an invalid operand.
+fn execute_unary_numeric_opcode(opcode: u8, stack: &mut Vec<ByteString>, flags: u32) -> bool {
+    let Some(a) = stack.pop().and_then(|a| decode_script_num(&a, flags)) else {
+        return false;
+    };
+    let result = match opcode {
+        0x8b => a + 1,
+        0x8c => a - 1,
+        0x8f => -a,
+        0x90 => a.abs(),
+        0x91 => (a == 0) as i64,
+        0x92 => (a != 0) as i64,
+        _ => return false,
+    };
+    stack.push(script_num_bytes(result));
+    true
+}
+
+/// Execute a binary numeric opcode (OP_ADD, OP_SUB, OP_BOOLAND .. OP_MAX)
+///
+/// Both operands must decode with `decode_script_num`. They are limited to
+/// 4 bytes, so no result can overflow an `i64`; a result that needs 5 bytes is
+/// pushed as is and fails when used as an operand. OP_NUMEQUALVERIFY pushes
+/// nothing and returns whether the operands are equal.
+fn execute_binary_numeric_opcode(opcode: u8, stack: &mut Vec<ByteString>, flags: u32) -> bool {
+    if stack.len() < 2 {
+        return false;
+    }
+    let b = stack.pop().unwrap();
+    let a = stack.pop().unwrap();
+    let (Some(a), Some(b)) = (decode_script_num(&a, flags), decode_script_num(&b, flags)) else {
+        return false;
+    };
+    let result = match opcode {
+        0x93 => a + b,
+        0x94 => a - b,
+        0x9a => (a != 0 && b != 0) as i64,
+        0x9b => (a != 0 || b != 0) as i64,
+        0x9c | 0x9d => (a == b) as i64,
+        0x9e => (a != b) as i64,
+        0x9f => (a < b) as i64,
+        0xa0 => (a > b) as i64,
+        0xa1 => (a <= b) as i64,
+        0xa2 => (a >= b) as i64,
+        0xa3 => a.min(b),
+        0xa4 => a.max(b),
+        _ => return false,
+    };
+    if opcode == 0x9d {
+        return result != 0;
+    }
+    stack.push(script_num_bytes(result));
+    true
+}
+
+/// Pop the depth operand of OP_PICK/OP_ROLL and resolve it to a stack position
+///
+/// The operand is a CScriptNum counted from the top of the remaining stack;
+/// `None` if it does not decode, is negative, or reaches past the bottom.
+fn pop_stack_index(stack: &mut Vec<ByteString>, flags: u32) -> Option<usize> {
+    let n = decode_script_num(&stack.pop()?, flags)?;
+    let n = usize::try_from(n).ok()?;
+    (n < stack.len()).then(|| stack.len() - 1 - n)
+}
+
+/// Execute a single opcode with full context including block height, median time-past, and network
+///
+/// `script_code` is the executing script from just after its last executed
+/// OP_CODESEPARATOR, which signature checks sign as `sig_version` requires
+/// (see `signature_hash`).
+#[allow(clippy::too_many_arguments)]
+fn execute_opcode_with_context_full(
+    opcode: u8,
+    stack: &mut Vec<ByteString>,
+    flags: u32,
+    tx: &Transaction,
+    input_index: usize,
+    prevouts: &[PrevOut],
+    script_code: &[u8],
+    sig_version: SigVersion,
+    block_height: Option<u64>,
+    median_time_past: Option<u64>,
+    network: crate::types::Network,
+    verifier: &dyn SignatureVerifier,
+) -> Result<bool> {
+    match opcode {
+        // OP_CHECKSIG - verify ECDSA signature
+        0xac => {
+            if stack.len() >= 2 {
+                let pubkey_bytes = stack.pop().unwrap();
+                let signature_bytes = stack.pop().unwrap();
+                if !check_signature_hashtype(&signature_bytes, flags) {
+                    return Ok(false);
+                }
+
+                // An empty signature is false without hashing or verifying,
+                // and is the one failing signature NULLFAIL permits
+                if signature_bytes.is_empty() {
+                    stack.push(vec![]);
+                    return Ok(true);
+                }
+
+                // The signature commits to the scriptCode, never to the scriptSig
+                // that carries it, under the hash type in its last byte
+                let sighash = signature_hash(
+                    tx,
+                    input_index,
+                    prevouts,
+                    script_code,
+                    std::slice::from_ref(&signature_bytes),
+                    signature_hash_type(&signature_bytes),
+                    sig_version,
+                )?;
+
+                // Verify signature with real transaction hash
+                let height = block_height.unwrap_or(0);
+                let is_valid = verify_signature(
+                    verifier,
+                    &pubkey_bytes,
+                    &signature_bytes,
+                    &sighash,
+                    flags,
+                    height,
+                    network,
+                )?;
+
+                // A failing non-empty signature fails the script instead of
+                // pushing false
+                if !is_valid && flags & SCRIPT_VERIFY_NULLFAIL != 0 {
+                    return Ok(false);
+                }
+
+                stack.push(if is_valid { vec![1] } else { vec![] });
+                Ok(true)
+            } else {
+                Ok(false)
+            }
+        }
+
+        // OP_CHECKSIGVERIFY - verify ECDSA signature and remove from stack
+        0xad => {
+            if stack.len() >= 2 {
+                let pubkey_bytes = stack.pop().unwrap();
+                let signature_bytes = stack.pop().unwrap();
+                if !check_signature_hashtype(&signature_bytes, flags) {
+                    return Ok(false);
+                }
+
+                // An empty signature can never verify
+                if signature_bytes.is_empty() {
+                    return Ok(false);
+                }
+
+                // The signature commits to the scriptCode, never to the scriptSig
+                // that carries it, under the hash type in its last byte
+                let sighash = signature_hash(
+                    tx,
+                    input_index,
+                    prevouts,
+                    script_code,
+                    std::slice::from_ref(&signature_bytes),
+                    signature_hash_type(&signature_bytes),
+                    sig_version,
+                )?;
+
+                // Verify signature with real transaction hash
+                let height = block_height.unwrap_or(0);
+                let is_valid = verify_signature(
+                    verifier,
+                    &pubkey_bytes,
+                    &signature_bytes,
+                    &sighash,
+                    flags,
+                    height,
+                    network,
+                )?;
+
+                if is_valid {
+                    Ok(true)
+                } else {
+                    Ok(false)
+                }
+            } else {
+                Ok(false)
+            }
+        }
+
+        // OP_CHECKLOCKTIMEVERIFY (BIP65) - 0xb1
+        // Validates that transaction locktime is >= top stack item
+        // Requires: block height and median time-past for full validation (BIP113)
+        // Note: Full BIP65 validation requires median time-past (BIP113) when locktime is time-based.
+        // This implementation validates locktime types match and transaction locktime >= required locktime.
         0xb1 => {
-            use crate::locktime::{decode_locktime_value, get_locktime_type, locktime_types_match};
+            use crate::locktime::{
+                decode_locktime_value, get_locktime_type, is_negative_locktime,
+                locktime_types_match,
+            };
 
             if stack.is_empty() {
                 return Ok(false);
             }
 
-            // Decode locktime value from stack using shared locktime logic
+            // BIP65: a negative operand fails (SCRIPT_ERR_NEGATIVE_LOCKTIME)
             let locktime_bytes = stack.last().unwrap();
+            if is_negative_locktime(locktime_bytes) {
+                return Ok(false);
+            }
+
+            // Decode locktime value from stack using shared locktime logic
             let locktime_value = match decode_locktime_value(locktime_bytes) {
                 Some(v) => v,
                 None => return Ok(false), // Invalid encoding
@@ -1324,9 +2355,13 @@ fn execute_opcode_with_context_full(
                 return Ok(false);
             }
 
-            let tx_locktime = tx.lock_time as u32;
+            // nLockTime is serialized as 4 bytes; anything wider cannot be satisfied
+            let Ok(tx_locktime) = u32::try_from(tx.lock_time) else {
+                return Ok(false);
+            };
 
-            // BIP65: Types must match (both block height or both timestamp)
+            // BIP65: Types must match (both block height or both timestamp);
+            // a height never satisfies a time lock or vice versa
             if !locktime_types_match(tx_locktime, locktime_value) {
                 return Ok(false);
             }
@@ -1369,203 +2404,419 @@ fn execute_opcode_with_context_full(
         // OP_CHECKSEQUENCEVERIFY (BIP112) - 0xb2
         // Validates that transaction input sequence number meets relative locktime requirement
         // Implements BIP68: Relative Lock-Time Using Consensus-Enforced Sequence Numbers
+        // Like OP_NOP3, which it redefines, it leaves its operand on the stack
         0xb2 => {
             use crate::locktime::{
                 decode_locktime_value, extract_sequence_locktime_value, extract_sequence_type_flag,
-                is_sequence_disabled,
+                is_negative_locktime, is_sequence_disabled,
             };
 
-            if stack.is_empty() {
+            // Not enforced: OP_NOP3
+            if flags & SCRIPT_VERIFY_CHECKSEQUENCEVERIFY == 0 {
+                return Ok(true);
+            }
+
+            let Some(sequence_bytes) = stack.last() else {
+                return Ok(false);
+            };
+
+            // BIP112: a negative operand fails (SCRIPT_ERR_NEGATIVE_LOCKTIME)
+            if is_negative_locktime(sequence_bytes) {
                 return Ok(false);
             }
 
             // Decode sequence value from stack using shared locktime logic
-            let sequence_bytes = stack.last().unwrap();
             let sequence_value = match decode_locktime_value(sequence_bytes) {
                 Some(v) => v,
                 None => return Ok(false), // Invalid encoding
             };
 
-            // Get input sequence number
-            if input_index >= tx.inputs.len() {
-                return Ok(false);
+            // BIP112: an operand with the disable flag set makes CSV a NOP
+            if is_sequence_disabled(sequence_value) {
+                return Ok(true);
             }
-            let input_sequence = tx.inputs[input_index].sequence as u32;
 
-            // BIP112/BIP68: Check if sequence is disabled (0x80000000 bit set)
-            // If disabled, CSV always fails
-            if is_sequence_disabled(input_sequence) {
+            // BIP68 sequence locks only apply to version 2+ transactions
+            if tx.version < 2 {
                 return Ok(false);
             }
 
-            // BIP68: Extract relative locktime type and value using shared logic
-            let type_flag = extract_sequence_type_flag(sequence_value);
-            let locktime_mask = extract_sequence_locktime_value(sequence_value) as u32;
-
-            // Extract input sequence flags and value
-            let input_type_flag = extract_sequence_type_flag(input_sequence);
-            let input_locktime = extract_sequence_locktime_value(input_sequence) as u32;
+            // Get input sequence number
+            if input_index >= tx.inputs.len() {
+                return Ok(false);
+            }
+            let input_sequence = tx.inputs[input_index].sequence as u32;
 
-            // BIP112: CSV fails if type_flag doesn't match input type
-            if type_flag != input_type_flag {
+            // BIP112: the input must itself carry an enabled relative lock
+            if is_sequence_disabled(input_sequence) {
                 return Ok(false);
             }
 
-            // BIP112: CSV fails if input locktime < required locktime
-            if input_locktime < locktime_mask {
+            // BIP112: both must be heights or both 512-second units (bit 22)
+            if extract_sequence_type_flag(sequence_value)
+                != extract_sequence_type_flag(input_sequence)
+            {
                 return Ok(false);
             }
 
-            // Validation passed - pop sequence value
-            stack.pop();
-            Ok(true)
+            // BIP112: the input's relative lock must be at least the operand's
+            Ok(extract_sequence_locktime_value(input_sequence)
+                >= extract_sequence_locktime_value(sequence_value))
         }
 
-        // OP_CHECKMULTISIG - verify m-of-n multisig
-        // Stack: [dummy] [sig1] [sig2] ... [sigm] [m] [pubkey1] ... [pubkeyn] [n]
-        // BIP147: Dummy element must be empty (OP_0) after activation
-        0xae => {
-            // BIP147: Check NULLDUMMY if flag is set (SCRIPT_VERIFY_NULLDUMMY = 0x10)
-            if flags & 0x10 != 0 {
-                let height = block_height.unwrap_or(0);
-                // Convert network type for BIP147
-                use crate::bip_validation::Bip147Network;
-                let bip147_network = match network {
-                    crate::types::Network::Mainnet => Bip147Network::Mainnet,
-                    crate::types::Network::Testnet => Bip147Network::Testnet,
-                    crate::types::Network::Regtest => Bip147Network::Regtest,
-                };
+        // OP_CHECKMULTISIG / OP_CHECKMULTISIGVERIFY - verify m-of-n multisig
+        0xae | 0xaf => execute_multisig_opcode(
+            opcode,
+            stack,
+            flags,
+            block_height.unwrap_or(0),
+            network,
+            verifier,
+            |signatures, hash_type| {
+                signature_hash(
+                    tx,
+                    input_index,
+                    prevouts,
+                    script_code,
+                    signatures,
+                    hash_type,
+                    sig_version,
+                )
+            },
+        ),
 
-                // Get scriptSig and scriptPubkey from context
-                // For OP_CHECKMULTISIG, we need to check the dummy element
-                // The dummy is the first element consumed, which is the last element pushed
-                // We check if the last stack element before OP_CHECKMULTISIG is OP_0
-                if stack.is_empty() {
-                    return Ok(false);
-                }
+        // For all other opcodes, delegate to the original execute_opcode
+        _ => execute_opcode(opcode, stack, flags),
+    }
+}
 
-                // The dummy element is the last element on the stack before OP_CHECKMULTISIG
-                // For BIP147, it must be empty (OP_0 = 0x00)
-                let dummy = stack.last().unwrap();
-                if !dummy.is_empty() && dummy[0] != 0x00 {
-                    // Check BIP147 - this will handle activation height
-                    // We need scriptSig and scriptPubkey, but we only have the stack
-                    // For now, we'll check the dummy element directly
-                    // A full implementation would need access to the full script context
-                    if height
-                        >= match bip147_network {
-                            Bip147Network::Mainnet => 481_824,
-                            Bip147Network::Testnet => 834_624,
-                            Bip147Network::Regtest => 0,
-                        }
-                    {
-                        return Ok(false); // BIP147 violation: non-empty dummy
-                    }
-                }
-            }
+/// Which signature hash CHECKSIG and CHECKMULTISIG check against
+///
+/// Reference: Bitcoin Core `interpreter.h` SigVersion
+#[derive(Debug, Clone, Copy)]
+enum SigVersion<'a> {
+    /// Bare and P2SH scripts: the original algorithm (see `legacy_script_code`)
+    Base,
+    /// Witness v0 scripts: BIP143, with the transaction's shared digests
+    WitnessV0(&'a SighashCache),
+}
 
-            // OP_CHECKMULTISIG implementation
-            // Stack layout: [dummy] [sig1] ... [sigm] [m] [pubkey1] ... [pubkeyn] [n]
-            if stack.len() < 2 {
-                return Ok(false);
-            }
+/// The hash a CHECKSIG-family signature signs
+///
+/// `script_code` runs from just after the last executed OP_CODESEPARATOR, and
+/// `signatures` are the signatures checked against it. A legacy signature
+/// signs `script_code` with those signatures removed; a witness v0 signature
+/// signs it as is, together with the amount of the spent output.
+fn signature_hash(
+    tx: &Transaction,
+    input_index: usize,
+    prevouts: &[PrevOut],
+    script_code: &[u8],
+    signatures: &[ByteString],
+    hash_type: u32,
+    sig_version: SigVersion,
+) -> Result<Hash> {
+    use crate::transaction_hash::{calculate_bip143_sighash_cached, calculate_legacy_sighash};
+
+    match sig_version {
+        SigVersion::Base => calculate_legacy_sighash(
+            tx,
+            input_index,
+            prevouts,
+            &legacy_script_code(script_code, signatures),
+            hash_type,
+        ),
+        SigVersion::WitnessV0(cache) => {
+            let amount = prevouts
+                .get(input_index)
+                .ok_or(ConsensusError::InvalidInputIndex(input_index))?
+                .value;
+            calculate_bip143_sighash_cached(tx, input_index, script_code, amount, hash_type, cache)
+        }
+    }
+}
 
-            // Pop n (number of public keys)
-            let n_bytes = stack.pop().unwrap();
-            if n_bytes.is_empty() {
-                return Ok(false);
-            }
-            let n = n_bytes[0] as usize;
-            if n > 20 || stack.len() < n + 1 {
-                return Ok(false);
-            }
+/// The scriptCode a legacy (pre-segwit) signature commits to
+///
+/// `script_code` runs from just after the last executed OP_CODESEPARATOR.
+/// Every push of one of `signatures` is removed, since a signature cannot sign
+/// itself, and so is every remaining OP_CODESEPARATOR, which Core's
+/// `SignatureHash` leaves out when serializing the scriptCode.
+fn legacy_script_code(script_code: &[u8], signatures: &[ByteString]) -> ByteString {
+    let mut script_code = script_code.to_vec();
+    for signature in signatures {
+        script_code = find_and_delete(&script_code, &push_script(signature));
+    }
+    find_and_delete(&script_code, &[0xab])
+}
 
-            // Pop n public keys
-            let mut pubkeys = Vec::with_capacity(n);
-            for _ in 0..n {
-                pubkeys.push(stack.pop().unwrap());
-            }
+/// Remove every occurrence of `pattern` that starts on an instruction boundary
+///
+/// Matches Core's `FindAndDelete`: occurrences are only looked for where an
+/// instruction begins, and back-to-back occurrences are all removed. A
+/// truncated push at the end is kept as it is.
+fn find_and_delete(script: &[u8], pattern: &[u8]) -> ByteString {
+    if pattern.is_empty() {
+        return script.to_vec();
+    }
+    let mut result = Vec::with_capacity(script.len());
+    let mut pc = 0;
+    while pc < script.len() {
+        while script[pc..].starts_with(pattern) {
+            pc += pattern.len();
+        }
+        if pc >= script.len() {
+            break;
+        }
+        let next = read_instruction(script, pc).map_or(script.len(), |(_, _, next)| next);
+        result.extend_from_slice(&script[pc..next]);
+        pc = next;
+    }
+    result
+}
 
-            // Pop m (number of required signatures)
-            let m_bytes = stack.pop().unwrap();
-            if m_bytes.is_empty() {
-                return Ok(false);
-            }
-            let m = m_bytes[0] as usize;
-            if m > n || m > 20 || stack.len() < m + 1 {
-                return Ok(false);
-            }
+/// `data` as a script push with the shortest opcode that fits its length
+///
+/// Like Core's `CScript() << data`, a single byte is still pushed as data and
+/// not as a small-number opcode.
+pub(crate) fn push_script(data: &[u8]) -> ByteString {
+    let mut script = Vec::with_capacity(data.len() + 5);
+    match data.len() {
+        len @ 0..=0x4b => script.push(len as u8),
+        len @ 0x4c..=0xff => script.extend_from_slice(&[0x4c, len as u8]),
+        len @ 0x100..=0xffff => {
+            script.push(0x4d);
+            script.extend_from_slice(&(len as u16).to_le_bytes());
+        }
+        len => {
+            script.push(0x4e);
+            script.extend_from_slice(&(len as u32).to_le_bytes());
+        }
+    }
+    script.extend_from_slice(data);
+    script
+}
 
-            // Pop m signatures
-            let mut signatures = Vec::with_capacity(m);
-            for _ in 0..m {
-                signatures.push(stack.pop().unwrap());
-            }
+/// Execute OP_CHECKMULTISIG / OP_CHECKMULTISIGVERIFY
+///
+/// Stack: [dummy] [sig1] ... [sigm] [m] [pubkey1] ... [pubkeyn] [n]. The
+/// counts are script numbers with 0 ≤ m ≤ n ≤ `MAX_PUBKEYS_PER_MULTISIG`; n
+/// is read with `multisig_key_count`, as for the operation count. Each
+/// signature is checked against `sighash(signatures, hash_type)` for its own
+/// hash type, computed when a signature first needs it. `height` and
+/// `network` decide whether BIP66 and BIP147 are active. The VERIFY variant
+/// consumes the same operands but fails instead of pushing false.
+fn execute_multisig_opcode(
+    opcode: u8,
+    stack: &mut Vec<ByteString>,
+    flags: u32,
+    height: Natural,
+    network: crate::types::Network,
+    verifier: &dyn SignatureVerifier,
+    mut sighash: impl FnMut(&[ByteString], u32) -> Result<Hash>,
+) -> Result<bool> {
+    if stack.len() < 2 {
+        return Ok(false);
+    }
 
-            // Pop dummy element (must be empty for BIP147, but we already checked)
-            let _dummy = stack.pop().unwrap();
+    // Pop n (number of public keys), decoded as for the operation count
+    let n = multisig_key_count(stack, flags)?;
+    stack.pop();
+    if stack.len() < n + 1 {
+        return Ok(false);
+    }
 
-            // Verify signatures against public keys
-            // We need to match signatures to public keys
-            // For simplicity, we'll verify signatures in order against public keys
-            let height = block_height.unwrap_or(0);
-            let mut sig_index = 0;
-            let mut valid_sigs = 0;
+    // Pop n public keys
+    let mut pubkeys = Vec::with_capacity(n);
+    for _ in 0..n {
+        pubkeys.push(stack.pop().unwrap());
+    }
 
-            for pubkey_bytes in &pubkeys {
-                if sig_index >= signatures.len() {
-                    break;
-                }
+    // Pop m (number of required signatures): 0 ≤ m ≤ n
+    let Some(m) = decode_script_num(&stack.pop().unwrap(), flags)
+        .and_then(|m| usize::try_from(m).ok())
+        .filter(|&m| m <= n)
+    else {
+        return Ok(false);
+    };
+    if stack.len() < m + 1 {
+        return Ok(false);
+    }
 
-                let signature_bytes = &signatures[sig_index];
-
-                // Calculate transaction sighash
-                use crate::transaction_hash::{calculate_transaction_sighash, SighashType};
-                let sighash =
-                    calculate_transaction_sighash(tx, input_index, prevouts, SighashType::All)?;
-
-                // Verify signature
-                #[cfg(feature = "production")]
-                let is_valid = SECP256K1_CONTEXT.with(|secp| {
-                    verify_signature(
-                        secp,
-                        pubkey_bytes,
-                        signature_bytes,
-                        &sighash,
-                        flags,
-                        height,
-                        network,
-                    )
-                })?;
-
-                #[cfg(not(feature = "production"))]
-                let is_valid = {
-                    let secp = Secp256k1::new();
-                    verify_signature(
-                        &secp,
-                        pubkey_bytes,
-                        signature_bytes,
-                        &sighash,
-                        flags,
-                        height,
-                        network,
-                    )?
-                };
+    // Pop m signatures
+    let mut signatures = Vec::with_capacity(m);
+    for _ in 0..m {
+        signatures.push(stack.pop().unwrap());
+    }
 
-                if is_valid {
-                    valid_sigs += 1;
-                    sig_index += 1;
-                }
+    // Pop the dummy element consumed by an off-by-one in the original
+    // implementation; BIP147 requires it to be empty
+    let dummy = stack.pop().unwrap();
+    if flags & SCRIPT_VERIFY_NULLDUMMY != 0
+        && height >= crate::bip_validation::bip147_activation_height(network)
+        && !dummy.is_empty()
+    {
+        return Ok(false);
+    }
+
+    // Verify signatures against public keys
+    // We need to match signatures to public keys
+    // For simplicity, we'll verify signatures in order against public keys
+    let mut sig_index = 0;
+    let mut valid_sigs = 0;
+    let mut messages: Vec<(u32, Hash)> = Vec::new();
+
+    for pubkey_bytes in &pubkeys {
+        if sig_index >= signatures.len() {
+            break;
+        }
+
+        let signature_bytes = &signatures[sig_index];
+        if !check_signature_hashtype(signature_bytes, flags) {
+            return Ok(false);
+        }
+
+        let hash_type = signature_hash_type(signature_bytes);
+        let sighash = match messages.iter().find(|(t, _)| *t == hash_type) {
+            Some(&(_, hash)) => hash,
+            None => {
+                let hash = sighash(&signatures, hash_type)?;
+                messages.push((hash_type, hash));
+                hash
             }
+        };
 
-            // Push result: 1 if valid_sigs >= m, 0 otherwise
-            stack.push(vec![if valid_sigs >= m { 1 } else { 0 }]);
-            Ok(true)
+        // Verify signature
+        let is_valid = verify_signature(
+            verifier,
+            pubkey_bytes,
+            signature_bytes,
+            &sighash,
+            flags,
+            height,
+            network,
+        )?;
+
+        if is_valid {
+            valid_sigs += 1;
+            sig_index += 1;
         }
+    }
 
-        // For all other opcodes, delegate to the original execute_opcode
-        _ => execute_opcode(opcode, stack, flags),
+    // SCRIPT_VERIFY_NULLFAIL: if the check fails, every signature must be
+    // empty, else the script fails
+    let success = valid_sigs >= m;
+    if !success
+        && flags & SCRIPT_VERIFY_NULLFAIL != 0
+        && signatures.iter().any(|signature| !signature.is_empty())
+    {
+        return Ok(false);
+    }
+
+    if opcode == 0xaf {
+        return Ok(success);
+    }
+
+    // Push result: 1 if valid_sigs >= m, else an empty false as in Core
+    stack.push(if success { vec![1] } else { vec![] });
+    Ok(true)
+}
+
+/// The nHashType a legacy signature is checked under: its trailing byte
+///
+/// An empty signature has none and hashes as 0; it never verifies anyway.
+fn signature_hash_type(signature_bytes: &[u8]) -> u32 {
+    signature_bytes
+        .last()
+        .map_or(0, |&hash_type| hash_type as u32)
+}
+
+/// SCRIPT_VERIFY_STRICTENC: check a signature's trailing sighash byte
+///
+/// Matches Core's `IsDefinedHashtypeSignature`: under the flag, a non-empty
+/// signature must end in SIGHASH_ALL, SIGHASH_NONE or SIGHASH_SINGLE,
+/// optionally with SIGHASH_ANYONECANPAY. An undefined type fails the script
+/// rather than just the signature check. Empty signatures pass here and
+/// simply fail verification.
+fn check_signature_hashtype(signature_bytes: &[u8], flags: u32) -> bool {
+    if flags & SCRIPT_VERIFY_STRICTENC == 0 {
+        return true;
+    }
+    if signature_bytes.is_empty() {
+        return true;
+    }
+    // The hash type the signature is then checked under, without ANYONECANPAY
+    let base_type = signature_hash_type(signature_bytes) & !0x80;
+    (0x01..=0x03).contains(&base_type)
+}
+
+/// Check the DER encoding of a signature, including its trailing sighash byte
+///
+/// Under SCRIPT_VERIFY_DERSIG, SCRIPT_VERIFY_LOW_S or
+/// SCRIPT_VERIFY_STRICTENC a non-empty signature must be strict DER (see
+/// `is_valid_signature_encoding`). A malformed signature makes the check fail,
+/// or is an error under STRICTENC. Empty signatures pass here and simply fail
+/// verification.
+///
+/// # Errors
+///
+/// Returns `ConsensusError::ScriptExecution` for a malformed signature under
+/// SCRIPT_VERIFY_STRICTENC.
+fn check_signature_encoding(signature_bytes: &[u8], flags: u32) -> Result<bool> {
+    if signature_bytes.is_empty()
+        || flags & (SCRIPT_VERIFY_DERSIG | SCRIPT_VERIFY_LOW_S | SCRIPT_VERIFY_STRICTENC) == 0
+        || is_valid_signature_encoding(signature_bytes)
+    {
+        return Ok(true);
+    }
+    if flags & SCRIPT_VERIFY_STRICTENC != 0 {
+        return Err(ConsensusError::ScriptExecution(
+            "Non-canonical DER signature".into(),
+        ));
     }
+    Ok(false)
+}
+
+/// Strict DER signature followed by a sighash byte (BIP66)
+///
+/// `0x30 [total-length] 0x02 [R-length] [R] 0x02 [S-length] [S] [sighash]`,
+/// where the lengths are exact, R and S are non-empty, not negative, and
+/// have no leading zero byte unless it is needed to keep them positive.
+///
+/// Reference: Bitcoin Core `interpreter.cpp` IsValidSignatureEncoding
+fn is_valid_signature_encoding(sig: &[u8]) -> bool {
+    // Minimum and maximum size, and a compound structure of the right length
+    if sig.len() < 9 || sig.len() > 73 || sig[0] != 0x30 || sig[1] as usize != sig.len() - 3 {
+        return false;
+    }
+
+    // R and S lengths must add up to the signature length
+    let len_r = sig[3] as usize;
+    if 5 + len_r >= sig.len() {
+        return false;
+    }
+    let len_s = sig[5 + len_r] as usize;
+    if len_r + len_s + 7 != sig.len() {
+        return false;
+    }
+
+    // R: an integer that is non-empty, not negative and minimally encoded
+    if sig[2] != 0x02 || len_r == 0 || sig[4] & 0x80 != 0 {
+        return false;
+    }
+    if len_r > 1 && sig[4] == 0x00 && sig[5] & 0x80 == 0 {
+        return false;
+    }
+
+    // S: the same rules
+    if sig[len_r + 4] != 0x02 || len_s == 0 || sig[len_r + 6] & 0x80 != 0 {
+        return false;
+    }
+    if len_s > 1 && sig[len_r + 6] == 0x00 && sig[len_r + 7] & 0x80 == 0 {
+        return false;
+    }
+
+    true
 }
 
 /// Phase 6.3: Fast-path validation for signature verification
@@ -1594,13 +2845,17 @@ fn verify_signature_fast_path(
     None // Needs full verification
 }
 
-/// Verify ECDSA signature using secp256k1
+/// Verify ECDSA signature
+///
+/// `signature_bytes` is the signature as pushed by the script, ending in its
+/// sighash type byte. Encoding rules are checked here; the curve check goes
+/// to `verifier`.
 ///
 /// Performance optimization (Phase 6.3): Uses fast-path checks before expensive crypto.
 ///
 /// BIP66: Enforces strict DER encoding for signatures after activation height.
-fn verify_signature<C: Context + Verification>(
-    secp: &Secp256k1<C>,
+fn verify_signature(
+    verifier: &dyn SignatureVerifier,
     pubkey_bytes: &[u8],
     signature_bytes: &[u8],
     sighash: &[u8; 32], // Real transaction hash
@@ -1614,50 +2869,41 @@ fn verify_signature<C: Context + Verification>(
         return Ok(result);
     }
 
-    // BIP66: Check strict DER encoding if flag is set (SCRIPT_VERIFY_DERSIG = 0x04)
-    if flags & 0x04 != 0 && !crate::bip_validation::check_bip66(signature_bytes, height, network)? {
+    // BIP66: DERSIG is only enforced from its activation height
+    let mut flags = flags;
+    if height < crate::bip_validation::bip66_activation_height(network) {
+        flags &= !SCRIPT_VERIFY_DERSIG;
+    }
+    if !check_signature_encoding(signature_bytes, flags)? {
         return Ok(false);
     }
 
-    // Parse signature (DER format) - needed for both LOW_S check and verification
-    let signature = match Signature::from_der(signature_bytes) {
+    // Strip the sighash byte and parse the rest as (possibly lax) DER, as
+    // Core does; strictness was enforced above when the flags ask for it
+    let Some((_, der)) = signature_bytes.split_last() else {
+        return Ok(false);
+    };
+    let signature = match Signature::from_der_lax(der) {
         Ok(sig) => sig,
         Err(_) => return Ok(false),
     };
 
-    // SCRIPT_VERIFY_LOW_S (0x08): Check that S value <= secp256k1 order / 2
+    // SCRIPT_VERIFY_LOW_S: Check that S value <= secp256k1 order / 2
     // Bitcoin Core enforces LOW_S to prevent signature malleability
     // secp256k1 curve order: 0xFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141
     // Order / 2: 0x7FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF5D576E7357A4501DDFE92F46681B20A0
-    if flags & 0x08 != 0 {
-        // Check if signature has high S (S > order/2)
-        // We can check this by normalizing the signature and comparing the serialized forms
-        // If normalize_s changes the signature, it means the original had high S
-        let original_der = signature.serialize_der();
-        let mut normalized_sig = signature;
-        normalized_sig.normalize_s();
-        let normalized_der = normalized_sig.serialize_der();
-
-        if original_der != normalized_der {
-            // Signature has high S (normalize_s changed it) - reject if LOW_S flag is set
-            return Ok(false);
-        }
+    // If normalize_s changes the signature, the original had high S
+    let mut normalized = signature;
+    normalized.normalize_s();
+    if flags & SCRIPT_VERIFY_LOW_S != 0 && normalized != signature {
+        return Ok(false);
     }
 
-    // Parse public key
-    let pubkey = match PublicKey::from_slice(pubkey_bytes) {
-        Ok(pk) => pk,
-        Err(_) => return Ok(false),
-    };
-
-    // Use the actual transaction sighash for verification
-    let message = match Message::from_digest_slice(sighash) {
-        Ok(msg) => msg,
-        Err(_) => return Ok(false),
-    };
+    // libsecp256k1 only verifies low-S signatures; without the flag a high-S
+    // signature is as valid as its normalized twin, as in Core
+    let signature = normalized;
 
-    // Verify signature
-    Ok(secp.verify_ecdsa(&message, &signature, &pubkey).is_ok())
+    Ok(verifier.verify_ecdsa(pubkey_bytes, &signature.serialize_der(), sighash))
 }
 
 /// Phase 6.1: Batch ECDSA signature verification
@@ -1667,7 +2913,8 @@ fn verify_signature<C: Context + Verification>(
 /// when batch size is large enough.
 ///
 /// # Arguments
-/// * `verification_tasks` - Vector of (pubkey_bytes, signature_bytes, sighash) tuples
+/// * `verification_tasks` - Vector of (pubkey_bytes, signature_bytes, sighash) tuples, each
+///   signature ending in its sighash type byte
 /// * `flags` - Script verification flags
 /// * `height` - Block height for BIP66 validation
 /// * `network` - Network type for BIP66 validation
@@ -1685,68 +2932,34 @@ pub fn batch_verify_signatures(
         return Ok(Vec::new());
     }
 
+    let verify = |(pubkey_bytes, signature_bytes, sighash): &(&[u8], &[u8], [u8; 32])| {
+        verify_signature(
+            default_verifier(),
+            pubkey_bytes,
+            signature_bytes,
+            sighash,
+            flags,
+            height,
+            network,
+        )
+    };
+
     // Small batches: sequential (overhead not worth parallelization)
     if verification_tasks.len() < 4 {
-        let mut results = Vec::with_capacity(verification_tasks.len());
-        for (pubkey_bytes, signature_bytes, sighash) in verification_tasks {
-            let result = SECP256K1_CONTEXT.with(|secp| {
-                verify_signature(
-                    secp,
-                    pubkey_bytes,
-                    signature_bytes,
-                    sighash,
-                    flags,
-                    height,
-                    network,
-                )
-            })?;
-            results.push(result);
-        }
-        return Ok(results);
+        return verification_tasks.iter().map(verify).collect();
     }
 
     // Medium/Large batches: parallelized using Rayon
     #[cfg(feature = "rayon")]
     {
         use rayon::prelude::*;
-
-        let results: Result<Vec<bool>> = verification_tasks
-            .par_iter()
-            .map(|(pubkey_bytes, signature_bytes, sighash)| {
-                SECP256K1_CONTEXT.with(|secp| {
-                    verify_signature(
-                        secp,
-                        pubkey_bytes,
-                        signature_bytes,
-                        sighash,
-                        flags,
-                        height,
-                        network,
-                    )
-                })
-            })
-            .collect();
-        results
+        verification_tasks.par_iter().map(verify).collect()
     }
 
     #[cfg(not(feature = "rayon"))]
     {
         // Fallback to sequential if rayon not available
-        let mut results = Vec::with_capacity(verification_tasks.len());
-        for (pubkey_bytes, signature_bytes, sighash) in verification_tasks {
-            let secp = Secp256k1::new();
-            let result = verify_signature(
-                &secp,
-                pubkey_bytes,
-                signature_bytes,
-                sighash,
-                flags,
-                height,
-                network,
-            )?;
-            results.push(result);
-        }
-        Ok(results)
+        verification_tasks.iter().map(verify).collect()
     }
 }
 
@@ -1814,6 +3027,30 @@ pub fn clear_all_caches() {
     clear_hash_cache();
 }
 
+/// Execute one opcode through `OPCODE_TABLE`
+///
+/// Exposed to compare table dispatch against `execute_opcode_via_match`.
+#[cfg(all(feature = "production", feature = "benchmarking"))]
+pub fn execute_opcode_via_table(
+    opcode: u8,
+    stack: &mut Vec<ByteString>,
+    flags: u32,
+) -> Result<bool> {
+    OPCODE_TABLE[opcode as usize](opcode, stack, flags)
+}
+
+/// Execute one opcode through the `match` in `execute_opcode_match`
+///
+/// Exposed to compare match dispatch against `execute_opcode_via_table`.
+#[cfg(all(feature = "production", feature = "benchmarking"))]
+pub fn execute_opcode_via_match(
+    opcode: u8,
+    stack: &mut Vec<ByteString>,
+    flags: u32,
+) -> Result<bool> {
+    execute_opcode_match(opcode, stack, flags)
+}
+
 /// Clear thread-local stack pool
 ///
 /// Clears the thread-local stack pool to reset allocation state for benchmarking.
@@ -1835,97 +3072,826 @@ pub fn clear_stack_pool() {
     });
 }
 
-/// Reset all benchmarking state
-///
-/// Convenience function to reset all caches and thread-local state for
-/// reproducible benchmarks. Also clears sighash templates cache.
-///
-/// # Example
-///
-/// ```rust
-/// use bllvm_consensus::script::reset_benchmarking_state;
-///
-/// // Reset all state before benchmark run
-/// reset_benchmarking_state();
-/// ```
-#[cfg(all(feature = "production", feature = "benchmarking"))]
-pub fn reset_benchmarking_state() {
-    clear_all_caches();
-    clear_stack_pool();
-    disable_caching(false); // Re-enable caching by default
-                            // Also clear sighash templates (currently no-op as templates aren't populated yet)
-    #[cfg(feature = "benchmarking")]
-    crate::transaction_hash::clear_sighash_templates();
-}
+/// Reset all benchmarking state
+///
+/// Convenience function to reset all caches and thread-local state for
+/// reproducible benchmarks. Also clears sighash templates cache.
+///
+/// # Example
+///
+/// ```rust
+/// use bllvm_consensus::script::reset_benchmarking_state;
+///
+/// // Reset all state before benchmark run
+/// reset_benchmarking_state();
+/// ```
+#[cfg(all(feature = "production", feature = "benchmarking"))]
+pub fn reset_benchmarking_state() {
+    clear_all_caches();
+    clear_stack_pool();
+    disable_caching(false); // Re-enable caching by default
+                            // Also clear sighash templates (currently no-op as templates aren't populated yet)
+    #[cfg(feature = "benchmarking")]
+    crate::transaction_hash::clear_sighash_templates();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secp256k1::Secp256k1;
+
+    #[test]
+    fn test_eval_script_simple() {
+        let script = vec![0x51]; // OP_1
+        let mut stack = Vec::new();
+
+        assert!(eval_script(&script, &mut stack, 0).unwrap());
+        assert_eq!(stack.len(), 1);
+        assert_eq!(stack[0], vec![1]);
+    }
+
+    #[test]
+    fn test_eval_script_overflow() {
+        let script = vec![0x51; MAX_STACK_SIZE + 1]; // Too many pushes
+        let mut stack = Vec::new();
+
+        assert!(eval_script(&script, &mut stack, 0).is_err());
+    }
+
+    #[test]
+    fn test_verify_script_simple() {
+        let _script_sig = [0x51]; // OP_1
+        let _script_pubkey = [0x51]; // OP_1
+
+        // This should work: OP_1 pushes 1, then OP_1 pushes another 1
+        // Final stack has [1, 1], which is not exactly one non-zero value
+        // Let's use a script that results in exactly one value on stack
+        let script_sig = vec![0x51]; // OP_1
+        let script_pubkey = vec![0x76, 0x88]; // OP_DUP, OP_EQUALVERIFY
+
+        // This should fail because OP_EQUALVERIFY removes both values
+        assert!(!verify_script(&script_sig, &script_pubkey, None, 0).unwrap());
+    }
+
+    // ============================================================================
+    // COMPREHENSIVE OPCODE TESTS
+    // ============================================================================
+
+    #[test]
+    fn test_op_0() {
+        let script = vec![0x00]; // OP_0
+        let mut stack = Vec::new();
+        let result = eval_script(&script, &mut stack, 0).unwrap();
+        assert!(!result); // OP_0 pushes empty array, which is "false"
+        assert_eq!(stack.len(), 1);
+        assert!(stack[0].is_empty());
+    }
+
+    #[test]
+    fn test_op_1_to_op_16() {
+        // Test OP_1 through OP_16
+        for i in 1..=16 {
+            let opcode = 0x50 + i;
+            let script = vec![opcode];
+            let mut stack = Vec::new();
+            let result = eval_script(&script, &mut stack, 0).unwrap();
+            assert!(result);
+            assert_eq!(stack.len(), 1);
+            assert_eq!(stack[0], vec![i]);
+        }
+    }
+
+    #[test]
+    fn test_op_dup() {
+        let script = vec![0x51, 0x76]; // OP_1, OP_DUP
+        let mut stack = Vec::new();
+        let result = eval_script(&script, &mut stack, 0).unwrap();
+        assert!(!result); // Final stack has 2 items [1, 1], not exactly 1
+        assert_eq!(stack.len(), 2);
+        assert_eq!(stack[0], vec![1]);
+        assert_eq!(stack[1], vec![1]);
+    }
+
+    #[test]
+    fn test_op_dup_empty_stack() {
+        let script = vec![0x76]; // OP_DUP on empty stack
+        let mut stack = Vec::new();
+        let result = eval_script(&script, &mut stack, 0).unwrap();
+        assert!(!result);
+    }
+
+    #[test]
+    fn test_op_hash160() {
+        let script = vec![0x51, 0xa9]; // OP_1, OP_HASH160
+        let mut stack = Vec::new();
+        let result = eval_script(&script, &mut stack, 0).unwrap();
+        assert!(result);
+        assert_eq!(stack.len(), 1);
+        assert_eq!(stack[0].len(), 20); // RIPEMD160 output is 20 bytes
+    }
+
+    #[test]
+    fn test_op_hash160_empty_stack() {
+        let script = vec![0xa9]; // OP_HASH160 on empty stack
+        let mut stack = Vec::new();
+        let result = eval_script(&script, &mut stack, 0).unwrap();
+        assert!(!result);
+    }
+
+    #[test]
+    fn test_op_hash256() {
+        let script = vec![0x51, 0xaa]; // OP_1, OP_HASH256
+        let mut stack = Vec::new();
+        let result = eval_script(&script, &mut stack, 0).unwrap();
+        assert!(result);
+        assert_eq!(stack.len(), 1);
+        assert_eq!(stack[0].len(), 32); // SHA256 output is 32 bytes
+    }
+
+    #[test]
+    fn test_op_hash256_empty_stack() {
+        let script = vec![0xaa]; // OP_HASH256 on empty stack
+        let mut stack = Vec::new();
+        let result = eval_script(&script, &mut stack, 0).unwrap();
+        assert!(!result);
+    }
+
+    #[test]
+    fn test_op_equal() {
+        let script = vec![0x51, 0x51, 0x87]; // OP_1, OP_1, OP_EQUAL
+        let mut stack = Vec::new();
+        let result = eval_script(&script, &mut stack, 0).unwrap();
+        assert!(result);
+        assert_eq!(stack.len(), 1);
+        assert_eq!(stack[0], vec![1]); // True
+    }
+
+    #[test]
+    fn test_op_equal_false() {
+        let script = vec![0x51, 0x52, 0x87]; // OP_1, OP_2, OP_EQUAL
+        let mut stack = Vec::new();
+        let result = eval_script(&script, &mut stack, 0).unwrap();
+        assert!(!result); // False value (0) is not considered "true"
+        assert_eq!(stack.len(), 1);
+        assert_eq!(stack[0], Vec::<u8>::new()); // False, as Core's vchFalse
+    }
+
+    #[test]
+    fn test_false_results_are_minimal_numbers() {
+        // OP_1 OP_2 OP_EQUAL OP_NOT: the false result is a valid minimally
+        // encoded zero for the numeric opcodes
+        let script = vec![0x51, 0x52, 0x87, 0x91];
+        for flags in [0, SCRIPT_VERIFY_MINIMALDATA] {
+            assert!(eval_script(&script, &mut Vec::new(), flags).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_op_equal_insufficient_stack() {
+        let script = vec![0x51, 0x87]; // OP_1, OP_EQUAL (need 2 items)
+        let mut stack = Vec::new();
+        let result = eval_script(&script, &mut stack, 0).unwrap();
+        assert!(!result);
+    }
+
+    #[test]
+    fn test_op_verify() {
+        let script = vec![0x51, 0x69]; // OP_1, OP_VERIFY
+        let mut stack = Vec::new();
+        let result = eval_script(&script, &mut stack, 0).unwrap();
+        assert!(!result); // Final stack is empty, not exactly 1 item
+        assert_eq!(stack.len(), 0); // OP_VERIFY consumes the top item
+    }
+
+    #[test]
+    fn test_op_verify_false() {
+        let script = vec![0x00, 0x69]; // OP_0, OP_VERIFY (false)
+        let mut stack = Vec::new();
+        let result = eval_script(&script, &mut stack, 0).unwrap();
+        assert!(!result);
+    }
+
+    #[test]
+    fn test_op_verify_empty_stack() {
+        let script = vec![0x69]; // OP_VERIFY on empty stack
+        let mut stack = Vec::new();
+        let result = eval_script(&script, &mut stack, 0).unwrap();
+        assert!(!result);
+    }
+
+    #[test]
+    fn test_op_equalverify() {
+        let script = vec![0x51, 0x51, 0x88]; // OP_1, OP_1, OP_EQUALVERIFY
+        let mut stack = Vec::new();
+        let result = eval_script(&script, &mut stack, 0).unwrap();
+        assert!(!result); // Final stack is empty, not exactly 1 item
+        assert_eq!(stack.len(), 0); // OP_EQUALVERIFY consumes both items
+    }
+
+    #[test]
+    fn test_op_equalverify_false() {
+        let script = vec![0x51, 0x52, 0x88]; // OP_1, OP_2, OP_EQUALVERIFY (false)
+        let mut stack = Vec::new();
+        let result = eval_script(&script, &mut stack, 0);
+        assert_eq!(
+            result,
+            Err(ConsensusError::ScriptExecution(
+                "Script failed an OP_EQUALVERIFY operation".into()
+            ))
+        );
+        assert!(stack.is_empty()); // both operands are consumed
+    }
+
+    #[test]
+    fn test_op_checksig() {
+        // This is a simplified test - real OP_CHECKSIG would need proper signature verification
+        let script = vec![0x51, 0x51, 0xac]; // OP_1, OP_1, OP_CHECKSIG
+        let mut stack = Vec::new();
+        let result = eval_script(&script, &mut stack, 0).unwrap();
+        assert!(!result); // OP_CHECKSIG returns false in our simplified implementation
+        assert_eq!(stack.len(), 1);
+        // OP_CHECKSIG result depends on implementation
+    }
+
+    #[test]
+    fn test_op_checksig_insufficient_stack() {
+        let script = vec![0x51, 0xac]; // OP_1, OP_CHECKSIG (need 2 items)
+        let mut stack = Vec::new();
+        let result = eval_script(&script, &mut stack, 0).unwrap();
+        assert!(!result);
+    }
+
+    #[test]
+    fn test_eval_script_budgeted_signature_heavy() {
+        // 10 × (OP_1 OP_1 OP_CHECKSIG OP_DROP) followed by OP_1
+        let mut script = Vec::new();
+        for _ in 0..10 {
+            script.extend_from_slice(&[0x51, 0x51, 0xac, 0x75]);
+        }
+        script.push(0x51);
+        let total_cost: u64 = script.iter().map(|op| opcode_cost(*op)).sum();
+        assert_eq!(total_cost, 10 * (3 + SCRIPT_COST_SIGCHECK) + 1);
+
+        // Tight budget: fails partway through the signature checks
+        let mut stack = Vec::new();
+        let result = eval_script_budgeted(&script, &mut stack, 0, 200);
+        assert!(matches!(result, Err(ConsensusError::ScriptExecution(_))));
+
+        // Exact budget and unbounded budget both succeed, matching eval_script
+        let mut stack = Vec::new();
+        assert!(eval_script_budgeted(&script, &mut stack, 0, total_cost).unwrap());
+        let mut stack = Vec::new();
+        assert!(eval_script_budgeted(&script, &mut stack, 0, u64::MAX).unwrap());
+        let mut stack = Vec::new();
+        assert!(eval_script(&script, &mut stack, 0).unwrap());
+    }
+
+    #[test]
+    fn test_verify_transaction_with_utxo_set() {
+        let outpoints = [
+            OutPoint {
+                hash: [1; 32],
+                index: 0,
+            },
+            OutPoint {
+                hash: [2; 32],
+                index: 3,
+            },
+        ];
+        let mut utxo_set = UtxoSet::new();
+        for outpoint in &outpoints {
+            utxo_set.insert(
+                outpoint.clone(),
+                UTXO {
+                    value: 50_000,
+                    script_pubkey: vec![0x51, 0x87], // OP_1 OP_EQUAL
+                    height: 10,
+                    is_coinbase: false,
+                },
+            );
+        }
+
+        let mut tx = Transaction {
+            version: 1,
+            inputs: outpoints
+                .iter()
+                .map(|prevout| TransactionInput {
+                    prevout: prevout.clone(),
+                    script_sig: vec![0x51], // OP_1
+                    sequence: 0xffffffff,
+                })
+                .collect(),
+            outputs: vec![TransactionOutput {
+                value: 90_000,
+                script_pubkey: vec![0x51],
+            }]
+            .into(),
+            lock_time: 0,
+        };
+
+        assert!(verify_transaction_with_utxo_set(&tx, &utxo_set, 0, 200).unwrap());
+
+        // Wrong unlocking script on the second input
+        tx.inputs[1].script_sig = vec![0x52]; // OP_2
+        assert!(!verify_transaction_with_utxo_set(&tx, &utxo_set, 0, 200).unwrap());
+
+        // Missing UTXO is an error, not a script failure
+        utxo_set.remove(&outpoints[1]);
+        assert!(matches!(
+            verify_transaction_with_utxo_set(&tx, &utxo_set, 0, 200),
+            Err(ConsensusError::UtxoNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_verify_script_with_prevout_map_out_of_order() {
+        let outpoints: Vec<OutPoint> = (1..=2u8)
+            .map(|n| OutPoint {
+                hash: [n; 32],
+                index: n as Natural,
+            })
+            .collect();
+        let tx = Transaction {
+            version: 1,
+            inputs: outpoints
+                .iter()
+                .zip([0x51, 0x52]) // OP_1, OP_2
+                .map(|(prevout, push)| TransactionInput {
+                    prevout: prevout.clone(),
+                    script_sig: vec![push],
+                    sequence: 0xffffffff,
+                })
+                .collect(),
+            outputs: crate::tx_outputs![TransactionOutput {
+                value: 90_000,
+                script_pubkey: vec![0x51],
+            }],
+            lock_time: 0,
+        };
+
+        // Inserted second input first; each spent script expects its own push
+        let mut prevouts = std::collections::HashMap::new();
+        for (prevout, expected) in outpoints.iter().zip([0x51, 0x52]).rev() {
+            prevouts.insert(
+                prevout.clone(),
+                PrevOut {
+                    value: 50_000,
+                    script_pubkey: vec![expected, 0x87], // OP_n OP_EQUAL
+                },
+            );
+        }
+
+        for (i, input) in tx.inputs.iter().enumerate() {
+            assert!(verify_script_with_prevout_map(
+                &input.script_sig,
+                None,
+                0,
+                &tx,
+                i,
+                &prevouts,
+                crate::types::Network::Regtest,
+            )
+            .unwrap());
+        }
+
+        // Every input's prevout must be present, not just the one verified
+        prevouts.remove(&outpoints[1]);
+        assert!(matches!(
+            verify_script_with_prevout_map(
+                &tx.inputs[0].script_sig,
+                None,
+                0,
+                &tx,
+                0,
+                &prevouts,
+                crate::types::Network::Regtest,
+            ),
+            Err(ConsensusError::UtxoNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_verify_script_rejects_op_return_prevout() {
+        let prevout = PrevOut {
+            value: 0,
+            script_pubkey: vec![0x6a, 0x04, 0xde, 0xad, 0xbe, 0xef], // OP_RETURN <data>
+        };
+        let tx = Transaction {
+            version: 1,
+            inputs: crate::tx_inputs![TransactionInput {
+                prevout: OutPoint {
+                    hash: [1; 32],
+                    index: 0,
+                },
+                script_sig: vec![0x51], // OP_1
+                sequence: 0xffffffff,
+            }],
+            outputs: crate::tx_outputs![],
+            lock_time: 0,
+        };
+
+        let result = verify_script_with_context(
+            &tx.inputs[0].script_sig,
+            &prevout.script_pubkey,
+            None,
+            0,
+            &tx,
+            0,
+            std::slice::from_ref(&prevout),
+            crate::types::Network::Regtest,
+        );
+        assert!(
+            matches!(result, Err(ConsensusError::ScriptExecution(ref msg)) if msg.contains("OP_RETURN"))
+        );
+    }
+
+    #[test]
+    fn test_verify_script_p2sh_multisig() {
+        // 2-of-2 multisig redeem script; without transaction context the
+        // signatures are checked against a zero hash
+        let secp = Secp256k1::new();
+        let message = secp256k1::Message::from_digest_slice(&[0u8; 32]).unwrap();
+        let keys: Vec<_> = (1..=3u8)
+            .map(|key| secp256k1::SecretKey::from_slice(&[key; 32]).unwrap())
+            .collect();
+        let sig = |key: &secp256k1::SecretKey| {
+            let mut der = secp.sign_ecdsa(&message, key).serialize_der().to_vec();
+            der.push(0x01); // SIGHASH_ALL
+            der
+        };
+        let mut redeem_script = vec![0x52]; // OP_2
+        for key in &keys[..2] {
+            redeem_script.push(0x21);
+            redeem_script
+                .extend_from_slice(&secp256k1::PublicKey::from_secret_key(&secp, key).serialize());
+        }
+        redeem_script.extend_from_slice(&[0x52, 0xae]); // OP_2 OP_CHECKMULTISIG
+
+        let p2sh = |redeem_script: &[u8]| {
+            let mut script = vec![0xa9, 0x14];
+            script.extend_from_slice(&Ripemd160::digest(Sha256::digest(redeem_script)));
+            script.push(0x87); // OP_HASH160 <hash> OP_EQUAL
+            script
+        };
+        let script_pubkey = p2sh(&redeem_script);
+        let script_sig = |sigs: &[ByteString], redeem_script: &[u8]| {
+            let mut script = vec![0x00]; // dummy
+            for sig in sigs {
+                script.push(sig.len() as u8);
+                script.extend_from_slice(sig);
+            }
+            script.push(redeem_script.len() as u8);
+            script.extend_from_slice(redeem_script);
+            script
+        };
+        let verify = |script_sig: &ByteString| {
+            verify_script(script_sig, &script_pubkey, None, SCRIPT_VERIFY_P2SH).unwrap()
+        };
+
+        let (sig1, sig2, sig3) = (sig(&keys[0]), sig(&keys[1]), sig(&keys[2]));
+        assert!(verify(&script_sig(
+            &[sig1.clone(), sig2.clone()],
+            &redeem_script
+        )));
+
+        // Signatures must be in key order, and all from the listed keys
+        assert!(!verify(&script_sig(
+            &[sig2.clone(), sig1.clone()],
+            &redeem_script
+        )));
+        assert!(!verify(&script_sig(&[sig1.clone(), sig3], &redeem_script)));
+
+        // A redeem script that does not match the hash fails
+        let mut other_redeem = redeem_script.clone();
+        other_redeem[0] = 0x51; // OP_1
+        assert!(!verify(&script_sig(
+            &[sig1.clone(), sig2.clone()],
+            &other_redeem
+        )));
+
+        // The scriptSig must be push-only
+        let mut not_push_only = script_sig(&[sig1, sig2], &redeem_script);
+        not_push_only.insert(0, 0x61); // OP_NOP
+        assert!(!verify(&not_push_only));
+    }
+
+    #[test]
+    fn test_verify_script_p2sh_op_1negate_script_sig() {
+        // OP_1NEGATE is push-only: scriptSig OP_1NEGATE <OP_1NEGATE OP_EQUAL>
+        let redeem_script = vec![0x4f, 0x87];
+        let mut script_pubkey = vec![0xa9, 0x14];
+        script_pubkey.extend_from_slice(&Ripemd160::digest(Sha256::digest(&redeem_script)));
+        script_pubkey.push(0x87); // OP_HASH160 <hash> OP_EQUAL
+        let script_sig = vec![0x4f, 0x02, 0x4f, 0x87];
+        assert!(verify_script(&script_sig, &script_pubkey, None, SCRIPT_VERIFY_P2SH).unwrap());
+
+        // The redeem script still sees the value: OP_1 fails its OP_EQUAL
+        let script_sig = vec![0x51, 0x02, 0x4f, 0x87];
+        assert!(!verify_script(&script_sig, &script_pubkey, None, SCRIPT_VERIFY_P2SH).unwrap());
+    }
+
+    #[test]
+    fn test_verify_input_multi_input() {
+        // Input 0 spends a bare OP_2 OP_EQUAL, input 1 a P2SH of OP_3 OP_EQUAL
+        let redeem_script = vec![0x53, 0x87];
+        let mut p2sh = vec![0xa9, 0x14];
+        p2sh.extend_from_slice(&Ripemd160::digest(Sha256::digest(&redeem_script)));
+        p2sh.push(0x87); // OP_HASH160 <hash> OP_EQUAL
+        let bare = PrevOut {
+            value: 1000,
+            script_pubkey: vec![0x52, 0x87],
+        };
+        let p2sh = PrevOut {
+            value: 2000,
+            script_pubkey: p2sh,
+        };
+        let spend = |script_sig: ByteString| Transaction {
+            version: 1,
+            inputs: crate::tx_inputs![
+                TransactionInput {
+                    prevout: OutPoint {
+                        hash: [1; 32],
+                        index: 0,
+                    },
+                    script_sig: vec![0x52], // OP_2
+                    sequence: 0xffffffff,
+                },
+                TransactionInput {
+                    prevout: OutPoint {
+                        hash: [2; 32],
+                        index: 0,
+                    },
+                    script_sig,
+                    sequence: 0xffffffff,
+                }
+            ],
+            outputs: crate::tx_outputs![],
+            lock_time: 0,
+        };
+        let with_redeem = |first: u8| [vec![first, 0x02], redeem_script.clone()].concat();
+
+        let no_witness = Vec::new();
+        let verify = |tx: &Transaction, index: usize, spent: &PrevOut, flags: u32| {
+            verify_input(
+                tx,
+                index,
+                &[bare.clone(), spent.clone()],
+                &no_witness,
+                flags,
+            )
+        };
+
+        let tx = spend(with_redeem(0x53)); // OP_3 <redeem>
+        assert!(verify(&tx, 0, &p2sh, SCRIPT_VERIFY_P2SH).unwrap());
+        assert!(verify(&tx, 1, &p2sh, SCRIPT_VERIFY_P2SH).unwrap());
+        assert!(!verify(&tx, 1, &bare, SCRIPT_VERIFY_P2SH).unwrap());
+
+        let tx = spend(with_redeem(0x54)); // OP_4 <redeem>
+        assert!(!verify(&tx, 1, &p2sh, SCRIPT_VERIFY_P2SH).unwrap());
+
+        // Before BIP16 only the redeem script's hash is checked: a redeem
+        // script of OP_0 fails with the flag and passes without it
+        let mut false_p2sh = vec![0xa9, 0x14];
+        false_p2sh.extend_from_slice(&Ripemd160::digest(Sha256::digest([0x00])));
+        false_p2sh.push(0x87);
+        let false_p2sh = PrevOut {
+            value: 2000,
+            script_pubkey: false_p2sh,
+        };
+        let tx = spend(vec![0x01, 0x00]); // <OP_0>
+        assert!(!verify(&tx, 1, &false_p2sh, SCRIPT_VERIFY_P2SH).unwrap());
+        assert!(verify(&tx, 1, &false_p2sh, 0).unwrap());
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        // P2SH scriptSigs must be push-only
+        let tx = spend([vec![0x52, 0x8b, 0x02], redeem_script.clone()].concat()); // OP_2 OP_1ADD <redeem>
+        assert!(!verify(&tx, 1, &p2sh, SCRIPT_VERIFY_P2SH).unwrap());
+
+        assert!(matches!(
+            verify(&tx, 2, &p2sh, 0),
+            Err(ConsensusError::InvalidInputIndex(2))
+        ));
+        assert!(matches!(
+            verify_input(&tx, 1, std::slice::from_ref(&p2sh), &no_witness, 0),
+            Err(ConsensusError::InvalidPrevoutsCount(2, 1))
+        ));
+    }
 
     #[test]
-    fn test_eval_script_simple() {
-        let script = vec![0x51]; // OP_1
+    fn test_verify_input_native_p2wpkh() {
+        use crate::serialization::transaction::deserialize_transaction_with_witness;
+
+        // BIP143 "Native P2WPKH" example, signed: input 0 spends a P2PK
+        // output, input 1 a P2WPKH output with [sig, pubkey]
+        let (tx, witnesses) = deserialize_transaction_with_witness(
+            &hex::decode(
+                "01000000000102fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f00\
+                 000000494830450221008b9d1dc26ba6a9cb62127b02742fa9d754cd3bebf337f7a55d114c8e5cdd\
+                 30be022040529b194ba3f9281a99f2b1c0a19c0489bc22ede944ccf4ecbab4cc618ef3ed01eeffff\
+                 ffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffff\
+                 ffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac909351\
+                 0d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac000247304402203609\
+                 e17b84f6a7d30c80bfa610b5b4542f32a8a0d5447a12fb1366d7f01cc44a0220573a954c45183315\
+                 61406f90300e8f3358f51928d43c212a8caed02de67eebee0121025476c2e83188368da1ff3e292e\
+                 7acafcdb3566bb0ad253f62fc70f07aeee635711000000",
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let prevouts = vec![
+            PrevOut {
+                value: 625_000_000,
+                script_pubkey: hex::decode(
+                    "2103c9f4836b9a4f77fc0d81f7bcb01b7f1b35916864b9476c241ce9fc198bd25432ac",
+                )
+                .unwrap(),
+            },
+            PrevOut {
+                value: 600_000_000,
+                script_pubkey: hex::decode("00141d0f172a0ecb48aee1be1f2687d2963ae33f71a1").unwrap(),
+            },
+        ];
+        let flags = SCRIPT_VERIFY_P2SH | SCRIPT_VERIFY_WITNESS;
+
+        assert!(verify_input(&tx, 0, &prevouts, &witnesses[0], flags).unwrap());
+        assert!(verify_input(&tx, 1, &prevouts, &witnesses[1], flags).unwrap());
+        // The witness signature commits to the spent amount
+        let mut wrong_amount = prevouts.clone();
+        wrong_amount[1].value -= 1;
+        assert!(!verify_input(&tx, 1, &wrong_amount, &witnesses[1], flags).unwrap());
+        // A P2WPKH spend needs its witness stack
+        assert!(!verify_input(&tx, 1, &prevouts, &Vec::new(), flags).unwrap());
+    }
+
+    #[test]
+    fn test_unknown_opcode() {
+        let script = vec![0xff]; // Unknown opcode
         let mut stack = Vec::new();
+        let result = eval_script(&script, &mut stack, 0).unwrap();
+        assert!(!result);
+    }
 
-        assert!(eval_script(&script, &mut stack, 0).unwrap());
-        assert_eq!(stack.len(), 1);
-        assert_eq!(stack[0], vec![1]);
+    #[test]
+    fn test_push_opcodes() {
+        // A direct push consumes its payload instead of executing it
+        let mut stack = Vec::new();
+        assert!(eval_script(&vec![0x03, 0xaa, 0xbb, 0xcc], &mut stack, 0).unwrap());
+        assert_eq!(stack, vec![vec![0xaa, 0xbb, 0xcc]]);
+
+        // OP_PUSHDATA1/2/4 up to MAX_SCRIPT_ELEMENT_SIZE
+        let max = MAX_SCRIPT_ELEMENT_SIZE as u32;
+        for (prefix, len) in [
+            (vec![0x4b], 0x4b),
+            (vec![0x4c, 0xff], 0xff),
+            (
+                vec![0x4d, max as u8, (max >> 8) as u8],
+                MAX_SCRIPT_ELEMENT_SIZE,
+            ),
+            (
+                [vec![0x4e], max.to_le_bytes().to_vec()].concat(),
+                MAX_SCRIPT_ELEMENT_SIZE,
+            ),
+        ] {
+            let script = [prefix, vec![0x01; len]].concat();
+            let mut stack = Vec::new();
+            assert!(eval_script(&script, &mut stack, 0).unwrap());
+            assert_eq!(stack, vec![vec![0x01; len]]);
+        }
+
+        // One byte more is rejected
+        let mut script = vec![0x4d, 0x09, 0x02];
+        script.extend(vec![0x01; MAX_SCRIPT_ELEMENT_SIZE + 1]);
+        assert!(eval_script(&script, &mut Vec::new(), 0).is_err());
     }
 
     #[test]
-    fn test_eval_script_overflow() {
-        let script = vec![0x51; MAX_STACK_SIZE + 1]; // Too many pushes
+    fn test_truncated_push() {
+        for script in [
+            vec![0x03, 0xaa, 0xbb],
+            vec![0x4c],
+            vec![0x4c, 0x02, 0xaa],
+            vec![0x4d, 0x01],
+            vec![0x4d, 0x01, 0x00],
+            vec![0x4e, 0x02, 0x00, 0x00],
+            vec![0x4e, 0x02, 0x00, 0x00, 0x00, 0xaa],
+            // Malformed even inside a branch not taken
+            vec![0x00, 0x63, 0x03, 0xaa, 0x68],
+        ] {
+            assert!(
+                eval_script(&script, &mut Vec::new(), 0).is_err(),
+                "{script:02x?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_script_size_limit() {
+        let script = vec![0x51; MAX_SCRIPT_SIZE + 1]; // Exceed size limit
         let mut stack = Vec::new();
+        let result = eval_script(&script, &mut stack, 0);
+        assert!(result.is_err());
+    }
 
-        assert!(eval_script(&script, &mut stack, 0).is_err());
+    #[test]
+    fn test_operation_count_limit() {
+        let script = vec![0x61; MAX_SCRIPT_OPS + 1]; // OP_NOP × 202: exceed operation limit
+        let mut stack = Vec::new();
+        let result = eval_script(&script, &mut stack, 0);
+        assert!(result.is_err());
     }
 
     #[test]
-    fn test_verify_script_simple() {
-        let _script_sig = [0x51]; // OP_1
-        let _script_pubkey = [0x51]; // OP_1
+    fn test_pushes_do_not_count_as_operations() {
+        // 202 data pushes: none of them counts towards MAX_SCRIPT_OPS
+        let pushes: Vec<u8> = [0x01, 0x2a].repeat(MAX_SCRIPT_OPS + 1);
+        let mut stack = Vec::new();
+        assert!(run_script(&pushes, &mut stack, 0, u64::MAX).unwrap());
+        assert_eq!(stack.len(), MAX_SCRIPT_OPS + 1);
 
-        // This should work: OP_1 pushes 1, then OP_1 pushes another 1
-        // Final stack has [1, 1], which is not exactly one non-zero value
-        // Let's use a script that results in exactly one value on stack
-        let script_sig = vec![0x51]; // OP_1
-        let script_pubkey = vec![0x76, 0x88]; // OP_DUP, OP_EQUALVERIFY
+        let tx = Transaction {
+            version: 1,
+            inputs: crate::tx_inputs![TransactionInput {
+                prevout: OutPoint {
+                    hash: [1; 32],
+                    index: 0,
+                },
+                script_sig: vec![],
+                sequence: 0xffffffff,
+            }],
+            outputs: crate::tx_outputs![],
+            lock_time: 0,
+        };
+        let mut stack = Vec::new();
+        assert!(eval_script_with_context(
+            &pushes,
+            &mut stack,
+            0,
+            &tx,
+            0,
+            &[],
+            crate::types::Network::Regtest
+        )
+        .unwrap());
+        assert_eq!(stack.len(), MAX_SCRIPT_OPS + 1);
 
-        // This should fail because OP_EQUALVERIFY removes both values
-        assert!(!verify_script(&script_sig, &script_pubkey, None, 0).unwrap());
+        // Nor do OP_1NEGATE and OP_1..OP_16
+        let mut stack = Vec::new();
+        assert!(run_script(&vec![0x60; MAX_SCRIPT_OPS + 1], &mut stack, 0, u64::MAX).unwrap());
+        assert_eq!(stack.len(), MAX_SCRIPT_OPS + 1);
     }
 
-    // ============================================================================
-    // COMPREHENSIVE OPCODE TESTS
-    // ============================================================================
+    #[test]
+    fn test_stack_underflow_multiple_ops() {
+        let script = vec![0x51, 0x87, 0x87]; // OP_1, OP_EQUAL, OP_EQUAL (second OP_EQUAL will underflow)
+        let mut stack = Vec::new();
+        let result = eval_script(&script, &mut stack, 0).unwrap();
+        assert!(!result);
+    }
 
     #[test]
-    fn test_op_0() {
-        let script = vec![0x00]; // OP_0
+    fn test_final_stack_empty() {
+        let script = vec![0x51, 0x52]; // OP_1, OP_2 (two items on final stack)
         let mut stack = Vec::new();
         let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(!result); // OP_0 pushes empty array, which is "false"
-        assert_eq!(stack.len(), 1);
-        assert!(stack[0].is_empty());
+        assert!(!result);
     }
 
     #[test]
-    fn test_op_1_to_op_16() {
-        // Test OP_1 through OP_16
-        for i in 1..=16 {
-            let opcode = 0x50 + i;
-            let script = vec![opcode];
-            let mut stack = Vec::new();
-            let result = eval_script(&script, &mut stack, 0).unwrap();
-            assert!(result);
-            assert_eq!(stack.len(), 1);
-            assert_eq!(stack[0], vec![i]);
-        }
+    fn test_final_stack_false() {
+        let script = vec![0x00]; // OP_0 (false on final stack)
+        let mut stack = Vec::new();
+        let result = eval_script(&script, &mut stack, 0).unwrap();
+        assert!(!result);
     }
 
     #[test]
-    fn test_op_dup() {
-        let script = vec![0x51, 0x76]; // OP_1, OP_DUP
+    fn test_verify_script_with_witness() {
+        let script_sig = vec![0x51]; // OP_1
+        let script_pubkey = vec![0x51]; // OP_1
+        let witness = vec![0x51]; // OP_1
+        let flags = 0;
+
+        let result = verify_script(&script_sig, &script_pubkey, Some(&witness), flags).unwrap();
+        assert!(!result); // Final stack has 2 items [1, 1], not exactly 1
+    }
+
+    #[test]
+    fn test_verify_script_failure() {
+        let script_sig = vec![0x51]; // OP_1
+        let script_pubkey = vec![0x52]; // OP_2
+        let witness = None;
+        let flags = 0;
+
+        let result = verify_script(&script_sig, &script_pubkey, witness, flags).unwrap();
+        assert!(!result);
+    }
+
+    // ============================================================================
+    // COMPREHENSIVE SCRIPT TESTS
+    // ============================================================================
+
+    #[test]
+    fn test_op_ifdup_true() {
+        let script = vec![0x51, 0x73]; // OP_1, OP_IFDUP
         let mut stack = Vec::new();
         let result = eval_script(&script, &mut stack, 0).unwrap();
         assert!(!result); // Final stack has 2 items [1, 1], not exactly 1
@@ -1935,594 +3901,1360 @@ mod tests {
     }
 
     #[test]
-    fn test_op_dup_empty_stack() {
-        let script = vec![0x76]; // OP_DUP on empty stack
+    fn test_op_ifdup_false() {
+        let script = vec![0x00, 0x73]; // OP_0, OP_IFDUP
         let mut stack = Vec::new();
         let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(!result);
+        assert!(!result); // Final stack has 1 item [0], which is false
+        assert_eq!(stack.len(), 1);
+        assert_eq!(stack[0], Vec::<u8>::new());
     }
 
     #[test]
-    fn test_op_hash160() {
-        let script = vec![0x51, 0xa9]; // OP_1, OP_HASH160
+    fn test_op_depth() {
+        let script = vec![0x51, 0x51, 0x74]; // OP_1, OP_1, OP_DEPTH
         let mut stack = Vec::new();
         let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(result);
+        assert!(!result); // Final stack has 3 items, not exactly 1
+        assert_eq!(stack.len(), 3);
+        assert_eq!(stack[2], vec![2]); // Depth should be 2 (before OP_DEPTH)
+    }
+
+    #[test]
+    fn test_op_drop() {
+        let script = vec![0x51, 0x52, 0x75]; // OP_1, OP_2, OP_DROP
+        let mut stack = Vec::new();
+        let result = eval_script(&script, &mut stack, 0).unwrap();
+        assert!(result); // Final stack has 1 item [1]
         assert_eq!(stack.len(), 1);
-        assert_eq!(stack[0].len(), 20); // RIPEMD160 output is 20 bytes
+        assert_eq!(stack[0], vec![1]);
     }
 
     #[test]
-    fn test_op_hash160_empty_stack() {
-        let script = vec![0xa9]; // OP_HASH160 on empty stack
+    fn test_op_drop_empty_stack() {
+        let script = vec![0x75]; // OP_DROP on empty stack
         let mut stack = Vec::new();
         let result = eval_script(&script, &mut stack, 0).unwrap();
         assert!(!result);
+        assert_eq!(stack.len(), 0);
     }
 
     #[test]
-    fn test_op_hash256() {
-        let script = vec![0x51, 0xaa]; // OP_1, OP_HASH256
+    fn test_op_nip() {
+        let script = vec![0x51, 0x52, 0x77]; // OP_1, OP_2, OP_NIP
         let mut stack = Vec::new();
         let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(result);
+        assert!(result); // Final stack has 1 item [2]
         assert_eq!(stack.len(), 1);
-        assert_eq!(stack[0].len(), 32); // SHA256 output is 32 bytes
+        assert_eq!(stack[0], vec![2]);
     }
 
     #[test]
-    fn test_op_hash256_empty_stack() {
-        let script = vec![0xaa]; // OP_HASH256 on empty stack
+    fn test_op_nip_insufficient_stack() {
+        let script = vec![0x51, 0x77]; // OP_1, OP_NIP (only 1 item)
         let mut stack = Vec::new();
         let result = eval_script(&script, &mut stack, 0).unwrap();
         assert!(!result);
+        assert_eq!(stack.len(), 1);
     }
 
     #[test]
-    fn test_op_equal() {
-        let script = vec![0x51, 0x51, 0x87]; // OP_1, OP_1, OP_EQUAL
+    fn test_op_over() {
+        let script = vec![0x51, 0x52, 0x78]; // OP_1, OP_2, OP_OVER
         let mut stack = Vec::new();
         let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(result);
-        assert_eq!(stack.len(), 1);
-        assert_eq!(stack[0], vec![1]); // True
+        assert!(!result); // Final stack has 3 items [1, 2, 1], not exactly 1
+        assert_eq!(stack.len(), 3);
+        assert_eq!(stack[0], vec![1]);
+        assert_eq!(stack[1], vec![2]);
+        assert_eq!(stack[2], vec![1]);
     }
 
     #[test]
-    fn test_op_equal_false() {
-        let script = vec![0x51, 0x52, 0x87]; // OP_1, OP_2, OP_EQUAL
+    fn test_op_over_insufficient_stack() {
+        let script = vec![0x51, 0x78]; // OP_1, OP_OVER (only 1 item)
         let mut stack = Vec::new();
         let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(!result); // False value (0) is not considered "true"
+        assert!(!result);
         assert_eq!(stack.len(), 1);
-        assert_eq!(stack[0], vec![0]); // False
     }
 
     #[test]
-    fn test_op_equal_insufficient_stack() {
-        let script = vec![0x51, 0x87]; // OP_1, OP_EQUAL (need 2 items)
+    fn test_op_pick() {
+        let script = vec![0x51, 0x52, 0x53, 0x51, 0x79]; // OP_1, OP_2, OP_3, OP_1, OP_PICK
         let mut stack = Vec::new();
         let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(!result);
+        assert!(!result); // Final stack has 4 items [1, 2, 3, 2], not exactly 1
+        assert_eq!(stack.len(), 4);
+        assert_eq!(stack[3], vec![2]); // Should pick index 1 (OP_2)
     }
 
     #[test]
-    fn test_op_verify() {
-        let script = vec![0x51, 0x69]; // OP_1, OP_VERIFY
+    fn test_op_pick_empty_n() {
+        let script = vec![0x51, 0x00, 0x79]; // OP_1, OP_0, OP_PICK (empty n is 0)
         let mut stack = Vec::new();
         let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(!result); // Final stack is empty, not exactly 1 item
-        assert_eq!(stack.len(), 0); // OP_VERIFY consumes the top item
+        assert!(!result); // Picks the top item: [1, 1]
+        assert_eq!(stack, vec![vec![1], vec![1]]);
     }
 
     #[test]
-    fn test_op_verify_false() {
-        let script = vec![0x00, 0x69]; // OP_0, OP_VERIFY (false)
+    fn test_op_pick_invalid_index() {
+        let script = vec![0x51, 0x52, 0x79]; // OP_1, OP_2, OP_PICK (n=2, but only 1 item)
         let mut stack = Vec::new();
         let result = eval_script(&script, &mut stack, 0).unwrap();
         assert!(!result);
+        assert_eq!(stack.len(), 1);
+    }
+
+    #[test]
+    fn test_op_roll() {
+        let script = vec![0x51, 0x52, 0x53, 0x51, 0x7a]; // OP_1, OP_2, OP_3, OP_1, OP_ROLL
+        let mut stack = Vec::new();
+        let result = eval_script(&script, &mut stack, 0).unwrap();
+        assert!(!result); // Final stack has 3 items [1, 3, 2], not exactly 1
+        assert_eq!(stack.len(), 3);
+        assert_eq!(stack[0], vec![1]);
+        assert_eq!(stack[1], vec![3]);
+        assert_eq!(stack[2], vec![2]); // Should roll index 1 (OP_2) to top
     }
 
     #[test]
-    fn test_op_verify_empty_stack() {
-        let script = vec![0x69]; // OP_VERIFY on empty stack
+    fn test_op_roll_empty_n() {
+        let script = vec![0x51, 0x00, 0x7a]; // OP_1, OP_0, OP_ROLL (empty n is 0)
         let mut stack = Vec::new();
         let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(!result);
+        assert!(result); // Rolling the top item is a no-op
+        assert_eq!(stack, vec![vec![1]]);
     }
 
     #[test]
-    fn test_op_equalverify() {
-        let script = vec![0x51, 0x51, 0x88]; // OP_1, OP_1, OP_EQUALVERIFY
-        let mut stack = Vec::new();
-        let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(!result); // Final stack is empty, not exactly 1 item
-        assert_eq!(stack.len(), 0); // OP_EQUALVERIFY consumes both items
+    fn test_op_pick_roll_multi_byte_index() {
+        let deep_stack: Vec<ByteString> = (0..300u16).map(|i| i.to_le_bytes().to_vec()).collect();
+
+        // Index 299 = 0x012b, a two-byte CScriptNum: the bottom item
+        let mut stack = deep_stack.clone();
+        stack.push(vec![0x2b, 0x01]);
+        assert!(!eval_script(&vec![0x79], &mut stack, 0).unwrap());
+        assert_eq!(stack.len(), 301);
+        assert_eq!(stack[300], vec![0, 0]);
+
+        let mut stack = deep_stack.clone();
+        stack.push(vec![0x2a, 0x01]); // 298
+        assert!(!eval_script(&vec![0x7a], &mut stack, 0).unwrap());
+        assert_eq!(stack.len(), 300);
+        assert_eq!(stack[299], vec![1, 0]);
+        assert_eq!(stack[1], vec![2, 0]);
+
+        // Out of range (300), negative (-1) and over-long indices fail
+        for n in [vec![0x2c, 0x01], vec![0x81], vec![0, 0, 0, 0, 0]] {
+            for opcode in [0x79, 0x7a] {
+                let mut stack = deep_stack.clone();
+                stack.push(n.clone());
+                assert!(!eval_script(&vec![opcode], &mut stack, 0).unwrap());
+                assert_eq!(stack.len(), 300);
+            }
+        }
     }
 
     #[test]
-    fn test_op_equalverify_false() {
-        let script = vec![0x51, 0x52, 0x88]; // OP_1, OP_2, OP_EQUALVERIFY (false)
+    fn test_op_roll_invalid_index() {
+        let script = vec![0x51, 0x52, 0x7a]; // OP_1, OP_2, OP_ROLL (n=2, but only 1 item)
         let mut stack = Vec::new();
         let result = eval_script(&script, &mut stack, 0).unwrap();
         assert!(!result);
+        assert_eq!(stack.len(), 1);
     }
 
     #[test]
-    fn test_op_checksig() {
-        // This is a simplified test - real OP_CHECKSIG would need proper signature verification
-        let script = vec![0x51, 0x51, 0xac]; // OP_1, OP_1, OP_CHECKSIG
+    fn test_op_rot() {
+        let script = vec![0x51, 0x52, 0x53, 0x7b]; // OP_1, OP_2, OP_3, OP_ROT
         let mut stack = Vec::new();
         let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(!result); // OP_CHECKSIG returns false in our simplified implementation
-        assert_eq!(stack.len(), 1);
-        // OP_CHECKSIG result depends on implementation
+        assert!(!result); // Final stack has 3 items [2, 3, 1], not exactly 1
+        assert_eq!(stack.len(), 3);
+        assert_eq!(stack[0], vec![2]);
+        assert_eq!(stack[1], vec![3]);
+        assert_eq!(stack[2], vec![1]);
     }
 
     #[test]
-    fn test_op_checksig_insufficient_stack() {
-        let script = vec![0x51, 0xac]; // OP_1, OP_CHECKSIG (need 2 items)
+    fn test_op_rot_insufficient_stack() {
+        let script = vec![0x51, 0x52, 0x7b]; // OP_1, OP_2, OP_ROT (only 2 items)
         let mut stack = Vec::new();
         let result = eval_script(&script, &mut stack, 0).unwrap();
         assert!(!result);
+        assert_eq!(stack.len(), 2);
     }
 
     #[test]
-    fn test_unknown_opcode() {
-        let script = vec![0xff]; // Unknown opcode
+    fn test_op_swap() {
+        let script = vec![0x51, 0x52, 0x7c]; // OP_1, OP_2, OP_SWAP
         let mut stack = Vec::new();
         let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(!result);
+        assert!(!result); // Final stack has 2 items [2, 1], not exactly 1
+        assert_eq!(stack.len(), 2);
+        assert_eq!(stack[0], vec![2]);
+        assert_eq!(stack[1], vec![1]);
     }
 
     #[test]
-    fn test_script_size_limit() {
-        let script = vec![0x51; MAX_SCRIPT_SIZE + 1]; // Exceed size limit
+    fn test_op_swap_insufficient_stack() {
+        let script = vec![0x51, 0x7c]; // OP_1, OP_SWAP (only 1 item)
         let mut stack = Vec::new();
-        let result = eval_script(&script, &mut stack, 0);
-        assert!(result.is_err());
+        let result = eval_script(&script, &mut stack, 0).unwrap();
+        assert!(!result);
+        assert_eq!(stack.len(), 1);
     }
 
     #[test]
-    fn test_operation_count_limit() {
-        let script = vec![0x51; MAX_SCRIPT_OPS + 1]; // Exceed operation limit
+    fn test_op_tuck() {
+        let script = vec![0x51, 0x52, 0x7d]; // OP_1, OP_2, OP_TUCK
         let mut stack = Vec::new();
-        let result = eval_script(&script, &mut stack, 0);
-        assert!(result.is_err());
+        let result = eval_script(&script, &mut stack, 0).unwrap();
+        assert!(!result); // Final stack has 3 items [2, 1, 2], not exactly 1
+        assert_eq!(stack.len(), 3);
+        assert_eq!(stack[0], vec![2]);
+        assert_eq!(stack[1], vec![1]);
+        assert_eq!(stack[2], vec![2]);
     }
 
     #[test]
-    fn test_stack_underflow_multiple_ops() {
-        let script = vec![0x51, 0x87, 0x87]; // OP_1, OP_EQUAL, OP_EQUAL (second OP_EQUAL will underflow)
+    fn test_op_tuck_insufficient_stack() {
+        let script = vec![0x51, 0x7d]; // OP_1, OP_TUCK (only 1 item)
         let mut stack = Vec::new();
         let result = eval_script(&script, &mut stack, 0).unwrap();
         assert!(!result);
+        assert_eq!(stack.len(), 1);
     }
 
     #[test]
-    fn test_final_stack_empty() {
-        let script = vec![0x51, 0x52]; // OP_1, OP_2 (two items on final stack)
+    fn test_op_2drop() {
+        let script = vec![0x51, 0x52, 0x53, 0x6d]; // OP_1, OP_2, OP_3, OP_2DROP
         let mut stack = Vec::new();
         let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(!result);
+        assert!(result); // Final stack has 1 item [1]
+        assert_eq!(stack.len(), 1);
+        assert_eq!(stack[0], vec![1]);
     }
 
     #[test]
-    fn test_final_stack_false() {
-        let script = vec![0x00]; // OP_0 (false on final stack)
+    fn test_op_2drop_insufficient_stack() {
+        let script = vec![0x51, 0x6d]; // OP_1, OP_2DROP (only 1 item)
         let mut stack = Vec::new();
         let result = eval_script(&script, &mut stack, 0).unwrap();
         assert!(!result);
+        assert_eq!(stack.len(), 1);
     }
 
     #[test]
-    fn test_verify_script_with_witness() {
-        let script_sig = vec![0x51]; // OP_1
-        let script_pubkey = vec![0x51]; // OP_1
-        let witness = vec![0x51]; // OP_1
-        let flags = 0;
-
-        let result = verify_script(&script_sig, &script_pubkey, Some(&witness), flags).unwrap();
-        assert!(!result); // Final stack has 2 items [1, 1], not exactly 1
-    }
-
-    #[test]
-    fn test_verify_script_failure() {
-        let script_sig = vec![0x51]; // OP_1
-        let script_pubkey = vec![0x52]; // OP_2
-        let witness = None;
-        let flags = 0;
-
-        let result = verify_script(&script_sig, &script_pubkey, witness, flags).unwrap();
-        assert!(!result);
+    fn test_op_2dup() {
+        let script = vec![0x51, 0x52, 0x6e]; // OP_1, OP_2, OP_2DUP
+        let mut stack = Vec::new();
+        let result = eval_script(&script, &mut stack, 0).unwrap();
+        assert!(!result); // Final stack has 4 items [1, 2, 1, 2], not exactly 1
+        assert_eq!(stack.len(), 4);
+        assert_eq!(stack[0], vec![1]);
+        assert_eq!(stack[1], vec![2]);
+        assert_eq!(stack[2], vec![1]);
+        assert_eq!(stack[3], vec![2]);
     }
 
-    // ============================================================================
-    // COMPREHENSIVE SCRIPT TESTS
-    // ============================================================================
-
     #[test]
-    fn test_op_ifdup_true() {
-        let script = vec![0x51, 0x73]; // OP_1, OP_IFDUP
+    fn test_op_2dup_insufficient_stack() {
+        let script = vec![0x51, 0x6e]; // OP_1, OP_2DUP (only 1 item)
         let mut stack = Vec::new();
         let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(!result); // Final stack has 2 items [1, 1], not exactly 1
-        assert_eq!(stack.len(), 2);
-        assert_eq!(stack[0], vec![1]);
-        assert_eq!(stack[1], vec![1]);
+        assert!(!result);
+        assert_eq!(stack.len(), 1);
     }
 
     #[test]
-    fn test_op_ifdup_false() {
-        let script = vec![0x00, 0x73]; // OP_0, OP_IFDUP
+    fn test_op_3dup() {
+        let script = vec![0x51, 0x52, 0x53, 0x6f]; // OP_1, OP_2, OP_3, OP_3DUP
         let mut stack = Vec::new();
         let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(!result); // Final stack has 1 item [0], which is false
-        assert_eq!(stack.len(), 1);
-        assert_eq!(stack[0], Vec::<u8>::new());
+        assert!(!result); // Final stack has 6 items, not exactly 1
+        assert_eq!(stack.len(), 6);
+        assert_eq!(stack[0], vec![1]);
+        assert_eq!(stack[1], vec![2]);
+        assert_eq!(stack[2], vec![3]);
+        assert_eq!(stack[3], vec![1]);
+        assert_eq!(stack[4], vec![2]);
+        assert_eq!(stack[5], vec![3]);
     }
 
     #[test]
-    fn test_op_depth() {
-        let script = vec![0x51, 0x51, 0x74]; // OP_1, OP_1, OP_DEPTH
+    fn test_op_3dup_insufficient_stack() {
+        let script = vec![0x51, 0x52, 0x6f]; // OP_1, OP_2, OP_3DUP (only 2 items)
         let mut stack = Vec::new();
         let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(!result); // Final stack has 3 items, not exactly 1
-        assert_eq!(stack.len(), 3);
-        assert_eq!(stack[2], vec![2]); // Depth should be 2 (before OP_DEPTH)
+        assert!(!result);
+        assert_eq!(stack.len(), 2);
     }
 
     #[test]
-    fn test_op_drop() {
-        let script = vec![0x51, 0x52, 0x75]; // OP_1, OP_2, OP_DROP
+    fn test_op_2over() {
+        let script = vec![0x51, 0x52, 0x53, 0x54, 0x70]; // OP_1, OP_2, OP_3, OP_4, OP_2OVER
         let mut stack = Vec::new();
         let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(result); // Final stack has 1 item [1]
-        assert_eq!(stack.len(), 1);
-        assert_eq!(stack[0], vec![1]);
+        assert!(!result); // Final stack has 6 items, not exactly 1
+        assert_eq!(stack.len(), 6);
+        assert_eq!(stack[4], vec![1]); // Should copy second pair
+        assert_eq!(stack[5], vec![2]);
     }
 
     #[test]
-    fn test_op_drop_empty_stack() {
-        let script = vec![0x75]; // OP_DROP on empty stack
+    fn test_op_2over_insufficient_stack() {
+        let script = vec![0x51, 0x52, 0x53, 0x70]; // OP_1, OP_2, OP_3, OP_2OVER (only 3 items)
         let mut stack = Vec::new();
         let result = eval_script(&script, &mut stack, 0).unwrap();
         assert!(!result);
-        assert_eq!(stack.len(), 0);
+        assert_eq!(stack.len(), 3);
     }
 
     #[test]
-    fn test_op_nip() {
-        let script = vec![0x51, 0x52, 0x77]; // OP_1, OP_2, OP_NIP
+    fn test_op_2rot() {
+        let script = vec![0x51, 0x52, 0x53, 0x54, 0x55, 0x56, 0x71]; // 6 items, OP_2ROT
         let mut stack = Vec::new();
         let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(result); // Final stack has 1 item [2]
-        assert_eq!(stack.len(), 1);
-        assert_eq!(stack[0], vec![2]);
+        assert!(!result); // Final stack has 6 items, not exactly 1
+        assert_eq!(stack.len(), 6);
+        assert_eq!(stack[4], vec![2]); // Should rotate second pair to top
+        assert_eq!(stack[5], vec![1]);
     }
 
     #[test]
-    fn test_op_nip_insufficient_stack() {
-        let script = vec![0x51, 0x77]; // OP_1, OP_NIP (only 1 item)
+    fn test_op_2rot_insufficient_stack() {
+        let script = vec![0x51, 0x52, 0x53, 0x54, 0x71]; // OP_1, OP_2, OP_3, OP_4, OP_2ROT (only 4 items)
         let mut stack = Vec::new();
         let result = eval_script(&script, &mut stack, 0).unwrap();
         assert!(!result);
-        assert_eq!(stack.len(), 1);
+        assert_eq!(stack.len(), 4);
     }
 
     #[test]
-    fn test_op_over() {
-        let script = vec![0x51, 0x52, 0x78]; // OP_1, OP_2, OP_OVER
+    fn test_op_2swap() {
+        let script = vec![0x51, 0x52, 0x53, 0x54, 0x72]; // OP_1, OP_2, OP_3, OP_4, OP_2SWAP
         let mut stack = Vec::new();
         let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(!result); // Final stack has 3 items [1, 2, 1], not exactly 1
-        assert_eq!(stack.len(), 3);
-        assert_eq!(stack[0], vec![1]);
-        assert_eq!(stack[1], vec![2]);
+        assert!(!result); // Final stack has 4 items, not exactly 1
+        assert_eq!(stack.len(), 4);
+        assert_eq!(stack[0], vec![3]); // Should swap second pair
+        assert_eq!(stack[1], vec![4]);
         assert_eq!(stack[2], vec![1]);
+        assert_eq!(stack[3], vec![2]);
     }
 
     #[test]
-    fn test_op_over_insufficient_stack() {
-        let script = vec![0x51, 0x78]; // OP_1, OP_OVER (only 1 item)
+    fn test_op_2swap_insufficient_stack() {
+        let script = vec![0x51, 0x52, 0x53, 0x72]; // OP_1, OP_2, OP_3, OP_2SWAP (only 3 items)
         let mut stack = Vec::new();
         let result = eval_script(&script, &mut stack, 0).unwrap();
         assert!(!result);
-        assert_eq!(stack.len(), 1);
+        assert_eq!(stack.len(), 3);
     }
 
     #[test]
-    fn test_op_pick() {
-        let script = vec![0x51, 0x52, 0x53, 0x51, 0x79]; // OP_1, OP_2, OP_3, OP_1, OP_PICK
+    fn test_op_size() {
+        let script = vec![0x51, 0x82]; // OP_1, OP_SIZE
         let mut stack = Vec::new();
         let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(!result); // Final stack has 4 items [1, 2, 3, 2], not exactly 1
-        assert_eq!(stack.len(), 4);
-        assert_eq!(stack[3], vec![2]); // Should pick index 1 (OP_2)
+        assert!(!result); // Final stack has 2 items [1, 1], not exactly 1
+        assert_eq!(stack.len(), 2);
+        assert_eq!(stack[0], vec![1]);
+        assert_eq!(stack[1], vec![1]); // Size of [1] is 1
     }
 
     #[test]
-    fn test_op_pick_empty_n() {
-        let script = vec![0x51, 0x00, 0x79]; // OP_1, OP_0, OP_PICK (n is empty)
+    fn test_op_size_empty_stack() {
+        let script = vec![0x82]; // OP_SIZE on empty stack
         let mut stack = Vec::new();
         let result = eval_script(&script, &mut stack, 0).unwrap();
         assert!(!result);
-        assert_eq!(stack.len(), 1);
+        assert_eq!(stack.len(), 0);
     }
 
     #[test]
-    fn test_op_pick_invalid_index() {
-        let script = vec![0x51, 0x52, 0x79]; // OP_1, OP_2, OP_PICK (n=2, but only 1 item)
+    fn test_op_return() {
+        let script = vec![0x51, 0x6a]; // OP_1, OP_RETURN
         let mut stack = Vec::new();
         let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(!result);
+        assert!(!result); // OP_RETURN always fails
         assert_eq!(stack.len(), 1);
     }
 
     #[test]
-    fn test_op_roll() {
-        let script = vec![0x51, 0x52, 0x53, 0x51, 0x7a]; // OP_1, OP_2, OP_3, OP_1, OP_ROLL
+    fn test_op_checksigverify() {
+        let script = vec![0x51, 0x52, 0xad]; // OP_1, OP_2, OP_CHECKSIGVERIFY
         let mut stack = Vec::new();
         let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(!result); // Final stack has 3 items [1, 3, 2], not exactly 1
-        assert_eq!(stack.len(), 3);
-        assert_eq!(stack[0], vec![1]);
-        assert_eq!(stack[1], vec![3]);
-        assert_eq!(stack[2], vec![2]); // Should roll index 1 (OP_2) to top
+        assert!(!result); // Should fail due to invalid signature
+        assert_eq!(stack.len(), 0);
     }
 
     #[test]
-    fn test_op_roll_empty_n() {
-        let script = vec![0x51, 0x00, 0x7a]; // OP_1, OP_0, OP_ROLL (n is empty)
-        let mut stack = Vec::new();
-        let result = eval_script(&script, &mut stack, 0).unwrap();
+    fn test_failing_verify_opcodes_consume_operands() {
+        // Each failing VERIFY opcode pops its operands, fails the script and
+        // leaves no boolean behind; items below the operands are untouched
+        let below = vec![0x42];
+
+        // OP_EQUALVERIFY errors rather than returning false
+        let mut stack = vec![below.clone(), vec![1], vec![2]];
+        assert!(execute_opcode(0x88, &mut stack, 0).is_err());
+        assert_eq!(stack, vec![below.clone()]);
+
+        // OP_NUMEQUALVERIFY
+        let mut stack = vec![below.clone(), vec![1], vec![2]];
+        assert!(!execute_opcode(0x9d, &mut stack, 0).unwrap());
+        assert_eq!(stack, vec![below.clone()]);
+
+        // OP_CHECKSIGVERIFY with a garbage signature
+        let mut stack = vec![below.clone(), vec![0x30, 0x01], vec![0x02; 33]];
+        assert!(!execute_opcode(0xad, &mut stack, 0).unwrap());
+        assert_eq!(stack, vec![below.clone()]);
+
+        // OP_CHECKMULTISIGVERIFY, 1-of-1 with a garbage signature
+        let tx = Transaction {
+            version: 1,
+            inputs: crate::tx_inputs![TransactionInput {
+                prevout: OutPoint {
+                    hash: [1; 32],
+                    index: 0,
+                },
+                script_sig: vec![],
+                sequence: 0xffffffff,
+            }],
+            outputs: crate::tx_outputs![TransactionOutput {
+                value: 1000,
+                script_pubkey: vec![0x51],
+            }],
+            lock_time: 0,
+        };
+        let prevouts = vec![TransactionOutput {
+            value: 2000,
+            script_pubkey: vec![0x51],
+        }];
+        let mut stack = vec![
+            below.clone(),
+            vec![],           // dummy
+            vec![0x30, 0x01], // signature
+            vec![1],          // m
+            vec![0x02; 33],   // pubkey
+            vec![1],          // n
+        ];
+        let result = execute_opcode_with_context_full(
+            0xaf,
+            &mut stack,
+            0,
+            &tx,
+            0,
+            &prevouts,
+            &[],
+            SigVersion::Base,
+            None,
+            None,
+            crate::types::Network::Regtest,
+            default_verifier(),
+        )
+        .unwrap();
         assert!(!result);
-        assert_eq!(stack.len(), 1);
+        assert_eq!(stack, vec![below]);
     }
 
     #[test]
-    fn test_op_roll_invalid_index() {
-        let script = vec![0x51, 0x52, 0x7a]; // OP_1, OP_2, OP_ROLL (n=2, but only 1 item)
-        let mut stack = Vec::new();
-        let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(!result);
-        assert_eq!(stack.len(), 1);
+    fn test_checkmultisig_nulldummy() {
+        let tx = Transaction {
+            version: 1,
+            inputs: crate::tx_inputs![TransactionInput {
+                prevout: OutPoint {
+                    hash: [1; 32],
+                    index: 0,
+                },
+                script_sig: vec![],
+                sequence: 0xffffffff,
+            }],
+            outputs: crate::tx_outputs![],
+            lock_time: 0,
+        };
+        let prevouts = vec![TransactionOutput {
+            value: 2000,
+            script_pubkey: vec![0x51],
+        }];
+        // A 0-of-0 multisig succeeds whatever the dummy, unless NULLDUMMY applies
+        let check = |dummy: ByteString, flags: u32, height: Option<u64>, network| {
+            let mut stack = vec![dummy, vec![0], vec![0]];
+            execute_opcode_with_context_full(
+                0xae,
+                &mut stack,
+                flags,
+                &tx,
+                0,
+                &prevouts,
+                &[],
+                SigVersion::Base,
+                height,
+                None,
+                network,
+                default_verifier(),
+            )
+            .unwrap()
+                && stack == vec![vec![1]]
+        };
+        let regtest = crate::types::Network::Regtest;
+
+        assert!(check(vec![], SCRIPT_VERIFY_NULLDUMMY, None, regtest));
+        assert!(check(vec![1], 0, None, regtest));
+        assert!(!check(vec![1], SCRIPT_VERIFY_NULLDUMMY, None, regtest));
+        // A zero byte is not the empty vector
+        assert!(!check(vec![0], SCRIPT_VERIFY_NULLDUMMY, None, regtest));
+
+        // Not enforced before activation
+        let mainnet = crate::types::Network::Mainnet;
+        let bip147 = crate::bip_validation::bip147_activation_height(mainnet);
+        assert!(check(
+            vec![1],
+            SCRIPT_VERIFY_NULLDUMMY,
+            Some(bip147 - 1),
+            mainnet
+        ));
+        assert!(!check(
+            vec![1],
+            SCRIPT_VERIFY_NULLDUMMY,
+            Some(bip147),
+            mainnet
+        ));
     }
 
     #[test]
-    fn test_op_rot() {
-        let script = vec![0x51, 0x52, 0x53, 0x7b]; // OP_1, OP_2, OP_3, OP_ROT
-        let mut stack = Vec::new();
-        let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(!result); // Final stack has 3 items [2, 3, 1], not exactly 1
-        assert_eq!(stack.len(), 3);
-        assert_eq!(stack[0], vec![2]);
-        assert_eq!(stack[1], vec![3]);
-        assert_eq!(stack[2], vec![1]);
-    }
+    fn test_checkmultisig_nullfail() {
+        let tx = Transaction {
+            version: 1,
+            inputs: crate::tx_inputs![TransactionInput {
+                prevout: OutPoint {
+                    hash: [1; 32],
+                    index: 0,
+                },
+                script_sig: vec![],
+                sequence: 0xffffffff,
+            }],
+            outputs: crate::tx_outputs![],
+            lock_time: 0,
+        };
+        let prevouts = vec![TransactionOutput {
+            value: 2000,
+            script_pubkey: vec![0x51],
+        }];
+        // 1-of-1 with a signature that does not verify
+        let checkmultisig = |signature: ByteString, flags: u32| {
+            let mut stack = vec![vec![], signature, vec![1], vec![0x02; 33], vec![1]];
+            let result = execute_opcode_with_context_full(
+                0xae,
+                &mut stack,
+                flags,
+                &tx,
+                0,
+                &prevouts,
+                &[],
+                SigVersion::Base,
+                None,
+                None,
+                crate::types::Network::Regtest,
+                default_verifier(),
+            )
+            .unwrap();
+            (result, stack)
+        };
 
-    #[test]
-    fn test_op_rot_insufficient_stack() {
-        let script = vec![0x51, 0x52, 0x7b]; // OP_1, OP_2, OP_ROT (only 2 items)
-        let mut stack = Vec::new();
-        let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(!result);
-        assert_eq!(stack.len(), 2);
+        let failing = vec![0x30, 0x06, 0x01];
+        assert_eq!(checkmultisig(failing.clone(), 0), (true, vec![vec![]]));
+        assert_eq!(
+            checkmultisig(failing, SCRIPT_VERIFY_NULLFAIL),
+            (false, vec![])
+        );
+        // Empty signatures may fail
+        assert_eq!(
+            checkmultisig(vec![], SCRIPT_VERIFY_NULLFAIL),
+            (true, vec![vec![]])
+        );
     }
 
     #[test]
-    fn test_op_swap() {
-        let script = vec![0x51, 0x52, 0x7c]; // OP_1, OP_2, OP_SWAP
-        let mut stack = Vec::new();
-        let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(!result); // Final stack has 2 items [2, 1], not exactly 1
-        assert_eq!(stack.len(), 2);
-        assert_eq!(stack[0], vec![2]);
-        assert_eq!(stack[1], vec![1]);
-    }
+    fn test_checkmultisig_key_count_limits() {
+        let tx = Transaction {
+            version: 1,
+            inputs: crate::tx_inputs![TransactionInput {
+                prevout: OutPoint {
+                    hash: [1; 32],
+                    index: 0,
+                },
+                script_sig: vec![],
+                sequence: 0xffffffff,
+            }],
+            outputs: crate::tx_outputs![TransactionOutput {
+                value: 1000,
+                script_pubkey: vec![0x51],
+            }],
+            lock_time: 0,
+        };
+        let prevouts = vec![TransactionOutput {
+            value: 2000,
+            script_pubkey: vec![0x51],
+        }];
+        let run = |script: &ByteString, stack: &mut Vec<ByteString>| {
+            eval_script_with_context_full(
+                script,
+                stack,
+                0,
+                &tx,
+                0,
+                &prevouts,
+                SigVersion::Base,
+                None,
+                None,
+                crate::types::Network::Regtest,
+                default_verifier(),
+            )
+        };
 
-    #[test]
-    fn test_op_swap_insufficient_stack() {
-        let script = vec![0x51, 0x7c]; // OP_1, OP_SWAP (only 1 item)
-        let mut stack = Vec::new();
-        let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(!result);
-        assert_eq!(stack.len(), 1);
+        // More than MAX_PUBKEYS_PER_MULTISIG keys
+        let mut stack = vec![vec![MAX_PUBKEYS_PER_MULTISIG as u8 + 1]];
+        assert_eq!(run(&vec![0xae], &mut stack), Err(make_pubkey_count_error()));
+        let mut stack = vec![vec![0x81]]; // -1
+        assert_eq!(run(&vec![0xae], &mut stack), Err(make_pubkey_count_error()));
+
+        // 16 keys cost 16 operations on top of the opcode itself (OP_16 is
+        // not counted): 185 NOPs + CHECKMULTISIG + 16 keys = 202 > MAX_SCRIPT_OPS
+        let mut script = vec![0x61; 185];
+        script.extend_from_slice(&[0x60, 0xae]);
+        assert_eq!(
+            run(&script, &mut Vec::new()),
+            Err(make_operation_limit_error())
+        );
+
+        // One NOP fewer stays within the limit; the multisig then just fails
+        script.remove(0);
+        assert_eq!(run(&script, &mut Vec::new()), Ok(false));
     }
 
     #[test]
-    fn test_op_tuck() {
-        let script = vec![0x51, 0x52, 0x7d]; // OP_1, OP_2, OP_TUCK
-        let mut stack = Vec::new();
-        let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(!result); // Final stack has 3 items [2, 1, 2], not exactly 1
-        assert_eq!(stack.len(), 3);
-        assert_eq!(stack[0], vec![2]);
-        assert_eq!(stack[1], vec![1]);
-        assert_eq!(stack[2], vec![2]);
+    fn test_checkmultisig_counts_are_script_numbers() {
+        let pubkey = [vec![0x21], vec![0x02; 33]].concat();
+        let run =
+            |script: ByteString| eval_script(&script, &mut Vec::new(), SCRIPT_VERIFY_MINIMALDATA);
+
+        // Zero keys and zero signatures are valid counts
+        assert_eq!(run(vec![0x00, 0x00, 0x00, 0xae]), Ok(true)); // 0-of-0
+        assert_eq!(
+            run([vec![0x00, 0x00], pubkey.clone(), vec![0x51, 0xae]].concat()),
+            Ok(true)
+        ); // 0-of-1
+
+        // Signature counts that are negative or above the key count fail
+        assert_eq!(run(vec![0x00, 0x4f, 0x00, 0xae]), Ok(false)); // -1-of-0
+        assert_eq!(run(vec![0x00, 0x00, 0x51, 0x00, 0xae]), Ok(false)); // 1-of-0
+
+        // Counts are decoded as script numbers, not by their first byte
+        assert_eq!(
+            run(vec![0x00, 0x00, 0x4f, 0xae]),
+            Err(make_pubkey_count_error())
+        ); // 0-of--1
+        assert_eq!(
+            run(vec![0x00, 0x00, 0x02, 0x00, 0x00, 0xae]),
+            Err(make_pubkey_count_error())
+        ); // non-minimal zero key count
     }
 
     #[test]
-    fn test_op_tuck_insufficient_stack() {
-        let script = vec![0x51, 0x7d]; // OP_1, OP_TUCK (only 1 item)
-        let mut stack = Vec::new();
-        let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(!result);
-        assert_eq!(stack.len(), 1);
+    fn test_cltv_rejects_mismatched_locktime_types() {
+        use crate::locktime::encode_locktime_value;
+
+        let tx_with_locktime = |lock_time: Natural| Transaction {
+            version: 1,
+            inputs: crate::tx_inputs![TransactionInput {
+                prevout: OutPoint {
+                    hash: [1; 32],
+                    index: 0,
+                },
+                script_sig: vec![],
+                sequence: 0xfffffffe,
+            }],
+            outputs: crate::tx_outputs![TransactionOutput {
+                value: 1000,
+                script_pubkey: vec![0x51],
+            }],
+            lock_time,
+        };
+        let prevouts = vec![TransactionOutput {
+            value: 2000,
+            script_pubkey: vec![0x51],
+        }];
+        let cltv = |lock_time: Natural, operand: ByteString| {
+            let tx = tx_with_locktime(lock_time);
+            let mut stack = vec![operand];
+            execute_opcode_with_context_full(
+                0xb1,
+                &mut stack,
+                0,
+                &tx,
+                0,
+                &prevouts,
+                &[],
+                SigVersion::Base,
+                None,
+                None,
+                crate::types::Network::Regtest,
+                default_verifier(),
+            )
+            .unwrap()
+        };
+        let height = 499_999_999;
+        let time = LOCKTIME_THRESHOLD as Natural;
+
+        // Same side of the threshold
+        assert!(cltv(height, encode_locktime_value(1000)));
+        assert!(cltv(time + 100, encode_locktime_value(time as u32)));
+
+        // Height operand against a time lock_time, and the reverse
+        assert!(!cltv(time, encode_locktime_value(1000)));
+        assert!(!cltv(height, encode_locktime_value(time as u32)));
+
+        // Negative operands fail instead of reading the sign bit as magnitude
+        assert!(!cltv(height, vec![0x81]));
+        assert!(!cltv(time + 100, vec![0x00, 0x65, 0xcd, 0x9d]));
     }
 
     #[test]
-    fn test_op_2drop() {
-        let script = vec![0x51, 0x52, 0x53, 0x6d]; // OP_1, OP_2, OP_3, OP_2DROP
-        let mut stack = Vec::new();
-        let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(result); // Final stack has 1 item [1]
-        assert_eq!(stack.len(), 1);
-        assert_eq!(stack[0], vec![1]);
+    fn test_csv_relative_locktime_rules() {
+        use crate::locktime::encode_locktime_value;
+
+        const TYPE_FLAG: u32 = 0x00400000;
+        const DISABLE_FLAG: u32 = 0x80000000;
+
+        let csv = |version: Natural, sequence: u32, operand: u32, flags: u32| {
+            let tx = Transaction {
+                version,
+                inputs: crate::tx_inputs![TransactionInput {
+                    prevout: OutPoint {
+                        hash: [1; 32],
+                        index: 0,
+                    },
+                    script_sig: vec![],
+                    sequence: sequence as Natural,
+                }],
+                outputs: crate::tx_outputs![TransactionOutput {
+                    value: 1000,
+                    script_pubkey: vec![0x51],
+                }],
+                lock_time: 0,
+            };
+            let operand = encode_locktime_value(operand);
+            let mut stack = vec![operand.clone()];
+            let result = execute_opcode_with_context_full(
+                0xb2,
+                &mut stack,
+                flags,
+                &tx,
+                0,
+                &[],
+                &[],
+                SigVersion::Base,
+                None,
+                None,
+                crate::types::Network::Regtest,
+                default_verifier(),
+            )
+            .unwrap();
+            // CSV never consumes its operand
+            assert_eq!(stack, vec![operand]);
+            result
+        };
+        let enforce = SCRIPT_VERIFY_CHECKSEQUENCEVERIFY;
+
+        // Block-height and time-based locks
+        assert!(csv(2, 10, 10, enforce));
+        assert!(csv(2, 20, 10, enforce));
+        assert!(!csv(2, 9, 10, enforce));
+        assert!(csv(2, TYPE_FLAG | 10, TYPE_FLAG | 10, enforce));
+
+        // Mismatched type flags
+        assert!(!csv(2, TYPE_FLAG | 100, 10, enforce));
+        assert!(!csv(2, 100, TYPE_FLAG | 10, enforce));
+
+        // Disabled input sequence fails; a disabled operand is a NOP
+        assert!(!csv(2, DISABLE_FLAG | 10, 10, enforce));
+        assert!(csv(2, DISABLE_FLAG, DISABLE_FLAG | 10, enforce));
+        assert!(csv(1, 0, DISABLE_FLAG, enforce));
+
+        // Version 1 transactions have no relative locks
+        assert!(!csv(1, 10, 10, enforce));
+
+        // A negative operand fails even though its magnitude is satisfied
+        let tx = Transaction {
+            version: 2,
+            inputs: crate::tx_inputs![TransactionInput {
+                prevout: OutPoint {
+                    hash: [1; 32],
+                    index: 0,
+                },
+                script_sig: vec![],
+                sequence: 10,
+            }],
+            outputs: crate::tx_outputs![],
+            lock_time: 0,
+        };
+        let mut stack = vec![vec![0x81]];
+        assert!(!execute_opcode_with_context_full(
+            0xb2,
+            &mut stack,
+            enforce,
+            &tx,
+            0,
+            &[],
+            &[],
+            SigVersion::Base,
+            None,
+            None,
+            crate::types::Network::Regtest,
+            default_verifier(),
+        )
+        .unwrap());
+
+        // Without the flag it is OP_NOP3
+        assert!(csv(1, DISABLE_FLAG, 10, 0));
+        let mut stack = vec![vec![0x81]];
+        assert!(execute_opcode(0xb2, &mut stack, 0).unwrap());
+        assert!(!execute_opcode(0xb2, &mut stack, enforce).unwrap());
     }
 
     #[test]
-    fn test_op_2drop_insufficient_stack() {
-        let script = vec![0x51, 0x6d]; // OP_1, OP_2DROP (only 1 item)
-        let mut stack = Vec::new();
-        let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(!result);
-        assert_eq!(stack.len(), 1);
+    fn test_op_numequal_and_numequalverify() {
+        // Numeric, not byte-wise, comparison: 5 == 5 with a padding byte
+        let mut stack = vec![vec![5], vec![5, 0x00]];
+        assert!(execute_opcode(0x9c, &mut stack, 0).unwrap());
+        assert_eq!(stack, vec![vec![1]]);
+
+        let mut stack = vec![vec![5], vec![5, 0x00]];
+        assert!(execute_opcode(0x9d, &mut stack, 0).unwrap());
+        assert!(stack.is_empty());
+
+        // -1 vs 1: false is the empty script number
+        let mut stack = vec![vec![0x81], vec![0x01]];
+        assert!(execute_opcode(0x9c, &mut stack, 0).unwrap());
+        assert_eq!(stack, vec![Vec::<u8>::new()]);
+
+        // Operands longer than 4 bytes are not numbers
+        let mut stack = vec![vec![0; 5], vec![0]];
+        assert!(!execute_opcode(0x9d, &mut stack, 0).unwrap());
     }
 
     #[test]
-    fn test_op_2dup() {
-        let script = vec![0x51, 0x52, 0x6e]; // OP_1, OP_2, OP_2DUP
-        let mut stack = Vec::new();
-        let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(!result); // Final stack has 4 items [1, 2, 1, 2], not exactly 1
-        assert_eq!(stack.len(), 4);
-        assert_eq!(stack[0], vec![1]);
-        assert_eq!(stack[1], vec![2]);
-        assert_eq!(stack[2], vec![1]);
-        assert_eq!(stack[3], vec![2]);
+    fn test_script_num_result_may_exceed_operand_limit() {
+        assert_eq!(script_num_bytes(0), Vec::<u8>::new());
+        assert_eq!(script_num_bytes(-1), vec![0x81]);
+        assert_eq!(script_num_bytes(0x80), vec![0x80, 0x00]);
+        assert_eq!(script_num_bytes(-0x80), vec![0x80, 0x80]);
+        assert_eq!(script_num_bytes(0x7fffffff), vec![0xff, 0xff, 0xff, 0x7f]);
+        for value in [0, 1, -1, 127, -128, 255, 0x7fffffff, -0x7fffffff] {
+            assert_eq!(decode_script_num(&script_num_bytes(value), 0), Some(value));
+        }
+
+        // 0x7fffffff + 0x7fffffff = 0xfffffffe needs a fifth (sign) byte
+        let max = script_num_bytes(0x7fffffff);
+        let mut stack = vec![max.clone(), max.clone()];
+        assert!(execute_opcode(0x93, &mut stack, 0).unwrap());
+        assert_eq!(stack, vec![vec![0xfe, 0xff, 0xff, 0xff, 0x00]]);
+
+        // The 5-byte result is not a valid operand for another OP_ADD
+        stack.push(vec![0x01]);
+        assert!(!execute_opcode(0x93, &mut stack, 0).unwrap());
     }
 
     #[test]
-    fn test_op_2dup_insufficient_stack() {
-        let script = vec![0x51, 0x6e]; // OP_1, OP_2DUP (only 1 item)
+    fn test_depth_and_size_push_script_numbers() {
+        // OP_DEPTH on an empty stack pushes zero, which is the empty vector
         let mut stack = Vec::new();
-        let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(!result);
-        assert_eq!(stack.len(), 1);
+        assert!(execute_opcode(0x74, &mut stack, 0).unwrap());
+        assert_eq!(stack, vec![Vec::<u8>::new()]);
+
+        // 300 items need two bytes
+        let mut stack = vec![vec![]; 300];
+        assert!(execute_opcode(0x74, &mut stack, 0).unwrap());
+        assert_eq!(stack.last().unwrap(), &vec![0x2c, 0x01]);
+
+        // A 128-byte item needs a sign byte
+        let mut stack = vec![vec![0xaa; 128]];
+        assert!(execute_opcode(0x82, &mut stack, 0).unwrap());
+        assert_eq!(stack[1], vec![0x80, 0x00]);
+        assert_eq!(decode_script_num(&stack[1], 0), Some(128));
     }
 
     #[test]
-    fn test_op_3dup() {
-        let script = vec![0x51, 0x52, 0x53, 0x6f]; // OP_1, OP_2, OP_3, OP_3DUP
-        let mut stack = Vec::new();
-        let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(!result); // Final stack has 6 items, not exactly 1
-        assert_eq!(stack.len(), 6);
-        assert_eq!(stack[0], vec![1]);
-        assert_eq!(stack[1], vec![2]);
-        assert_eq!(stack[2], vec![3]);
-        assert_eq!(stack[3], vec![1]);
-        assert_eq!(stack[4], vec![2]);
-        assert_eq!(stack[5], vec![3]);
+    fn test_numeric_opcodes() {
+        let num = script_num_bytes;
+        let unary = |opcode: u8, a: &[u8]| {
+            let mut stack = vec![a.to_vec()];
+            execute_opcode(opcode, &mut stack, 0)
+                .unwrap()
+                .then(|| stack.pop().unwrap())
+        };
+        let binary = |opcode: u8, a: i64, b: i64| {
+            let mut stack = vec![num(a), num(b)];
+            assert!(execute_opcode(opcode, &mut stack, 0).unwrap());
+            assert_eq!(stack.len(), 1);
+            decode_script_num(&stack[0], 0).unwrap()
+        };
+
+        assert_eq!(unary(0x8b, &num(-1)), Some(vec![])); // OP_1ADD
+        assert_eq!(unary(0x8c, &num(0)), Some(num(-1))); // OP_1SUB
+        assert_eq!(unary(0x8f, &num(5)), Some(num(-5))); // OP_NEGATE
+        assert_eq!(unary(0x90, &num(-5)), Some(num(5))); // OP_ABS
+        assert_eq!(unary(0x91, &num(7)), Some(vec![])); // OP_NOT
+        assert_eq!(unary(0x92, &num(7)), Some(num(1))); // OP_0NOTEQUAL
+
+        // Negative zero decodes as zero, and results are never negative zero
+        for zero in [vec![0x80], vec![0x00, 0x80], vec![0x00, 0x00]] {
+            assert_eq!(unary(0x91, &zero), Some(num(1)));
+            assert_eq!(unary(0x92, &zero), Some(vec![]));
+            assert_eq!(unary(0x8f, &zero), Some(vec![]));
+            assert_eq!(unary(0x90, &zero), Some(vec![]));
+        }
+        assert_eq!(binary(0x9c, 0, 0), 1);
+        let mut stack = vec![vec![0x80], vec![]];
+        assert!(execute_opcode(0x9d, &mut stack, 0).unwrap());
+
+        assert_eq!(binary(0x94, 3, 5), -2); // OP_SUB: second minus top
+        assert_eq!(binary(0x9a, 1, 0), 0); // OP_BOOLAND
+        assert_eq!(binary(0x9b, 1, 0), 1); // OP_BOOLOR
+        assert_eq!(binary(0x9e, 1, 2), 1); // OP_NUMNOTEQUAL
+        assert_eq!(binary(0x9f, -1, 1), 1); // OP_LESSTHAN
+        assert_eq!(binary(0xa0, -1, 1), 0); // OP_GREATERTHAN
+        assert_eq!(binary(0xa1, 2, 2), 1); // OP_LESSTHANOREQUAL
+        assert_eq!(binary(0xa2, 1, 2), 0); // OP_GREATERTHANOREQUAL
+        assert_eq!(binary(0xa3, -3, 2), -3); // OP_MIN
+        assert_eq!(binary(0xa4, -3, 2), 2); // OP_MAX
+
+        // OP_WITHIN: min <= x < max
+        for (x, expected) in [(1, num(1)), (3, vec![]), (0, vec![])] {
+            let mut stack = vec![num(x), num(1), num(3)];
+            assert!(execute_opcode(0xa5, &mut stack, 0).unwrap());
+            assert_eq!(stack, vec![expected]);
+        }
+
+        // Overflow fails cleanly: results past 4 bytes are not operands
+        let min = num(-0x7fffffff);
+        let mut stack = vec![min.clone(), num(1)];
+        assert!(execute_opcode(0x94, &mut stack, 0).unwrap());
+        assert_eq!(stack, vec![vec![0x00, 0x00, 0x00, 0x80, 0x80]]);
+        assert_eq!(unary(0x8c, &stack[0]), None);
+        assert_eq!(unary(0x8c, &min), Some(vec![0x00, 0x00, 0x00, 0x80, 0x80]));
+
+        // Missing operands fail
+        assert_eq!(unary(0x8b, &[0; 5]), None);
+        assert!(!execute_opcode(0x8b, &mut vec![], 0).unwrap());
+        assert!(!execute_opcode(0x93, &mut vec![num(1)], 0).unwrap());
+        assert!(!execute_opcode(0xa5, &mut vec![num(1), num(2)], 0).unwrap());
     }
 
     #[test]
-    fn test_op_3dup_insufficient_stack() {
-        let script = vec![0x51, 0x52, 0x6f]; // OP_1, OP_2, OP_3DUP (only 2 items)
-        let mut stack = Vec::new();
-        let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(!result);
-        assert_eq!(stack.len(), 2);
+    fn test_opcode_table_matches_match_dispatch() {
+        let stacks: [Vec<ByteString>; 5] = [
+            vec![],
+            vec![vec![0x01]],
+            vec![vec![], vec![0x81]],
+            vec![vec![0x02], vec![0xaa; 3], vec![0x01], vec![0x01]],
+            vec![
+                vec![0x05],
+                vec![0x00, 0x80],
+                vec![0x7f; 4],
+                vec![0x01, 0x00],
+                vec![0x03],
+            ],
+        ];
+        let flags = [
+            0,
+            SCRIPT_VERIFY_STRICTENC | SCRIPT_VERIFY_MINIMALDATA | SCRIPT_VERIFY_CHECKSEQUENCEVERIFY,
+        ];
+        for opcode in 0..=u8::MAX {
+            for &flags in &flags {
+                for stack in &stacks {
+                    let mut by_table = stack.clone();
+                    let mut by_match = stack.clone();
+                    let table_result = OPCODE_TABLE[opcode as usize](opcode, &mut by_table, flags);
+                    let match_result = execute_opcode_match(opcode, &mut by_match, flags);
+                    assert_eq!(
+                        (table_result, by_table),
+                        (match_result, by_match),
+                        "opcode {opcode:#04x}, flags {flags:#x}"
+                    );
+                }
+            }
+        }
     }
 
     #[test]
-    fn test_op_2over() {
-        let script = vec![0x51, 0x52, 0x53, 0x54, 0x70]; // OP_1, OP_2, OP_3, OP_4, OP_2OVER
-        let mut stack = Vec::new();
-        let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(!result); // Final stack has 6 items, not exactly 1
-        assert_eq!(stack.len(), 6);
-        assert_eq!(stack[4], vec![1]); // Should copy second pair
-        assert_eq!(stack[5], vec![2]);
+    fn test_numeric_operands_minimaldata() {
+        // OP_1ADD on each operand, with and without MINIMALDATA
+        let one_add = |a: &[u8], flags: u32| {
+            let mut stack = vec![a.to_vec()];
+            execute_opcode(0x8b, &mut stack, flags)
+                .unwrap()
+                .then(|| stack.pop().unwrap())
+        };
+
+        // Non-minimal zero and one decode without the flag
+        assert_eq!(one_add(&[0x00], 0), Some(vec![0x01]));
+        assert_eq!(one_add(&[0x01, 0x00], 0), Some(vec![0x02]));
+        assert_eq!(one_add(&[0x00], SCRIPT_VERIFY_MINIMALDATA), None);
+        assert_eq!(one_add(&[0x01, 0x00], SCRIPT_VERIFY_MINIMALDATA), None);
+
+        // Their minimal forms, and a needed sign byte, pass with it
+        assert_eq!(one_add(&[], SCRIPT_VERIFY_MINIMALDATA), Some(vec![0x01]));
+        assert_eq!(
+            one_add(&[0x01], SCRIPT_VERIFY_MINIMALDATA),
+            Some(vec![0x02])
+        );
+        assert_eq!(
+            one_add(&[0xff, 0x00], SCRIPT_VERIFY_MINIMALDATA),
+            Some(vec![0x00, 0x01])
+        );
+
+        // Binary opcodes and OP_PICK operands are checked too
+        let mut stack = vec![vec![0x01], vec![0x01, 0x00]];
+        assert!(execute_opcode(0x93, &mut stack.clone(), 0).unwrap());
+        assert!(!execute_opcode(0x93, &mut stack, SCRIPT_VERIFY_MINIMALDATA).unwrap());
+        let mut stack = vec![vec![0xaa], vec![0x00]];
+        assert!(execute_opcode(0x79, &mut stack.clone(), 0).unwrap());
+        assert!(!execute_opcode(0x79, &mut stack, SCRIPT_VERIFY_MINIMALDATA).unwrap());
     }
 
     #[test]
-    fn test_op_2over_insufficient_stack() {
-        let script = vec![0x51, 0x52, 0x53, 0x70]; // OP_1, OP_2, OP_3, OP_2OVER (only 3 items)
-        let mut stack = Vec::new();
-        let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(!result);
-        assert_eq!(stack.len(), 3);
+    fn test_conditional_flow() {
+        let run = |script: &[u8], flags: u32| {
+            let mut stack = Vec::new();
+            eval_script(&script.to_vec(), &mut stack, flags).unwrap()
+        };
+
+        // OP_1 OP_IF OP_1 OP_ELSE OP_RETURN OP_ENDIF
+        assert!(run(&[0x51, 0x63, 0x51, 0x67, 0x6a, 0x68], 0));
+        // OP_0 OP_IF OP_RETURN OP_ELSE OP_1 OP_ENDIF
+        assert!(run(&[0x00, 0x63, 0x6a, 0x67, 0x51, 0x68], 0));
+        // OP_0 OP_NOTIF OP_1 OP_ENDIF
+        assert!(run(&[0x00, 0x64, 0x51, 0x68], 0));
+        // OP_1 OP_NOTIF OP_RETURN OP_ENDIF leaves nothing on the stack
+        assert!(!run(&[0x51, 0x64, 0x6a, 0x68], 0));
+
+        // Nested: OP_1 OP_IF OP_0 OP_IF OP_RETURN OP_ELSE OP_1 OP_ENDIF OP_ENDIF
+        assert!(run(
+            &[0x51, 0x63, 0x00, 0x63, 0x6a, 0x67, 0x51, 0x68, 0x68],
+            0
+        ));
+        // An OP_IF inside a branch not taken pops nothing and is never taken,
+        // even after its OP_ELSE:
+        // OP_0 OP_IF OP_IF OP_RETURN OP_ELSE OP_RETURN OP_ENDIF OP_ELSE OP_1 OP_ENDIF
+        assert!(run(
+            &[0x00, 0x63, 0x63, 0x6a, 0x67, 0x6a, 0x68, 0x67, 0x51, 0x68],
+            0
+        ));
+
+        // Dangling OP_ELSE / OP_ENDIF, OP_IF on an empty stack
+        assert!(!run(&[0x51, 0x67], 0));
+        assert!(!run(&[0x51, 0x68], 0));
+        assert!(!run(&[0x63, 0x51, 0x68], 0));
+        // Unbalanced at end of script, even with a true stack top
+        assert!(!run(&[0x51, 0x51, 0x63, 0x51], 0));
+        assert!(!run(&[0x51, 0x00, 0x63, 0x6a], 0));
+
+        // Negative zero is false
+        let mut stack = vec![vec![0x00, 0x80]];
+        assert!(eval_script(&vec![0x63, 0x6a, 0x67, 0x51, 0x68], &mut stack, 0).unwrap());
+
+        // Disabled opcodes and OP_VERIF/OP_VERNOTIF fail even when not executed
+        // OP_0 OP_IF OP_CAT OP_ENDIF OP_1
+        assert!(!run(&[0x00, 0x63, 0x7e, 0x68, 0x51], 0));
+        for opcode in [0x7f, 0x83, 0x8d, 0x95, 0x99, 0x65, 0x66] {
+            assert!(!run(&[0x00, 0x63, opcode, 0x68, 0x51], 0));
+        }
+        // Other unknown opcodes are only rejected when executed
+        assert!(run(&[0x00, 0x63, 0xba, 0x68, 0x51], 0));
+        assert!(run(&[0x00, 0x63, 0x50, 0x68, 0x51], 0));
     }
 
     #[test]
-    fn test_op_2rot() {
-        let script = vec![0x51, 0x52, 0x53, 0x54, 0x55, 0x56, 0x71]; // 6 items, OP_2ROT
-        let mut stack = Vec::new();
-        let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(!result); // Final stack has 6 items, not exactly 1
-        assert_eq!(stack.len(), 6);
-        assert_eq!(stack[4], vec![2]); // Should rotate second pair to top
-        assert_eq!(stack[5], vec![1]);
+    fn test_minimal_if_applies_to_witness_v0_only() {
+        let tx = Transaction {
+            version: 1,
+            inputs: crate::tx_inputs![TransactionInput {
+                prevout: OutPoint {
+                    hash: [1; 32],
+                    index: 0,
+                },
+                script_sig: vec![],
+                sequence: 0xffffffff,
+            }],
+            outputs: crate::tx_outputs![TransactionOutput {
+                value: 1000,
+                script_pubkey: vec![0x51],
+            }],
+            lock_time: 0,
+        };
+        let prevouts = vec![TransactionOutput {
+            value: 2000,
+            script_pubkey: vec![0x51],
+        }];
+        let cache = SighashCache::new(&tx);
+        let run = |script: &[u8], flags: u32, sig_version: SigVersion| {
+            let mut stack = Vec::new();
+            eval_script_with_context_full(
+                &script.to_vec(),
+                &mut stack,
+                flags,
+                &tx,
+                0,
+                &prevouts,
+                sig_version,
+                None,
+                None,
+                crate::types::Network::Regtest,
+                default_verifier(),
+            )
+            .unwrap()
+        };
+        let v0 = SigVersion::WitnessV0(&cache);
+
+        // Only empty or [0x01] may be used as a witness v0 condition
+        let op_2_if = [0x52, 0x63, 0x51, 0x68];
+        assert!(run(&op_2_if, 0, v0));
+        assert!(!run(&op_2_if, SCRIPT_VERIFY_MINIMALIF, v0));
+        assert!(run(&[0x51, 0x63, 0x51, 0x68], SCRIPT_VERIFY_MINIMALIF, v0));
+        assert!(run(&[0x00, 0x64, 0x51, 0x68], SCRIPT_VERIFY_MINIMALIF, v0));
+        // Not checked inside a branch not taken, where nothing is popped
+        assert!(run(
+            &[0x00, 0x63, 0x52, 0x63, 0x68, 0x67, 0x51, 0x68],
+            SCRIPT_VERIFY_MINIMALIF,
+            v0
+        ));
+        // OP_EQUAL's false result is empty, a minimal condition:
+        // OP_1 OP_2 OP_EQUAL OP_NOTIF OP_1 OP_ENDIF
+        assert!(run(
+            &[0x51, 0x52, 0x87, 0x64, 0x51, 0x68],
+            SCRIPT_VERIFY_MINIMALIF,
+            v0
+        ));
+        // As in BOLT 3 HTLC scripts: <item> OP_SIZE 32 OP_EQUAL OP_NOTIF
+        // OP_1 OP_ELSE OP_1 OP_ENDIF, with a 1-byte item
+        assert!(run(
+            &[0x01, 0xaa, 0x82, 0x01, 0x20, 0x87, 0x64, 0x51, 0x67, 0x51, 0x68],
+            SCRIPT_VERIFY_MINIMALIF,
+            v0
+        ));
+
+        // Bare and P2SH scripts ignore the flag
+        assert!(run(&op_2_if, SCRIPT_VERIFY_MINIMALIF, SigVersion::Base));
+        let mut stack = Vec::new();
+        assert!(eval_script(&op_2_if.to_vec(), &mut stack, SCRIPT_VERIFY_MINIMALIF).unwrap());
     }
 
     #[test]
-    fn test_op_2rot_insufficient_stack() {
-        let script = vec![0x51, 0x52, 0x53, 0x54, 0x71]; // OP_1, OP_2, OP_3, OP_4, OP_2ROT (only 4 items)
+    fn test_op_checksigverify_insufficient_stack() {
+        let script = vec![0x51, 0xad]; // OP_1, OP_CHECKSIGVERIFY (only 1 item)
         let mut stack = Vec::new();
         let result = eval_script(&script, &mut stack, 0).unwrap();
         assert!(!result);
-        assert_eq!(stack.len(), 4);
+        assert_eq!(stack.len(), 1);
     }
 
     #[test]
-    fn test_op_2swap() {
-        let script = vec![0x51, 0x52, 0x53, 0x54, 0x72]; // OP_1, OP_2, OP_3, OP_4, OP_2SWAP
+    fn test_altstack_round_trip() {
+        // OP_2 OP_TOALTSTACK OP_1 OP_DROP OP_FROMALTSTACK
+        let script = vec![0x52, 0x6b, 0x51, 0x75, 0x6c];
         let mut stack = Vec::new();
-        let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(!result); // Final stack has 4 items, not exactly 1
-        assert_eq!(stack.len(), 4);
-        assert_eq!(stack[0], vec![3]); // Should swap second pair
-        assert_eq!(stack[1], vec![4]);
-        assert_eq!(stack[2], vec![1]);
-        assert_eq!(stack[3], vec![2]);
+        assert!(eval_script(&script, &mut stack, 0).unwrap());
+        assert_eq!(stack, vec![vec![2]]);
+
+        // Underflow on either side is an error
+        assert!(eval_script(&vec![0x6b], &mut Vec::new(), 0).is_err());
+        assert!(eval_script(&vec![0x51, 0x6c], &mut Vec::new(), 0).is_err());
     }
 
     #[test]
-    fn test_op_2swap_insufficient_stack() {
-        let script = vec![0x51, 0x52, 0x53, 0x72]; // OP_1, OP_2, OP_3, OP_2SWAP (only 3 items)
-        let mut stack = Vec::new();
-        let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(!result);
-        assert_eq!(stack.len(), 3);
+    fn test_altstack_counts_toward_stack_limit() {
+        // OP_TOALTSTACK OP_1: the push brings stack + altstack to 1001
+        let script = vec![0x6b, 0x51];
+        let mut stack = vec![vec![1]; MAX_STACK_SIZE];
+        assert!(eval_script(&script, &mut stack, 0).is_err());
     }
 
     #[test]
-    fn test_op_size() {
-        let script = vec![0x51, 0x82]; // OP_1, OP_SIZE
-        let mut stack = Vec::new();
-        let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(!result); // Final stack has 2 items [1, 1], not exactly 1
-        assert_eq!(stack.len(), 2);
-        assert_eq!(stack[0], vec![1]);
-        assert_eq!(stack[1], vec![1]); // Size of [1] is 1
+    fn test_check_stack_size_combined_limit() {
+        let items = |n| vec![vec![1u8]; n];
+
+        // 600 on each stack is over the limit even though each alone is under it
+        assert!(check_stack_size(&items(600), &items(600)).is_err());
+
+        // The limit is inclusive of exactly 1000 combined items
+        assert!(check_stack_size(&items(600), &items(400)).is_ok());
+        assert!(check_stack_size(&items(600), &items(401)).is_err());
+        assert!(check_stack_size(&items(MAX_STACK_SIZE), &[]).is_ok());
     }
 
     #[test]
-    fn test_op_size_empty_stack() {
-        let script = vec![0x82]; // OP_SIZE on empty stack
-        let mut stack = Vec::new();
-        let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(!result);
-        assert_eq!(stack.len(), 0);
+    fn test_checksig_undefined_sighash_type_under_strictenc() {
+        let tx = Transaction {
+            version: 1,
+            inputs: crate::tx_inputs![TransactionInput {
+                prevout: OutPoint {
+                    hash: [1; 32],
+                    index: 0,
+                },
+                script_sig: vec![],
+                sequence: 0xffffffff,
+            }],
+            outputs: crate::tx_outputs![TransactionOutput {
+                value: 1000,
+                script_pubkey: vec![0x51],
+            }],
+            lock_time: 0,
+        };
+        let prevouts = vec![TransactionOutput {
+            value: 2000,
+            script_pubkey: vec![0x51],
+        }];
+        let checksig = |opcode, hash_type, flags| {
+            // Strict DER for r = s = 1, so only the hash type is at issue
+            let signature = vec![0x30, 0x06, 0x02, 0x01, 0x01, 0x02, 0x01, 0x01, hash_type];
+            let mut stack = vec![signature, vec![0x02; 33]];
+            let result = execute_opcode_with_context_full(
+                opcode,
+                &mut stack,
+                flags,
+                &tx,
+                0,
+                &prevouts,
+                &[],
+                SigVersion::Base,
+                None,
+                None,
+                crate::types::Network::Regtest,
+                default_verifier(),
+            )
+            .unwrap();
+            (result, stack)
+        };
+
+        // 0x04 is not a defined sighash type: the script fails under STRICTENC
+        assert_eq!(checksig(0xac, 0x04, 0x02), (false, vec![]));
+        assert_eq!(checksig(0xad, 0x04, 0x02), (false, vec![]));
+        assert_eq!(checksig(0xac, 0x84, 0x02), (false, vec![]));
+
+        // A defined type only fails the signature check itself
+        assert_eq!(checksig(0xac, 0x81, 0x02), (true, vec![vec![]]));
+
+        // Without STRICTENC the hash type is not checked
+        assert_eq!(checksig(0xac, 0x04, 0), (true, vec![vec![]]));
+
+        // Context-free OP_CHECKSIG enforces the same rule
+        let mut stack = vec![vec![0x30, 0x06, 0x04], vec![0x02; 33]];
+        assert!(!execute_opcode(0xac, &mut stack, 0x02).unwrap());
     }
 
     #[test]
-    fn test_op_return() {
-        let script = vec![0x51, 0x6a]; // OP_1, OP_RETURN
-        let mut stack = Vec::new();
-        let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(!result); // OP_RETURN always fails
-        assert_eq!(stack.len(), 1);
+    fn test_checksig_empty_signature() {
+        let tx = Transaction {
+            version: 1,
+            inputs: crate::tx_inputs![],
+            outputs: crate::tx_outputs![],
+            lock_time: 0,
+        };
+        // No input 0 and no prevouts: any sighash computation would error
+        let checksig = |opcode, signature: Vec<u8>, flags| {
+            let mut stack = vec![signature, vec![0x02; 33]];
+            let result = execute_opcode_with_context_full(
+                opcode,
+                &mut stack,
+                flags,
+                &tx,
+                0,
+                &[],
+                &[],
+                SigVersion::Base,
+                None,
+                None,
+                crate::types::Network::Regtest,
+                default_verifier(),
+            );
+            (result, stack)
+        };
+
+        // CHECKSIG pushes false, with or without NULLFAIL
+        for flags in [0, SCRIPT_VERIFY_NULLFAIL | SCRIPT_VERIFY_STRICTENC] {
+            let (result, stack) = checksig(0xac, vec![], flags);
+            assert!(result.unwrap());
+            assert_eq!(stack, vec![Vec::<u8>::new()]);
+        }
+
+        // CHECKSIGVERIFY fails
+        let (result, stack) = checksig(0xad, vec![], 0);
+        assert!(!result.unwrap());
+        assert!(stack.is_empty());
+
+        // A non-empty signature still goes through the sighash path
+        assert!(checksig(0xac, vec![0x30, 0x06, 0x01], 0).0.is_err());
     }
 
     #[test]
-    fn test_op_checksigverify() {
-        let script = vec![0x51, 0x52, 0xad]; // OP_1, OP_2, OP_CHECKSIGVERIFY
-        let mut stack = Vec::new();
-        let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(!result); // Should fail due to invalid signature
-        assert_eq!(stack.len(), 0);
+    fn test_checksig_nullfail() {
+        let tx = Transaction {
+            version: 1,
+            inputs: crate::tx_inputs![TransactionInput {
+                prevout: OutPoint {
+                    hash: [1; 32],
+                    index: 0,
+                },
+                script_sig: vec![],
+                sequence: 0xffffffff,
+            }],
+            outputs: crate::tx_outputs![TransactionOutput {
+                value: 1000,
+                script_pubkey: vec![0x51],
+            }],
+            lock_time: 0,
+        };
+        let prevouts = vec![TransactionOutput {
+            value: 2000,
+            script_pubkey: vec![0x51],
+        }];
+        let checksig = |flags| {
+            let mut stack = vec![vec![0x30, 0x06, 0x01], vec![0x02; 33]];
+            let result = execute_opcode_with_context_full(
+                0xac,
+                &mut stack,
+                flags,
+                &tx,
+                0,
+                &prevouts,
+                &[],
+                SigVersion::Base,
+                None,
+                None,
+                crate::types::Network::Regtest,
+                default_verifier(),
+            )
+            .unwrap();
+            (result, stack)
+        };
+
+        // A failing non-empty signature pushes false, or fails under NULLFAIL
+        assert_eq!(checksig(0), (true, vec![vec![]]));
+        assert_eq!(checksig(SCRIPT_VERIFY_NULLFAIL), (false, vec![]));
     }
 
     #[test]
-    fn test_op_checksigverify_insufficient_stack() {
-        let script = vec![0x51, 0xad]; // OP_1, OP_CHECKSIGVERIFY (only 1 item)
+    fn test_op_nop() {
+        let script = vec![0x51, 0x61]; // OP_1, OP_NOP
         let mut stack = Vec::new();
-        let result = eval_script(&script, &mut stack, 0).unwrap();
-        assert!(!result);
-        assert_eq!(stack.len(), 1);
+        assert!(eval_script(&script, &mut stack, 0).unwrap());
+        assert_eq!(stack, vec![vec![1]]);
     }
 
     #[test]
@@ -2536,12 +5268,11 @@ mod tests {
 
     #[test]
     fn test_verify_signature_invalid_pubkey() {
-        let secp = Secp256k1::new();
         let invalid_pubkey = vec![0x00]; // Invalid pubkey
         let signature = vec![0x30, 0x06, 0x02, 0x01, 0x00, 0x02, 0x01, 0x00]; // Valid DER signature
         let dummy_hash = [0u8; 32];
         let result = verify_signature(
-            &secp,
+            default_verifier(),
             &invalid_pubkey,
             &signature,
             &dummy_hash,
@@ -2554,7 +5285,6 @@ mod tests {
 
     #[test]
     fn test_verify_signature_invalid_signature() {
-        let secp = Secp256k1::new();
         let pubkey = vec![
             0x02, 0x79, 0xbe, 0x66, 0x7e, 0xf9, 0xdc, 0xbb, 0xac, 0x55, 0xa0, 0x62, 0x95, 0xce,
             0x87, 0x0b, 0x07, 0x02, 0x9b, 0xfc, 0xdb, 0x2d, 0xce, 0x28, 0xd9, 0x59, 0xf2, 0x81,
@@ -2563,7 +5293,7 @@ mod tests {
         let invalid_signature = vec![0x00]; // Invalid signature
         let dummy_hash = [0u8; 32];
         let result = verify_signature(
-            &secp,
+            default_verifier(),
             &pubkey,
             &invalid_signature,
             &dummy_hash,
@@ -2573,6 +5303,736 @@ mod tests {
         );
         assert!(!result.unwrap_or(false));
     }
+
+    #[test]
+    fn test_verify_signature_dersig() {
+        let secp = Secp256k1::new();
+        let secret_key = secp256k1::SecretKey::from_slice(&[0x22; 32]).unwrap();
+        let pubkey = secp256k1::PublicKey::from_secret_key(&secp, &secret_key).serialize();
+        let sighash = [0x33; 32];
+        let message = secp256k1::Message::from_digest_slice(&sighash).unwrap();
+        let der = secp
+            .sign_ecdsa(&message, &secret_key)
+            .serialize_der()
+            .to_vec();
+
+        let mut canonical = der.clone();
+        canonical.push(0x01); // SIGHASH_ALL
+        assert!(is_valid_signature_encoding(&canonical));
+
+        // Same signature with a superfluous zero byte in front of R
+        let mut padded = vec![0x30, der[1] + 1, 0x02, der[3] + 1, 0x00];
+        padded.extend_from_slice(&der[4..]);
+        padded.push(0x01);
+        assert!(!is_valid_signature_encoding(&padded));
+
+        let verify = |signature: &[u8], flags, height| {
+            verify_signature(
+                default_verifier(),
+                &pubkey,
+                signature,
+                &sighash,
+                flags,
+                height,
+                crate::types::Network::Mainnet,
+            )
+        };
+        let dersig = SCRIPT_VERIFY_DERSIG;
+        let bip66 = crate::bip_validation::bip66_activation_height(crate::types::Network::Mainnet);
+
+        // Lax DER verifies without the flag, and before BIP66 activation
+        assert!(verify(&padded, 0, bip66).unwrap());
+        assert!(verify(&padded, dersig, bip66 - 1).unwrap());
+        // Under DERSIG it fails the check, under STRICTENC the script
+        assert!(!verify(&padded, dersig, bip66).unwrap());
+        assert!(verify(&padded, SCRIPT_VERIFY_STRICTENC, bip66).is_err());
+        assert!(verify(&canonical, dersig | SCRIPT_VERIFY_STRICTENC, bip66).unwrap());
+
+        // Structural violations: negative R, zero-length S, wrong total length
+        for bad in [
+            vec![0x30, 0x06, 0x02, 0x01, 0x81, 0x02, 0x01, 0x01, 0x01],
+            vec![0x30, 0x06, 0x02, 0x02, 0x01, 0x01, 0x02, 0x00, 0x01],
+            vec![0x30, 0x07, 0x02, 0x01, 0x01, 0x02, 0x01, 0x01, 0x01],
+        ] {
+            assert!(!is_valid_signature_encoding(&bad), "{bad:02x?}");
+            assert!(!check_signature_encoding(&bad, dersig).unwrap());
+            assert!(check_signature_encoding(&bad, 0).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_verify_signature_low_s() {
+        // secp256k1 curve order
+        const N: [u8; 32] = [
+            0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+            0xff, 0xfe, 0xba, 0xae, 0xdc, 0xe6, 0xaf, 0x48, 0xa0, 0x3b, 0xbf, 0xd2, 0x5e, 0x8c,
+            0xd0, 0x36, 0x41, 0x41,
+        ];
+        // The same signature with S replaced by N - S
+        fn high_s(signature: &Signature) -> Signature {
+            let mut compact = signature.serialize_compact();
+            let mut borrow = 0i16;
+            for i in (0..32).rev() {
+                let diff = N[i] as i16 - compact[32 + i] as i16 - borrow;
+                borrow = (diff < 0) as i16;
+                compact[32 + i] = diff.rem_euclid(256) as u8;
+            }
+            Signature::from_compact(&compact).unwrap()
+        }
+
+        let secp = Secp256k1::new();
+        for key in 1..=8u8 {
+            let secret_key = secp256k1::SecretKey::from_slice(&[key; 32]).unwrap();
+            let pubkey = secp256k1::PublicKey::from_secret_key(&secp, &secret_key).serialize();
+            let message = secp256k1::Message::from_digest_slice(&[0u8; 32]).unwrap();
+            let low = secp.sign_ecdsa(&message, &secret_key);
+            let high = high_s(&low);
+            assert_ne!(high, low);
+            let mut normalized = high;
+            normalized.normalize_s();
+            assert_eq!(normalized, low);
+
+            // OP_CHECKSIG without transaction context checks against a zero hash
+            let checksig = |signature: &Signature, flags| {
+                let mut der = signature.serialize_der().to_vec();
+                der.push(0x01); // SIGHASH_ALL
+                let mut stack = vec![der, pubkey.to_vec()];
+                assert!(execute_opcode(0xac, &mut stack, flags).unwrap());
+                stack == vec![vec![1]]
+            };
+
+            // Low-S signatures always pass; high-S only without the flag
+            assert!(checksig(&low, 0));
+            assert!(checksig(&low, SCRIPT_VERIFY_LOW_S));
+            assert!(checksig(&high, 0));
+            assert!(!checksig(&high, SCRIPT_VERIFY_LOW_S));
+        }
+    }
+
+    #[test]
+    fn test_validate_transaction_verbose_pinpoints_failing_input() {
+        let secp = Secp256k1::new();
+        let secret_key = secp256k1::SecretKey::from_slice(&[0x11; 32]).unwrap();
+        let pubkey = secp256k1::PublicKey::from_secret_key(&secp, &secret_key).serialize();
+
+        // A well-formed signature over the wrong message, with SIGHASH_ALL
+        let message = secp256k1::Message::from_digest_slice(&[0x01; 32]).unwrap();
+        let mut bad_signature = secp
+            .sign_ecdsa(&message, &secret_key)
+            .serialize_der()
+            .to_vec();
+        bad_signature.push(0x01);
+
+        let mut p2pk = vec![pubkey.len() as u8];
+        p2pk.extend_from_slice(&pubkey);
+        p2pk.push(0xac); // OP_CHECKSIG
+        let mut bad_script_sig = vec![bad_signature.len() as u8];
+        bad_script_sig.extend_from_slice(&bad_signature);
+
+        let input = |tag: u8, script_sig: ByteString| TransactionInput {
+            prevout: OutPoint {
+                hash: [tag; 32],
+                index: 0,
+            },
+            script_sig,
+            sequence: 0xffffffff,
+        };
+        let tx = Transaction {
+            version: 1,
+            inputs: crate::tx_inputs![
+                input(1, vec![0x51]), // OP_1
+                input(2, bad_script_sig),
+                input(3, vec![0x51])
+            ],
+            outputs: crate::tx_outputs![TransactionOutput {
+                value: 1000,
+                script_pubkey: vec![0x51],
+            }],
+            lock_time: 0,
+        };
+        let op_true = PrevOut {
+            value: 1000,
+            script_pubkey: vec![0x51, 0x87], // OP_1 OP_EQUAL
+        };
+        let prevouts = vec![
+            op_true.clone(),
+            PrevOut {
+                value: 1000,
+                script_pubkey: p2pk,
+            },
+            op_true.clone(),
+        ];
+
+        let (index, error) =
+            validate_transaction_verbose(&tx, &prevouts, &[], 0, crate::types::Network::Regtest)
+                .unwrap_err();
+        assert_eq!(index, 1);
+        assert!(matches!(error, ConsensusError::ScriptExecution(_)));
+
+        // A short prevouts list fails at the first uncovered input
+        let (index, error) = validate_transaction_verbose(
+            &tx,
+            &prevouts[..2],
+            &[],
+            0,
+            crate::types::Network::Regtest,
+        )
+        .unwrap_err();
+        assert_eq!(index, 2);
+        assert!(matches!(error, ConsensusError::InvalidPrevoutsCount(3, 2)));
+
+        // With a passing script at input 1, the transaction verifies
+        let mut fixed = tx.clone();
+        fixed.inputs[1].script_sig = vec![0x51];
+        let prevouts = vec![op_true.clone(), op_true.clone(), op_true];
+        assert!(validate_transaction_verbose(
+            &fixed,
+            &prevouts,
+            &[],
+            0,
+            crate::types::Network::Regtest
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_verify_script_with_witness_bip143_native_p2wpkh() {
+        use crate::serialization::transaction::deserialize_transaction;
+
+        // BIP143 "Native P2WPKH" example: input 1 spends 6 BTC from a P2WPKH output
+        let tx = deserialize_transaction(
+            &hex::decode(
+                "0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f00000000\
+                 00eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a01000000\
+                 00ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac90\
+                 93510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000",
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let prevouts = vec![
+            PrevOut {
+                value: 625_000_000,
+                script_pubkey: hex::decode(
+                    "2103c9f4836b9a4f77fc0d81f7bcb01b7f1b35916864b9476c241ce9fc198bd25432ac",
+                )
+                .unwrap(),
+            },
+            PrevOut {
+                value: 600_000_000,
+                script_pubkey: hex::decode("00141d0f172a0ecb48aee1be1f2687d2963ae33f71a1").unwrap(),
+            },
+        ];
+        let witness = vec![
+            hex::decode(
+                "304402203609e17b84f6a7d30c80bfa610b5b4542f32a8a0d5447a12fb1366d7f01cc44a0220573a\
+                 954c4518331561406f90300e8f3358f51928d43c212a8caed02de67eebee01",
+            )
+            .unwrap(),
+            hex::decode("025476c2e83188368da1ff3e292e7acafcdb3566bb0ad253f62fc70f07aeee6357")
+                .unwrap(),
+        ];
+        let sighash_cache = SighashCache::new(&tx);
+        let verify = |script_sig: &ByteString, witness: &[ByteString], prevouts: &[PrevOut]| {
+            verify_script_with_witness(
+                script_sig,
+                &prevouts[1].script_pubkey,
+                witness,
+                SCRIPT_VERIFY_P2SH | SCRIPT_VERIFY_WITNESS,
+                &tx,
+                1,
+                prevouts,
+                &sighash_cache,
+                None,
+                None,
+                crate::types::Network::Mainnet,
+                default_verifier(),
+            )
+            .unwrap()
+        };
+
+        assert!(verify(&vec![], &witness, &prevouts));
+        // The signature commits to the spent amount
+        let mut wrong_amount = prevouts.clone();
+        wrong_amount[1].value -= 1;
+        assert!(!verify(&vec![], &witness, &wrong_amount));
+        // A native program takes an empty scriptSig and a signature and key
+        assert!(!verify(&vec![0x00], &witness, &prevouts));
+        assert!(!verify(&vec![], &witness[..1], &prevouts));
+        assert!(!verify(&vec![], &[], &prevouts));
+    }
+
+    #[test]
+    fn test_verify_script_with_witness_p2wsh_and_unexpected_witness() {
+        let tx = Transaction {
+            version: 2,
+            inputs: crate::tx_inputs![TransactionInput {
+                prevout: OutPoint {
+                    hash: [1; 32],
+                    index: 0,
+                },
+                script_sig: vec![],
+                sequence: 0xffffffff,
+            }],
+            outputs: crate::tx_outputs![TransactionOutput {
+                value: 1000,
+                script_pubkey: vec![0x51],
+            }],
+            lock_time: 0,
+        };
+        let sighash_cache = SighashCache::new(&tx);
+        let verify =
+            |script_sig: &ByteString, script_pubkey: &ByteString, witness: &[ByteString]| {
+                let prevouts = vec![PrevOut {
+                    value: 2000,
+                    script_pubkey: script_pubkey.clone(),
+                }];
+                verify_script_with_witness(
+                    script_sig,
+                    script_pubkey,
+                    witness,
+                    SCRIPT_VERIFY_P2SH | SCRIPT_VERIFY_WITNESS,
+                    &tx,
+                    0,
+                    &prevouts,
+                    &sighash_cache,
+                    None,
+                    None,
+                    crate::types::Network::Regtest,
+                    default_verifier(),
+                )
+                .unwrap()
+            };
+
+        // P2WSH of `OP_2 OP_EQUAL`, natively and nested in P2SH
+        let witness_script = vec![0x52, 0x87];
+        let mut p2wsh = vec![0x00, 0x20];
+        p2wsh.extend_from_slice(&crate::crypto::sha256(&witness_script));
+        let mut p2sh_p2wsh = vec![0xa9, 0x14];
+        p2sh_p2wsh.extend_from_slice(&Ripemd160::digest(Sha256::digest(&p2wsh)));
+        p2sh_p2wsh.push(0x87);
+        let nested_script_sig = push_script(&p2wsh);
+
+        let spend = vec![vec![0x02], witness_script.clone()];
+        assert!(verify(&vec![], &p2wsh, &spend));
+        assert!(verify(&nested_script_sig, &p2sh_p2wsh, &spend));
+        assert!(!verify(
+            &vec![],
+            &p2wsh,
+            &[vec![0x03], witness_script.clone()]
+        ));
+        // The witness script must hash to the program
+        assert!(!verify(
+            &vec![],
+            &p2wsh,
+            &[vec![0x02], vec![0x52, 0x87, 0x61]]
+        ));
+        // It must leave exactly one element
+        assert!(!verify(
+            &vec![],
+            &p2wsh,
+            &[vec![0x02], vec![0x02], witness_script]
+        ));
+        // The nested program's scriptSig pushes only the redeem script
+        let mut padded_script_sig = vec![0x00];
+        padded_script_sig.extend_from_slice(&nested_script_sig);
+        assert!(!verify(&padded_script_sig, &p2sh_p2wsh, &spend));
+
+        // Undefined versions are left to future soft forks
+        let mut v2 = vec![0x52, 0x20];
+        v2.extend_from_slice(&[7; 32]);
+        assert!(verify(&vec![], &v2, &[vec![0xff]]));
+
+        // A witness for a non-witness output fails the spend
+        assert!(verify(&vec![], &vec![0x51], &[]));
+        assert!(!verify(&vec![], &vec![0x51], &[vec![0x01]]));
+    }
+
+    #[test]
+    fn test_verify_legacy_p2pkh_spend_signs_script_code() {
+        use crate::serialization::transaction::deserialize_transaction;
+
+        let verify = |tx_hex: &str, script_pubkey: &str, tamper: bool| {
+            let mut tx = deserialize_transaction(&hex::decode(tx_hex).unwrap()).unwrap();
+            if tamper {
+                tx.outputs[0].value += 1;
+            }
+            let prevouts = vec![PrevOut {
+                value: 0,
+                script_pubkey: hex::decode(script_pubkey).unwrap(),
+            }];
+            verify_script_with_context(
+                &tx.inputs[0].script_sig,
+                &prevouts[0].script_pubkey,
+                None,
+                0,
+                &tx,
+                0,
+                &prevouts,
+                crate::types::Network::Mainnet,
+            )
+            .unwrap()
+        };
+
+        // Mainnet transaction ddc454a1..., from Core's tx_valid.json: a
+        // SIGHASH_ALL P2PKH spend of an output that first pushes and drops 174
+        // bytes, all of which are part of the signed scriptCode
+        let p2pkh_tx = "0100000001482f7a028730a233ac9b48411a8edfb107b749e61faf7531f4257ad95d0a51c5000000008b483045022100bf0bbae9bde51ad2b222e87fbf67530fbafc25c903519a1e5dcc52a32ff5844e022028c4d9ad49b006dd59974372a54291d5764be541574bb0c4dc208ec51f80b7190141049dd4aad62741dc27d5f267f7b70682eee22e7e9c1923b9c0957bdae0b96374569b460eb8d5b40d972e8c7c0ad441de3d94c4a29864b212d56050acb980b72b2bffffffff0180969800000000001976a914e336d0017a9d28de99d16472f6ca6d5a3a8ebc9988ac00000000";
+        let p2pkh_script_pubkey = concat!(
+            "4cae",
+            "606563686f2022553246736447566b58312b5a536e587574356542793066794778625456415675534a6c376a6a334878416945325364667657734f53474f36633338584d7439435c6e543249584967306a486956304f376e775236644546673d3d22203e20743b206f70656e73736c20656e63202d7061737320706173733a5b314a564d7751432d707269766b65792d6865785d202d64202d6165732d3235362d636263202d61202d696e207460",
+            "75",
+            "76a914bfd7436b6265aa9de506f8a994f881ff08cc287288ac"
+        );
+        let mut txid = crate::block::calculate_tx_id(
+            &deserialize_transaction(&hex::decode(p2pkh_tx).unwrap()).unwrap(),
+        );
+        txid.reverse();
+        assert_eq!(
+            hex::encode(txid),
+            "ddc454a1c0c35c188c98976b17670f69e586d9c0f3593ea879928332f0a069e7"
+        );
+        assert!(verify(p2pkh_tx, p2pkh_script_pubkey, false));
+        assert!(!verify(p2pkh_tx, p2pkh_script_pubkey, true));
+
+        // OP_CODESEPARATOR is left out of the scriptCode, and the scriptCode
+        // starts after the last one executed (tx_valid.json)
+        let pubkey = "038479a0fa998cd35259a2ef0a7a5c68662c1474f88ccb6d08a7677bbec7f22041";
+        let leading_separator = "01000000012432b60dc72cebc1a27ce0969c0989c895bdd9e62e8234839117f8fc32d17fbc000000004a493046022100a576b52051962c25e642c0fd3d77ee6c92487048e5d90818bcf5b51abaccd7900221008204f8fb121be4ec3b24483b1f92d89b1b0548513a134e345c5442e86e8617a501ffffffff010000000000000000016a00000000";
+        let separator_before_checksig = "01000000015ebaa001d8e4ec7a88703a3bcf69d98c874bca6299cca0f191512bf2a7826832000000004948304502203bf754d1c6732fbf87c5dcd81258aefd30f2060d7bd8ac4a5696f7927091dad1022100f5bcb726c4cf5ed0ed34cc13dadeedf628ae1045b7cb34421bc60b89f4cecae701ffffffff010000000000000000016a00000000";
+        assert!(verify(leading_separator, &format!("ab21{pubkey}ac"), false));
+        assert!(verify(
+            separator_before_checksig,
+            &format!("21{pubkey}abac"),
+            false
+        ));
+        assert!(!verify(
+            separator_before_checksig,
+            &format!("21{pubkey}ac"),
+            false
+        ));
+    }
+
+    #[test]
+    fn test_legacy_checksig_signs_under_signature_hash_type() {
+        use crate::transaction_hash::calculate_legacy_sighash;
+
+        let secp = Secp256k1::new();
+        let secret_key = secp256k1::SecretKey::from_slice(&[0x21; 32]).unwrap();
+        let pubkey = secp256k1::PublicKey::from_secret_key(&secp, &secret_key).serialize();
+        let mut script_pubkey = vec![0x76, 0xa9, 0x14];
+        script_pubkey.extend_from_slice(&Ripemd160::digest(Sha256::digest(pubkey)));
+        script_pubkey.extend_from_slice(&[0x88, 0xac]);
+        let prevouts = vec![
+            PrevOut {
+                value: 5000,
+                script_pubkey: script_pubkey.clone(),
+            },
+            PrevOut {
+                value: 5000,
+                script_pubkey: vec![0x51],
+            },
+        ];
+        let input = |tag: u8| TransactionInput {
+            prevout: OutPoint {
+                hash: [tag; 32],
+                index: 0,
+            },
+            script_sig: vec![],
+            sequence: 0xffffffff,
+        };
+        let output = |value| TransactionOutput {
+            value,
+            script_pubkey: vec![0x51],
+        };
+        let unsigned = Transaction {
+            version: 1,
+            inputs: crate::tx_inputs![input(1), input(2)],
+            outputs: crate::tx_outputs![output(4000), output(5000)],
+            lock_time: 0,
+        };
+        let sign = |tx: &Transaction, hash_type: u8| {
+            let sighash =
+                calculate_legacy_sighash(tx, 0, &prevouts, &script_pubkey, hash_type as u32)
+                    .unwrap();
+            let message = secp256k1::Message::from_digest_slice(&sighash).unwrap();
+            let mut signature = secp
+                .sign_ecdsa(&message, &secret_key)
+                .serialize_der()
+                .to_vec();
+            signature.push(hash_type);
+            let mut signed = tx.clone();
+            signed.inputs[0].script_sig = vec![signature.len() as u8];
+            signed.inputs[0].script_sig.extend_from_slice(&signature);
+            signed.inputs[0].script_sig.push(pubkey.len() as u8);
+            signed.inputs[0].script_sig.extend_from_slice(&pubkey);
+            signed
+        };
+        let verify = |tx: &Transaction| {
+            verify_script_with_context(
+                &tx.inputs[0].script_sig,
+                &script_pubkey,
+                None,
+                0,
+                tx,
+                0,
+                &prevouts,
+                crate::types::Network::Regtest,
+            )
+            .unwrap()
+        };
+
+        // Every hash type verifies, including undefined ones
+        for hash_type in [0x00, 0x01, 0x02, 0x03, 0x04, 0x81, 0x82, 0x83] {
+            assert!(verify(&sign(&unsigned, hash_type)), "{hash_type:#04x}");
+        }
+
+        // What each type commits to: ALL covers every output, NONE none of
+        // them, SINGLE only output 0, and ANYONECANPAY no other input
+        let change_output_1 = |mut tx: Transaction| {
+            tx.outputs[1].value -= 1;
+            tx
+        };
+        let change_input_1 = |mut tx: Transaction| {
+            tx.inputs[1].prevout.index = 1;
+            tx
+        };
+        assert!(!verify(&change_output_1(sign(&unsigned, 0x01))));
+        assert!(verify(&change_output_1(sign(&unsigned, 0x02))));
+        assert!(verify(&change_output_1(sign(&unsigned, 0x03))));
+        assert!(!verify(&change_input_1(sign(&unsigned, 0x01))));
+        assert!(verify(&change_input_1(sign(&unsigned, 0x81))));
+
+        // The hash type byte itself is signed
+        let mut relabelled = sign(&unsigned, 0x01);
+        let last = relabelled.inputs[0].script_sig[0] as usize;
+        relabelled.inputs[0].script_sig[last] = 0x81;
+        assert!(!verify(&relabelled));
+
+        // STRICTENC admits only the defined types, and a signature it admits
+        // is checked under that same type
+        let verify_strictenc = |tx: &Transaction| {
+            verify_script_with_context(
+                &tx.inputs[0].script_sig,
+                &script_pubkey,
+                None,
+                SCRIPT_VERIFY_STRICTENC,
+                tx,
+                0,
+                &prevouts,
+                crate::types::Network::Regtest,
+            )
+            .unwrap()
+        };
+        for hash_type in [0x01, 0x02, 0x03, 0x81, 0x82, 0x83] {
+            assert!(
+                verify_strictenc(&sign(&unsigned, hash_type)),
+                "{hash_type:#04x}"
+            );
+        }
+        for hash_type in [0x00, 0x04, 0x80, 0x84] {
+            assert!(
+                !verify_strictenc(&sign(&unsigned, hash_type)),
+                "{hash_type:#04x}"
+            );
+        }
+        assert!(verify_strictenc(&change_output_1(sign(&unsigned, 0x83))));
+        assert!(!verify_strictenc(&change_output_1(sign(&unsigned, 0x81))));
+    }
+
+    #[test]
+    fn test_checksig_sighash_single_bug() {
+        use crate::transaction_hash::SIGHASH_SINGLE_BUG_HASH;
+
+        // Input 1 of a two-input, one-output spend has no output to pair
+        // with: a SIGHASH_SINGLE signature signs the constant 1
+        let secp = Secp256k1::new();
+        let secret_key = secp256k1::SecretKey::from_slice(&[0x22; 32]).unwrap();
+        let pubkey = secp256k1::PublicKey::from_secret_key(&secp, &secret_key).serialize();
+        let mut script_pubkey = vec![0x76, 0xa9, 0x14];
+        script_pubkey.extend_from_slice(&Ripemd160::digest(Sha256::digest(pubkey)));
+        script_pubkey.extend_from_slice(&[0x88, 0xac]);
+        let prevouts = vec![
+            PrevOut {
+                value: 5000,
+                script_pubkey: vec![0x51],
+            },
+            PrevOut {
+                value: 5000,
+                script_pubkey: script_pubkey.clone(),
+            },
+        ];
+        let input = |tag: u8| TransactionInput {
+            prevout: OutPoint {
+                hash: [tag; 32],
+                index: 0,
+            },
+            script_sig: vec![],
+            sequence: 0xffffffff,
+        };
+        let mut tx = Transaction {
+            version: 1,
+            inputs: crate::tx_inputs![input(1), input(2)],
+            outputs: crate::tx_outputs![TransactionOutput {
+                value: 9000,
+                script_pubkey: vec![0x51],
+            }],
+            lock_time: 0,
+        };
+
+        let message = secp256k1::Message::from_digest_slice(&SIGHASH_SINGLE_BUG_HASH).unwrap();
+        let mut signature = secp
+            .sign_ecdsa(&message, &secret_key)
+            .serialize_der()
+            .to_vec();
+        signature.push(0x03);
+        let mut script_sig = vec![signature.len() as u8];
+        script_sig.extend_from_slice(&signature);
+        script_sig.push(pubkey.len() as u8);
+        script_sig.extend_from_slice(&pubkey);
+        tx.inputs[1].script_sig = script_sig;
+
+        let verify = |tx: &Transaction, flags: u32| {
+            verify_script_with_context(
+                &tx.inputs[1].script_sig,
+                &script_pubkey,
+                None,
+                flags,
+                tx,
+                1,
+                &prevouts,
+                crate::types::Network::Regtest,
+            )
+            .unwrap()
+        };
+        assert!(verify(&tx, 0));
+        assert!(verify(&tx, SCRIPT_VERIFY_STRICTENC));
+
+        // The signature commits to nothing in the transaction
+        tx.outputs[0].value = 1;
+        tx.inputs[0].prevout.index = 7;
+        assert!(verify(&tx, 0));
+
+        // With a second output the input signs a real preimage again
+        tx.outputs.push(TransactionOutput {
+            value: 1,
+            script_pubkey: vec![0x51],
+        });
+        assert!(!verify(&tx, 0));
+    }
+
+    #[test]
+    fn test_find_and_delete_matches_on_instruction_boundaries() {
+        // Back-to-back copies are all removed
+        assert_eq!(
+            find_and_delete(&[0x01, 0x02, 0x01, 0x02, 0x51], &[0x01, 0x02]),
+            vec![0x51]
+        );
+        // Inside a push the pattern is data and stays
+        assert_eq!(
+            find_and_delete(&[0x02, 0x01, 0x02, 0x51], &[0x01, 0x02]),
+            vec![0x02, 0x01, 0x02, 0x51]
+        );
+        // Signature pushes and OP_CODESEPARATORs are both removed
+        assert_eq!(
+            legacy_script_code(&[0x02, 0xab, 0xcd, 0xab, 0x51], &[vec![0xab, 0xcd]]),
+            vec![0x51]
+        );
+        assert_eq!(push_script(&[0x55; 76])[..2], [0x4c, 76]);
+    }
+
+    #[test]
+    fn test_verify_script_with_witness_uses_given_verifier() {
+        use crate::crypto::signature::{Secp256k1Verifier, SignatureVerifier};
+        use crate::serialization::transaction::deserialize_transaction;
+        use std::sync::Mutex;
+
+        /// Records every ECDSA call, then defers to libsecp256k1 unless rejecting
+        #[derive(Default)]
+        struct RecordingVerifier {
+            calls: Mutex<Vec<(Vec<u8>, [u8; 32])>>,
+            reject: bool,
+            inner: Secp256k1Verifier,
+        }
+
+        impl SignatureVerifier for RecordingVerifier {
+            fn verify_ecdsa(&self, pubkey: &[u8], signature: &[u8], sighash: &[u8; 32]) -> bool {
+                self.calls.lock().unwrap().push((pubkey.to_vec(), *sighash));
+                !self.reject && self.inner.verify_ecdsa(pubkey, signature, sighash)
+            }
+
+            fn verify_schnorr(&self, pubkey: &[u8], signature: &[u8], sighash: &[u8; 32]) -> bool {
+                !self.reject && self.inner.verify_schnorr(pubkey, signature, sighash)
+            }
+        }
+
+        // BIP143 "Native P2WPKH" example, input 1
+        let tx = deserialize_transaction(
+            &hex::decode(
+                "0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f00000000\
+                 00eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a01000000\
+                 00ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac90\
+                 93510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000",
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let prevouts = vec![
+            PrevOut {
+                value: 625_000_000,
+                script_pubkey: hex::decode(
+                    "2103c9f4836b9a4f77fc0d81f7bcb01b7f1b35916864b9476c241ce9fc198bd25432ac",
+                )
+                .unwrap(),
+            },
+            PrevOut {
+                value: 600_000_000,
+                script_pubkey: hex::decode("00141d0f172a0ecb48aee1be1f2687d2963ae33f71a1").unwrap(),
+            },
+        ];
+        let pubkey =
+            hex::decode("025476c2e83188368da1ff3e292e7acafcdb3566bb0ad253f62fc70f07aeee6357")
+                .unwrap();
+        let witness = vec![
+            hex::decode(
+                "304402203609e17b84f6a7d30c80bfa610b5b4542f32a8a0d5447a12fb1366d7f01cc44a0220573a\
+                 954c4518331561406f90300e8f3358f51928d43c212a8caed02de67eebee01",
+            )
+            .unwrap(),
+            pubkey.clone(),
+        ];
+        let sighash_cache = SighashCache::new(&tx);
+        let verify = |verifier: &dyn SignatureVerifier| {
+            verify_script_with_witness(
+                &vec![],
+                &prevouts[1].script_pubkey,
+                &witness,
+                SCRIPT_VERIFY_P2SH | SCRIPT_VERIFY_WITNESS,
+                &tx,
+                1,
+                &prevouts,
+                &sighash_cache,
+                None,
+                None,
+                crate::types::Network::Mainnet,
+                verifier,
+            )
+            .unwrap()
+        };
+
+        // Each call sees only the verifier it was given
+        let recording = RecordingVerifier::default();
+        assert!(verify(&recording));
+        assert_eq!(recording.calls.lock().unwrap().len(), 1);
+        assert_eq!(recording.calls.lock().unwrap()[0].0, pubkey);
+
+        let rejecting = RecordingVerifier {
+            reject: true,
+            ..Default::default()
+        };
+        assert!(!verify(&rejecting));
+        assert_eq!(rejecting.calls.lock().unwrap().len(), 1);
+    }
 }
 
 #[cfg(kani)]
@@ -2641,9 +6101,12 @@ mod kani_proofs {
             &tx,
             0,
             &[],
+            &[],
+            SigVersion::Base,
             Some(tx_locktime as u64),
             None,
             crate::types::Network::Regtest,
+            default_verifier(),
         );
 
         // Should fail due to type mismatch
@@ -2688,9 +6151,12 @@ mod kani_proofs {
             &tx,
             0,
             &[],
+            &[],
+            SigVersion::Base,
             None,
             None,
             crate::types::Network::Regtest,
+            default_verifier(),
         );
 
         // Should fail due to zero locktime
@@ -2701,18 +6167,19 @@ mod kani_proofs {
     /// Kani proof: BIP112 CSV always fails if sequence disabled (0x80000000)
     ///
     /// Mathematical specification:
-    /// ∀ tx ∈ Transaction where input.sequence has 0x80000000 bit set:
-    /// CSV validation fails
+    /// ∀ tx ∈ Transaction where input.sequence has 0x80000000 bit set and an
+    /// operand without it: CSV validation fails
     #[kani::proof]
     fn kani_bip112_csv_sequence_disabled_fails() {
         let sequence: u32 = kani::any();
         let required_sequence: u32 = kani::any();
 
-        // Ensure sequence disabled bit is set
+        // Ensure sequence disabled bit is set; an operand with it set is a NOP
         let disabled_sequence = sequence | 0x80000000;
+        kani::assume(required_sequence & 0x80000000 == 0);
 
         let tx = Transaction {
-            version: 1,
+            version: 2,
             inputs: vec![TransactionInput {
                 prevout: OutPoint {
                     hash: [0; 32].into(),
@@ -2735,13 +6202,16 @@ mod kani_proofs {
         let result = execute_opcode_with_context_full(
             0xb2, // CSV
             &mut stack,
-            0,
+            SCRIPT_VERIFY_CHECKSEQUENCEVERIFY,
             &tx,
             0,
             &[],
+            &[],
+            SigVersion::Base,
             None,
             None,
             crate::types::Network::Regtest,
+            default_verifier(),
         );
 
         // Should fail due to disabled sequence
@@ -2846,7 +6316,7 @@ mod kani_proofs {
     fn kani_resource_limit_boundary_enforcement() {
         // Test 1: Operation count boundary (MAX_SCRIPT_OPS = 201)
         // Script with exactly 201 operations should check limit correctly
-        let script_max_ops: Vec<u8> = vec![0x51; MAX_SCRIPT_OPS]; // OP_1 repeated 201 times
+        let script_max_ops: Vec<u8> = vec![0x61; MAX_SCRIPT_OPS]; // OP_NOP repeated 201 times
         let mut stack = Vec::new();
         let flags: u32 = 0;
 
@@ -2865,7 +6335,7 @@ mod kani_proofs {
         }
 
         // Script with MAX_SCRIPT_OPS + 1 operations must fail
-        let script_exceed_ops: Vec<u8> = vec![0x51; MAX_SCRIPT_OPS + 1];
+        let script_exceed_ops: Vec<u8> = vec![0x61; MAX_SCRIPT_OPS + 1];
         let mut stack2 = Vec::new();
         let result_exceed_ops = eval_script(&script_exceed_ops, &mut stack2, flags);
 
@@ -3510,7 +6980,7 @@ mod property_tests {
     /// Property test: execute_opcode handles all opcodes without panicking
     ///
     /// Mathematical specification:
-    /// ∀ opcode ∈ {0..255}, stack ∈ Vec<ByteString>: execute_opcode(opcode, stack) ∈ {true, false}
+    /// ∀ opcode ∈ {0..255} \ {OP_EQUALVERIFY, OP_CHECKMULTISIG(VERIFY)}, stack ∈ Vec<ByteString>: execute_opcode(opcode, stack) ∈ {true, false}
     proptest! {
         #[test]
         fn prop_execute_opcode_no_panic(
@@ -3524,11 +6994,11 @@ mod property_tests {
             let mut stack = stack_items;
             let result = execute_opcode(opcode, &mut stack, flags);
 
-            // Should not panic and return valid boolean
-            assert!(result.is_ok());
-            let success = result.unwrap();
-            // Just test it returns a boolean (success is either true or false)
-            let _ = success;
+            // Should not panic; only OP_EQUALVERIFY and a CHECKMULTISIG with a
+            // bad key count report failure as an error
+            if !matches!(opcode, 0x88 | 0xae | 0xaf) {
+                assert!(result.is_ok());
+            }
 
             // Stack should remain within bounds
             assert!(stack.len() <= MAX_STACK_SIZE);
@@ -3792,9 +7262,12 @@ mod kani_proofs_2 {
             &tx,
             input_index,
             &prevouts,
+            &[],
+            SigVersion::Base,
             block_height,
             median_time_past,
             crate::types::Network::Regtest,
+            default_verifier(),
         );
 
         if result.is_ok() && result.unwrap() {
@@ -3822,7 +7295,7 @@ mod kani_proofs_2 {
     /// - OP_HASH160 (0xa9): computes RIPEMD160(SHA256(x))
     /// - OP_HASH256 (0xaa): computes SHA256(SHA256(x))
     /// - OP_EQUAL (0x87): pushes 1 if top two items equal, else 0
-    /// - OP_EQUALVERIFY (0x88): returns true if top two items equal, else false
+    /// - OP_EQUALVERIFY (0x88): returns true if top two items equal, else errors
     ///
     /// This ensures individual opcode execution matches Bitcoin specification exactly.
     #[kani::proof]
@@ -4004,7 +7477,7 @@ mod kani_proofs_2 {
             );
         }
 
-        // Test OP_EQUALVERIFY: returns true if equal, false if not
+        // Test OP_EQUALVERIFY: returns true if equal, errors if not
         {
             // Equal items
             let item = vec![1, 2, 3];
@@ -4026,8 +7499,8 @@ mod kani_proofs_2 {
             let mut stack = vec![vec![1, 2, 3], vec![4, 5, 6]];
             let result = execute_opcode(0x88, &mut stack, 0);
             assert!(
-                result.is_ok() && !result.unwrap(),
-                "execute_opcode: OP_EQUALVERIFY must fail for unequal items"
+                result.is_err(),
+                "execute_opcode: OP_EQUALVERIFY must error for unequal items"
             );
         }
 
@@ -4046,8 +7519,9 @@ mod kani_proofs_2 {
     ///
     /// Mathematical specification:
     /// ∀ tx ∈ TX, input_index ∈ N, sequence_value ∈ [0, 2^32), stack ∈ ST:
-    /// - CSV(tx, input_index, sequence_value) = true ⟹
-    ///   (sequence_disabled_bit(input.sequence) = false ∧
+    /// - CSV(tx, input_index, sequence_value) = true ∧ ¬disabled(sequence_value) ⟹
+    ///   (tx.version >= 2 ∧
+    ///    sequence_disabled_bit(input.sequence) = false ∧
     ///    type_flags_match(sequence_value, input.sequence) ∧
     ///    input_locktime >= required_locktime)
     #[kani::proof]
@@ -4086,17 +7560,22 @@ mod kani_proofs_2 {
         let result = execute_opcode_with_context_full(
             0xb2, // OP_CHECKSEQUENCEVERIFY
             &mut stack,
-            0,
+            SCRIPT_VERIFY_CHECKSEQUENCEVERIFY,
             &tx,
             input_index,
             &prevouts,
+            &[],
+            SigVersion::Base,
             None,
             None,
             crate::types::Network::Regtest,
+            default_verifier(),
         );
 
-        if result.is_ok() && result.unwrap() {
-            // If CSV passes, these must be true:
+        let operand_disabled = (sequence_value & 0x80000000) != 0;
+        if result.is_ok() && result.unwrap() && !operand_disabled {
+            // If CSV passes on an enabled operand, these must be true:
+            assert!(tx.version >= 2, "CSV requires transaction version >= 2");
             assert!(!sequence_disabled, "CSV fails if sequence disabled");
 
             let type_flag = (sequence_value & 0x00400000) != 0;
@@ -0,0 +1,173 @@
+//! Script numbers (CScriptNum)
+//!
+//! Numeric stack items are little-endian sign-magnitude integers: the high bit
+//! of the last byte is the sign and zero is the empty vector. Operands of
+//! numeric opcodes are limited to `DEFAULT_MAX_NUM_SIZE` bytes, while results
+//! may be longer (adding two 4-byte values can need 5 bytes); such a result
+//! can stay on the stack but is rejected if used as an operand.
+//!
+//! Reference: Bitcoin Core `script/script.h` CScriptNum
+
+use crate::error::{ConsensusError, Result};
+use crate::types::ByteString;
+
+/// Operand size limit of the numeric opcodes (Core's default `nMaxNumSize`)
+pub const DEFAULT_MAX_NUM_SIZE: usize = 4;
+
+/// A script number
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct ScriptNum(i64);
+
+impl ScriptNum {
+    /// Wrap an integer value
+    pub fn new(value: i64) -> Self {
+        Self(value)
+    }
+
+    /// The integer value
+    pub fn value(self) -> i64 {
+        self.0
+    }
+
+    /// Decode a stack item as a script number
+    ///
+    /// Fails if `bytes` is longer than `max_size` (at most 8), or, with
+    /// `require_minimal`, if it is not the shortest encoding of its value:
+    /// the last byte may only be 0x00 or 0x80 when the byte before it has its
+    /// high bit set. Negative zero (`[0x80]`) decodes as zero, but is not
+    /// minimal.
+    pub fn from_bytes(bytes: &[u8], max_size: usize, require_minimal: bool) -> Result<Self> {
+        if bytes.len() > max_size.min(8) {
+            return Err(ConsensusError::ScriptExecution(
+                format!(
+                    "Script number of {} bytes exceeds the {max_size}-byte limit",
+                    bytes.len()
+                )
+                .into(),
+            ));
+        }
+        let Some((&last, rest)) = bytes.split_last() else {
+            return Ok(Self(0));
+        };
+        if require_minimal && last & 0x7f == 0 && rest.last().is_none_or(|&b| b & 0x80 == 0) {
+            return Err(ConsensusError::ScriptExecution(
+                "Non-minimally encoded script number".into(),
+            ));
+        }
+
+        let mut magnitude: u64 = 0;
+        for (i, &byte) in bytes.iter().enumerate() {
+            magnitude |= (byte as u64) << (8 * i);
+        }
+
+        // The high bit of the last byte is the sign
+        if last & 0x80 != 0 {
+            let sign_bit = 0x80u64 << (8 * (bytes.len() - 1));
+            Ok(Self(-((magnitude & !sign_bit) as i64)))
+        } else {
+            Ok(Self(magnitude as i64))
+        }
+    }
+
+    /// Minimal encoding of the number; zero is the empty vector
+    pub fn to_bytes(self) -> ByteString {
+        let mut bytes = Vec::new();
+        let mut magnitude = self.0.unsigned_abs();
+        while magnitude > 0 {
+            bytes.push((magnitude & 0xff) as u8);
+            magnitude >>= 8;
+        }
+
+        // The high bit of the last byte is the sign; add a byte if it is taken
+        if let Some(last) = bytes.last_mut() {
+            if *last & 0x80 != 0 {
+                bytes.push(if self.0 < 0 { 0x80 } else { 0x00 });
+            } else if self.0 < 0 {
+                *last |= 0x80;
+            }
+        }
+        bytes
+    }
+}
+
+impl From<i64> for ScriptNum {
+    fn from(value: i64) -> Self {
+        Self(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(bytes: &[u8], require_minimal: bool) -> Option<i64> {
+        ScriptNum::from_bytes(bytes, DEFAULT_MAX_NUM_SIZE, require_minimal)
+            .ok()
+            .map(ScriptNum::value)
+    }
+
+    #[test]
+    fn test_script_num_core_vectors() {
+        // Values from Core's scriptnum_tests, with their minimal encodings
+        let vectors: [(i64, &[u8]); 12] = [
+            (0, &[]),
+            (1, &[0x01]),
+            (-1, &[0x81]),
+            (127, &[0x7f]),
+            (-127, &[0xff]),
+            (128, &[0x80, 0x00]),
+            (-128, &[0x80, 0x80]),
+            (255, &[0xff, 0x00]),
+            (256, &[0x00, 0x01]),
+            (-32768, &[0x00, 0x80, 0x80]),
+            (2147483647, &[0xff, 0xff, 0xff, 0x7f]),
+            (-2147483647, &[0xff, 0xff, 0xff, 0xff]),
+        ];
+        for (value, bytes) in vectors {
+            assert_eq!(ScriptNum::new(value).to_bytes(), bytes, "encode {value}");
+            assert_eq!(decode(bytes, true), Some(value), "decode {value}");
+        }
+
+        // 2^31 and -2^31 need a fifth byte: valid results, invalid operands
+        assert_eq!(
+            ScriptNum::new(2147483648).to_bytes(),
+            vec![0x00, 0x00, 0x00, 0x80, 0x00]
+        );
+        assert_eq!(
+            ScriptNum::new(-2147483648).to_bytes(),
+            vec![0x00, 0x00, 0x00, 0x80, 0x80]
+        );
+        assert_eq!(decode(&[0x00, 0x00, 0x00, 0x80, 0x00], false), None);
+        assert_eq!(
+            ScriptNum::from_bytes(&[0x00, 0x00, 0x00, 0x80, 0x00], 5, true)
+                .unwrap()
+                .value(),
+            2147483648
+        );
+    }
+
+    #[test]
+    fn test_script_num_negative_zero_and_minimality() {
+        // Negative zero and zero padding decode as zero but are not minimal
+        for zero in [
+            &[0x80][..],
+            &[0x00],
+            &[0x00, 0x80],
+            &[0x00, 0x00, 0x00, 0x00],
+        ] {
+            assert_eq!(decode(zero, false), Some(0));
+            assert_eq!(decode(zero, true), None);
+        }
+        assert_eq!(decode(&[0x01, 0x00], false), Some(1));
+        assert_eq!(decode(&[0x01, 0x00], true), None);
+        assert_eq!(decode(&[0x01, 0x80], false), Some(-1));
+        assert_eq!(decode(&[0x01, 0x80], true), None);
+
+        // A sign byte is needed when the magnitude's high bit is set
+        assert_eq!(decode(&[0xff, 0x00], true), Some(255));
+        assert_eq!(decode(&[0xff, 0x80], true), Some(-255));
+
+        // Encoding never produces negative zero
+        assert_eq!(ScriptNum::new(-0).to_bytes(), Vec::<u8>::new());
+    }
+}
@@ -0,0 +1,289 @@
+//! scriptPubKey template classification
+//!
+//! Matches output scripts against the standard templates recognised by
+//! Bitcoin Core's `Solver` (`script/solver.cpp`), so standardness, sigop
+//! counting and address encoding share one definition of each template.
+
+/// Classified scriptPubKey template
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScriptType {
+    /// `<pubkey> OP_CHECKSIG` (compressed or uncompressed key)
+    P2PK(Vec<u8>),
+    /// `OP_DUP OP_HASH160 <20-byte hash> OP_EQUALVERIFY OP_CHECKSIG`
+    P2PKH([u8; 20]),
+    /// `OP_HASH160 <20-byte hash> OP_EQUAL`
+    P2SH([u8; 20]),
+    /// `OP_0 <20-byte program>`
+    P2WPKH([u8; 20]),
+    /// `OP_0 <32-byte program>`
+    P2WSH([u8; 32]),
+    /// `OP_1 <32-byte program>`
+    P2TR([u8; 32]),
+    /// `OP_m <pubkey>... OP_n OP_CHECKMULTISIG` with 1 <= m <= n <= 16
+    Multisig { m: u8, n: u8, keys: Vec<Vec<u8>> },
+    /// `OP_RETURN` followed only by data pushes; holds the bytes after `OP_RETURN`
+    OpReturn(Vec<u8>),
+    /// Anything that matches no template above
+    NonStandard,
+}
+
+const OP_0: u8 = 0x00;
+const OP_PUSHDATA1: u8 = 0x4c;
+const OP_PUSHDATA2: u8 = 0x4d;
+const OP_PUSHDATA4: u8 = 0x4e;
+const OP_1NEGATE: u8 = 0x4f;
+const OP_RESERVED: u8 = 0x50;
+const OP_1: u8 = 0x51;
+const OP_16: u8 = 0x60;
+const OP_RETURN: u8 = 0x6a;
+const OP_DUP: u8 = 0x76;
+const OP_EQUAL: u8 = 0x87;
+const OP_EQUALVERIFY: u8 = 0x88;
+const OP_HASH160: u8 = 0xa9;
+const OP_CHECKSIG: u8 = 0xac;
+const OP_CHECKMULTISIG: u8 = 0xae;
+
+/// Classify a scriptPubKey into a standard template
+///
+/// Templates are matched exactly, byte for byte, as in Core. Scripts that
+/// match none of them are `ScriptType::NonStandard`.
+pub fn classify_script(script: &[u8]) -> ScriptType {
+    match script {
+        [OP_DUP, OP_HASH160, 0x14, hash @ .., OP_EQUALVERIFY, OP_CHECKSIG] if hash.len() == 20 => {
+            ScriptType::P2PKH(to_array(hash))
+        }
+        [OP_HASH160, 0x14, hash @ .., OP_EQUAL] if hash.len() == 20 => {
+            ScriptType::P2SH(to_array(hash))
+        }
+        [OP_0, 0x14, program @ ..] if program.len() == 20 => ScriptType::P2WPKH(to_array(program)),
+        [OP_0, 0x20, program @ ..] if program.len() == 32 => ScriptType::P2WSH(to_array(program)),
+        [OP_1, 0x20, program @ ..] if program.len() == 32 => ScriptType::P2TR(to_array(program)),
+        [len, key @ .., OP_CHECKSIG]
+            if key.len() == *len as usize && is_valid_pubkey_length(key.len()) =>
+        {
+            ScriptType::P2PK(key.to_vec())
+        }
+        [OP_RETURN, data @ ..] if parse_pushes(data).is_some() => {
+            ScriptType::OpReturn(data.to_vec())
+        }
+        [m @ OP_1..=OP_16, .., n @ OP_1..=OP_16, OP_CHECKMULTISIG] => {
+            classify_multisig(script, *m - OP_1 + 1, *n - OP_1 + 1)
+        }
+        _ => ScriptType::NonStandard,
+    }
+}
+
+/// Split a witness program into its version and program bytes
+///
+/// A witness program is a version opcode (`OP_0` or `OP_1`..`OP_16`) followed
+/// by a single direct push of 2 to 40 bytes, as in Core's
+/// `CScript::IsWitnessProgram`. Versions and lengths without defined semantics
+/// still match: they are consensus-valid, reserved for future soft forks.
+pub fn witness_program(script: &[u8]) -> Option<(u8, &[u8])> {
+    if !(4..=42).contains(&script.len()) || script[1] as usize != script.len() - 2 {
+        return None;
+    }
+    let version = match script[0] {
+        OP_0 => 0,
+        op @ OP_1..=OP_16 => op - OP_1 + 1,
+        _ => return None,
+    };
+    Some((version, &script[2..]))
+}
+
+/// Match `OP_m <pubkey>... OP_n OP_CHECKMULTISIG` once m and n are known
+fn classify_multisig(script: &[u8], m: u8, n: u8) -> ScriptType {
+    let keys = match parse_pushes(&script[1..script.len() - 2]) {
+        Some(keys) => keys,
+        None => return ScriptType::NonStandard,
+    };
+
+    if m > n
+        || keys.len() != n as usize
+        || !keys.iter().all(|key| is_valid_pubkey_length(key.len()))
+    {
+        return ScriptType::NonStandard;
+    }
+
+    ScriptType::Multisig { m, n, keys }
+}
+
+/// Split a push-only script into its pushed data items
+///
+/// Returns `None` if the script contains a non-push opcode or a truncated push.
+/// As in Core's `IsPushOnly`, every opcode up to `OP_16` counts as a push:
+/// `OP_0`, `OP_1NEGATE` and `OP_1`..`OP_16` yield their numeric encoding, and
+/// `OP_RESERVED` yields nothing (executing it fails the script).
+pub(crate) fn parse_pushes(script: &[u8]) -> Option<Vec<Vec<u8>>> {
+    let mut items = Vec::new();
+    let mut i = 0;
+
+    while i < script.len() {
+        let opcode = script[i];
+        i += 1;
+
+        let len = match opcode {
+            OP_0 => 0,
+            0x01..=0x4b => opcode as usize,
+            OP_PUSHDATA1 => {
+                let len = *script.get(i)? as usize;
+                i += 1;
+                len
+            }
+            OP_PUSHDATA2 => {
+                let bytes = script.get(i..i + 2)?;
+                i += 2;
+                u16::from_le_bytes([bytes[0], bytes[1]]) as usize
+            }
+            OP_PUSHDATA4 => {
+                let bytes = script.get(i..i + 4)?;
+                i += 4;
+                u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize
+            }
+            OP_1NEGATE => {
+                items.push(vec![0x81]);
+                continue;
+            }
+            OP_RESERVED => continue,
+            OP_1..=OP_16 => {
+                items.push(vec![opcode - OP_1 + 1]);
+                continue;
+            }
+            _ => return None,
+        };
+
+        items.push(script.get(i..i.checked_add(len)?)?.to_vec());
+        i += len;
+    }
+
+    Some(items)
+}
+
+/// Compressed (33-byte) or uncompressed (65-byte) public key length
+fn is_valid_pubkey_length(len: usize) -> bool {
+    len == 33 || len == 65
+}
+
+fn to_array<const N: usize>(bytes: &[u8]) -> [u8; N] {
+    let mut array = [0u8; N];
+    array.copy_from_slice(bytes);
+    array
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_p2pkh() {
+        let mut script = vec![OP_DUP, OP_HASH160, 0x14];
+        script.extend_from_slice(&[0x11; 20]);
+        script.extend_from_slice(&[OP_EQUALVERIFY, OP_CHECKSIG]);
+        assert_eq!(classify_script(&script), ScriptType::P2PKH([0x11; 20]));
+    }
+
+    #[test]
+    fn test_classify_p2sh() {
+        let mut script = vec![OP_HASH160, 0x14];
+        script.extend_from_slice(&[0x22; 20]);
+        script.push(OP_EQUAL);
+        assert_eq!(classify_script(&script), ScriptType::P2SH([0x22; 20]));
+    }
+
+    #[test]
+    fn test_classify_witness_programs() {
+        let mut p2wpkh = vec![OP_0, 0x14];
+        p2wpkh.extend_from_slice(&[0x33; 20]);
+        assert_eq!(classify_script(&p2wpkh), ScriptType::P2WPKH([0x33; 20]));
+
+        let mut p2wsh = vec![OP_0, 0x20];
+        p2wsh.extend_from_slice(&[0x44; 32]);
+        assert_eq!(classify_script(&p2wsh), ScriptType::P2WSH([0x44; 32]));
+
+        let mut p2tr = vec![OP_1, 0x20];
+        p2tr.extend_from_slice(&[0x55; 32]);
+        assert_eq!(classify_script(&p2tr), ScriptType::P2TR([0x55; 32]));
+        assert_eq!(witness_program(&p2tr), Some((1, &[0x55; 32][..])));
+
+        // Future versions are witness programs but match no template
+        let mut v16 = vec![OP_16, 0x02, 0x66, 0x66];
+        assert_eq!(witness_program(&v16), Some((16, &[0x66, 0x66][..])));
+        assert_eq!(classify_script(&v16), ScriptType::NonStandard);
+
+        // A one-byte program or a push that does not span the script is not
+        v16[1] = 0x01;
+        assert_eq!(witness_program(&v16), None);
+        assert_eq!(witness_program(&[OP_1, 0x01, 0x66]), None);
+    }
+
+    #[test]
+    fn test_classify_p2pk() {
+        let mut script = vec![33];
+        script.extend_from_slice(&[0x02; 33]);
+        script.push(OP_CHECKSIG);
+        assert_eq!(classify_script(&script), ScriptType::P2PK(vec![0x02; 33]));
+    }
+
+    #[test]
+    fn test_classify_multisig() {
+        // 2-of-3 with compressed keys
+        let mut script = vec![OP_1 + 1];
+        for i in 0..3u8 {
+            script.push(33);
+            script.extend_from_slice(&[i; 33]);
+        }
+        script.extend_from_slice(&[OP_1 + 2, OP_CHECKMULTISIG]);
+        assert_eq!(
+            classify_script(&script),
+            ScriptType::Multisig {
+                m: 2,
+                n: 3,
+                keys: vec![vec![0; 33], vec![1; 33], vec![2; 33]],
+            }
+        );
+
+        // n does not match the number of keys
+        let mut bad = vec![OP_1];
+        bad.push(33);
+        bad.extend_from_slice(&[0x02; 33]);
+        bad.extend_from_slice(&[OP_1 + 1, OP_CHECKMULTISIG]);
+        assert_eq!(classify_script(&bad), ScriptType::NonStandard);
+    }
+
+    #[test]
+    fn test_classify_op_return() {
+        let script = vec![OP_RETURN, 0x04, 0xde, 0xad, 0xbe, 0xef];
+        assert_eq!(
+            classify_script(&script),
+            ScriptType::OpReturn(vec![0x04, 0xde, 0xad, 0xbe, 0xef])
+        );
+        assert_eq!(classify_script(&[OP_RETURN]), ScriptType::OpReturn(vec![]));
+
+        // Non-push opcode after OP_RETURN
+        assert_eq!(
+            classify_script(&[OP_RETURN, OP_DUP]),
+            ScriptType::NonStandard
+        );
+    }
+
+    #[test]
+    fn test_parse_pushes_accepts_opcodes_up_to_op_16() {
+        assert_eq!(
+            parse_pushes(&[OP_0, OP_1NEGATE, OP_RESERVED, OP_1, OP_16, 0x01, 0xaa]),
+            Some(vec![vec![], vec![0x81], vec![1], vec![16], vec![0xaa]])
+        );
+        assert_eq!(parse_pushes(&[OP_16 + 1]), None); // OP_NOP
+        assert_eq!(parse_pushes(&[0x02, 0xaa]), None); // Truncated push
+    }
+
+    #[test]
+    fn test_classify_non_standard() {
+        assert_eq!(classify_script(&[]), ScriptType::NonStandard);
+        assert_eq!(classify_script(&[0x51]), ScriptType::NonStandard);
+        // P2SH with a 19-byte hash
+        let mut short = vec![OP_HASH160, 0x13];
+        short.extend_from_slice(&[0x22; 19]);
+        short.push(OP_EQUAL);
+        assert_eq!(classify_script(&short), ScriptType::NonStandard);
+    }
+}
@@ -1,10 +1,10 @@
 //! Segregated Witness (SegWit) functions from Orange Paper Section 11.1
 
+use crate::constants::MINIMUM_WITNESS_COMMITMENT;
 use crate::error::Result;
 use crate::types::*;
 use crate::types::{ByteString, Hash, Natural};
 use crate::witness;
-use bitcoin_hashes::{sha256d, Hash as BitcoinHash, HashEngine};
 
 /// Witness Data: 𝒲 = 𝕊* (stack of witness elements)
 ///
@@ -12,16 +12,18 @@ use bitcoin_hashes::{sha256d, Hash as BitcoinHash, HashEngine};
 pub use crate::witness::Witness;
 
 /// Calculate transaction weight for SegWit
-/// Weight(tx) = 4 × |Serialize(tx ∖ witness)| + |Serialize(tx)|
+/// Weight(tx) = 3 × |Serialize(tx ∖ witness)| + |Serialize(tx)|
+///
+/// `witnesses` holds one witness stack per input.
 pub fn calculate_transaction_weight(
     tx: &Transaction,
-    witness: Option<&Witness>,
+    witnesses: Option<&[Witness]>,
 ) -> Result<Natural> {
     // Calculate base size (transaction without witness data)
     let base_size = calculate_base_size(tx);
 
     // Calculate total size (transaction with witness data)
-    let total_size = calculate_total_size(tx, witness);
+    let total_size = calculate_total_size(tx, witnesses);
 
     // Use unified witness framework for weight formula
     Ok(witness::calculate_transaction_weight_segwit(
@@ -42,20 +44,20 @@ pub fn calculate_base_size(tx: &Transaction) -> Natural {
 /// Calculate base size (transaction without witness data)
 #[cfg(not(kani))]
 fn calculate_base_size(tx: &Transaction) -> Natural {
-    // Simplified calculation - in reality this would be the actual serialized size
-    (4 + // version
-    tx.inputs.len() * (32 + 4 + 1 + 4) + // inputs (OutPoint + script_sig_len + sequence)
-    tx.outputs.len() * (8 + 1) + // outputs (value + script_pubkey_len)
-    4) as Natural // lock_time
+    crate::transaction::calculate_transaction_size(tx) as Natural
 }
 
 /// Calculate total size (transaction with witness data)
 #[cfg(kani)]
-pub fn calculate_total_size(tx: &Transaction, witness: Option<&Witness>) -> Natural {
+pub fn calculate_total_size(tx: &Transaction, witnesses: Option<&[Witness]>) -> Natural {
     let base_size = calculate_base_size(tx);
 
-    if let Some(witness_data) = witness {
-        let witness_size: Natural = witness_data.iter().map(|w| w.len() as Natural).sum();
+    if let Some(witness_data) = witnesses {
+        let witness_size: Natural = witness_data
+            .iter()
+            .flatten()
+            .map(|w| w.len() as Natural)
+            .sum();
         base_size + witness_size
     } else {
         base_size
@@ -64,52 +66,63 @@ pub fn calculate_total_size(tx: &Transaction, witness: Option<&Witness>) -> Natu
 
 /// Calculate total size (transaction with witness data)
 #[cfg(not(kani))]
-fn calculate_total_size(tx: &Transaction, witness: Option<&Witness>) -> Natural {
-    let base_size = calculate_base_size(tx);
-
-    if let Some(witness_data) = witness {
-        let witness_size: Natural = witness_data.iter().map(|w| w.len() as Natural).sum();
-        base_size + witness_size
-    } else {
-        base_size
-    }
+fn calculate_total_size(tx: &Transaction, witnesses: Option<&[Witness]>) -> Natural {
+    crate::transaction::calculate_transaction_sizes(tx, witnesses.unwrap_or_default()).1 as Natural
 }
 
-/// Compute witness merkle root for block
-/// WitnessRoot = ComputeMerkleRoot({Hash(tx.witness) : tx ∈ block.transactions})
-pub fn compute_witness_merkle_root(block: &Block, witnesses: &[Witness]) -> Result<Hash> {
+/// Compute witness merkle root for block (BIP141)
+/// WitnessRoot = ComputeMerkleRoot({wtxid(tx) : tx ∈ block.transactions}), wtxid(coinbase) = 0
+///
+/// `witnesses` holds each transaction's per-input witness stacks; a missing
+/// or all-empty set of stacks gives wtxid == txid.
+pub fn compute_witness_merkle_root(block: &Block, witnesses: &[Vec<Witness>]) -> Result<Hash> {
     if block.transactions.is_empty() {
         return Err(crate::error::ConsensusError::ConsensusRuleViolation(
             "Cannot compute witness merkle root for empty block".into(),
         ));
     }
 
-    // Hash each witness
-    let mut witness_hashes = Vec::new();
-    for (i, witness) in witnesses.iter().enumerate() {
-        if i == 0 {
-            // Coinbase transaction has empty witness
-            witness_hashes.push([0u8; 32]);
-        } else {
-            let witness_hash = hash_witness(witness);
-            witness_hashes.push(witness_hash);
-        }
-    }
+    // The coinbase's wtxid is defined as all zeros: its witness carries the
+    // reserved value committed alongside this root
+    let wtxids: Vec<Hash> = block
+        .transactions
+        .iter()
+        .enumerate()
+        .map(|(i, tx)| {
+            if i == 0 {
+                [0u8; 32]
+            } else {
+                let stacks = witnesses.get(i).map_or(&[][..], Vec::as_slice);
+                crate::block::calculate_wtxid(tx, stacks)
+            }
+        })
+        .collect();
 
-    // Compute merkle root of witness hashes
-    compute_merkle_root(&witness_hashes)
+    compute_merkle_root(&wtxids)
 }
 
-/// Hash witness data
-fn hash_witness(witness: &Witness) -> Hash {
-    let mut hasher = sha256d::Hash::engine();
-    for element in witness {
-        hasher.input(element);
+/// Compute the witness commitment for a block (BIP141)
+/// Commitment = SHA256d(WitnessRoot || witness reserved value)
+///
+/// The reserved value is the single item of the coinbase input's witness.
+pub fn compute_witness_commitment(witness_root: &Hash, witness_reserved_value: &[u8]) -> Hash {
+    use crate::crypto::OptimizedSha256;
+
+    let mut preimage = Vec::with_capacity(32 + witness_reserved_value.len());
+    preimage.extend_from_slice(witness_root);
+    preimage.extend_from_slice(witness_reserved_value);
+    OptimizedSha256::new().hash256(&preimage)
+}
+
+/// Witness reserved value of a coinbase input's witness stack
+///
+/// Core requires the stack to hold exactly one 32-byte item whenever the
+/// coinbase carries a witness commitment (`bad-witness-nonce-size`).
+pub(crate) fn witness_reserved_value(coinbase_witness: &Witness) -> Option<&ByteString> {
+    match coinbase_witness.as_slice() {
+        [value] if value.len() == 32 => Some(value),
+        _ => None,
     }
-    let result = sha256d::Hash::from_engine(hasher);
-    let mut hash = [0u8; 32];
-    hash.copy_from_slice(&result);
-    hash
 }
 
 /// Compute merkle root from hashes
@@ -120,48 +133,89 @@ fn compute_merkle_root(hashes: &[Hash]) -> Result<Hash> {
         ));
     }
 
-    if hashes.len() == 1 {
-        return Ok(hashes[0]);
-    }
-
-    // Simplified merkle root calculation
-    // In reality, this would use proper merkle tree construction
-    let mut hasher = sha256d::Hash::engine();
-    hasher.input(&hashes[0]);
-    hasher.input(&hashes[1]);
-    let result = sha256d::Hash::from_engine(hasher);
-    let mut root = [0u8; 32];
-    root.copy_from_slice(&result);
-    Ok(root)
+    crate::mining::calculate_merkle_root_from_hashes(hashes.to_vec())
 }
 
 /// Validate witness commitment in coinbase transaction
-pub fn validate_witness_commitment(
-    coinbase_tx: &Transaction,
-    witness_merkle_root: &Hash,
-) -> Result<bool> {
+///
+/// `commitment` is the expected value from `compute_witness_commitment`. As in
+/// Core, the last commitment output counts when there are several.
+pub fn validate_witness_commitment(coinbase_tx: &Transaction, commitment: &Hash) -> Result<bool> {
     // Look for witness commitment in coinbase script
-    for output in &coinbase_tx.outputs {
-        if let Some(commitment) = extract_witness_commitment(&output.script_pubkey) {
-            return Ok(commitment == *witness_merkle_root);
-        }
+    if let Some(found) = coinbase_tx
+        .outputs
+        .iter()
+        .rev()
+        .find_map(|output| extract_witness_commitment(&output.script_pubkey))
+    {
+        return Ok(found == *commitment);
     }
 
     // No witness commitment found - this is valid for non-SegWit blocks
     Ok(true)
 }
 
+/// Validate a block's witness commitment against its witness data (BIP141)
+///
+/// A coinbase without a commitment output passes. Otherwise the coinbase
+/// input's witness must be the reserved value, and the commitment must equal
+/// `compute_witness_commitment` over the block's witness root.
+pub fn validate_block_witness_commitment(
+    block: &Block,
+    witnesses: &[Vec<Witness>],
+) -> Result<bool> {
+    let Some(coinbase) = block.transactions.first() else {
+        return Ok(true);
+    };
+    if !coinbase
+        .outputs
+        .iter()
+        .any(|output| extract_witness_commitment(&output.script_pubkey).is_some())
+    {
+        return Ok(true);
+    }
+
+    let coinbase_witness = witnesses.first().and_then(|stacks| stacks.first());
+    let Some(reserved_value) = coinbase_witness.and_then(witness_reserved_value) else {
+        return Ok(false);
+    };
+    let witness_root = compute_witness_merkle_root(block, witnesses)?;
+    validate_witness_commitment(
+        coinbase,
+        &compute_witness_commitment(&witness_root, reserved_value),
+    )
+}
+
+/// Header that marks a coinbase output as the witness commitment (BIP141)
+pub const WITNESS_COMMITMENT_HEADER: [u8; 4] = [0xaa, 0x21, 0xa9, 0xed];
+
+/// Build the witness commitment output script for a coinbase transaction
+///
+/// Inverse of `extract_witness_commitment`: OP_RETURN, a 36-byte push of the
+/// commitment header 0xaa21a9ed followed by the 32-byte commitment.
+pub fn create_witness_commitment_script(commitment: &Hash) -> ByteString {
+    let mut script = Vec::with_capacity(MINIMUM_WITNESS_COMMITMENT);
+    script.push(0x6a); // OP_RETURN
+    script.push(0x24); // 36 bytes
+    script.extend_from_slice(&WITNESS_COMMITMENT_HEADER);
+    script.extend_from_slice(commitment);
+    script
+}
+
 /// Extract witness commitment from script
+///
+/// Matches Core's GetWitnessCommitmentIndex: at least 38 bytes starting
+/// OP_RETURN, a 36-byte push and the commitment header; bytes past 38 are
+/// ignored.
 pub(crate) fn extract_witness_commitment(script: &ByteString) -> Option<Hash> {
-    // Look for OP_RETURN followed by witness commitment
-    if script.len() >= 38 && script[0] == 0x6a {
-        // OP_RETURN
-        if script.len() >= 38 && script[1] == 0x24 {
-            // 36 bytes
-            let mut commitment = [0u8; 32];
-            commitment.copy_from_slice(&script[2..34]);
-            return Some(commitment);
-        }
+    if script.len() >= MINIMUM_WITNESS_COMMITMENT
+        && script[0] == 0x6a
+        && script[1] == 0x24
+        && script[2..6] == WITNESS_COMMITMENT_HEADER
+    {
+        let mut commitment = [0u8; 32];
+        commitment.copy_from_slice(&script[6..38]);
+        return Some(commitment);
     }
     None
 }
@@ -177,31 +231,41 @@ pub fn is_segwit_transaction(tx: &Transaction) -> bool {
 }
 
 /// Calculate block weight for SegWit blocks
-pub fn calculate_block_weight(block: &Block, witnesses: &[Witness]) -> Result<Natural> {
+///
+/// `witnesses` holds each transaction's per-input witness stacks.
+pub fn calculate_block_weight(block: &Block, witnesses: &[Vec<Witness>]) -> Result<Natural> {
     let mut total_weight = 0;
 
     for (i, tx) in block.transactions.iter().enumerate() {
-        let witness = if i < witnesses.len() {
-            Some(&witnesses[i])
-        } else {
-            None
-        };
-
-        total_weight += calculate_transaction_weight(tx, witness)?;
+        let tx_witnesses = witnesses.get(i).map(Vec::as_slice);
+        total_weight += calculate_transaction_weight(tx, tx_witnesses)?;
     }
 
     Ok(total_weight)
 }
 
+/// Serialized block size in bytes, with witness data
+pub(crate) fn calculate_block_total_size(block: &Block, witnesses: &[Vec<Witness>]) -> Natural {
+    let tx_count_len =
+        crate::serialization::varint::encode_varint(block.transactions.len() as u64).len();
+    let transactions: Natural = block
+        .transactions
+        .iter()
+        .enumerate()
+        .map(|(i, tx)| calculate_total_size(tx, witnesses.get(i).map(Vec::as_slice)))
+        .sum();
+    (80 + tx_count_len) as Natural + transactions
+}
+
 /// Validate SegWit block
 pub fn validate_segwit_block(
     block: &Block,
-    witnesses: &[Witness],
+    witnesses: &[Vec<Witness>],
     max_block_weight: Natural,
 ) -> Result<bool> {
-    // Validate witness structure for all transactions using unified framework
-    for (i, _tx) in block.transactions.iter().enumerate() {
-        if i < witnesses.len() && !witness::validate_segwit_witness_structure(&witnesses[i])? {
+    // Validate witness structure for every input using unified framework
+    for stack in witnesses.iter().flatten() {
+        if !witness::validate_segwit_witness_structure(stack)? {
             return Ok(false);
         }
     }
@@ -213,11 +277,8 @@ pub fn validate_segwit_block(
     }
 
     // Validate witness commitment
-    if !block.transactions.is_empty() {
-        let witness_root = compute_witness_merkle_root(block, witnesses)?;
-        if !validate_witness_commitment(&block.transactions[0], &witness_root)? {
-            return Ok(false);
-        }
+    if !validate_block_witness_commitment(block, witnesses)? {
+        return Ok(false);
     }
 
     Ok(true)
@@ -230,7 +291,7 @@ mod tests {
     #[test]
     fn test_calculate_transaction_weight() {
         let tx = create_test_transaction();
-        let witness = vec![vec![0x51], vec![0x52]]; // OP_1, OP_2
+        let witness = vec![vec![vec![0x51], vec![0x52]]]; // OP_1, OP_2
 
         let weight = calculate_transaction_weight(&tx, Some(&witness)).unwrap();
         assert!(weight > 0);
@@ -248,14 +309,55 @@ mod tests {
     fn test_compute_witness_merkle_root() {
         let block = create_test_block();
         let witnesses = vec![
-            vec![],           // Coinbase witness (empty)
-            vec![vec![0x51]], // First transaction witness
+            vec![],                 // Coinbase witness (empty)
+            vec![vec![vec![0x51]]], // First transaction's input stacks
         ];
 
         let root = compute_witness_merkle_root(&block, &witnesses).unwrap();
         assert_eq!(root.len(), 32);
     }
 
+    #[test]
+    fn test_compute_witness_merkle_root_uses_wtxids() {
+        let coinbase = create_test_transaction();
+        let plain = create_test_transaction();
+        let mut witnessed = create_test_transaction();
+        witnessed.inputs[0].prevout.index = 1;
+        let block = Block {
+            header: create_test_header(),
+            transactions: vec![coinbase, plain.clone(), witnessed.clone()].into_boxed_slice(),
+        };
+        let witnesses = vec![
+            vec![vec![vec![0xaa; 32]]], // Coinbase witness reserved value: ignored
+            vec![],
+            vec![vec![vec![0x51], vec![0x52]]],
+        ];
+
+        let expected = crate::mining::calculate_merkle_root_from_hashes(vec![
+            [0u8; 32],
+            crate::block::calculate_tx_id(&plain),
+            crate::block::calculate_wtxid(&witnessed, &[vec![vec![0x51], vec![0x52]]]),
+        ])
+        .unwrap();
+        assert_eq!(
+            compute_witness_merkle_root(&block, &witnesses).unwrap(),
+            expected
+        );
+
+        // The coinbase witness does not affect the root; other witnesses do
+        let mut other = witnesses.clone();
+        other[0] = vec![];
+        assert_eq!(
+            compute_witness_merkle_root(&block, &other).unwrap(),
+            expected
+        );
+        other[2] = vec![vec![vec![0x51]]];
+        assert_ne!(
+            compute_witness_merkle_root(&block, &other).unwrap(),
+            expected
+        );
+    }
+
     #[test]
     fn test_compute_witness_merkle_root_empty_block() {
         let block = Block {
@@ -292,20 +394,29 @@ mod tests {
     fn test_calculate_block_weight() {
         let block = create_test_block();
         let witnesses = vec![
-            vec![],           // Coinbase
-            vec![vec![0x51]], // First tx
+            vec![],                 // Coinbase
+            vec![vec![vec![0x51]]], // First tx
         ];
 
         let weight = calculate_block_weight(&block, &witnesses).unwrap();
         assert!(weight > 0);
     }
 
+    #[test]
+    fn test_calculate_block_weight_genesis() {
+        // The 204-byte genesis coinbase carries no witness: weight 4 × 204
+        let genesis = crate::chain_params::ChainParams::mainnet().genesis_block();
+        let weight = calculate_transaction_weight(&genesis.transactions[0], None).unwrap();
+        assert_eq!(weight, 816);
+        assert_eq!(calculate_block_weight(&genesis, &[]).unwrap(), 816);
+    }
+
     #[test]
     fn test_validate_segwit_block() {
         let block = create_test_block();
         let witnesses = vec![
-            vec![],           // Coinbase
-            vec![vec![0x51]], // First tx
+            vec![],                 // Coinbase
+            vec![vec![vec![0x51]]], // First tx
         ];
 
         let is_valid = validate_segwit_block(&block, &witnesses, 4_000_000).unwrap();
@@ -316,8 +427,8 @@ mod tests {
     fn test_validate_segwit_block_exceeds_weight() {
         let block = create_test_block();
         let witnesses = vec![
-            vec![],           // Coinbase
-            vec![vec![0x51]], // First tx
+            vec![],                 // Coinbase
+            vec![vec![vec![0x51]]], // First tx
         ];
 
         let is_valid = validate_segwit_block(&block, &witnesses, 1).unwrap(); // Very low weight limit
@@ -328,8 +439,8 @@ mod tests {
     fn test_validate_segwit_block_invalid_commitment() {
         let mut block = create_test_block();
         let witnesses = vec![
-            vec![],           // Coinbase
-            vec![vec![0x51]], // First tx
+            vec![],                 // Coinbase
+            vec![vec![vec![0x51]]], // First tx
         ];
 
         // Create coinbase with invalid witness commitment
@@ -401,24 +512,91 @@ mod tests {
     }
 
     #[test]
-    fn test_hash_witness() {
-        let witness = vec![vec![0x51], vec![0x52]];
-        let hash = hash_witness(&witness);
+    fn test_witness_commitment_script_layout() {
+        let commitment: Hash = core::array::from_fn(|i| i as u8);
+        let script = create_witness_commitment_script(&commitment);
+
+        // BIP141: 0x6a24aa21a9ed followed by the commitment
+        let mut expected = hex::decode("6a24aa21a9ed").unwrap();
+        expected.extend_from_slice(&commitment);
+        assert_eq!(script, expected);
+        assert_eq!(script.len(), MINIMUM_WITNESS_COMMITMENT);
 
-        assert_eq!(hash.len(), 32);
+        // Trailing bytes are ignored, as in Core
+        let mut extended = script.clone();
+        extended.extend_from_slice(&[0xff; 4]);
+        assert_eq!(extract_witness_commitment(&extended), Some(commitment));
 
-        // Different witness should produce different hash
-        let witness2 = vec![vec![0x53], vec![0x54]];
-        let hash2 = hash_witness(&witness2);
-        assert_ne!(hash, hash2);
+        // A 36-byte push without the header is not a commitment
+        let mut headerless = vec![0x6a, 0x24];
+        headerless.extend_from_slice(&commitment);
+        headerless.extend_from_slice(&[0x00; 4]);
+        assert_eq!(extract_witness_commitment(&headerless), None);
     }
 
     #[test]
-    fn test_hash_witness_empty() {
-        let witness = vec![];
-        let hash = hash_witness(&witness);
+    fn test_witness_commitment_of_signed_p2sh_p2wpkh_block() {
+        use crate::crypto::OptimizedSha256;
+        use crate::serialization::transaction::deserialize_transaction_with_witness;
+
+        // BIP143 "P2SH-P2WPKH" example, signed: one input spent by [sig, pubkey]
+        let raw = hex::decode(
+            "01000000000101db6b1b20aa0fd7b23880be2ecbd4a98130974cf4748fb66092ac4d3ceb1a5477010000\
+             001716001479091972186c449eb1ded22b78e40d009bdf0089feffffff02b8b4eb0b000000001976a914\
+             a457b684d7f0d539a46a45bbc043f35b59d0d96388ac0008af2f000000001976a914fd270b1ee6abcaea\
+             97fea7ad0402e8bd8ad6d77c88ac02473044022047ac8e878352d3ebbde1c94ce3a10d057c24175747\
+             116f8288e5d794d12d482f0220217f36a485cae903c713331d877c1f64677e3622ad4010726870540656\
+             fe9dcb012103ad1d8e89212f0b92c74d23bb710c00662ad1470198ac48c43f7d6f93a2a2687392040000",
+        )
+        .unwrap();
+        let (tx, stacks) = deserialize_transaction_with_witness(&raw).unwrap();
+        assert_eq!(stacks.len(), 1);
+        assert_eq!(stacks[0].len(), 2);
+
+        // The wtxid is the hash of the wire bytes, witness included
+        let wtxid = OptimizedSha256::new().hash256(&raw);
+        assert_eq!(crate::block::calculate_wtxid(&tx, &stacks), wtxid);
+        assert_ne!(wtxid, crate::block::calculate_tx_id(&tx));
 
-        assert_eq!(hash.len(), 32);
+        let block = Block {
+            header: create_test_header(),
+            transactions: vec![create_test_transaction(), tx].into_boxed_slice(),
+        };
+        let reserved_value = crate::mining::WITNESS_RESERVED_VALUE;
+        let witnesses = vec![vec![vec![reserved_value.to_vec()]], stacks.clone()];
+
+        // Root over [0, wtxid], commitment over root || reserved value
+        let mut pair = [0u8; 64];
+        pair[32..].copy_from_slice(&wtxid);
+        let witness_root = OptimizedSha256::new().hash256(&pair);
+        assert_eq!(
+            compute_witness_merkle_root(&block, &witnesses).unwrap(),
+            witness_root
+        );
+        let mut preimage = witness_root.to_vec();
+        preimage.extend_from_slice(&reserved_value);
+        let commitment = OptimizedSha256::new().hash256(&preimage);
+        assert_eq!(
+            compute_witness_commitment(&witness_root, &reserved_value),
+            commitment
+        );
+
+        let mut block = block;
+        block.transactions[0].outputs[0].script_pubkey =
+            create_witness_commitment_script(&commitment);
+        assert!(validate_block_witness_commitment(&block, &witnesses).unwrap());
+
+        // Each input's stack is hashed whole: dropping the pubkey breaks it
+        let mut truncated = witnesses.clone();
+        truncated[1][0].truncate(1);
+        assert!(!validate_block_witness_commitment(&block, &truncated).unwrap());
+
+        // The coinbase witness must be the 32-byte reserved value
+        let mut no_reserved = witnesses.clone();
+        no_reserved[0] = vec![vec![]];
+        assert!(!validate_block_witness_commitment(&block, &no_reserved).unwrap());
+        no_reserved[0] = vec![vec![vec![0; 31]]];
+        assert!(!validate_block_witness_commitment(&block, &no_reserved).unwrap());
     }
 
     #[test]
@@ -456,7 +634,7 @@ mod tests {
     #[test]
     fn test_calculate_total_size_with_witness() {
         let tx = create_test_transaction();
-        let witness = vec![vec![0x51], vec![0x52]];
+        let witness = vec![vec![vec![0x51], vec![0x52]]];
 
         let total_size = calculate_total_size(&tx, Some(&witness));
         let base_size = calculate_base_size(&tx);
@@ -517,14 +695,6 @@ mod tests {
             nonce: 0,
         }
     }
-
-    fn create_witness_commitment_script(commitment: &Hash) -> ByteString {
-        let mut script = vec![0x6a, 0x24]; // OP_RETURN, 36 bytes
-        script.extend_from_slice(commitment);
-        // Add 4 more bytes to make it 38 bytes total as expected by extract_witness_commitment
-        script.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]);
-        script
-    }
 }
 
 #[cfg(kani)]
@@ -535,7 +705,7 @@ mod kani_proofs {
     /// Verify calculate_transaction_weight formula is correct
     ///
     /// Mathematical specification:
-    /// Weight(tx) = 4 × |Serialize(tx ∖ witness)| + |Serialize(tx)|
+    /// Weight(tx) = 3 × |Serialize(tx ∖ witness)| + |Serialize(tx)|
     /// ∀ tx ∈ Transaction, witness ∈ Option<Witness>:
     /// - Weight ≥ 0 (non-negative)
     /// - Weight formula is correctly applied
@@ -561,15 +731,16 @@ mod kani_proofs {
             None
         };
 
-        let weight = calculate_transaction_weight(&tx, witness.as_ref()).unwrap();
+        let weight =
+            calculate_transaction_weight(&tx, witness.as_ref().map(std::slice::from_ref)).unwrap();
 
         // Weight should be non-negative
         assert!(weight >= 0);
 
-        // Weight should follow the formula: 4 * base_size + total_size
+        // Weight should follow the formula: 3 * base_size + total_size
         let base_size = calculate_base_size(&tx);
-        let total_size = calculate_total_size(&tx, witness.as_ref());
-        let expected_weight = 4 * base_size + total_size;
+        let total_size = calculate_total_size(&tx, witness.as_ref().map(std::slice::from_ref));
+        let expected_weight = 3 * base_size + total_size;
         assert_eq!(weight, expected_weight);
     }
 
@@ -637,8 +808,11 @@ mod kani_proofs {
             }
         };
 
-        let witnesses = if has_transactions {
+        let witnesses: Vec<Vec<Witness>> = if has_transactions {
             create_bounded_witnesses(&block)
+                .into_iter()
+                .map(|w| vec![w])
+                .collect()
         } else {
             vec![]
         };
@@ -661,7 +835,7 @@ mod kani_proofs {
     /// Mathematical specification:
     /// ∀ tx ∈ Transaction, witness ∈ Option<Witness>:
     /// - calculate_transaction_weight(tx, witness) ≥ 0
-    /// - Weight follows formula: 4 × base_size + total_size
+    /// - Weight follows formula: 3 × base_size + total_size
     /// - Weight is bounded by transaction structure
     ///
     /// This ensures weight calculations are always valid.
@@ -689,7 +863,8 @@ mod kani_proofs {
             None
         };
 
-        let weight_result = calculate_transaction_weight(&tx, witness.as_ref());
+        let weight_result =
+            calculate_transaction_weight(&tx, witness.as_ref().map(std::slice::from_ref));
 
         // Weight calculation should always succeed
         assert!(weight_result.is_ok(), "Weight calculation must succeed");
@@ -701,11 +876,11 @@ mod kani_proofs {
 
         // Weight must follow the formula
         let base_size = calculate_base_size(&tx);
-        let total_size = calculate_total_size(&tx, witness.as_ref());
-        let expected_weight = 4 * base_size + total_size;
+        let total_size = calculate_total_size(&tx, witness.as_ref().map(std::slice::from_ref));
+        let expected_weight = 3 * base_size + total_size;
         assert_eq!(
             weight, expected_weight,
-            "Weight must follow formula: 4*base + total"
+            "Weight must follow formula: 3*base + total"
         );
 
         // Weight must be bounded by reasonable limits
@@ -869,7 +1044,7 @@ mod property_tests {
             tx in create_transaction_strategy(),
             witness in prop::option::of(create_witness_strategy())
         ) {
-            let _weight = calculate_transaction_weight(&tx, witness.as_ref()).unwrap();
+            let _weight = calculate_transaction_weight(&tx, witness.as_ref().map(std::slice::from_ref)).unwrap();
             // Weight is always non-negative (Natural type) - verified by type system
         }
     }
@@ -878,17 +1053,17 @@ mod property_tests {
     ///
     /// Mathematical specification:
     /// ∀ tx ∈ Transaction, witness ∈ Option<Witness>:
-    /// Weight(tx) = 4 × base_size + total_size
+    /// Weight(tx) = 3 × base_size + total_size
     proptest! {
         #[test]
         fn prop_transaction_weight_formula(
             tx in create_transaction_strategy(),
             witness in prop::option::of(create_witness_strategy())
         ) {
-            let weight = calculate_transaction_weight(&tx, witness.as_ref()).unwrap();
+            let weight = calculate_transaction_weight(&tx, witness.as_ref().map(std::slice::from_ref)).unwrap();
             let base_size = calculate_base_size(&tx);
-            let total_size = calculate_total_size(&tx, witness.as_ref());
-            let expected_weight = 4 * base_size + total_size;
+            let total_size = calculate_total_size(&tx, witness.as_ref().map(std::slice::from_ref));
+            let expected_weight = 3 * base_size + total_size;
 
             assert_eq!(weight, expected_weight);
         }
@@ -906,6 +1081,7 @@ mod property_tests {
             witnesses in create_witnesses_strategy(),
             max_weight in 1..10_000_000u64
         ) {
+            let witnesses: Vec<Vec<Witness>> = witnesses.into_iter().map(|w| vec![w]).collect();
             // Handle errors from invalid blocks/witnesses
             match (calculate_block_weight(&block, &witnesses), validate_segwit_block(&block, &witnesses, max_weight as Natural)) {
                 (Ok(actual_weight), Ok(is_valid)) => {
@@ -951,6 +1127,7 @@ mod property_tests {
             witnesses in create_witnesses_strategy()
         ) {
             if !block.transactions.is_empty() {
+                let witnesses: Vec<Vec<Witness>> = witnesses.into_iter().map(|w| vec![w]).collect();
                 let result1 = compute_witness_merkle_root(&block, &witnesses);
                 let result2 = compute_witness_merkle_root(&block, &witnesses);
 
@@ -977,17 +1154,19 @@ mod property_tests {
         }
     }
 
-    /// Property test: witness hashing is deterministic
+    /// Property test: wtxid computation is deterministic
     ///
     /// Mathematical specification:
-    /// ∀ witness ∈ Witness: hash_witness(witness) is deterministic
+    /// ∀ witness ∈ Witness: calculate_wtxid(tx, [witness]) is deterministic
     proptest! {
         #[test]
         fn prop_witness_hashing_deterministic(
+            tx in create_transaction_strategy(),
             witness in create_witness_strategy()
         ) {
-            let hash1 = hash_witness(&witness);
-            let hash2 = hash_witness(&witness);
+            let stacks = std::slice::from_ref(&witness);
+            let hash1 = crate::block::calculate_wtxid(&tx, stacks);
+            let hash2 = crate::block::calculate_wtxid(&tx, stacks);
 
             assert_eq!(hash1, hash2);
             assert_eq!(hash1.len(), 32);
@@ -1071,7 +1250,7 @@ mod property_tests {
             witness in create_witness_strategy()
         ) {
             let base_size = calculate_base_size(&tx);
-            let total_size = calculate_total_size(&tx, Some(&witness));
+            let total_size = calculate_total_size(&tx, Some(std::slice::from_ref(&witness)));
 
             assert!(total_size >= base_size);
         }
@@ -1196,7 +1375,7 @@ mod kani_proofs_2 {
     ///
     /// Mathematical specification:
     /// ∀ tx ∈ Transaction, witness ∈ Option<Witness>:
-    /// - Weight(tx) = 4 × base_size(tx) + total_size(tx, witness)
+    /// - Weight(tx) = 3 × base_size(tx) + total_size(tx, witness)
     ///
     /// This proves the weight calculation matches the Orange Paper specification exactly.
     #[kani::proof]
@@ -1213,20 +1392,21 @@ mod kani_proofs_2 {
         }
 
         // Calculate weight
-        let weight_result = calculate_transaction_weight(&tx, witness.as_ref());
+        let weight_result =
+            calculate_transaction_weight(&tx, witness.as_ref().map(std::slice::from_ref));
 
         if weight_result.is_ok() {
             let weight = weight_result.unwrap();
 
             // Calculate base size and total size
             let base_size = calculate_base_size(&tx);
-            let total_size = calculate_total_size(&tx, witness.as_ref());
+            let total_size = calculate_total_size(&tx, witness.as_ref().map(std::slice::from_ref));
 
-            // Weight formula: Weight = 4 × base_size + total_size
-            let expected_weight = (4 * base_size) + total_size;
+            // Weight formula: Weight = 3 × base_size + total_size
+            let expected_weight = (3 * base_size) + total_size;
 
             assert_eq!(weight, expected_weight,
-                "Transaction weight calculation must match Orange Paper: Weight = 4 × base_size + total_size");
+                "Transaction weight calculation must match Orange Paper: Weight = 3 × base_size + total_size");
 
             // Weight must be positive
             assert!(weight > 0, "Transaction weight must be positive");
@@ -1255,13 +1435,14 @@ mod kani_proofs_2 {
         }
 
         // Calculate weight
-        let weight_result = calculate_transaction_weight(&tx, witness.as_ref());
+        let weight_result =
+            calculate_transaction_weight(&tx, witness.as_ref().map(std::slice::from_ref));
 
         if weight_result.is_ok() {
             let weight = weight_result.unwrap();
 
             // Maximum transaction weight is 400,000 weight units (equivalent to 1MB base size)
-            // Weight = 4 × base_size + total_size, so max weight ≈ 4 × 1MB + 1MB = 5MB
+            // Weight = 3 × base_size + total_size, so max weight ≈ 3 × 1MB + 1MB = 4MB
             // But for practical purposes, we enforce weight ≤ 400,000
             let max_weight = 400_000u64;
 
@@ -1346,7 +1527,7 @@ mod kani_proofs_2 {
         }
 
         let base_size = calculate_base_size(&tx);
-        let total_size = calculate_total_size(&tx, witness.as_ref());
+        let total_size = calculate_total_size(&tx, witness.as_ref().map(std::slice::from_ref));
 
         // Critical invariant: total_size >= base_size (witness adds to size)
         assert!(
@@ -1426,6 +1607,9 @@ mod kani_proofs_2 {
         kani::assume(witnesses1.len() <= 5);
         kani::assume(witnesses2.len() <= 5);
 
+        let witnesses1: Vec<Vec<Witness>> = witnesses1.into_iter().map(|w| vec![w]).collect();
+        let witnesses2: Vec<Vec<Witness>> = witnesses2.into_iter().map(|w| vec![w]).collect();
+
         let root1_result = compute_witness_merkle_root(&block, &witnesses1);
         let root2_result = compute_witness_merkle_root(&block, &witnesses2);
 
@@ -30,7 +30,7 @@ const SEQUENCE_LOCKTIME_MASK: u32 = 0x0000ffff;
 
 /// Sequence locktime granularity (for time-based locks)
 /// Time-based locks are measured in 512-second intervals
-const SEQUENCE_LOCKTIME_GRANULARITY: u32 = 9; // 2^9 = 512 seconds
+pub(crate) const SEQUENCE_LOCKTIME_GRANULARITY: u32 = 9; // 2^9 = 512 seconds
 
 /// Locktime verify sequence flag
 /// Must be set to enable BIP68 sequence lock enforcement
@@ -252,6 +252,50 @@ pub fn sequence_locks(
     Ok(evaluate_sequence_locks(block_height, block_time, lock_pair))
 }
 
+/// Check both the absolute (nLockTime) and relative (BIP68) locks of a transaction
+///
+/// Returns false if either lock is unsatisfied at `block_height` /
+/// `block_mtp`. Input confirmation heights are taken from `utxo_set`; a
+/// missing input is an error. Coinbase transactions have no relative locks.
+pub fn check_all_locks(
+    tx: &Transaction,
+    utxo_set: &UtxoSet,
+    block_height: Natural,
+    block_mtp: Natural,
+) -> Result<bool> {
+    if !crate::transaction::is_final_tx(tx, block_height, block_mtp) {
+        return Ok(false);
+    }
+    if crate::transaction::is_coinbase(tx) {
+        return Ok(true);
+    }
+
+    let prev_heights = tx
+        .inputs
+        .iter()
+        .enumerate()
+        .map(|(i, input)| {
+            utxo_set
+                .get(&input.prevout)
+                .map(|utxo| utxo.height)
+                .ok_or_else(|| {
+                    crate::error::ConsensusError::UtxoNotFound(
+                        format!("Input {i} spends missing output").into(),
+                    )
+                })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    sequence_locks(
+        tx,
+        LOCKTIME_VERIFY_SEQUENCE,
+        &prev_heights,
+        block_height,
+        block_mtp,
+        None,
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -321,6 +365,47 @@ mod tests {
         // Block height 1098 does not satisfy
         assert!(!evaluate_sequence_locks(1098, 0, lock_pair));
     }
+
+    #[test]
+    fn test_check_all_locks() {
+        let prevout = OutPoint {
+            hash: [1; 32],
+            index: 0,
+        };
+        let mut utxo_set = UtxoSet::new();
+        utxo_set.insert(
+            prevout.clone(),
+            UTXO {
+                value: 1000,
+                script_pubkey: vec![0x51],
+                height: 1000,
+                is_coinbase: false,
+            },
+        );
+        let tx = |lock_time, sequence| Transaction {
+            version: 2,
+            inputs: crate::tx_inputs![TransactionInput {
+                prevout: prevout.clone(),
+                script_sig: vec![],
+                sequence,
+            }],
+            outputs: crate::tx_outputs![],
+            lock_time,
+        };
+
+        // Both locks satisfied: height lock 1050, 10-block relative lock
+        assert!(check_all_locks(&tx(1050, 10), &utxo_set, 1100, 0).unwrap());
+
+        // Only the absolute lock fails
+        assert!(!check_all_locks(&tx(1200, 10), &utxo_set, 1100, 0).unwrap());
+
+        // Only the relative lock fails: needs height > 1000 + 200 - 1
+        assert!(!check_all_locks(&tx(1050, 200), &utxo_set, 1100, 0).unwrap());
+
+        // Missing input
+        utxo_set.clear();
+        assert!(check_all_locks(&tx(0, 10), &utxo_set, 1100, 0).is_err());
+    }
 }
 
 #[cfg(kani)]
@@ -3,7 +3,7 @@
 //! Bitcoin block header wire format specification.
 //! Must match Bitcoin Core's serialization exactly for consensus compatibility.
 
-use super::transaction::{deserialize_transaction, serialize_transaction};
+use super::transaction::parse_transaction;
 use super::varint::decode_varint;
 use crate::error::{ConsensusError, Result};
 use crate::segwit::Witness;
@@ -61,6 +61,14 @@ pub fn serialize_block_header(header: &BlockHeader) -> Vec<u8> {
     result
 }
 
+/// Block hash: double SHA256 of the 80-byte serialized header
+///
+/// This is the hash in internal byte order; block explorers display it
+/// reversed.
+pub fn block_hash(header: &BlockHeader) -> Hash {
+    crate::crypto::hash256(&serialize_block_header(header))
+}
+
 /// Deserialize a block header from Bitcoin wire format
 pub fn deserialize_block_header(data: &[u8]) -> Result<BlockHeader> {
     // Block header must be exactly 80 bytes
@@ -127,50 +135,18 @@ pub fn deserialize_block_header(data: &[u8]) -> Result<BlockHeader> {
     })
 }
 
-/// Parse witness data from Bitcoin wire format
-///
-/// Witness format for each transaction:
-/// - VarInt: witness stack count
-/// - For each witness element:
-///   - VarInt: element length
-///   - Element bytes
-fn parse_witness(data: &[u8], mut offset: usize) -> Result<(Witness, usize)> {
-    // Witness stack count (VarInt)
-    let (stack_count, varint_len) = decode_varint(&data[offset..])?;
-    offset += varint_len;
-
-    let mut witness = Witness::new();
-
-    for _ in 0..stack_count {
-        // Witness element length (VarInt)
-        let (element_len, varint_len) = decode_varint(&data[offset..])?;
-        offset += varint_len;
-
-        if data.len() < offset + element_len as usize {
-            return Err(ConsensusError::Serialization(Cow::Owned(
-                BlockParseError::InsufficientBytes.to_string(),
-            )));
-        }
-
-        // Witness element bytes
-        let element = data[offset..offset + element_len as usize].to_vec();
-        witness.push(element);
-        offset += element_len as usize;
-    }
-
-    Ok((witness, offset))
-}
-
 /// Deserialize a complete block from Bitcoin wire format (including witness data)
 ///
 /// Format:
 /// - Block header (80 bytes)
 /// - VarInt: transaction count
-/// - For each transaction:
-///   - Transaction (non-witness serialization)
-/// - If SegWit block (marker 0x0001 found):
-///   - Witness data for each transaction
-pub fn deserialize_block_with_witnesses(data: &[u8]) -> Result<(Block, Vec<Witness>)> {
+/// - Each transaction in BIP144 serialization: transactions with witness data
+///   carry the 0x00 0x01 marker and flag, and their witness stacks follow the
+///   outputs, one stack per input
+///
+/// Returns the block and, for each transaction, its per-input witness stacks
+/// (empty stacks for transactions without witness data).
+pub fn deserialize_block_with_witnesses(data: &[u8]) -> Result<(Block, Vec<Vec<Witness>>)> {
     if data.len() < 80 {
         return Err(ConsensusError::Serialization(Cow::Owned(
             BlockParseError::InsufficientBytes.to_string(),
@@ -187,7 +163,9 @@ pub fn deserialize_block_with_witnesses(data: &[u8]) -> Result<(Block, Vec<Witne
     let (tx_count, varint_len) = decode_varint(&data[offset..])?;
     offset += varint_len;
 
-    if tx_count == 0 {
+    // Reject counts that could not fit in MAX_BLOCK_WEIGHT before parsing any
+    // transaction, so a bogus count cannot drive per-transaction work
+    if tx_count == 0 || tx_count > crate::constants::MAX_BLOCK_TRANSACTIONS as u64 {
         return Err(ConsensusError::Serialization(Cow::Owned(
             BlockParseError::InvalidTransactionCount.to_string(),
         )));
@@ -196,59 +174,14 @@ pub fn deserialize_block_with_witnesses(data: &[u8]) -> Result<(Block, Vec<Witne
     let mut transactions = Vec::new();
     let mut witnesses = Vec::new();
 
-    // Check for SegWit marker (0x0001) after transaction count
-    // In SegWit blocks, the transaction count is followed by 0x00 0x01 (witness marker)
-    let is_segwit = if data.len() >= offset + 2 {
-        data[offset] == 0x00 && data[offset + 1] == 0x01
-    } else {
-        false
-    };
-
-    if is_segwit {
-        offset += 2; // Skip witness marker
-    }
-
-    // Parse transactions (non-witness serialization)
-    // Use deserialize_transaction which handles the format correctly
-    // Then calculate size by re-serializing (inefficient but correct)
+    // Each transaction is parsed from the front of the remaining bytes, and the
+    // parser reports how many bytes it consumed
     for _ in 0..tx_count {
-        // Parse transaction
-        let tx = deserialize_transaction(&data[offset..])?;
-
-        // Calculate size by serializing back (to know where witness data starts)
-        // Note: This is inefficient but ensures correctness
-        // In production, we'd track the size during parsing
-        let tx_serialized = serialize_transaction(&tx);
-        offset += tx_serialized.len();
+        let (tx, tx_witnesses, consumed) = parse_transaction(&data[offset..])?;
+        offset += consumed;
 
         transactions.push(tx);
-    }
-
-    // Parse witness data if SegWit block
-    // In Bitcoin wire format, witness data comes after ALL transactions
-    // Each transaction's witness is parsed sequentially
-    if is_segwit {
-        for _ in 0..tx_count {
-            if offset >= data.len() {
-                // No more witness data - create empty witness
-                witnesses.push(Witness::new());
-                continue;
-            }
-
-            let (witness, new_offset) = parse_witness(data, offset)?;
-            witnesses.push(witness);
-            offset = new_offset;
-        }
-    } else {
-        // Non-SegWit block: all witnesses are empty
-        for _ in 0..tx_count {
-            witnesses.push(Witness::new());
-        }
-    }
-
-    // Ensure we have witnesses for all transactions
-    while witnesses.len() < transactions.len() {
-        witnesses.push(Witness::new());
+        witnesses.push(tx_witnesses);
     }
 
     Ok((
@@ -264,6 +197,17 @@ pub fn deserialize_block_with_witnesses(data: &[u8]) -> Result<(Block, Vec<Witne
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_block_hash_mainnet_genesis() {
+        let genesis = crate::chain_params::ChainParams::mainnet().genesis_block();
+        let mut hash = block_hash(&genesis.header);
+        hash.reverse();
+        assert_eq!(
+            hex::encode(hash),
+            "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"
+        );
+    }
+
     #[test]
     fn test_serialize_deserialize_block_header() {
         let header = BlockHeader {
@@ -293,6 +237,59 @@ mod tests {
         let result = deserialize_block_header(&data);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_deserialize_block_with_per_input_witness_stacks() {
+        use super::super::transaction::{
+            serialize_transaction, serialize_transaction_with_witness,
+        };
+        use super::super::varint::encode_varint;
+
+        let genesis = crate::chain_params::ChainParams::mainnet().genesis_block();
+        let coinbase = genesis.transactions[0].clone();
+        let mut spend = coinbase.clone();
+        spend.inputs[0].prevout.index = 0;
+        spend.inputs.push(spend.inputs[0].clone());
+        // A witness on the second input only: the first input's stack is empty
+        let stacks: Vec<Witness> = vec![vec![], vec![vec![0x30; 71], vec![0x02; 33]]];
+
+        let mut data = serialize_block_header(&genesis.header);
+        data.extend_from_slice(&encode_varint(2));
+        data.extend_from_slice(&serialize_transaction(&coinbase));
+        data.extend_from_slice(&serialize_transaction_with_witness(&spend, &stacks));
+
+        let (block, witnesses) = deserialize_block_with_witnesses(&data).unwrap();
+        assert_eq!(block.transactions.len(), 2);
+        assert_eq!(block.transactions[1], spend);
+        assert_eq!(witnesses, vec![vec![vec![]], stacks]);
+    }
+
+    #[test]
+    fn test_deserialize_block_rejects_impossible_transaction_count() {
+        use super::super::varint::encode_varint;
+        use crate::constants::MAX_BLOCK_TRANSACTIONS;
+
+        let header = BlockHeader {
+            version: 1,
+            prev_block_hash: [0; 32],
+            merkle_root: [0; 32],
+            timestamp: 1231006505,
+            bits: 0x1d00ffff,
+            nonce: 0,
+        };
+        let mut data = serialize_block_header(&header);
+        data.extend_from_slice(&encode_varint(MAX_BLOCK_TRANSACTIONS as u64 + 1));
+        data.extend_from_slice(&[0u8; 64]);
+
+        let err = deserialize_block_with_witnesses(&data).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            ConsensusError::Serialization(Cow::Owned(
+                BlockParseError::InvalidTransactionCount.to_string()
+            ))
+            .to_string()
+        );
+    }
 }
 
 #[cfg(kani)]
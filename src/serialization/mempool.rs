@@ -0,0 +1,231 @@
+//! Mempool persistence
+//!
+//! Serializes the transactions stored in a `Mempool` so the pool survives a
+//! restart. The format is a VarInt format version (`MEMPOOL_DUMP_VERSION`), a
+//! VarInt entry count, and one record per entry:
+//!
+//! - VarInt-length-prefixed transaction (`serialize_transaction`)
+//! - VarInt count of witness stacks, each a VarInt item count followed by
+//!   VarInt-length-prefixed items
+//! - entry time (8 bytes, little-endian)
+//! - fee (8 bytes, little-endian)
+//!
+//! Records are written oldest first, with every in-pool parent ahead of its
+//! children, so `read_mempool` can re-validate each entry against the pool
+//! rebuilt so far. Entries that are no longer acceptable (spent inputs,
+//! changed policy) are dropped on load rather than failing it.
+
+use super::transaction::{deserialize_transaction, serialize_transaction};
+use super::utxo::{io_error, read_array, read_varint};
+use super::varint::encode_varint;
+use crate::error::{ConsensusError, Result};
+use crate::mempool::{accept_to_memory_pool, Mempool, MempoolEntry, MempoolResult};
+use crate::types::*;
+use crate::witness::Witness;
+use std::collections::HashSet;
+use std::io::{Read, Write};
+
+/// Version written at the start of a mempool dump
+pub const MEMPOOL_DUMP_VERSION: u64 = 1;
+
+/// Write the stored entries of `mempool` to `writer`
+pub fn write_mempool<W: Write>(mempool: &Mempool, writer: &mut W) -> Result<()> {
+    let entries = entries_in_load_order(mempool);
+    writer
+        .write_all(&encode_varint(MEMPOOL_DUMP_VERSION))
+        .map_err(io_error)?;
+    writer
+        .write_all(&encode_varint(entries.len() as u64))
+        .map_err(io_error)?;
+
+    let mut record = Vec::new();
+    for entry in entries {
+        record.clear();
+        encode_entry_record(&mut record, entry);
+        writer.write_all(&record).map_err(io_error)?;
+    }
+
+    writer.flush().map_err(io_error)?;
+    Ok(())
+}
+
+/// Load a dump written by `write_mempool`, re-validating every entry
+///
+/// Each transaction is passed through `accept_to_memory_pool` against
+/// `utxo_set` at `height` and the entries already reloaded; those that are
+/// rejected are dropped. Malformed dumps are an error.
+pub fn read_mempool<R: Read>(
+    reader: &mut R,
+    utxo_set: &UtxoSet,
+    height: Natural,
+) -> Result<Mempool> {
+    let version = read_varint(reader)?;
+    if version != MEMPOOL_DUMP_VERSION {
+        return Err(ConsensusError::Serialization(
+            format!("Unsupported mempool dump version {version}").into(),
+        ));
+    }
+
+    let count = read_varint(reader)?;
+    let mut mempool = Mempool::new();
+    for _ in 0..count {
+        let tx = deserialize_transaction(&read_bytes(reader)?)?;
+        let stack_count = read_varint(reader)?;
+        let mut witnesses = Vec::new();
+        for _ in 0..stack_count {
+            let item_count = read_varint(reader)?;
+            let mut witness = Witness::new();
+            for _ in 0..item_count {
+                witness.push(read_bytes(reader)?);
+            }
+            witnesses.push(witness);
+        }
+        let entry_time = u64::from_le_bytes(read_array(reader)?);
+        let fee = i64::from_le_bytes(read_array(reader)?);
+
+        let witness_arg = (!witnesses.is_empty()).then_some(witnesses.as_slice());
+        if let Ok(MempoolResult::Accepted { .. }) =
+            accept_to_memory_pool(&tx, witness_arg, utxo_set, &mempool, height)
+        {
+            mempool.add_transaction_at(tx, witnesses, fee, entry_time);
+        }
+    }
+
+    Ok(mempool)
+}
+
+/// Entries ordered by (entry time, txid), with in-pool parents moved ahead
+/// of their children
+fn entries_in_load_order(mempool: &Mempool) -> Vec<&MempoolEntry> {
+    let mut by_age: Vec<&MempoolEntry> = mempool.entries().collect();
+    by_age.sort_by_key(|entry| (entry.entry_time, entry.txid));
+
+    let mut ordered = Vec::with_capacity(by_age.len());
+    let mut visited = HashSet::new();
+    for entry in by_age {
+        push_with_parents(mempool, entry, &mut visited, &mut ordered);
+    }
+    ordered
+}
+
+fn push_with_parents<'a>(
+    mempool: &'a Mempool,
+    entry: &'a MempoolEntry,
+    visited: &mut HashSet<Hash>,
+    ordered: &mut Vec<&'a MempoolEntry>,
+) {
+    if !visited.insert(entry.txid) {
+        return;
+    }
+    for input in entry.tx.inputs.iter() {
+        if let Some(parent) = mempool.get(&input.prevout.hash) {
+            push_with_parents(mempool, parent, visited, ordered);
+        }
+    }
+    ordered.push(entry);
+}
+
+fn encode_entry_record(out: &mut Vec<u8>, entry: &MempoolEntry) {
+    let tx_bytes = serialize_transaction(&entry.tx);
+    out.extend_from_slice(&encode_varint(tx_bytes.len() as u64));
+    out.extend_from_slice(&tx_bytes);
+    out.extend_from_slice(&encode_varint(entry.witnesses.len() as u64));
+    for witness in entry.witnesses.iter() {
+        out.extend_from_slice(&encode_varint(witness.len() as u64));
+        for item in witness.iter() {
+            out.extend_from_slice(&encode_varint(item.len() as u64));
+            out.extend_from_slice(item);
+        }
+    }
+    out.extend_from_slice(&entry.entry_time.to_le_bytes());
+    out.extend_from_slice(&entry.fee.to_le_bytes());
+}
+
+fn read_bytes<R: Read>(reader: &mut R) -> Result<ByteString> {
+    let len = read_varint(reader)?;
+    let mut bytes = Vec::new();
+    reader
+        .by_ref()
+        .take(len)
+        .read_to_end(&mut bytes)
+        .map_err(io_error)?;
+    if bytes.len() as u64 != len {
+        return Err(io_error(std::io::ErrorKind::UnexpectedEof.into()));
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spend(prevout: OutPoint, value: Integer) -> Transaction {
+        Transaction {
+            version: 1,
+            inputs: crate::tx_inputs![TransactionInput {
+                prevout,
+                script_sig: vec![0x51],
+                sequence: 0xffffffff,
+            }],
+            outputs: crate::tx_outputs![TransactionOutput {
+                value,
+                script_pubkey: vec![0x51, 0x87], // OP_1 OP_EQUAL
+            }],
+            lock_time: 0,
+        }
+    }
+
+    fn coin(hash: Hash) -> (OutPoint, UTXO) {
+        (
+            OutPoint { hash, index: 0 },
+            UTXO {
+                value: 10000,
+                script_pubkey: vec![0x51, 0x87],
+                height: 0,
+                is_coinbase: false,
+            },
+        )
+    }
+
+    #[test]
+    fn test_mempool_round_trip() {
+        let utxo_set: UtxoSet = [coin([1; 32]), coin([2; 32])].into_iter().collect();
+        let mut mempool = Mempool::new();
+
+        let parent = spend(coin([1; 32]).0, 8000);
+        let (parent_txid, _) = mempool.add_transaction_at(parent, vec![], 2000, 1_700_000_200);
+        // Older than its parent, but must still be written after it
+        let child = spend(
+            OutPoint {
+                hash: parent_txid,
+                index: 0,
+            },
+            7000,
+        );
+        let (child_txid, _) = mempool.add_transaction_at(child, vec![], 1000, 1_700_000_100);
+        let other = spend(coin([2; 32]).0, 9000);
+        let (other_txid, _) = mempool.add_transaction_at(other, vec![vec![]], 1000, 1_700_000_300);
+
+        let mut dump = Vec::new();
+        mempool.serialize(&mut dump).unwrap();
+        let reloaded = Mempool::deserialize(&mut dump.as_slice(), &utxo_set, 100).unwrap();
+        assert_eq!(reloaded, mempool);
+        assert_eq!(reloaded.get(&child_txid).unwrap().entry_time, 1_700_000_100);
+        assert_eq!(reloaded.get(&other_txid).unwrap().fee, 1000);
+
+        // The parent's coin was spent while the node was down: parent and
+        // child are dropped, the unrelated entry survives
+        let mut spent = utxo_set.clone();
+        spent.remove(&coin([1; 32]).0);
+        let reloaded = Mempool::deserialize(&mut dump.as_slice(), &spent, 100).unwrap();
+        assert_eq!(reloaded.len(), 1);
+        assert!(reloaded.contains(&other_txid));
+        assert!(!reloaded.contains(&parent_txid));
+
+        // Truncated and unknown-version dumps are errors
+        assert!(Mempool::deserialize(&mut &dump[..dump.len() - 1], &utxo_set, 100).is_err());
+        let mut future = dump.clone();
+        future[0] = 2;
+        assert!(Mempool::deserialize(&mut future.as_slice(), &utxo_set, 100).is_err());
+    }
+}
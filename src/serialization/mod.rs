@@ -6,11 +6,20 @@
 //! All serialization uses little-endian byte order (Bitcoin standard).
 
 pub mod block;
+pub mod mempool;
 pub mod transaction;
+pub mod utxo;
 pub mod varint;
+pub mod vector;
 
 pub use block::{
-    deserialize_block_header, deserialize_block_with_witnesses, serialize_block_header,
+    block_hash, deserialize_block_header, deserialize_block_with_witnesses, serialize_block_header,
 };
-pub use transaction::{deserialize_transaction, serialize_transaction};
+pub use mempool::{read_mempool, write_mempool, MEMPOOL_DUMP_VERSION};
+pub use transaction::{
+    deserialize_transaction, deserialize_transaction_prefix, deserialize_transaction_with_witness,
+    serialize_transaction, serialize_transaction_with_witness,
+};
+pub use utxo::{read_utxo_snapshot, serialize_utxo_set, write_utxo_snapshot};
 pub use varint::{decode_varint, encode_varint, VarIntError};
+pub use vector::{read_vec, write_vec, MAX_VEC_ELEMENTS};
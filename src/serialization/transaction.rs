@@ -4,8 +4,10 @@
 //! Must match Bitcoin Core's serialization exactly for consensus compatibility.
 
 use super::varint::{decode_varint, encode_varint};
+use super::vector::{read_vec, write_vec, MAX_VEC_ELEMENTS};
 use crate::error::{ConsensusError, Result};
 use crate::types::*;
+use crate::witness::Witness;
 use std::borrow::Cow;
 
 #[cfg(feature = "production")]
@@ -85,6 +87,9 @@ pub enum TransactionParseError {
     InvalidOutputCount,
     InvalidScriptLength,
     InvalidLockTime,
+    TrailingBytes,
+    UnknownWitnessFlag,
+    SuperfluousWitness,
 }
 
 impl std::fmt::Display for TransactionParseError {
@@ -98,6 +103,15 @@ impl std::fmt::Display for TransactionParseError {
             TransactionParseError::InvalidOutputCount => write!(f, "Invalid output count"),
             TransactionParseError::InvalidScriptLength => write!(f, "Invalid script length"),
             TransactionParseError::InvalidLockTime => write!(f, "Invalid lock time"),
+            TransactionParseError::TrailingBytes => {
+                write!(f, "Trailing bytes after transaction")
+            }
+            TransactionParseError::UnknownWitnessFlag => {
+                write!(f, "Unknown transaction optional data")
+            }
+            TransactionParseError::SuperfluousWitness => {
+                write!(f, "Witness flag set but all witnesses are empty")
+            }
         }
     }
 }
@@ -177,41 +191,31 @@ fn serialize_transaction_inner(tx: &Transaction) -> Vec<u8> {
     // Version (4 bytes, little-endian) - Bitcoin uses signed 32-bit in wire format
     result.extend_from_slice(&(tx.version as i32).to_le_bytes());
 
-    // Input count (VarInt)
-    result.extend_from_slice(&encode_varint(tx.inputs.len() as u64));
-
-    // Inputs
-    for input in &tx.inputs {
+    // Inputs (VarInt count + inputs)
+    write_vec(&mut result, &tx.inputs, |out, input| {
         // Previous output hash (32 bytes)
-        result.extend_from_slice(&input.prevout.hash);
+        out.extend_from_slice(&input.prevout.hash);
 
         // Previous output index (4 bytes, little-endian) - Bitcoin uses u32 in wire format
-        result.extend_from_slice(&(input.prevout.index as u32).to_le_bytes());
-
-        // Script length (VarInt)
-        result.extend_from_slice(&encode_varint(input.script_sig.len() as u64));
+        out.extend_from_slice(&(input.prevout.index as u32).to_le_bytes());
 
-        // Script bytes
-        result.extend_from_slice(&input.script_sig);
+        // Script length (VarInt) + script bytes
+        out.extend_from_slice(&encode_varint(input.script_sig.len() as u64));
+        out.extend_from_slice(&input.script_sig);
 
         // Sequence (4 bytes, little-endian) - Bitcoin uses u32 in wire format
-        result.extend_from_slice(&(input.sequence as u32).to_le_bytes());
-    }
-
-    // Output count (VarInt)
-    result.extend_from_slice(&encode_varint(tx.outputs.len() as u64));
+        out.extend_from_slice(&(input.sequence as u32).to_le_bytes());
+    });
 
-    // Outputs
-    for output in &tx.outputs {
+    // Outputs (VarInt count + outputs)
+    write_vec(&mut result, &tx.outputs, |out, output| {
         // Value (8 bytes, little-endian)
-        result.extend_from_slice(&(output.value as u64).to_le_bytes());
+        out.extend_from_slice(&(output.value as u64).to_le_bytes());
 
-        // Script length (VarInt)
-        result.extend_from_slice(&encode_varint(output.script_pubkey.len() as u64));
-
-        // Script bytes
-        result.extend_from_slice(&output.script_pubkey);
-    }
+        // Script length (VarInt) + script bytes
+        out.extend_from_slice(&encode_varint(output.script_pubkey.len() as u64));
+        out.extend_from_slice(&output.script_pubkey);
+    });
 
     // Lock time (4 bytes, little-endian) - Bitcoin uses u32 in wire format
     result.extend_from_slice(&(tx.lock_time as u32).to_le_bytes());
@@ -219,8 +223,93 @@ fn serialize_transaction_inner(tx: &Transaction) -> Vec<u8> {
     result
 }
 
+/// Serialize a transaction with witness data (BIP144)
+///
+/// Format (SegWit):
+/// - Version (4 bytes, little-endian)
+/// - Marker (0x00) and flag (0x01)
+/// - Inputs and outputs as in the non-witness format
+/// - For each input: witness stack item count (VarInt), then each item (VarInt length + bytes)
+/// - Lock time (4 bytes, little-endian)
+///
+/// `witnesses` holds one witness stack per input. If every stack is empty the
+/// transaction has no witness and the non-witness format is returned, as in Core.
+pub fn serialize_transaction_with_witness(
+    tx: &Transaction,
+    witnesses: &[crate::segwit::Witness],
+) -> Vec<u8> {
+    if witnesses.iter().all(|w| w.is_empty()) {
+        return serialize_transaction(tx);
+    }
+
+    let base = serialize_transaction(tx);
+    let mut result = Vec::with_capacity(base.len() + 2 + witnesses.len() * 4);
+
+    // Version, then marker + flag
+    result.extend_from_slice(&base[..4]);
+    result.push(0x00);
+    result.push(0x01);
+
+    // Inputs and outputs are unchanged from the base serialization
+    result.extend_from_slice(&base[4..base.len() - 4]);
+
+    // Witness stacks, one per input (missing stacks serialize as empty)
+    for i in 0..tx.inputs.len() {
+        match witnesses.get(i) {
+            Some(witness) => write_vec(&mut result, witness, |out, element| {
+                out.extend_from_slice(&encode_varint(element.len() as u64));
+                out.extend_from_slice(element);
+            }),
+            None => result.push(0x00),
+        }
+    }
+
+    // Lock time
+    result.extend_from_slice(&base[base.len() - 4..]);
+
+    result
+}
+
 /// Deserialize a transaction from Bitcoin wire format
+///
+/// `data` must hold exactly one transaction: leftover bytes after it are an
+/// error. Use [`deserialize_transaction_prefix`] to parse from a stream. A
+/// witness serialization (BIP144) is accepted and its witnesses dropped; use
+/// [`deserialize_transaction_with_witness`] to keep them.
 pub fn deserialize_transaction(data: &[u8]) -> Result<Transaction> {
+    deserialize_transaction_with_witness(data).map(|(tx, _)| tx)
+}
+
+/// Deserialize a transaction and its witness stacks (BIP144)
+///
+/// The inverse of `serialize_transaction_with_witness`: returns one witness
+/// stack per input, all empty for a non-witness serialization. As in Core, a
+/// witness flag other than 0x01, or a flagged transaction whose witness stacks
+/// are all empty, is rejected, so every transaction has a single encoding.
+pub fn deserialize_transaction_with_witness(data: &[u8]) -> Result<(Transaction, Vec<Witness>)> {
+    let (tx, witnesses, consumed) = parse_transaction(data)?;
+    if consumed != data.len() {
+        return Err(ConsensusError::Serialization(Cow::Owned(format!(
+            "{}: {} of {} bytes unused",
+            TransactionParseError::TrailingBytes,
+            data.len() - consumed,
+            data.len()
+        ))));
+    }
+    Ok((tx, witnesses))
+}
+
+/// Deserialize a transaction from the start of `data`
+///
+/// Returns the transaction and the number of bytes it occupied, leaving any
+/// following bytes unread. Used when transactions are concatenated, as in a
+/// block's transaction list.
+pub fn deserialize_transaction_prefix(data: &[u8]) -> Result<(Transaction, usize)> {
+    parse_transaction(data).map(|(tx, _, consumed)| (tx, consumed))
+}
+
+/// Parse a transaction, with or without witness data, from the start of `data`
+pub(crate) fn parse_transaction(data: &[u8]) -> Result<(Transaction, Vec<Witness>, usize)> {
     let mut offset = 0;
 
     // Version (4 bytes) - Bitcoin uses signed 32-bit in wire format, but we store as u64
@@ -237,133 +326,51 @@ pub fn deserialize_transaction(data: &[u8]) -> Result<Transaction> {
     ]) as u64;
     offset += 4;
 
-    // Input count (VarInt)
-    let (input_count, varint_len) = decode_varint(&data[offset..])?;
-    offset += varint_len;
-
-    if input_count > 1000000 {
-        return Err(ConsensusError::Serialization(Cow::Owned(
-            TransactionParseError::InvalidInputCount.to_string(),
-        )));
-    }
-
+    // Inputs (VarInt count + inputs)
     #[cfg(feature = "production")]
-    let mut inputs = SmallVec::<[TransactionInput; 2]>::new();
+    let mut inputs: SmallVec<[TransactionInput; 2]> =
+        read_vec(data, &mut offset, MAX_VEC_ELEMENTS, parse_input)?;
     #[cfg(not(feature = "production"))]
-    let mut inputs = Vec::new();
-    for _ in 0..input_count {
-        // Previous output hash (32 bytes)
-        if data.len() < offset + 32 {
-            return Err(ConsensusError::Serialization(Cow::Owned(
-                TransactionParseError::InsufficientBytes.to_string(),
-            )));
-        }
-        let mut hash = [0u8; 32];
-        hash.copy_from_slice(&data[offset..offset + 32]);
-        offset += 32;
-
-        // Previous output index (4 bytes)
-        if data.len() < offset + 4 {
-            return Err(ConsensusError::Serialization(Cow::Owned(
-                TransactionParseError::InsufficientBytes.to_string(),
-            )));
-        }
-        let index = u64::from_le_bytes([
-            data[offset],
-            data[offset + 1],
-            data[offset + 2],
-            data[offset + 3],
-            0,
-            0,
-            0,
-            0,
-        ]);
-        offset += 4;
-
-        // Script length (VarInt)
-        let (script_len, varint_len) = decode_varint(&data[offset..])?;
-        offset += varint_len;
-
-        // Script bytes
-        if data.len() < offset + script_len as usize {
-            return Err(ConsensusError::Serialization(Cow::Owned(
-                TransactionParseError::InsufficientBytes.to_string(),
-            )));
-        }
-        let script_sig = data[offset..offset + script_len as usize].to_vec();
-        offset += script_len as usize;
-
-        // Sequence (4 bytes) - Bitcoin uses u32 in wire format, but we store as u64
-        if data.len() < offset + 4 {
-            return Err(ConsensusError::Serialization(Cow::Owned(
-                TransactionParseError::InsufficientBytes.to_string(),
-            )));
+    let mut inputs: Vec<TransactionInput> =
+        read_vec(data, &mut offset, MAX_VEC_ELEMENTS, parse_input)?;
+
+    // BIP144: an empty input list followed by a non-zero byte is the witness
+    // marker and flag rather than a transaction without inputs or outputs
+    let has_witness = match data.get(offset) {
+        Some(&flag) if inputs.is_empty() && flag != 0 => {
+            if flag != 0x01 {
+                return Err(ConsensusError::Serialization(Cow::Owned(
+                    TransactionParseError::UnknownWitnessFlag.to_string(),
+                )));
+            }
+            offset += 1;
+            inputs = read_vec(data, &mut offset, MAX_VEC_ELEMENTS, parse_input)?;
+            true
         }
-        let sequence = u32::from_le_bytes([
-            data[offset],
-            data[offset + 1],
-            data[offset + 2],
-            data[offset + 3],
-        ]) as u64;
-        offset += 4;
-
-        inputs.push(TransactionInput {
-            prevout: OutPoint { hash, index },
-            script_sig,
-            sequence,
-        });
-    }
-
-    // Output count (VarInt)
-    let (output_count, varint_len) = decode_varint(&data[offset..])?;
-    offset += varint_len;
-
-    if output_count > 1000000 {
-        return Err(ConsensusError::Serialization(Cow::Owned(
-            TransactionParseError::InvalidOutputCount.to_string(),
-        )));
-    }
+        _ => false,
+    };
 
+    // Outputs (VarInt count + outputs)
     #[cfg(feature = "production")]
-    let mut outputs = SmallVec::<[TransactionOutput; 2]>::new();
+    let outputs: SmallVec<[TransactionOutput; 2]> =
+        read_vec(data, &mut offset, MAX_VEC_ELEMENTS, parse_output)?;
     #[cfg(not(feature = "production"))]
-    let mut outputs = Vec::new();
-    for _ in 0..output_count {
-        // Value (8 bytes)
-        if data.len() < offset + 8 {
-            return Err(ConsensusError::Serialization(Cow::Owned(
-                TransactionParseError::InsufficientBytes.to_string(),
-            )));
+    let outputs: Vec<TransactionOutput> =
+        read_vec(data, &mut offset, MAX_VEC_ELEMENTS, parse_output)?;
+
+    // Witness stacks, one per input, each a list of VarInt-length-prefixed items
+    let mut witnesses = Vec::with_capacity(inputs.len());
+    if has_witness {
+        for _ in 0..inputs.len() {
+            witnesses.push(read_vec(data, &mut offset, MAX_VEC_ELEMENTS, parse_script)?);
         }
-        let value = i64::from_le_bytes([
-            data[offset],
-            data[offset + 1],
-            data[offset + 2],
-            data[offset + 3],
-            data[offset + 4],
-            data[offset + 5],
-            data[offset + 6],
-            data[offset + 7],
-        ]);
-        offset += 8;
-
-        // Script length (VarInt)
-        let (script_len, varint_len) = decode_varint(&data[offset..])?;
-        offset += varint_len;
-
-        // Script bytes
-        if data.len() < offset + script_len as usize {
+        if witnesses.iter().all(|w: &Witness| w.is_empty()) {
             return Err(ConsensusError::Serialization(Cow::Owned(
-                TransactionParseError::InsufficientBytes.to_string(),
+                TransactionParseError::SuperfluousWitness.to_string(),
             )));
         }
-        let script_pubkey = data[offset..offset + script_len as usize].to_vec();
-        offset += script_len as usize;
-
-        outputs.push(TransactionOutput {
-            value,
-            script_pubkey,
-        });
+    } else {
+        witnesses.resize(inputs.len(), Witness::new());
     }
 
     // Lock time (4 bytes) - Bitcoin uses u32 in wire format, but we store as u64
@@ -378,15 +385,107 @@ pub fn deserialize_transaction(data: &[u8]) -> Result<Transaction> {
         data[offset + 2],
         data[offset + 3],
     ]) as u64;
+    offset += 4;
+
+    Ok((
+        Transaction {
+            version,
+            inputs,
+            outputs,
+            lock_time,
+        },
+        witnesses,
+        offset,
+    ))
+}
+
+/// Parse a single transaction input at `offset`
+fn parse_input(data: &[u8], offset: &mut usize) -> Result<TransactionInput> {
+    // Previous output hash (32 bytes)
+    if data.len() < *offset + 32 {
+        return Err(ConsensusError::Serialization(Cow::Owned(
+            TransactionParseError::InsufficientBytes.to_string(),
+        )));
+    }
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&data[*offset..*offset + 32]);
+    *offset += 32;
+
+    // Previous output index (4 bytes)
+    if data.len() < *offset + 4 {
+        return Err(ConsensusError::Serialization(Cow::Owned(
+            TransactionParseError::InsufficientBytes.to_string(),
+        )));
+    }
+    let index = u32::from_le_bytes([
+        data[*offset],
+        data[*offset + 1],
+        data[*offset + 2],
+        data[*offset + 3],
+    ]) as u64;
+    *offset += 4;
+
+    // Script length (VarInt) + script bytes
+    let script_sig = parse_script(data, offset)?;
+
+    // Sequence (4 bytes) - Bitcoin uses u32 in wire format, but we store as u64
+    if data.len() < *offset + 4 {
+        return Err(ConsensusError::Serialization(Cow::Owned(
+            TransactionParseError::InsufficientBytes.to_string(),
+        )));
+    }
+    let sequence = u32::from_le_bytes([
+        data[*offset],
+        data[*offset + 1],
+        data[*offset + 2],
+        data[*offset + 3],
+    ]) as u64;
+    *offset += 4;
+
+    Ok(TransactionInput {
+        prevout: OutPoint { hash, index },
+        script_sig,
+        sequence,
+    })
+}
 
-    Ok(Transaction {
-        version,
-        inputs,
-        outputs,
-        lock_time,
+/// Parse a single transaction output at `offset`
+fn parse_output(data: &[u8], offset: &mut usize) -> Result<TransactionOutput> {
+    // Value (8 bytes)
+    if data.len() < *offset + 8 {
+        return Err(ConsensusError::Serialization(Cow::Owned(
+            TransactionParseError::InsufficientBytes.to_string(),
+        )));
+    }
+    let mut value_bytes = [0u8; 8];
+    value_bytes.copy_from_slice(&data[*offset..*offset + 8]);
+    let value = i64::from_le_bytes(value_bytes);
+    *offset += 8;
+
+    // Script length (VarInt) + script bytes
+    let script_pubkey = parse_script(data, offset)?;
+
+    Ok(TransactionOutput {
+        value,
+        script_pubkey,
     })
 }
 
+/// Parse a VarInt-length-prefixed script at `offset`
+fn parse_script(data: &[u8], offset: &mut usize) -> Result<ByteString> {
+    let (script_len, varint_len) = decode_varint(&data[*offset..])?;
+    *offset += varint_len;
+
+    if (data.len() - *offset) < script_len as usize {
+        return Err(ConsensusError::Serialization(Cow::Owned(
+            TransactionParseError::InsufficientBytes.to_string(),
+        )));
+    }
+    let script = data[*offset..*offset + script_len as usize].to_vec();
+    *offset += script_len as usize;
+    Ok(script)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -440,6 +539,112 @@ mod tests {
         assert!(deserialize_transaction(&[0, 0, 0, 0]).is_err()); // Only version
         assert!(deserialize_transaction(&[0, 0, 0, 0, 1]).is_err()); // Version + input count
     }
+
+    #[test]
+    fn test_deserialize_rejects_oversized_input_count() {
+        // Version, then an input count of 2^32 with nothing behind it
+        let data = [1, 0, 0, 0, 0xff, 0, 0, 0, 0, 1, 0, 0, 0];
+        assert!(deserialize_transaction(&data).is_err());
+    }
+
+    fn sample_transaction(lock_time: u64) -> Transaction {
+        Transaction {
+            version: 2,
+            inputs: crate::tx_inputs![TransactionInput {
+                prevout: OutPoint {
+                    hash: [7; 32],
+                    index: 1,
+                },
+                script_sig: vec![0x51],
+                sequence: 0xfffffffe,
+            }],
+            outputs: crate::tx_outputs![TransactionOutput {
+                value: 1000,
+                script_pubkey: vec![0x51, 0x87],
+            }],
+            lock_time,
+        }
+    }
+
+    #[test]
+    fn test_deserialize_exact_truncated_and_trailing() {
+        let tx = sample_transaction(0);
+        let serialized = serialize_transaction(&tx);
+
+        // Exact
+        assert_eq!(deserialize_transaction(&serialized).unwrap(), tx);
+        assert_eq!(
+            deserialize_transaction_prefix(&serialized).unwrap(),
+            (tx.clone(), serialized.len())
+        );
+
+        // Truncated: missing the last lock time byte
+        let truncated = &serialized[..serialized.len() - 1];
+        assert!(deserialize_transaction(truncated).is_err());
+        assert!(deserialize_transaction_prefix(truncated).is_err());
+
+        // Trailing garbage: rejected by the single-tx API, reported by the prefix API
+        let mut trailing = serialized.clone();
+        trailing.extend_from_slice(&[0xde, 0xad]);
+        let err = deserialize_transaction(&trailing).unwrap_err();
+        assert!(err.to_string().contains("Trailing bytes"));
+        assert_eq!(
+            deserialize_transaction_prefix(&trailing).unwrap(),
+            (tx, serialized.len())
+        );
+    }
+
+    #[test]
+    fn test_deserialize_witness_round_trip_and_empty_witness_flag() {
+        let tx = sample_transaction(0);
+        let witnesses = vec![vec![vec![0x30; 72], vec![0x02; 33]]];
+        let serialized = serialize_transaction_with_witness(&tx, &witnesses);
+        assert_eq!(&serialized[4..6], &[0x00, 0x01]);
+        assert_eq!(
+            deserialize_transaction_with_witness(&serialized).unwrap(),
+            (tx.clone(), witnesses)
+        );
+        assert_eq!(deserialize_transaction(&serialized).unwrap(), tx);
+
+        // Without witness data the plain encoding comes back with empty stacks
+        let plain = serialize_transaction(&tx);
+        assert_eq!(
+            deserialize_transaction_with_witness(&plain).unwrap(),
+            (tx.clone(), vec![vec![]])
+        );
+
+        // The marker and flag with only empty witness stacks is malformed
+        let mut flagged = plain[..4].to_vec();
+        flagged.extend_from_slice(&[0x00, 0x01]);
+        flagged.extend_from_slice(&plain[4..plain.len() - 4]);
+        flagged.push(0x00); // empty witness stack
+        flagged.extend_from_slice(&plain[plain.len() - 4..]);
+        let err = deserialize_transaction(&flagged).unwrap_err();
+        assert!(err.to_string().contains("all witnesses are empty"));
+        assert!(deserialize_transaction_prefix(&flagged).is_err());
+
+        // So is any flag other than 0x01
+        let mut unknown = serialized.clone();
+        unknown[5] = 0x02;
+        let err = deserialize_transaction(&unknown).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Unknown transaction optional data"));
+    }
+
+    #[test]
+    fn test_deserialize_transaction_prefix_stream() {
+        let first = sample_transaction(0);
+        let second = sample_transaction(500_000);
+        let mut stream = serialize_transaction(&first);
+        stream.extend_from_slice(&serialize_transaction(&second));
+
+        let (parsed_first, consumed) = deserialize_transaction_prefix(&stream).unwrap();
+        assert_eq!(parsed_first, first);
+        let (parsed_second, rest) = deserialize_transaction_prefix(&stream[consumed..]).unwrap();
+        assert_eq!(parsed_second, second);
+        assert_eq!(consumed + rest, stream.len());
+    }
 }
 
 #[cfg(kani)]
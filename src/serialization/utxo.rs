@@ -0,0 +1,231 @@
+//! UTXO set snapshot serialization
+//!
+//! Serializes a `UtxoSet` as a VarInt entry count followed by one record per
+//! entry, in `(txid, index)` order so the same set always produces the same
+//! bytes. Each record is:
+//!
+//! - txid (32 bytes) and output index (4 bytes, little-endian)
+//! - creation height (VarInt)
+//! - coinbase flag (1 byte)
+//! - value (8 bytes, little-endian) and VarInt-length-prefixed scriptPubKey
+//!
+//! `write_utxo_snapshot` streams the same bytes to a writer while folding each
+//! entry into a MuHash commitment, and `read_utxo_snapshot` loads a snapshot
+//! and recomputes it. The commitment equals `ChainState::utxo_commitment` for
+//! the same set, so a loaded snapshot can be checked against a trusted value.
+
+use super::varint::{decode_varint, encode_varint};
+use crate::crypto::muhash::MuHash3072;
+use crate::error::{ConsensusError, Result};
+use crate::types::*;
+use std::io::{Read, Write};
+
+/// Serialize a UTXO set deterministically
+pub fn serialize_utxo_set(utxo_set: &UtxoSet) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&encode_varint(utxo_set.len() as u64));
+    for (outpoint, utxo) in utxo_set.iter_sorted() {
+        encode_utxo_record(&mut out, outpoint, utxo);
+    }
+    out
+}
+
+/// Stream a UTXO set snapshot to `writer`, returning its commitment
+///
+/// Writes the `serialize_utxo_set` format one record at a time, so the
+/// serialized set is never held in memory, and returns the MuHash of the
+/// entries written.
+pub fn write_utxo_snapshot<W: Write>(utxo_set: &UtxoSet, writer: &mut W) -> Result<Hash> {
+    let mut muhash = MuHash3072::new();
+    writer
+        .write_all(&encode_varint(utxo_set.len() as u64))
+        .map_err(io_error)?;
+
+    let mut record = Vec::new();
+    for (outpoint, utxo) in utxo_set.iter_sorted() {
+        record.clear();
+        encode_utxo_record(&mut record, outpoint, utxo);
+        writer.write_all(&record).map_err(io_error)?;
+        muhash.insert(&serialize_utxo_for_commitment(outpoint, utxo));
+    }
+
+    writer.flush().map_err(io_error)?;
+    Ok(muhash.finalize())
+}
+
+/// Load a UTXO set snapshot from `reader`, returning the set and its commitment
+///
+/// Entries must be in strictly increasing `(txid, index)` order, as
+/// `write_utxo_snapshot` produces them; anything else is rejected, so each set
+/// has exactly one valid encoding.
+pub fn read_utxo_snapshot<R: Read>(reader: &mut R) -> Result<(UtxoSet, Hash)> {
+    let count = read_varint(reader)?;
+    let mut utxo_set = UtxoSet::new();
+    let mut muhash = MuHash3072::new();
+    let mut previous: Option<(Hash, Natural)> = None;
+
+    for _ in 0..count {
+        let hash: Hash = read_array(reader)?;
+        let index = u32::from_le_bytes(read_array(reader)?) as Natural;
+        if previous.is_some_and(|previous| previous >= (hash, index)) {
+            return Err(ConsensusError::Serialization(
+                "UTXO snapshot entries are not in strictly increasing order".into(),
+            ));
+        }
+        previous = Some((hash, index));
+
+        let height = read_varint(reader)?;
+        let is_coinbase = match read_array::<_, 1>(reader)?[0] {
+            0 => false,
+            1 => true,
+            flag => {
+                return Err(ConsensusError::Serialization(
+                    format!("Invalid coinbase flag {flag} in UTXO snapshot").into(),
+                ))
+            }
+        };
+        let value = i64::from_le_bytes(read_array(reader)?);
+        let script_len = read_varint(reader)?;
+        let mut script_pubkey = Vec::new();
+        reader
+            .by_ref()
+            .take(script_len)
+            .read_to_end(&mut script_pubkey)
+            .map_err(io_error)?;
+        if script_pubkey.len() as u64 != script_len {
+            return Err(io_error(std::io::ErrorKind::UnexpectedEof.into()));
+        }
+
+        let outpoint = OutPoint { hash, index };
+        let utxo = UTXO {
+            value,
+            script_pubkey,
+            height,
+            is_coinbase,
+        };
+        muhash.insert(&serialize_utxo_for_commitment(&outpoint, &utxo));
+        utxo_set.insert(outpoint, utxo);
+    }
+
+    Ok((utxo_set, muhash.finalize()))
+}
+
+/// Serialize a UTXO as a MuHash element
+///
+/// Matches Core's coinstats `TxOutSer`: outpoint, then `height * 2 + coinbase`
+/// as a u32, then the output (value and VarInt-prefixed script).
+pub(crate) fn serialize_utxo_for_commitment(outpoint: &OutPoint, utxo: &UTXO) -> Vec<u8> {
+    let mut data = Vec::with_capacity(32 + 4 + 4 + 8 + 1 + utxo.script_pubkey.len());
+    data.extend_from_slice(&outpoint.hash);
+    data.extend_from_slice(&(outpoint.index as u32).to_le_bytes());
+    let code = ((utxo.height as u32) << 1) | utxo.is_coinbase as u32;
+    data.extend_from_slice(&code.to_le_bytes());
+    data.extend_from_slice(&utxo.value.to_le_bytes());
+    data.extend_from_slice(&encode_varint(utxo.script_pubkey.len() as u64));
+    data.extend_from_slice(&utxo.script_pubkey);
+    data
+}
+
+fn encode_utxo_record(out: &mut Vec<u8>, outpoint: &OutPoint, utxo: &UTXO) {
+    out.extend_from_slice(&outpoint.hash);
+    out.extend_from_slice(&(outpoint.index as u32).to_le_bytes());
+    out.extend_from_slice(&encode_varint(utxo.height));
+    out.push(u8::from(utxo.is_coinbase));
+    out.extend_from_slice(&utxo.value.to_le_bytes());
+    out.extend_from_slice(&encode_varint(utxo.script_pubkey.len() as u64));
+    out.extend_from_slice(&utxo.script_pubkey);
+}
+
+pub(super) fn read_array<R: Read, const N: usize>(reader: &mut R) -> Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    reader.read_exact(&mut bytes).map_err(io_error)?;
+    Ok(bytes)
+}
+
+pub(super) fn read_varint<R: Read>(reader: &mut R) -> Result<u64> {
+    let mut bytes = [0u8; 9];
+    reader.read_exact(&mut bytes[..1]).map_err(io_error)?;
+    let len = match bytes[0] {
+        0xfd => 3,
+        0xfe => 5,
+        0xff => 9,
+        _ => 1,
+    };
+    reader.read_exact(&mut bytes[1..len]).map_err(io_error)?;
+    Ok(decode_varint(&bytes[..len])?.0)
+}
+
+pub(super) fn io_error(e: std::io::Error) -> ConsensusError {
+    ConsensusError::Serialization(format!("I/O error: {e}").into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries() -> Vec<(OutPoint, UTXO)> {
+        (0u8..20)
+            .map(|i| {
+                (
+                    OutPoint {
+                        hash: [i % 7; 32],
+                        index: u64::from(i),
+                    },
+                    UTXO {
+                        value: 1000 * i64::from(i),
+                        script_pubkey: vec![0x51; usize::from(i % 3)],
+                        height: u64::from(i),
+                        is_coinbase: i % 2 == 0,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_serialize_utxo_set_is_deterministic() {
+        let forward: UtxoSet = entries().into_iter().collect();
+        let reverse: UtxoSet = entries().into_iter().rev().collect();
+
+        let bytes = serialize_utxo_set(&forward);
+        assert_eq!(bytes, serialize_utxo_set(&forward));
+        assert_eq!(bytes, serialize_utxo_set(&reverse));
+    }
+
+    #[test]
+    fn test_utxo_snapshot_round_trip_commitment() {
+        let utxo_set: UtxoSet = entries().into_iter().collect();
+
+        let mut snapshot = Vec::new();
+        let written = write_utxo_snapshot(&utxo_set, &mut snapshot).unwrap();
+        assert_eq!(snapshot, serialize_utxo_set(&utxo_set));
+
+        let (loaded, read) = read_utxo_snapshot(&mut snapshot.as_slice()).unwrap();
+        assert_eq!(read, written);
+        assert_eq!(loaded, utxo_set);
+
+        // Matches the commitment of the same set built up incrementally
+        let mut muhash = MuHash3072::new();
+        for (outpoint, utxo) in &utxo_set {
+            muhash.insert(&serialize_utxo_for_commitment(outpoint, utxo));
+        }
+        assert_eq!(written, muhash.finalize());
+
+        // Truncated snapshots are rejected
+        assert!(read_utxo_snapshot(&mut &snapshot[..snapshot.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_iter_sorted_orders_by_txid_then_index() {
+        let utxo_set: UtxoSet = entries().into_iter().collect();
+        let keys: Vec<(Hash, Natural)> = utxo_set
+            .iter_sorted()
+            .map(|(outpoint, _)| (outpoint.hash, outpoint.index))
+            .collect();
+
+        let mut expected = keys.clone();
+        expected.sort();
+        assert_eq!(keys, expected);
+        assert_eq!(keys.len(), 20);
+    }
+}
@@ -0,0 +1,138 @@
+//! Compact-size-prefixed vector serialization/deserialization
+//!
+//! Bitcoin encodes every list on the wire (inputs, outputs, witness stacks,
+//! block transactions) as a VarInt element count followed by the elements.
+//! These helpers implement that pattern once, with a bound on the declared
+//! count so a malicious length cannot trigger a huge allocation.
+
+use super::varint::{decode_varint, encode_varint};
+use crate::error::{ConsensusError, Result};
+use std::borrow::Cow;
+
+/// Default maximum element count accepted by [`read_vec`]
+///
+/// Matches the input/output count limit previously applied by transaction
+/// deserialization. No consensus-valid list comes close to this.
+pub const MAX_VEC_ELEMENTS: u64 = 1_000_000;
+
+/// Read a VarInt count followed by that many elements
+///
+/// `parse_element` is called with the full buffer and the current offset, and
+/// must advance the offset past the element it parsed. The declared count is
+/// checked against `max_count` and against the number of remaining bytes
+/// (every element takes at least one byte) before anything is allocated.
+///
+/// # Errors
+///
+/// Returns `ConsensusError::Serialization` if the count is malformed, exceeds
+/// `max_count`, cannot fit in the remaining bytes, or an element fails to parse.
+pub fn read_vec<T, C, F>(
+    data: &[u8],
+    offset: &mut usize,
+    max_count: u64,
+    mut parse_element: F,
+) -> Result<C>
+where
+    C: FromIterator<T>,
+    F: FnMut(&[u8], &mut usize) -> Result<T>,
+{
+    let remaining = data
+        .get(*offset..)
+        .ok_or(ConsensusError::Serialization(Cow::Borrowed(
+            "Offset past end of buffer",
+        )))?;
+    let (count, varint_len) = decode_varint(remaining)?;
+    *offset += varint_len;
+
+    if count > max_count {
+        return Err(ConsensusError::Serialization(Cow::Owned(format!(
+            "Declared element count {count} exceeds maximum {max_count}"
+        ))));
+    }
+    if count > (data.len() - *offset) as u64 {
+        return Err(ConsensusError::Serialization(Cow::Owned(format!(
+            "Declared element count {count} exceeds remaining {} bytes",
+            data.len() - *offset
+        ))));
+    }
+
+    (0..count).map(|_| parse_element(data, offset)).collect()
+}
+
+/// Write a VarInt count followed by each element
+///
+/// `write_element` appends the serialization of a single element to `out`.
+pub fn write_vec<T, F>(out: &mut Vec<u8>, items: &[T], mut write_element: F)
+where
+    F: FnMut(&mut Vec<u8>, &T),
+{
+    out.extend_from_slice(&encode_varint(items.len() as u64));
+    for item in items {
+        write_element(out, item);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_byte(data: &[u8], offset: &mut usize) -> Result<u8> {
+        let byte = *data
+            .get(*offset)
+            .ok_or(ConsensusError::Serialization(Cow::Borrowed(
+                "Insufficient bytes",
+            )))?;
+        *offset += 1;
+        Ok(byte)
+    }
+
+    #[test]
+    fn test_read_write_vec_round_trip() {
+        let items = vec![7u8, 8, 9];
+        let mut out = Vec::new();
+        write_vec(&mut out, &items, |out, b| out.push(*b));
+        assert_eq!(out, vec![3, 7, 8, 9]);
+
+        let mut offset = 0;
+        let parsed: Vec<u8> = read_vec(&out, &mut offset, MAX_VEC_ELEMENTS, read_byte).unwrap();
+        assert_eq!(parsed, items);
+        assert_eq!(offset, out.len());
+    }
+
+    #[test]
+    fn test_read_vec_rejects_oversized_count() {
+        // Declares u64::MAX elements with no element data behind it
+        let data = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+        let mut offset = 0;
+        let mut calls = 0;
+        let result: Result<Vec<u8>> = read_vec(&data, &mut offset, MAX_VEC_ELEMENTS, |d, o| {
+            calls += 1;
+            read_byte(d, o)
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 0);
+
+        // Within the maximum, but more elements than remaining bytes
+        let data = [0xfd, 0x00, 0x01, 0x01];
+        let mut offset = 0;
+        let result: Result<Vec<u8>> = read_vec(&data, &mut offset, MAX_VEC_ELEMENTS, read_byte);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_read_vec_truncated_element() {
+        // Two elements declared, each two bytes, only three bytes present
+        let data = [2, 0xaa, 0xbb, 0xcc];
+        let mut offset = 0;
+        let result: Result<Vec<u16>> = read_vec(&data, &mut offset, MAX_VEC_ELEMENTS, |d, o| {
+            let bytes = d
+                .get(*o..*o + 2)
+                .ok_or(ConsensusError::Serialization(Cow::Borrowed(
+                    "Insufficient bytes",
+                )))?;
+            *o += 2;
+            Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+        });
+        assert!(result.is_err());
+    }
+}
@@ -6,6 +6,7 @@
 //! Reference: Bitcoin Core `tx_verify.cpp` and `script.cpp`
 
 use crate::error::Result;
+use crate::script_type::{classify_script, ScriptType};
 use crate::segwit::Witness;
 use crate::types::*;
 
@@ -117,10 +118,7 @@ pub fn count_sigops_in_script(script: &ByteString, accurate: bool) -> u32 {
 ///
 /// P2SH scripts have the format: OP_HASH160 (0xa9) <20-byte-hash> OP_EQUAL (0x87)
 fn is_pay_to_script_hash(script: &ByteString) -> bool {
-    script.len() == 23
-        && script[0] == 0xa9  // OP_HASH160
-        && script[1] == 0x14  // Push 20 bytes
-        && script[22] == 0x87 // OP_EQUAL
+    matches!(classify_script(script), ScriptType::P2SH(_))
 }
 
 /// Extract redeem script from P2SH scriptSig
@@ -330,7 +328,7 @@ fn count_witness_sigops(
 /// # Arguments
 /// * `tx` - Transaction to count sigops in
 /// * `utxo_set` - UTXO set to lookup inputs
-/// * `witness` - Witness data for this transaction (one Witness per input)
+/// * `witnesses` - Witness data for this transaction (one Witness per input)
 /// * `flags` - Script verification flags
 ///
 /// # Returns
@@ -338,7 +336,7 @@ fn count_witness_sigops(
 pub fn get_transaction_sigop_cost(
     tx: &Transaction,
     utxo_set: &UtxoSet,
-    witness: Option<&Witness>,
+    witnesses: Option<&[Witness]>,
     flags: u32,
 ) -> Result<u64> {
     // Legacy sigops × witness scale factor
@@ -358,10 +356,8 @@ pub fn get_transaction_sigop_cost(
     }
 
     // Witness sigops (actual count, not scaled)
-    if let Some(witness) = witness {
-        // Convert single Witness to slice for count_witness_sigops
-        let witness_slice = std::slice::from_ref(witness);
-        let witness_count = count_witness_sigops(tx, witness_slice, utxo_set, flags)?;
+    if let Some(witnesses) = witnesses {
+        let witness_count = count_witness_sigops(tx, witnesses, utxo_set, flags)?;
         total_cost = total_cost.saturating_add(witness_count);
     }
 
@@ -16,6 +16,12 @@ pub use crate::witness::Witness;
 /// Taproot output script: OP_1 <32-byte-hash>
 pub const TAPROOT_SCRIPT_PREFIX: u8 = 0x51; // OP_1
 
+/// First byte of a Taproot annex (BIP341)
+pub const TAPROOT_ANNEX_TAG: u8 = 0x50;
+
+/// Implicit sighash type of a 64-byte Taproot signature (BIP341 SIGHASH_DEFAULT)
+pub const TAPROOT_SIGHASH_DEFAULT: u8 = 0x00;
+
 /// Validate Taproot output script
 pub fn validate_taproot_script(script: &ByteString) -> Result<bool> {
     use crate::constants::TAPROOT_SCRIPT_LENGTH;
@@ -151,6 +157,35 @@ pub fn is_taproot_output(output: &TransactionOutput) -> bool {
     validate_taproot_script(&output.script_pubkey).unwrap_or(false)
 }
 
+/// Split a Taproot signature into its 64-byte Schnorr signature and sighash type
+///
+/// BIP341: a 64-byte signature uses SIGHASH_DEFAULT; a 65-byte signature
+/// carries an explicit sighash type, which must not be 0x00 (the default has
+/// to use the 64-byte form) and must be a defined type. Any other length fails.
+pub fn parse_taproot_signature(signature: &[u8]) -> Option<(&[u8], u8)> {
+    match signature.len() {
+        64 => Some((signature, TAPROOT_SIGHASH_DEFAULT)),
+        65 => {
+            let hash_type = signature[64];
+            matches!(hash_type, 0x01..=0x03 | 0x81..=0x83).then(|| (&signature[..64], hash_type))
+        }
+        _ => None,
+    }
+}
+
+/// Split the annex off a Taproot witness stack
+///
+/// BIP341: with at least two witness elements, a last element starting with
+/// 0x50 is the annex and is not part of the stack.
+pub fn split_annex(witness: &Witness) -> (&[ByteString], Option<&ByteString>) {
+    match witness.split_last() {
+        Some((last, rest)) if !rest.is_empty() && last.first() == Some(&TAPROOT_ANNEX_TAG) => {
+            (rest, Some(last))
+        }
+        _ => (witness, None),
+    }
+}
+
 /// Validate Taproot transaction
 pub fn validate_taproot_transaction(tx: &Transaction, witness: Option<&Witness>) -> Result<bool> {
     // Check if any output is Taproot
@@ -164,11 +199,12 @@ pub fn validate_taproot_transaction(tx: &Transaction, witness: Option<&Witness>)
     }
 
     // Validate Taproot witness structure using unified framework
-    // Determine if this is a script path spend based on witness structure
-    // Script path has at least 2 elements (script + control block), key path has 1 element (signature)
+    // Script path has at least 2 elements (script + control block), key path has 1 element (signature),
+    // both counted after the annex is removed
     if let Some(w) = witness {
-        let is_script_path = w.len() >= 2;
-        if !witness::validate_taproot_witness_structure(w, is_script_path)? {
+        let (stack, _annex) = split_annex(w);
+        let is_script_path = stack.len() >= 2;
+        if !witness::validate_taproot_witness_structure(&stack.to_vec(), is_script_path)? {
             return Ok(false);
         }
     }
@@ -180,7 +216,7 @@ pub fn validate_taproot_transaction(tx: &Transaction, witness: Option<&Witness>)
 pub fn compute_taproot_signature_hash(
     tx: &Transaction,
     input_index: usize,
-    prevouts: &[TransactionOutput],
+    prevouts: &[PrevOut],
     sighash_type: u8,
 ) -> Result<Hash> {
     // Create SHA256 hasher for Taproot signature hash
@@ -406,6 +442,49 @@ mod tests {
         assert_eq!(sig_hash.len(), 32);
     }
 
+    #[test]
+    fn test_compute_taproot_signature_hash_commits_to_prevout() {
+        let tx = Transaction {
+            version: 2,
+            inputs: crate::tx_inputs![TransactionInput {
+                prevout: OutPoint {
+                    hash: [7; 32],
+                    index: 1,
+                },
+                script_sig: vec![],
+                sequence: 0xfffffffd,
+            }],
+            outputs: crate::tx_outputs![TransactionOutput {
+                value: 1000,
+                script_pubkey: vec![0x51],
+            }],
+            lock_time: 0,
+        };
+        let spent = PrevOut {
+            value: 2000,
+            script_pubkey: create_taproot_script(&[1u8; 32]),
+        };
+        let sig_hash = compute_taproot_signature_hash(&tx, 0, &[spent.clone()], 0x00).unwrap();
+
+        // A different spent amount or spent script changes the hash
+        let other_amount = PrevOut {
+            value: 2001,
+            ..spent.clone()
+        };
+        let other_script = PrevOut {
+            script_pubkey: create_taproot_script(&[2u8; 32]),
+            ..spent
+        };
+        assert_ne!(
+            compute_taproot_signature_hash(&tx, 0, &[other_amount], 0x00).unwrap(),
+            sig_hash
+        );
+        assert_ne!(
+            compute_taproot_signature_hash(&tx, 0, &[other_script], 0x00).unwrap(),
+            sig_hash
+        );
+    }
+
     #[test]
     fn test_compute_taproot_signature_hash_invalid_input_index() {
         let tx = Transaction {
@@ -647,6 +726,77 @@ mod tests {
         assert!(validate_taproot_transaction(&tx, witness.as_ref()).unwrap());
     }
 
+    #[test]
+    fn test_split_annex() {
+        let annex = vec![TAPROOT_ANNEX_TAG, 0xaa, 0xbb];
+
+        // Key path with annex: the annex is not the signature
+        let witness = vec![vec![1u8; 64], annex.clone()];
+        assert_eq!(split_annex(&witness), (&witness[..1], Some(&annex)));
+
+        // Script path with annex: stack, script and control block exclude it
+        let witness = vec![vec![7], vec![0x51], vec![0xc0; 33], annex.clone()];
+        assert_eq!(split_annex(&witness), (&witness[..3], Some(&annex)));
+
+        // A lone 0x50-prefixed element is a signature, not an annex
+        let witness = vec![annex];
+        assert_eq!(split_annex(&witness), (&witness[..], None));
+    }
+
+    #[test]
+    fn test_validate_taproot_transaction_key_path_with_annex() {
+        let tx = Transaction {
+            version: 1,
+            inputs: crate::tx_inputs![TransactionInput {
+                prevout: OutPoint {
+                    hash: [0; 32],
+                    index: 0,
+                },
+                script_sig: vec![],
+                sequence: 0xffffffff,
+            }],
+            outputs: crate::tx_outputs![TransactionOutput {
+                value: 1000,
+                script_pubkey: create_taproot_script(&[1u8; 32]),
+            }],
+            lock_time: 0,
+        };
+
+        let witness = vec![vec![0u8; 64], vec![TAPROOT_ANNEX_TAG]];
+        assert!(validate_taproot_transaction(&tx, Some(&witness)).unwrap());
+    }
+
+    #[test]
+    fn test_parse_taproot_signature_length_rule() {
+        // 64 bytes: SIGHASH_DEFAULT
+        let default_sig = [0x11u8; 64];
+        assert_eq!(
+            parse_taproot_signature(&default_sig),
+            Some((&default_sig[..], TAPROOT_SIGHASH_DEFAULT))
+        );
+
+        // 63 bytes: invalid length
+        assert_eq!(parse_taproot_signature(&default_sig[..63]), None);
+
+        // 65 bytes with an explicit SIGHASH_ALL
+        let mut all_sig = default_sig.to_vec();
+        all_sig.push(0x01);
+        assert_eq!(
+            parse_taproot_signature(&all_sig),
+            Some((&default_sig[..], 0x01))
+        );
+
+        // 65 bytes with an explicit 0x00 must use the 64-byte form instead
+        let mut explicit_default = default_sig.to_vec();
+        explicit_default.push(TAPROOT_SIGHASH_DEFAULT);
+        assert_eq!(parse_taproot_signature(&explicit_default), None);
+
+        // 65 bytes with an undefined sighash type
+        let mut undefined = default_sig.to_vec();
+        undefined.push(0x04);
+        assert_eq!(parse_taproot_signature(&undefined), None);
+    }
+
     // Helper function
     fn create_taproot_script(output_key: &[u8; 32]) -> ByteString {
         let mut script = vec![TAPROOT_SCRIPT_PREFIX];
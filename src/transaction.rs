@@ -27,8 +27,11 @@ fn make_fee_calculation_underflow_error() -> ConsensusError {
 #[cfg(feature = "production")]
 fn check_transaction_fast_path(tx: &Transaction) -> Option<ValidationResult> {
     // Quick reject: empty inputs or outputs (most common invalid case)
-    if tx.inputs.is_empty() || tx.outputs.is_empty() {
-        return Some(ValidationResult::Invalid("Empty inputs or outputs".into()));
+    if tx.inputs.is_empty() {
+        return Some(RejectReason::VinEmpty.into());
+    }
+    if tx.outputs.is_empty() {
+        return Some(RejectReason::VoutEmpty.into());
     }
 
     // Quick reject: obviously too many inputs/outputs (before expensive size calculation)
@@ -109,11 +112,30 @@ fn check_transaction_fast_path(tx: &Transaction) -> Option<ValidationResult> {
 /// 7. ∀i,j ∈ ins: i ≠ j ⟹ i.prevout ≠ j.prevout (no duplicate inputs)
 /// 8. If tx is coinbase: 2 ≤ |ins[0].scriptSig| ≤ 100
 ///
+/// Rule 6 is checked on the stripped (non-witness) serialization, as in Core.
+/// Use [`check_transaction_with_witness`] when the witness stacks are known.
+///
 /// Performance optimization (Phase 6.3): Uses fast-path checks before full validation.
 #[track_caller] // Better error messages showing caller location
 #[cfg_attr(feature = "production", inline(always))]
 #[cfg_attr(not(feature = "production"), inline)]
 pub fn check_transaction(tx: &Transaction) -> Result<ValidationResult> {
+    check_transaction_with_witness(tx, &[])
+}
+
+/// CheckTransaction with witness data: 𝒯𝒳 × 𝒲* → {valid, invalid}
+///
+/// Same rules as [`check_transaction`], except that rule 6 is a weight limit:
+/// Weight(tx) = 3 × |stripped| + |total| ≤ W_max, so witness bytes count once
+/// and base bytes four times. `witnesses` holds one stack per input; with no
+/// witness data this is exactly the stripped-size check.
+#[track_caller]
+#[cfg_attr(feature = "production", inline(always))]
+#[cfg_attr(not(feature = "production"), inline)]
+pub fn check_transaction_with_witness(
+    tx: &Transaction,
+    witnesses: &[crate::witness::Witness],
+) -> Result<ValidationResult> {
     // Phase 6.3: Fast-path early exit for obviously invalid transactions
     #[cfg(feature = "production")]
     if let Some(result) = check_transaction_fast_path(tx) {
@@ -121,10 +143,11 @@ pub fn check_transaction(tx: &Transaction) -> Result<ValidationResult> {
     }
 
     // 1. Check inputs and outputs are not empty (redundant if fast-path worked, but safe fallback)
-    if tx.inputs.is_empty() || tx.outputs.is_empty() {
-        return Ok(ValidationResult::Invalid(
-            "Empty inputs or outputs".to_string(),
-        ));
+    if tx.inputs.is_empty() {
+        return Ok(RejectReason::VinEmpty.into());
+    }
+    if tx.outputs.is_empty() {
+        return Ok(RejectReason::VoutEmpty.into());
     }
 
     // 2. Check output values are valid and calculate total sum in one pass (Orange Paper Section 5.1, rules 2 & 3)
@@ -208,20 +231,25 @@ pub fn check_transaction(tx: &Transaction) -> Result<ValidationResult> {
         )));
     }
 
-    // 5. Check transaction size limit (matches Bitcoin Core's CheckTransaction exactly)
+    // 5. Check transaction size and weight limits
     // Core: GetSerializeSize(TX_NO_WITNESS(tx)) * WITNESS_SCALE_FACTOR > MAX_BLOCK_WEIGHT
-    // This checks: stripped_size * 4 > 4,000,000, i.e., stripped_size > 1,000,000
+    // This checks: stripped_size * 4 > 4,000,000, i.e., stripped_size > MAX_TX_SIZE
     // Note: Core's comment says "this doesn't take the witness into account, as that hasn't been checked for malleability"
-    // BLLVM: calculate_transaction_size returns stripped size (no witness), matching TX_NO_WITNESS
-    use crate::constants::MAX_BLOCK_WEIGHT;
-    const WITNESS_SCALE_FACTOR: usize = 4;
-    let tx_stripped_size = calculate_transaction_size(tx); // This is TX_NO_WITNESS size
-    if tx_stripped_size * WITNESS_SCALE_FACTOR > MAX_BLOCK_WEIGHT {
+    // BLLVM: with witness stacks supplied, the witness bytes are added at 1x on top,
+    // so the limit applies to the full BIP141 weight. Without them this is TX_NO_WITNESS.
+    use crate::constants::{MAX_BLOCK_WEIGHT, MAX_TX_SIZE};
+    let tx_stripped_size = calculate_transaction_size(tx); // Actual TX_NO_WITNESS byte length
+    if tx_stripped_size > MAX_TX_SIZE {
+        return Ok(ValidationResult::Invalid(format!(
+            "{}: stripped size {tx_stripped_size} bytes > {MAX_TX_SIZE}",
+            RejectReason::Oversize
+        )));
+    }
+    let tx_weight = calculate_transaction_weight_with_witness(tx, witnesses);
+    if tx_weight > MAX_BLOCK_WEIGHT {
         return Ok(ValidationResult::Invalid(format!(
-            "Transaction too large: stripped size {} bytes (weight {} > {})",
-            tx_stripped_size,
-            tx_stripped_size * WITNESS_SCALE_FACTOR,
-            MAX_BLOCK_WEIGHT
+            "{}: weight {tx_weight} > {MAX_BLOCK_WEIGHT}",
+            RejectReason::Oversize
         )));
     }
 
@@ -339,12 +367,35 @@ pub fn check_tx_inputs(
                 }
             }
 
+            // Spent values and their running total must be valid amounts
+            // (Bitcoin Core: bad-txns-inputvalues-outofrange). A UTXO outside
+            // [0, MAX_MONEY] means the UTXO set itself is inconsistent.
+            if !(0..=MAX_MONEY).contains(&utxo.value) {
+                return Ok((
+                    ValidationResult::Invalid(format!(
+                        "{}: input {i} spends value {}",
+                        RejectReason::InputValuesOutOfRange,
+                        utxo.value
+                    )),
+                    0,
+                ));
+            }
+
             // Use checked arithmetic to prevent overflow
             total_input_value = total_input_value.checked_add(utxo.value).ok_or_else(|| {
                 ConsensusError::TransactionValidation(
                     format!("Input value overflow at input {i}").into(),
                 )
             })?;
+            if total_input_value > MAX_MONEY {
+                return Ok((
+                    ValidationResult::Invalid(format!(
+                        "{}: input total exceeds MAX_MONEY at input {i}",
+                        RejectReason::InputValuesOutOfRange
+                    )),
+                    0,
+                ));
+            }
         } else {
             return Ok((
                 ValidationResult::Invalid(format!("Input {i} not found in UTXO set")),
@@ -389,6 +440,188 @@ pub fn check_tx_inputs(
     Ok((ValidationResult::Valid, fee))
 }
 
+/// Sum the values of the outputs spent by a transaction's inputs
+///
+/// Looks up each input's prevout in `utxo_set` without any of the other
+/// `check_tx_inputs` rules, so it can be used for fee estimation and display.
+/// A coinbase spends no outputs and totals zero.
+///
+/// # Errors
+///
+/// Returns `ConsensusError::UtxoNotFound` if an input's prevout is missing,
+/// and `ConsensusError::TransactionValidation` if a spent value or the running
+/// total falls outside `[0, MAX_MONEY]`.
+pub fn total_input_value(tx: &Transaction, utxo_set: &UtxoSet) -> Result<Integer> {
+    if is_coinbase(tx) {
+        return Ok(0);
+    }
+
+    tx.inputs
+        .iter()
+        .enumerate()
+        .try_fold(0i64, |total, (i, input)| {
+            let utxo = utxo_set.get(&input.prevout).ok_or_else(|| {
+                ConsensusError::UtxoNotFound(
+                    format!(
+                        "Input {i} spends missing output {}:{}",
+                        hex::encode(input.prevout.hash),
+                        input.prevout.index
+                    )
+                    .into(),
+                )
+            })?;
+            total
+                .checked_add(utxo.value)
+                .filter(|total| (0..=MAX_MONEY).contains(&utxo.value) && *total <= MAX_MONEY)
+                .ok_or_else(|| {
+                    ConsensusError::TransactionValidation(
+                        format!(
+                            "{}: input {i} spends value {}",
+                            RejectReason::InputValuesOutOfRange,
+                            utxo.value
+                        )
+                        .into(),
+                    )
+                })
+        })
+}
+
+/// Check if transaction is final (Orange Paper Section 9.1 - Transaction Finality)
+///
+/// Matches Bitcoin Core's IsFinalTx() exactly.
+///
+/// A transaction is final if:
+/// 1. tx.lock_time == 0 (no locktime restriction), OR
+/// 2. If locktime < LOCKTIME_THRESHOLD (block height): height > tx.lock_time
+/// 3. If locktime >= LOCKTIME_THRESHOLD (timestamp): block_time > tx.lock_time
+/// 4. OR if all inputs have SEQUENCE_FINAL (0xffffffff), locktime is ignored
+///
+/// Mathematical specification:
+/// ∀ tx ∈ Transaction, height ∈ ℕ, block_time ∈ ℕ:
+/// - is_final_tx(tx, height, block_time) = true ⟹
+///   (tx.lock_time = 0 ∨
+///   (tx.lock_time < LOCKTIME_THRESHOLD ∧ height > tx.lock_time) ∨
+///   (tx.lock_time >= LOCKTIME_THRESHOLD ∧ block_time > tx.lock_time) ∨
+///   (∀ input ∈ tx.inputs: input.sequence == SEQUENCE_FINAL))
+pub fn is_final_tx(tx: &Transaction, height: Natural, block_time: Natural) -> bool {
+    // If locktime is 0, transaction is always final
+    if tx.lock_time == 0 {
+        return true;
+    }
+
+    // Check if locktime is satisfied based on type
+    // Core's logic: if (tx.nLockTime < (tx.nLockTime < LOCKTIME_THRESHOLD ? nBlockHeight : nBlockTime))
+    // This means: locktime < (condition ? height : block_time)
+    // So: if locktime < threshold, check locktime < height
+    //     if locktime >= threshold, check locktime < block_time
+    let locktime_satisfied = if tx.lock_time < LOCKTIME_THRESHOLD as Natural {
+        // Block height locktime: check if locktime < height
+        tx.lock_time < height
+    } else {
+        // Timestamp locktime: check if locktime < block_time
+        tx.lock_time < block_time
+    };
+
+    if locktime_satisfied {
+        return true;
+    }
+
+    // Even if locktime isn't satisfied, transaction is final if all inputs have SEQUENCE_FINAL
+    // This allows transactions to bypass locktime by setting all sequences to 0xffffffff
+    // Core's behavior: if all inputs have SEQUENCE_FINAL, locktime is ignored
+    for input in &tx.inputs {
+        if (input.sequence as u32) != SEQUENCE_FINAL {
+            return false;
+        }
+    }
+
+    // All inputs have SEQUENCE_FINAL - transaction is final regardless of locktime
+    true
+}
+
+/// Check the BIP68 relative locktimes of a transaction's inputs
+///
+/// For a version 2+ transaction, each input whose sequence does not have the
+/// disable bit (bit 31) set must spend an output that has aged enough:
+///
+/// - Height lock (bit 22 clear): `height >= coin height + value`
+/// - Time lock (bit 22 set): `median_time_past >= coin time + value × 512`,
+///   where the coin time is the median time-past of the block before the one
+///   that confirmed it
+///
+/// The value is the low 16 bits of the sequence. `height` is the height of the
+/// block the transaction would be included in and `median_time_past` that of
+/// its parent (BIP113); `median_time_past_at` gives the median time-past of
+/// the block at a height. The first input whose lock is not yet satisfied is
+/// reported as `bad-txns-nonfinal`. Coinbase transactions have no relative
+/// locks.
+///
+/// # Errors
+///
+/// Returns `ConsensusError::UtxoNotFound` if a locked input's prevout is
+/// missing, and `ConsensusError::ConsensusRuleViolation` if
+/// `median_time_past_at` has no time for a time-locked input's coin.
+pub fn check_sequence_locks(
+    tx: &Transaction,
+    utxo_set: &UtxoSet,
+    height: Natural,
+    median_time_past: Natural,
+    median_time_past_at: impl Fn(Natural) -> Option<Natural>,
+) -> Result<ValidationResult> {
+    use crate::locktime::{
+        extract_sequence_locktime_value, extract_sequence_type_flag, is_sequence_disabled,
+    };
+    use crate::sequence_locks::SEQUENCE_LOCKTIME_GRANULARITY;
+
+    if tx.version < 2 || is_coinbase(tx) {
+        return Ok(ValidationResult::Valid);
+    }
+
+    for (i, input) in tx.inputs.iter().enumerate() {
+        let sequence = input.sequence as u32;
+        if is_sequence_disabled(sequence) {
+            continue;
+        }
+        let coin_height = utxo_set
+            .get(&input.prevout)
+            .ok_or_else(|| {
+                ConsensusError::UtxoNotFound(format!("Input {i} spends missing output").into())
+            })?
+            .height;
+        let value = Natural::from(extract_sequence_locktime_value(sequence));
+
+        if extract_sequence_type_flag(sequence) {
+            let coin_time =
+                median_time_past_at(coin_height.saturating_sub(1)).ok_or_else(|| {
+                    ConsensusError::ConsensusRuleViolation(
+                        format!(
+                            "No median time-past for height {} to check input {i}",
+                            coin_height.saturating_sub(1)
+                        )
+                        .into(),
+                    )
+                })?;
+            let unlock_time = coin_time + (value << SEQUENCE_LOCKTIME_GRANULARITY);
+            if median_time_past < unlock_time {
+                return Ok(ValidationResult::Invalid(format!(
+                    "{}: input {i} is time-locked until median time-past {unlock_time}",
+                    RejectReason::NonFinal
+                )));
+            }
+        } else {
+            let unlock_height = coin_height + value;
+            if height < unlock_height {
+                return Ok(ValidationResult::Invalid(format!(
+                    "{}: input {i} is height-locked until block {unlock_height}",
+                    RejectReason::NonFinal
+                )));
+            }
+        }
+    }
+
+    Ok(ValidationResult::Valid)
+}
+
 /// Check if transaction is coinbase
 #[inline]
 pub fn is_coinbase(tx: &Transaction) -> bool {
@@ -409,8 +642,36 @@ pub fn is_coinbase(tx: &Transaction) -> bool {
     }
 }
 
-/// Calculate transaction size (simplified)
-#[inline]
+/// Check that a coinbase transaction's input spends nothing
+///
+/// A coinbase has exactly one input, which references the null outpoint
+/// (all-zero hash, index 0xffffffff), so connecting it never looks up a UTXO.
+/// `is_coinbase` only detects this shape; this reports what is wrong with a
+/// transaction that should be a coinbase, as `bad-cb-missing`.
+pub fn check_coinbase_input(tx: &Transaction) -> ValidationResult {
+    let [input] = &tx.inputs[..] else {
+        return ValidationResult::Invalid(format!(
+            "{}: coinbase has {} inputs, expected 1",
+            RejectReason::CoinbaseMissing,
+            tx.inputs.len()
+        ));
+    };
+    if input.prevout.hash != [0u8; 32] {
+        return ValidationResult::Invalid(format!(
+            "{}: coinbase prevout hash is not null",
+            RejectReason::CoinbaseMissing
+        ));
+    }
+    if input.prevout.index != 0xffffffff {
+        return ValidationResult::Invalid(format!(
+            "{}: coinbase prevout index {} is not 0xffffffff",
+            RejectReason::CoinbaseMissing,
+            input.prevout.index
+        ));
+    }
+    ValidationResult::Valid
+}
+
 /// Calculate transaction size (non-witness serialization)
 ///
 /// This function calculates the size of a transaction when serialized
@@ -418,13 +679,59 @@ pub fn is_coinbase(tx: &Transaction) -> bool {
 ///
 /// CRITICAL: This must match the actual serialized size exactly to ensure
 /// consensus compatibility with Bitcoin Core.
+#[inline]
 pub fn calculate_transaction_size(tx: &Transaction) -> usize {
-    // Use actual serialization to match Bitcoin Core's behavior
-    // This replaces the simplified calculation that didn't account for varint encoding
+    // Use actual serialization to match Bitcoin Core's behavior, including
+    // varint-encoded counts and script lengths
     use crate::serialization::transaction::serialize_transaction;
     serialize_transaction(tx).len()
 }
 
+/// Calculate the stripped and total serialized sizes of a transaction
+///
+/// The stripped size excludes witness data; the total size is that of the
+/// BIP144 serialization with marker, flag and witness stacks. `witnesses`
+/// holds one stack per input; if all are empty the transaction serializes
+/// without a witness and both sizes are equal.
+pub fn calculate_transaction_sizes(
+    tx: &Transaction,
+    witnesses: &[crate::witness::Witness],
+) -> (usize, usize) {
+    use crate::serialization::transaction::serialize_transaction_with_witness;
+    let stripped_size = calculate_transaction_size(tx);
+    let total_size = if witnesses.iter().all(|w| w.is_empty()) {
+        stripped_size
+    } else {
+        serialize_transaction_with_witness(tx, witnesses).len()
+    };
+    (stripped_size, total_size)
+}
+
+/// Calculate transaction weight (BIP141)
+///
+/// Weight = 3 × stripped size + total size, where the total size includes the
+/// marker, flag and witness stacks. `witnesses` holds one stack per input; if
+/// all are empty the weight is 4 × stripped size.
+pub fn calculate_transaction_weight_with_witness(
+    tx: &Transaction,
+    witnesses: &[crate::witness::Witness],
+) -> usize {
+    const WITNESS_SCALE_FACTOR: usize = 4;
+    let (stripped_size, total_size) = calculate_transaction_sizes(tx, witnesses);
+    stripped_size * (WITNESS_SCALE_FACTOR - 1) + total_size
+}
+
+/// Calculate transaction virtual size (BIP141)
+///
+/// vsize = ⌈weight / 4⌉, the size fee rates are measured against. Without
+/// witness data it equals the serialized size.
+pub fn calculate_transaction_vsize(
+    tx: &Transaction,
+    witnesses: &[crate::witness::Witness],
+) -> usize {
+    calculate_transaction_weight_with_witness(tx, witnesses).div_ceil(4)
+}
+
 // ============================================================================
 // FORMAL VERIFICATION
 // ============================================================================
@@ -1731,6 +2038,54 @@ mod tests {
         assert_eq!(check_transaction(&tx).unwrap(), ValidationResult::Valid);
     }
 
+    #[test]
+    fn test_check_transaction_with_witness_uses_weight() {
+        let tx = Transaction {
+            version: 2,
+            inputs: crate::tx_inputs![TransactionInput {
+                prevout: OutPoint {
+                    hash: [1; 32],
+                    index: 0,
+                },
+                script_sig: vec![],
+                sequence: 0xffffffff,
+            }],
+            outputs: crate::tx_outputs![TransactionOutput {
+                value: 1000,
+                script_pubkey: vec![0x51],
+            }],
+            lock_time: 0,
+        };
+
+        // 1.2 MB of witness data: over a 1 MB size limit on the full serialization,
+        // but witness bytes count once, so the weight stays under 4M
+        let witnesses = vec![vec![vec![0u8; 1_200_000]]];
+        let total_size =
+            crate::serialization::transaction::serialize_transaction_with_witness(&tx, &witnesses)
+                .len();
+        assert!(total_size > MAX_TX_SIZE);
+        assert!(calculate_transaction_weight_with_witness(&tx, &witnesses) <= MAX_BLOCK_WEIGHT);
+        assert_eq!(
+            check_transaction_with_witness(&tx, &witnesses).unwrap(),
+            ValidationResult::Valid
+        );
+
+        // Witness alone pushing the weight over 4M is rejected, although the
+        // stripped-size check passes
+        let witnesses = vec![vec![vec![0u8; MAX_BLOCK_WEIGHT]]];
+        assert_eq!(check_transaction(&tx).unwrap(), ValidationResult::Valid);
+        assert!(matches!(
+            check_transaction_with_witness(&tx, &witnesses).unwrap(),
+            ValidationResult::Invalid(_)
+        ));
+
+        // No witness data: weight is 4 × stripped size
+        assert_eq!(
+            calculate_transaction_weight_with_witness(&tx, &[]),
+            calculate_transaction_size(&tx) * 4
+        );
+    }
+
     #[test]
     fn test_check_transaction_empty_inputs() {
         let tx = Transaction {
@@ -1744,10 +2099,85 @@ mod tests {
             lock_time: 0,
         };
 
-        assert!(matches!(
-            check_transaction(&tx).unwrap(),
-            ValidationResult::Invalid(_)
-        ));
+        let result = check_transaction(&tx).unwrap();
+        assert_eq!(result.reject_reason(), Some(RejectReason::VinEmpty));
+        assert_eq!(
+            result,
+            ValidationResult::Invalid("bad-txns-vin-empty".to_string())
+        );
+
+        // With no outputs either, the inputs are reported first, as in Core
+        let mut tx = tx;
+        tx.outputs = crate::tx_outputs![];
+        assert_eq!(
+            check_transaction(&tx).unwrap().reject_reason(),
+            Some(RejectReason::VinEmpty)
+        );
+    }
+
+    #[test]
+    fn test_check_tx_inputs_out_of_range_utxo_value() {
+        let outpoint = |index| OutPoint {
+            hash: [1; 32],
+            index,
+        };
+        let utxo = |value| UTXO {
+            value,
+            script_pubkey: vec![],
+            height: 0,
+            is_coinbase: false,
+        };
+        let mut tx = Transaction {
+            version: 1,
+            inputs: crate::tx_inputs![TransactionInput {
+                prevout: outpoint(0),
+                script_sig: vec![],
+                sequence: 0xffffffff,
+            }],
+            outputs: crate::tx_outputs![TransactionOutput {
+                value: 1000,
+                script_pubkey: vec![],
+            }],
+            lock_time: 0,
+        };
+
+        let reason = |tx: &Transaction, utxo_set: &UtxoSet| {
+            check_tx_inputs(tx, utxo_set, 0).unwrap().0.reject_reason()
+        };
+
+        // A single UTXO above MAX_MONEY
+        let mut utxo_set = UtxoSet::new();
+        utxo_set.insert(outpoint(0), utxo(MAX_MONEY + 1));
+        assert_eq!(
+            reason(&tx, &utxo_set),
+            Some(RejectReason::InputValuesOutOfRange)
+        );
+
+        // A negative UTXO value
+        utxo_set.insert(outpoint(0), utxo(-1));
+        assert_eq!(
+            reason(&tx, &utxo_set),
+            Some(RejectReason::InputValuesOutOfRange)
+        );
+
+        // Two in-range UTXOs whose sum exceeds MAX_MONEY
+        utxo_set.insert(outpoint(0), utxo(MAX_MONEY));
+        utxo_set.insert(outpoint(1), utxo(1));
+        tx.inputs.push(TransactionInput {
+            prevout: outpoint(1),
+            script_sig: vec![],
+            sequence: 0xffffffff,
+        });
+        assert_eq!(
+            reason(&tx, &utxo_set),
+            Some(RejectReason::InputValuesOutOfRange)
+        );
+
+        // Exactly MAX_MONEY is fine
+        tx.inputs.truncate(1);
+        let (result, fee) = check_tx_inputs(&tx, &utxo_set, 0).unwrap();
+        assert_eq!(result, ValidationResult::Valid);
+        assert_eq!(fee, MAX_MONEY - 1000);
     }
 
     #[test]
@@ -1799,10 +2229,12 @@ mod tests {
             lock_time: 0,
         };
 
-        assert!(matches!(
-            check_transaction(&tx).unwrap(),
-            ValidationResult::Invalid(_)
-        ));
+        let result = check_transaction(&tx).unwrap();
+        assert_eq!(result.reject_reason(), Some(RejectReason::VoutEmpty));
+        assert_eq!(
+            result,
+            ValidationResult::Invalid("bad-txns-vout-empty".to_string())
+        );
     }
 
     #[test]
@@ -2009,37 +2441,38 @@ mod tests {
 
     #[test]
     fn test_check_transaction_too_large() {
-        // Create a transaction that will exceed MAX_TX_SIZE
-        // calculate_transaction_size now uses actual serialization, so we need to create
-        // a transaction with enough inputs/outputs to exceed the size limit
-        let mut inputs = Vec::new();
-        for i in 0..25000 {
-            // This should create a transaction > 1MB
-            inputs.push(TransactionInput {
+        // One input and one output whose script pads the serialization to
+        // exactly MAX_TX_SIZE bytes; one more script byte is over the limit
+        let mut tx = Transaction {
+            version: 1,
+            inputs: vec![TransactionInput {
                 prevout: OutPoint {
-                    hash: [i as u8; 32],
+                    hash: [1; 32],
                     index: 0,
                 },
-                script_sig: vec![0u8; 100], // Large script to increase size
+                script_sig: vec![],
                 sequence: 0xffffffff,
-            });
-        }
-
-        let tx = Transaction {
-            version: 1,
-            inputs: inputs.into(),
+            }]
+            .into(),
             outputs: vec![TransactionOutput {
                 value: 1000,
-                script_pubkey: vec![].into(),
+                script_pubkey: vec![],
             }]
             .into(),
             lock_time: 0,
         };
+        // Scripts over 0xffff bytes take a 5-byte varint (1 byte when empty)
+        let padding = MAX_TX_SIZE - calculate_transaction_size(&tx) - 4;
+        tx.outputs[0].script_pubkey = vec![0x6a; padding];
+        assert_eq!(calculate_transaction_size(&tx), MAX_TX_SIZE);
+        assert_eq!(check_transaction(&tx).unwrap(), ValidationResult::Valid);
 
-        assert!(matches!(
-            check_transaction(&tx).unwrap(),
-            ValidationResult::Invalid(_)
-        ));
+        tx.outputs[0].script_pubkey.push(0x6a);
+        assert_eq!(calculate_transaction_size(&tx), MAX_TX_SIZE + 1);
+        match check_transaction(&tx).unwrap() {
+            ValidationResult::Invalid(reason) => assert!(reason.starts_with("bad-txns-oversize")),
+            ValidationResult::Valid => panic!("transaction over MAX_TX_SIZE must be rejected"),
+        }
     }
 
     #[test]
@@ -2219,6 +2652,240 @@ mod tests {
         assert_eq!(fee, 100000000); // 1 BTC fee (8 BTC input - 7 BTC output)
     }
 
+    #[test]
+    fn test_total_input_value() {
+        let present = OutPoint {
+            hash: [1; 32],
+            index: 0,
+        };
+        let missing = OutPoint {
+            hash: [2; 32],
+            index: 1,
+        };
+        let mut utxo_set = UtxoSet::new();
+        utxo_set.insert(
+            present.clone(),
+            UTXO {
+                value: 500000000,
+                script_pubkey: vec![],
+                height: 0,
+                is_coinbase: false,
+            },
+        );
+
+        let spend = |prevouts: Vec<OutPoint>| Transaction {
+            version: 1,
+            inputs: prevouts
+                .into_iter()
+                .map(|prevout| TransactionInput {
+                    prevout,
+                    script_sig: vec![],
+                    sequence: 0xffffffff,
+                })
+                .collect(),
+            outputs: vec![].into(),
+            lock_time: 0,
+        };
+
+        let tx = spend(vec![present.clone(), missing.clone()]);
+        assert!(matches!(
+            total_input_value(&tx, &utxo_set),
+            Err(ConsensusError::UtxoNotFound(_))
+        ));
+
+        utxo_set.insert(
+            missing.clone(),
+            UTXO {
+                value: 300000000,
+                script_pubkey: vec![],
+                height: 0,
+                is_coinbase: false,
+            },
+        );
+        assert_eq!(total_input_value(&tx, &utxo_set).unwrap(), 800000000);
+
+        // Totals past MAX_MONEY are an error rather than a wrapped sum
+        utxo_set.get_mut(&missing).unwrap().value = MAX_MONEY;
+        assert!(matches!(
+            total_input_value(&tx, &utxo_set),
+            Err(ConsensusError::TransactionValidation(_))
+        ));
+    }
+
+    #[test]
+    fn test_check_coinbase_input() {
+        let genesis = crate::chain_params::ChainParams::mainnet().genesis_block();
+        let coinbase = genesis.transactions[0].clone();
+        assert_eq!(check_coinbase_input(&coinbase), ValidationResult::Valid);
+
+        let reason = |tx: &Transaction| match check_coinbase_input(tx) {
+            ValidationResult::Invalid(message) => message,
+            ValidationResult::Valid => panic!("malformed coinbase accepted"),
+        };
+
+        // Non-null prevout hash: this would spend a real UTXO
+        let mut tx = coinbase.clone();
+        tx.inputs[0].prevout.hash = [1; 32];
+        assert!(!is_coinbase(&tx));
+        assert_eq!(
+            reason(&tx),
+            "bad-cb-missing: coinbase prevout hash is not null"
+        );
+
+        // Null hash with a real index
+        let mut tx = coinbase.clone();
+        tx.inputs[0].prevout.index = 0;
+        assert_eq!(
+            reason(&tx),
+            "bad-cb-missing: coinbase prevout index 0 is not 0xffffffff"
+        );
+
+        // A second input, even another null one
+        let mut tx = coinbase.clone();
+        tx.inputs.push(coinbase.inputs[0].clone());
+        assert_eq!(
+            reason(&tx),
+            "bad-cb-missing: coinbase has 2 inputs, expected 1"
+        );
+
+        // No inputs at all
+        let mut tx = coinbase;
+        tx.inputs.clear();
+        assert_eq!(
+            reason(&tx),
+            "bad-cb-missing: coinbase has 0 inputs, expected 1"
+        );
+        assert_eq!(
+            ValidationResult::Invalid(reason(&tx)).reject_reason(),
+            Some(RejectReason::CoinbaseMissing)
+        );
+    }
+
+    #[test]
+    fn test_is_final_tx_locktime_threshold_boundary() {
+        let mut tx = Transaction {
+            version: 1,
+            inputs: vec![TransactionInput {
+                prevout: OutPoint {
+                    hash: [1; 32],
+                    index: 0,
+                },
+                script_sig: vec![],
+                sequence: 0xfffffffe,
+            }]
+            .into(),
+            outputs: vec![TransactionOutput {
+                value: 1000,
+                script_pubkey: vec![0x51],
+            }]
+            .into(),
+            lock_time: LOCKTIME_THRESHOLD as Natural - 1,
+        };
+
+        // Just under the threshold the lock time is a block height: final only
+        // in a later block, whatever the block time
+        let height = LOCKTIME_THRESHOLD as Natural - 1;
+        assert!(!is_final_tx(&tx, height, Natural::MAX));
+        assert!(is_final_tx(&tx, height + 1, 0));
+
+        // At the threshold it is a timestamp: final only once the block time
+        // is past it, whatever the height
+        tx.lock_time = LOCKTIME_THRESHOLD as Natural;
+        let time = LOCKTIME_THRESHOLD as Natural;
+        assert!(!is_final_tx(&tx, Natural::MAX, time));
+        assert!(is_final_tx(&tx, 0, time + 1));
+
+        // Final sequences disable the lock time
+        tx.inputs[0].sequence = SEQUENCE_FINAL as Natural;
+        assert!(is_final_tx(&tx, 0, 0));
+    }
+
+    #[test]
+    fn test_check_sequence_locks_mixed_inputs() {
+        let input = |index, sequence| TransactionInput {
+            prevout: OutPoint {
+                hash: [9; 32],
+                index,
+            },
+            script_sig: vec![],
+            sequence,
+        };
+        let mut tx = Transaction {
+            version: 2,
+            inputs: vec![
+                input(0, 10),          // 10 blocks
+                input(1, 0x00400002),  // 2 × 512 seconds
+                input(2, 0x8000_ffff), // disabled: no lock at all
+                input(3, 0x00410001),  // bit 16 is ignored: 512 seconds
+            ]
+            .into(),
+            outputs: vec![TransactionOutput {
+                value: 1000,
+                script_pubkey: vec![0x51],
+            }]
+            .into(),
+            lock_time: 0,
+        };
+        let mut utxo_set = UtxoSet::new();
+        for (index, coin_height) in [(0, 100), (1, 105), (2, 109), (3, 104)] {
+            utxo_set.insert(
+                OutPoint {
+                    hash: [9; 32],
+                    index,
+                },
+                UTXO {
+                    value: 1000,
+                    script_pubkey: vec![0x51],
+                    height: coin_height,
+                    is_coinbase: false,
+                },
+            );
+        }
+
+        // Block at height h has median time-past 1,000,000 + 600h, so input 1's
+        // coin time is that of block 104 and input 3's that of block 103
+        let mtp_at = |h: Natural| Some(1_000_000 + 600 * h);
+        let unlock_1 = 1_000_000 + 600 * 104 + 1024;
+        let unlock_3 = 1_000_000 + 600 * 103 + 512;
+        assert!(unlock_3 < unlock_1);
+        let check = |tx: &Transaction, height, mtp| {
+            check_sequence_locks(tx, &utxo_set, height, mtp, mtp_at).unwrap()
+        };
+
+        assert_eq!(check(&tx, 110, unlock_1), ValidationResult::Valid);
+        assert_eq!(
+            check(&tx, 109, unlock_1),
+            ValidationResult::Invalid(
+                "bad-txns-nonfinal: input 0 is height-locked until block 110".into()
+            )
+        );
+        assert_eq!(
+            check(&tx, 110, unlock_1 - 1),
+            ValidationResult::Invalid(format!(
+                "bad-txns-nonfinal: input 1 is time-locked until median time-past {unlock_1}"
+            ))
+        );
+
+        // Input 3 alone: one 512-second unit
+        let mut single = tx.clone();
+        single.inputs = vec![tx.inputs[3].clone()].into();
+        assert_eq!(check(&single, 0, unlock_3), ValidationResult::Valid);
+        assert_eq!(
+            check(&single, 0, unlock_3 - 1),
+            ValidationResult::Invalid(format!(
+                "bad-txns-nonfinal: input 0 is time-locked until median time-past {unlock_3}"
+            ))
+        );
+
+        // Version 1 transactions are not subject to BIP68
+        tx.version = 1;
+        assert_eq!(check(&tx, 0, 0), ValidationResult::Valid);
+
+        // A time lock needs the coin's median time-past
+        tx.version = 2;
+        assert!(check_sequence_locks(&tx, &utxo_set, 110, unlock_1, |_| None).is_err());
+    }
+
     #[test]
     fn test_is_coinbase_edge_cases() {
         // Valid coinbase
@@ -2355,5 +3022,53 @@ mod tests {
         // 4 (lock_time) = 4 + 1 + 88 + 1 + 24 + 4 = 122
         // This matches actual serialization (not simplified calculation)
         assert_eq!(size, 122);
+        assert_eq!(calculate_transaction_sizes(&tx, &[]), (122, 122));
+    }
+
+    #[test]
+    fn test_calculate_transaction_sizes_real_transactions() {
+        // Mainnet genesis coinbase: 204 bytes, no witness
+        let genesis = crate::chain_params::ChainParams::mainnet().genesis_block();
+        assert_eq!(calculate_transaction_size(&genesis.transactions[0]), 204);
+
+        // P2WPKH spend from Core's tx_valid.json: 195 bytes with a 2-byte
+        // marker and flag and a 108-byte witness, 85 bytes stripped
+        let bytes = hex::decode(
+            "0100000000010100010000000000000000000000000000000000000000000000000000000000000000000000ffffffff01e8030000000000001976a9144c9c3dfac4207d5d8cb89df5722cb3d712385e3f88ac02483045022100cfb07164b36ba64c1b1e8c7720a56ad64d96f6ef332d3d37f9cb3c96477dc44502200a464cd7a9cf94cd70f66ce4f4f0625ef650052c7afcfe29d7d7e01830ff91ed012103596d3451025c19dbbdeb932d6bf8bfb4ad499b95b6f88db8899efac102e5fc7100000000",
+        )
+        .unwrap();
+        let (tx, witnesses) =
+            crate::serialization::transaction::deserialize_transaction_with_witness(&bytes)
+                .unwrap();
+        assert_eq!(calculate_transaction_size(&tx), 85);
+        assert_eq!(calculate_transaction_sizes(&tx, &witnesses), (85, 195));
+        assert_eq!(calculate_transaction_sizes(&tx, &[]), (85, 85));
+    }
+
+    #[test]
+    fn test_calculate_transaction_weight_and_vsize() {
+        // Legacy: weight is 4 × size and vsize is the size
+        let genesis = crate::chain_params::ChainParams::mainnet().genesis_block();
+        let coinbase = &genesis.transactions[0];
+        assert_eq!(
+            calculate_transaction_weight_with_witness(coinbase, &[]),
+            816
+        );
+        assert_eq!(calculate_transaction_vsize(coinbase, &[]), 204);
+
+        // P2WPKH spend: 3 × 85 + 195 = 450, vsize rounds 112.5 up
+        let bytes = hex::decode(
+            "0100000000010100010000000000000000000000000000000000000000000000000000000000000000000000ffffffff01e8030000000000001976a9144c9c3dfac4207d5d8cb89df5722cb3d712385e3f88ac02483045022100cfb07164b36ba64c1b1e8c7720a56ad64d96f6ef332d3d37f9cb3c96477dc44502200a464cd7a9cf94cd70f66ce4f4f0625ef650052c7afcfe29d7d7e01830ff91ed012103596d3451025c19dbbdeb932d6bf8bfb4ad499b95b6f88db8899efac102e5fc7100000000",
+        )
+        .unwrap();
+        let (tx, witnesses) =
+            crate::serialization::transaction::deserialize_transaction_with_witness(&bytes)
+                .unwrap();
+        assert_eq!(
+            calculate_transaction_weight_with_witness(&tx, &witnesses),
+            450
+        );
+        assert_eq!(calculate_transaction_vsize(&tx, &witnesses), 113);
+        assert!(calculate_transaction_vsize(&tx, &witnesses) < bytes.len());
     }
 }
@@ -26,6 +26,12 @@ pub enum SighashType {
     Single = 0x03,
     /// Sign only this input (anyone can spend other inputs)
     AnyoneCanPay = 0x80,
+    /// SIGHASH_ALL | SIGHASH_ANYONECANPAY
+    AllAnyoneCanPay = 0x81,
+    /// SIGHASH_NONE | SIGHASH_ANYONECANPAY
+    NoneAnyoneCanPay = 0x82,
+    /// SIGHASH_SINGLE | SIGHASH_ANYONECANPAY
+    SingleAnyoneCanPay = 0x83,
 }
 
 impl SighashType {
@@ -48,9 +54,9 @@ impl std::ops::BitOr for SighashType {
 
     fn bitor(self, rhs: Self) -> Self::Output {
         match (self, rhs) {
-            (SighashType::All, SighashType::AnyoneCanPay) => SighashType::All,
-            (SighashType::None, SighashType::AnyoneCanPay) => SighashType::None,
-            (SighashType::Single, SighashType::AnyoneCanPay) => SighashType::Single,
+            (SighashType::All, SighashType::AnyoneCanPay) => SighashType::AllAnyoneCanPay,
+            (SighashType::None, SighashType::AnyoneCanPay) => SighashType::NoneAnyoneCanPay,
+            (SighashType::Single, SighashType::AnyoneCanPay) => SighashType::SingleAnyoneCanPay,
             _ => self,
         }
     }
@@ -64,7 +70,7 @@ struct SighashPattern {
     input_count: usize,
     output_count: usize,
     lock_time: u64,
-    sighash_type: SighashType,
+    hash_type: u32,
 }
 
 /// Phase 6.2: Precomputed sighash template cache
@@ -77,17 +83,13 @@ static SIGHASH_TEMPLATES: OnceLock<HashMap<SighashPattern, Vec<u8>>> = OnceLock:
 /// Phase 6.2: Check if transaction matches a common pattern suitable for templating
 #[cfg(feature = "production")]
 #[inline]
-fn matches_template_pattern(
-    tx: &Transaction,
-    input_index: usize,
-    sighash_type: SighashType,
-) -> bool {
+fn matches_template_pattern(tx: &Transaction, input_index: usize, hash_type: u32) -> bool {
     // Only cache simple patterns to avoid complexity
     // Pattern: Single input, single output, standard P2PKH structure
     tx.inputs.len() == 1
         && tx.outputs.len() == 1
         && input_index == 0
-        && matches!(sighash_type, SighashType::All)
+        && hash_type == SighashType::All as u32
         && tx.version == 1
         && tx.lock_time == 0
 }
@@ -97,10 +99,10 @@ fn matches_template_pattern(
 fn get_sighash_template(
     tx: &Transaction,
     input_index: usize,
-    _prevouts: &[TransactionOutput],
-    sighash_type: SighashType,
+    _prevouts: &[PrevOut],
+    hash_type: u32,
 ) -> Option<Vec<u8>> {
-    if !matches_template_pattern(tx, input_index, sighash_type) {
+    if !matches_template_pattern(tx, input_index, hash_type) {
         return None;
     }
 
@@ -109,7 +111,7 @@ fn get_sighash_template(
         input_count: tx.inputs.len(),
         output_count: tx.outputs.len(),
         lock_time: tx.lock_time,
-        sighash_type,
+        hash_type,
     };
 
     let templates = SIGHASH_TEMPLATES.get_or_init(|| HashMap::new());
@@ -119,10 +121,23 @@ fn get_sighash_template(
     templates.get(&pattern).cloned()
 }
 
+/// Legacy sighash of a SIGHASH_SINGLE input with no output at its index
+///
+/// The 256-bit integer 1, little-endian. Core's `SignatureHash` returns it
+/// rather than failing, so such signatures commit to nothing in the
+/// transaction; historical transactions rely on it.
+pub const SIGHASH_SINGLE_BUG_HASH: Hash = {
+    let mut hash = [0u8; 32];
+    hash[0] = 1;
+    hash
+};
+
 /// Calculate transaction sighash for signature verification
 ///
 /// This implements the Bitcoin transaction hash algorithm used for ECDSA signatures.
-/// The sighash determines which parts of the transaction are signed.
+/// The sighash determines which parts of the transaction are signed. A
+/// SIGHASH_SINGLE input past the last output hashes to
+/// `SIGHASH_SINGLE_BUG_HASH`.
 ///
 /// Performance optimization (Phase 6.2): Checks for precomputed templates
 /// before computing sighash from scratch.
@@ -138,8 +153,22 @@ fn get_sighash_template(
 pub fn calculate_transaction_sighash(
     tx: &Transaction,
     input_index: usize,
-    prevouts: &[TransactionOutput],
+    prevouts: &[PrevOut],
     sighash_type: SighashType,
+) -> Result<Hash> {
+    legacy_signature_hash(tx, input_index, prevouts, sighash_type as u32)
+}
+
+/// Legacy sighash for a raw nHashType, as Core's `SignatureHash`
+///
+/// `hash_type` is the signature's trailing byte: any value is hashed, with
+/// the low five bits selecting NONE or SINGLE (anything else behaves as ALL)
+/// and bit 7 ANYONECANPAY, and is committed to as is.
+fn legacy_signature_hash(
+    tx: &Transaction,
+    input_index: usize,
+    prevouts: &[PrevOut],
+    hash_type: u32,
 ) -> Result<Hash> {
     // Validate input index
     if input_index >= tx.inputs.len() {
@@ -154,9 +183,15 @@ pub fn calculate_transaction_sighash(
         ));
     }
 
+    // SIGHASH_SINGLE without a matching output signs the constant 1 instead
+    // of a preimage (Core's SignatureHash "SIGHASH_SINGLE bug")
+    if hash_type & 0x1f == SighashType::Single as u32 && input_index >= tx.outputs.len() {
+        return Ok(SIGHASH_SINGLE_BUG_HASH);
+    }
+
     // Phase 6.2: Check for template cache (only for common patterns)
     #[cfg(feature = "production")]
-    if let Some(template) = get_sighash_template(tx, input_index, prevouts, sighash_type) {
+    if let Some(template) = get_sighash_template(tx, input_index, prevouts, hash_type) {
         // Template found - hash it directly
         let first_hash = Sha256::digest(&template);
         let second_hash = Sha256::digest(first_hash);
@@ -166,81 +201,311 @@ pub fn calculate_transaction_sighash(
     }
 
     // Create sighash preimage (standard computation)
-    let mut preimage = Vec::new();
+    let preimage = serialize_sighash_preimage(tx, input_index, prevouts, hash_type);
 
-    // 1. Transaction version (4 bytes, little endian)
-    preimage.extend_from_slice(&tx.version.to_le_bytes());
+    // Calculate double SHA256 hash
+    let first_hash = Sha256::digest(&preimage);
+    let second_hash = Sha256::digest(first_hash);
 
-    // 2. Number of inputs (varint)
-    preimage.extend_from_slice(&encode_varint(tx.inputs.len() as u64));
+    let mut result = [0u8; 32];
+    result.copy_from_slice(&second_hash);
 
-    // 3. Inputs (depending on sighash type)
-    for (i, input) in tx.inputs.iter().enumerate() {
-        if matches!(sighash_type, SighashType::AnyoneCanPay) || i == input_index {
-            // Include this input
-            preimage.extend_from_slice(&input.prevout.hash);
-            preimage.extend_from_slice(&input.prevout.index.to_le_bytes());
-            preimage.extend_from_slice(&encode_varint(input.script_sig.len() as u64));
-            preimage.extend_from_slice(&input.script_sig);
-            preimage.extend_from_slice(&input.sequence.to_le_bytes());
+    Ok(result)
+}
+
+/// Calculate the legacy signature hash of an input with an explicit scriptCode
+///
+/// Core's `SignatureHash` serializes the transaction with `script_code` in
+/// place of the signed input's scriptSig and empty scripts in all the other
+/// inputs, then hashes it as `calculate_transaction_sighash` does. The caller
+/// prepares `script_code`: the executing script from its last executed
+/// OP_CODESEPARATOR, with the signatures and remaining OP_CODESEPARATORs
+/// removed.
+///
+/// `hash_type` is the raw nHashType, the signature's trailing byte. Unlike
+/// `SighashType` it may be any value, as historical signatures use undefined
+/// types; it is interpreted and committed to as Core does.
+pub fn calculate_legacy_sighash(
+    tx: &Transaction,
+    input_index: usize,
+    prevouts: &[PrevOut],
+    script_code: &[u8],
+    hash_type: u32,
+) -> Result<Hash> {
+    let mut signed_tx = tx.clone();
+    for (i, input) in signed_tx.inputs.iter_mut().enumerate() {
+        input.script_sig = if i == input_index {
+            script_code.to_vec()
         } else {
-            // Skip this input (use dummy values)
-            preimage.extend_from_slice(&[0u8; 32]); // prevout hash
-            preimage.extend_from_slice(&[0u8; 4]); // prevout index
-            preimage.push(0); // empty script_sig
-            preimage.extend_from_slice(&[0u8; 4]); // sequence
-        }
+            Vec::new()
+        };
     }
+    legacy_signature_hash(&signed_tx, input_index, prevouts, hash_type)
+}
 
-    // 4. Number of outputs (varint)
-    preimage.extend_from_slice(&encode_varint(tx.outputs.len() as u64));
+/// SIGHASH_ANYONECANPAY flag bit
+const SIGHASH_ANYONECANPAY_FLAG: u32 = 0x80;
 
-    // 5. Outputs (depending on sighash type)
-    match sighash_type {
-        SighashType::All => {
-            // Include all outputs
-            for output in &tx.outputs {
-                preimage.extend_from_slice(&output.value.to_le_bytes());
-                preimage.extend_from_slice(&encode_varint(output.script_pubkey.len() as u64));
-                preimage.extend_from_slice(&output.script_pubkey);
-            }
+/// Calculate the BIP143 signature hash for a SegWit v0 input
+///
+/// Unlike the legacy algorithm, the preimage commits to the spent amount and
+/// replaces per-input/per-output serialization with three digests whose contents
+/// depend on the sighash flags:
+///
+/// | flags          | hashPrevouts | hashSequence | hashOutputs             |
+/// |----------------|--------------|--------------|-------------------------|
+/// | ALL            | all inputs   | all inputs   | all outputs             |
+/// | NONE           | all inputs   | zero         | zero                    |
+/// | SINGLE         | all inputs   | zero         | output at input_index*  |
+/// | ALL \| ACP     | zero         | zero         | all outputs             |
+/// | NONE \| ACP    | zero         | zero         | zero                    |
+/// | SINGLE \| ACP  | zero         | zero         | output at input_index*  |
+///
+/// \* zero if `input_index >= tx.outputs.len()`.
+///
+/// `sighash_type` is the full hash type as committed in the preimage; only the
+/// low five bits and the ANYONECANPAY bit affect the digests, as in Core.
+///
+/// # Arguments
+/// * `tx` - The transaction being signed
+/// * `input_index` - Index of the input being signed
+/// * `script_code` - BIP143 scriptCode for the input (without length prefix)
+/// * `amount` - Value of the output spent by this input
+/// * `sighash_type` - Sighash type
+///
+/// Recomputes the transaction-wide digests on every call; use
+/// `calculate_bip143_sighash_cached` when hashing several inputs of one
+/// transaction.
+pub fn calculate_bip143_sighash(
+    tx: &Transaction,
+    input_index: usize,
+    script_code: &[u8],
+    amount: Integer,
+    sighash_type: u32,
+) -> Result<Hash> {
+    calculate_bip143_sighash_cached(
+        tx,
+        input_index,
+        script_code,
+        amount,
+        sighash_type,
+        &SighashCache::new(tx),
+    )
+}
+
+/// Calculate the BIP143 signature hash for a SegWit v0 input, reusing the
+/// transaction's precomputed digests
+///
+/// `cache` must have been built from `tx`. The result is identical to
+/// `calculate_bip143_sighash`.
+pub fn calculate_bip143_sighash_cached(
+    tx: &Transaction,
+    input_index: usize,
+    script_code: &[u8],
+    amount: Integer,
+    sighash_type: u32,
+    cache: &SighashCache,
+) -> Result<Hash> {
+    if input_index >= tx.inputs.len() {
+        return Err(crate::error::ConsensusError::InvalidInputIndex(input_index));
+    }
+
+    let preimage =
+        serialize_bip143_preimage_cached(tx, input_index, script_code, amount, sighash_type, cache);
+    Ok(double_sha256(&preimage))
+}
+
+/// BIP143 digests shared by every input of a transaction
+///
+/// hashPrevouts, hashSequence and hashOutputs over all inputs and outputs
+/// depend only on the transaction, so computing them once keeps hashing every
+/// input linear in the transaction size instead of quadratic. Build one per
+/// transaction and pass it to `calculate_bip143_sighash_cached` for each input.
+///
+/// Reference: Bitcoin Core `interpreter.h` PrecomputedTransactionData
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SighashCache {
+    hash_prevouts: Hash,
+    hash_sequence: Hash,
+    hash_outputs: Hash,
+}
+
+impl SighashCache {
+    /// Precompute the digests of `tx`
+    pub fn new(tx: &Transaction) -> Self {
+        let mut prevouts = Vec::with_capacity(tx.inputs.len() * 36);
+        let mut sequences = Vec::with_capacity(tx.inputs.len() * 4);
+        for input in &tx.inputs {
+            prevouts.extend_from_slice(&input.prevout.hash);
+            prevouts.extend_from_slice(&(input.prevout.index as u32).to_le_bytes());
+            sequences.extend_from_slice(&(input.sequence as u32).to_le_bytes());
         }
-        SighashType::None => {
-            // No outputs
+        let mut outputs = Vec::new();
+        for output in &tx.outputs {
+            serialize_output(&mut outputs, output);
         }
-        SighashType::Single => {
-            // Include output at same index as input
-            if input_index < tx.outputs.len() {
-                let output = &tx.outputs[input_index];
-                preimage.extend_from_slice(&output.value.to_le_bytes());
-                preimage.extend_from_slice(&encode_varint(output.script_pubkey.len() as u64));
-                preimage.extend_from_slice(&output.script_pubkey);
-            }
+
+        Self {
+            hash_prevouts: double_sha256(&prevouts),
+            hash_sequence: double_sha256(&sequences),
+            hash_outputs: double_sha256(&outputs),
         }
-        _ => {
-            // AnyoneCanPay combinations
-            for output in &tx.outputs {
-                preimage.extend_from_slice(&output.value.to_le_bytes());
-                preimage.extend_from_slice(&encode_varint(output.script_pubkey.len() as u64));
-                preimage.extend_from_slice(&output.script_pubkey);
+    }
+}
+
+/// BIP143 scriptCode for a witness v0 input
+///
+/// `script_pubkey` is the script of the spent output. A P2SH output is a
+/// nested witness spend (BIP141) when its redeem script is a witness program:
+/// the scriptSig must be exactly one push of that redeem script, and the
+/// redeem script must hash to the output's script hash. Native witness
+/// outputs require an empty scriptSig. The witness program then gives the
+/// scriptCode:
+///
+/// - P2WPKH: `OP_DUP OP_HASH160 <program> OP_EQUALVERIFY OP_CHECKSIG`
+/// - P2WSH: the witness script (last witness item), which must hash to the
+///   program
+///
+/// Returns `None` for inputs that are not witness v0 spends or whose scripts
+/// do not match.
+pub fn bip143_script_code(
+    script_pubkey: &[u8],
+    script_sig: &[u8],
+    witness: &[ByteString],
+) -> Option<ByteString> {
+    use crate::script_type::{classify_script, parse_pushes, ScriptType};
+
+    let program_script = match classify_script(script_pubkey) {
+        ScriptType::P2SH(script_hash) => {
+            let redeem_script = match parse_pushes(script_sig)?.as_slice() {
+                [redeem_script] => redeem_script.clone(),
+                _ => return None,
+            };
+            if hash160(&redeem_script) != script_hash {
+                return None;
             }
+            redeem_script
         }
+        _ if script_sig.is_empty() => script_pubkey.to_vec(),
+        _ => return None,
+    };
+
+    match classify_script(&program_script) {
+        ScriptType::P2WPKH(pubkey_hash) => {
+            let mut script_code = vec![0x76, 0xa9, 0x14];
+            script_code.extend_from_slice(&pubkey_hash);
+            script_code.extend_from_slice(&[0x88, 0xac]);
+            Some(script_code)
+        }
+        ScriptType::P2WSH(script_hash) => {
+            let witness_script = witness.last()?;
+            (crate::crypto::sha256(witness_script) == script_hash).then(|| witness_script.clone())
+        }
+        _ => None,
     }
+}
 
-    // 6. Lock time (4 bytes, little endian)
-    preimage.extend_from_slice(&tx.lock_time.to_le_bytes());
+/// RIPEMD160(SHA256(data))
+pub(crate) fn hash160(data: &[u8]) -> [u8; 20] {
+    use ripemd::Ripemd160;
+    let mut hash = [0u8; 20];
+    hash.copy_from_slice(&Ripemd160::digest(Sha256::digest(data)));
+    hash
+}
 
-    // 7. SIGHASH type (4 bytes, little endian)
-    preimage.extend_from_slice(&(sighash_type as u32).to_le_bytes());
+/// Serialize the BIP143 sighash preimage (see `calculate_bip143_sighash`)
+#[cfg(test)]
+fn serialize_bip143_preimage(
+    tx: &Transaction,
+    input_index: usize,
+    script_code: &[u8],
+    amount: Integer,
+    sighash_type: u32,
+) -> Vec<u8> {
+    let cache = SighashCache::new(tx);
+    serialize_bip143_preimage_cached(tx, input_index, script_code, amount, sighash_type, &cache)
+}
 
-    // Calculate double SHA256 hash
-    let first_hash = Sha256::digest(&preimage);
-    let second_hash = Sha256::digest(first_hash);
+/// Serialize the BIP143 sighash preimage from precomputed digests
+fn serialize_bip143_preimage_cached(
+    tx: &Transaction,
+    input_index: usize,
+    script_code: &[u8],
+    amount: Integer,
+    sighash_type: u32,
+    cache: &SighashCache,
+) -> Vec<u8> {
+    let anyone_can_pay = sighash_type & SIGHASH_ANYONECANPAY_FLAG != 0;
+    let base_type = sighash_type & 0x1f;
+    let is_none = base_type == SighashType::None as u32;
+    let is_single = base_type == SighashType::Single as u32;
+
+    // hashPrevouts: zero with ANYONECANPAY
+    let hash_prevouts = if anyone_can_pay {
+        [0u8; 32]
+    } else {
+        cache.hash_prevouts
+    };
+
+    // hashSequence: zero with ANYONECANPAY, SINGLE or NONE
+    let hash_sequence = if anyone_can_pay || is_single || is_none {
+        [0u8; 32]
+    } else {
+        cache.hash_sequence
+    };
 
+    // hashOutputs: all outputs, only the matching output for SINGLE, zero otherwise
+    let hash_outputs = if !is_single && !is_none {
+        cache.hash_outputs
+    } else if is_single && input_index < tx.outputs.len() {
+        let mut data = Vec::new();
+        serialize_output(&mut data, &tx.outputs[input_index]);
+        double_sha256(&data)
+    } else {
+        [0u8; 32]
+    };
+
+    let input = &tx.inputs[input_index];
+    let mut preimage = Vec::with_capacity(156 + script_code.len());
+
+    // 1. nVersion
+    preimage.extend_from_slice(&(tx.version as u32).to_le_bytes());
+    // 2. hashPrevouts
+    preimage.extend_from_slice(&hash_prevouts);
+    // 3. hashSequence
+    preimage.extend_from_slice(&hash_sequence);
+    // 4. outpoint
+    preimage.extend_from_slice(&input.prevout.hash);
+    preimage.extend_from_slice(&(input.prevout.index as u32).to_le_bytes());
+    // 5. scriptCode
+    preimage.extend_from_slice(&encode_varint(script_code.len() as u64));
+    preimage.extend_from_slice(script_code);
+    // 6. amount
+    preimage.extend_from_slice(&amount.to_le_bytes());
+    // 7. nSequence
+    preimage.extend_from_slice(&(input.sequence as u32).to_le_bytes());
+    // 8. hashOutputs
+    preimage.extend_from_slice(&hash_outputs);
+    // 9. nLocktime
+    preimage.extend_from_slice(&(tx.lock_time as u32).to_le_bytes());
+    // 10. sighash type
+    preimage.extend_from_slice(&sighash_type.to_le_bytes());
+
+    preimage
+}
+
+fn serialize_output(data: &mut Vec<u8>, output: &TransactionOutput) {
+    data.extend_from_slice(&output.value.to_le_bytes());
+    data.extend_from_slice(&encode_varint(output.script_pubkey.len() as u64));
+    data.extend_from_slice(&output.script_pubkey);
+}
+
+fn double_sha256(data: &[u8]) -> Hash {
+    let first_hash = Sha256::digest(data);
+    let second_hash = Sha256::digest(first_hash);
     let mut result = [0u8; 32];
     result.copy_from_slice(&second_hash);
-
-    Ok(result)
+    result
 }
 
 /// Batch compute sighashes for all inputs of a transaction
@@ -258,7 +523,7 @@ pub fn calculate_transaction_sighash(
 /// Vector of 32-byte hashes, one per input (in same order)
 pub fn batch_compute_sighashes(
     tx: &Transaction,
-    prevouts: &[TransactionOutput],
+    prevouts: &[PrevOut],
     sighash_type: SighashType,
 ) -> Result<Vec<Hash>> {
     // Validate prevouts match inputs
@@ -281,7 +546,7 @@ pub fn batch_compute_sighashes(
                 (0..tx.inputs.len())
                     .into_par_iter()
                     .map(|input_index| {
-                        serialize_sighash_preimage(tx, input_index, prevouts, sighash_type)
+                        serialize_sighash_preimage(tx, input_index, prevouts, sighash_type as u32)
                     })
                     .collect()
             }
@@ -289,7 +554,7 @@ pub fn batch_compute_sighashes(
             {
                 (0..tx.inputs.len())
                     .map(|input_index| {
-                        serialize_sighash_preimage(tx, input_index, prevouts, sighash_type)
+                        serialize_sighash_preimage(tx, input_index, prevouts, sighash_type as u32)
                     })
                     .collect()
             }
@@ -297,7 +562,18 @@ pub fn batch_compute_sighashes(
 
         // Batch hash all preimages using double SHA256
         let preimage_refs: Vec<&[u8]> = preimages.iter().map(|v| v.as_slice()).collect();
-        Ok(simd_vectorization::batch_double_sha256(&preimage_refs))
+        let mut hashes = simd_vectorization::batch_double_sha256(&preimage_refs);
+
+        // SIGHASH_SINGLE inputs past the last output sign the constant 1
+        if matches!(
+            sighash_type,
+            SighashType::Single | SighashType::SingleAnyoneCanPay
+        ) {
+            for hash in hashes.iter_mut().skip(tx.outputs.len()) {
+                *hash = SIGHASH_SINGLE_BUG_HASH;
+            }
+        }
+        Ok(hashes)
     }
 
     #[cfg(not(feature = "production"))]
@@ -316,83 +592,94 @@ pub fn batch_compute_sighashes(
     }
 }
 
-/// Serialize sighash preimage (helper for batch computation)
+/// Serialize the legacy sighash preimage of an input
+///
+/// Shared by `calculate_transaction_sighash` and `batch_compute_sighashes`.
+/// As in Core's `CTransactionSignatureSerializer`, the transaction is
+/// serialized with its 4-byte fields, the signed input's scriptSig as its
+/// script, and empty scripts in the other inputs, then adjusted for the hash
+/// type:
 ///
-/// This extracts the serialization logic from calculate_transaction_sighash
-/// to allow batch processing.
-#[allow(dead_code)]
+/// - ANYONECANPAY (bit 7): only the signed input is serialized
+/// - NONE (low five bits 2): no outputs, and the other inputs' sequences are
+///   zero
+/// - SINGLE (low five bits 3): outputs up to the signed input's index, the
+///   earlier ones blank (value -1, empty script), and the other inputs'
+///   sequences are zero
+///
+/// Any other type signs all outputs. `hash_type` is appended as is. The
+/// caller handles SIGHASH_SINGLE without a matching output.
 fn serialize_sighash_preimage(
     tx: &Transaction,
     input_index: usize,
-    _prevouts: &[TransactionOutput],
-    sighash_type: SighashType,
+    _prevouts: &[PrevOut],
+    hash_type: u32,
 ) -> Vec<u8> {
     let mut preimage = Vec::new();
 
     // 1. Transaction version (4 bytes, little endian)
-    preimage.extend_from_slice(&tx.version.to_le_bytes());
-
-    // 2. Number of inputs (varint)
-    preimage.extend_from_slice(&encode_varint(tx.inputs.len() as u64));
-
-    // 3. Inputs (depending on sighash type)
+    preimage.extend_from_slice(&(tx.version as u32).to_le_bytes());
+
+    // 2-3. Inputs: all of them, or only the signed one under ANYONECANPAY
+    let anyone_can_pay = hash_type & SIGHASH_ANYONECANPAY_FLAG != 0;
+    let hash_none = hash_type & 0x1f == SighashType::None as u32;
+    let hash_single = hash_type & 0x1f == SighashType::Single as u32;
+    let zero_other_sequences = hash_none || hash_single;
+    let input_count = if anyone_can_pay { 1 } else { tx.inputs.len() };
+    preimage.extend_from_slice(&encode_varint(input_count as u64));
     for (i, input) in tx.inputs.iter().enumerate() {
-        if matches!(sighash_type, SighashType::AnyoneCanPay) || i == input_index {
-            // Include this input
-            preimage.extend_from_slice(&input.prevout.hash);
-            preimage.extend_from_slice(&input.prevout.index.to_le_bytes());
+        if anyone_can_pay && i != input_index {
+            continue;
+        }
+        preimage.extend_from_slice(&input.prevout.hash);
+        preimage.extend_from_slice(&(input.prevout.index as u32).to_le_bytes());
+        if i == input_index {
             preimage.extend_from_slice(&encode_varint(input.script_sig.len() as u64));
             preimage.extend_from_slice(&input.script_sig);
-            preimage.extend_from_slice(&input.sequence.to_le_bytes());
         } else {
-            // Skip this input (use dummy values)
-            preimage.extend_from_slice(&[0u8; 32]); // prevout hash
-            preimage.extend_from_slice(&[0u8; 4]); // prevout index
             preimage.push(0); // empty script_sig
-            preimage.extend_from_slice(&[0u8; 4]); // sequence
         }
+        let sequence = if i != input_index && zero_other_sequences {
+            0
+        } else {
+            input.sequence as u32
+        };
+        preimage.extend_from_slice(&sequence.to_le_bytes());
     }
 
-    // 4. Number of outputs (varint)
-    preimage.extend_from_slice(&encode_varint(tx.outputs.len() as u64));
-
-    // 5. Outputs (depending on sighash type)
-    match sighash_type {
-        SighashType::All => {
-            // Include all outputs
-            for output in &tx.outputs {
-                preimage.extend_from_slice(&output.value.to_le_bytes());
-                preimage.extend_from_slice(&encode_varint(output.script_pubkey.len() as u64));
-                preimage.extend_from_slice(&output.script_pubkey);
-            }
-        }
-        SighashType::None => {
-            // No outputs
-        }
-        SighashType::Single => {
-            // Include output at same index as input
-            if input_index < tx.outputs.len() {
-                let output = &tx.outputs[input_index];
+    // 4-5. Outputs (depending on sighash type)
+    if hash_none {
+        // No outputs
+        preimage.push(0);
+    } else if hash_single {
+        // Outputs up to the signed input's index, only that one filled in
+        let outputs = &tx.outputs[..(input_index + 1).min(tx.outputs.len())];
+        preimage.extend_from_slice(&encode_varint(outputs.len() as u64));
+        for (i, output) in outputs.iter().enumerate() {
+            if i == input_index {
                 preimage.extend_from_slice(&output.value.to_le_bytes());
                 preimage.extend_from_slice(&encode_varint(output.script_pubkey.len() as u64));
                 preimage.extend_from_slice(&output.script_pubkey);
+            } else {
+                preimage.extend_from_slice(&(-1i64).to_le_bytes());
+                preimage.push(0); // empty script_pubkey
             }
         }
-        _ => {
-            // AnyoneCanPay combinations
-            for output in &tx.outputs {
-                preimage.extend_from_slice(&output.value.to_le_bytes());
-                preimage.extend_from_slice(&encode_varint(output.script_pubkey.len() as u64));
-                preimage.extend_from_slice(&output.script_pubkey);
-            }
+    } else {
+        // Include all outputs
+        preimage.extend_from_slice(&encode_varint(tx.outputs.len() as u64));
+        for output in &tx.outputs {
+            preimage.extend_from_slice(&output.value.to_le_bytes());
+            preimage.extend_from_slice(&encode_varint(output.script_pubkey.len() as u64));
+            preimage.extend_from_slice(&output.script_pubkey);
         }
     }
 
     // 6. Lock time (4 bytes, little endian)
-    preimage.extend_from_slice(&tx.lock_time.to_le_bytes());
+    preimage.extend_from_slice(&(tx.lock_time as u32).to_le_bytes());
 
     // 7. SIGHASH type (4 bytes, little endian)
-    preimage.extend_from_slice(&(sighash_type as u32).to_le_bytes());
+    preimage.extend_from_slice(&hash_type.to_le_bytes());
 
     preimage
 }
@@ -451,6 +738,11 @@ mod tests {
         assert_eq!(SighashType::from_byte(0x02).unwrap(), SighashType::None);
         assert_eq!(SighashType::from_byte(0x03).unwrap(), SighashType::Single);
         assert!(SighashType::from_byte(0x00).is_err());
+
+        // ANYONECANPAY is kept, so the committed hash type is the original byte
+        for byte in [0x81, 0x82, 0x83] {
+            assert_eq!(SighashType::from_byte(byte).unwrap() as u32, byte as u32);
+        }
     }
 
     #[test]
@@ -511,6 +803,362 @@ mod tests {
         assert_ne!(sighash, sighash_single);
     }
 
+    #[test]
+    fn test_sighash_single_without_matching_output() {
+        let input = |index| TransactionInput {
+            prevout: OutPoint {
+                hash: [1u8; 32],
+                index,
+            },
+            script_sig: vec![],
+            sequence: 0xffffffff,
+        };
+        let tx = Transaction {
+            version: 1,
+            inputs: vec![input(0), input(1)].into(),
+            outputs: vec![TransactionOutput {
+                value: 5000000000,
+                script_pubkey: vec![0x51],
+            }]
+            .into(),
+            lock_time: 0,
+        };
+        let prevouts = vec![
+            TransactionOutput {
+                value: 10000000000,
+                script_pubkey: vec![0x51],
+            };
+            2
+        ];
+
+        // Input 1 has no output 1: the sentinel, not a hash of a preimage
+        let mut one = [0u8; 32];
+        one[0] = 1;
+        assert_eq!(
+            calculate_transaction_sighash(&tx, 1, &prevouts, SighashType::Single).unwrap(),
+            one
+        );
+        assert_eq!(
+            calculate_transaction_sighash(&tx, 1, &prevouts, SighashType::from_byte(0x83).unwrap())
+                .unwrap(),
+            one
+        );
+        let batch = batch_compute_sighashes(&tx, &prevouts, SighashType::Single).unwrap();
+        assert_eq!(batch[1], one);
+        assert_ne!(batch[0], one);
+
+        // Input 0 has a matching output, and other types are unaffected
+        for sighash_type in [SighashType::Single, SighashType::All, SighashType::None] {
+            assert_ne!(
+                calculate_transaction_sighash(&tx, 0, &prevouts, sighash_type).unwrap(),
+                one
+            );
+        }
+        assert_ne!(
+            calculate_transaction_sighash(&tx, 1, &prevouts, SighashType::All).unwrap(),
+            one
+        );
+    }
+
+    #[test]
+    fn test_bip143_native_p2wpkh_vector() {
+        // BIP143 "Native P2WPKH" example, second input
+        let tx = crate::serialization::deserialize_transaction(
+            &hex::decode(
+                "0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f00000000\
+                 00eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a01000000\
+                 00ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac90\
+                 93510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000",
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let script_code =
+            hex::decode("76a9141d0f172a0ecb48aee1be1f2687d2963ae33f71a188ac").unwrap();
+
+        // Intermediate digests as listed in BIP143
+        let preimage = serialize_bip143_preimage(&tx, 1, &script_code, 600_000_000, 0x01);
+        assert_eq!(
+            hex::encode(&preimage[4..36]),
+            "96b827c8483d4e9b96712b6713a7b68d6e8003a781feba36c31143470b4efd37"
+        );
+        assert_eq!(
+            hex::encode(&preimage[36..68]),
+            "52b0a642eea2fb7ae638c36f6252b6750293dbe574a806984b8e4d8548339a3b"
+        );
+        let outputs_offset = preimage.len() - 4 - 4 - 32;
+        assert_eq!(
+            hex::encode(&preimage[outputs_offset..outputs_offset + 32]),
+            "863ef3e1a92afbfdb97f31ad0fc7683ee943e9abcf2501590ff8f6551f47e5e5"
+        );
+
+        let sighash = calculate_bip143_sighash(&tx, 1, &script_code, 600_000_000, 0x01).unwrap();
+        assert_eq!(
+            hex::encode(sighash),
+            "c37af31116d1b27caf68aae9e3ac82f1477929014d5b917657d0eb49478cb670"
+        );
+    }
+
+    #[test]
+    fn test_bip143_p2sh_p2wpkh_spend() {
+        use crate::crypto::signature::{Secp256k1Verifier, SignatureVerifier};
+
+        // BIP143 "P2SH-P2WPKH" example
+        let tx = crate::serialization::deserialize_transaction(
+            &hex::decode(
+                "0100000001db6b1b20aa0fd7b23880be2ecbd4a98130974cf4748fb66092ac4d3ceb1a54770100000000\
+                 feffffff02b8b4eb0b000000001976a914a457b684d7f0d539a46a45bbc043f35b59d0d96388ac0008\
+                 af2f000000001976a914fd270b1ee6abcaea97fea7ad0402e8bd8ad6d77c88ac92040000",
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let script_pubkey = hex::decode("a9144733f37cf4db86fbc2efed2500b4f4e49f31202387").unwrap();
+        let redeem_script = hex::decode("001479091972186c449eb1ded22b78e40d009bdf0089").unwrap();
+        let mut script_sig = vec![redeem_script.len() as u8];
+        script_sig.extend_from_slice(&redeem_script);
+        let signature = hex::decode(
+            "3044022047ac8e878352d3ebbde1c94ce3a10d057c24175747116f8288e5d794d12d482f0220217f36a4\
+             85cae903c713331d877c1f64677e3622ad4010726870540656fe9dcb01",
+        )
+        .unwrap();
+        let pubkey =
+            hex::decode("03ad1d8e89212f0b92c74d23bb710c00662ad1470198ac48c43f7d6f93a2a26873")
+                .unwrap();
+        let witness = vec![signature.clone(), pubkey.clone()];
+
+        // The scriptCode comes from the program inside the redeem script
+        let script_code = bip143_script_code(&script_pubkey, &script_sig, &witness).unwrap();
+        assert_eq!(
+            hex::encode(&script_code),
+            "76a91479091972186c449eb1ded22b78e40d009bdf008988ac"
+        );
+        assert_eq!(hash160(&pubkey).as_slice(), &redeem_script[2..]);
+
+        let sighash = calculate_bip143_sighash(&tx, 0, &script_code, 1_000_000_000, 0x01).unwrap();
+        assert_eq!(
+            hex::encode(sighash),
+            "64f3b0f4dd2bb3aa1ce8566d220cc74dda9df97d8490cc81d89d735c92e59fb6"
+        );
+        let (&hash_type, der) = signature.split_last().unwrap();
+        assert_eq!(hash_type, 0x01);
+        assert!(Secp256k1Verifier::new().verify_ecdsa(&pubkey, der, &sighash));
+
+        // The spend itself verifies through the script interpreter
+        use crate::constants::{SCRIPT_VERIFY_P2SH, SCRIPT_VERIFY_WITNESS};
+        let verify = |tx: &Transaction, script_sig: &ByteString, witness: &[ByteString]| {
+            let prevouts = vec![PrevOut {
+                value: 1_000_000_000,
+                script_pubkey: script_pubkey.clone(),
+            }];
+            crate::script::verify_script_with_witness(
+                script_sig,
+                &script_pubkey,
+                witness,
+                SCRIPT_VERIFY_P2SH | SCRIPT_VERIFY_WITNESS,
+                tx,
+                0,
+                &prevouts,
+                &SighashCache::new(tx),
+                None,
+                None,
+                crate::types::Network::Mainnet,
+                crate::crypto::signature::default_verifier(),
+            )
+            .unwrap()
+        };
+        assert!(verify(&tx, &script_sig, &witness));
+        assert!(!verify(&tx, &script_sig, &witness[..1]));
+        assert!(!verify(&tx, &redeem_script, &witness));
+        let mut tampered = tx.clone();
+        tampered.outputs[1].value -= 1;
+        assert!(!verify(&tampered, &script_sig, &witness));
+
+        // The redeem script must be the only push and match the script hash
+        let mut extra_push = vec![0x51];
+        extra_push.extend_from_slice(&script_sig);
+        assert_eq!(
+            bip143_script_code(&script_pubkey, &extra_push, &witness),
+            None
+        );
+        let mut wrong_program = script_sig.clone();
+        wrong_program[3] ^= 1;
+        assert_eq!(
+            bip143_script_code(&script_pubkey, &wrong_program, &witness),
+            None
+        );
+
+        // Native P2WPKH needs an empty scriptSig
+        assert_eq!(
+            bip143_script_code(&redeem_script, &[], &witness),
+            Some(script_code)
+        );
+        assert_eq!(
+            bip143_script_code(&redeem_script, &script_sig, &witness),
+            None
+        );
+
+        // P2WSH, nested or not: the witness script, if it matches the program
+        let witness_script = vec![0x51];
+        let mut p2wsh = vec![0x00, 0x20];
+        p2wsh.extend_from_slice(&crate::crypto::sha256(&witness_script));
+        let mut p2sh_p2wsh = vec![0xa9, 0x14];
+        p2sh_p2wsh.extend_from_slice(&hash160(&p2wsh));
+        p2sh_p2wsh.push(0x87);
+        let mut nested_sig = vec![p2wsh.len() as u8];
+        nested_sig.extend_from_slice(&p2wsh);
+        let witness = vec![witness_script.clone()];
+        assert_eq!(
+            bip143_script_code(&p2wsh, &[], &witness),
+            Some(witness_script.clone())
+        );
+        assert_eq!(
+            bip143_script_code(&p2sh_p2wsh, &nested_sig, &witness),
+            Some(witness_script)
+        );
+        assert_eq!(bip143_script_code(&p2wsh, &[], &[vec![0x52]]), None);
+    }
+
+    #[test]
+    fn test_bip143_sighash_flag_combinations() {
+        let tx = Transaction {
+            version: 2,
+            inputs: (0..2u8)
+                .map(|i| TransactionInput {
+                    prevout: OutPoint {
+                        hash: [i + 1; 32],
+                        index: i as u64,
+                    },
+                    script_sig: vec![],
+                    sequence: 0xfffffffd,
+                })
+                .collect(),
+            outputs: (0..2u8)
+                .map(|i| TransactionOutput {
+                    value: 1000 * (i as i64 + 1),
+                    script_pubkey: vec![0x51 + i],
+                })
+                .collect(),
+            lock_time: 0,
+        };
+        let script_code = vec![0x76, 0xa9, 0x14, 0x00, 0x88, 0xac];
+
+        let mut all_prevouts = Vec::new();
+        let mut all_sequences = Vec::new();
+        let mut all_outputs = Vec::new();
+        for input in &tx.inputs {
+            all_prevouts.extend_from_slice(&input.prevout.hash);
+            all_prevouts.extend_from_slice(&(input.prevout.index as u32).to_le_bytes());
+            all_sequences.extend_from_slice(&(input.sequence as u32).to_le_bytes());
+        }
+        for output in &tx.outputs {
+            serialize_output(&mut all_outputs, output);
+        }
+        let mut second_output = Vec::new();
+        serialize_output(&mut second_output, &tx.outputs[1]);
+
+        let zero = [0u8; 32];
+        let hash_prevouts = double_sha256(&all_prevouts);
+        let hash_sequence = double_sha256(&all_sequences);
+        let hash_all_outputs = double_sha256(&all_outputs);
+        let hash_single_output = double_sha256(&second_output);
+
+        // hashOutputs sits after version, two digests, outpoint, scriptCode,
+        // amount and nSequence
+        let outputs_offset = 4 + 32 + 32 + 36 + 1 + script_code.len() + 8 + 4;
+
+        // (flags, hashPrevouts, hashSequence, hashOutputs) for input 1
+        let cases = [
+            (0x01, hash_prevouts, hash_sequence, hash_all_outputs),
+            (0x02, hash_prevouts, zero, zero),
+            (0x03, hash_prevouts, zero, hash_single_output),
+            (0x81, zero, zero, hash_all_outputs),
+            (0x82, zero, zero, zero),
+            (0x83, zero, zero, hash_single_output),
+        ];
+        let mut sighashes = Vec::new();
+        for (flags, prevouts, sequence, outputs) in cases {
+            let preimage = serialize_bip143_preimage(&tx, 1, &script_code, 5000, flags);
+            assert_eq!(&preimage[4..36], &prevouts, "hashPrevouts for {flags:#x}");
+            assert_eq!(&preimage[36..68], &sequence, "hashSequence for {flags:#x}");
+            assert_eq!(
+                &preimage[outputs_offset..outputs_offset + 32],
+                &outputs,
+                "hashOutputs for {flags:#x}"
+            );
+            assert_eq!(&preimage[preimage.len() - 4..], &flags.to_le_bytes());
+            sighashes.push(calculate_bip143_sighash(&tx, 1, &script_code, 5000, flags).unwrap());
+        }
+
+        // Every combination commits to a different preimage
+        for i in 0..sighashes.len() {
+            for j in i + 1..sighashes.len() {
+                assert_ne!(sighashes[i], sighashes[j]);
+            }
+        }
+
+        // SINGLE with no matching output hashes zero outputs, with and without ACP
+        let mut short_tx = tx.clone();
+        short_tx.outputs.truncate(1);
+        for flags in [0x03, 0x83] {
+            let preimage = serialize_bip143_preimage(&short_tx, 1, &script_code, 5000, flags);
+            assert_eq!(&preimage[outputs_offset..outputs_offset + 32], &zero);
+        }
+    }
+
+    #[test]
+    fn test_bip143_sighash_cache_matches_uncached() {
+        // 100 inputs: the cached path hashes the shared digests once instead
+        // of once per input
+        let tx = Transaction {
+            version: 2,
+            inputs: (0..100u32)
+                .map(|i| TransactionInput {
+                    prevout: OutPoint {
+                        hash: [i as u8; 32],
+                        index: i as u64,
+                    },
+                    script_sig: vec![],
+                    sequence: 0xfffffffd - i as u64,
+                })
+                .collect(),
+            outputs: (0..3u8)
+                .map(|i| TransactionOutput {
+                    value: 1000 * (i as i64 + 1),
+                    script_pubkey: vec![0x51 + i],
+                })
+                .collect(),
+            lock_time: 0,
+        };
+        let script_code = vec![0x76, 0xa9, 0x14, 0x00, 0x88, 0xac];
+        let cache = SighashCache::new(&tx);
+
+        for flags in [0x01, 0x02, 0x03, 0x81, 0x82, 0x83] {
+            for input_index in 0..tx.inputs.len() {
+                let amount = 5000 + input_index as i64;
+                assert_eq!(
+                    calculate_bip143_sighash_cached(
+                        &tx,
+                        input_index,
+                        &script_code,
+                        amount,
+                        flags,
+                        &cache
+                    )
+                    .unwrap(),
+                    calculate_bip143_sighash(&tx, input_index, &script_code, amount, flags)
+                        .unwrap(),
+                    "input {input_index}, flags {flags:#x}"
+                );
+            }
+        }
+
+        assert!(matches!(
+            calculate_bip143_sighash_cached(&tx, 100, &script_code, 0, 0x01, &cache),
+            Err(crate::error::ConsensusError::InvalidInputIndex(100))
+        ));
+    }
+
     #[test]
     fn test_sighash_invalid_input_index() {
         let tx = Transaction {
@@ -197,6 +197,9 @@ impl std::ops::Deref for BlockHash {
 
 /// OutPoint: 𝒪 = ℍ × ℕ
 ///
+/// Content-addressable: an outpoint uniquely names a transaction output, so it
+/// is the key of the UTXO set and of duplicate-input checks.
+///
 /// Performance optimization: Cache-line aligned for better memory access patterns
 #[repr(align(64))]
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -209,7 +212,7 @@ pub struct OutPoint {
 ///
 /// Performance optimization: Hot fields (prevout, sequence) grouped together
 /// for better cache locality. script_sig is accessed less frequently.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct TransactionInput {
     pub prevout: OutPoint,      // Hot: 40 bytes (frequently accessed)
     pub sequence: Natural,      // Hot: 8 bytes (frequently accessed)
@@ -217,17 +220,28 @@ pub struct TransactionInput {
 }
 
 /// Transaction Output: 𝒯 = ℤ × 𝕊
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct TransactionOutput {
     pub value: Integer,
     pub script_pubkey: ByteString,
 }
 
+/// Spent output: the amount and scriptPubKey of the output an input spends
+///
+/// Same shape as `TransactionOutput`, but named for its role in signature
+/// verification: BIP143 commits to the spent amount, and BIP341 to the
+/// amounts and scripts of every spent output. Verification APIs take
+/// `&[PrevOut]`, one entry per input in input order.
+pub type PrevOut = TransactionOutput;
+
 /// Transaction: 𝒯𝒳 = ℕ × ℐ* × 𝒯* × ℕ
 ///
 /// Performance optimization: Uses SmallVec for inputs/outputs to eliminate
 /// heap allocations for the common case of 1-2 inputs/outputs (80%+ of transactions).
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+///
+/// Content-addressable: equality and hashing cover every field, matching the
+/// fields committed to by the txid (witness data is held separately).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Transaction {
     pub version: Natural,
     #[cfg(feature = "production")]
@@ -242,7 +256,10 @@ pub struct Transaction {
 }
 
 /// Block Header: ℋ = ℤ × ℍ × ℍ × ℕ × ℕ × ℕ
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+///
+/// Content-addressable: equality and hashing cover exactly the fields
+/// committed to by the block hash.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct BlockHeader {
     pub version: Integer,
     pub prev_block_hash: Hash,
@@ -257,14 +274,14 @@ pub struct BlockHeader {
 /// Performance optimization: Uses Box<[Transaction]> instead of Vec<Transaction>
 /// since transactions are never modified after block creation. This saves 8 bytes
 /// (no capacity field) and provides better cache usage.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Block {
     pub header: BlockHeader,
     pub transactions: Box<[Transaction]>,
 }
 
 /// UTXO: 𝒰 = ℤ × 𝕊 × ℕ
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
 pub struct UTXO {
     pub value: Integer,
     pub script_pubkey: ByteString,
@@ -277,6 +294,24 @@ pub struct UTXO {
 /// UTXO Set: 𝒰𝒮 = 𝒪 → 𝒰
 pub type UtxoSet = HashMap<OutPoint, UTXO>;
 
+/// Deterministic iteration over a `UtxoSet`
+///
+/// `HashMap` iteration order differs between runs, so anything that
+/// serializes or hashes a UTXO set in sequence (snapshots, commitments) must
+/// iterate through `iter_sorted` to be reproducible.
+pub trait UtxoSetExt {
+    /// Entries ordered by `(txid, index)`
+    fn iter_sorted(&self) -> std::vec::IntoIter<(&OutPoint, &UTXO)>;
+}
+
+impl UtxoSetExt for UtxoSet {
+    fn iter_sorted(&self) -> std::vec::IntoIter<(&OutPoint, &UTXO)> {
+        let mut entries: Vec<_> = self.iter().collect();
+        entries.sort_unstable_by_key(|(outpoint, _)| (outpoint.hash, outpoint.index));
+        entries.into_iter()
+    }
+}
+
 /// Validation result
 ///
 /// Important: This result must be checked - ignoring validation results
@@ -288,6 +323,188 @@ pub enum ValidationResult {
     Invalid(String),
 }
 
+impl ValidationResult {
+    /// Reject reason for an invalid result, `None` if valid
+    ///
+    /// Messages that are not one of Core's reject strings become `RejectReason::Other`.
+    pub fn reject_reason(&self) -> Option<RejectReason> {
+        match self {
+            ValidationResult::Valid => None,
+            ValidationResult::Invalid(message) => Some(RejectReason::from_message(message)),
+        }
+    }
+
+    /// BIP61 `reject` message code and reason string for an invalid result
+    ///
+    /// Returns `None` for `Valid`.
+    pub fn to_reject(&self) -> Option<(u8, String)> {
+        self.reject_reason()
+            .map(|reason| (reason.code(), reason.to_string()))
+    }
+}
+
+/// BIP61 reject code: message could not be decoded
+pub const REJECT_MALFORMED: u8 = 0x01;
+/// BIP61 reject code: consensus-invalid
+pub const REJECT_INVALID: u8 = 0x10;
+/// BIP61 reject code: obsolete version
+pub const REJECT_OBSOLETE: u8 = 0x11;
+/// BIP61 reject code: duplicate (already known)
+pub const REJECT_DUPLICATE: u8 = 0x12;
+/// BIP61 reject code: valid but not standard
+pub const REJECT_NONSTANDARD: u8 = 0x40;
+/// BIP61 reject code: output below dust threshold
+pub const REJECT_DUST: u8 = 0x41;
+/// BIP61 reject code: fee too low for relay or mining
+pub const REJECT_INSUFFICIENTFEE: u8 = 0x42;
+/// BIP61 reject code: conflicts with a checkpoint
+pub const REJECT_CHECKPOINT: u8 = 0x43;
+
+/// Reason a transaction or block was rejected
+///
+/// Each variant displays as Bitcoin Core's reject string (e.g. `bad-txns-vin-empty`)
+/// and maps to a BIP61 reject code, so validation failures can be relayed as
+/// `reject` messages that other implementations understand.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RejectReason {
+    /// Transaction has no inputs
+    VinEmpty,
+    /// Transaction has no outputs
+    VoutEmpty,
+    /// Transaction exceeds the maximum size
+    Oversize,
+    /// Output value is negative
+    VoutNegative,
+    /// Output value exceeds MAX_MONEY
+    VoutTooLarge,
+    /// Sum of output values exceeds MAX_MONEY
+    TxOutTotalTooLarge,
+    /// Same prevout spent twice in one transaction
+    InputsDuplicate,
+    /// Non-coinbase input spends a null prevout
+    PrevoutNull,
+    /// Coinbase scriptSig length outside 2..=100 bytes
+    CoinbaseLength,
+    /// Block's first transaction is not a well-formed coinbase
+    CoinbaseMissing,
+    /// Input spends a missing or already spent output
+    InputsMissingOrSpent,
+    /// Coinbase output spent before maturity
+    PrematureCoinbaseSpend,
+    /// Spent output value or input total outside [0, MAX_MONEY]
+    InputValuesOutOfRange,
+    /// Input value is below output value
+    InBelowOut,
+    /// Transaction is not final
+    NonFinal,
+    /// Script verification failed under consensus flags
+    ScriptVerifyFailed,
+    /// Transaction is not standard
+    NonStandard,
+    /// Sigop cost, counting P2SH redeem scripts, exceeds the per-transaction cap
+    TooManySigops,
+    /// Output is below the dust threshold
+    Dust,
+    /// Fee is below the minimum relay fee
+    InsufficientFee,
+    /// Transaction is already in the mempool
+    AlreadyInMempool,
+    /// Transaction conflicts with a mempool transaction
+    MempoolConflict,
+    /// Any other failure, carrying its message
+    Other(String),
+}
+
+impl RejectReason {
+    /// BIP61 reject code for this reason
+    pub fn code(&self) -> u8 {
+        match self {
+            RejectReason::NonStandard | RejectReason::TooManySigops => REJECT_NONSTANDARD,
+            RejectReason::Dust => REJECT_DUST,
+            RejectReason::InsufficientFee => REJECT_INSUFFICIENTFEE,
+            RejectReason::AlreadyInMempool | RejectReason::MempoolConflict => REJECT_DUPLICATE,
+            _ => REJECT_INVALID,
+        }
+    }
+
+    /// Core's reject string for this reason
+    pub fn as_str(&self) -> &str {
+        match self {
+            RejectReason::VinEmpty => "bad-txns-vin-empty",
+            RejectReason::VoutEmpty => "bad-txns-vout-empty",
+            RejectReason::Oversize => "bad-txns-oversize",
+            RejectReason::VoutNegative => "bad-txns-vout-negative",
+            RejectReason::VoutTooLarge => "bad-txns-vout-toolarge",
+            RejectReason::TxOutTotalTooLarge => "bad-txns-txouttotal-toolarge",
+            RejectReason::InputsDuplicate => "bad-txns-inputs-duplicate",
+            RejectReason::PrevoutNull => "bad-txns-prevout-null",
+            RejectReason::CoinbaseLength => "bad-cb-length",
+            RejectReason::CoinbaseMissing => "bad-cb-missing",
+            RejectReason::InputsMissingOrSpent => "bad-txns-inputs-missingorspent",
+            RejectReason::PrematureCoinbaseSpend => "bad-txns-premature-spend-of-coinbase",
+            RejectReason::InputValuesOutOfRange => "bad-txns-inputvalues-outofrange",
+            RejectReason::InBelowOut => "bad-txns-in-belowout",
+            RejectReason::NonFinal => "bad-txns-nonfinal",
+            RejectReason::ScriptVerifyFailed => "mandatory-script-verify-flag-failed",
+            RejectReason::NonStandard => "non-standard",
+            RejectReason::TooManySigops => "bad-txns-too-many-sigops",
+            RejectReason::Dust => "dust",
+            RejectReason::InsufficientFee => "min relay fee not met",
+            RejectReason::AlreadyInMempool => "txn-already-in-mempool",
+            RejectReason::MempoolConflict => "txn-mempool-conflict",
+            RejectReason::Other(message) => message,
+        }
+    }
+
+    /// Parse a validation message back into a reason
+    ///
+    /// Matches Core's reject strings, optionally followed by `: <detail>`;
+    /// anything else is `Other`.
+    pub fn from_message(message: &str) -> Self {
+        let reason = message.split(": ").next().unwrap_or(message);
+        const KNOWN: [RejectReason; 22] = [
+            RejectReason::VinEmpty,
+            RejectReason::VoutEmpty,
+            RejectReason::Oversize,
+            RejectReason::VoutNegative,
+            RejectReason::VoutTooLarge,
+            RejectReason::TxOutTotalTooLarge,
+            RejectReason::InputsDuplicate,
+            RejectReason::PrevoutNull,
+            RejectReason::CoinbaseLength,
+            RejectReason::CoinbaseMissing,
+            RejectReason::InputsMissingOrSpent,
+            RejectReason::PrematureCoinbaseSpend,
+            RejectReason::InputValuesOutOfRange,
+            RejectReason::InBelowOut,
+            RejectReason::NonFinal,
+            RejectReason::ScriptVerifyFailed,
+            RejectReason::NonStandard,
+            RejectReason::TooManySigops,
+            RejectReason::Dust,
+            RejectReason::InsufficientFee,
+            RejectReason::AlreadyInMempool,
+            RejectReason::MempoolConflict,
+        ];
+        KNOWN
+            .into_iter()
+            .find(|known| known.as_str() == reason)
+            .unwrap_or_else(|| RejectReason::Other(message.to_string()))
+    }
+}
+
+impl std::fmt::Display for RejectReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<RejectReason> for ValidationResult {
+    fn from(reason: RejectReason) -> Self {
+        ValidationResult::Invalid(reason.to_string())
+    }
+}
+
 /// Script execution context
 #[derive(Debug, Clone)]
 pub struct ScriptContext {
@@ -304,3 +521,114 @@ pub struct BlockContext {
     pub prev_headers: Vec<BlockHeader>,
     pub utxo_set: UtxoSet,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_core_types_usable_in_hash_sets() {
+        let outpoint = OutPoint {
+            hash: [1; 32],
+            index: 0,
+        };
+        let input = TransactionInput {
+            prevout: outpoint.clone(),
+            sequence: 0xffffffff,
+            script_sig: vec![0x51],
+        };
+        let output = TransactionOutput {
+            value: 1000,
+            script_pubkey: vec![0x51],
+        };
+        let tx = Transaction {
+            version: 1,
+            inputs: crate::tx_inputs![input.clone()],
+            outputs: crate::tx_outputs![output.clone()],
+            lock_time: 0,
+        };
+        let header = BlockHeader {
+            version: 1,
+            prev_block_hash: [0; 32],
+            merkle_root: [2; 32],
+            timestamp: 1231006505,
+            bits: 0x1d00ffff,
+            nonce: 0,
+        };
+
+        let outpoints: HashSet<_> = [outpoint.clone(), outpoint].into_iter().collect();
+        assert_eq!(outpoints.len(), 1);
+
+        let inputs: HashSet<_> = [input.clone(), input].into_iter().collect();
+        assert_eq!(inputs.len(), 1);
+
+        let mut other_output = output.clone();
+        other_output.value += 1;
+        let outputs: HashSet<_> = [output.clone(), output, other_output].into_iter().collect();
+        assert_eq!(outputs.len(), 2);
+
+        let txs: HashSet<_> = [tx.clone(), tx].into_iter().collect();
+        assert_eq!(txs.len(), 1);
+
+        let mut other_header = header.clone();
+        other_header.nonce = 1;
+        let headers: HashSet<_> = [header.clone(), header, other_header].into_iter().collect();
+        assert_eq!(headers.len(), 2);
+    }
+
+    #[test]
+    fn test_validation_result_to_reject() {
+        assert_eq!(ValidationResult::Valid.to_reject(), None);
+
+        let result: ValidationResult = RejectReason::VinEmpty.into();
+        assert_eq!(
+            result.to_reject(),
+            Some((REJECT_INVALID, "bad-txns-vin-empty".to_string()))
+        );
+
+        let result: ValidationResult = RejectReason::Dust.into();
+        assert_eq!(result.to_reject(), Some((REJECT_DUST, "dust".to_string())));
+
+        let result: ValidationResult = RejectReason::InsufficientFee.into();
+        assert_eq!(
+            result.to_reject(),
+            Some((REJECT_INSUFFICIENTFEE, "min relay fee not met".to_string()))
+        );
+
+        let result: ValidationResult = RejectReason::NonStandard.into();
+        assert_eq!(
+            result.to_reject(),
+            Some((REJECT_NONSTANDARD, "non-standard".to_string()))
+        );
+
+        let result: ValidationResult = RejectReason::MempoolConflict.into();
+        assert_eq!(
+            result.to_reject(),
+            Some((REJECT_DUPLICATE, "txn-mempool-conflict".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_reject_reason_with_detail() {
+        let result =
+            ValidationResult::Invalid("bad-txns-premature-spend-of-coinbase: depth 5".into());
+        assert_eq!(
+            result.reject_reason(),
+            Some(RejectReason::PrematureCoinbaseSpend)
+        );
+    }
+
+    #[test]
+    fn test_free_form_message_rejects_as_invalid() {
+        let result = ValidationResult::Invalid("Script verification failed".into());
+        assert_eq!(
+            result.reject_reason(),
+            Some(RejectReason::Other("Script verification failed".into()))
+        );
+        assert_eq!(
+            result.to_reject(),
+            Some((REJECT_INVALID, "Script verification failed".to_string()))
+        );
+    }
+}
@@ -59,7 +59,7 @@ impl Default for StorageConfig {
 }
 
 /// Complete configuration for UTXO commitments module
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct UtxoCommitmentsConfig {
     /// Sync mode
     pub sync_mode: SyncMode,
@@ -74,7 +74,7 @@ pub struct UtxoCommitmentsConfig {
 }
 
 /// Serializable version of ConsensusConfig
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ConsensusConfigSerializable {
     pub min_peers: usize,
     pub target_peers: usize,
@@ -108,13 +108,19 @@ impl From<ConsensusConfig> for ConsensusConfigSerializable {
 }
 
 /// Serializable version of SpamFilterConfig
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SpamFilterConfigSerializable {
     pub filter_ordinals: bool,
     pub filter_dust: bool,
     pub filter_brc20: bool,
     pub dust_threshold: i64,
     pub min_output_value: i64,
+    #[serde(default = "default_max_datacarrier_bytes")]
+    pub max_datacarrier_bytes: usize,
+}
+
+fn default_max_datacarrier_bytes() -> usize {
+    crate::constants::MAX_OP_RETURN_RELAY
 }
 
 impl From<SpamFilterConfigSerializable> for SpamFilterConfig {
@@ -125,6 +131,7 @@ impl From<SpamFilterConfigSerializable> for SpamFilterConfig {
             filter_brc20: serializable.filter_brc20,
             dust_threshold: serializable.dust_threshold,
             min_output_value: serializable.min_output_value,
+            max_datacarrier_bytes: serializable.max_datacarrier_bytes,
         }
     }
 }
@@ -137,6 +144,7 @@ impl From<SpamFilterConfig> for SpamFilterConfigSerializable {
             filter_brc20: config.filter_brc20,
             dust_threshold: config.dust_threshold,
             min_output_value: config.min_output_value,
+            max_datacarrier_bytes: config.max_datacarrier_bytes,
         }
     }
 }
@@ -159,6 +167,7 @@ impl Default for UtxoCommitmentsConfig {
                 filter_brc20: true,
                 dust_threshold: 546,
                 min_output_value: 546,
+                max_datacarrier_bytes: crate::constants::MAX_OP_RETURN_RELAY,
             },
             storage: StorageConfig::default(),
         }
@@ -9,6 +9,7 @@
 //! 6. Download UTXO set
 
 #[cfg(feature = "utxo-commitments")]
+use crate::serialization::block_hash;
 use crate::types::{BlockHeader, Hash, Natural, OutPoint, Transaction, UTXO};
 #[cfg(feature = "utxo-commitments")]
 use crate::utxo_commitments::data_structures::{
@@ -101,7 +102,7 @@ impl InitialSync {
         }
 
         let checkpoint_header = &header_chain[checkpoint_height as usize];
-        let checkpoint_hash = compute_block_hash(checkpoint_header);
+        let checkpoint_hash = block_hash(checkpoint_header);
 
         // Step 3: Request UTXO sets from peers
         let peer_commitments = self
@@ -295,6 +296,7 @@ impl InitialSync {
                         value: output.value,
                         script_pubkey: output.script_pubkey.clone(),
                         height: block_height,
+                        is_coinbase: is_coinbase(tx),
                     };
 
                     if let Err(e) = utxo_tree.insert(outpoint, utxo) {
@@ -409,6 +411,7 @@ pub fn update_commitments_after_block(
                     value: output.value,
                     script_pubkey: output.script_pubkey.clone(),
                     height: block_height,
+                    is_coinbase: is_coinbase(tx),
                 };
 
                 utxo_tree.insert(outpoint, utxo)?;
@@ -445,23 +448,3 @@ fn compute_tx_id(tx: &Transaction) -> Hash {
 
     txid
 }
-
-/// Compute block header hash (double SHA256)
-fn compute_block_hash(header: &BlockHeader) -> Hash {
-    use sha2::{Digest, Sha256};
-
-    let mut bytes = Vec::with_capacity(80);
-    bytes.extend_from_slice(&header.version.to_le_bytes());
-    bytes.extend_from_slice(&header.prev_block_hash);
-    bytes.extend_from_slice(&header.merkle_root);
-    bytes.extend_from_slice(&header.timestamp.to_le_bytes());
-    bytes.extend_from_slice(&header.bits.to_le_bytes());
-    bytes.extend_from_slice(&header.nonce.to_le_bytes());
-
-    let first_hash = Sha256::digest(&bytes);
-    let second_hash = Sha256::digest(&first_hash);
-
-    let mut hash = [0u8; 32];
-    hash.copy_from_slice(&second_hash);
-    hash
-}
@@ -2,6 +2,11 @@
 //!
 //! Wraps sparse-merkle-tree to provide UTXO-specific operations.
 //! Handles incremental updates (insert/remove) and proof generation.
+//!
+//! Hashing is domain separated: a leaf is SHA256(0x00 || serialized UTXO) and
+//! an internal node is SHA256(0x01 || merge input). A leaf preimage can then
+//! never be presented as an internal node (or vice versa) to forge a proof
+//! (second-preimage attack on the tree).
 
 #[cfg(feature = "utxo-commitments")]
 use crate::types::{Hash, Natural, OutPoint, UTXO};
@@ -20,13 +25,32 @@ use sparse_merkle_tree::{SparseMerkleTree, H256};
 #[cfg(feature = "utxo-commitments")]
 use std::collections::HashMap;
 
-/// SHA256 hasher for UTXO Merkle tree
+/// Domain separation prefix for leaf hashes
+#[cfg(feature = "utxo-commitments")]
+pub const LEAF_HASH_PREFIX: u8 = 0x00;
+
+/// Domain separation prefix for internal node hashes
+#[cfg(feature = "utxo-commitments")]
+pub const NODE_HASH_PREFIX: u8 = 0x01;
+
+/// SHA256 hasher for internal nodes of the UTXO Merkle tree
+///
+/// Every hash starts with `NODE_HASH_PREFIX`.
 #[cfg(feature = "utxo-commitments")]
-#[derive(Default, Clone, Debug)]
+#[derive(Clone, Debug)]
 pub struct UtxoHasher {
     hasher: Sha256,
 }
 
+#[cfg(feature = "utxo-commitments")]
+impl Default for UtxoHasher {
+    fn default() -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update([NODE_HASH_PREFIX]);
+        Self { hasher }
+    }
+}
+
 #[cfg(feature = "utxo-commitments")]
 impl Hasher for UtxoHasher {
     fn write_h256(&mut self, h: &H256) {
@@ -55,8 +79,10 @@ pub struct UtxoValue {
 
 #[cfg(feature = "utxo-commitments")]
 impl Value for UtxoValue {
+    /// Leaf hash: SHA256(`LEAF_HASH_PREFIX` || data)
     fn to_h256(&self) -> H256 {
         let mut hasher = Sha256::new();
+        hasher.update([LEAF_HASH_PREFIX]);
         hasher.update(&self.data);
         let hash = hasher.finalize();
         let mut bytes = [0u8; 32];
@@ -319,10 +345,12 @@ impl UtxoMerkleTree {
 
     /// Serialize UTXO to bytes
     fn serialize_utxo(&self, utxo: &UTXO) -> UtxoCommitmentResult<Vec<u8>> {
-        // Simple serialization: value (8 bytes) + height (8 bytes) + script_pubkey (variable)
-        let mut bytes = Vec::with_capacity(16 + utxo.script_pubkey.len());
+        // Simple serialization: value (8 bytes) + height (8 bytes) + coinbase flag (1 byte)
+        // + script_pubkey (variable)
+        let mut bytes = Vec::with_capacity(18 + utxo.script_pubkey.len());
         bytes.extend_from_slice(&utxo.value.to_be_bytes());
         bytes.extend_from_slice(&utxo.height.to_be_bytes());
+        bytes.push(utxo.is_coinbase as u8);
         bytes.push(utxo.script_pubkey.len() as u8);
         bytes.extend_from_slice(&utxo.script_pubkey);
         Ok(bytes)
@@ -330,7 +358,7 @@ impl UtxoMerkleTree {
 
     /// Deserialize bytes to UTXO
     fn deserialize_utxo(&self, data: &[u8]) -> UtxoCommitmentResult<UTXO> {
-        if data.len() < 17 {
+        if data.len() < 18 {
             return Err(UtxoCommitmentError::InvalidUtxo(
                 "Data too short".to_string(),
             ));
@@ -351,6 +379,17 @@ impl UtxoMerkleTree {
         );
         offset += 8;
 
+        let is_coinbase = match data[offset] {
+            0 => false,
+            1 => true,
+            _ => {
+                return Err(UtxoCommitmentError::InvalidUtxo(
+                    "Invalid coinbase flag".to_string(),
+                ))
+            }
+        };
+        offset += 1;
+
         let script_len = data[offset] as usize;
         offset += 1;
 
@@ -366,6 +405,7 @@ impl UtxoMerkleTree {
             value,
             script_pubkey,
             height,
+            is_coinbase,
         })
     }
 }
@@ -435,6 +475,7 @@ mod kani_proofs {
             value: utxo_value,
             script_pubkey: vec![], // Simplified for tractability
             height: 0,
+            is_coinbase: false,
         };
 
         // Insert UTXO
@@ -478,6 +519,7 @@ mod kani_proofs {
             value: utxo_value,
             script_pubkey: vec![],
             height: 0,
+            is_coinbase: false,
         };
 
         tree.insert(outpoint.clone(), utxo.clone()).unwrap();
@@ -525,6 +567,7 @@ mod kani_proofs {
             value: utxo_value,
             script_pubkey: vec![],
             height: 0,
+            is_coinbase: false,
         };
 
         tree1.insert(outpoint.clone(), utxo.clone()).unwrap();
@@ -560,6 +603,7 @@ mod kani_proofs {
             value: utxo_value,
             script_pubkey: vec![],
             height: 0,
+            is_coinbase: false,
         };
 
         tree.insert(outpoint, utxo.clone()).unwrap();
@@ -601,3 +645,29 @@ mod kani_proofs {
         );
     }
 }
+
+#[cfg(all(test, feature = "utxo-commitments"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_leaf_cannot_be_forged_as_internal_node() {
+        let left = H256::from([0x11; 32]);
+        let right = H256::from([0x22; 32]);
+        let mut node = UtxoHasher::default();
+        node.write_h256(&left);
+        node.write_h256(&right);
+        let node_hash = node.finish();
+
+        // A leaf whose data is exactly the node's two children
+        let mut data = left.as_slice().to_vec();
+        data.extend_from_slice(right.as_slice());
+        let leaf_hash = UtxoValue { data: data.clone() }.to_h256();
+        assert_ne!(leaf_hash, node_hash);
+
+        // Neither is the unprefixed hash of the same bytes
+        let plain: [u8; 32] = Sha256::digest(&data).into();
+        assert_ne!(leaf_hash, H256::from(plain));
+        assert_ne!(node_hash, H256::from(plain));
+    }
+}
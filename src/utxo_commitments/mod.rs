@@ -21,7 +21,7 @@
 //!
 //! // Add UTXO
 //! let outpoint = OutPoint { hash: [1; 32], index: 0 };
-//! let utxo = UTXO { value: 1000, script_pubkey: vec![], height: 0 };
+//! let utxo = UTXO { value: 1000, script_pubkey: vec![], height: 0, is_coinbase: false };
 //! commitment_set.insert(outpoint, utxo)?;
 //!
 //! // Generate commitment
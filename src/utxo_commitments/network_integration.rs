@@ -4,6 +4,7 @@
 //! with the P2P network layer in reference-node.
 
 #[cfg(feature = "utxo-commitments")]
+use crate::serialization::block_hash;
 use crate::types::{BlockHeader, Hash, Natural, Transaction};
 #[cfg(feature = "utxo-commitments")]
 use crate::utxo_commitments::data_structures::{
@@ -100,7 +101,7 @@ pub fn process_and_verify_filtered_block(
     // (In real implementation, would re-apply filter and compare)
 
     // Verify commitment block hash matches header
-    let computed_hash = compute_block_hash(&filtered_block.header);
+    let computed_hash = block_hash(&filtered_block.header);
     if filtered_block.commitment.block_hash != computed_hash {
         return Err(UtxoCommitmentError::VerificationFailed(format!(
             "Block hash mismatch: expected {:?}, got {:?}",
@@ -110,23 +111,3 @@ pub fn process_and_verify_filtered_block(
 
     Ok(true)
 }
-
-/// Compute block header hash (double SHA256)
-fn compute_block_hash(header: &BlockHeader) -> Hash {
-    use sha2::{Digest, Sha256};
-
-    let mut bytes = Vec::with_capacity(80);
-    bytes.extend_from_slice(&header.version.to_le_bytes());
-    bytes.extend_from_slice(&header.prev_block_hash);
-    bytes.extend_from_slice(&header.merkle_root);
-    bytes.extend_from_slice(&header.timestamp.to_le_bytes());
-    bytes.extend_from_slice(&header.bits.to_le_bytes());
-    bytes.extend_from_slice(&header.nonce.to_le_bytes());
-
-    let first_hash = Sha256::digest(&bytes);
-    let second_hash = Sha256::digest(&first_hash);
-
-    let mut hash = [0u8; 32];
-    hash.copy_from_slice(&second_hash);
-    hash
-}
@@ -5,6 +5,7 @@
 //! without trusting any single peer.
 
 #[cfg(feature = "utxo-commitments")]
+use crate::serialization::block_hash;
 use crate::types::{BlockHeader, Hash, Natural};
 #[cfg(feature = "utxo-commitments")]
 use crate::utxo_commitments::data_structures::{
@@ -397,7 +398,7 @@ impl PeerConsensus {
         }
 
         let expected_header = &header_chain[consensus.commitment.block_height as usize];
-        let expected_hash = compute_block_hash(expected_header);
+        let expected_hash = block_hash(expected_header);
 
         if consensus.commitment.block_hash != expected_hash {
             return Err(UtxoCommitmentError::VerificationFailed(format!(
@@ -410,28 +411,6 @@ impl PeerConsensus {
     }
 }
 
-/// Compute block header hash (double SHA256)
-fn compute_block_hash(header: &BlockHeader) -> Hash {
-    use sha2::{Digest, Sha256};
-
-    // Serialize block header
-    let mut bytes = Vec::with_capacity(80);
-    bytes.extend_from_slice(&header.version.to_le_bytes());
-    bytes.extend_from_slice(&header.prev_block_hash);
-    bytes.extend_from_slice(&header.merkle_root);
-    bytes.extend_from_slice(&header.timestamp.to_le_bytes());
-    bytes.extend_from_slice(&header.bits.to_le_bytes());
-    bytes.extend_from_slice(&header.nonce.to_le_bytes());
-
-    // Double SHA256
-    let first_hash = Sha256::digest(&bytes);
-    let second_hash = Sha256::digest(&first_hash);
-
-    let mut hash = [0u8; 32];
-    hash.copy_from_slice(&second_hash);
-    hash
-}
-
 // ============================================================================
 // FORMAL VERIFICATION
 // ============================================================================
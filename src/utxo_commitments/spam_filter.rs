@@ -18,7 +18,7 @@
 //! this correctly by processing all transactions but only adding non-spam outputs.
 
 #[cfg(feature = "utxo-commitments")]
-use crate::types::{ByteString, Transaction};
+use crate::types::{ByteString, Transaction, UtxoSet};
 
 /// Default dust threshold (546 satoshis = 0.00000546 BTC)
 pub const DEFAULT_DUST_THRESHOLD: i64 = 546;
@@ -49,6 +49,8 @@ pub struct SpamFilterConfig {
     pub dust_threshold: i64,
     /// Minimum output value to include in filtered blocks (satoshis)
     pub min_output_value: i64,
+    /// Largest OP_RETURN scriptPubKey not treated as spam (bytes)
+    pub max_datacarrier_bytes: usize,
 }
 
 impl Default for SpamFilterConfig {
@@ -59,6 +61,9 @@ impl Default for SpamFilterConfig {
             filter_brc20: true,
             dust_threshold: DEFAULT_DUST_THRESHOLD,
             min_output_value: DEFAULT_DUST_THRESHOLD,
+            max_datacarrier_bytes: crate::config::get_consensus_config()
+                .mempool
+                .max_datacarrier_bytes,
         }
     }
 }
@@ -135,6 +140,28 @@ impl SpamFilter {
         }
     }
 
+    /// Estimate how many UTXOs would remain in a spam-filtered set
+    ///
+    /// Applies the configured output rules to each UTXO individually: dust
+    /// (value below `dust_threshold`) and data carriers (Ordinals or BRC-20
+    /// patterns in the scriptPubKey). The set is only read, not modified.
+    ///
+    /// This lets operators compare the filtered set size with the full set
+    /// before deciding whether to commit to a filtered set.
+    pub fn estimate_filtered_set_size(&self, utxo_set: &UtxoSet) -> usize {
+        utxo_set
+            .values()
+            .filter(|utxo| !self.is_spam_output(utxo.value, &utxo.script_pubkey))
+            .count()
+    }
+
+    /// Check a single output against the enabled output-level rules
+    fn is_spam_output(&self, value: i64, script_pubkey: &ByteString) -> bool {
+        (self.config.filter_dust && value < self.config.dust_threshold)
+            || (self.config.filter_ordinals && self.has_ordinal_pattern(script_pubkey))
+            || (self.config.filter_brc20 && self.has_brc20_pattern(script_pubkey))
+    }
+
     /// Detect Ordinals/Inscriptions in transaction
     ///
     /// Ordinals typically embed data in:
@@ -173,12 +200,9 @@ impl SpamFilter {
         }
 
         // Check for OP_RETURN (0x6a) - common in Ordinals
+        // Data carriers are judged by the same size limit as standardness
         if script[0] == 0x6a {
-            // OP_RETURN followed by data suggests Ordinals
-            if script.len() > 80 {
-                // Large data pushes are suspicious
-                return true;
-            }
+            return script.len() > self.config.max_datacarrier_bytes;
         }
 
         // Check for envelope protocol pattern
@@ -10,7 +10,8 @@ use crate::economic::total_supply;
 #[cfg(feature = "utxo-commitments")]
 use crate::pow::check_proof_of_work;
 #[cfg(feature = "utxo-commitments")]
-use crate::types::{BlockHeader, Hash, Natural};
+use crate::serialization::block_hash;
+use crate::types::{BlockHeader, Natural};
 #[cfg(feature = "utxo-commitments")]
 use crate::utxo_commitments::data_structures::{
     UtxoCommitment, UtxoCommitmentError, UtxoCommitmentResult,
@@ -67,7 +68,7 @@ pub fn verify_header_chain(headers: &[BlockHeader]) -> UtxoCommitmentResult<bool
         if i > 0 {
             let prev_header = &headers[i - 1];
             // Compute block hash using double SHA256
-            let expected_prev_hash = compute_block_hash(prev_header);
+            let expected_prev_hash = block_hash(prev_header);
 
             if header.prev_block_hash != expected_prev_hash {
                 return Err(UtxoCommitmentError::VerificationFailed(format!(
@@ -89,7 +90,7 @@ pub fn verify_commitment_block_hash(
     commitment: &UtxoCommitment,
     header: &BlockHeader,
 ) -> UtxoCommitmentResult<bool> {
-    let computed_hash = compute_block_hash(header);
+    let computed_hash = block_hash(header);
 
     if commitment.block_hash != computed_hash {
         return Err(UtxoCommitmentError::VerificationFailed(format!(
@@ -101,28 +102,6 @@ pub fn verify_commitment_block_hash(
     Ok(true)
 }
 
-/// Compute block header hash (double SHA256)
-fn compute_block_hash(header: &BlockHeader) -> Hash {
-    use sha2::{Digest, Sha256};
-
-    // Serialize block header (version, prev_block_hash, merkle_root, timestamp, bits, nonce)
-    let mut bytes = Vec::with_capacity(80);
-    bytes.extend_from_slice(&header.version.to_le_bytes());
-    bytes.extend_from_slice(&header.prev_block_hash);
-    bytes.extend_from_slice(&header.merkle_root);
-    bytes.extend_from_slice(&header.timestamp.to_le_bytes());
-    bytes.extend_from_slice(&header.bits.to_le_bytes());
-    bytes.extend_from_slice(&header.nonce.to_le_bytes());
-
-    // Double SHA256
-    let first_hash = Sha256::digest(&bytes);
-    let second_hash = Sha256::digest(&first_hash);
-
-    let mut hash = [0u8; 32];
-    hash.copy_from_slice(&second_hash);
-    hash
-}
-
 /// Verify forward consistency
 ///
 /// Verifies that applying a sequence of blocks to a commitment results in
@@ -272,7 +251,7 @@ mod kani_proofs {
     #[kani::proof]
     fn kani_block_hash_verification() {
         let header = crate::kani_helpers::create_bounded_block_header();
-        let block_hash = super::compute_block_hash(&header);
+        let block_hash = block_hash(&header);
 
         // Correct commitment
         let commitment_correct = UtxoCommitment::new([0; 32], 0, 0, 0, block_hash);
@@ -0,0 +1,367 @@
+//! Validation cost estimation
+//!
+//! `estimate_validation_cost` walks every script a block would execute and
+//! tallies signature checks, hash opcodes and executed opcodes, so miners and
+//! relay policy can spot blocks that are unusually expensive to validate
+//! before running them. Nothing is executed.
+//!
+//! Witness programs are counted from the spent output and the input's witness
+//! stack: a P2WPKH spend is its implicit P2PKH script, a P2WSH spend its
+//! witness script (the last stack element), and a P2TR spend one Schnorr
+//! check, as for a key path spend; tapscripts are not walked.
+
+use crate::block::calculate_tx_id;
+use crate::constants::MAX_PUBKEYS_PER_MULTISIG;
+use crate::script::{SCRIPT_COST_BASE, SCRIPT_COST_HASH, SCRIPT_COST_SIGCHECK};
+use crate::script_type::{classify_script, parse_pushes, ScriptType};
+use crate::segwit::Witness;
+use crate::transaction::is_coinbase;
+use crate::types::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Estimated validation work of a block
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationCost {
+    /// ECDSA signature checks (CHECKMULTISIG counts one per key)
+    pub ecdsa_sigchecks: u64,
+    /// BIP340 Schnorr signature checks
+    pub schnorr_sigchecks: u64,
+    /// Hash opcodes (OP_RIPEMD160 .. OP_HASH256)
+    pub hash_ops: u64,
+    /// Executed non-push opcodes, as counted against the per-script op limit
+    pub script_ops: u64,
+}
+
+impl ValidationCost {
+    /// Total signature checks of either kind
+    pub fn sigchecks(&self) -> u64 {
+        self.ecdsa_sigchecks.saturating_add(self.schnorr_sigchecks)
+    }
+
+    /// Single weighted figure in the units of `script::opcode_cost`
+    pub fn weighted(&self) -> u64 {
+        self.sigchecks()
+            .saturating_mul(SCRIPT_COST_SIGCHECK)
+            .saturating_add(self.hash_ops.saturating_mul(SCRIPT_COST_HASH))
+            .saturating_add(self.script_ops.saturating_mul(SCRIPT_COST_BASE))
+    }
+
+    /// Add the opcodes of one executed script
+    fn add_script(&mut self, script: &[u8]) {
+        let mut last_opcode = None;
+        let mut i = 0;
+
+        while i < script.len() {
+            let opcode = script[i];
+            i += 1;
+
+            // Skip push data; a truncated push ends execution
+            let push_len = match opcode {
+                0x01..=0x4b => Some(opcode as usize),
+                0x4c => script.get(i).map(|&len| (len as usize) + 1),
+                0x4d => script
+                    .get(i..i + 2)
+                    .map(|len| u16::from_le_bytes([len[0], len[1]]) as usize + 2),
+                0x4e => script
+                    .get(i..i + 4)
+                    .map(|len| u32::from_le_bytes([len[0], len[1], len[2], len[3]]) as usize + 4),
+                _ => None,
+            };
+            if let Some(len) = push_len {
+                i = i.saturating_add(len);
+                last_opcode = Some(opcode);
+                continue;
+            }
+            if matches!(opcode, 0x4c..=0x4e) {
+                break;
+            }
+
+            // OP_0 and OP_1NEGATE .. OP_16 do not count towards the op limit
+            if opcode > 0x60 {
+                self.script_ops += 1;
+            }
+            match opcode {
+                // OP_RIPEMD160, OP_SHA1, OP_SHA256, OP_HASH160, OP_HASH256
+                0xa6..=0xaa => self.hash_ops += 1,
+                // OP_CHECKSIG, OP_CHECKSIGVERIFY
+                0xac | 0xad => self.ecdsa_sigchecks += 1,
+                // OP_CHECKMULTISIG, OP_CHECKMULTISIGVERIFY: one check per key
+                0xae | 0xaf => {
+                    self.ecdsa_sigchecks += match last_opcode {
+                        Some(n @ 0x51..=0x60) => (n - 0x50) as u64,
+                        // No preceding OP_N: assume the most keys allowed
+                        _ => MAX_PUBKEYS_PER_MULTISIG as u64,
+                    }
+                }
+                _ => {}
+            }
+            last_opcode = Some(opcode);
+        }
+    }
+
+    /// Add a witness program spend with its witness stack, if `script_pubkey` is one
+    fn add_witness_program(&mut self, script_pubkey: &[u8], witness: &[ByteString]) -> bool {
+        match classify_script(script_pubkey) {
+            ScriptType::P2WPKH(hash) => {
+                // OP_DUP OP_HASH160 <hash> OP_EQUALVERIFY OP_CHECKSIG
+                let mut script_code = vec![0x76, 0xa9, 0x14];
+                script_code.extend_from_slice(&hash);
+                script_code.extend_from_slice(&[0x88, 0xac]);
+                self.add_script(&script_code);
+                true
+            }
+            ScriptType::P2TR(_) => {
+                self.schnorr_sigchecks += 1;
+                true
+            }
+            ScriptType::P2WSH(_) => {
+                if let Some(witness_script) = witness.last() {
+                    self.add_script(witness_script);
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Add the scripts run to spend `script_pubkey` with `script_sig` and `witness`
+    fn add_input(&mut self, script_sig: &[u8], script_pubkey: &[u8], witness: &[ByteString]) {
+        self.add_script(script_sig);
+        if self.add_witness_program(script_pubkey, witness) {
+            return;
+        }
+        self.add_script(script_pubkey);
+
+        if let ScriptType::P2SH(_) = classify_script(script_pubkey) {
+            let redeem_script = parse_pushes(script_sig).and_then(|mut items| items.pop());
+            if let Some(redeem_script) = redeem_script {
+                if !self.add_witness_program(&redeem_script, witness) {
+                    self.add_script(&redeem_script);
+                }
+            }
+        }
+    }
+}
+
+/// Estimate the validation cost of a block without executing it
+///
+/// `witnesses` holds the witness stacks of the block's transactions, in the
+/// shape `connect_block` takes; a missing stack counts as empty. `prevouts`
+/// supplies the outputs spent by the block; outputs created earlier in the
+/// same block are resolved from the block itself. Inputs whose spent output
+/// is found in neither only contribute their scriptSig. Multisig is counted
+/// at its worst case of one check per key.
+pub fn estimate_validation_cost(
+    block: &Block,
+    witnesses: &[Vec<Witness>],
+    prevouts: &UtxoSet,
+) -> ValidationCost {
+    let mut cost = ValidationCost::default();
+    let mut created: HashMap<OutPoint, &ByteString> = HashMap::new();
+
+    for (tx_index, tx) in block.transactions.iter().enumerate() {
+        if !is_coinbase(tx) {
+            for (input_index, input) in tx.inputs.iter().enumerate() {
+                let witness = witnesses
+                    .get(tx_index)
+                    .and_then(|stacks| stacks.get(input_index))
+                    .map_or(&[][..], Vec::as_slice);
+                let script_pubkey = prevouts
+                    .get(&input.prevout)
+                    .map(|utxo| &utxo.script_pubkey)
+                    .or_else(|| created.get(&input.prevout).copied());
+                match script_pubkey {
+                    Some(script_pubkey) => {
+                        cost.add_input(&input.script_sig, script_pubkey, witness)
+                    }
+                    None => cost.add_script(&input.script_sig),
+                }
+            }
+        }
+
+        let txid = calculate_tx_id(tx);
+        for (index, output) in tx.outputs.iter().enumerate() {
+            created.insert(
+                OutPoint {
+                    hash: txid,
+                    index: index as Natural,
+                },
+                &output.script_pubkey,
+            );
+        }
+    }
+
+    cost
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(hash: Hash, index: Natural, script_sig: ByteString) -> TransactionInput {
+        TransactionInput {
+            prevout: OutPoint { hash, index },
+            script_sig,
+            sequence: 0xffffffff,
+        }
+    }
+
+    fn tx(inputs: Vec<TransactionInput>, script_pubkey: ByteString) -> Transaction {
+        Transaction {
+            version: 1,
+            inputs: inputs.into_iter().collect(),
+            outputs: crate::tx_outputs![TransactionOutput {
+                value: 1000,
+                script_pubkey,
+            }],
+            lock_time: 0,
+        }
+    }
+
+    fn utxo(script_pubkey: ByteString) -> UTXO {
+        UTXO {
+            value: 2000,
+            script_pubkey,
+            height: 1,
+            is_coinbase: false,
+        }
+    }
+
+    #[test]
+    fn test_estimate_validation_cost_breakdown() {
+        let key = [0x02; 33];
+        let sig = [0x30; 72];
+        let push = |data: &[u8]| {
+            let mut script = vec![data.len() as u8];
+            script.extend_from_slice(data);
+            script
+        };
+
+        // P2PKH
+        let mut p2pkh = vec![0x76, 0xa9, 0x14];
+        p2pkh.extend_from_slice(&[0x11; 20]);
+        p2pkh.extend_from_slice(&[0x88, 0xac]);
+        // Bare 2-of-3 multisig
+        let mut multisig = vec![0x52];
+        for _ in 0..3 {
+            multisig.extend(push(&key));
+        }
+        multisig.extend_from_slice(&[0x53, 0xae]);
+        // P2WPKH, P2TR and P2WSH
+        let mut p2wpkh = vec![0x00, 0x14];
+        p2wpkh.extend_from_slice(&[0x22; 20]);
+        let mut p2tr = vec![0x51, 0x20];
+        p2tr.extend_from_slice(&[0x33; 32]);
+        // Witness script: OP_SHA256 <32 bytes> OP_EQUALVERIFY <key> OP_CHECKSIG
+        let mut witness_script = vec![0xa8, 0x20];
+        witness_script.extend_from_slice(&[0x66; 32]);
+        witness_script.push(0x88);
+        witness_script.extend(push(&key));
+        witness_script.push(0xac);
+        let mut p2wsh = vec![0x00, 0x20];
+        p2wsh.extend_from_slice(&crate::crypto::sha256(&witness_script));
+        // OP_SHA256 <32 bytes> OP_EQUAL, spent within the block
+        let mut hashlock = vec![0xa8, 0x20];
+        hashlock.extend_from_slice(&[0x44; 32]);
+        hashlock.push(0x87);
+
+        let mut prevouts = UtxoSet::new();
+        for (i, script_pubkey) in [p2pkh, multisig, p2wpkh, p2tr, p2wsh]
+            .into_iter()
+            .enumerate()
+        {
+            prevouts.insert(
+                OutPoint {
+                    hash: [0xaa; 32],
+                    index: i as Natural,
+                },
+                utxo(script_pubkey),
+            );
+        }
+
+        let coinbase = tx(vec![input([0; 32], 0xffffffff, vec![0xac; 4])], vec![0x51]);
+        let spend_p2pkh = tx(
+            vec![input([0xaa; 32], 0, [push(&sig), push(&key)].concat())],
+            hashlock,
+        );
+        let spend_multisig = tx(
+            vec![input(
+                [0xaa; 32],
+                1,
+                [vec![0x00], push(&sig), push(&sig)].concat(),
+            )],
+            vec![0x51],
+        );
+        let spend_segwit = tx(
+            vec![
+                input([0xaa; 32], 2, vec![]),
+                input([0xaa; 32], 3, vec![]),
+                input([0xaa; 32], 4, vec![]),
+            ],
+            vec![0x51],
+        );
+        let spend_hashlock = tx(
+            vec![input(calculate_tx_id(&spend_p2pkh), 0, push(&[0x55; 32]))],
+            vec![0x51],
+        );
+
+        let block = Block {
+            header: BlockHeader {
+                version: 1,
+                prev_block_hash: [0; 32],
+                merkle_root: [0; 32],
+                timestamp: 0,
+                bits: 0x207fffff,
+                nonce: 0,
+            },
+            transactions: vec![
+                coinbase,
+                spend_p2pkh,
+                spend_multisig,
+                spend_segwit,
+                spend_hashlock,
+            ]
+            .into_boxed_slice(),
+        };
+
+        let mut witnesses: Vec<Vec<Witness>> = block
+            .transactions
+            .iter()
+            .map(|tx| vec![Vec::new(); tx.inputs.len()])
+            .collect();
+        witnesses[3][2] = vec![sig.to_vec(), vec![0x55; 32], witness_script];
+
+        let cost = estimate_validation_cost(&block, &witnesses, &prevouts);
+        assert_eq!(
+            cost,
+            ValidationCost {
+                // P2PKH + 3 multisig keys + P2WPKH + P2WSH
+                ecdsa_sigchecks: 6,
+                // P2TR key path
+                schnorr_sigchecks: 1,
+                // P2PKH + P2WPKH OP_HASH160, hashlock + P2WSH OP_SHA256
+                hash_ops: 4,
+                // 4 (P2PKH) + 1 (CHECKMULTISIG) + 4 (P2WPKH) + 2 (hashlock) + 3 (P2WSH)
+                script_ops: 14,
+            }
+        );
+        assert_eq!(cost.sigchecks(), 7);
+        assert_eq!(
+            cost.weighted(),
+            7 * SCRIPT_COST_SIGCHECK + 4 * SCRIPT_COST_HASH + 14 * SCRIPT_COST_BASE
+        );
+
+        // Without its witness the P2WSH script is not visible
+        let cost = estimate_validation_cost(&block, &[], &prevouts);
+        assert_eq!(cost.ecdsa_sigchecks, 5);
+        assert_eq!(cost.hash_ops, 3);
+        assert_eq!(cost.script_ops, 11);
+
+        // Without prevouts only the scriptSigs are walked; the coinbase never is
+        let cost = estimate_validation_cost(&block, &witnesses, &UtxoSet::new());
+        assert_eq!(cost.sigchecks(), 0);
+        assert_eq!(cost.hash_ops, 1);
+        assert_eq!(cost.script_ops, 2);
+    }
+}
@@ -0,0 +1,363 @@
+//! Structured validation reports
+//!
+//! `TxValidationReport` and `BlockValidationReport` collect what a validator
+//! CLI or CI gate wants to show about a transaction or block: the verdict, the
+//! reject reason, fees, weight, sigop cost and the script result of every
+//! input. Both are serde-serializable; `to_json` renders them as JSON.
+//!
+//! Hashes are rendered as hex in the usual display (byte-reversed) order.
+
+use crate::block::{apply_transaction, calculate_script_flags_for_block, calculate_tx_id};
+use crate::crypto::signature::default_verifier;
+use crate::error::{ConsensusError, Result};
+use crate::script::verify_script_with_witness;
+use crate::segwit::{calculate_block_weight, calculate_transaction_weight, Witness};
+use crate::sigop::get_transaction_sigop_cost;
+use crate::transaction::{check_transaction, check_tx_inputs, is_coinbase};
+use crate::transaction_hash::SighashCache;
+use crate::types::*;
+use serde::{Deserialize, Serialize};
+
+/// Script verification result for one input
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InputScriptResult {
+    /// Input index within the transaction
+    pub index: usize,
+    /// Whether the input's scripts verified
+    pub valid: bool,
+}
+
+/// Validation report for a single transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TxValidationReport {
+    /// Transaction id (hex, display order)
+    pub txid: String,
+    /// Overall verdict
+    pub valid: bool,
+    /// Why the transaction was rejected, if it was
+    pub reject_reason: Option<String>,
+    /// Fee in satoshis; `None` if the inputs could not be checked
+    pub fee: Option<Integer>,
+    /// BIP141 weight
+    pub weight: Natural,
+    /// Sigop cost (legacy and P2SH sigops × 4, plus witness sigops)
+    pub sigop_cost: u64,
+    /// Per-input script results; empty if validation stopped before scripts ran
+    pub inputs: Vec<InputScriptResult>,
+}
+
+/// Validation report for a block
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockValidationReport {
+    /// Block height the block was validated at
+    pub height: Natural,
+    /// Overall verdict, as returned by `connect_block`
+    pub valid: bool,
+    /// Why the block was rejected, if it was
+    pub reject_reason: Option<String>,
+    /// Sum of the fees of the non-coinbase transactions
+    pub total_fees: Integer,
+    /// BIP141 weight of all transactions
+    pub weight: Natural,
+    /// Sum of the transactions' sigop costs
+    pub sigop_cost: u64,
+    /// Per-transaction reports, in block order, up to the first invalid one
+    pub transactions: Vec<TxValidationReport>,
+}
+
+impl TxValidationReport {
+    /// Render the report as JSON
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| ConsensusError::Serialization(e.to_string().into()))
+    }
+}
+
+impl BlockValidationReport {
+    /// An empty report for a block at `height`, not yet known to be valid
+    pub fn new(height: Natural) -> Self {
+        BlockValidationReport {
+            height,
+            valid: false,
+            reject_reason: None,
+            total_fees: 0,
+            weight: 0,
+            sigop_cost: 0,
+            transactions: Vec::new(),
+        }
+    }
+
+    /// Render the report as JSON
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| ConsensusError::Serialization(e.to_string().into()))
+    }
+}
+
+/// Validate a transaction against `utxo_set` and report the result
+///
+/// Runs `check_transaction`, `check_tx_inputs` and script verification for
+/// every input, in that order; the first failing stage sets `reject_reason`.
+/// `witness` holds the transaction's witness stacks, one per input, as passed
+/// to `connect_block`.
+pub fn validate_transaction_report(
+    tx: &Transaction,
+    witness: Option<&[Witness]>,
+    utxo_set: &UtxoSet,
+    height: Natural,
+    network: Network,
+) -> Result<TxValidationReport> {
+    let flags = calculate_script_flags_for_block(tx, witness);
+    let mut report = TxValidationReport {
+        txid: display_hex(&calculate_tx_id(tx)),
+        valid: false,
+        reject_reason: None,
+        fee: None,
+        weight: calculate_transaction_weight(tx, witness)?,
+        sigop_cost: get_transaction_sigop_cost(tx, utxo_set, witness, flags)?,
+        inputs: Vec::new(),
+    };
+
+    if let ValidationResult::Invalid(reason) = check_transaction(tx)? {
+        report.reject_reason = Some(reason);
+        return Ok(report);
+    }
+
+    let (result, fee) = check_tx_inputs(tx, utxo_set, height)?;
+    if let ValidationResult::Invalid(reason) = result {
+        report.reject_reason = Some(reason);
+        return Ok(report);
+    }
+    report.fee = Some(fee);
+
+    if !is_coinbase(tx) {
+        let prevouts: Vec<PrevOut> = tx
+            .inputs
+            .iter()
+            .filter_map(|input| utxo_set.get(&input.prevout))
+            .map(|utxo| PrevOut {
+                value: utxo.value,
+                script_pubkey: utxo.script_pubkey.clone(),
+            })
+            .collect();
+
+        let sighash_cache = SighashCache::new(tx);
+        for (index, input) in tx.inputs.iter().enumerate() {
+            // check_tx_inputs has confirmed every prevout exists
+            let script_pubkey = &prevouts[index].script_pubkey;
+            let stack = witness
+                .and_then(|w| w.get(index))
+                .map_or(&[][..], Vec::as_slice);
+            let valid = verify_script_with_witness(
+                &input.script_sig,
+                script_pubkey,
+                stack,
+                flags,
+                tx,
+                index,
+                &prevouts,
+                &sighash_cache,
+                Some(height),
+                None,
+                network,
+                default_verifier(),
+            )
+            .unwrap_or(false);
+            if !valid && report.reject_reason.is_none() {
+                report.reject_reason = Some(format!("Invalid script at input {index}"));
+            }
+            report.inputs.push(InputScriptResult { index, valid });
+        }
+    }
+
+    report.valid = report.reject_reason.is_none();
+    Ok(report)
+}
+
+/// Validate a block and report the result
+///
+/// The verdict and reject reason come from `connect_block`. Transactions are
+/// then reported one by one against the UTXO set as it stood before each of
+/// them, stopping after the first invalid transaction.
+pub fn validate_block_report(
+    block: &Block,
+    witnesses: &[Vec<Witness>],
+    utxo_set: UtxoSet,
+    height: Natural,
+    network: Network,
+) -> Result<BlockValidationReport> {
+    let spent = block_spent_utxos(block, &utxo_set);
+    let (result, _, _) =
+        crate::block::connect_block(block, witnesses, utxo_set, height, None, network)?;
+    block_report(block, witnesses, spent, height, network, result)
+}
+
+/// The entries of `utxo_set` spent by `block`'s transactions
+///
+/// All a block report needs of the UTXO set: see `block_report`.
+pub(crate) fn block_spent_utxos(block: &Block, utxo_set: &UtxoSet) -> UtxoSet {
+    block
+        .transactions
+        .iter()
+        .flat_map(|tx| tx.inputs.iter())
+        .filter_map(|input| {
+            utxo_set
+                .get(&input.prevout)
+                .map(|utxo| (input.prevout.clone(), utxo.clone()))
+        })
+        .collect()
+}
+
+/// Report on a block whose verdict is already known
+///
+/// `result` is the block's verdict from `connect_block` or `verify_block`, and
+/// `spent` the outputs it spends as found by `block_spent_utxos`. Each
+/// transaction is reported against `spent` with the outputs of the
+/// transactions before it applied, as in `validate_block_report`.
+pub(crate) fn block_report(
+    block: &Block,
+    witnesses: &[Vec<Witness>],
+    spent: UtxoSet,
+    height: Natural,
+    network: Network,
+    result: ValidationResult,
+) -> Result<BlockValidationReport> {
+    let mut report = BlockValidationReport {
+        height,
+        valid: result == ValidationResult::Valid,
+        reject_reason: match result {
+            ValidationResult::Valid => None,
+            ValidationResult::Invalid(reason) => Some(reason),
+        },
+        total_fees: 0,
+        weight: calculate_block_weight(block, witnesses)?,
+        sigop_cost: 0,
+        transactions: Vec::new(),
+    };
+
+    let mut utxo_set = spent;
+    for (i, tx) in block.transactions.iter().enumerate() {
+        let tx_report = validate_transaction_report(
+            tx,
+            witnesses.get(i).map(Vec::as_slice),
+            &utxo_set,
+            height,
+            network,
+        )?;
+        report.sigop_cost = report.sigop_cost.saturating_add(tx_report.sigop_cost);
+        if !is_coinbase(tx) {
+            report.total_fees = report.total_fees.saturating_add(tx_report.fee.unwrap_or(0));
+        }
+        let tx_valid = tx_report.valid;
+        report.transactions.push(tx_report);
+        if !tx_valid {
+            break;
+        }
+        utxo_set = apply_transaction(tx, utxo_set, height)?.0;
+    }
+
+    Ok(report)
+}
+
+/// Hex-encode a hash in display (byte-reversed) order
+fn display_hex(hash: &Hash) -> String {
+    let mut reversed = *hash;
+    reversed.reverse();
+    hex::encode(reversed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    fn funded_utxo_set() -> UtxoSet {
+        let mut utxo_set = UtxoSet::new();
+        utxo_set.insert(
+            OutPoint {
+                hash: [1; 32],
+                index: 0,
+            },
+            UTXO {
+                value: 10_000,
+                script_pubkey: vec![0x51, 0x87], // OP_1 OP_EQUAL
+                height: 0,
+                is_coinbase: false,
+            },
+        );
+        utxo_set
+    }
+
+    fn spend(value: Integer) -> Transaction {
+        Transaction {
+            version: 1,
+            inputs: crate::tx_inputs![TransactionInput {
+                prevout: OutPoint {
+                    hash: [1; 32],
+                    index: 0,
+                },
+                script_sig: vec![0x51], // OP_1
+                sequence: 0xffffffff,
+            }],
+            outputs: crate::tx_outputs![TransactionOutput {
+                value,
+                script_pubkey: vec![0x51],
+            }],
+            lock_time: 0,
+        }
+    }
+
+    fn to_value(report: &TxValidationReport) -> Value {
+        serde_json::from_str(&report.to_json().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_tx_validation_report_json_valid() {
+        let tx = spend(9_000);
+        let report =
+            validate_transaction_report(&tx, None, &funded_utxo_set(), 100, Network::Regtest)
+                .unwrap();
+        let json = to_value(&report);
+
+        assert_eq!(json["txid"].as_str().unwrap().len(), 64);
+        assert_eq!(json["valid"], Value::Bool(true));
+        assert_eq!(json["reject_reason"], Value::Null);
+        assert_eq!(json["fee"], 1_000);
+        assert!(json["weight"].as_u64().unwrap() > 0);
+        assert_eq!(json["sigop_cost"], 0);
+        assert_eq!(
+            json["inputs"],
+            serde_json::json!([{ "index": 0, "valid": true }])
+        );
+    }
+
+    #[test]
+    fn test_tx_validation_report_json_invalid() {
+        // Outputs exceed inputs: rejected before scripts run
+        let tx = spend(20_000);
+        let report =
+            validate_transaction_report(&tx, None, &funded_utxo_set(), 100, Network::Regtest)
+                .unwrap();
+        let json = to_value(&report);
+
+        assert_eq!(json["valid"], Value::Bool(false));
+        assert!(json["reject_reason"].is_string());
+        assert_eq!(json["fee"], Value::Null);
+        assert_eq!(json["inputs"], serde_json::json!([]));
+
+        // A failing script is reported against its input
+        let mut utxo_set = funded_utxo_set();
+        utxo_set
+            .values_mut()
+            .for_each(|utxo| utxo.script_pubkey = vec![0x52, 0x87]); // OP_2 OP_EQUAL
+        let report =
+            validate_transaction_report(&spend(9_000), None, &utxo_set, 100, Network::Regtest)
+                .unwrap();
+        let json = to_value(&report);
+        assert_eq!(json["valid"], Value::Bool(false));
+        assert_eq!(json["reject_reason"], "Invalid script at input 0");
+        assert_eq!(json["fee"], 1_000);
+        assert_eq!(
+            json["inputs"],
+            serde_json::json!([{ "index": 0, "valid": false }])
+        );
+    }
+}
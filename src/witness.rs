@@ -42,7 +42,7 @@ pub fn validate_segwit_witness_structure(witness: &Witness) -> Result<bool> {
 /// Validate witness structure for Taproot
 ///
 /// BIP341: Taproot witness structure depends on spending path:
-/// - Key path: single signature (64 bytes)
+/// - Key path: single signature (64 bytes, or 65 with a non-default sighash type)
 /// - Script path: script, control block (33 + 32n bytes), and witness items
 pub fn validate_taproot_witness_structure(witness: &Witness, is_script_path: bool) -> Result<bool> {
     if witness.is_empty() {
@@ -67,11 +67,11 @@ pub fn validate_taproot_witness_structure(witness: &Witness, is_script_path: boo
             return Ok(false);
         }
     } else {
-        // Key path: single Schnorr signature (64 bytes)
+        // Key path: single Schnorr signature (64 bytes, or 65 with a sighash type)
         if witness.len() != 1 {
             return Ok(false);
         }
-        if witness[0].len() != 64 {
+        if crate::taproot::parse_taproot_signature(&witness[0]).is_none() {
             return Ok(false);
         }
     }
@@ -81,11 +81,13 @@ pub fn validate_taproot_witness_structure(witness: &Witness, is_script_path: boo
 
 /// Calculate transaction weight using SegWit formula
 ///
-/// BIP141: Weight(tx) = 4 × BaseSize(tx) + TotalSize(tx)
+/// BIP141: Weight(tx) = 3 × BaseSize(tx) + TotalSize(tx)
 /// BaseSize: Transaction size without witness data
 /// TotalSize: Transaction size with witness data
+///
+/// Without witness data TotalSize == BaseSize, so the weight is 4 × BaseSize.
 pub fn calculate_transaction_weight_segwit(base_size: Natural, total_size: Natural) -> Natural {
-    4 * base_size + total_size
+    3 * base_size + total_size
 }
 
 /// Calculate virtual size (vsize) from weight
@@ -204,6 +206,12 @@ mod tests {
         let invalid = vec![vec![0x01; 63]];
         assert!(!validate_taproot_witness_structure(&invalid, false).unwrap());
 
+        // 65 bytes: explicit sighash type, but never an explicit SIGHASH_DEFAULT
+        assert!(validate_taproot_witness_structure(&vec![vec![0x01; 65]], false).unwrap());
+        let mut explicit_default = vec![0x01; 64];
+        explicit_default.push(0x00);
+        assert!(!validate_taproot_witness_structure(&vec![explicit_default], false).unwrap());
+
         // Invalid: multiple elements
         let invalid2 = vec![vec![0x01; 64], vec![0x02; 32]];
         assert!(!validate_taproot_witness_structure(&invalid2, false).unwrap());
@@ -232,7 +240,7 @@ mod tests {
         let base_size = 100;
         let total_size = 150;
         let weight = calculate_transaction_weight_segwit(base_size, total_size);
-        assert_eq!(weight, 4 * 100 + 150); // 550
+        assert_eq!(weight, 3 * 100 + 150); // 450
     }
 
     #[test]
@@ -350,7 +358,7 @@ mod kani_proofs {
     /// ∀ witness ∈ Witness, is_script_path ∈ bool:
     /// - validate_taproot_witness_structure(witness, is_script_path) = true ⟹
     ///   (if is_script_path: |witness| >= 2 ∧ |witness[-1]| >= 33 ∧ (|witness[-1]| - 33) % 32 == 0)
-    ///   (if !is_script_path: |witness| == 1 ∧ |witness[0]| == 64)
+    ///   (if !is_script_path: |witness| == 1 ∧ (|witness[0]| == 64 ∨ (|witness[0]| == 65 ∧ witness[0][64] ≠ 0)))
     #[kani::proof]
     fn kani_taproot_witness_structure_validation() {
         let witness = crate::kani_helpers::create_bounded_witness(5, 10);
@@ -380,12 +388,13 @@ mod kani_proofs {
                             "Taproot witness structure validation: control block size must be 33 + 32n bytes");
                     }
                 } else {
-                    // Key path: exactly 1 element, 64 bytes
+                    // Key path: exactly 1 element, 64 bytes or 65 with a non-default sighash type
                     assert_eq!(witness.len(), 1,
                         "Taproot witness structure validation: key path must have exactly 1 element");
                     if !witness.is_empty() {
-                        assert_eq!(witness[0].len(), 64,
-                            "Taproot witness structure validation: key path signature must be 64 bytes");
+                        let sig = &witness[0];
+                        assert!(sig.len() == 64 || (sig.len() == 65 && sig[64] != 0x00),
+                            "Taproot witness structure validation: key path signature must be 64 or 65 bytes");
                     }
                 }
             }
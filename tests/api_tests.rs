@@ -327,7 +327,7 @@ fn test_accept_to_memory_pool() {
         Ok(mempool_result) => {
             assert!(matches!(
                 mempool_result,
-                MempoolResult::Accepted | MempoolResult::Rejected(_)
+                MempoolResult::Accepted { .. } | MempoolResult::Rejected(_)
             ));
         }
         Err(_) => {
@@ -663,9 +663,9 @@ fn test_calculate_transaction_weight() {
         lock_time: 0,
     };
 
-    let witness = Some(Witness::new());
+    let witness = vec![Witness::new()];
     let weight = consensus
-        .calculate_transaction_weight(&tx, witness.as_ref())
+        .calculate_transaction_weight(&tx, Some(&witness))
         .unwrap();
     assert!(weight > 0);
 }
@@ -709,7 +709,7 @@ fn test_validate_segwit_block() {
         .into_boxed_slice(),
     };
 
-    let witnesses = vec![Witness::new()];
+    let witnesses = vec![vec![Witness::new()]];
     let result = consensus
         .validate_segwit_block(&block, &witnesses, 4000000)
         .unwrap();
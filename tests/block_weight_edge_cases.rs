@@ -39,8 +39,8 @@ fn test_block_weight_exact_limit() {
         lock_time: 0,
     };
 
-    let witness = Some(Witness::new());
-    let weight = calculate_transaction_weight(&tx, witness.as_ref());
+    let witness = Some(vec![Witness::new()]);
+    let weight = calculate_transaction_weight(&tx, witness.as_deref());
 
     // Should calculate weight successfully
     assert!(weight.is_ok());
@@ -93,8 +93,8 @@ fn test_segwit_weight_discount() {
     assert!(weight_no_witness.is_ok());
 
     // Transaction with witness (SegWit)
-    let witness = Some(Witness::new());
-    let weight_with_witness = calculate_transaction_weight(&tx, witness.as_ref());
+    let witness = Some(vec![Witness::new()]);
+    let weight_with_witness = calculate_transaction_weight(&tx, witness.as_deref());
     assert!(weight_with_witness.is_ok());
 
     // SegWit transaction should have different weight calculation
@@ -149,7 +149,7 @@ fn test_mixed_witness_weight() {
     };
 
     // Calculate weights separately
-    let segwit_weight = calculate_transaction_weight(&segwit_tx, Some(&Witness::new()));
+    let segwit_weight = calculate_transaction_weight(&segwit_tx, Some(&[Witness::new()]));
     let non_segwit_weight = calculate_transaction_weight(&non_segwit_tx, None);
 
     assert!(segwit_weight.is_ok());
@@ -221,9 +221,9 @@ fn test_weight_large_witness() {
 
     // Create large witness (up to 520 bytes per element)
     let large_witness_element = vec![0x42; 520];
-    let witness = Some(vec![large_witness_element]);
+    let witness = Some(vec![vec![large_witness_element]]);
 
-    let weight = calculate_transaction_weight(&tx, witness.as_ref());
+    let weight = calculate_transaction_weight(&tx, witness.as_deref());
 
     // Should calculate weight successfully
     assert!(weight.is_ok());
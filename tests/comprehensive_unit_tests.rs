@@ -712,10 +712,10 @@ fn test_script_operation_limits() {
     // Just test it returns a boolean (result is either true or false)
     let _ = result;
 
-    // Test script exceeding operation limit
+    // Test script exceeding operation limit (pushes such as OP_1 are not counted)
     let mut large_script = Vec::new();
     for _ in 0..=MAX_SCRIPT_OPS {
-        large_script.push(0x51);
+        large_script.push(0x61); // OP_NOP
     }
 
     let result = verify_script(&large_script, &large_script, None, 0);
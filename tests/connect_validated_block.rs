@@ -0,0 +1,145 @@
+//! Validate-and-connect through `ChainState::connect_validated_block`
+//!
+//! A valid block updates the chain state, the UTXO set and the UTXO
+//! commitment and returns its undo log; an invalid block is reported with a
+//! reject reason and leaves all of them untouched.
+
+use bllvm_consensus::chain_params::ChainParams;
+use bllvm_consensus::mining::calculate_merkle_root;
+use bllvm_consensus::network::ChainState;
+use bllvm_consensus::segwit::Witness;
+use bllvm_consensus::serialization::block_hash;
+use bllvm_consensus::types::*;
+
+fn coinbase_block(value: Integer) -> Block {
+    let coinbase = Transaction {
+        version: 1,
+        inputs: bllvm_consensus::tx_inputs![TransactionInput {
+            prevout: OutPoint {
+                hash: [0; 32],
+                index: 0xffffffff,
+            },
+            script_sig: vec![0x51, 0x51],
+            sequence: 0xffffffff,
+        }],
+        outputs: bllvm_consensus::tx_outputs![TransactionOutput {
+            value,
+            script_pubkey: vec![0x51],
+        }],
+        lock_time: 0,
+    };
+    Block {
+        header: BlockHeader {
+            version: 1,
+            prev_block_hash: [1; 32],
+            merkle_root: calculate_merkle_root(std::slice::from_ref(&coinbase)).unwrap(),
+            timestamp: 1231006505,
+            bits: 0x1d00ffff,
+            nonce: 0,
+        },
+        transactions: vec![coinbase].into_boxed_slice(),
+    }
+}
+
+#[test]
+fn test_connect_validated_block_connects_valid_block() {
+    let block = coinbase_block(50 * 100_000_000);
+    let witnesses: Vec<Vec<Witness>> = vec![vec![Vec::new()]];
+    let mut chain_state = ChainState::new();
+    let mut utxo_set = UtxoSet::new();
+    let empty_commitment = chain_state.utxo_commitment();
+
+    let undo_log = chain_state
+        .connect_validated_block(
+            &block,
+            &witnesses,
+            &mut utxo_set,
+            1,
+            &ChainParams::mainnet(),
+        )
+        .unwrap();
+
+    assert_eq!(undo_log.entries.len(), 1);
+    assert_eq!(utxo_set.len(), 1);
+    assert_ne!(chain_state.utxo_commitment(), empty_commitment);
+    let hash = block_hash(&block.header);
+    assert!(chain_state.has_object(&hash));
+    assert_eq!(chain_state.headers.get(&hash), Some(&block.header));
+}
+
+#[test]
+fn test_connect_validated_block_rejects_without_mutating_state() {
+    // Coinbase claims one satoshi more than the subsidy
+    let block = coinbase_block(50 * 100_000_000 + 1);
+    let witnesses: Vec<Vec<Witness>> = vec![vec![Vec::new()]];
+    let mut chain_state = ChainState::new();
+    let mut utxo_set = UtxoSet::new();
+    let commitment = chain_state.utxo_commitment();
+
+    let report = chain_state
+        .connect_validated_block(
+            &block,
+            &witnesses,
+            &mut utxo_set,
+            1,
+            &ChainParams::mainnet(),
+        )
+        .unwrap_err();
+
+    assert!(!report.valid);
+    assert_eq!(report.height, 1);
+    assert!(report.reject_reason.is_some());
+    // The report still breaks the block down per transaction
+    assert_eq!(report.transactions.len(), 1);
+    assert!(utxo_set.is_empty());
+    assert_eq!(chain_state.utxo_commitment(), commitment);
+    assert!(chain_state.blocks.is_empty());
+    assert!(chain_state.headers.is_empty());
+}
+
+#[test]
+fn test_connect_validated_block_keeps_untouched_utxos_and_enforces_bip30() {
+    let block = coinbase_block(50 * 100_000_000);
+    let witnesses: Vec<Vec<Witness>> = vec![vec![Vec::new()]];
+    let mut chain_state = ChainState::new();
+    let unrelated = OutPoint {
+        hash: [9; 32],
+        index: 0,
+    };
+    let mut utxo_set = UtxoSet::new();
+    utxo_set.insert(
+        unrelated.clone(),
+        UTXO {
+            value: 1000,
+            script_pubkey: vec![0x51],
+            height: 0,
+            is_coinbase: false,
+        },
+    );
+
+    chain_state
+        .connect_validated_block(
+            &block,
+            &witnesses,
+            &mut utxo_set,
+            1,
+            &ChainParams::mainnet(),
+        )
+        .unwrap();
+    assert_eq!(utxo_set.len(), 2);
+    assert!(utxo_set.contains_key(&unrelated));
+
+    // The same coinbase again duplicates a txid still in the set
+    let before = utxo_set.clone();
+    let report = chain_state
+        .connect_validated_block(
+            &block,
+            &witnesses,
+            &mut utxo_set,
+            2,
+            &ChainParams::mainnet(),
+        )
+        .unwrap_err();
+    assert!(report.reject_reason.unwrap().contains("BIP30"));
+    assert_eq!(utxo_set, before);
+}
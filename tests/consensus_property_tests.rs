@@ -151,7 +151,7 @@ proptest! {
     /// Invariant: Proof of work target expansion produces valid values
     ///
     /// Mathematical specification:
-    /// ∀ bits ∈ [0x03000000, 0x1d00ffff]:
+    /// ∀ bits ∈ [0x03000000, 0x1d00ffff] with the sign bit (0x00800000) clear:
     ///   Let expanded = expand_target(bits)
     ///   Then: expanded is a valid U256 value
     ///         expanded can be used for proof of work validation
@@ -159,6 +159,9 @@ proptest! {
     fn prop_pow_target_expansion_valid_range(
         bits in 0x03000000u32..0x1d00ffffu32
     ) {
+        // Negative targets (sign bit set) are rejected, as in Core
+        prop_assume!(bits & 0x00800000 == 0);
+
         // Verify that expand_target succeeds for all valid bits
         // This ensures the function handles the entire valid range correctly
         let expanded_result = pow::expand_target(bits as u64);
@@ -935,7 +938,7 @@ proptest! {
 
         // Connect block1
         let utxo_set = UtxoSet::new();
-        let witnesses1: Vec<Witness> = block1.transactions.iter().map(|_| Vec::new()).collect();
+        let witnesses1: Vec<Vec<Witness>> = block1.transactions.iter().map(|tx| vec![Vec::new(); tx.inputs.len()]).collect();
         let result1 = block::connect_block(&block1, &witnesses1, utxo_set, height1 as u64, None, Network::Mainnet);
 
         if let Ok((ValidationResult::Valid, utxo_set1, _undo_log1)) = result1 {
@@ -947,7 +950,7 @@ proptest! {
                 .unwrap_or(MAX_MONEY as i64);
 
             // Connect block2
-            let witnesses2: Vec<Witness> = block2.transactions.iter().map(|_| Vec::new()).collect();
+            let witnesses2: Vec<Vec<Witness>> = block2.transactions.iter().map(|tx| vec![Vec::new(); tx.inputs.len()]).collect();
             let result2 = block::connect_block(&block2, &witnesses2, utxo_set1, height2 as u64, None, Network::Mainnet);
 
             if let Ok((ValidationResult::Valid, utxo_set2, _undo_log2)) = result2 {
@@ -1175,7 +1178,7 @@ proptest! {
 
         // Connect block1
         let utxo_set = UtxoSet::new();
-        let witnesses1: Vec<Witness> = block1.transactions.iter().map(|_| Vec::new()).collect();
+        let witnesses1: Vec<Vec<Witness>> = block1.transactions.iter().map(|tx| vec![Vec::new(); tx.inputs.len()]).collect();
         let result1 = block::connect_block(&block1, &witnesses1, utxo_set, height1 as u64, None, Network::Mainnet);
 
         if let Ok((ValidationResult::Valid, utxo_set1, _undo_log1)) = result1 {
@@ -1190,7 +1193,7 @@ proptest! {
             prop_assert!(supply1 <= MAX_MONEY as i64, "Supply after block1 must be <= MAX_MONEY: {} <= {}", supply1, MAX_MONEY);
 
             // Connect block2
-            let witnesses2: Vec<Witness> = block2.transactions.iter().map(|_| Vec::new()).collect();
+            let witnesses2: Vec<Vec<Witness>> = block2.transactions.iter().map(|tx| vec![Vec::new(); tx.inputs.len()]).collect();
             let result2 = block::connect_block(&block2, &witnesses2, utxo_set1, height2 as u64, None, Network::Mainnet);
 
             if let Ok((ValidationResult::Valid, utxo_set2, _undo_log2)) = result2 {
@@ -1251,7 +1254,11 @@ proptest! {
 
         // Connect block
         let utxo_set_before = UtxoSet::new();
-        let witnesses: Vec<Witness> = block.transactions.iter().map(|_| Vec::new()).collect();
+        let witnesses: Vec<Vec<Witness>> = block
+            .transactions
+            .iter()
+            .map(|tx| vec![Vec::new(); tx.inputs.len()])
+            .collect();
         let result = block::connect_block(&block, &witnesses, utxo_set_before.clone(), height as u64, None, Network::Mainnet);
 
         if let Ok((ValidationResult::Valid, utxo_set_after_connect, _undo_log)) = result {
@@ -1392,6 +1399,7 @@ proptest! {
     /// Mathematical specification (BIP141):
     /// ∀ base_size, witness_size ∈ ℕ:
     ///   weight = 4 * base_size + witness_size
+    ///   (3 * base_size + total_size, total_size = base_size + witness_size)
     ///   - weight >= base_size (always)
     ///   - weight >= witness_size (always)
     #[test]
@@ -1406,9 +1414,9 @@ proptest! {
             (base_size + witness_size) as u64
         );
 
-        // Weight = 4 * base_size + total_size
+        // Weight = 3 * base_size + total_size
         // Where total_size = base_size + witness_size
-        let expected_weight = 4 * base_size + (base_size + witness_size);
+        let expected_weight = 3 * base_size + (base_size + witness_size);
 
         prop_assert_eq!(weight, expected_weight,
             "Weight calculation: base_size={}, witness_size={}, expected={}, actual={}",
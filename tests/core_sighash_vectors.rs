@@ -0,0 +1,8 @@
+//! Bitcoin Core sighash.json test vectors
+//!
+//! Builds `core_test_vectors/sighash_tests.rs` as its own test target. Download
+//! sighash.json as described in `core_test_vectors/README.md`; without it only the
+//! inline vectors in that file run.
+
+#[path = "core_test_vectors/sighash_tests.rs"]
+mod sighash_tests;
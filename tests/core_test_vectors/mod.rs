@@ -9,9 +9,11 @@
 mod block_tests;
 mod transaction_tests;
 mod script_tests;
+mod sighash_tests;
 mod integration_test;
 
 pub use block_tests::*;
 pub use transaction_tests::*;
 pub use script_tests::*;
+pub use sighash_tests::*;
 
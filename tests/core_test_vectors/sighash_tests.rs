@@ -0,0 +1,255 @@
+//! Bitcoin Core sighash test vector integration
+//!
+//! Tests legacy signature hash calculation using Core's sighash.json.
+//!
+//! Core test vector format (sighash.json):
+//! Array of arrays: [[raw_tx_hex, script_hex, input_index, hash_type, expected_hash], ...]
+//! - raw_tx_hex: Transaction in hex format (non-witness serialization)
+//! - script_hex: scriptCode being signed for the input
+//! - input_index: Index of the input being signed
+//! - hash_type: Sighash type as a signed 32-bit integer
+//! - expected_hash: Expected signature hash (uint256 hex, i.e. byte-reversed)
+//!
+//! The first entry is a header comment and is skipped.
+//!
+//! sighash.json only covers the legacy algorithm. BIP143 (SegWit v0) sighashes
+//! are covered by the tx_valid.json witness cases instead.
+
+use bllvm_consensus::serialization::transaction::deserialize_transaction;
+use bllvm_consensus::transaction_hash::calculate_legacy_sighash;
+use bllvm_consensus::{Transaction, TransactionOutput};
+use serde_json::Value;
+use std::fs;
+use std::path::PathBuf;
+
+/// OP_CODESEPARATOR, removed from the scriptCode before signing
+const OP_CODESEPARATOR: u8 = 0xab;
+
+/// Sighash test vector structure
+#[derive(Debug, Clone)]
+pub struct SighashTestVector {
+    pub transaction: Transaction,
+    pub script_code: Vec<u8>,
+    pub input_index: usize,
+    pub hash_type: i32,
+    /// Expected hash in internal byte order (already un-reversed)
+    pub expected_hash: [u8; 32],
+}
+
+/// Sighash vectors that run even when sighash.json is not downloaded
+///
+/// Same format as sighash.json. sighash.json itself is not vendored, so these
+/// rows are taken from inputs of transactions in Core's tx_valid.json: each
+/// digest was computed with Core's legacy `SignatureHash` and confirmed by
+/// verifying that input's own signature against it. In order they cover:
+/// - undefined hash type 0
+/// - SIGHASH_ALL
+/// - SIGHASH_ALL|ANYONECANPAY
+/// - SIGHASH_SINGLE
+/// - SIGHASH_SINGLE past the last output
+/// - SIGHASH_SINGLE|ANYONECANPAY
+/// - OP_CODESEPARATOR in the scriptCode
+pub const INLINE_SIGHASH_VECTORS: &str = r#"[
+    ["raw_transaction, script, input_index, hashType, signature_hash (result)"],
+    ["01000000010276b76b07f4935c70acf54fbf1f438a4c397a9fb7e633873c4dd3bc062b6b40000000008c493046022100d23459d03ed7e9511a47d13292d3430a04627de6235b6e51a40f9cd386f2abe3022100e7d25b080f0bb8d8d5f878bba7d54ad2fda650ea8d158a33ee3cbd11768191fd004104b0e2c879e4daf7b9ab68350228c159766676a14f5815084ba166432aab46198d4cca98fa3e9981d0a90b2effc514b76279476550ba3663fdcaff94c38420e9d5000000000100093d00000000001976a9149a7b0f3b80c6baaeedce0a0842553800f832ba1f88ac00000000", "76a914dc44b1164188067c3a32d4780f5996fa14a4f2d988ac", 0, 0, "e243d52f530a7114de264e9b7f7dfe310574a224f14fbd9ae8249e0e223b7411"],
+    ["010000000200010000000000000000000000000000000000000000000000000000000000000000000049483045022100d180fd2eb9140aeb4210c9204d3f358766eb53842b2a9473db687fa24b12a3cc022079781799cd4f038b85135bbe49ec2b57f306b2bb17101b17f71f000fcab2b6fb01ffffffff0002000000000000000000000000000000000000000000000000000000000000000000004847304402205f7530653eea9b38699e476320ab135b74771e1c48b81a5d041e2ca84b9be7a802200ac8d1f40fb026674fe5a5edd3dea715c27baa9baca51ed45ea750ac9dc0a55e81ffffffff010100000000000000015100000000", "21035e7f0d4d0841bcd56c39337ed086b1a633ee770c1ffdd94ac552a95ac2ce0efcac", 0, 1, "cc2562dd136abb3494d151d8483a7984aa6d61f4fa1e7086f8e62e5d9c639bf6"],
+    ["010000000200010000000000000000000000000000000000000000000000000000000000000000000049483045022100d180fd2eb9140aeb4210c9204d3f358766eb53842b2a9473db687fa24b12a3cc022079781799cd4f038b85135bbe49ec2b57f306b2bb17101b17f71f000fcab2b6fb01ffffffff0002000000000000000000000000000000000000000000000000000000000000000000004847304402205f7530653eea9b38699e476320ab135b74771e1c48b81a5d041e2ca84b9be7a802200ac8d1f40fb026674fe5a5edd3dea715c27baa9baca51ed45ea750ac9dc0a55e81ffffffff010100000000000000015100000000", "21035e7f0d4d0841bcd56c39337ed086b1a633ee770c1ffdd94ac552a95ac2ce0efcac", 1, 129, "e8a7724c0be78ed230060a2f79fe35990f1211d0437aefa83fb78d544da5f557"],
+    ["010000000370ac0a1ae588aaf284c308d67ca92c69a39e2db81337e563bf40c59da0a5cf63000000006a4730440220360d20baff382059040ba9be98947fd678fb08aab2bb0c172efa996fd8ece9b702201b4fb0de67f015c90e7ac8a193aeab486a1f587e0f54d0fb9552ef7f5ce6caec032103579ca2e6d107522f012cd00b52b9a65fb46f0c57b9b8b6e377c48f526a44741affffffff7d815b6447e35fbea097e00e028fb7dfbad4f3f0987b4734676c84f3fcd0e804010000006b483045022100c714310be1e3a9ff1c5f7cacc65c2d8e781fc3a88ceb063c6153bf950650802102200b2d0979c76e12bb480da635f192cc8dc6f905380dd4ac1ff35a4f68f462fffd032103579ca2e6d107522f012cd00b52b9a65fb46f0c57b9b8b6e377c48f526a44741affffffff3f1f097333e4d46d51f5e77b53264db8f7f5d2e18217e1099957d0f5af7713ee010000006c493046022100b663499ef73273a3788dea342717c2640ac43c5a1cf862c9e09b206fcb3f6bb8022100b09972e75972d9148f2bdd462e5cb69b57c1214b88fc55ca638676c07cfc10d8032103579ca2e6d107522f012cd00b52b9a65fb46f0c57b9b8b6e377c48f526a44741affffffff0380841e00000000001976a914bfb282c70c4191f45b5a6665cad1682f2c9cfdfb88ac80841e00000000001976a9149857cc07bed33a5cf12b9c5e0500b675d500c81188ace0fd1c00000000001976a91443c52850606c872403c0601e69fa34b26f62db4a88ac00000000", "76a914dcf72c4fd02f5a987cf9b02f2fabfcac3341a87d88ac", 0, 3, "1eccdc1f7a4783924a49113b491a847de2f89a1e7d73b1ae561d80f918035f46"],
+    ["01000000020002000000000000000000000000000000000000000000000000000000000000000000000151ffffffff0001000000000000000000000000000000000000000000000000000000000000000000006b483045022100c9cdd08798a28af9d1baf44a6c77bcc7e279f47dc487c8c899911bc48feaffcc0220503c5c50ae3998a733263c5c0f7061b483e2b56c4c41b456e7d2f5a78a74c077032102d5c25adb51b61339d2b05315791e21bbe80ea470a49db0135720983c905aace0ffffffff010000000000000000015100000000", "76a914e52b482f2faa8ecbf0db344f93c84ac908557f3388ac", 1, 3, "0000000000000000000000000000000000000000000000000000000000000001"],
+    ["010000000390d31c6107013d754529d8818eff285fe40a3e7635f6930fec5d12eb02107a43010000006b483045022100f40815ae3c81a0dd851cc8d376d6fd226c88416671346a9033468cca2cdcc6c202204f764623903e6c4bed1b734b75d82c40f1725e4471a55ad4f51218f86130ac038321033d710ab45bb54ac99618ad23b3c1da661631aa25f23bfe9d22b41876f1d46e4effffffff3ff04a68e22bdd52e7c8cb848156d2d158bd5515b3c50adabc87d0ca2cd3482d010000006a4730440220598d263c107004008e9e26baa1e770be30fd31ee55ded1898f7c00da05a75977022045536bead322ca246779698b9c3df3003377090f41afeca7fb2ce9e328ec4af2832102b738b531def73020bd637f32935924cc88549c8206976226d968edd3a42fc2d7ffffffff46a8dc8970eb96622f27a516adcf40e0fcec5731e7556e174f2a271aef6861c7010000006b483045022100c5b90a777a9fdc90c208dbef7290d1fc1be651f47151ee4ccff646872a454cf90220640cfbc4550446968fbbe9d12528f3adf7d87b31541569c59e790db8a220482583210391332546e22bbe8fe3af54addfad6f8b83d05fa4f5e047593d4c07ae938795beffffffff028036be26000000001976a914ddfb29efad43a667465ac59ff14dc6442a1adfca88ac3d5cba01000000001976a914b64dde7a505a13ca986c40e86e984a8dc81368b688ac00000000", "76a914383fb81cb0a3fc724b5e08cf8bbd404336d711f688ac", 0, 131, "5bffdca7b2bf1c1a32de20daad79750701b5ebadbf02d48544eea465ae8485dc"],
+    ["01000000012432b60dc72cebc1a27ce0969c0989c895bdd9e62e8234839117f8fc32d17fbc000000004a493046022100a576b52051962c25e642c0fd3d77ee6c92487048e5d90818bcf5b51abaccd7900221008204f8fb121be4ec3b24483b1f92d89b1b0548513a134e345c5442e86e8617a501ffffffff010000000000000000016a00000000", "ab21038479a0fa998cd35259a2ef0a7a5c68662c1474f88ccb6d08a7677bbec7f22041ac", 0, 1, "2820037610ca45e1d691606b928cdb864b05e0f5214b3b474068a43be6846fd7"]
+]"#;
+
+/// Load sighash test vectors from Bitcoin Core JSON format
+///
+/// Looks for `sighash.json` in `dir`. A missing directory or file yields no vectors.
+pub fn load_sighash_test_vectors(
+    dir: &str,
+) -> Result<Vec<SighashTestVector>, Box<dyn std::error::Error>> {
+    let path = PathBuf::from(dir).join("sighash.json");
+
+    if !path.exists() {
+        // If test vectors aren't downloaded, return empty (not an error)
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(&path)?;
+    parse_sighash_test_vectors(&content)
+}
+
+/// Parse sighash test vectors from the contents of a sighash.json-format file
+pub fn parse_sighash_test_vectors(
+    json: &str,
+) -> Result<Vec<SighashTestVector>, Box<dyn std::error::Error>> {
+    let mut vectors = Vec::new();
+    let json: Value = serde_json::from_str(json)?;
+    if let Value::Array(cases) = json {
+        for (i, case) in cases.iter().enumerate() {
+            if let Value::Array(test_case) = case {
+                // Skip the header comment (single string entry)
+                if test_case.len() != 5 {
+                    continue;
+                }
+
+                let tx_hex = test_case[0]
+                    .as_str()
+                    .ok_or_else(|| format!("Invalid raw_tx at index {i}"))?;
+                let transaction = match deserialize_transaction(&hex::decode(tx_hex)?) {
+                    Ok(tx) => tx,
+                    Err(e) => {
+                        eprintln!("Warning: Failed to deserialize transaction at index {i}: {e}");
+                        continue;
+                    }
+                };
+
+                let script_hex = test_case[1]
+                    .as_str()
+                    .ok_or_else(|| format!("Invalid script at index {i}"))?;
+                let script_code = hex::decode(script_hex)?;
+
+                let input_index = test_case[2]
+                    .as_u64()
+                    .ok_or_else(|| format!("Invalid input_index at index {i}"))?
+                    as usize;
+
+                let hash_type = test_case[3]
+                    .as_i64()
+                    .ok_or_else(|| format!("Invalid hash_type at index {i}"))?
+                    as i32;
+
+                // uint256 hex is displayed byte-reversed
+                let expected_hex = test_case[4]
+                    .as_str()
+                    .ok_or_else(|| format!("Invalid expected hash at index {i}"))?;
+                let mut expected_hash = [0u8; 32];
+                expected_hash.copy_from_slice(&hex::decode(expected_hex)?);
+                expected_hash.reverse();
+
+                vectors.push(SighashTestVector {
+                    transaction,
+                    script_code,
+                    input_index,
+                    hash_type,
+                    expected_hash,
+                });
+            }
+        }
+    }
+
+    Ok(vectors)
+}
+
+/// Remove OP_CODESEPARATOR opcodes from a script, skipping over push data
+///
+/// Matches Core's `FindAndDelete(scriptCode, CScript(OP_CODESEPARATOR))` for the
+/// scriptCode passed to `SignatureHash`.
+pub fn strip_codeseparators(script: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(script.len());
+    let mut i = 0;
+    while i < script.len() {
+        let opcode = script[i];
+        let push_len = match opcode {
+            0x01..=0x4b => opcode as usize,
+            0x4c if i + 1 < script.len() => 1 + script[i + 1] as usize,
+            0x4d if i + 2 < script.len() => {
+                2 + u16::from_le_bytes([script[i + 1], script[i + 2]]) as usize
+            }
+            0x4e if i + 4 < script.len() => {
+                4 + u32::from_le_bytes([script[i + 1], script[i + 2], script[i + 3], script[i + 4]])
+                    as usize
+            }
+            _ => 0,
+        };
+        let end = (i + 1 + push_len).min(script.len());
+        if opcode != OP_CODESEPARATOR {
+            result.extend_from_slice(&script[i..end]);
+        }
+        i = end;
+    }
+    result
+}
+
+/// Run Core sighash test vectors against `calculate_legacy_sighash`
+///
+/// Every vector runs: the hash type is passed through as the raw nHashType,
+/// including the undefined and negative values Core's generator produces.
+pub fn run_core_sighash_tests(
+    vectors: &[SighashTestVector],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut passed = 0;
+    let mut failed = 0;
+
+    for (i, vector) in vectors.iter().enumerate() {
+        let script_code = strip_codeseparators(&vector.script_code);
+        let prevouts: Vec<TransactionOutput> = vector
+            .transaction
+            .inputs
+            .iter()
+            .map(|_| TransactionOutput {
+                value: 0,
+                script_pubkey: Vec::new(),
+            })
+            .collect();
+
+        match calculate_legacy_sighash(
+            &vector.transaction,
+            vector.input_index,
+            &prevouts,
+            &script_code,
+            vector.hash_type as u32,
+        ) {
+            Ok(hash) if hash == vector.expected_hash => passed += 1,
+            Ok(hash) => {
+                failed += 1;
+                eprintln!(
+                    "Sighash test {} failed: expected {}, got {} (hash_type {})",
+                    i,
+                    hex::encode(vector.expected_hash),
+                    hex::encode(hash),
+                    vector.hash_type
+                );
+            }
+            Err(e) => {
+                failed += 1;
+                eprintln!("Sighash test {i} failed with error: {e}");
+            }
+        }
+    }
+
+    println!("Core sighash test vectors: {passed} passed, {failed} failed");
+
+    if failed > 0 {
+        Err(format!("{failed} sighash test vectors failed").into())
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_core_sighash_vectors() {
+        // If sighash.json isn't downloaded, that's OK - vectors will be empty
+        let vectors = load_sighash_test_vectors("tests/test_data/core_vectors/transactions")
+            .expect("sighash.json should parse");
+        run_core_sighash_tests(&vectors).unwrap();
+    }
+
+    #[test]
+    fn test_inline_sighash_vectors() {
+        let vectors = parse_sighash_test_vectors(INLINE_SIGHASH_VECTORS)
+            .expect("inline sighash vectors should parse");
+        assert_eq!(vectors.len(), 7);
+        run_core_sighash_tests(&vectors).unwrap();
+    }
+
+    #[test]
+    fn test_strip_codeseparators() {
+        // OP_CODESEPARATOR OP_1 <push 0xab> OP_CODESEPARATOR
+        let script = vec![0xab, 0x51, 0x01, 0xab, 0xab];
+        // The pushed 0xab byte is data, not an opcode, and is kept
+        assert_eq!(strip_codeseparators(&script), vec![0x51, 0x01, 0xab]);
+    }
+}
@@ -10,11 +10,10 @@ use bllvm_consensus::constants::MAX_BLOCK_WEIGHT;
 use super::bip_test_helpers::*;
 
 /// Create witness commitment script (helper for tests)
-/// OP_RETURN <36-byte-commitment>
+/// OP_RETURN <0xaa21a9ed || 32-byte commitment>
 fn create_witness_commitment_script(commitment: &[u8; 32]) -> Vec<u8> {
-    let mut script = vec![0x6a, 0x24]; // OP_RETURN, 36 bytes
+    let mut script = vec![0x6a, 0x24, 0xaa, 0x21, 0xa9, 0xed]; // OP_RETURN, 36 bytes, header
     script.extend_from_slice(commitment);
-    script.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]); // 4 bytes padding
     script
 }
 
@@ -73,7 +73,7 @@ fn test_script_execution_errors() {
     let consensus = ConsensusProof::new();
 
     // Test script with too many operations
-    let large_script = vec![0x51; MAX_SCRIPT_OPS + 1];
+    let large_script = vec![0x61; MAX_SCRIPT_OPS + 1]; // OP_NOP; pushes are not counted
     let result = consensus.verify_script(&large_script, &vec![0x51], None, 0);
     assert!(result.is_err()); // Exceeds op limit should error
 }
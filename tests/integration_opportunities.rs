@@ -242,7 +242,7 @@ fn test_performance_integration() {
         let result = consensus
             .accept_to_memory_pool(tx, &utxo_set, &mempool, 100)
             .unwrap();
-        if matches!(result, mempool::MempoolResult::Accepted) {
+        if matches!(result, mempool::MempoolResult::Accepted { .. }) {
             accepted += 1;
         }
     }
@@ -31,7 +31,7 @@ fn test_genesis_block_validation() {
     if let Some(bytes) = block_bytes {
         if let Ok((block, witnesses)) = deserialize_block_with_witnesses(&bytes) {
             let utxo_set = UtxoSet::new();
-            // connect_block expects &[Witness] where Witness is Vec<ByteString> (one per transaction)
+            // connect_block expects one witness stack per input of every transaction
             let result = connect_block(&block, &witnesses, utxo_set, 0, None, Network::Mainnet);
 
             // Genesis block should validate (or fail gracefully with missing context)
@@ -320,6 +320,9 @@ fn test_real_world_transaction_patterns() {
     assert!(true);
 }
 
+/// A block and, per transaction, one witness stack per input
+type BlockWithWitnesses = (Block, Vec<Vec<Witness>>);
+
 /// Load a mainnet block from disk
 ///
 /// Helper function to load a block at a specific height from the mainnet blocks directory.
@@ -327,7 +330,7 @@ fn test_real_world_transaction_patterns() {
 pub fn load_mainnet_block_from_disk(
     block_dir: &std::path::PathBuf,
     height: u64,
-) -> Result<(Block, Vec<Witness>), Box<dyn std::error::Error>> {
+) -> Result<BlockWithWitnesses, Box<dyn std::error::Error>> {
     let bin_path = block_dir.join(format!("block_{height}.bin"));
     let hex_path = block_dir.join(format!("block_{height}.hex"));
 
@@ -0,0 +1,163 @@
+//! End-to-end 2-of-3 multisig spends
+//!
+//! Builds bare and P2SH 2-of-3 multisig outputs from freshly derived keys,
+//! signs a spend with two of the three keys, and checks it with the script
+//! interpreter. Exercises key/signature encoding, the P2SH redeem-script
+//! commitment and CHECKMULTISIG key ordering together.
+
+use bllvm_consensus::bip_validation::bip147_activation_height;
+use bllvm_consensus::constants::*;
+use bllvm_consensus::multisig::{MultisigSpend, SIGHASH_ALL};
+use bllvm_consensus::script::verify_script_with_witness;
+use bllvm_consensus::transaction_hash::SighashCache;
+use bllvm_consensus::types::*;
+use secp256k1::{Message, PublicKey, Secp256k1, SecretKey};
+
+fn secret_keys() -> Vec<SecretKey> {
+    (1u8..=3)
+        .map(|seed| SecretKey::from_slice(&[seed; 32]).unwrap())
+        .collect()
+}
+
+fn multisig_2_of_3() -> MultisigSpend {
+    let secp = Secp256k1::new();
+    let pubkeys = secret_keys()
+        .iter()
+        .map(|sk| PublicKey::from_secret_key(&secp, sk).serialize().to_vec())
+        .collect();
+    MultisigSpend::new(2, pubkeys).unwrap()
+}
+
+fn spending_tx() -> Transaction {
+    Transaction {
+        version: 1,
+        inputs: bllvm_consensus::tx_inputs![TransactionInput {
+            prevout: OutPoint {
+                hash: [0xab; 32],
+                index: 0,
+            },
+            script_sig: vec![],
+            sequence: 0xffffffff,
+        }],
+        outputs: bllvm_consensus::tx_outputs![TransactionOutput {
+            value: 90_000,
+            script_pubkey: vec![0x51],
+        }],
+        lock_time: 0,
+    }
+}
+
+/// Verify input 0 of `tx` on mainnet after BIP147, with P2SH, strict
+/// signature encoding and NULLDUMMY enforced
+fn verify_spend(tx: &Transaction, prevouts: &[TransactionOutput]) -> bool {
+    verify_script_with_witness(
+        &tx.inputs[0].script_sig,
+        &prevouts[0].script_pubkey,
+        &[],
+        SCRIPT_VERIFY_P2SH
+            | SCRIPT_VERIFY_STRICTENC
+            | SCRIPT_VERIFY_DERSIG
+            | SCRIPT_VERIFY_NULLDUMMY,
+        tx,
+        0,
+        prevouts,
+        &SighashCache::new(tx),
+        Some(bip147_activation_height(Network::Mainnet)),
+        None,
+        Network::Mainnet,
+        bllvm_consensus::crypto::signature::default_verifier(),
+    )
+    .unwrap()
+}
+
+/// Sign input 0 of `tx` with the given keys, in key order
+fn sign(
+    spend: &MultisigSpend,
+    tx: &Transaction,
+    prevouts: &[TransactionOutput],
+    signers: &[usize],
+) -> Vec<ByteString> {
+    let secp = Secp256k1::new();
+    let sighash = spend.signature_hash(tx, 0, prevouts).unwrap();
+    let message = Message::from_digest_slice(&sighash).unwrap();
+    let keys = secret_keys();
+    signers
+        .iter()
+        .map(|&i| {
+            let mut signature = secp.sign_ecdsa(&message, &keys[i]).serialize_der().to_vec();
+            signature.push(SIGHASH_ALL);
+            signature
+        })
+        .collect()
+}
+
+#[test]
+fn test_bare_2_of_3_spend() {
+    let spend = multisig_2_of_3();
+    let prevouts = vec![TransactionOutput {
+        value: 100_000,
+        script_pubkey: spend.redeem_script(),
+    }];
+    let mut tx = spending_tx();
+
+    let signatures = sign(&spend, &tx, &prevouts, &[0, 2]);
+    tx.inputs[0].script_sig = spend.bare_script_sig(&signatures);
+    assert!(verify_spend(&tx, &prevouts));
+
+    // Signatures out of key order do not match
+    let reversed: Vec<_> = signatures.iter().rev().cloned().collect();
+    tx.inputs[0].script_sig = spend.bare_script_sig(&reversed);
+    assert!(!verify_spend(&tx, &prevouts));
+
+    // A single signature is not enough
+    tx.inputs[0].script_sig = spend.bare_script_sig(&signatures[..1]);
+    assert!(!verify_spend(&tx, &prevouts));
+}
+
+#[test]
+fn test_p2sh_2_of_3_spend() {
+    let spend = multisig_2_of_3();
+    let prevouts = vec![TransactionOutput {
+        value: 100_000,
+        script_pubkey: spend.p2sh_script_pubkey(),
+    }];
+    let mut tx = spending_tx();
+
+    for signers in [[0, 1], [0, 2], [1, 2]] {
+        let signatures = sign(&spend, &tx, &prevouts, &signers);
+        tx.inputs[0].script_sig = spend.p2sh_script_sig(&signatures);
+        assert!(
+            verify_spend(&tx, &prevouts),
+            "signers {signers:?} must satisfy 2-of-3"
+        );
+    }
+
+    // Changing an output invalidates the signatures
+    let signatures = sign(&spend, &tx, &prevouts, &[0, 1]);
+    let mut tampered = tx.clone();
+    tampered.inputs[0].script_sig = spend.p2sh_script_sig(&signatures);
+    tampered.outputs[0].value += 1;
+    assert!(!verify_spend(&tampered, &prevouts));
+
+    // A redeem script that does not hash to the output is rejected
+    let other = MultisigSpend::new(1, spend.pubkeys.clone()).unwrap();
+    tx.inputs[0].script_sig = other.p2sh_script_sig(&signatures[..1]);
+    assert!(!verify_spend(&tx, &prevouts));
+}
+
+#[test]
+fn test_multisig_non_empty_dummy_rejected() {
+    let spend = multisig_2_of_3();
+    let prevouts = vec![TransactionOutput {
+        value: 100_000,
+        script_pubkey: spend.redeem_script(),
+    }];
+    let mut tx = spending_tx();
+    let signatures = sign(&spend, &tx, &prevouts, &[0, 1]);
+
+    // Replace OP_0 dummy with OP_1 (BIP147 NULLDUMMY)
+    let mut script_sig = spend.bare_script_sig(&signatures);
+    script_sig[0] = 0x51;
+    tx.inputs[0].script_sig = script_sig;
+    assert!(!verify_spend(&tx, &prevouts));
+}
@@ -105,7 +105,7 @@ fn test_eval_script_stack_underflow() {
 
 #[test]
 fn test_eval_script_operation_limit() {
-    let script = vec![0x51; MAX_SCRIPT_OPS + 1]; // Too many operations
+    let script = vec![0x61; MAX_SCRIPT_OPS + 1]; // Too many operations (OP_NOP; pushes are not counted)
     let mut stack = Vec::new();
     let result = eval_script(&script, &mut stack, 0);
     assert!(result.is_err()); // Should fail due to operation limit
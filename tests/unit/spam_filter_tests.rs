@@ -2,7 +2,7 @@
 
 #[cfg(feature = "utxo-commitments")]
 mod tests {
-    use bllvm_consensus::types::{Transaction, TransactionInput, TransactionOutput, OutPoint, ByteString};
+    use bllvm_consensus::types::{Transaction, TransactionInput, TransactionOutput, OutPoint, ByteString, UtxoSet, UTXO};
     use bllvm_consensus::utxo_commitments::spam_filter::*;
 
     fn create_test_transaction(script_pubkey: ByteString) -> Transaction {
@@ -116,6 +116,41 @@ mod tests {
         assert!(result.detected_types.contains(&SpamType::BRC20));
     }
 
+    #[test]
+    fn test_estimate_filtered_set_size() {
+        let filter = SpamFilter::new();
+
+        let mut utxo_set = UtxoSet::new();
+        let mut add_utxo = |index: u64, value: i64, script_pubkey: ByteString| {
+            utxo_set.insert(
+                OutPoint { hash: [1; 32], index },
+                UTXO { value, script_pubkey, height: 1, is_coinbase: false },
+            );
+        };
+
+        // Two normal P2PKH-sized outputs
+        add_utxo(0, 10_000, vec![0x76, 0xa9, 0x14]);
+        add_utxo(1, 50_000, vec![0x76, 0xa9, 0x14]);
+        // Dust
+        add_utxo(2, 100, vec![0x76, 0xa9, 0x14]);
+        add_utxo(3, 545, vec![0x76, 0xa9, 0x14]);
+        // Large data carrier above the dust threshold
+        add_utxo(4, 10_000, vec![0x00; 101]);
+
+        assert_eq!(utxo_set.len(), 5);
+        assert_eq!(filter.estimate_filtered_set_size(&utxo_set), 2);
+        // The set itself is untouched
+        assert_eq!(utxo_set.len(), 5);
+
+        // With dust filtering disabled only the data carrier is dropped
+        let config = SpamFilterConfig {
+            filter_dust: false,
+            ..SpamFilterConfig::default()
+        };
+        let filter = SpamFilter::with_config(config);
+        assert_eq!(filter.estimate_filtered_set_size(&utxo_set), 4);
+    }
+
     #[test]
     fn test_filter_block() {
         let filter = SpamFilter::new();
@@ -153,6 +188,23 @@ mod tests {
         assert!(summary.by_type.ordinals > 0 || summary.by_type.dust > 0);
     }
 
+    #[test]
+    fn test_datacarrier_size_limit() {
+        let mut script = vec![0x6a, 0x4c, 100]; // OP_RETURN OP_PUSHDATA1 100
+        script.extend(vec![0xab; 100]);
+        let tx = create_test_transaction(script);
+
+        let default = SpamFilterConfig::default();
+        assert_eq!(default.max_datacarrier_bytes, 83);
+        assert!(SpamFilter::with_config(default.clone()).is_spam(&tx).is_spam);
+
+        let raised = SpamFilterConfig {
+            max_datacarrier_bytes: 103,
+            ..default
+        };
+        assert!(!SpamFilter::with_config(raised).is_spam(&tx).is_spam);
+    }
+
     #[test]
     fn test_custom_config() {
         // Disable Ordinals filtering
@@ -162,6 +214,7 @@ mod tests {
             filter_brc20: true,
             dust_threshold: 546,
             min_output_value: 546,
+            max_datacarrier_bytes: 83,
         };
         
         let filter = SpamFilter::with_config(config);
@@ -50,7 +50,7 @@ fn test_witness_commitment_segwit_block() {
     };
 
     // Create witness data
-    let witnesses = vec![Witness::new()]; // Empty witness for coinbase
+    let witnesses = vec![vec![Witness::new()]]; // Empty witness for the coinbase input
 
     // Compute witness merkle root
     let witness_root = compute_witness_merkle_root(&block, &witnesses);
@@ -93,7 +93,7 @@ fn test_witness_commitment_activation_height() {
         .into_boxed_slice(),
     };
 
-    let witnesses = vec![Witness::new()];
+    let witnesses = vec![vec![Witness::new()]];
     let witness_root = compute_witness_merkle_root(&block, &witnesses);
 
     // At activation height, witness commitment should be validated
@@ -134,7 +134,7 @@ fn test_witness_commitment_no_witness_txs() {
     };
 
     // All witnesses are empty (no witness transactions)
-    let witnesses = vec![Witness::new()];
+    let witnesses = vec![vec![Witness::new()]];
     let witness_root = compute_witness_merkle_root(&block, &witnesses);
 
     // Should still compute witness root (all zeros)
@@ -173,7 +173,7 @@ fn test_invalid_witness_commitment_rejection() {
         .into_boxed_slice(),
     };
 
-    let witnesses = vec![Witness::new()];
+    let witnesses = vec![vec![Witness::new()]];
     let witness_root = compute_witness_merkle_root(&block, &witnesses).unwrap();
 
     // Create coinbase with wrong commitment